@@ -15,6 +15,10 @@ pub(crate) enum Error {
     TracingInit,
     #[error("Invalid message received")]
     InvalidMessage,
+    #[error("Timed out waiting for bartos to send the next export page")]
+    ExportTimeout,
+    #[error("Timed out waiting for bartos to accept the subscription")]
+    SubscribeTimeout,
 }
 
 #[cfg(test)]
@@ -44,4 +48,20 @@ mod tests {
             "Invalid message received"
         );
     }
+
+    #[test]
+    fn export_timeout_display() {
+        assert_eq!(
+            Error::ExportTimeout.to_string(),
+            "Timed out waiting for bartos to send the next export page"
+        );
+    }
+
+    #[test]
+    fn subscribe_timeout_display() {
+        assert_eq!(
+            Error::SubscribeTimeout.to_string(),
+            "Timed out waiting for bartos to accept the subscription"
+        );
+    }
 }
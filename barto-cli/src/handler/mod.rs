@@ -19,10 +19,16 @@ use console::{Key, Style, Term};
 use count_digits::CountDigits;
 use futures_util::{StreamExt as _, stream::SplitStream};
 use libbarto::{
-    BartosToBartoCli, ClientData, FailedOutput, Garuda, ListOutput, UpdateKind, UuidWrapper,
-    clean_output_string,
+    BartosToBartoCli, ClientData, ClientEvent, ClientEventKind, Data, ExportRow, FailedOutput,
+    Garuda, ListOutput, OutputKind, QueryRow, SearchHit, UpdateKind, UuidWrapper,
+    clean_output_string, describe_exit,
+};
+use tokio::{
+    net::TcpStream,
+    select,
+    signal::ctrl_c,
+    time::{sleep, timeout},
 };
-use tokio::{net::TcpStream, select, time::sleep};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
 use tracing::trace;
 use vergen_pretty::PrettyExt;
@@ -32,12 +38,21 @@ use crate::error::Error;
 pub(crate) static BOLD_BLUE: LazyLock<Style> = LazyLock::new(|| Style::new().bold().blue());
 pub(crate) static BOLD_GREEN: LazyLock<Style> = LazyLock::new(|| Style::new().bold().green());
 pub(crate) static BOLD_YELLOW: LazyLock<Style> = LazyLock::new(|| Style::new().bold().yellow());
+pub(crate) static BOLD_RED: LazyLock<Style> = LazyLock::new(|| Style::new().bold().red());
+pub(crate) static DIM: LazyLock<Style> = LazyLock::new(|| Style::new().dim());
+/// How long to wait for each page of an export, which bartos reads and decompresses in full
+const EXPORT_PAGE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long to wait for bartos to accept a subscription
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(5);
 type WsMessage = Option<std::result::Result<Message, tokio_tungstenite::tungstenite::Error>>;
 type Stream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
 
 #[derive(Builder, Debug)]
 pub(crate) struct Handler {
     stream: Stream,
+    /// The search words to highlight in search results
+    #[builder(default)]
+    highlight: Vec<String>,
 }
 
 impl Handler {
@@ -46,12 +61,62 @@ impl Handler {
         select! {
             () = sleep(Duration::from_secs(5)) => {},
             msg_opt_res = self.stream.next() => {
-                Self::handle_message(msg_opt_res)?;
+                Self::handle_message(msg_opt_res, &self.highlight)?;
             },
         }
         Ok(())
     }
 
+    /// Wait for the next page of an export. Any other reply, such as a denial, is handled as
+    /// usual and ends the export with `None`.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub(crate) async fn export_page(&mut self) -> Result<Option<Vec<ExportRow>>> {
+        let msg_opt_res = timeout(EXPORT_PAGE_TIMEOUT, self.stream.next())
+            .await
+            .map_err(|_| Error::ExportTimeout)?;
+        Self::export_rows(msg_opt_res, &self.highlight)
+    }
+
+    /// Print the records of the subscribed runs as they arrive. Without `follow`, stop once
+    /// every run shown has finished, otherwise keep going until interrupted. Any reply other
+    /// than the subscription being accepted, such as a denial, is handled as usual.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub(crate) async fn tail(&mut self, follow: bool) -> Result<()> {
+        let msg_opt_res = timeout(SUBSCRIBE_TIMEOUT, self.stream.next())
+            .await
+            .map_err(|_| Error::SubscribeTimeout)?;
+        let msg = msg_opt_res.ok_or(Error::InvalidMessage)??;
+        let Message::Binary(bytes) = &msg else {
+            return Err(Error::InvalidMessage.into());
+        };
+        if !matches!(
+            decode_from_slice(bytes, standard()),
+            Ok((BartosToBartoCli::Subscribed, _))
+        ) {
+            Self::handle_binary(bytes, &self.highlight);
+            return Ok(());
+        }
+        let mut runs = TailRuns::default();
+        while follow || !runs.finished() {
+            select! {
+                _ = ctrl_c() => break,
+                msg_opt_res = self.stream.next() => match msg_opt_res {
+                    Some(Ok(Message::Binary(bytes))) => {
+                        if let Ok((BartosToBartoCli::Tail(data), _)) = decode_from_slice(&bytes, standard())
+                            && let Some(line) = runs.line(&data)
+                        {
+                            println!("{line}");
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                },
+            }
+        }
+        Ok(())
+    }
+
     #[cfg_attr(coverage_nightly, coverage(off))]
     pub(crate) async fn wait_for_close(&mut self) {
         select! {
@@ -71,17 +136,29 @@ impl Handler {
         }
     }
 
-    fn handle_message(msg_opt_res: WsMessage) -> Result<()> {
+    fn handle_message(msg_opt_res: WsMessage, highlight: &[String]) -> Result<()> {
         let msg = msg_opt_res.ok_or(Error::InvalidMessage)??;
         if let Message::Binary(bytes) = &msg {
-            Self::handle_binary(bytes);
+            Self::handle_binary(bytes, highlight);
             Ok(())
         } else {
             Err(Error::InvalidMessage.into())
         }
     }
 
-    fn handle_binary(bytes: &[u8]) {
+    fn export_rows(msg_opt_res: WsMessage, highlight: &[String]) -> Result<Option<Vec<ExportRow>>> {
+        let msg = msg_opt_res.ok_or(Error::InvalidMessage)??;
+        let Message::Binary(bytes) = &msg else {
+            return Err(Error::InvalidMessage.into());
+        };
+        if let Ok((BartosToBartoCli::Export(rows), _)) = decode_from_slice(bytes, standard()) {
+            return Ok(Some(rows));
+        }
+        Self::handle_binary(bytes, highlight);
+        Ok(None)
+    }
+
+    fn handle_binary(bytes: &[u8], highlight: &[String]) {
         match decode_from_slice(bytes, standard()) {
             Err(e) => trace!("unable to decode binary message: {e}"),
             Ok((msg, _)) => match msg {
@@ -93,13 +170,26 @@ impl Handler {
                 BartosToBartoCli::ClientVersions(versions) => {
                     Self::handle_client_versions(&versions);
                 }
-                BartosToBartoCli::Query(map) => Self::handle_query(map),
+                BartosToBartoCli::ClientHistory(events) => Self::handle_client_history(&events),
+                BartosToBartoCli::Query(rows) => Self::handle_query(&rows),
+                BartosToBartoCli::RawQuery(map) => Self::handle_raw_query(map),
+                BartosToBartoCli::Search(hits) => Self::handle_search(&hits, highlight),
+                BartosToBartoCli::Export(rows) => {
+                    trace!("ignoring {} export rows outside of an export", rows.len());
+                }
                 BartosToBartoCli::List(list) => {
                     let _ = Self::handle_list(&list, false);
                 }
                 BartosToBartoCli::Failed(failed_output) => Self::handle_failed(&failed_output),
                 BartosToBartoCli::ListCommands(cmds) => Self::handle_list_commands(&cmds),
                 BartosToBartoCli::Cmd(cmd_output) => Self::handle_cmd_output(&cmd_output),
+                BartosToBartoCli::Enroll((name, token)) => Self::handle_enroll(&name, token),
+                BartosToBartoCli::Revoke((name, revoked)) => Self::handle_revoke(&name, revoked),
+                BartosToBartoCli::Denied(reason) => {
+                    eprintln!("{} {reason}", BOLD_YELLOW.apply_to("denied:"));
+                }
+                BartosToBartoCli::Subscribed => trace!("ignoring subscription outside of a tail"),
+                BartosToBartoCli::Tail(_) => trace!("ignoring tail record outside of a tail"),
             },
         }
     }
@@ -178,7 +268,7 @@ impl Handler {
             "{} {} {}",
             BOLD_GREEN.apply_to("deleted"),
             BOLD_YELLOW.apply_to(deleted.0),
-            BOLD_GREEN.apply_to("output rows")
+            BOLD_GREEN.apply_to("output lines")
         );
         println!(
             "{} {} {}",
@@ -194,6 +284,39 @@ impl Handler {
         );
     }
 
+    fn handle_enroll(name: &str, token: Option<String>) {
+        println!(
+            "{} {}",
+            BOLD_GREEN.apply_to("enrolled"),
+            BOLD_YELLOW.apply_to(name)
+        );
+        if let Some(token) = token {
+            println!(
+                "{} {}",
+                BOLD_GREEN.apply_to("client_token:"),
+                BOLD_BLUE.apply_to(token)
+            );
+            println!("Set this as 'bartos.client_token' in the client's configuration.");
+            println!("It is not stored by bartos and cannot be shown again.");
+        }
+    }
+
+    fn handle_revoke(name: &str, revoked: bool) {
+        if revoked {
+            println!(
+                "{} {}",
+                BOLD_GREEN.apply_to("revoked"),
+                BOLD_YELLOW.apply_to(name)
+            );
+        } else {
+            println!(
+                "{} {}",
+                BOLD_YELLOW.apply_to(name),
+                BOLD_GREEN.apply_to("has no active credential")
+            );
+        }
+    }
+
     fn handle_clients(clients: &HashMap<UuidWrapper, ClientData>) {
         let mut client_datas = clients.values().cloned().collect::<Vec<ClientData>>();
         client_datas.sort_by(|a, b| a.name().cmp(b.name()));
@@ -245,7 +368,182 @@ impl Handler {
         );
     }
 
-    fn handle_query(results: BTreeMap<usize, BTreeMap<String, String>>) {
+    fn handle_client_history(events: &[ClientEvent]) {
+        let (max_name, max_ip) = Self::maxes_client_events(events);
+        println!(
+            "{} {}",
+            BOLD_GREEN.apply_to("Total events:"),
+            BOLD_YELLOW.apply_to(events.len())
+        );
+        if events.is_empty() {
+            return;
+        }
+        println!();
+        for event in events {
+            let kind = event.kind().to_string();
+            let kind = match event.kind() {
+                ClientEventKind::Connect => BOLD_GREEN.apply_to(format!("{kind:<10}")),
+                ClientEventKind::Disconnect => BOLD_RED.apply_to(format!("{kind:<10}")),
+            };
+            let reason = match (event.reason(), event.close_code()) {
+                (Some(reason), Some(code)) => format!("{reason} ({code})"),
+                (Some(reason), None) => reason.clone(),
+                (None, _) => String::new(),
+            };
+            println!(
+                "{}: {:<max_name$} ({:>max_ip$}) {} {}",
+                BOLD_GREEN.apply_to(event.timestamp().0),
+                BOLD_YELLOW.apply_to(event.bartoc_name()),
+                BOLD_YELLOW.apply_to(event.ip()),
+                kind,
+                BOLD_BLUE.apply_to(reason),
+            );
+        }
+    }
+
+    fn handle_query(rows: &[QueryRow]) {
+        let (max_bartoc_name, max_schedule_name) = Self::maxes_query_rows(rows);
+        println!(
+            "{} {}",
+            BOLD_GREEN.apply_to("Total outputs:"),
+            BOLD_YELLOW.apply_to(rows.len())
+        );
+        if rows.is_empty() {
+            return;
+        }
+        println!();
+        let digits = rows.len().count_digits();
+        let term = Term::stdout();
+        let (height, width) = term.size_checked().unwrap_or((80, 24));
+        let print_height = usize::from(height).saturating_sub(8).max(1);
+        'outer: for (idx, row) in rows.iter().enumerate() {
+            let timestamp = row
+                .timestamp()
+                .as_ref()
+                .map_or("None".to_string(), |t| t.0.to_string());
+            let bartoc_name = row.bartoc_name().as_ref().map_or("None", String::as_str);
+            let schedule_name = row.schedule_name().as_ref().map_or("None", String::as_str);
+            let exit_code = describe_exit(row.exit_code(), row.exit_signal(), row.core_dumped());
+            let data = row.data().as_ref().map_or("", String::as_str);
+
+            let known_width = digits
+                + timestamp.len()
+                + max_bartoc_name
+                + max_schedule_name
+                + exit_code.len().max(3)
+                + 14;
+            let (mut final_data, data_uw) = clean_output_string(data);
+            let disp_data = if data_uw <= usize::from(width).saturating_sub(known_width) {
+                final_data
+            } else {
+                final_data.truncate(usize::from(width).saturating_sub(known_width));
+                final_data.push_str(" ...");
+                final_data
+            };
+            let exit_style = if row.success() == 1 {
+                &*BOLD_GREEN
+            } else {
+                &*BOLD_YELLOW
+            };
+            println!(
+                "{:>digits$} - {}: {:<max_bartoc_name$} {:<max_schedule_name$} {:>3} {}",
+                BOLD_GREEN.apply_to(idx + 1),
+                BOLD_GREEN.apply_to(timestamp),
+                BOLD_YELLOW.apply_to(bartoc_name),
+                BOLD_YELLOW.apply_to(schedule_name),
+                exit_style.apply_to(exit_code),
+                BOLD_BLUE.apply_to(disp_data),
+            );
+            if idx > 0 && (idx + 1) % print_height == 0 {
+                println!();
+                println!(
+                    "{}",
+                    BOLD_YELLOW.apply_to("Press any key to continue, 'x' to exit...")
+                );
+                match term.read_key() {
+                    Ok(key) => {
+                        if key == Key::Char('x') {
+                            let _res = term.clear_last_lines(1);
+                            println!("{}", BOLD_YELLOW.apply_to("Exiting..."));
+                            break 'outer;
+                        }
+                        let _res = term.clear_last_lines(print_height + 2);
+                    }
+                    // No key can be read (e.g. stdin is not a terminal), so stop paging.
+                    Err(_) => break 'outer,
+                }
+            }
+        }
+    }
+
+    fn handle_search(hits: &[SearchHit], highlight: &[String]) {
+        let (max_bartoc_name, max_schedule_name) = Self::maxes_search_hits(hits);
+        println!(
+            "{} {}",
+            BOLD_GREEN.apply_to("Total matches:"),
+            BOLD_YELLOW.apply_to(hits.len())
+        );
+        let digits = hits.len().count_digits();
+        let context = |line: &str| {
+            let (line, _) = clean_output_string(line);
+            println!("{:>digits$}   {}", "", DIM.apply_to(line));
+        };
+        for (idx, hit) in hits.iter().enumerate() {
+            let schedule_name = hit.schedule_name().as_ref().map_or("None", String::as_str);
+            println!();
+            println!(
+                "{:>digits$} - {}: {:<max_bartoc_name$} {:<max_schedule_name$} {}",
+                BOLD_GREEN.apply_to(idx + 1),
+                BOLD_GREEN.apply_to(hit.timestamp().0),
+                BOLD_YELLOW.apply_to(hit.bartoc_name()),
+                BOLD_YELLOW.apply_to(schedule_name),
+                DIM.apply_to(hit.cmd_uuid().0),
+            );
+            hit.before().iter().for_each(|line| context(line));
+            let (line, _) = clean_output_string(hit.line());
+            let highlighted: String = Self::split_matches(&line, highlight)
+                .into_iter()
+                .map(|(part, matched)| {
+                    if matched {
+                        BOLD_RED.apply_to(part).to_string()
+                    } else {
+                        BOLD_BLUE.apply_to(part).to_string()
+                    }
+                })
+                .collect();
+            println!("{:>digits$} > {highlighted}", "");
+            hit.after().iter().for_each(|line| context(line));
+        }
+    }
+
+    /// Split a line into the parts that are one of the search words and the parts between,
+    /// finding words the way the full-text indexes do: runs of letters, digits and
+    /// underscores, ignoring case.
+    fn split_matches<'a>(line: &'a str, words: &[String]) -> Vec<(&'a str, bool)> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut parts = vec![];
+        let mut plain_from = 0;
+        let mut at = 0;
+        while let Some(start) = line[at..].find(is_word).map(|offset| at + offset) {
+            let end = line[start..]
+                .find(|c| !is_word(c))
+                .map_or(line.len(), |len| start + len);
+            if words.contains(&line[start..end].to_lowercase()) {
+                if plain_from < start {
+                    parts.push((&line[plain_from..start], false));
+                }
+                parts.push((&line[start..end], true));
+                plain_from = end;
+            }
+            at = end;
+        }
+        if plain_from < line.len() {
+            parts.push((&line[plain_from..], false));
+        }
+        parts
+    }
+
+    fn handle_raw_query(results: BTreeMap<usize, BTreeMap<String, String>>) {
         let (max_col_label, _max_val_label) = Self::maxes_query(&results);
         println!(
             "{} {}",
@@ -315,7 +613,11 @@ impl Handler {
             println!(
                 "{}: {}\n{}: {}",
                 BOLD_GREEN.apply_to("Exit Status"),
-                BOLD_BLUE.apply_to(list[0].exit_code()),
+                BOLD_BLUE.apply_to(describe_exit(
+                    list[0].exit_code(),
+                    list[0].exit_signal(),
+                    list[0].core_dumped()
+                )),
                 BOLD_GREEN.apply_to("Success"),
                 BOLD_BLUE.apply_to(list[0].success())
             );
@@ -433,10 +735,18 @@ impl Handler {
                     .as_ref()
                     .map_or("None", String::as_str)
                     .to_string();
-                let _exit_code = output.exit_code();
-                let _success = output.success();
+                let exit_code = describe_exit(
+                    output.exit_code(),
+                    output.exit_signal(),
+                    output.core_dumped(),
+                );
 
-                let known_width = digits + timestamp.len() + max_bartoc_name + max_cmd_name + 12;
+                let known_width = digits
+                    + timestamp.len()
+                    + max_bartoc_name
+                    + max_cmd_name
+                    + exit_code.len().max(3)
+                    + 13;
                 let (mut final_data, data_uw) = clean_output_string(&data);
                 let disp_data = if data_uw <= usize::from(width).saturating_sub(known_width) {
                     final_data
@@ -446,11 +756,12 @@ impl Handler {
                     final_data
                 };
                 println!(
-                    "{:>digits$} - {}: {:<max_bartoc_name$} {:<max_cmd_name$} {}",
+                    "{:>digits$} - {}: {:<max_bartoc_name$} {:<max_cmd_name$} {:>3} {}",
                     BOLD_GREEN.apply_to(idx + 1),
                     BOLD_GREEN.apply_to(timestamp),
                     BOLD_YELLOW.apply_to(bartoc_name),
                     BOLD_YELLOW.apply_to(cmd_name),
+                    BOLD_YELLOW.apply_to(exit_code),
                     BOLD_BLUE.apply_to(disp_data),
                 );
                 if idx > 0 && (idx + 1) % print_height == 0 {
@@ -581,6 +892,42 @@ impl Handler {
         )
     }
 
+    fn maxes_query_rows(rows: &[QueryRow]) -> (usize, usize) {
+        let mut max_bartoc_name = 0;
+        let mut max_schedule_name = 0;
+        for row in rows {
+            if let Some(bartoc_name) = row.bartoc_name() {
+                max_bartoc_name = max_bartoc_name.max(bartoc_name.len());
+            }
+            if let Some(schedule_name) = row.schedule_name() {
+                max_schedule_name = max_schedule_name.max(schedule_name.len());
+            }
+        }
+        (max_bartoc_name, max_schedule_name)
+    }
+
+    fn maxes_client_events(events: &[ClientEvent]) -> (usize, usize) {
+        let mut max_name = 0;
+        let mut max_ip = 0;
+        for event in events {
+            max_name = max_name.max(event.bartoc_name().len());
+            max_ip = max_ip.max(event.ip().len());
+        }
+        (max_name, max_ip)
+    }
+
+    fn maxes_search_hits(hits: &[SearchHit]) -> (usize, usize) {
+        let mut max_bartoc_name = 0;
+        let mut max_schedule_name = 0;
+        for hit in hits {
+            max_bartoc_name = max_bartoc_name.max(hit.bartoc_name().len());
+            if let Some(schedule_name) = hit.schedule_name() {
+                max_schedule_name = max_schedule_name.max(schedule_name.len());
+            }
+        }
+        (max_bartoc_name, max_schedule_name)
+    }
+
     fn maxes_query(map: &BTreeMap<usize, BTreeMap<String, String>>) -> (usize, usize) {
         let mut max_col_label = 0;
         let mut max_val_label = 0;
@@ -635,18 +982,100 @@ impl Handler {
     }
 }
 
+/// The runs a tail has shown
+#[derive(Debug, Default)]
+struct TailRuns {
+    /// The client and schedule of each run shown that has not finished
+    running: HashMap<UuidWrapper, (String, String)>,
+    /// Whether any run has been shown yet
+    shown: bool,
+}
+
+impl TailRuns {
+    /// The line to print for a record of a subscribed run
+    fn line(&mut self, data: &Data) -> Option<String> {
+        let (timestamp, client, schedule, text) = match data {
+            Data::Started(run_start) => {
+                let _old = self.running.insert(
+                    run_start.cmd_uuid(),
+                    (
+                        run_start.bartoc_name().clone(),
+                        run_start.schedule_name().clone(),
+                    ),
+                );
+                (
+                    run_start.timestamp(),
+                    run_start.bartoc_name().clone(),
+                    run_start.schedule_name().clone(),
+                    format!("{} {}", BOLD_BLUE.apply_to("started"), run_start.cmd()),
+                )
+            }
+            Data::Output(output) => {
+                let _old = self.running.insert(
+                    output.cmd_uuid(),
+                    (output.bartoc_name().clone(), output.cmd_name().clone()),
+                );
+                let (data, _width) = clean_output_string(output.data());
+                let data = match output.kind() {
+                    OutputKind::Stdout => data,
+                    OutputKind::Stderr => BOLD_YELLOW.apply_to(data).to_string(),
+                };
+                (
+                    output.timestamp(),
+                    output.bartoc_name().clone(),
+                    output.cmd_name().clone(),
+                    data,
+                )
+            }
+            Data::Status(status) => {
+                let (client, schedule) = self.running.remove(&status.cmd_uuid())?;
+                let style = if status.success() {
+                    &*BOLD_GREEN
+                } else {
+                    &*BOLD_RED
+                };
+                let exit = describe_exit(
+                    status.exit_code(),
+                    status.exit_signal(),
+                    status.core_dumped(),
+                );
+                (
+                    status.timestamp(),
+                    client,
+                    schedule,
+                    format!("{} {exit}", style.apply_to("finished")),
+                )
+            }
+        };
+        self.shown = true;
+        Some(format!(
+            "{}: {} {} {text}",
+            BOLD_GREEN.apply_to(timestamp.0),
+            BOLD_YELLOW.apply_to(client),
+            BOLD_BLUE.apply_to(schedule),
+        ))
+    }
+
+    /// Whether every run shown has finished
+    fn finished(&self) -> bool {
+        self.shown && self.running.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::{BTreeMap, HashMap};
 
     use bincode_next::{config::standard, encode_to_vec};
     use libbarto::{
-        BartosToBartoCli, ClientData, FailedOutput, Garuda, ListOutput, UpdateKind, UuidWrapper,
+        BartosToBartoCli, ClientData, ClientEvent, ClientEventKind, Data, ExportRow, FailedOutput,
+        Garuda, ListOutput, OffsetDataTimeWrapper, Output, OutputKind, QueryRow, RunStart,
+        SearchHit, Status, UpdateKind, UuidWrapper,
     };
     use tokio_tungstenite::tungstenite::Message;
     use uuid::Uuid;
 
-    use super::Handler;
+    use super::{Handler, TailRuns};
     use crate::error::Error;
 
     fn garuda(channel: &str, package: &str) -> Garuda {
@@ -668,6 +1097,44 @@ mod tests {
             .build()
     }
 
+    fn started(cmd_uuid: Uuid) -> Data {
+        Data::Started(
+            RunStart::builder()
+                .cmd_uuid(UuidWrapper(cmd_uuid))
+                .bartoc_uuid(UuidWrapper(Uuid::nil()))
+                .bartoc_name("host1".to_string())
+                .schedule_name("backup".to_string())
+                .cmd("rsync -a /home /backup".to_string())
+                .timestamp(OffsetDataTimeWrapper(time::OffsetDateTime::UNIX_EPOCH))
+                .build(),
+        )
+    }
+
+    fn output(cmd_uuid: Uuid, kind: OutputKind, line: &str) -> Data {
+        Data::Output(
+            Output::builder()
+                .bartoc_uuid(UuidWrapper(Uuid::nil()))
+                .bartoc_name("host1".to_string())
+                .timestamp(OffsetDataTimeWrapper(time::OffsetDateTime::UNIX_EPOCH))
+                .cmd_uuid(UuidWrapper(cmd_uuid))
+                .cmd_name("backup".to_string())
+                .kind(kind)
+                .data(line.to_string())
+                .build(),
+        )
+    }
+
+    fn status(cmd_uuid: Uuid, exit_code: i32) -> Data {
+        Data::Status(
+            Status::builder()
+                .cmd_uuid(UuidWrapper(cmd_uuid))
+                .timestamp(OffsetDataTimeWrapper(time::OffsetDateTime::UNIX_EPOCH))
+                .exit_code(Some(exit_code))
+                .success(exit_code == 0)
+                .build(),
+        )
+    }
+
     // Kept intentionally narrow: the display fns fall back to a 24-column
     // terminal width when run headless. The width math uses `saturating_sub`,
     // so wider content no longer panics, but narrow data keeps the printed
@@ -677,11 +1144,44 @@ mod tests {
             .bartoc_name("h".to_string())
             .cmd_name("c".to_string())
             .data("d".to_string())
-            .exit_code(1)
+            .exit_signal(9)
+            .success(0)
+            .build()
+    }
+
+    fn query_row() -> QueryRow {
+        QueryRow::builder()
+            .cmd_uuid(UuidWrapper(Uuid::nil()))
+            .bartoc_name("host1".to_string())
+            .schedule_name("backup".to_string())
+            .data("d".to_string())
             .success(0)
             .build()
     }
 
+    fn search_hit() -> SearchHit {
+        SearchHit::builder()
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .timestamp(OffsetDataTimeWrapper(time::OffsetDateTime::UNIX_EPOCH))
+            .bartoc_name("host1".to_string())
+            .schedule_name("backup".to_string())
+            .line("error: backup failed".to_string())
+            .before(vec!["starting backup".to_string()])
+            .build()
+    }
+
+    fn client_event() -> ClientEvent {
+        ClientEvent::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name("host1".to_string())
+            .ip("10.0.0.1".to_string())
+            .kind(ClientEventKind::Disconnect)
+            .reason("closed".to_string())
+            .close_code(1000)
+            .timestamp(OffsetDataTimeWrapper(time::OffsetDateTime::UNIX_EPOCH))
+            .build()
+    }
+
     #[test]
     fn maxes_garuda_widths() {
         let garudas = vec![garuda("ch", "pkgname")];
@@ -690,6 +1190,47 @@ mod tests {
         assert_eq!(max_channel, "ch".len());
     }
 
+    #[test]
+    fn maxes_query_rows_widths() {
+        let (max_bartoc_name, max_schedule_name) = Handler::maxes_query_rows(&[query_row()]);
+        assert_eq!(max_bartoc_name, "host1".len());
+        assert_eq!(max_schedule_name, "backup".len());
+    }
+
+    #[test]
+    fn maxes_search_hits_widths() {
+        let (max_bartoc_name, max_schedule_name) = Handler::maxes_search_hits(&[search_hit()]);
+        assert_eq!(max_bartoc_name, "host1".len());
+        assert_eq!(max_schedule_name, "backup".len());
+    }
+
+    #[test]
+    fn maxes_client_events_widths() {
+        let (max_name, max_ip) = Handler::maxes_client_events(&[client_event()]);
+        assert_eq!(max_name, "host1".len());
+        assert_eq!(max_ip, "10.0.0.1".len());
+    }
+
+    #[test]
+    fn split_matches_marks_whole_words() {
+        let words = ["commit".to_string(), "failed".to_string()];
+        assert_eq!(
+            Handler::split_matches("error: FAILED to commit, recommit", &words),
+            vec![
+                ("error: ", false),
+                ("FAILED", true),
+                (" to ", false),
+                ("commit", true),
+                (", recommit", false),
+            ]
+        );
+        assert_eq!(
+            Handler::split_matches("nothing here", &words),
+            vec![("nothing here", false)]
+        );
+        assert!(Handler::split_matches("", &words).is_empty());
+    }
+
     #[test]
     fn maxes_query_widths() {
         let mut row = BTreeMap::new();
@@ -715,7 +1256,7 @@ mod tests {
 
     #[test]
     fn handle_message_none_is_err() {
-        let res = Handler::handle_message(None);
+        let res = Handler::handle_message(None, &[]);
         assert!(matches!(
             res.unwrap_err().downcast_ref::<Error>(),
             Some(Error::InvalidMessage)
@@ -724,7 +1265,7 @@ mod tests {
 
     #[test]
     fn handle_message_non_binary_is_err() {
-        let res = Handler::handle_message(Some(Ok(Message::Text("nope".into()))));
+        let res = Handler::handle_message(Some(Ok(Message::Text("nope".into()))), &[]);
         assert!(matches!(
             res.unwrap_err().downcast_ref::<Error>(),
             Some(Error::InvalidMessage)
@@ -734,13 +1275,30 @@ mod tests {
     #[test]
     fn handle_message_binary_is_ok() {
         let payload = encode_to_vec(BartosToBartoCli::Cleanup((1, 2, 3)), standard()).unwrap();
-        let res = Handler::handle_message(Some(Ok(Message::Binary(payload.into()))));
+        let res = Handler::handle_message(Some(Ok(Message::Binary(payload.into()))), &[]);
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn export_rows_take_only_export_pages() {
+        let row = ExportRow::builder()
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .build();
+        let binary = |msg| {
+            Some(Ok(Message::Binary(
+                encode_to_vec(msg, standard()).unwrap().into(),
+            )))
+        };
+        let rows = Handler::export_rows(binary(BartosToBartoCli::Export(vec![row.clone()])), &[]);
+        assert_eq!(rows.unwrap(), Some(vec![row]));
+        let denied = binary(BartosToBartoCli::Denied("no".to_string()));
+        assert_eq!(Handler::export_rows(denied, &[]).unwrap(), None);
+        assert!(Handler::export_rows(None, &[]).is_err());
+    }
+
     #[test]
     fn handle_binary_garbage_does_not_panic() {
-        Handler::handle_binary(&[0xff, 0xff, 0xff, 0xff]);
+        Handler::handle_binary(&[0xff, 0xff, 0xff, 0xff], &[]);
     }
 
     #[test]
@@ -764,26 +1322,66 @@ mod tests {
             BartosToBartoCli::Cleanup((1, 2, 3)),
             BartosToBartoCli::Clients(clients),
             BartosToBartoCli::ClientVersions(versions),
-            BartosToBartoCli::Query(query),
+            BartosToBartoCli::ClientHistory(vec![client_event()]),
+            BartosToBartoCli::ClientHistory(vec![]),
+            BartosToBartoCli::Query(vec![query_row()]),
+            BartosToBartoCli::RawQuery(query),
+            BartosToBartoCli::Search(vec![search_hit()]),
+            BartosToBartoCli::Search(vec![]),
             BartosToBartoCli::List(vec![list_output()]),
             BartosToBartoCli::Failed(vec![failed_output()]),
             BartosToBartoCli::ListCommands(vec!["backup".to_string(), "restore".to_string()]),
             BartosToBartoCli::Updates(UpdateKind::Garuda(vec![garuda("ch", "pkg")])),
+            BartosToBartoCli::Enroll(("host1".to_string(), Some("token".to_string()))),
+            BartosToBartoCli::Enroll(("host2".to_string(), None)),
+            BartosToBartoCli::Revoke(("host1".to_string(), true)),
+            BartosToBartoCli::Revoke(("host3".to_string(), false)),
+            BartosToBartoCli::Denied("enroll requires admin permission".to_string()),
+            BartosToBartoCli::Subscribed,
+            BartosToBartoCli::Tail(status(Uuid::nil(), 0)),
         ];
         for msg in messages {
             let bytes = encode_to_vec(msg, standard()).unwrap();
-            Handler::handle_binary(&bytes);
+            Handler::handle_binary(&bytes, &["backup".to_string()]);
         }
     }
 
+    #[test]
+    fn tail_runs_finish_with_their_status() {
+        let mut runs = TailRuns::default();
+        assert!(!runs.finished());
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let line = runs.line(&started(first)).unwrap();
+        assert!(line.contains("host1"));
+        assert!(line.contains("rsync -a /home /backup"));
+        let line = runs
+            .line(&output(second, OutputKind::Stderr, "copied\n"))
+            .unwrap();
+        assert!(line.contains("copied"));
+        assert!(!runs.finished());
+        // A status of a run never shown has nothing to attach to
+        assert!(runs.line(&status(Uuid::new_v4(), 0)).is_none());
+        let line = runs.line(&status(first, 0)).unwrap();
+        assert!(line.contains("finished"));
+        assert!(!runs.finished());
+        let line = runs.line(&status(second, 2)).unwrap();
+        assert!(line.contains("backup"));
+        assert!(runs.finished());
+    }
+
     #[test]
     fn handle_empty_collections_do_not_panic() {
-        Handler::handle_binary(&encode_to_vec(BartosToBartoCli::List(vec![]), standard()).unwrap());
+        Handler::handle_binary(
+            &encode_to_vec(BartosToBartoCli::List(vec![]), standard()).unwrap(),
+            &[],
+        );
         Handler::handle_binary(
             &encode_to_vec(BartosToBartoCli::Failed(vec![]), standard()).unwrap(),
+            &[],
         );
         Handler::handle_binary(
             &encode_to_vec(BartosToBartoCli::ListCommands(vec![]), standard()).unwrap(),
+            &[],
         );
     }
 }
@@ -6,7 +6,7 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::{io::Cursor, sync::LazyLock};
+use std::{io::Cursor, path::PathBuf, sync::LazyLock};
 
 use clap::{ArgAction, Parser, Subcommand};
 use config::{ConfigError, Map, Source, Value, ValueKind};
@@ -166,17 +166,179 @@ pub(crate) enum Commands {
     },
     #[clap(about = "Perform cleanup of old database entries")]
     Cleanup,
-    #[clap(about = "List the currently connected clients")]
+    #[clap(about = "List the currently connected clients, or manage client enrollment")]
     Clients {
         /// Show the bartoc binary version for each connected client
         #[clap(long, help = "Show the bartoc version for each client")]
         versions: bool,
+        /// Show the recorded connects and disconnects instead of the connected clients
+        #[clap(
+            long,
+            conflicts_with = "versions",
+            help = "Show the recorded connects and disconnects, newest first"
+        )]
+        history: bool,
+        /// Only the history of this bartoc client
+        #[clap(
+            short,
+            long,
+            requires = "history",
+            help = "Only the history of this bartoc client"
+        )]
+        name: Option<String>,
+        /// Only events at or after this time
+        #[clap(
+            long,
+            requires = "history",
+            help = "Only events at or after this time (RFC 3339, or an age such as 12h or 7d)"
+        )]
+        since: Option<String>,
+        /// The most events to return
+        #[clap(
+            short,
+            long,
+            requires = "history",
+            help = "The most events to return (bartos defaults to 100)"
+        )]
+        limit: Option<u32>,
+        /// Enroll or revoke a client in the bartos client registry
+        #[command(subcommand)]
+        action: Option<ClientsSubcommand>,
     },
-    #[clap(about = "Run a query on bartos")]
+    #[clap(about = "Query the runs recorded on bartos")]
     Query {
-        /// The query to run on bartos
-        #[clap(short, long, help = "The query to run on bartos")]
-        query: String,
+        /// Only runs on this bartoc client
+        #[clap(short, long, help = "Only runs on this bartoc client")]
+        name: Option<String>,
+        /// Only runs of this schedule
+        #[clap(short, long, help = "Only runs of this schedule")]
+        schedule: Option<String>,
+        /// Only output at or after this time
+        #[clap(
+            long,
+            help = "Only output at or after this time (RFC 3339, or an age such as 12h or 7d)"
+        )]
+        since: Option<String>,
+        /// Only output before this time
+        #[clap(
+            long,
+            help = "Only output before this time (RFC 3339, or an age such as 12h or 7d)"
+        )]
+        until: Option<String>,
+        /// Only runs in this state
+        #[clap(long, help = "Only runs in this state (succeeded, failed or running)")]
+        state: Option<String>,
+        /// Only output lines containing this text
+        #[clap(
+            short,
+            long,
+            help = "Only output lines containing this text, ignoring case"
+        )]
+        text: Option<String>,
+        /// The most rows to return
+        #[clap(short, long, help = "The most rows to return (bartos defaults to 100)")]
+        limit: Option<u32>,
+        /// The number of rows to skip
+        #[clap(short, long, default_value_t = 0, help = "The number of rows to skip")]
+        offset: u32,
+        /// Raw read-only SQL to run instead of a filter
+        #[clap(
+            long,
+            conflicts_with_all = ["name", "schedule", "since", "until", "state", "text", "limit", "offset"],
+            help = "Run this read-only SQL instead (requires raw query permission on bartos)"
+        )]
+        raw: Option<String>,
+    },
+    #[clap(about = "Search the stored job output for words, with the lines around each match")]
+    Search {
+        /// The words to search for
+        #[clap(help = "The words to search for, matched whole, in order and ignoring case")]
+        text: String,
+        /// Only output of this bartoc client
+        #[clap(short, long, help = "Only output of this bartoc client")]
+        name: Option<String>,
+        /// Only output of this schedule
+        #[clap(short, long, help = "Only output of this schedule")]
+        schedule: Option<String>,
+        /// Only output at or after this time
+        #[clap(
+            long,
+            help = "Only output at or after this time (RFC 3339, or an age such as 12h or 7d)"
+        )]
+        since: Option<String>,
+        /// Only output before this time
+        #[clap(
+            long,
+            help = "Only output before this time (RFC 3339, or an age such as 12h or 7d)"
+        )]
+        until: Option<String>,
+        /// The most matches to return
+        #[clap(
+            short,
+            long,
+            help = "The most matches to return (bartos defaults to 50)"
+        )]
+        limit: Option<u32>,
+        /// The lines of context to show around each match
+        #[clap(
+            short = 'C',
+            long,
+            default_value_t = 2,
+            help = "The lines of the same run to show before and after each match"
+        )]
+        context: u8,
+    },
+    #[clap(about = "Export the job history as JSON Lines, CSV or an Arrow IPC stream")]
+    Export {
+        /// Only runs of this bartoc client
+        #[clap(short, long, help = "Only runs of this bartoc client")]
+        name: Option<String>,
+        /// Only runs of this schedule
+        #[clap(short, long, help = "Only runs of this schedule")]
+        schedule: Option<String>,
+        /// Only runs that started at or after this time
+        #[clap(
+            long,
+            help = "Only runs that started at or after this time (RFC 3339, or an age such as 12h or 7d)"
+        )]
+        since: Option<String>,
+        /// Only runs that started before this time
+        #[clap(
+            long,
+            help = "Only runs that started before this time (RFC 3339, or an age such as 12h or 7d)"
+        )]
+        until: Option<String>,
+        /// The format to write
+        #[clap(
+            short,
+            long,
+            help = "The format to write: jsonl, csv or arrow (defaults to the output file extension, then jsonl)"
+        )]
+        format: Option<String>,
+        /// The file to write
+        #[clap(short, long, help = "The file to write, rather than stdout")]
+        output: Option<PathBuf>,
+    },
+    #[clap(about = "Stream the output of running jobs as it reaches bartos")]
+    Tail {
+        /// Only runs on this bartoc client
+        #[clap(
+            short,
+            long,
+            visible_alias = "client",
+            help = "Only runs on this bartoc client"
+        )]
+        name: Option<String>,
+        /// Only runs of this schedule
+        #[clap(short, long, help = "Only runs of this schedule")]
+        schedule: Option<String>,
+        /// Keep streaming new runs rather than stopping once the runs shown have finished
+        #[clap(
+            short,
+            long,
+            help = "Keep streaming new runs until interrupted, rather than stopping once the runs shown have finished"
+        )]
+        follow: bool,
     },
     #[clap(about = "List the output for the given command")]
     List {
@@ -201,6 +363,25 @@ pub(crate) enum Commands {
     },
 }
 
+#[derive(Clone, Debug, Subcommand)]
+pub(crate) enum ClientsSubcommand {
+    /// Enroll a client in the bartos client registry (requires admin permission).
+    /// Without `--public-key`, bartos issues a token that is printed once.
+    Enroll {
+        /// Name of the client to enroll
+        name: String,
+        /// Base64 Ed25519 public key the client signs its name with
+        #[clap(long)]
+        public_key: Option<String>,
+    },
+    /// Revoke a client's credential (requires admin permission).
+    /// The client is disconnected and rejected until it is enrolled again.
+    Revoke {
+        /// Name of the client to revoke
+        name: String,
+    },
+}
+
 /// Wrapper so `secrets` appears as a subcommand with its own sub-subcommands.
 #[derive(Clone, Debug, clap::Args)]
 pub(crate) struct SecretsArgs {
@@ -228,15 +409,27 @@ pub(crate) enum SecretsSubcommand {
         /// Name of the secret to delete
         key: String,
     },
+    /// Generate new Ed25519 signing material for a key rotation and print the
+    /// bartos.toml and bartoc.toml changes needed. Nothing is stored.
+    Rotate {
+        /// The key ID for the new key (must differ from the key IDs currently in use)
+        #[clap(long)]
+        key_id: u32,
+        /// Also generate a new HMAC key under the same key ID
+        #[clap(long, default_value_t = false)]
+        hmac: bool,
+    },
 }
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use clap::Parser;
     use config::Source;
     use libbarto::PathDefaults;
 
-    use super::{Cli, Commands, SecretsSubcommand};
+    use super::{Cli, ClientsSubcommand, Commands, SecretsSubcommand};
 
     fn parse(args: &[&str]) -> Cli {
         Cli::parse_from(std::iter::once("barto-cli").chain(args.iter().copied()))
@@ -313,22 +506,212 @@ mod tests {
     fn command_clients() {
         assert!(matches!(
             parse(&["clients"]).command(),
-            Commands::Clients { versions: false }
+            Commands::Clients {
+                versions: false,
+                history: false,
+                action: None,
+                ..
+            }
         ));
         assert!(matches!(
             parse(&["clients", "--versions"]).command(),
-            Commands::Clients { versions: true }
+            Commands::Clients {
+                versions: true,
+                action: None,
+                ..
+            }
         ));
     }
 
+    #[test]
+    fn command_clients_history() {
+        match parse(&[
+            "clients",
+            "--history",
+            "-n",
+            "host1",
+            "--since",
+            "7d",
+            "-l",
+            "20",
+        ])
+        .command()
+        {
+            Commands::Clients {
+                history,
+                name,
+                since,
+                limit,
+                action: None,
+                ..
+            } => {
+                assert!(history);
+                assert_eq!(name.as_deref(), Some("host1"));
+                assert_eq!(since.as_deref(), Some("7d"));
+                assert_eq!(*limit, Some(20));
+            }
+            other => panic!("expected Clients, got {other:?}"),
+        }
+        assert!(Cli::try_parse_from(["barto-cli", "clients", "-n", "host1"]).is_err());
+        assert!(Cli::try_parse_from(["barto-cli", "clients", "--history", "--versions"]).is_err());
+    }
+
+    #[test]
+    fn command_tail() {
+        match parse(&["tail", "--client", "host1", "-s", "backup", "-f"]).command() {
+            Commands::Tail {
+                name,
+                schedule,
+                follow,
+            } => {
+                assert_eq!(name.as_deref(), Some("host1"));
+                assert_eq!(schedule.as_deref(), Some("backup"));
+                assert!(follow);
+            }
+            other => panic!("expected Tail, got {other:?}"),
+        }
+        assert!(matches!(
+            parse(&["tail"]).command(),
+            Commands::Tail {
+                name: None,
+                schedule: None,
+                follow: false
+            }
+        ));
+    }
+
+    #[test]
+    fn command_clients_enroll() {
+        match parse(&["clients", "enroll", "host1"]).command() {
+            Commands::Clients {
+                action: Some(ClientsSubcommand::Enroll { name, public_key }),
+                ..
+            } => {
+                assert_eq!(name, "host1");
+                assert!(public_key.is_none());
+            }
+            other => panic!("expected Enroll, got {other:?}"),
+        }
+        match parse(&["clients", "enroll", "host1", "--public-key", "PK"]).command() {
+            Commands::Clients {
+                action: Some(ClientsSubcommand::Enroll { public_key, .. }),
+                ..
+            } => assert_eq!(public_key.as_deref(), Some("PK")),
+            other => panic!("expected Enroll, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn command_clients_revoke() {
+        match parse(&["clients", "revoke", "host1"]).command() {
+            Commands::Clients {
+                action: Some(ClientsSubcommand::Revoke { name }),
+                ..
+            } => assert_eq!(name, "host1"),
+            other => panic!("expected Revoke, got {other:?}"),
+        }
+    }
+
     #[test]
     fn command_query() {
-        match parse(&["query", "-q", "select 1"]).command() {
-            Commands::Query { query } => assert_eq!(query, "select 1"),
+        match parse(&[
+            "query", "-n", "host1", "--state", "failed", "--since", "7d", "-l", "10",
+        ])
+        .command()
+        {
+            Commands::Query {
+                name,
+                state,
+                since,
+                limit,
+                offset,
+                raw,
+                ..
+            } => {
+                assert_eq!(name.as_deref(), Some("host1"));
+                assert_eq!(state.as_deref(), Some("failed"));
+                assert_eq!(since.as_deref(), Some("7d"));
+                assert_eq!((*limit, *offset), (Some(10), 0));
+                assert!(raw.is_none());
+            }
+            other => panic!("expected Query, got {other:?}"),
+        }
+        match parse(&["query", "--raw", "select 1"]).command() {
+            Commands::Query { raw, .. } => assert_eq!(raw.as_deref(), Some("select 1")),
             other => panic!("expected Query, got {other:?}"),
         }
     }
 
+    #[test]
+    fn command_query_raw_conflicts_with_filters() {
+        let result =
+            Cli::try_parse_from(["barto-cli", "query", "--raw", "select 1", "-n", "host1"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn command_search() {
+        match parse(&["search", "failed to commit", "-n", "host1", "--since", "7d"]).command() {
+            Commands::Search {
+                text,
+                name,
+                since,
+                limit,
+                context,
+                ..
+            } => {
+                assert_eq!(text, "failed to commit");
+                assert_eq!(name.as_deref(), Some("host1"));
+                assert_eq!(since.as_deref(), Some("7d"));
+                assert_eq!((*limit, *context), (None, 2));
+            }
+            other => panic!("expected Search, got {other:?}"),
+        }
+        match parse(&["search", "-C", "0", "error"]).command() {
+            Commands::Search { context, .. } => assert_eq!(*context, 0),
+            other => panic!("expected Search, got {other:?}"),
+        }
+        assert!(Cli::try_parse_from(["barto-cli", "search"]).is_err());
+    }
+
+    #[test]
+    fn command_export() {
+        match parse(&[
+            "export",
+            "-s",
+            "backup",
+            "--since",
+            "30d",
+            "-o",
+            "history.csv",
+        ])
+        .command()
+        {
+            Commands::Export {
+                name,
+                schedule,
+                since,
+                format,
+                output,
+                ..
+            } => {
+                assert!(name.is_none());
+                assert_eq!(schedule.as_deref(), Some("backup"));
+                assert_eq!(since.as_deref(), Some("30d"));
+                assert!(format.is_none());
+                assert_eq!(output.as_deref(), Some(Path::new("history.csv")));
+            }
+            other => panic!("expected Export, got {other:?}"),
+        }
+        match parse(&["export", "-f", "arrow"]).command() {
+            Commands::Export { format, output, .. } => {
+                assert_eq!(format.as_deref(), Some("arrow"));
+                assert!(output.is_none());
+            }
+            other => panic!("expected Export, got {other:?}"),
+        }
+    }
+
     #[test]
     fn command_list() {
         match parse(&["list", "-n", "host1"]).command() {
@@ -399,6 +782,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn command_secrets_rotate() {
+        match secrets_subcommand(&["secrets", "rotate", "--key-id", "2"]) {
+            SecretsSubcommand::Rotate { key_id, hmac } => {
+                assert_eq!(key_id, 2);
+                assert!(!hmac);
+            }
+            other => panic!("expected Rotate, got {other:?}"),
+        }
+        match secrets_subcommand(&["secrets", "rotate", "--key-id", "3", "--hmac"]) {
+            SecretsSubcommand::Rotate { key_id, hmac } => {
+                assert_eq!(key_id, 3);
+                assert!(hmac);
+            }
+            other => panic!("expected Rotate, got {other:?}"),
+        }
+    }
+
     #[test]
     fn source_collect_basic_keys() {
         let cli = parse(&["-v", "info"]);
@@ -11,28 +11,41 @@ mod secrets;
 
 use std::{
     ffi::OsString,
-    io::{Write, stdout},
+    fs::File,
+    io::{BufWriter, Write, stdout},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
 use anyhow::{Context as _, Result};
 use bincode_next::{config::standard, encode_to_vec};
 use clap::Parser as _;
-use futures_util::{SinkExt as _, StreamExt as _};
+use futures_util::{Sink, SinkExt as _, StreamExt as _};
 use libbarto::{
-    BartoCli, CliUpdateKind, header, init_tracing, load, load_client_cert_and_key,
-    load_pinned_root_store,
+    BartoCli, CliUpdateKind, ClientEventFilter, ExportFilter, ExportFormat, ExportRow,
+    ExportWriter, OffsetDataTimeWrapper, QueryFilter, RunState, SearchFilter, TailFilter,
+    client_auth_headers, header, init_tracing, load, load_client_cert_and_key,
+    load_pinned_root_store, parse_time_bound, search_words,
 };
+use time::OffsetDateTime;
 use tokio_tungstenite::{
     Connector, connect_async_tls_with_config,
-    tungstenite::{Message, client::ClientRequestBuilder, http::Uri},
+    tungstenite::{self, Message, client::ClientRequestBuilder, http::Uri},
 };
 use tracing::trace;
 
-use crate::{config::Config, error::Error, handler::Handler, runtime::cli::Commands};
+use crate::{
+    config::Config,
+    error::Error,
+    handler::Handler,
+    runtime::cli::{ClientsSubcommand, Commands},
+};
 
 use self::cli::Cli;
 
+/// Runs requested per export page
+const EXPORT_PAGE_RUNS: u32 = 100;
+
 const HEADER_PREFIX: &str = r"██████╗  █████╗ ██████╗ ████████╗ ██████╗        ██████╗██╗     ██╗
 ██╔══██╗██╔══██╗██╔══██╗╚══██╔══╝██╔═══██╗      ██╔════╝██║     ██║
 ██████╔╝███████║██████╔╝   ██║   ██║   ██║█████╗██║     ██║     ██║
@@ -86,23 +99,49 @@ where
     );
     trace!("connecting to bartos at {url}");
     let uri: Uri = url.parse()?;
-    let ws_req = if let Some(token) = config.bartos().api_key() {
-        trace!("adding Bearer auth header to WebSocket upgrade");
-        ClientRequestBuilder::new(uri).with_header("Authorization", format!("Bearer {token}"))
-    } else {
-        ClientRequestBuilder::new(uri)
-    };
+    let ws_req = client_auth_headers(config.bartos(), config.name())?
+        .into_iter()
+        .fold(ClientRequestBuilder::new(uri), |req, (header, value)| {
+            trace!("adding '{header}' auth header to WebSocket upgrade");
+            req.with_header(header, value)
+        });
     let (ws_stream, _) =
         connect_async_tls_with_config(ws_req, None, false, Some(make_tls_connector(&config)?))
             .await?;
     trace!("websocket connected");
     let (mut sink, stream) = ws_stream.split();
-    let mut handler = Handler::builder().stream(stream).build();
+    let highlight = match cli.command() {
+        Commands::Search { text, .. } => search_words(text),
+        _ => vec![],
+    };
+    let mut handler = Handler::builder()
+        .stream(stream)
+        .highlight(highlight)
+        .build();
+
+    if let Commands::Export {
+        name,
+        schedule,
+        since,
+        until,
+        format,
+        output,
+    } = cli.command()
+    {
+        let filter = export_filter(name, schedule, since, until)?;
+        let format = export_format(format.as_deref(), output.as_deref())?;
+        export(&mut sink, &mut handler, &filter, format, output.as_ref()).await?;
+    } else if let Commands::Tail { follow, .. } = cli.command() {
+        sink.send(build_message(cli.command())?).await?;
+        trace!("subscribe sent");
 
-    sink.send(build_message(cli.command())?).await?;
-    trace!("message sent");
+        handler.tail(*follow).await?;
+    } else {
+        sink.send(build_message(cli.command())?).await?;
+        trace!("message sent");
 
-    handler.handle().await?;
+        handler.handle().await?;
+    }
 
     sink.send(Message::Close(None)).await?;
     trace!("close sent");
@@ -112,8 +151,9 @@ where
 
 fn build_message(command: &Commands) -> Result<Message> {
     let payload = match command {
-        // Secrets are handled before reaching this point — see run().
+        // Secrets and exports are handled before reaching this point — see run().
         Commands::Secrets(_) => unreachable!("secrets handled before build_message"),
+        Commands::Export { .. } => unreachable!("exports handled before build_message"),
         Commands::Info { json } => encode_to_vec(BartoCli::Info { json: *json }, standard())?,
         Commands::Updates { name, update_kind } => {
             let kind = CliUpdateKind::try_from(update_kind.as_str())?;
@@ -126,19 +166,87 @@ fn build_message(command: &Commands) -> Result<Message> {
             )?
         }
         Commands::Cleanup => encode_to_vec(BartoCli::Cleanup, standard())?,
-        Commands::Clients { versions } => {
-            if *versions {
-                encode_to_vec(BartoCli::ClientVersions, standard())?
-            } else {
-                encode_to_vec(BartoCli::Clients, standard())?
-            }
+        Commands::Clients {
+            history: true,
+            name,
+            since,
+            limit,
+            ..
+        } => {
+            let filter = ClientEventFilter::builder()
+                .maybe_client(name.clone())
+                .maybe_since(time_bound(since)?)
+                .maybe_limit(*limit)
+                .build();
+            encode_to_vec(BartoCli::ClientHistory { filter }, standard())?
         }
-        Commands::Query { query } => encode_to_vec(
-            BartoCli::Query {
+        Commands::Clients {
+            versions, action, ..
+        } => match action {
+            Some(ClientsSubcommand::Enroll { name, public_key }) => encode_to_vec(
+                BartoCli::Enroll {
+                    name: name.clone(),
+                    public_key: public_key.clone(),
+                },
+                standard(),
+            )?,
+            Some(ClientsSubcommand::Revoke { name }) => {
+                encode_to_vec(BartoCli::Revoke { name: name.clone() }, standard())?
+            }
+            None if *versions => encode_to_vec(BartoCli::ClientVersions, standard())?,
+            None => encode_to_vec(BartoCli::Clients, standard())?,
+        },
+        Commands::Query {
+            raw: Some(query), ..
+        } => encode_to_vec(
+            BartoCli::RawQuery {
                 query: query.clone(),
             },
             standard(),
         )?,
+        Commands::Query {
+            name,
+            schedule,
+            since,
+            until,
+            state,
+            text,
+            limit,
+            offset,
+            raw: None,
+        } => {
+            let filter = QueryFilter::builder()
+                .maybe_client(name.clone())
+                .maybe_schedule(schedule.clone())
+                .maybe_since(time_bound(since)?)
+                .maybe_until(time_bound(until)?)
+                .maybe_state(state.as_deref().map(RunState::try_from).transpose()?)
+                .maybe_text(text.clone())
+                .maybe_limit(*limit)
+                .offset(*offset)
+                .build();
+            encode_to_vec(BartoCli::Query { filter }, standard())?
+        }
+        Commands::Search {
+            text,
+            name,
+            schedule,
+            since,
+            until,
+            limit,
+            context,
+        } => {
+            let filter = SearchFilter::builder()
+                .text(text.clone())
+                .maybe_client(name.clone())
+                .maybe_schedule(schedule.clone())
+                .maybe_since(time_bound(since)?)
+                .maybe_until(time_bound(until)?)
+                .maybe_limit(*limit)
+                .context(*context)
+                .build();
+            encode_to_vec(BartoCli::Search { filter }, standard())?
+        }
         Commands::List { name, cmd_name_opt } => {
             if let Some(cmd_name) = cmd_name_opt {
                 encode_to_vec(
@@ -152,6 +260,13 @@ fn build_message(command: &Commands) -> Result<Message> {
                 encode_to_vec(BartoCli::ListCommands { name: name.clone() }, standard())?
             }
         }
+        Commands::Tail { name, schedule, .. } => {
+            let filter = TailFilter::builder()
+                .maybe_client(name.clone())
+                .maybe_schedule(schedule.clone())
+                .build();
+            encode_to_vec(BartoCli::Subscribe { filter }, standard())?
+        }
         Commands::Failed => encode_to_vec(BartoCli::Failed, standard())?,
         Commands::Cmd { cmd_name } => encode_to_vec(
             BartoCli::Cmd {
@@ -163,6 +278,97 @@ fn build_message(command: &Commands) -> Result<Message> {
     Ok(Message::Binary(payload.into()))
 }
 
+/// The filter of an export, paged from the first run
+fn export_filter(
+    name: &Option<String>,
+    schedule: &Option<String>,
+    since: &Option<String>,
+    until: &Option<String>,
+) -> Result<ExportFilter> {
+    Ok(ExportFilter::builder()
+        .maybe_client(name.clone())
+        .maybe_schedule(schedule.clone())
+        .maybe_since(time_bound(since)?)
+        .maybe_until(time_bound(until)?)
+        .limit(EXPORT_PAGE_RUNS)
+        .build())
+}
+
+/// The `--format` of an export, or the one its `--output` extension names, or JSON Lines
+fn export_format(format: Option<&str>, output: Option<&Path>) -> Result<ExportFormat> {
+    Ok(match format {
+        Some(format) => ExportFormat::try_from(format)?,
+        None => output
+            .and_then(ExportFormat::from_path)
+            .unwrap_or(ExportFormat::Jsonl),
+    })
+}
+
+/// The number of runs in an export page, whose rows hold each run together
+fn page_runs(rows: &[ExportRow]) -> u32 {
+    let mut runs = 0;
+    let mut last = None;
+    for row in rows {
+        if last != Some(row.cmd_uuid()) {
+            runs += 1;
+            last = Some(row.cmd_uuid());
+        }
+    }
+    runs
+}
+
+/// Request the export a page of runs at a time until bartos runs out, writing each page as
+/// it arrives
+#[cfg_attr(coverage_nightly, coverage(off))]
+async fn export<S>(
+    sink: &mut S,
+    handler: &mut Handler,
+    filter: &ExportFilter,
+    format: ExportFormat,
+    output: Option<&PathBuf>,
+) -> Result<()>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(stdout()),
+    };
+    let mut writer = ExportWriter::new(format, writer)?;
+    let mut offset = 0;
+    loop {
+        let page = ExportFilter::builder()
+            .maybe_client(filter.client().clone())
+            .maybe_schedule(filter.schedule().clone())
+            .maybe_since(filter.since())
+            .maybe_until(filter.until())
+            .maybe_limit(filter.limit())
+            .offset(offset)
+            .build();
+        let payload = encode_to_vec(BartoCli::Export { filter: page }, standard())?;
+        sink.send(Message::Binary(payload.into())).await?;
+        trace!("export page at offset {offset} requested");
+        let Some(rows) = handler.export_page().await? else {
+            break;
+        };
+        writer.write(&rows)?;
+        let runs = page_runs(&rows);
+        if runs < EXPORT_PAGE_RUNS {
+            break;
+        }
+        offset += runs;
+    }
+    writer.finish()
+}
+
+/// Parse an optional `--since` or `--until` bound, relative to now
+fn time_bound(bound: &Option<String>) -> Result<Option<OffsetDataTimeWrapper>> {
+    bound
+        .as_deref()
+        .map(|bound| parse_time_bound(bound, OffsetDateTime::now_utc()))
+        .transpose()
+}
+
 fn make_tls_connector(config: &Config) -> Result<Connector> {
     use rustls::{ClientConfig, RootCertStore};
     let root_store = if let Some(ca_cert_path) = config.bartos().ca_cert() {
@@ -185,12 +391,14 @@ fn make_tls_connector(config: &Config) -> Result<Connector> {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use bincode_next::{config::standard, decode_from_slice};
-    use libbarto::BartoCli;
+    use libbarto::{BartoCli, ExportFormat, ExportRow, RunState, UuidWrapper};
     use tokio_tungstenite::tungstenite::Message;
 
-    use super::build_message;
-    use crate::runtime::cli::Commands;
+    use super::{EXPORT_PAGE_RUNS, build_message, export_filter, export_format, page_runs};
+    use crate::runtime::cli::{ClientsSubcommand, Commands};
 
     fn payload(msg: Message) -> Vec<u8> {
         match msg {
@@ -233,26 +441,232 @@ mod tests {
 
     #[test]
     fn build_message_clients_variants() {
-        let msg = build_message(&Commands::Clients { versions: false }).expect("build");
+        let msg = build_message(&Commands::Clients {
+            versions: false,
+            history: false,
+            name: None,
+            since: None,
+            limit: None,
+            action: None,
+        })
+        .expect("build");
         let (decoded, _): (BartoCli, _) =
             decode_from_slice(&payload(msg), standard()).expect("decode");
         assert!(matches!(decoded, BartoCli::Clients));
 
-        let msg = build_message(&Commands::Clients { versions: true }).expect("build");
+        let msg = build_message(&Commands::Clients {
+            versions: true,
+            history: false,
+            name: None,
+            since: None,
+            limit: None,
+            action: None,
+        })
+        .expect("build");
         let (decoded, _): (BartoCli, _) =
             decode_from_slice(&payload(msg), standard()).expect("decode");
         assert!(matches!(decoded, BartoCli::ClientVersions));
     }
 
     #[test]
-    fn build_message_query() {
-        let msg = build_message(&Commands::Query {
-            query: "select 1".to_string(),
+    fn build_message_clients_history() {
+        let msg = build_message(&Commands::Clients {
+            versions: false,
+            history: true,
+            name: Some("host1".to_string()),
+            since: Some("7d".to_string()),
+            limit: Some(20),
+            action: None,
+        })
+        .expect("build");
+        let (decoded, _): (BartoCli, _) =
+            decode_from_slice(&payload(msg), standard()).expect("decode");
+        let BartoCli::ClientHistory { filter } = decoded else {
+            panic!("expected ClientHistory, got {decoded:?}");
+        };
+        assert_eq!(filter.client().as_deref(), Some("host1"));
+        assert!(filter.since().is_some());
+        assert_eq!(filter.limit(), Some(20));
+    }
+
+    #[test]
+    fn build_message_tail() {
+        let msg = build_message(&Commands::Tail {
+            name: None,
+            schedule: Some("backup".to_string()),
+            follow: true,
+        })
+        .expect("build");
+        let (decoded, _): (BartoCli, _) =
+            decode_from_slice(&payload(msg), standard()).expect("decode");
+        let BartoCli::Subscribe { filter } = decoded else {
+            panic!("expected Subscribe, got {decoded:?}");
+        };
+        assert!(filter.client().is_none());
+        assert_eq!(filter.schedule().as_deref(), Some("backup"));
+    }
+
+    #[test]
+    fn build_message_clients_enroll_revoke() {
+        let msg = build_message(&Commands::Clients {
+            versions: false,
+            history: false,
+            name: None,
+            since: None,
+            limit: None,
+            action: Some(ClientsSubcommand::Enroll {
+                name: "host1".to_string(),
+                public_key: None,
+            }),
+        })
+        .expect("build");
+        let (decoded, _): (BartoCli, _) =
+            decode_from_slice(&payload(msg), standard()).expect("decode");
+        assert_eq!(
+            decoded,
+            BartoCli::Enroll {
+                name: "host1".to_string(),
+                public_key: None
+            }
+        );
+
+        let msg = build_message(&Commands::Clients {
+            versions: false,
+            history: false,
+            name: None,
+            since: None,
+            limit: None,
+            action: Some(ClientsSubcommand::Revoke {
+                name: "host1".to_string(),
+            }),
         })
         .expect("build");
         let (decoded, _): (BartoCli, _) =
             decode_from_slice(&payload(msg), standard()).expect("decode");
-        assert!(matches!(decoded, BartoCli::Query { .. }));
+        assert_eq!(
+            decoded,
+            BartoCli::Revoke {
+                name: "host1".to_string()
+            }
+        );
+    }
+
+    fn query(raw: Option<&str>) -> Commands {
+        Commands::Query {
+            name: Some("host1".to_string()),
+            schedule: None,
+            since: Some("7d".to_string()),
+            until: None,
+            state: Some("failed".to_string()),
+            text: None,
+            limit: Some(10),
+            offset: 5,
+            raw: raw.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn build_message_query() {
+        let msg = build_message(&query(None)).expect("build");
+        let (decoded, _): (BartoCli, _) =
+            decode_from_slice(&payload(msg), standard()).expect("decode");
+        let BartoCli::Query { filter } = decoded else {
+            panic!("expected Query, got {decoded:?}");
+        };
+        assert_eq!(filter.client().as_deref(), Some("host1"));
+        assert_eq!(filter.state(), Some(RunState::Failed));
+        assert!(filter.since().is_some());
+        assert_eq!((filter.limit(), filter.offset()), (Some(10), 5));
+
+        let msg = build_message(&query(Some("select 1"))).expect("build");
+        let (decoded, _): (BartoCli, _) =
+            decode_from_slice(&payload(msg), standard()).expect("decode");
+        assert_eq!(
+            decoded,
+            BartoCli::RawQuery {
+                query: "select 1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn build_message_query_rejects_bad_filters() {
+        let mut bad_state = query(None);
+        if let Commands::Query { state, .. } = &mut bad_state {
+            *state = Some("done".to_string());
+        }
+        assert!(build_message(&bad_state).is_err());
+        let mut bad_since = query(None);
+        if let Commands::Query { since, .. } = &mut bad_since {
+            *since = Some("last week".to_string());
+        }
+        assert!(build_message(&bad_since).is_err());
+    }
+
+    #[test]
+    fn build_message_search() {
+        let search = |since: &str| Commands::Search {
+            text: "failed to commit".to_string(),
+            name: Some("host1".to_string()),
+            schedule: None,
+            since: Some(since.to_string()),
+            until: None,
+            limit: Some(5),
+            context: 3,
+        };
+        let msg = build_message(&search("7d")).expect("build");
+        let (decoded, _): (BartoCli, _) =
+            decode_from_slice(&payload(msg), standard()).expect("decode");
+        let BartoCli::Search { filter } = decoded else {
+            panic!("expected Search, got {decoded:?}");
+        };
+        assert_eq!(filter.text(), "failed to commit");
+        assert_eq!(filter.client().as_deref(), Some("host1"));
+        assert!(filter.since().is_some());
+        assert_eq!((filter.limit(), filter.context()), (Some(5), 3));
+        assert!(build_message(&search("last week")).is_err());
+    }
+
+    #[test]
+    fn export_filter_pages_from_the_start() {
+        let filter = export_filter(
+            &Some("host1".to_string()),
+            &None,
+            &Some("30d".to_string()),
+            &None,
+        )
+        .expect("filter");
+        assert_eq!(filter.client().as_deref(), Some("host1"));
+        assert!(filter.since().is_some());
+        assert_eq!(
+            (filter.limit(), filter.offset()),
+            (Some(EXPORT_PAGE_RUNS), 0)
+        );
+        assert!(export_filter(&None, &None, &None, &Some("soon".to_string())).is_err());
+    }
+
+    #[test]
+    fn export_format_defaults() {
+        let format = |format, output: Option<&str>| export_format(format, output.map(Path::new));
+        assert_eq!(format(None, None).unwrap(), ExportFormat::Jsonl);
+        assert_eq!(format(None, Some("out.csv")).unwrap(), ExportFormat::Csv);
+        assert_eq!(format(None, Some("out.txt")).unwrap(), ExportFormat::Jsonl);
+        assert_eq!(
+            format(Some("arrow"), Some("out.csv")).unwrap(),
+            ExportFormat::Arrow
+        );
+        assert!(format(Some("parquet"), None).is_err());
+    }
+
+    #[test]
+    fn page_runs_counts_each_run_once() {
+        let row = |n| {
+            ExportRow::builder()
+                .cmd_uuid(UuidWrapper(uuid::Uuid::from_u128(n)))
+                .build()
+        };
+        assert_eq!(page_runs(&[]), 0);
+        assert_eq!(page_runs(&[row(1), row(1), row(2), row(3), row(3)]), 3);
     }
 
     #[test]
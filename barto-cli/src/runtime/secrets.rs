@@ -12,8 +12,14 @@
 //! protocol), macOS Keychain on macOS, and Windows Credential Manager on
 //! Windows.
 
+use std::fmt::Write as _;
+
 use anyhow::{Context as _, Result};
 use keyring_core::Entry;
+use libbarto::{
+    SigningKey, generate_hmac_key, generate_signing_key, key_fingerprint, public_key_b64,
+    signing_key_b64,
+};
 
 use crate::runtime::cli::SecretsSubcommand;
 
@@ -22,8 +28,8 @@ const SERVICE: &str = "barto";
 /// Known client-side barto secrets managed via the platform keychain.
 ///
 /// `bartos` system-service secrets (`hmac_key`, `signing_key`, `api_key`,
-/// `mariadb_password`) are managed separately via `bartos-secrets-init` and
-/// systemd credentials — not listed here.
+/// `mariadb_password`, `postgres_password`) are managed separately via
+/// `bartos-secrets-init` and systemd credentials — not listed here.
 const KNOWN_SECRETS: &[(&str, &str)] = &[
     (
         "BARTOC_HMAC_KEY",
@@ -68,6 +74,15 @@ fn init_store() -> Result<()> {
 
 #[cfg_attr(coverage_nightly, coverage(off))]
 pub(crate) fn handle(cmd: &SecretsSubcommand) -> Result<()> {
+    // Rotation only generates and prints key material — it never touches the keychain.
+    if let SecretsSubcommand::Rotate { key_id, hmac } = *cmd {
+        let hmac_key = hmac.then(generate_hmac_key);
+        print!(
+            "{}",
+            rotation_instructions(key_id, &generate_signing_key(), hmac_key.as_deref())
+        );
+        return Ok(());
+    }
     init_store()?;
     match cmd {
         SecretsSubcommand::Set { key } => set(key),
@@ -77,7 +92,48 @@ pub(crate) fn handle(cmd: &SecretsSubcommand) -> Result<()> {
             Ok(())
         }
         SecretsSubcommand::Delete { key } => delete(key),
+        SecretsSubcommand::Rotate { .. } => unreachable!("rotate handled above"),
+    }
+}
+
+/// Renders the staged config changes for rotating to a new key under `key_id`.
+///
+/// bartoc accepts every key in its lists, so the new keys are added there first, bartos is
+/// switched over second, and the old keys are removed last — no flag day across the fleet.
+fn rotation_instructions(key_id: u32, signing_key: &SigningKey, hmac_key: Option<&str>) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "New Ed25519 key {key_id} (public fingerprint: {})\n",
+        key_fingerprint(&signing_key.verifying_key())
+    );
+    let _ = writeln!(
+        out,
+        "1. Add the new key(s) to every bartoc.toml, keeping the current ones:\n"
+    );
+    let _ = writeln!(out, "[[server_public_keys]]");
+    let _ = writeln!(out, "id = {key_id}");
+    let _ = writeln!(out, "key = \"{}\"", public_key_b64(signing_key));
+    if let Some(hmac_key) = hmac_key {
+        let _ = writeln!(out, "\n[[hmac_keys]]");
+        let _ = writeln!(out, "id = {key_id}");
+        let _ = writeln!(out, "key = \"{hmac_key}\"");
+    }
+    let _ = writeln!(
+        out,
+        "\n2. Once every bartoc has been restarted, switch bartos.toml to the new key(s):\n"
+    );
+    let _ = writeln!(out, "signing_key = \"{}\"", signing_key_b64(signing_key));
+    let _ = writeln!(out, "signing_key_id = {key_id}");
+    if let Some(hmac_key) = hmac_key {
+        let _ = writeln!(out, "hmac_key = \"{hmac_key}\"");
+        let _ = writeln!(out, "hmac_key_id = {key_id}");
     }
+    let _ = writeln!(
+        out,
+        "\n3. After bartos has restarted, remove the old key(s) from every bartoc.toml."
+    );
+    out
 }
 
 #[cfg_attr(coverage_nightly, coverage(off))]
@@ -122,3 +178,29 @@ fn delete(key: &str) -> Result<()> {
     println!("{key} deleted.");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use libbarto::{SigningKey, public_key_b64, signing_key_b64};
+
+    use super::rotation_instructions;
+
+    #[test]
+    fn rotation_instructions_signing_key_only() {
+        let sk = SigningKey::from_bytes(&[5; 32]);
+        let out = rotation_instructions(2, &sk, None);
+        assert!(out.contains("[[server_public_keys]]\nid = 2\n"));
+        assert!(out.contains(&format!("key = \"{}\"", public_key_b64(&sk))));
+        assert!(out.contains(&format!("signing_key = \"{}\"", signing_key_b64(&sk))));
+        assert!(out.contains("signing_key_id = 2"));
+        assert!(!out.contains("hmac_key"));
+    }
+
+    #[test]
+    fn rotation_instructions_with_hmac_key() {
+        let sk = SigningKey::from_bytes(&[5; 32]);
+        let out = rotation_instructions(3, &sk, Some("new-hmac"));
+        assert!(out.contains("[[hmac_keys]]\nid = 3\nkey = \"new-hmac\""));
+        assert!(out.contains("hmac_key = \"new-hmac\"\nhmac_key_id = 3"));
+    }
+}
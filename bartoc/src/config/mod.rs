@@ -12,7 +12,9 @@ use anyhow::{Context, Result};
 use config::Source;
 use dirs2::data_dir;
 use getset::{CopyGetters, Getters, Setters};
-use libbarto::{Bartos, MissedTick, PathDefaults, Tracing, TracingConfigExt, load, to_path_buf};
+use libbarto::{
+    Bartos, KeyWithId, MissedTick, PathDefaults, Tracing, TracingConfigExt, load, to_path_buf,
+};
 use serde::{Deserialize, Serialize};
 use tracing::Level;
 use tracing_subscriber_init::{TracingConfig, get_effective_level};
@@ -55,6 +57,12 @@ pub(crate) struct Config {
     #[getset(get = "pub(crate)")]
     #[serde(default)]
     server_public_key: Option<String>,
+    /// Accepted Ed25519 public keys of the bartos server, identified by key ID.
+    /// Verifies messages signed with a `signing_key_id`; list the old and the new key while a
+    /// rotation is in progress. `server_public_key` is still tried for messages without a key ID.
+    #[getset(get = "pub(crate)")]
+    #[serde(default)]
+    server_public_keys: Vec<KeyWithId>,
     /// Optional shared secret for HMAC-SHA256 authentication of incoming messages from bartos.
     /// When set, messages must carry a valid HMAC-SHA256 envelope (timestamp + nonce + MAC).
     /// Messages outside the replay window or with replayed nonces are rejected.
@@ -62,11 +70,27 @@ pub(crate) struct Config {
     #[getset(get = "pub(crate)")]
     #[serde(default)]
     hmac_key: Option<String>,
+    /// Accepted HMAC-SHA256 secrets, identified by key ID.
+    /// Verifies envelopes made with an `hmac_key_id`; list the old and the new key while a
+    /// rotation is in progress. `hmac_key` is still tried for envelopes without a key ID.
+    #[getset(get = "pub(crate)")]
+    #[serde(default)]
+    hmac_keys: Vec<KeyWithId>,
     /// Replay protection window in seconds (default: 60).
     /// Messages with a timestamp outside this window of the current time are rejected.
     #[getset(get_copy = "pub(crate)")]
     #[serde(default)]
     replay_window_secs: Option<u64>,
+    /// Offer zstd compression of batched output records to bartos (default: false).
+    /// Only used when bartos accepts it on the WebSocket upgrade.
+    #[getset(get_copy = "pub(crate)")]
+    #[serde(default)]
+    compress_output: bool,
+    /// Send each run start, output line and status to bartos as soon as it is recorded rather
+    /// than on the next minutely flush, so `barto-cli tail` shows it live (default: false).
+    #[getset(get_copy = "pub(crate)", set = "pub(crate)")]
+    #[serde(default)]
+    stream_output: bool,
 }
 
 impl TracingConfig for Config {
@@ -7,4 +7,5 @@
 // modified, or distributed except according to those terms.
 
 pub(crate) mod output;
+pub(crate) mod run;
 pub(crate) mod status;
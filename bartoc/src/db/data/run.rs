@@ -0,0 +1,151 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt::{Display, Formatter};
+
+use bincode_next::{Decode, Encode};
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+use libbarto::{OffsetDataTimeWrapper, RunStart, TriggerKind, UuidWrapper};
+
+#[derive(
+    Builder, Clone, Copy, CopyGetters, Debug, Decode, Encode, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
+#[get_copy = "pub(crate)"]
+pub(crate) struct RunKey {
+    cmd_uuid: UuidWrapper,
+}
+
+impl Display for RunKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cmd_uuid.0)
+    }
+}
+
+impl From<&RunStart> for RunKey {
+    fn from(run_start: &RunStart) -> Self {
+        RunKey {
+            cmd_uuid: run_start.cmd_uuid(),
+        }
+    }
+}
+
+#[derive(
+    Builder,
+    Clone,
+    CopyGetters,
+    Debug,
+    Decode,
+    Encode,
+    Eq,
+    Getters,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+)]
+pub(crate) struct RunValue {
+    #[get_copy = "pub(crate)"]
+    bartoc_uuid: UuidWrapper,
+    #[get = "pub(crate)"]
+    bartoc_name: String,
+    #[get = "pub(crate)"]
+    schedule_name: String,
+    #[get = "pub(crate)"]
+    cmd: String,
+    #[get_copy = "pub(crate)"]
+    trigger: TriggerKind,
+    #[get_copy = "pub(crate)"]
+    attempt: u32,
+    #[get_copy = "pub(crate)"]
+    timestamp: OffsetDataTimeWrapper,
+}
+
+impl Display for RunValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} attempt {}: {}",
+            self.schedule_name, self.trigger, self.attempt, self.cmd
+        )
+    }
+}
+
+impl From<&RunStart> for RunValue {
+    fn from(run_start: &RunStart) -> Self {
+        RunValue {
+            bartoc_uuid: run_start.bartoc_uuid(),
+            bartoc_name: run_start.bartoc_name().clone(),
+            schedule_name: run_start.schedule_name().clone(),
+            cmd: run_start.cmd().clone(),
+            trigger: run_start.trigger(),
+            attempt: run_start.attempt(),
+            timestamp: run_start.timestamp(),
+        }
+    }
+}
+
+impl RunValue {
+    /// Rebuild the run start record stored under `key`
+    pub(crate) fn into_run_start(self, key: RunKey) -> RunStart {
+        RunStart::builder()
+            .cmd_uuid(key.cmd_uuid)
+            .bartoc_uuid(self.bartoc_uuid)
+            .bartoc_name(self.bartoc_name)
+            .schedule_name(self.schedule_name)
+            .cmd(self.cmd)
+            .trigger(self.trigger)
+            .attempt(self.attempt)
+            .timestamp(self.timestamp)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libbarto::{OffsetDataTimeWrapper, RunStart, UuidWrapper};
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    use super::{RunKey, RunValue};
+
+    fn make_run_start() -> RunStart {
+        RunStart::builder()
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name("test-bartoc".to_string())
+            .schedule_name("backup".to_string())
+            .cmd("restic backup".to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+            .build()
+    }
+
+    #[test]
+    fn run_key_display_is_uuid_string() {
+        let run_start = make_run_start();
+        let key = RunKey::from(&run_start);
+        assert_eq!(key.to_string(), run_start.cmd_uuid().0.to_string());
+    }
+
+    #[test]
+    fn run_value_round_trips() {
+        let run_start = make_run_start();
+        let key = RunKey::from(&run_start);
+        let value = RunValue::from(&run_start);
+        assert_eq!(value.into_run_start(key), run_start);
+    }
+
+    #[test]
+    fn run_value_display() {
+        let value = RunValue::from(&make_run_start());
+        assert_eq!(
+            value.to_string(),
+            "backup schedule attempt 1: restic backup"
+        );
+    }
+}
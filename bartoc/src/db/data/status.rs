@@ -35,6 +35,8 @@ impl From<&Status> for StatusKey {
     }
 }
 
+/// A status as stored in the `status` table before exit signals were recorded. An upgraded
+/// bartoc only reads these to flush what the previous version left behind.
 #[derive(
     Builder, Clone, CopyGetters, Debug, Decode, Encode, Eq, Hash, Ord, PartialEq, PartialOrd,
 )]
@@ -45,20 +47,68 @@ pub(crate) struct StatusValue {
     success: bool,
 }
 
-impl Display for StatusValue {
+impl StatusValue {
+    pub(crate) fn to_status(&self, key: &StatusKey) -> Status {
+        Status::builder()
+            .cmd_uuid(key.cmd_uuid())
+            .timestamp(self.timestamp)
+            .exit_code(self.exit_code)
+            .success(self.success)
+            .build()
+    }
+}
+
+#[derive(
+    Builder, Clone, CopyGetters, Debug, Decode, Encode, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
+#[get_copy = "pub(crate)"]
+pub(crate) struct ExitStatusValue {
+    timestamp: OffsetDataTimeWrapper,
+    exit_code: Option<i32>,
+    exit_signal: Option<i32>,
+    core_dumped: bool,
+    wait_status: Option<i32>,
+    success: bool,
+}
+
+impl ExitStatusValue {
+    pub(crate) fn to_status(&self, key: &StatusKey) -> Status {
+        Status::builder()
+            .cmd_uuid(key.cmd_uuid())
+            .timestamp(self.timestamp)
+            .exit_code(self.exit_code)
+            .maybe_exit_signal(self.exit_signal)
+            .core_dumped(self.core_dumped)
+            .maybe_wait_status(self.wait_status)
+            .success(self.success)
+            .build()
+    }
+}
+
+impl Display for ExitStatusValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self.exit_code {
-            Some(code) => write!(f, "exit code: {code}, success: {}", self.success),
-            None => write!(f, "exit code: None, success: {}", self.success),
+            Some(code) => write!(f, "exit code: {code}")?,
+            None => write!(f, "exit code: None")?,
         }
+        if let Some(signal) = self.exit_signal {
+            write!(f, ", signal: {signal}")?;
+        }
+        if self.core_dumped {
+            write!(f, ", core dumped")?;
+        }
+        write!(f, ", success: {}", self.success)
     }
 }
 
-impl From<&Status> for StatusValue {
+impl From<&Status> for ExitStatusValue {
     fn from(status: &Status) -> Self {
-        StatusValue {
+        ExitStatusValue {
             timestamp: status.timestamp(),
             exit_code: status.exit_code(),
+            exit_signal: status.exit_signal(),
+            core_dumped: status.core_dumped(),
+            wait_status: status.wait_status(),
             success: status.success(),
         }
     }
@@ -70,7 +120,7 @@ mod tests {
     use time::OffsetDateTime;
     use uuid::Uuid;
 
-    use super::{StatusKey, StatusValue};
+    use super::{ExitStatusValue, StatusKey, StatusValue};
 
     fn make_status(exit_code: Option<i32>, success: bool) -> Status {
         Status::builder()
@@ -98,7 +148,7 @@ mod tests {
     #[test]
     fn status_value_from_with_exit_code() {
         let status = make_status(Some(42), false);
-        let value = StatusValue::from(&status);
+        let value = ExitStatusValue::from(&status);
         assert_eq!(value.exit_code(), Some(42));
         assert!(!value.success());
     }
@@ -106,7 +156,7 @@ mod tests {
     #[test]
     fn status_value_from_success() {
         let status = make_status(Some(0), true);
-        let value = StatusValue::from(&status);
+        let value = ExitStatusValue::from(&status);
         assert_eq!(value.exit_code(), Some(0));
         assert!(value.success());
     }
@@ -114,7 +164,7 @@ mod tests {
     #[test]
     fn status_value_from_no_exit_code() {
         let status = make_status(None, false);
-        let value = StatusValue::from(&status);
+        let value = ExitStatusValue::from(&status);
         assert_eq!(value.exit_code(), None);
         assert!(!value.success());
     }
@@ -122,7 +172,7 @@ mod tests {
     #[test]
     fn status_value_display_with_code() {
         let status = make_status(Some(1), false);
-        let value = StatusValue::from(&status);
+        let value = ExitStatusValue::from(&status);
         let s = value.to_string();
         assert!(s.contains("exit code: 1"));
         assert!(s.contains("success: false"));
@@ -131,16 +181,46 @@ mod tests {
     #[test]
     fn status_value_display_no_code() {
         let status = make_status(None, true);
-        let value = StatusValue::from(&status);
+        let value = ExitStatusValue::from(&status);
         let s = value.to_string();
         assert!(s.contains("exit code: None"));
         assert!(s.contains("success: true"));
     }
 
+    #[test]
+    fn status_value_keeps_the_signal() {
+        let status = Status::builder()
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+            .exit_code(None)
+            .exit_signal(11)
+            .core_dumped(true)
+            .wait_status(139)
+            .success(false)
+            .build();
+        let value = ExitStatusValue::from(&status);
+        assert_eq!(value.to_status(&StatusKey::from(&status)), status);
+        assert_eq!(
+            value.to_string(),
+            "exit code: None, signal: 11, core dumped, success: false"
+        );
+    }
+
+    #[test]
+    fn legacy_status_value_has_no_signal() {
+        let status = make_status(Some(2), false);
+        let value = StatusValue::builder()
+            .timestamp(status.timestamp())
+            .exit_code(2)
+            .success(false)
+            .build();
+        assert_eq!(value.to_status(&StatusKey::from(&status)), status);
+    }
+
     #[test]
     fn status_value_preserves_timestamp() {
         let status = make_status(Some(0), true);
-        let value = StatusValue::from(&status);
+        let value = ExitStatusValue::from(&status);
         assert_eq!(value.timestamp(), status.timestamp());
     }
 }
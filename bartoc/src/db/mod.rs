@@ -6,35 +6,51 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::{path::PathBuf, time::Duration};
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
 
 use anyhow::Result;
-use libbarto::{Bincode, Data, Output, Status, midnight};
-use redb::{Database, ReadableTableMetadata, TableDefinition};
+use libbarto::{Bincode, Data, OffsetDataTimeWrapper, Output, TraceParent, midnight};
+use redb::{
+    Database, ReadableTable as _, ReadableTableMetadata, TableDefinition, TableHandle as _,
+};
 use tokio::{
     select,
     sync::mpsc::{UnboundedReceiver, UnboundedSender},
     time::interval,
 };
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info, trace};
+use tracing::{error, info, info_span, trace};
 
 use crate::{
     config::Config,
     db::data::{
         output::{OutputKey, OutputValue},
-        status::{StatusKey, StatusValue},
+        run::{RunKey, RunValue},
+        status::{ExitStatusValue, StatusKey, StatusValue},
     },
     error::Error,
     handler::BartocMessage,
 };
 
 pub(crate) mod data;
+pub(crate) mod nonce;
 
 const OUTPUT_TABLE: TableDefinition<'_, Bincode<OutputKey>, Bincode<OutputValue>> =
     TableDefinition::new("output");
-const STATUS_TABLE: TableDefinition<'_, Bincode<StatusKey>, Bincode<StatusValue>> =
+const STATUS_TABLE: TableDefinition<'_, Bincode<StatusKey>, Bincode<ExitStatusValue>> =
+    TableDefinition::new("exit_status");
+// Statuses buffered by a bartoc that predates exit signals, flushed once and then dropped.
+const LEGACY_STATUS_TABLE: TableDefinition<'_, Bincode<StatusKey>, Bincode<StatusValue>> =
     TableDefinition::new("status");
+const RUN_TABLE: TableDefinition<'_, Bincode<RunKey>, Bincode<RunValue>> =
+    TableDefinition::new("runs");
+// The trace context of each run whose spans are exported, with the time the run started,
+// kept until its status is flushed so every record of the run joins the same trace.
+const TRACE_TABLE: TableDefinition<
+    '_,
+    Bincode<RunKey>,
+    Bincode<(TraceParent, OffsetDataTimeWrapper)>,
+> = TableDefinition::new("traces");
 
 #[derive(Debug)]
 pub(crate) struct BartocDatabase {
@@ -42,6 +58,8 @@ pub(crate) struct BartocDatabase {
     db: Database,
     db_tx: UnboundedSender<BartocMessage>,
     redb_path: PathBuf,
+    /// Flush each record as soon as it is written rather than only on the interval
+    stream_output: bool,
 }
 
 impl BartocDatabase {
@@ -68,6 +86,8 @@ impl BartocDatabase {
                 }
                 rx_opt = data_rx.recv() => {
                     if let Some(data) = rx_opt {
+                        // Output lines carry no trace, they are too many to get a span each
+                        let _entered = TraceParent::span_in(data.trace_parent(), || info_span!("buffer")).entered();
                         match data {
                             Data::Output(output) => {
                                 if let Err(e) = self.write_output(&OutputKey::from(&output), &OutputValue::from(&output)) {
@@ -75,21 +95,27 @@ impl BartocDatabase {
                                 }
                             }
                             Data::Status(status) => {
-                                if let Err(e) = self.write_status(&StatusKey::from(&status), &StatusValue::from(&status)) {
+                                if let Err(e) = self.write_status(&StatusKey::from(&status), &ExitStatusValue::from(&status)) {
                                     error!("unable to write status to database: {e}");
                                 }
                             }
+                            Data::Started(run_start) => {
+                                if let Err(e) = self.write_run(&RunKey::from(&run_start), &RunValue::from(&run_start)) {
+                                    error!("unable to write run start to database: {e}");
+                                }
+                                if let Some(trace_parent) = run_start.trace_parent()
+                                    && let Err(e) = self.write_trace(&RunKey::from(&run_start), trace_parent, run_start.timestamp())
+                                {
+                                    error!("unable to write trace context to database: {e}");
+                                }
+                            }
+                        }
+                        if self.stream_output {
+                            self.flush();
                         }
                     }
                 },
-                _val = interval.tick() => {
-                    if let Err(e) = self.flush_output() {
-                        error!("unable to flush output table: {e}");
-                    }
-                    if let Err(e) = self.flush_status() {
-                        error!("unable to flush status table: {e}");
-                    }
-                }
+                _val = interval.tick() => self.flush(),
             }
         }
         Ok(())
@@ -108,9 +134,22 @@ impl BartocDatabase {
             db,
             db_tx,
             redb_path: redb_path.clone(),
+            stream_output: config.stream_output(),
         })
     }
 
+    fn flush(&mut self) {
+        if let Err(e) = self.flush_runs() {
+            error!("unable to flush runs table: {e}");
+        }
+        if let Err(e) = self.flush_output() {
+            error!("unable to flush output table: {e}");
+        }
+        if let Err(e) = self.flush_status() {
+            error!("unable to flush status table: {e}");
+        }
+    }
+
     fn write_output(&mut self, key: &OutputKey, value: &OutputValue) -> Result<()> {
         let write_txn = self.db.begin_write()?;
         {
@@ -121,7 +160,7 @@ impl BartocDatabase {
         Ok(())
     }
 
-    fn write_status(&mut self, key: &StatusKey, value: &StatusValue) -> Result<()> {
+    fn write_status(&mut self, key: &StatusKey, value: &ExitStatusValue) -> Result<()> {
         let write_txn = self.db.begin_write()?;
         {
             let mut table = write_txn.open_table(STATUS_TABLE)?;
@@ -131,11 +170,66 @@ impl BartocDatabase {
         Ok(())
     }
 
+    fn write_run(&mut self, key: &RunKey, value: &RunValue) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(RUN_TABLE)?;
+            let _old = table.insert(key, value)?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    fn write_trace(
+        &mut self,
+        key: &RunKey,
+        trace_parent: TraceParent,
+        timestamp: OffsetDataTimeWrapper,
+    ) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(TRACE_TABLE)?;
+            let _old = table.insert(key, (trace_parent, timestamp))?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    fn flush_runs(&mut self) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        trace!("Flushing run starts to bartos");
+        {
+            let mut table = write_txn.open_table(RUN_TABLE)?;
+            let traces = write_txn.open_table(TRACE_TABLE)?;
+            loop {
+                match table.pop_first() {
+                    Ok(Some((key, value))) => {
+                        let mut run_start = value.value().into_run_start(key.value());
+                        let trace_parent = traces.get(key.value())?.map(|trace| trace.value().0);
+                        let _ = run_start.set_trace_parent(trace_parent);
+                        self.db_tx
+                            .send(BartocMessage::RecordData(Data::Started(run_start)))?;
+                        trace!("Flushed run start record: {}", key.value());
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        return Err(e.into());
+                    }
+                }
+            }
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
     fn flush_output(&mut self) -> Result<()> {
         let write_txn = self.db.begin_write()?;
         trace!("Flushing output to bartos");
+        let mut outputs = vec![];
+        let mut trace_parents = BTreeMap::new();
         {
             let mut table = write_txn.open_table(OUTPUT_TABLE)?;
+            let traces = write_txn.open_table(TRACE_TABLE)?;
             loop {
                 match table.pop_first() {
                     Ok(Some((key, value))) => {
@@ -148,9 +242,14 @@ impl BartocDatabase {
                             .kind(value.value().kind())
                             .data(value.value().data().clone())
                             .build();
-                        self.db_tx
-                            .send(BartocMessage::RecordData(Data::Output(output)))?;
                         trace!("Flushed output record: {}", key.value());
+                        if !trace_parents.contains_key(&output.cmd_uuid())
+                            && let Some(trace) =
+                                traces.get(RunKey::builder().cmd_uuid(output.cmd_uuid()).build())?
+                        {
+                            let _old = trace_parents.insert(output.cmd_uuid(), trace.value().0);
+                        }
+                        outputs.push(output);
                     }
                     Ok(None) => break,
                     Err(e) => {
@@ -159,6 +258,10 @@ impl BartocDatabase {
                 }
             }
         }
+        if !outputs.is_empty() {
+            self.db_tx
+                .send(BartocMessage::RecordOutputs(outputs, trace_parents))?;
+        }
         write_txn.commit()?;
         Ok(())
     }
@@ -166,17 +269,31 @@ impl BartocDatabase {
     fn flush_status(&mut self) -> Result<()> {
         let write_txn = self.db.begin_write()?;
         trace!("Flushing status to bartos");
+        if write_txn
+            .list_tables()?
+            .any(|table| table.name() == LEGACY_STATUS_TABLE.name())
+        {
+            {
+                let mut table = write_txn.open_table(LEGACY_STATUS_TABLE)?;
+                while let Some((key, value)) = table.pop_first()? {
+                    let status = value.value().to_status(&key.value());
+                    self.db_tx
+                        .send(BartocMessage::RecordData(Data::Status(status)))?;
+                    trace!("Flushed legacy status record: {}", key.value());
+                }
+            }
+            let _deleted = write_txn.delete_table(LEGACY_STATUS_TABLE)?;
+        }
         {
             let mut table = write_txn.open_table(STATUS_TABLE)?;
+            let mut traces = write_txn.open_table(TRACE_TABLE)?;
             loop {
                 match table.pop_first() {
                     Ok(Some((key, value))) => {
-                        let status = Status::builder()
-                            .cmd_uuid(key.value().cmd_uuid())
-                            .timestamp(value.value().timestamp())
-                            .exit_code(value.value().exit_code())
-                            .success(value.value().success())
-                            .build();
+                        let mut status = value.value().to_status(&key.value());
+                        let run_key = RunKey::builder().cmd_uuid(status.cmd_uuid()).build();
+                        let trace_parent = traces.remove(run_key)?.map(|trace| trace.value().0);
+                        let _ = status.set_trace_parent(trace_parent);
                         self.db_tx
                             .send(BartocMessage::RecordData(Data::Status(status)))?;
                         trace!("Flushed status record: {}", key.value());
@@ -192,9 +309,10 @@ impl BartocDatabase {
         Ok(())
     }
 
-    /// Delete entries from both redb tables whose timestamp is older than today's midnight,
+    /// Delete entries from the redb tables whose timestamp is older than today's midnight,
     /// mirroring the date-based cleanup `bartos` performs on its `MariaDB` tables. The `output`
-    /// table is keyed by timestamp, while the `status` table keeps its timestamp in the value.
+    /// table is keyed by timestamp, while the `status` and `runs` tables keep their timestamp in
+    /// the value.
     fn cleanup_redb(&mut self) -> Result<(u64, u64)> {
         let cutoff = midnight()?;
         info!("cleaning up redb records older than: {cutoff}");
@@ -209,6 +327,12 @@ impl BartocDatabase {
             let before = status_table.len()?;
             status_table.retain(|_key, value| value.timestamp().0 >= cutoff)?;
             let status_deleted = before - status_table.len()?;
+
+            let mut run_table = write_txn.open_table(RUN_TABLE)?;
+            run_table.retain(|_key, value| value.timestamp().0 >= cutoff)?;
+
+            let mut trace_table = write_txn.open_table(TRACE_TABLE)?;
+            trace_table.retain(|_key, (_trace_parent, timestamp)| timestamp.0 >= cutoff)?;
             (output_deleted, status_deleted)
         };
         write_txn.commit()?;
@@ -235,21 +359,28 @@ impl BartocDatabase {
 
 #[cfg(test)]
 mod tests {
-    use libbarto::{OffsetDataTimeWrapper, Output, OutputKind, Status, UuidWrapper};
+    use std::time::Duration;
+
+    use libbarto::{
+        Data, OffsetDataTimeWrapper, Output, OutputKind, RunStart, Status, TraceParent, UuidWrapper,
+    };
+    use redb::TableHandle as _;
     use time::OffsetDateTime;
-    use tokio::sync::mpsc::unbounded_channel;
+    use tokio::{sync::mpsc::unbounded_channel, time::timeout};
+    use tokio_util::sync::CancellationToken;
     use uuid::Uuid;
 
     use crate::{
         config::Config,
         db::data::{
             output::{OutputKey, OutputValue},
-            status::{StatusKey, StatusValue},
+            run::{RunKey, RunValue},
+            status::{ExitStatusValue, StatusKey, StatusValue},
         },
         handler::BartocMessage,
     };
 
-    use super::BartocDatabase;
+    use super::{BartocDatabase, LEGACY_STATUS_TABLE};
 
     fn make_db() -> (
         BartocDatabase,
@@ -263,6 +394,38 @@ mod tests {
         (db, rx)
     }
 
+    #[tokio::test]
+    async fn stream_output_flushes_each_record() {
+        let path = std::env::temp_dir().join(format!("{}.redb", Uuid::new_v4()));
+        let (db_tx, mut rx) = unbounded_channel::<BartocMessage>();
+        let mut config = Config::default();
+        let _ = config.set_redb_path(Some(path)).set_stream_output(true);
+        let mut db = BartocDatabase::new(&config, db_tx).expect("BartocDatabase::new");
+        let (data_tx, data_rx) = unbounded_channel();
+        let (_cleanup_tx, cleanup_rx) = unbounded_channel();
+        let token = CancellationToken::new();
+        let monitor_token = token.clone();
+        let monitor =
+            tokio::spawn(async move { db.monitor(data_rx, cleanup_rx, monitor_token).await });
+
+        // The first record may go out with the immediate first tick, the second can not
+        for _ in 0..2 {
+            let run_start = make_run_start();
+            data_tx
+                .send(Data::Started(run_start.clone()))
+                .expect("send run start");
+            let msg = timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("record flushed before the next tick")
+                .expect("message from flush");
+            assert!(
+                matches!(msg, BartocMessage::RecordData(Data::Started(flushed)) if flushed == run_start)
+            );
+        }
+        token.cancel();
+        monitor.await.expect("monitor task").expect("monitor");
+    }
+
     fn make_output_kv() -> (OutputKey, OutputValue) {
         let output = Output::builder()
             .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
@@ -276,14 +439,25 @@ mod tests {
         (OutputKey::from(&output), OutputValue::from(&output))
     }
 
-    fn make_status_kv() -> (StatusKey, StatusValue) {
+    fn make_run_start() -> RunStart {
+        RunStart::builder()
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name("test-bartoc".to_string())
+            .schedule_name("test-schedule".to_string())
+            .cmd("echo test".to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+            .build()
+    }
+
+    fn make_status_kv() -> (StatusKey, ExitStatusValue) {
         let status = Status::builder()
             .cmd_uuid(UuidWrapper(Uuid::new_v4()))
             .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
             .exit_code(Some(0))
             .success(true)
             .build();
-        (StatusKey::from(&status), StatusValue::from(&status))
+        (StatusKey::from(&status), ExitStatusValue::from(&status))
     }
 
     #[test]
@@ -312,7 +486,60 @@ mod tests {
         db.write_output(&key, &value).expect("write_output");
         db.flush_output().expect("flush_output");
         let msg = rx.try_recv().expect("message from flush");
-        assert!(matches!(msg, BartocMessage::RecordData(_)));
+        assert!(matches!(msg, BartocMessage::RecordOutputs(_, _)));
+    }
+
+    #[test]
+    fn flush_runs_sends_to_channel() {
+        let (mut db, mut rx) = make_db();
+        let run_start = make_run_start();
+        db.write_run(&RunKey::from(&run_start), &RunValue::from(&run_start))
+            .expect("write_run");
+        db.flush_runs().expect("flush_runs");
+        let msg = rx.try_recv().expect("message from flush");
+        assert!(
+            matches!(msg, BartocMessage::RecordData(Data::Started(flushed)) if flushed == run_start)
+        );
+        db.flush_runs().expect("second flush (empty)");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn flush_carries_the_trace_of_the_run() {
+        let (mut db, mut rx) = make_db();
+        let trace_parent =
+            TraceParent::try_from("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .expect("trace parent");
+        let run_start = make_run_start();
+        let key = RunKey::from(&run_start);
+        db.write_run(&key, &RunValue::from(&run_start))
+            .expect("write_run");
+        db.write_trace(&key, trace_parent, run_start.timestamp())
+            .expect("write_trace");
+        let status = Status::builder()
+            .cmd_uuid(run_start.cmd_uuid())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+            .exit_code(Some(0))
+            .success(true)
+            .build();
+        db.write_status(&StatusKey::from(&status), &ExitStatusValue::from(&status))
+            .expect("write_status");
+
+        db.flush_runs().expect("flush_runs");
+        db.flush_status().expect("flush_status");
+        for _ in 0..2 {
+            let msg = rx.try_recv().expect("message from flush");
+            assert!(
+                matches!(msg, BartocMessage::RecordData(data) if data.trace_parent() == Some(trace_parent))
+            );
+        }
+
+        // The trace is forgotten once the status of the run is flushed
+        db.write_status(&StatusKey::from(&status), &ExitStatusValue::from(&status))
+            .expect("write_status");
+        db.flush_status().expect("flush_status");
+        let msg = rx.try_recv().expect("message from flush");
+        assert!(matches!(msg, BartocMessage::RecordData(data) if data.trace_parent().is_none()));
     }
 
     #[test]
@@ -325,14 +552,50 @@ mod tests {
         assert!(matches!(msg, BartocMessage::RecordData(_)));
     }
 
+    #[test]
+    fn flush_status_drains_the_legacy_table() {
+        let (mut db, mut rx) = make_db();
+        let (key, _) = make_status_kv();
+        let legacy = StatusValue::builder()
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+            .exit_code(3)
+            .success(false)
+            .build();
+        let write_txn = db.db.begin_write().expect("begin_write");
+        {
+            let mut table = write_txn
+                .open_table(LEGACY_STATUS_TABLE)
+                .expect("legacy table");
+            let _old = table.insert(&key, &legacy).expect("insert");
+        }
+        write_txn.commit().expect("commit");
+
+        db.flush_status().expect("flush_status");
+        let msg = rx.try_recv().expect("message from flush");
+        assert!(matches!(
+            msg,
+            BartocMessage::RecordData(Data::Status(status))
+                if status.exit_code() == Some(3) && status.exit_signal().is_none()
+        ));
+        let write_txn = db.db.begin_write().expect("begin_write");
+        assert!(
+            !write_txn
+                .list_tables()
+                .expect("list_tables")
+                .any(|table| table.name() == LEGACY_STATUS_TABLE.name())
+        );
+    }
+
     #[test]
     fn flush_output_empties_table() {
-        let (mut db, _rx) = make_db();
+        let (mut db, mut rx) = make_db();
         let (key, value) = make_output_kv();
         db.write_output(&key, &value).expect("write_output");
         db.flush_output().expect("first flush");
+        let _first = rx.try_recv().expect("message from first flush");
         // A second flush on an empty table sends nothing and succeeds.
         db.flush_output().expect("second flush (empty)");
+        assert!(rx.try_recv().is_err());
     }
 
     #[test]
@@ -359,10 +622,9 @@ mod tests {
             db.write_output(&key, &value).expect("write_output");
         }
         db.flush_output().expect("flush_output");
-        let mut count = 0_u8;
-        while rx.try_recv().is_ok() {
-            count += 1;
-        }
-        assert_eq!(count, 3);
+        // All pending records are flushed together so the handler can batch them.
+        let msg = rx.try_recv().expect("message from flush");
+        assert!(matches!(msg, BartocMessage::RecordOutputs(outputs, _) if outputs.len() == 3));
+        assert!(rx.try_recv().is_err());
     }
 }
@@ -0,0 +1,136 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use redb::{Database, ReadableTable, TableDefinition};
+use tracing::trace;
+
+use crate::{config::Config, error::Error};
+
+const NONCE_TABLE: TableDefinition<'_, u64, u64> = TableDefinition::new("nonces");
+
+/// Persistent cache of the HMAC envelope nonces seen within the replay window, keyed by
+/// nonce → message timestamp.
+///
+/// The cache lives in its own redb file next to the output database, so it survives a bartoc
+/// restart without contending with the compaction of the main database.
+#[derive(Clone, Debug)]
+pub(crate) struct NonceStore {
+    db: Arc<Database>,
+}
+
+impl NonceStore {
+    pub(crate) fn new(config: &Config) -> Result<Self> {
+        let redb_path = config.redb_path().as_ref().ok_or(Error::NoRedbPath)?;
+        let path = nonce_store_path(redb_path);
+        trace!("Using nonce database path: {}", path.display());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let db = Database::create(path)?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// Returns `true` if the nonce is fresh, recording it for future checks, or `false` if it
+    /// has already been seen. Nonces whose timestamps have left the replay window are pruned
+    /// in the same transaction.
+    pub(crate) fn check_and_record(
+        &self,
+        nonce: u64,
+        timestamp: u64,
+        now: u64,
+        window: u64,
+    ) -> Result<bool> {
+        let write_txn = self.db.begin_write()?;
+        let fresh = {
+            let mut table = write_txn.open_table(NONCE_TABLE)?;
+            table.retain(|_nonce, ts| now.abs_diff(ts) <= window)?;
+            if table.get(nonce)?.is_some() {
+                false
+            } else {
+                let _old = table.insert(nonce, timestamp)?;
+                true
+            }
+        };
+        write_txn.commit()?;
+        Ok(fresh)
+    }
+}
+
+/// The nonce database sits beside the output database, e.g. `bartoc/db` → `bartoc/db.nonces`.
+fn nonce_store_path(redb_path: &Path) -> PathBuf {
+    let mut file_name = redb_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".nonces");
+    redb_path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use uuid::Uuid;
+
+    use crate::config::Config;
+
+    use super::{NonceStore, nonce_store_path};
+
+    fn make_store() -> (NonceStore, PathBuf) {
+        let path = std::env::temp_dir().join(format!("{}.redb", Uuid::new_v4()));
+        let mut config = Config::default();
+        let _ = config.set_redb_path(Some(path.clone()));
+        (NonceStore::new(&config).expect("NonceStore::new"), path)
+    }
+
+    #[test]
+    fn nonce_store_path_is_sibling() {
+        assert_eq!(
+            nonce_store_path(Path::new("bartoc/db")),
+            PathBuf::from("bartoc/db.nonces")
+        );
+        assert_eq!(
+            nonce_store_path(Path::new("/var/lib/bartoc.redb")),
+            PathBuf::from("/var/lib/bartoc.redb.nonces")
+        );
+    }
+
+    #[test]
+    fn new_without_redb_path_errors() {
+        assert!(NonceStore::new(&Config::default()).is_err());
+    }
+
+    #[test]
+    fn replayed_nonce_is_rejected() {
+        let (store, _path) = make_store();
+        assert!(store.check_and_record(1, 1_000, 1_000, 60).unwrap());
+        assert!(!store.check_and_record(1, 1_000, 1_001, 60).unwrap());
+        assert!(store.check_and_record(2, 1_000, 1_001, 60).unwrap());
+    }
+
+    #[test]
+    fn expired_nonces_are_pruned() {
+        let (store, _path) = make_store();
+        assert!(store.check_and_record(1, 1_000, 1_000, 60).unwrap());
+        assert!(store.check_and_record(1, 2_000, 2_000, 60).unwrap());
+    }
+
+    #[test]
+    fn nonces_survive_reopen() {
+        let (store, path) = make_store();
+        assert!(store.check_and_record(7, 1_000, 1_000, 60).unwrap());
+        drop(store);
+        let mut config = Config::default();
+        let _ = config.set_redb_path(Some(path));
+        let store = NonceStore::new(&config).expect("NonceStore::new");
+        assert!(!store.check_and_record(7, 1_000, 1_010, 60).unwrap());
+    }
+}
@@ -10,9 +10,11 @@ pub(crate) mod stream;
 
 #[cfg(unix)]
 use std::env::var_os;
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt as _;
 use std::{
-    collections::HashMap,
-    process::Stdio,
+    collections::{BTreeMap, HashMap},
+    process::{ExitStatus, Stdio},
     sync::{
         Arc,
         atomic::{AtomicI64, Ordering},
@@ -25,8 +27,9 @@ use bincode_next::{Decode, Encode, config::standard, encode_to_vec};
 use bon::Builder;
 use futures_util::{SinkExt as _, stream::SplitSink};
 use libbarto::{
-    Bartoc, BartocInfo, BartocWs, BartosToBartoc, Data, MissedTick, OffsetDataTimeWrapper, Output,
-    OutputKind, Realtime, Status, UuidWrapper, parse_ts_ping, send_ts_ping,
+    Bartoc, BartocInfo, BartocWs, BartosToBartoc, Data, LinkEncoding, MissedTick,
+    OffsetDataTimeWrapper, Output, OutputBatch, OutputKind, Realtime, RunStart, Status,
+    TraceParent, UuidWrapper, compress, describe_exit, parse_ts_ping, send_ts_ping,
 };
 use time::OffsetDateTime;
 use tokio::{
@@ -44,7 +47,7 @@ use tokio_tungstenite::{
     tungstenite::{Message, protocol::CloseFrame},
 };
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info, trace};
+use tracing::{Instrument as _, Span, error, info, info_span, trace};
 use uuid::Uuid;
 
 use crate::error::Error;
@@ -53,6 +56,8 @@ use crate::error::Error;
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 /// How long before lack of client response causes a timeout
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Line data per output batch, keeping each frame under the 64 KiB frame limit on bartos
+const MAX_BATCH_DATA_LEN: usize = 32 * 1024;
 
 #[derive(Clone, Debug, Decode, Encode)]
 pub(crate) enum BartocMessage {
@@ -63,6 +68,7 @@ pub(crate) enum BartocMessage {
     BartosToBartoc(BartosToBartoc),
     Data(Data),
     RecordData(Data),
+    RecordOutputs(Vec<Output>, BTreeMap<UuidWrapper, TraceParent>),
     ClientInfo(BartocInfo),
 }
 
@@ -112,6 +118,9 @@ pub(crate) struct Handler {
     id: Option<UuidWrapper>,
     bartoc_name: String,
     missed_tick: Option<MissedTick>,
+    // the output record encodings bartos accepted for this connection
+    #[builder(default)]
+    encoding: LinkEncoding,
     // Unix-second of the most recently dispatched schedule tick. Shared across every
     // monitor task spawned by this handler so that a given wall-clock second can be
     // dispatched at most once, even when `Burst` replays a missed tick or a restarted
@@ -175,14 +184,28 @@ impl Handler {
                 Ok(())
             }
             BartocMessage::RecordData(data) => {
-                let bartoc_msg = Bartoc::Record(data.clone());
+                let span = TraceParent::span_in(data.trace_parent(), || info_span!("deliver"));
+                let mut data = data.clone();
+                // bartos joins the trace below the delivery
+                if let Some(trace_parent) = TraceParent::of(&span) {
+                    data.set_trace_parent(Some(trace_parent));
+                }
+                let bartoc_msg = Bartoc::Record(data);
                 let msg_bytes = encode_to_vec(&bartoc_msg, standard())?;
                 let msg = Message::Binary(msg_bytes.into());
-                if let Err(e) = self.send_message(msg).await {
+                if let Err(e) = self.send_message(msg).instrument(span).await {
                     error!("unable to send message to websocket: {e}");
                 }
                 Ok(())
             }
+            BartocMessage::RecordOutputs(outputs, trace_parents) => {
+                for msg_bytes in encode_outputs(outputs.clone(), trace_parents, self.encoding)? {
+                    if let Err(e) = self.send_message(Message::Binary(msg_bytes.into())).await {
+                        error!("unable to send message to websocket: {e}");
+                    }
+                }
+                Ok(())
+            }
             BartocMessage::ClientInfo(ci) => {
                 let bartoc_msg = Bartoc::ClientInfo(ci.clone());
                 let msg_bytes = encode_to_vec(&bartoc_msg, standard())?;
@@ -346,6 +369,9 @@ impl Handler {
                                     let name_c = name.clone();
                                     for cmd in cmds {
                                         let id = Uuid::new_v4();
+                                        // Each run is one trace, rooted at the trigger
+                                        let trigger = info_span!(parent: None, "schedule_trigger", schedule = %name, cmd_uuid = %id);
+                                        let run = info_span!(parent: &trigger, "run_cmd", cmd = %cmd);
                                         info!("running command: {name_c} ({id})");
                                         Self::run_cmd(
                                             id,
@@ -354,7 +380,7 @@ impl Handler {
                                             &name,
                                             &cmd,
                                             tx_c.clone()
-                                        ).await.unwrap_or_else(|e| error!("unable to run command: {e}"));
+                                        ).instrument(run).await.unwrap_or_else(|e| error!("unable to run command: {e}"));
                                     }
                                 }
                             });
@@ -377,7 +403,19 @@ impl Handler {
         tx: UnboundedSender<BartocMessage>,
     ) -> Result<()> {
         let mut cmd = Self::setup_cmd(cmd_str)?;
+        let trace_parent = TraceParent::of(&Span::current());
+        let started = OffsetDateTime::now_utc();
         let mut child = cmd.spawn()?;
+        let run_start = RunStart::builder()
+            .cmd_uuid(UuidWrapper(id))
+            .bartoc_uuid(bartoc_id)
+            .bartoc_name(bartoc_name.to_string())
+            .schedule_name(cmd_name.to_string())
+            .cmd(cmd_str.to_string())
+            .timestamp(OffsetDataTimeWrapper(started))
+            .maybe_trace_parent(trace_parent)
+            .build();
+        tx.send(BartocMessage::Data(Data::Started(run_start)))?;
         let stdout = child.stdout.take().ok_or(Error::StdoutHandle)?;
         let stderr = child.stderr.take().ok_or(Error::StderrHandle)?;
         let cmd_handle = spawn(async move { child.wait().await.map_err(Into::into) });
@@ -427,23 +465,18 @@ impl Handler {
             flatten(stderr_handle)
         ) {
             Ok((status, _stdout_res, _stderr_res)) => {
-                if let Some(code) = status.code() {
-                    if status.success() {
-                        info!("command {id} exited successfully with code: {code}");
-                    } else {
-                        error!("command {id} exited with failure code: {code}");
-                    }
-                } else if status.success() {
-                    info!("command {id} exited successfully without a code");
+                let mut status = exit_status(id, status);
+                let _ = status.set_trace_parent(trace_parent);
+                let exit = describe_exit(
+                    status.exit_code(),
+                    status.exit_signal(),
+                    status.core_dumped(),
+                );
+                if status.success() {
+                    info!("command {id} exited successfully: {exit}");
                 } else {
-                    error!("command {id} exited with failure without a code");
+                    error!("command {id} exited with failure: {exit}");
                 }
-                let status = Status::builder()
-                    .cmd_uuid(UuidWrapper(id))
-                    .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
-                    .exit_code(status.code())
-                    .success(status.success())
-                    .build();
                 tx.send(BartocMessage::Data(Data::Status(status)))?;
             }
             Err(e) => error!("command handling failed: {e}"),
@@ -477,6 +510,22 @@ impl Handler {
     }
 }
 
+/// The status record of a finished command. A command killed by a signal has no exit code, so
+/// on Unix the signal, whether it dumped core and the raw wait status are recorded as well.
+fn exit_status(id: Uuid, status: ExitStatus) -> Status {
+    let builder = Status::builder()
+        .cmd_uuid(UuidWrapper(id))
+        .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+        .exit_code(status.code())
+        .success(status.success());
+    #[cfg(unix)]
+    let builder = builder
+        .maybe_exit_signal(status.signal())
+        .core_dumped(status.core_dumped())
+        .wait_status(status.into_raw());
+    builder.build()
+}
+
 async fn flatten<T>(handle: JoinHandle<Result<T>>) -> Result<T> {
     match handle.await {
         Ok(Ok(result)) => Ok(result),
@@ -485,6 +534,40 @@ async fn flatten<T>(handle: JoinHandle<Result<T>>) -> Result<T> {
     }
 }
 
+/// Encode flushed output records as worker frames: per-command batches, optionally zstd
+/// compressed, when bartos accepted them, otherwise one `Record` frame per line. Batches
+/// carry the trace context of their run, lines sent one at a time carry none.
+fn encode_outputs(
+    outputs: Vec<Output>,
+    trace_parents: &BTreeMap<UuidWrapper, TraceParent>,
+    encoding: LinkEncoding,
+) -> Result<Vec<Vec<u8>>> {
+    if !encoding.batch {
+        return outputs
+            .into_iter()
+            .map(|output| {
+                Ok(encode_to_vec(
+                    Bartoc::Record(Data::Output(output)),
+                    standard(),
+                )?)
+            })
+            .collect();
+    }
+    OutputBatch::from_outputs(outputs, MAX_BATCH_DATA_LEN)
+        .into_iter()
+        .map(|mut batch| {
+            let _ = batch.set_trace_parent(trace_parents.get(&batch.cmd_uuid()).copied());
+            let msg_bytes = encode_to_vec(Bartoc::RecordBatch(batch), standard())?;
+            if encoding.zstd {
+                let compressed = Bartoc::Compressed(compress(&msg_bytes)?);
+                Ok(encode_to_vec(compressed, standard())?)
+            } else {
+                Ok(msg_bytes)
+            }
+        })
+        .collect()
+}
+
 /// Atomically claim a wall-clock second for dispatch.
 ///
 /// Returns `true` if `ts` was successfully claimed (the caller should dispatch), or `false`
@@ -501,9 +584,102 @@ fn claim_second(slot: &AtomicI64, ts: i64) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::atomic::AtomicI64;
+    use std::{collections::BTreeMap, sync::atomic::AtomicI64};
+
+    use bincode_next::{config::standard, decode_from_slice};
+    use libbarto::{
+        Bartoc, Data, LinkEncoding, OffsetDataTimeWrapper, Output, OutputKind, TraceParent,
+        UuidWrapper, decompress,
+    };
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    use super::{claim_second, encode_outputs};
+
+    #[cfg(unix)]
+    #[test]
+    fn exit_status_records_signals() {
+        use std::{os::unix::process::ExitStatusExt as _, process::ExitStatus};
+
+        use super::exit_status;
+
+        let id = Uuid::new_v4();
+        let exited = exit_status(id, ExitStatus::from_raw(3 << 8));
+        assert_eq!(exited.cmd_uuid(), UuidWrapper(id));
+        assert_eq!((exited.exit_code(), exited.exit_signal()), (Some(3), None));
+        assert_eq!(exited.wait_status(), Some(3 << 8));
+        assert!(!exited.success());
+
+        // SIGSEGV with the core dump flag set
+        let killed = exit_status(id, ExitStatus::from_raw(0x80 | 11));
+        assert_eq!((killed.exit_code(), killed.exit_signal()), (None, Some(11)));
+        assert!(killed.core_dumped());
+        assert_eq!(killed.wait_status(), Some(139));
+    }
+
+    fn outputs() -> Vec<Output> {
+        let cmd_uuid = UuidWrapper(Uuid::new_v4());
+        (0..3)
+            .map(|i| {
+                Output::builder()
+                    .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+                    .bartoc_name("bartoc".to_string())
+                    .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+                    .cmd_uuid(cmd_uuid)
+                    .cmd_name("cmd".to_string())
+                    .kind(OutputKind::Stdout)
+                    .data(format!("line {i}"))
+                    .build()
+            })
+            .collect()
+    }
+
+    fn decode(bytes: &[u8]) -> Bartoc {
+        decode_from_slice(bytes, standard()).unwrap().0
+    }
 
-    use super::claim_second;
+    #[test]
+    fn encode_outputs_unbatched_sends_one_record_per_line() {
+        let frames = encode_outputs(outputs(), &BTreeMap::new(), LinkEncoding::default()).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert!(matches!(
+            decode(&frames[0]),
+            Bartoc::Record(Data::Output(_))
+        ));
+    }
+
+    #[test]
+    fn encode_outputs_batched() {
+        let encoding = LinkEncoding {
+            batch: true,
+            zstd: false,
+        };
+        let outputs = outputs();
+        let trace_parent =
+            TraceParent::try_from("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .unwrap();
+        let trace_parents = BTreeMap::from([(outputs[0].cmd_uuid(), trace_parent)]);
+        let frames = encode_outputs(outputs, &trace_parents, encoding).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert!(
+            matches!(decode(&frames[0]), Bartoc::RecordBatch(batch) if batch.lines().len() == 3 && batch.trace_parent() == Some(trace_parent))
+        );
+    }
+
+    #[test]
+    fn encode_outputs_batched_and_compressed() {
+        let encoding = LinkEncoding {
+            batch: true,
+            zstd: true,
+        };
+        let frames = encode_outputs(outputs(), &BTreeMap::new(), encoding).unwrap();
+        assert_eq!(frames.len(), 1);
+        let Bartoc::Compressed(compressed) = decode(&frames[0]) else {
+            panic!("expected a compressed frame");
+        };
+        let inner = decompress(&compressed).unwrap();
+        assert!(matches!(decode(&inner), Bartoc::RecordBatch(batch) if batch.lines().len() == 3));
+    }
 
     #[test]
     fn first_claim_for_a_second_succeeds() {
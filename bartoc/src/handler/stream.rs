@@ -7,7 +7,7 @@
 // modified, or distributed except according to those terms.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -17,13 +17,17 @@ use bincode_next::{
     decode_from_slice,
 };
 use bon::Builder;
-use libbarto::{BartosToBartoc, VerifyingKey, hmac_verify_and_extract, verify_and_extract};
+use libbarto::{
+    BartosToBartoc, VerifyingKey, hmac_verify_and_extract, hmac_verify_and_extract_with_id,
+    sequence_unwrap, verify_and_extract, verify_and_extract_with_id,
+};
 use tokio::sync::mpsc::UnboundedSender;
 use tokio_tungstenite::tungstenite::{Error, Message};
 use tokio_util::sync::CancellationToken;
 use tracing::{error, trace, warn};
+use uuid::Uuid;
 
-use crate::handler::BartocMessage;
+use crate::{db::nonce::NonceStore, handler::BartocMessage};
 
 const DEFAULT_REPLAY_WINDOW_SECS: u64 = 60;
 
@@ -34,16 +38,34 @@ pub(crate) struct WsHandler {
     /// Optional Ed25519 verifying key — when set, incoming binary messages must carry a valid
     /// 64-byte signature prefix. Messages that fail verification are dropped and logged.
     verifying_key: Option<VerifyingKey>,
+    /// Ed25519 verifying keys by key ID — when non-empty, messages may instead carry a keyed
+    /// signature prefix naming one of these keys.
+    #[builder(default)]
+    verifying_keys: BTreeMap<u32, VerifyingKey>,
     /// Optional HMAC-SHA256 key — when set, the payload (after any Ed25519 unwrap) must carry
     /// a valid authenticated envelope. Messages with bad MACs, expired timestamps, or replayed
     /// nonces are dropped and logged.
     hmac_key: Option<Vec<u8>>,
+    /// HMAC-SHA256 keys by key ID — when non-empty, envelopes may instead carry a key ID naming
+    /// one of these keys.
+    #[builder(default)]
+    hmac_keys: BTreeMap<u32, Vec<u8>>,
     /// Replay window in seconds. Defaults to 60.
     #[builder(default = DEFAULT_REPLAY_WINDOW_SECS)]
     replay_window_secs: u64,
     /// Seen nonces within the current replay window, keyed by nonce → message timestamp.
+    /// Only used when no persistent `nonce_store` is configured.
     #[builder(default)]
     seen_nonces: HashMap<u64, u64>,
+    /// Optional persistent nonce cache — when set, replayed nonces are rejected across
+    /// bartoc restarts as well.
+    nonce_store: Option<NonceStore>,
+    /// The session id bartos echoed on the WebSocket upgrade — when set, every message must
+    /// carry a sequence header for this session with a strictly increasing counter.
+    session: Option<Uuid>,
+    /// The last message counter accepted on this connection.
+    #[builder(skip)]
+    last_counter: u64,
 }
 
 impl WsHandler {
@@ -107,29 +129,31 @@ impl WsHandler {
     /// Verifies, authenticates, decodes, and forwards an inbound binary message.
     ///
     /// Peels the Ed25519 signature (layer 5) and HMAC/replay envelope (layer 4) when those keys
-    /// are configured, then decodes the `BartosToBartoc` payload and forwards it to the handler.
+    /// are configured, then the sequence header when bartos agreed to sequence the connection,
+    /// then decodes the `BartosToBartoc` payload and forwards it to the handler.
     /// Messages that fail any layer are dropped and logged.
     fn handle_binary(&mut self, bytes: &[u8]) {
         // Layer 5: Ed25519 verify (outermost layer).
-        let after_ed25519: Option<Vec<u8>> = if let Some(vk) = &self.verifying_key {
-            match verify_and_extract(vk, bytes) {
-                Ok(payload) => {
-                    trace!("binary message Ed25519 signature verified");
-                    Some(payload)
-                }
-                Err(e) => {
-                    warn!("message Ed25519 signature invalid, dropping: {e}");
-                    None
+        let after_ed25519: Option<Vec<u8>> =
+            if self.verifying_key.is_some() || !self.verifying_keys.is_empty() {
+                match self.verify_signature(bytes) {
+                    Ok(payload) => {
+                        trace!("binary message Ed25519 signature verified");
+                        Some(payload)
+                    }
+                    Err(e) => {
+                        warn!("message Ed25519 signature invalid, dropping: {e}");
+                        None
+                    }
                 }
-            }
-        } else {
-            Some(bytes.to_vec())
-        };
+            } else {
+                Some(bytes.to_vec())
+            };
 
         // Layer 4: HMAC-SHA256 verify and replay check.
         let decode_target: Option<Vec<u8>> = if let Some(payload) = after_ed25519 {
-            if let Some(hmac_key) = &self.hmac_key.clone() {
-                match hmac_verify_and_extract(hmac_key, &payload, self.replay_window_secs) {
+            if self.hmac_key.is_some() || !self.hmac_keys.is_empty() {
+                match self.verify_hmac(&payload) {
                     Ok((inner, ts, nonce)) => {
                         if self.check_and_record_nonce(nonce, ts) {
                             trace!("binary message HMAC verified");
@@ -151,6 +175,13 @@ impl WsHandler {
             None
         };
 
+        // Per-connection sequence check.
+        let decode_target = match (decode_target, self.session) {
+            (Some(payload), Some(session)) => self.check_sequence(session, &payload),
+            (decode_target, None) => decode_target,
+            (None, Some(_)) => None,
+        };
+
         if let Some(payload) = decode_target {
             if let Ok((btb, _)) =
                 decode_from_slice::<BartosToBartoc, Configuration>(&payload, standard())
@@ -166,8 +197,69 @@ impl WsHandler {
         }
     }
 
+    /// Verifies the Ed25519 signature prefix, trying the keyed format first when key IDs are
+    /// configured and falling back to the single `verifying_key`, so both formats are accepted
+    /// while a fleet moves to key IDs.
+    fn verify_signature(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        if !self.verifying_keys.is_empty() {
+            match verify_and_extract_with_id(&self.verifying_keys, bytes) {
+                Ok(payload) => return Ok(payload),
+                Err(e) if self.verifying_key.is_none() => return Err(e),
+                Err(_) => {}
+            }
+        }
+        match &self.verifying_key {
+            Some(vk) => verify_and_extract(vk, bytes),
+            None => Err(libbarto::Error::SignatureInvalid.into()),
+        }
+    }
+
+    /// Verifies the HMAC envelope, trying the keyed format first when key IDs are configured
+    /// and falling back to the single `hmac_key`. Returns `(payload, timestamp, nonce)`.
+    fn verify_hmac(&self, payload: &[u8]) -> Result<(Vec<u8>, u64, u64)> {
+        if !self.hmac_keys.is_empty() {
+            match hmac_verify_and_extract_with_id(&self.hmac_keys, payload, self.replay_window_secs)
+            {
+                Ok(verified) => return Ok(verified),
+                Err(e) if self.hmac_key.is_none() => return Err(e),
+                Err(_) => {}
+            }
+        }
+        match &self.hmac_key {
+            Some(hmac_key) => hmac_verify_and_extract(hmac_key, payload, self.replay_window_secs),
+            None => Err(libbarto::Error::HmacInvalid.into()),
+        }
+    }
+
+    /// Strips the sequence header, returning the payload if it belongs to this connection's
+    /// session and its counter is greater than any accepted so far.
+    fn check_sequence(&mut self, session: Uuid, payload: &[u8]) -> Option<Vec<u8>> {
+        match sequence_unwrap(payload) {
+            Ok((msg_session, counter, inner)) => {
+                if msg_session != session {
+                    warn!("message sequenced for another session, dropping");
+                    None
+                } else if counter <= self.last_counter {
+                    warn!(
+                        "message counter {counter} not after {}, dropping",
+                        self.last_counter
+                    );
+                    None
+                } else {
+                    self.last_counter = counter;
+                    Some(inner)
+                }
+            }
+            Err(e) => {
+                warn!("message sequence header invalid, dropping: {e}");
+                None
+            }
+        }
+    }
+
     /// Returns `true` if the nonce is fresh (not seen before), recording it for future checks.
-    /// Returns `false` if the nonce has already been seen (replay detected).
+    /// Returns `false` if the nonce has already been seen (replay detected), or if the
+    /// persistent nonce store cannot be read.
     /// Also prunes nonces whose timestamps have expired from the replay window.
     fn check_and_record_nonce(&mut self, nonce: u64, timestamp: u64) -> bool {
         let now = SystemTime::now()
@@ -175,6 +267,14 @@ impl WsHandler {
             .unwrap_or_default()
             .as_secs();
         let window = self.replay_window_secs;
+        if let Some(store) = &self.nonce_store {
+            return store
+                .check_and_record(nonce, timestamp, now, window)
+                .unwrap_or_else(|e| {
+                    error!("unable to check nonce store, dropping message: {e}");
+                    false
+                });
+        }
         self.seen_nonces
             .retain(|_, &mut ts| now.abs_diff(ts) <= window);
         if self.seen_nonces.contains_key(&nonce) {
@@ -187,12 +287,22 @@ impl WsHandler {
 
 #[cfg(test)]
 mod tests {
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::{
+        collections::BTreeMap,
+        time::{SystemTime, UNIX_EPOCH},
+    };
 
-    use tokio::sync::mpsc::unbounded_channel;
+    use bincode_next::{config::standard, encode_to_vec};
+    use libbarto::{
+        BartosToBartoc, SigningKey, hmac_sign, hmac_sign_with_id, parse_hmac_key, sequence_wrap,
+        sign_payload, sign_payload_with_id,
+    };
+    use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
     use tokio_util::sync::CancellationToken;
+    use uuid::Uuid;
 
     use super::WsHandler;
+    use crate::{config::Config, db::nonce::NonceStore, handler::BartocMessage};
 
     fn now_secs() -> u64 {
         SystemTime::now()
@@ -267,4 +377,147 @@ mod tests {
         assert!(handler.check_and_record_nonce(55, old_ts));
         assert!(handler.check_and_record_nonce(55, now_secs()));
     }
+
+    fn cleanup_bytes() -> Vec<u8> {
+        encode_to_vec(BartosToBartoc::Cleanup, standard()).unwrap()
+    }
+
+    fn keyed_handler(
+        verifying_key: Option<&SigningKey>,
+        hmac_key: Option<&str>,
+    ) -> (WsHandler, UnboundedReceiver<BartocMessage>) {
+        let (tx, rx) = unbounded_channel();
+        let handler = WsHandler::builder()
+            .tx(tx)
+            .token(CancellationToken::new())
+            .maybe_verifying_key(verifying_key.map(SigningKey::verifying_key))
+            .verifying_keys(BTreeMap::from([
+                (1, SigningKey::from_bytes(&[1; 32]).verifying_key()),
+                (2, SigningKey::from_bytes(&[2; 32]).verifying_key()),
+            ]))
+            .maybe_hmac_key(hmac_key.map(parse_hmac_key))
+            .hmac_keys(BTreeMap::from([
+                (1, parse_hmac_key("old-secret")),
+                (2, parse_hmac_key("new-secret")),
+            ]))
+            .build();
+        (handler, rx)
+    }
+
+    fn forwarded(rx: &mut UnboundedReceiver<BartocMessage>) -> bool {
+        matches!(
+            rx.try_recv(),
+            Ok(BartocMessage::BartosToBartoc(BartosToBartoc::Cleanup))
+        )
+    }
+
+    #[test]
+    fn keyed_message_with_accepted_key_ids_forwarded() {
+        let (mut handler, mut rx) = keyed_handler(None, None);
+        let enveloped = hmac_sign_with_id(&parse_hmac_key("old-secret"), 1, &cleanup_bytes());
+        let signed = sign_payload_with_id(&SigningKey::from_bytes(&[2; 32]), 2, &enveloped);
+        handler.handle_binary(&signed);
+        assert!(forwarded(&mut rx));
+    }
+
+    #[test]
+    fn keyed_message_with_unknown_key_id_dropped() {
+        let (mut handler, mut rx) = keyed_handler(None, None);
+        let enveloped = hmac_sign_with_id(&parse_hmac_key("new-secret"), 2, &cleanup_bytes());
+        let signed = sign_payload_with_id(&SigningKey::from_bytes(&[3; 32]), 3, &enveloped);
+        handler.handle_binary(&signed);
+        assert!(!forwarded(&mut rx));
+    }
+
+    #[test]
+    fn legacy_message_accepted_alongside_key_ids() {
+        let legacy = SigningKey::from_bytes(&[9; 32]);
+        let (mut handler, mut rx) = keyed_handler(Some(&legacy), Some("legacy-secret"));
+        let enveloped = hmac_sign(&parse_hmac_key("legacy-secret"), &cleanup_bytes());
+        handler.handle_binary(&sign_payload(&legacy, &enveloped));
+        assert!(forwarded(&mut rx));
+    }
+
+    #[test]
+    fn legacy_message_without_legacy_key_dropped() {
+        let legacy = SigningKey::from_bytes(&[9; 32]);
+        let (mut handler, mut rx) = keyed_handler(None, None);
+        let enveloped = hmac_sign(&parse_hmac_key("new-secret"), &cleanup_bytes());
+        handler.handle_binary(&sign_payload(&legacy, &enveloped));
+        assert!(!forwarded(&mut rx));
+    }
+
+    fn sequenced_handler(session: Uuid) -> (WsHandler, UnboundedReceiver<BartocMessage>) {
+        let (tx, rx) = unbounded_channel();
+        let handler = WsHandler::builder()
+            .tx(tx)
+            .token(CancellationToken::new())
+            .session(session)
+            .build();
+        (handler, rx)
+    }
+
+    #[test]
+    fn sequenced_messages_forwarded_in_order() {
+        let session = Uuid::new_v4();
+        let (mut handler, mut rx) = sequenced_handler(session);
+        handler.handle_binary(&sequence_wrap(&session, 1, &cleanup_bytes()));
+        assert!(forwarded(&mut rx));
+        handler.handle_binary(&sequence_wrap(&session, 3, &cleanup_bytes()));
+        assert!(forwarded(&mut rx));
+    }
+
+    #[test]
+    fn replayed_counter_dropped() {
+        let session = Uuid::new_v4();
+        let (mut handler, mut rx) = sequenced_handler(session);
+        let message = sequence_wrap(&session, 5, &cleanup_bytes());
+        handler.handle_binary(&message);
+        assert!(forwarded(&mut rx));
+        handler.handle_binary(&message);
+        assert!(!forwarded(&mut rx));
+        handler.handle_binary(&sequence_wrap(&session, 4, &cleanup_bytes()));
+        assert!(!forwarded(&mut rx));
+    }
+
+    #[test]
+    fn other_session_dropped() {
+        let (mut handler, mut rx) = sequenced_handler(Uuid::new_v4());
+        handler.handle_binary(&sequence_wrap(&Uuid::new_v4(), 1, &cleanup_bytes()));
+        assert!(!forwarded(&mut rx));
+    }
+
+    #[test]
+    fn unsequenced_message_dropped_when_sequenced() {
+        let (mut handler, mut rx) = sequenced_handler(Uuid::new_v4());
+        handler.handle_binary(&cleanup_bytes());
+        assert!(!forwarded(&mut rx));
+    }
+
+    #[test]
+    fn persistent_nonce_store_rejects_replay_after_restart() {
+        let mut config = Config::default();
+        let path = std::env::temp_dir().join(format!("{}.redb", Uuid::new_v4()));
+        let _ = config.set_redb_path(Some(path));
+        let store = NonceStore::new(&config).unwrap();
+        let message = hmac_sign(&parse_hmac_key("secret"), &cleanup_bytes());
+        let (tx, mut rx) = unbounded_channel();
+        let mut handler = WsHandler::builder()
+            .tx(tx.clone())
+            .token(CancellationToken::new())
+            .hmac_key(parse_hmac_key("secret"))
+            .nonce_store(store.clone())
+            .build();
+        handler.handle_binary(&message);
+        assert!(forwarded(&mut rx));
+        // A fresh handler, as after a restart, still sees the recorded nonce.
+        let mut handler = WsHandler::builder()
+            .tx(tx)
+            .token(CancellationToken::new())
+            .hmac_key(parse_hmac_key("secret"))
+            .nonce_store(store)
+            .build();
+        handler.handle_binary(&message);
+        assert!(!forwarded(&mut rx));
+    }
 }
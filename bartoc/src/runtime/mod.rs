@@ -9,6 +9,7 @@
 mod cli;
 
 use std::{
+    collections::BTreeMap,
     ffi::OsString,
     io::{Write, stdout},
     sync::{
@@ -22,7 +23,8 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use futures_util::{StreamExt, stream::SplitSink};
 use libbarto::{
-    Data, header, init_tracing, key_fingerprint, load_client_cert_and_key, load_pinned_root_store,
+    Data, ENCODING_HEADER, LinkEncoding, SESSION_HEADER, VerifyingKey, client_auth_headers, header,
+    init_tracing, key_fingerprint, load_client_cert_and_key, load_pinned_root_store, otlp_layer,
     parse_hmac_key, parse_verifying_key,
 };
 #[cfg(not(unix))]
@@ -39,17 +41,21 @@ use tokio::{
 use tokio_tungstenite::{
     Connector, MaybeTlsStream, WebSocketStream, connect_async_tls_with_config,
     tungstenite::{
-        Message, client::ClientRequestBuilder, http::Uri, protocol::frame::coding::CloseCode,
+        Message,
+        client::ClientRequestBuilder,
+        http::{HeaderMap, Uri},
+        protocol::frame::coding::CloseCode,
     },
 };
 use tokio_util::sync::CancellationToken;
 #[cfg(windows)]
 use tracing::warn;
 use tracing::{error, info, trace};
+use uuid::Uuid;
 
 use crate::{
     config::{Config, load_bartoc},
-    db::BartocDatabase,
+    db::{BartocDatabase, nonce::NonceStore},
     error::Error,
     handler::{BartocMessage, Handler, stream::WsHandler},
 };
@@ -125,8 +131,21 @@ where
     let config = load_bartoc::<Cli, Cli>(&cli, &cli).with_context(|| Error::ConfigLoad)?;
 
     // Initialize tracing
-    init_tracing(&config, config.tracing().file(), &cli, None)
-        .with_context(|| Error::TracingInit)?;
+    let (otlp_layer, _otlp_guard) = config
+        .tracing()
+        .otlp()
+        .as_ref()
+        .map(|otlp| otlp_layer(otlp, env!("CARGO_PKG_NAME")))
+        .transpose()
+        .with_context(|| Error::TracingInit)?
+        .unzip();
+    init_tracing(
+        &config,
+        config.tracing().file(),
+        &cli,
+        otlp_layer.map(|layer| vec![layer]),
+    )
+    .with_context(|| Error::TracingInit)?;
 
     trace!("configuration loaded");
     trace!("tracing initialized");
@@ -147,6 +166,12 @@ where
     let mut retry_count = *config.retry_count();
     let mut error_count = 0;
     let shutdown = Arc::new(AtomicBool::new(false));
+    // Opened once so the replay cache outlives individual connections.
+    let nonce_store = if config.hmac_key().is_some() || !config.hmac_keys().is_empty() {
+        Some(NonceStore::new(&config)?)
+    } else {
+        None
+    };
 
     while retry_count > 0 {
         if let Some(ref st) = service_token
@@ -158,6 +183,7 @@ where
         let sd_c = Arc::clone(&shutdown);
         let res = run_connection(
             &config,
+            nonce_store.as_ref(),
             sd_c,
             &mut retry_count,
             &mut error_count,
@@ -185,6 +211,7 @@ where
 
 async fn run_connection(
     config: &Config,
+    nonce_store: Option<&NonceStore>,
     sd_c: Arc<AtomicBool>,
     retry_count: &mut u8,
     error_count: &mut u32,
@@ -209,16 +236,32 @@ async fn run_connection(
     );
     trace!("connecting to bartos at {url}");
     let uri: Uri = url.parse()?;
-    let ws_req = if let Some(token) = config.bartos().api_key() {
-        trace!("adding Bearer auth header to WebSocket upgrade");
-        ClientRequestBuilder::new(uri).with_header("Authorization", format!("Bearer {token}"))
-    } else {
-        ClientRequestBuilder::new(uri)
-    };
-    let (ws_stream, _) =
+    let session = Uuid::new_v4();
+    let ws_req = client_auth_headers(config.bartos(), config.name())?
+        .into_iter()
+        .fold(ClientRequestBuilder::new(uri), |req, (header, value)| {
+            trace!("adding '{header}' auth header to WebSocket upgrade");
+            req.with_header(header, value)
+        })
+        .with_header(SESSION_HEADER, session.to_string())
+        .with_header(ENCODING_HEADER, offered_encoding(config));
+    let (ws_stream, response) =
         connect_async_tls_with_config(ws_req, None, false, Some(make_tls_connector(config)?))
             .await?;
     trace!("websocket connected");
+    let session = echoed_session(response.headers(), session);
+    let encoding = response
+        .headers()
+        .get(ENCODING_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(LinkEncoding::parse)
+        .unwrap_or_default();
+    trace!("output record encoding: {encoding:?}");
+    if session.is_some() {
+        trace!("bartos agreed to sequence messages for session {session:?}");
+    } else {
+        info!("bartos did not echo the session header — messages will not be sequenced");
+    }
     *retry_count = *config.retry_count(); // reset on successful connection
     *error_count = 0; // reset on successful connection
     trace!("retry and error counts reset");
@@ -229,6 +272,7 @@ async fn run_connection(
         data_tx.clone(),
         cleanup_tx,
         heartbeat_token,
+        encoding,
         config,
     )
     .await?;
@@ -238,21 +282,42 @@ async fn run_connection(
         .as_deref()
         .map(parse_verifying_key)
         .transpose()?;
+    let verifying_keys = config
+        .server_public_keys()
+        .iter()
+        .map(|entry| Ok((entry.id(), parse_verifying_key(entry.key())?)))
+        .collect::<Result<BTreeMap<u32, VerifyingKey>>>()?;
     if let Some(vk) = &verifying_key {
         info!(
             "Ed25519 verifying key loaded (fingerprint: {})",
             key_fingerprint(vk)
         );
-    } else {
+    }
+    for (key_id, vk) in &verifying_keys {
+        info!(
+            "Ed25519 verifying key {key_id} loaded (fingerprint: {})",
+            key_fingerprint(vk)
+        );
+    }
+    if verifying_key.is_none() && verifying_keys.is_empty() {
         info!("Ed25519 verifying key not configured — signatures will not be verified");
     }
     let hmac_key = config.hmac_key().as_deref().map(parse_hmac_key);
+    let hmac_keys = config
+        .hmac_keys()
+        .iter()
+        .map(|entry| (entry.id(), parse_hmac_key(entry.key())))
+        .collect::<BTreeMap<u32, Vec<u8>>>();
     let mut ws_handler = WsHandler::builder()
         .tx(tx.clone())
         .token(stream_token.clone())
         .maybe_verifying_key(verifying_key)
+        .verifying_keys(verifying_keys)
         .maybe_hmac_key(hmac_key)
+        .hmac_keys(hmac_keys)
         .maybe_replay_window_secs(config.replay_window_secs())
+        .maybe_nonce_store(nonce_store.cloned())
+        .maybe_session(session)
         .build();
     let sink_handle = spawn(async move {
         while let Some(msg) = rx.recv().await {
@@ -289,6 +354,26 @@ async fn run_connection(
     Ok(())
 }
 
+/// The output record encodings offered to bartos on the WebSocket upgrade.
+fn offered_encoding(config: &Config) -> String {
+    LinkEncoding {
+        batch: true,
+        zstd: config.compress_output(),
+    }
+    .header_value()
+    .unwrap_or_default()
+}
+
+/// Returns the session id if bartos echoed it on the upgrade response, meaning it will
+/// sequence the messages it sends on this connection.
+fn echoed_session(headers: &HeaderMap, session: Uuid) -> Option<Uuid> {
+    headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<Uuid>().ok())
+        .filter(|echoed| *echoed == session)
+}
+
 fn make_tls_connector(config: &Config) -> Result<Connector> {
     use rustls::{ClientConfig, RootCertStore};
     let root_store = if let Some(ca_cert_path) = config.bartos().ca_cert() {
@@ -330,6 +415,7 @@ async fn setup_handler(
     data_tx: UnboundedSender<Data>,
     cleanup_tx: UnboundedSender<()>,
     heartbeat_token: CancellationToken,
+    encoding: LinkEncoding,
     config: &Config,
 ) -> Result<Handler> {
     let mut handler = Handler::builder()
@@ -340,6 +426,7 @@ async fn setup_handler(
         .token(heartbeat_token)
         .bartoc_name(config.name().clone())
         .maybe_missed_tick(config.missed_tick())
+        .encoding(encoding)
         .build();
     handler.heartbeat(config.client_timeout());
     handler.bartoc_info().await?;
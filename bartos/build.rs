@@ -12,6 +12,8 @@ use vergen_gix::{Build, Cargo, Emitter, Gix, Rustc, Sysinfo};
 pub fn main() -> Result<()> {
     println!("cargo:rustc-check-cfg=cfg(coverage_nightly)");
     nightly();
+    // the migrations are embedded with `sqlx::migrate!`
+    println!("cargo:rerun-if-changed=../migrations");
     Emitter::default()
         .add_instructions(&Build::all_build())?
         .add_instructions(&Cargo::all_cargo())?
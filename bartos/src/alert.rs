@@ -0,0 +1,1061 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Alerts on failing runs and offline clients
+//!
+//! Every `Status` a bartoc client reports, every run the watchdog finds missing, every
+//! schedule it cannot watch and every client found offline is checked against the
+//! `[[alerts.rules]]`. The failure streak of each
+//! client schedule is kept in memory and is not rebuilt from the stored runs, so it starts
+//! over when `bartos` restarts. A rule alerts once when a streak reaches its
+//! `consecutive_failures`, not on every failure after that. Only the run a status belongs to
+//! is read before the rules are checked. The last lines of its output are read in the
+//! background, and only when an alert is raised, then the alerts are delivered. A target
+//! that cannot be reached is logged, never retried.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport as _, Message, Tokio1Executor,
+    message::{Mailbox, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+use libbarto::{ExportRow, Status};
+use serde::Serialize;
+use serde_json::json;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tokio::spawn;
+use tracing::{error, trace, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::{AlertRule, AlertTarget, Alerts, Smtp, SmtpTls},
+    db::{Queryable, Store},
+    presence::{Change, Offline},
+    watchdog::Unwatchable,
+};
+
+/// The schedule name of a run whose start was never recorded
+const UNKNOWN: &str = "unknown";
+
+/// Why an alert was sent
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Event {
+    /// A run failed
+    Failure,
+    /// A schedule was expected to fire but no run was reported
+    Missed,
+    /// A run succeeded after failures that were alerted on
+    Recovery,
+}
+
+/// Why an alert about a client was sent
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ClientEvent {
+    /// A known client has been offline longer than the threshold
+    Offline,
+    /// A client that was alerted on as offline reconnected
+    Online,
+}
+
+/// Why an alert about a schedule was sent
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ScheduleEvent {
+    /// The schedule's calendar cannot be parsed, so its missed runs are not found
+    Unwatchable,
+}
+
+/// What every kind of alert gives its targets
+trait Notice: Serialize {
+    /// A one line description of the alert, used as the email subject
+    fn summary(&self) -> String;
+
+    /// The alert as plain text, wrapping any output in `fence`
+    fn text(&self, fence: &str) -> String;
+}
+
+/// An alert about one run, as POSTed to a webhook target
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub(crate) struct Alert {
+    /// The rule that fired
+    rule: String,
+    /// Why the rule fired
+    event: Event,
+    /// The bartoc client the run belongs to
+    client: String,
+    /// The schedule the run belongs to
+    schedule: String,
+    /// The command that was run
+    cmd: Option<String>,
+    /// The UUID of the run
+    cmd_uuid: Uuid,
+    /// The exit code of the run, `null` when it was killed by a signal
+    exit_code: Option<i32>,
+    /// The signal that killed the run
+    exit_signal: Option<i32>,
+    /// Whether the run dumped core when it was killed
+    core_dumped: bool,
+    /// Runs of the schedule that failed in a row, this one included
+    consecutive_failures: u32,
+    /// When the run ended, RFC 3339
+    timestamp: String,
+    /// The last lines of the run's output
+    output: Vec<String>,
+}
+
+impl Alert {
+    fn outcome(&self) -> String {
+        match (self.exit_signal, self.exit_code) {
+            (Some(signal), _) if self.core_dumped => format!("signal {signal}, core dumped"),
+            (Some(signal), _) => format!("signal {signal}"),
+            (None, Some(code)) => format!("exit {code}"),
+            (None, None) => "no exit status".to_string(),
+        }
+    }
+}
+
+impl Notice for Alert {
+    fn summary(&self) -> String {
+        match self.event {
+            Event::Failure => {
+                let mut summary = format!(
+                    "{} on {} failed ({})",
+                    self.schedule,
+                    self.client,
+                    self.outcome()
+                );
+                if self.consecutive_failures > 1 {
+                    let _ = write!(summary, ", {} runs in a row", self.consecutive_failures);
+                }
+                summary
+            }
+            Event::Missed => {
+                let mut summary = format!(
+                    "{} on {} did not run at {}",
+                    self.schedule, self.client, self.timestamp
+                );
+                if self.consecutive_failures > 1 {
+                    let _ = write!(summary, ", {} runs in a row", self.consecutive_failures);
+                }
+                summary
+            }
+            Event::Recovery => format!("{} on {} recovered", self.schedule, self.client),
+        }
+    }
+
+    fn text(&self, fence: &str) -> String {
+        let mut text = format!(
+            "{}\n\nrule: {}\nrun: {}\n",
+            self.summary(),
+            self.rule,
+            self.cmd_uuid
+        );
+        if let Some(cmd) = &self.cmd {
+            let _ = writeln!(text, "command: {cmd}");
+        }
+        if !self.output.is_empty() {
+            let _ = write!(
+                text,
+                "\nlast {} lines of output:\n{fence}\n{}\n{fence}\n",
+                self.output.len(),
+                self.output.join("\n")
+            );
+        }
+        text
+    }
+}
+
+/// An alert about a client that went offline or came back, as POSTed to a webhook target
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub(crate) struct ClientAlert {
+    /// The rule that fired
+    rule: String,
+    /// Why the rule fired
+    event: ClientEvent,
+    /// The bartoc client
+    client: String,
+    /// The address the client last connected from
+    ip: String,
+    /// Why the client last disconnected, if that was recorded
+    reason: Option<String>,
+    /// When the client went offline, RFC 3339
+    offline_since: String,
+    /// When the change was noticed, RFC 3339
+    timestamp: String,
+}
+
+impl Notice for ClientAlert {
+    fn summary(&self) -> String {
+        match self.event {
+            ClientEvent::Offline => {
+                format!(
+                    "{} has been offline since {}",
+                    self.client, self.offline_since
+                )
+            }
+            ClientEvent::Online => format!("{} is back online", self.client),
+        }
+    }
+
+    fn text(&self, _fence: &str) -> String {
+        let mut text = format!(
+            "{}\n\nrule: {}\naddress: {}\n",
+            self.summary(),
+            self.rule,
+            self.ip
+        );
+        if let Some(reason) = &self.reason {
+            let _ = writeln!(text, "disconnected: {reason}");
+        }
+        text
+    }
+}
+
+/// An alert about a schedule the watchdog cannot watch, as POSTed to a webhook target
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub(crate) struct ScheduleAlert {
+    /// The rule that fired
+    rule: String,
+    /// Why the rule fired
+    event: ScheduleEvent,
+    /// The bartoc client the schedule belongs to
+    client: String,
+    /// The schedule
+    schedule: String,
+    /// The schedule's `on_calendar`
+    on_calendar: String,
+    /// Why the calendar cannot be parsed
+    error: String,
+    /// When the calendar was found, RFC 3339
+    timestamp: String,
+}
+
+impl Notice for ScheduleAlert {
+    fn summary(&self) -> String {
+        match self.event {
+            ScheduleEvent::Unwatchable => format!(
+                "{} on {} is not watched for missed runs",
+                self.schedule, self.client
+            ),
+        }
+    }
+
+    fn text(&self, _fence: &str) -> String {
+        format!(
+            "{}\n\nrule: {}\non_calendar: {}\nerror: {}\n",
+            self.summary(),
+            self.rule,
+            self.on_calendar,
+            self.error
+        )
+    }
+}
+
+/// Where a client schedule stands
+#[derive(Clone, Copy, Debug, Default)]
+struct Streak {
+    /// Runs that failed in a row
+    failures: u32,
+    /// The last run counted, so a status reported twice is only counted once
+    last_run: Option<Uuid>,
+}
+
+/// Decides which statuses alert which targets and delivers the alerts
+#[derive(Debug)]
+pub(crate) struct Alerter {
+    alerts: Alerts,
+    streaks: Mutex<HashMap<(String, String), Streak>>,
+    courier: Courier,
+}
+
+/// Delivers alerts to the configured targets. Clones share the HTTP client and SMTP transport.
+#[derive(Clone, Debug)]
+struct Courier {
+    targets: BTreeMap<String, AlertTarget>,
+    http: reqwest::Client,
+    mailer: Option<(AsyncSmtpTransport<Tokio1Executor>, Mailbox)>,
+}
+
+impl Alerter {
+    /// Set up the HTTP client and SMTP transport for the `[alerts]` section
+    pub(crate) fn new(alerts: &Alerts) -> Result<Self> {
+        let timeout = Duration::from_secs(alerts.timeout_secs());
+        for rule in alerts.rules() {
+            for target in rule.targets() {
+                match alerts.targets().get(target) {
+                    None => warn!(
+                        "alert rule '{}' names unknown target '{target}', it will be skipped",
+                        rule.name()
+                    ),
+                    Some(AlertTarget::Email { .. }) if alerts.smtp().is_none() => warn!(
+                        "alert target '{target}' is an email target but [alerts.smtp] is not set, it will be skipped"
+                    ),
+                    Some(_) => {}
+                }
+            }
+        }
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("bartos/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .context("unable to create the alert HTTP client")?;
+        let mailer = alerts
+            .smtp()
+            .as_ref()
+            .map(|smtp| mailer(smtp, timeout))
+            .transpose()?;
+        Ok(Self {
+            alerts: alerts.clone(),
+            streaks: Mutex::new(HashMap::new()),
+            courier: Courier {
+                targets: alerts.targets().clone(),
+                http,
+                mailer,
+            },
+        })
+    }
+
+    /// Check a status against the rules and deliver any alerts it raises
+    pub(crate) async fn status(&self, store: &Store, bartoc_name: &str, status: &Status) {
+        self.check(store, bartoc_name, status, Event::Failure).await;
+    }
+
+    /// Check the failed status recorded for a missed run against the rules. It counts as a
+    /// failure, so it alerts the rules that alert on failures.
+    pub(crate) async fn missed(&self, store: &Store, bartoc_name: &str, status: &Status) {
+        self.check(store, bartoc_name, status, Event::Missed).await;
+    }
+
+    async fn check(&self, store: &Store, bartoc_name: &str, status: &Status, failure: Event) {
+        if self.alerts.rules().is_empty() {
+            return;
+        }
+        let cmd_uuid = status.cmd_uuid().0;
+        let run = store.run_tail(cmd_uuid, 0).await.unwrap_or_else(|e| {
+            error!("unable to read the run for alerting: {e}");
+            Vec::new()
+        });
+        let alerts: Vec<(Alert, Vec<String>)> = self
+            .evaluate(bartoc_name, status, run.first(), failure)
+            .into_iter()
+            .map(|(alert, targets)| (alert, targets.to_vec()))
+            .collect();
+        if alerts.is_empty() {
+            return;
+        }
+        let store = store.clone();
+        let courier = self.courier.clone();
+        let lines = self.alerts.output_lines();
+        let _handle = spawn(async move {
+            let output = last_lines(&store, cmd_uuid, lines).await;
+            for (mut alert, targets) in alerts {
+                alert.output.clone_from(&output);
+                for target in &targets {
+                    courier.deliver(&alert, target);
+                }
+            }
+        });
+    }
+
+    /// Alert the rules that watch a client about it going offline or coming back
+    pub(crate) fn client(&self, change: &Change, now: OffsetDateTime) {
+        for (alert, targets) in self.evaluate_client(change, now) {
+            for target in targets {
+                self.courier.deliver(&alert, target);
+            }
+        }
+    }
+
+    /// Alert the rules that alert on failures of a schedule that its calendar cannot be
+    /// parsed, since its missed runs will not be found
+    pub(crate) fn unwatchable(&self, schedule: &Unwatchable, now: OffsetDateTime) {
+        for (alert, targets) in self.evaluate_unwatchable(schedule, now) {
+            for target in targets {
+                self.courier.deliver(&alert, target);
+            }
+        }
+    }
+
+    /// Return the alerts a schedule the watchdog cannot watch raises, with the names of the
+    /// targets each one goes to
+    fn evaluate_unwatchable<'a>(
+        &'a self,
+        schedule: &Unwatchable,
+        now: OffsetDateTime,
+    ) -> Vec<(ScheduleAlert, &'a [String])> {
+        let timestamp = now.format(&Rfc3339).unwrap_or_default();
+        self.alerts
+            .rules()
+            .iter()
+            .filter(|rule| {
+                rule.on_failure() && rule.matches(schedule.bartoc_name(), schedule.schedule_name())
+            })
+            .map(|rule| {
+                let alert = ScheduleAlert {
+                    rule: rule.name().clone(),
+                    event: ScheduleEvent::Unwatchable,
+                    client: schedule.bartoc_name().to_string(),
+                    schedule: schedule.schedule_name().to_string(),
+                    on_calendar: schedule.on_calendar().to_string(),
+                    error: schedule.error().to_string(),
+                    timestamp: timestamp.clone(),
+                };
+                (alert, rule.targets().as_slice())
+            })
+            .collect()
+    }
+
+    /// Return the alerts a change in a client raises, with the names of the targets each
+    /// one goes to
+    fn evaluate_client<'a>(
+        &'a self,
+        change: &Change,
+        now: OffsetDateTime,
+    ) -> Vec<(ClientAlert, &'a [String])> {
+        let (offline, event) = match change {
+            Change::Offline(offline) => (offline, ClientEvent::Offline),
+            Change::Back(offline) => (offline, ClientEvent::Online),
+        };
+        let rfc3339 = |at: OffsetDateTime| at.format(&Rfc3339).unwrap_or_default();
+        self.alerts
+            .rules()
+            .iter()
+            .filter(|rule| {
+                rule.on_offline()
+                    && rule.matches_client(offline.bartoc_name())
+                    && (event == ClientEvent::Offline || rule.on_recovery())
+            })
+            .map(|rule| {
+                let alert =
+                    client_alert(rule, event, offline, rfc3339(offline.since()), rfc3339(now));
+                (alert, rule.targets().as_slice())
+            })
+            .collect()
+    }
+
+    /// Update the schedule's streak and return the alerts the status of `run` raises, with
+    /// the names of the targets each one goes to. A failed status raises `failure`. The
+    /// alerts are returned without output.
+    fn evaluate<'a>(
+        &'a self,
+        bartoc_name: &str,
+        status: &Status,
+        run: Option<&ExportRow>,
+        failure: Event,
+    ) -> Vec<(Alert, &'a [String])> {
+        let schedule = run
+            .and_then(|run| run.schedule_name().clone())
+            .unwrap_or_else(|| UNKNOWN.to_string());
+        let cmd_uuid = status.cmd_uuid().0;
+        let (previous, current) = {
+            let mut streaks = self.streaks();
+            let streak = streaks
+                .entry((bartoc_name.to_string(), schedule.clone()))
+                .or_default();
+            if streak.last_run == Some(cmd_uuid) {
+                trace!("status for run {cmd_uuid} already counted, not alerting again");
+                return Vec::new();
+            }
+            let previous = streak.failures;
+            streak.failures = if status.success() { 0 } else { previous + 1 };
+            streak.last_run = Some(cmd_uuid);
+            (previous, streak.failures)
+        };
+
+        let fires = |rule: &AlertRule| {
+            if !rule.matches(bartoc_name, &schedule) {
+                None
+            } else if status.success() {
+                (rule.on_recovery() && previous >= rule.consecutive_failures().max(1))
+                    .then_some(Event::Recovery)
+            } else {
+                (rule.on_failure() && current == rule.consecutive_failures().max(1))
+                    .then_some(failure)
+            }
+        };
+        let timestamp = status.timestamp().0.format(&Rfc3339).unwrap_or_default();
+
+        self.alerts
+            .rules()
+            .iter()
+            .filter_map(|rule| {
+                let event = fires(rule)?;
+                let alert = Alert {
+                    rule: rule.name().clone(),
+                    event,
+                    client: bartoc_name.to_string(),
+                    schedule: schedule.clone(),
+                    cmd: run.and_then(|run| run.cmd().clone()),
+                    cmd_uuid,
+                    exit_code: status.exit_code(),
+                    exit_signal: status.exit_signal(),
+                    core_dumped: status.core_dumped(),
+                    consecutive_failures: if status.success() { previous } else { current },
+                    timestamp: timestamp.clone(),
+                    output: Vec::new(),
+                };
+                Some((alert, rule.targets().as_slice()))
+            })
+            .collect()
+    }
+
+    fn streaks(&self) -> MutexGuard<'_, HashMap<(String, String), Streak>> {
+        self.streaks
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl Courier {
+    /// Send an alert to a target in the background
+    fn deliver<A: Notice>(&self, alert: &A, target_name: &str) {
+        let Some(target) = self.targets.get(target_name) else {
+            return;
+        };
+        let delivery = match target {
+            AlertTarget::Webhook { url, headers } => {
+                let mut request = self.http.post(url).json(alert);
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                Delivery::Http(Box::new(request))
+            }
+            AlertTarget::Slack { url } => Delivery::Http(Box::new(
+                self.http
+                    .post(url)
+                    .json(&json!({ "text": alert.text("```") })),
+            )),
+            AlertTarget::Email { to } => {
+                let Some((mailer, from)) = &self.mailer else {
+                    return;
+                };
+                match email(alert, from, to) {
+                    Ok(message) => Delivery::Email(mailer.clone(), Box::new(message)),
+                    Err(e) => {
+                        error!("unable to build the alert email for '{target_name}': {e}");
+                        return;
+                    }
+                }
+            }
+        };
+        let target_name = target_name.to_string();
+        let _handle = spawn(async move {
+            match delivery.send().await {
+                Ok(()) => trace!("alert delivered to '{target_name}'"),
+                Err(e) => error!("unable to deliver alert to '{target_name}': {e:#}"),
+            }
+        });
+    }
+}
+
+/// The last `lines` lines of a run's output, none when they cannot be read
+async fn last_lines<Q: Queryable>(store: &Q, cmd_uuid: Uuid, lines: usize) -> Vec<String> {
+    if lines == 0 {
+        return Vec::new();
+    }
+    match store.run_tail(cmd_uuid, lines).await {
+        Ok(rows) => rows
+            .into_iter()
+            .filter_map(|row| row.data().clone())
+            .collect(),
+        Err(e) => {
+            error!("unable to read the output of run {cmd_uuid} for alerting: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// An alert ready to be sent to one target
+enum Delivery {
+    Http(Box<reqwest::RequestBuilder>),
+    Email(AsyncSmtpTransport<Tokio1Executor>, Box<Message>),
+}
+
+impl Delivery {
+    async fn send(self) -> Result<()> {
+        match self {
+            Self::Http(request) => {
+                let _response = request.send().await?.error_for_status()?;
+            }
+            Self::Email(mailer, message) => {
+                let _response = mailer.send(*message).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn mailer(smtp: &Smtp, timeout: Duration) -> Result<(AsyncSmtpTransport<Tokio1Executor>, Mailbox)> {
+    let host = smtp.host();
+    let mut builder = match smtp.tls() {
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+    }
+    .timeout(Some(timeout));
+    if let Some(port) = smtp.port() {
+        builder = builder.port(port);
+    }
+    if let Some(username) = smtp.username() {
+        builder = builder.credentials(Credentials::new(
+            username.clone(),
+            smtp.password().clone().unwrap_or_default(),
+        ));
+    }
+    let from = smtp
+        .from()
+        .parse()
+        .with_context(|| format!("invalid alerts.smtp.from address '{}'", smtp.from()))?;
+    Ok((builder.build(), from))
+}
+
+fn client_alert(
+    rule: &AlertRule,
+    event: ClientEvent,
+    offline: &Offline,
+    offline_since: String,
+    timestamp: String,
+) -> ClientAlert {
+    ClientAlert {
+        rule: rule.name().clone(),
+        event,
+        client: offline.bartoc_name().to_string(),
+        ip: offline.ip().to_string(),
+        reason: offline.reason().map(str::to_string),
+        offline_since,
+        timestamp,
+    }
+}
+
+fn email<A: Notice>(alert: &A, from: &Mailbox, to: &[String]) -> Result<Message> {
+    let mut builder = Message::builder()
+        .from(from.clone())
+        .subject(format!("[bartos] {}", alert.summary()))
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in to {
+        builder = builder.to(recipient
+            .parse()
+            .with_context(|| format!("invalid recipient '{recipient}'"))?);
+    }
+    Ok(builder.body(alert.text(""))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use libbarto::{
+        ExportRow, OffsetDataTimeWrapper, Output, OutputKind, RunStart, Status, UuidWrapper,
+    };
+    use time::OffsetDateTime;
+    use tokio::{
+        io::{AsyncReadExt as _, AsyncWriteExt as _},
+        net::TcpListener,
+    };
+    use uuid::Uuid;
+
+    use super::{
+        Alert, Alerter, ClientEvent, Event, Notice as _, ScheduleEvent, email, last_lines,
+    };
+    use crate::{
+        config::{AlertRule, AlertTarget, Alerts, MissedRuns, OfflineClients, Smtp},
+        db::{Queryable as _, memory::MemoryHandler},
+        presence::{Change, Presence},
+        watchdog::Watchdog,
+    };
+
+    fn alerter(rule: AlertRule, targets: BTreeMap<String, AlertTarget>) -> Alerter {
+        Alerter::new(
+            &Alerts::builder()
+                .output_lines(2)
+                .rules(vec![rule])
+                .targets(targets)
+                .build(),
+        )
+        .unwrap()
+    }
+
+    fn rule() -> AlertRule {
+        AlertRule::builder()
+            .name("backups")
+            .schedules(vec!["backup".to_string()])
+            .targets(vec!["ops".to_string()])
+            .build()
+    }
+
+    fn status(success: bool) -> Status {
+        Status::builder()
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+            .exit_code(Some(i32::from(!success)))
+            .success(success)
+            .build()
+    }
+
+    fn rows(status: &Status, lines: &[&str]) -> Vec<ExportRow> {
+        lines
+            .iter()
+            .map(|line| {
+                ExportRow::builder()
+                    .cmd_uuid(status.cmd_uuid())
+                    .bartoc_name("host".to_string())
+                    .schedule_name("backup".to_string())
+                    .cmd("backup.sh".to_string())
+                    .data((*line).to_string())
+                    .build()
+            })
+            .collect()
+    }
+
+    fn events(alerter: &Alerter, client: &str, success: bool) -> Vec<Event> {
+        let status = status(success);
+        alerter
+            .evaluate(
+                client,
+                &status,
+                rows(&status, &["line"]).first(),
+                Event::Failure,
+            )
+            .into_iter()
+            .map(|(alert, _)| alert.event)
+            .collect()
+    }
+
+    #[test]
+    fn alerts_on_the_first_failure_by_default() {
+        let alerter = alerter(rule(), BTreeMap::new());
+        assert_eq!(events(&alerter, "host", false), [Event::Failure]);
+        assert!(events(&alerter, "host", false).is_empty());
+        assert!(events(&alerter, "host", true).is_empty());
+        // a new streak alerts again
+        assert_eq!(events(&alerter, "host", false), [Event::Failure]);
+    }
+
+    #[test]
+    fn alerts_after_consecutive_failures_and_on_recovery() {
+        let rule = AlertRule::builder()
+            .name("backups")
+            .consecutive_failures(3)
+            .on_recovery(true)
+            .targets(vec!["ops".to_string()])
+            .build();
+        let alerter = alerter(rule, BTreeMap::new());
+        assert!(events(&alerter, "host", false).is_empty());
+        assert!(events(&alerter, "host", false).is_empty());
+        assert!(events(&alerter, "host", true).is_empty());
+        assert!(events(&alerter, "host", false).is_empty());
+        assert!(events(&alerter, "host", false).is_empty());
+        assert_eq!(events(&alerter, "host", false), [Event::Failure]);
+        // the streak was alerted on when it reached 3, not again at 4
+        assert!(events(&alerter, "host", false).is_empty());
+        // another client's streak is its own
+        assert!(events(&alerter, "other", false).is_empty());
+        assert_eq!(events(&alerter, "host", true), [Event::Recovery]);
+        assert!(events(&alerter, "host", true).is_empty());
+    }
+
+    #[test]
+    fn skips_unmatched_runs_and_repeated_statuses() {
+        let alerter = alerter(rule(), BTreeMap::new());
+        let status = status(false);
+        let mut rows = rows(&status, &["line"]);
+        assert_eq!(
+            alerter
+                .evaluate("host", &status, rows.first(), Event::Failure)
+                .len(),
+            1
+        );
+        assert!(
+            alerter
+                .evaluate("host", &status, rows.first(), Event::Failure)
+                .is_empty()
+        );
+
+        rows = rows
+            .into_iter()
+            .map(|row| {
+                ExportRow::builder()
+                    .cmd_uuid(row.cmd_uuid())
+                    .schedule_name("update".to_string())
+                    .build()
+            })
+            .collect();
+        let other = self::status(false);
+        assert!(
+            alerter
+                .evaluate("host", &other, rows.first(), Event::Missed)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn describes_the_run_without_its_output() {
+        let alerter = alerter(rule(), BTreeMap::new());
+        let status = status(false);
+        let alerts = alerter.evaluate(
+            "host",
+            &status,
+            rows(&status, &["one"]).first(),
+            Event::Failure,
+        );
+        let (alert, targets) = &alerts[0];
+        assert!(alert.output.is_empty());
+        assert_eq!(alert.cmd.as_deref(), Some("backup.sh"));
+        assert_eq!(alert.timestamp, "1970-01-01T00:00:00Z");
+        assert_eq!(*targets, ["ops".to_string()]);
+        assert_eq!(alert.summary(), "backup on host failed (exit 1)");
+        assert!(!alert.text("```").contains("```"));
+    }
+
+    #[tokio::test]
+    async fn reads_the_last_lines_of_output() {
+        let store = MemoryHandler::default();
+        let cmd_uuid = Uuid::new_v4();
+        let run_start = RunStart::builder()
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .bartoc_uuid(UuidWrapper(Uuid::nil()))
+            .bartoc_name("host".to_string())
+            .schedule_name("backup".to_string())
+            .cmd("backup.sh".to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+            .build();
+        let _ = store.insert_run_start(&run_start).await.unwrap();
+        let outputs: Vec<Output> = ["one", "two", "three"]
+            .into_iter()
+            .map(|line| {
+                Output::builder()
+                    .bartoc_uuid(UuidWrapper(Uuid::nil()))
+                    .bartoc_name("host".to_string())
+                    .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+                    .cmd_uuid(UuidWrapper(cmd_uuid))
+                    .cmd_name("backup".to_string())
+                    .kind(OutputKind::Stdout)
+                    .data(line.to_string())
+                    .build()
+            })
+            .collect();
+        let _ = store.insert_outputs(&outputs).await.unwrap();
+
+        assert_eq!(last_lines(&store, cmd_uuid, 2).await, ["two", "three"]);
+        assert!(last_lines(&store, cmd_uuid, 0).await.is_empty());
+        assert!(last_lines(&store, Uuid::new_v4(), 2).await.is_empty());
+    }
+
+    fn alert() -> Alert {
+        Alert {
+            rule: "backups".to_string(),
+            event: Event::Failure,
+            client: "host".to_string(),
+            schedule: "backup".to_string(),
+            cmd: None,
+            cmd_uuid: Uuid::nil(),
+            exit_code: None,
+            exit_signal: Some(9),
+            core_dumped: false,
+            consecutive_failures: 2,
+            timestamp: "1970-01-01T00:00:00Z".to_string(),
+            output: vec!["oops".to_string()],
+        }
+    }
+
+    #[test]
+    fn summarizes_signals_and_recoveries() {
+        let mut alert = alert();
+        assert_eq!(
+            alert.summary(),
+            "backup on host failed (signal 9), 2 runs in a row"
+        );
+        alert.event = Event::Missed;
+        assert_eq!(
+            alert.summary(),
+            "backup on host did not run at 1970-01-01T00:00:00Z, 2 runs in a row"
+        );
+        alert.event = Event::Recovery;
+        assert_eq!(alert.summary(), "backup on host recovered");
+    }
+
+    /// The change for `host` after it has been offline for an hour
+    fn offline_change() -> Change {
+        let presence = Presence::new(OfflineClients::default(), OffsetDateTime::UNIX_EPOCH);
+        let event = libbarto::ClientEvent::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name("host".to_string())
+            .ip("10.0.0.1".to_string())
+            .kind(libbarto::ClientEventKind::Disconnect)
+            .reason("heartbeat_timeout".to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+            .build();
+        let now = OffsetDateTime::UNIX_EPOCH + time::Duration::hours(1);
+        presence
+            .check(&[event], &std::collections::HashSet::new(), now)
+            .pop()
+            .unwrap()
+    }
+
+    #[test]
+    fn alerts_offline_clients_to_rules_that_ask() {
+        let watching = AlertRule::builder()
+            .name("fleet")
+            .clients(vec!["host".to_string()])
+            .on_offline(true)
+            .on_recovery(true)
+            .targets(vec!["ops".to_string()])
+            .build();
+        let alerter = alerter(watching, BTreeMap::new());
+        let now = OffsetDateTime::UNIX_EPOCH + time::Duration::hours(1);
+        let Change::Offline(offline) = offline_change() else {
+            panic!("expected the client to be offline");
+        };
+
+        let alerts = alerter.evaluate_client(&Change::Offline(offline.clone()), now);
+        let (alert, targets) = &alerts[0];
+        assert_eq!(alert.event, ClientEvent::Offline);
+        assert_eq!(*targets, ["ops".to_string()]);
+        assert_eq!(
+            alert.summary(),
+            "host has been offline since 1970-01-01T00:00:00Z"
+        );
+        assert!(alert.text("").contains("disconnected: heartbeat_timeout"));
+        let back = alerter.evaluate_client(&Change::Back(offline.clone()), now);
+        assert_eq!(back[0].0.event, ClientEvent::Online);
+        assert_eq!(back[0].0.summary(), "host is back online");
+
+        // Rules alert on offline clients only when they ask to
+        let failures = self::alerter(rule(), BTreeMap::new());
+        assert!(
+            failures
+                .evaluate_client(&Change::Offline(offline), now)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn alerts_unwatchable_schedules_to_failure_rules() {
+        let schedules = BTreeMap::from([(
+            "host".to_string(),
+            serde_json::from_str(
+                r#"{"schedules":[{"name":"backup","on_calendar":"not a calendar","cmds":["backup.sh"]}]}"#,
+            )
+            .unwrap(),
+        )]);
+        let watchdog = Watchdog::new(MissedRuns::builder().grace_secs(60).build());
+        let _none = watchdog.check(&schedules, OffsetDateTime::UNIX_EPOCH);
+        let unwatchable = watchdog.take_unwatchable().pop().unwrap();
+
+        let alerter = alerter(rule(), BTreeMap::new());
+        let alerts = alerter.evaluate_unwatchable(&unwatchable, OffsetDateTime::UNIX_EPOCH);
+        let (alert, targets) = &alerts[0];
+        assert_eq!(alert.event, ScheduleEvent::Unwatchable);
+        assert_eq!(*targets, ["ops".to_string()]);
+        assert_eq!(
+            alert.summary(),
+            "backup on host is not watched for missed runs"
+        );
+        assert!(alert.text("").contains("on_calendar: not a calendar"));
+
+        // Rules that do not alert on failures are not told
+        let quiet = AlertRule::builder()
+            .name("quiet")
+            .on_failure(false)
+            .targets(vec!["ops".to_string()])
+            .build();
+        let quiet = self::alerter(quiet, BTreeMap::new());
+        assert!(
+            quiet
+                .evaluate_unwatchable(&unwatchable, OffsetDateTime::UNIX_EPOCH)
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn builds_the_email() {
+        let smtp = Smtp::builder()
+            .host("localhost")
+            .from("bartos@example.com")
+            .build();
+        let (_mailer, from) = super::mailer(&smtp, std::time::Duration::from_secs(1)).unwrap();
+        let message = email(&alert(), &from, &["ops@example.com".to_string()]).unwrap();
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+        assert!(formatted.contains("To: ops@example.com"));
+        assert!(
+            formatted
+                .contains("Subject: [bartos] backup on host failed (signal 9), 2 runs in a row")
+        );
+        assert!(formatted.contains("oops"));
+        assert!(email(&alert(), &from, &["not an address".to_string()]).is_err());
+    }
+
+    /// Accept one HTTP request, answer `204` and return its body
+    async fn receive_one(listener: TcpListener) -> String {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let read = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    socket
+                        .write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n")
+                        .await
+                        .unwrap();
+                    return text.into_owned();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_webhook_and_slack_payloads() {
+        for (target, expected) in [
+            (
+                AlertTarget::Webhook {
+                    url: String::new(),
+                    headers: BTreeMap::from([("x-token".to_string(), "secret".to_string())]),
+                },
+                "\"event\":\"failure\"",
+            ),
+            (
+                AlertTarget::Slack { url: String::new() },
+                "{\"text\":\"backup on host failed",
+            ),
+        ] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/hook", listener.local_addr().unwrap());
+            let target = match target {
+                AlertTarget::Webhook { headers, .. } => AlertTarget::Webhook { url, headers },
+                AlertTarget::Slack { .. } => AlertTarget::Slack { url },
+                email @ AlertTarget::Email { .. } => email,
+            };
+            let alerter = alerter(rule(), BTreeMap::from([("ops".to_string(), target)]));
+            let receiver = tokio::spawn(receive_one(listener));
+            alerter.courier.deliver(&alert(), "ops");
+            let request = receiver.await.unwrap();
+            assert!(request.starts_with("POST /hook HTTP/1.1"), "{request}");
+            assert!(request.contains(expected), "{request}");
+        }
+    }
+}
@@ -6,20 +6,165 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use bon::Builder;
 use getset::Getters;
 use libbarto::{BartocInfo, ClientData};
+use serde::Serialize;
 use uuid::Uuid;
 
-/// A signal broadcast from bartos to every connected bartoc worker task.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// A signal broadcast from bartos to every connected bartoc worker task and barto-cli session.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) enum WorkerSignal {
     /// Re-send the (possibly updated) schedules to the worker.
     Reload,
     /// Ask the worker to clean up old entries from its local redb database.
     Cleanup,
+    /// Disconnect every worker and cli session connected under the given name (e.g. its
+    /// credential was revoked).
+    Disconnect(String),
+}
+
+/// Why a bartoc client's connection ended, recorded with its disconnect event
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum DisconnectReason {
+    /// The client sent a close frame, with its close code if it gave one
+    Closed(Option<u16>),
+    /// Nothing arrived from the client within the heartbeat timeout
+    HeartbeatTimeout,
+    /// A client with the same name connected and took over
+    Replaced,
+    /// The client's credential was revoked
+    Revoked,
+    /// The WebSocket failed
+    Error,
+    /// The connection ended without a close frame
+    Dropped,
+    /// bartos is shutting down
+    Shutdown,
+}
+
+impl DisconnectReason {
+    /// The close code the client sent, if it closed the connection
+    pub(crate) fn close_code(self) -> Option<u16> {
+        match self {
+            DisconnectReason::Closed(code) => code,
+            _ => None,
+        }
+    }
+}
+
+impl From<DisconnectReason> for &'static str {
+    fn from(reason: DisconnectReason) -> Self {
+        match reason {
+            DisconnectReason::Closed(_) => "closed",
+            DisconnectReason::HeartbeatTimeout => "heartbeat_timeout",
+            DisconnectReason::Replaced => "replaced",
+            DisconnectReason::Revoked => "revoked",
+            DisconnectReason::Error => "error",
+            DisconnectReason::Dropped => "dropped",
+            DisconnectReason::Shutdown => "shutdown",
+        }
+    }
+}
+
+/// A change in the fleet, pushed as JSON to every open dashboard so it can refresh what changed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum DashboardEvent {
+    /// A bartoc client connected.
+    Connected { client: String },
+    /// A bartoc client disconnected.
+    Disconnected { client: String },
+    /// A bartoc client reported its system information.
+    ClientInfo { client: String },
+    /// A run started on a client.
+    RunStarted {
+        client: String,
+        schedule: String,
+        cmd_uuid: Uuid,
+    },
+    /// A run on a client wrote more output.
+    RunOutput { client: String, cmd_uuid: Uuid },
+    /// A run on a client ended.
+    RunFinished {
+        client: String,
+        cmd_uuid: Uuid,
+        success: bool,
+    },
+    /// The schedules were reloaded from the configuration.
+    SchedulesReloaded,
+    /// The watchdog cannot watch a schedule for missed runs, since its calendar cannot be parsed.
+    ScheduleUnwatchable {
+        client: String,
+        schedule: String,
+        error: String,
+    },
+    /// The dashboard fell behind and missed events, so it should refresh everything.
+    Lagged,
+}
+
+/// Short-lived, single-use tickets that let a browser open the dashboard WebSocket, since
+/// a browser cannot send an `Authorization` header on a WebSocket upgrade.
+#[derive(Debug, Default)]
+pub(crate) struct DashboardTickets {
+    tickets: HashMap<Uuid, Instant>,
+}
+
+impl DashboardTickets {
+    /// Issues a ticket valid for `ttl`, forgetting the tickets that have expired.
+    pub(crate) fn issue(&mut self, ttl: Duration) -> Uuid {
+        let now = Instant::now();
+        self.tickets.retain(|_, expires| *expires > now);
+        let ticket = Uuid::new_v4();
+        let _old = self.tickets.insert(ticket, now + ttl);
+        ticket
+    }
+
+    /// Uses up a ticket, returning whether it was issued and has not expired.
+    pub(crate) fn redeem(&mut self, ticket: &Uuid) -> bool {
+        self.tickets
+            .remove(ticket)
+            .is_some_and(|expires| expires > Instant::now())
+    }
+}
+
+/// A credential stored for a client in the client registry.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ClientCredential {
+    /// The hex SHA-256 hash of the token issued at enrollment.
+    Token(String),
+    /// The base64 Ed25519 public key the client signs its name with.
+    PublicKey(String),
+    /// The credential was revoked; the client may not connect until it is re-enrolled.
+    Revoked,
+}
+
+/// The identities from a verified mutual TLS client certificate, attached to each TLS
+/// connection as connection data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CertIdentity {
+    /// The certificate's common name(s) followed by its DNS subject alternative names.
+    names: Vec<String>,
+}
+
+impl CertIdentity {
+    pub(crate) fn new(names: Vec<String>) -> Self {
+        Self { names }
+    }
+
+    /// The identity used when the client does not claim a name: the CN, or the first DNS SAN.
+    pub(crate) fn primary(&self) -> Option<&str> {
+        self.names.first().map(String::as_str)
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
 }
 
 #[derive(Builder, Clone, Debug, Eq, Getters, PartialEq)]
@@ -42,12 +187,12 @@ impl Clients {
         self.clients.remove(id)
     }
 
-    pub(crate) fn remove_client_by_name(&mut self, name: &str) -> Option<ClientData> {
+    pub(crate) fn remove_client_by_name(&mut self, name: &str) -> Option<(Uuid, ClientData)> {
         let id = self
             .clients
             .iter()
             .find_map(|(id, cd)| if cd.name() == name { Some(*id) } else { None })?;
-        self.clients.remove(&id)
+        self.clients.remove(&id).map(|cd| (id, cd))
     }
 
     pub(crate) fn add_client_data(&mut self, id: &Uuid, bartoc_info: BartocInfo) {
@@ -59,10 +204,24 @@ impl Clients {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use libbarto::BartocInfo;
     use uuid::Uuid;
 
-    use super::{Clients, WorkerSignal};
+    use super::{
+        CertIdentity, Clients, DashboardEvent, DashboardTickets, DisconnectReason, WorkerSignal,
+    };
+
+    #[test]
+    fn cert_identity_primary_and_contains() {
+        let identity = CertIdentity::new(vec!["host1".to_string(), "host1.lan".to_string()]);
+        assert_eq!(identity.primary(), Some("host1"));
+        assert!(identity.contains("host1"));
+        assert!(identity.contains("host1.lan"));
+        assert!(!identity.contains("host2"));
+        assert_eq!(CertIdentity::new(vec![]).primary(), None);
+    }
 
     #[test]
     fn add_client_inserts_and_replaces() {
@@ -101,7 +260,8 @@ mod tests {
         let id = Uuid::new_v4();
         let _old = clients.add_client(id, "host1", "10.0.0.1");
         assert!(clients.remove_client_by_name("nope").is_none());
-        let removed = clients.remove_client_by_name("host1").expect("removed");
+        let (removed_id, removed) = clients.remove_client_by_name("host1").expect("removed");
+        assert_eq!(removed_id, id);
         assert_eq!(removed.name(), "host1");
         assert!(clients.clients().is_empty());
     }
@@ -133,5 +293,75 @@ mod tests {
         assert_ne!(WorkerSignal::Reload, WorkerSignal::Cleanup);
         let copied = WorkerSignal::Cleanup;
         assert_eq!(copied, WorkerSignal::Cleanup);
+        assert_eq!(
+            WorkerSignal::Disconnect("host1".to_string()),
+            WorkerSignal::Disconnect("host1".to_string())
+        );
+        assert_ne!(
+            WorkerSignal::Disconnect("host1".to_string()),
+            WorkerSignal::Disconnect("host2".to_string())
+        );
+    }
+
+    #[test]
+    fn disconnect_reasons() {
+        let closed = DisconnectReason::Closed(Some(1000));
+        assert_eq!(<&'static str>::from(closed), "closed");
+        assert_eq!(closed.close_code(), Some(1000));
+        assert_eq!(
+            <&'static str>::from(DisconnectReason::HeartbeatTimeout),
+            "heartbeat_timeout"
+        );
+        assert_eq!(DisconnectReason::Replaced.close_code(), None);
+    }
+
+    #[test]
+    fn dashboard_tickets_are_single_use() {
+        let mut tickets = DashboardTickets::default();
+        let ticket = tickets.issue(Duration::from_secs(30));
+        assert!(tickets.redeem(&ticket));
+        assert!(!tickets.redeem(&ticket));
+        assert!(!tickets.redeem(&Uuid::new_v4()));
+    }
+
+    #[test]
+    fn dashboard_tickets_expire() {
+        let mut tickets = DashboardTickets::default();
+        let expired = tickets.issue(Duration::ZERO);
+        assert!(!tickets.redeem(&expired));
+        let _ = tickets.issue(Duration::ZERO);
+        let _ = tickets.issue(Duration::from_secs(30));
+        assert_eq!(tickets.tickets.len(), 1);
+    }
+
+    #[test]
+    fn dashboard_event_json() {
+        let event = DashboardEvent::RunFinished {
+            client: "host1".to_string(),
+            cmd_uuid: Uuid::nil(),
+            success: true,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "run_finished",
+                "client": "host1",
+                "cmd_uuid": "00000000-0000-0000-0000-000000000000",
+                "success": true,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(DashboardEvent::SchedulesReloaded).unwrap(),
+            serde_json::json!({ "event": "schedules_reloaded" })
+        );
+        let event = DashboardEvent::ScheduleUnwatchable {
+            client: "host1".to_string(),
+            schedule: "backup".to_string(),
+            error: "invalid".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap()["event"],
+            "schedule_unwatchable"
+        );
     }
 }
@@ -0,0 +1,268 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_OUTPUT_LINES: usize = 20;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Who is told about failing runs, configured by the `[alerts]` section
+#[derive(Builder, Clone, CopyGetters, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+#[serde(default)]
+pub(crate) struct Alerts {
+    /// The last lines of a run's output included in an alert
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default = DEFAULT_OUTPUT_LINES)]
+    output_lines: usize,
+    /// Seconds to wait for a webhook or the SMTP server before giving up on an alert
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default = DEFAULT_TIMEOUT_SECS)]
+    timeout_secs: u64,
+    /// Where alerts are delivered, by target name
+    #[getset(get = "pub(crate)")]
+    #[builder(default)]
+    targets: BTreeMap<String, AlertTarget>,
+    /// The rules deciding which runs alert which targets
+    #[getset(get = "pub(crate)")]
+    #[builder(default)]
+    rules: Vec<AlertRule>,
+    /// The SMTP server email targets are sent through
+    #[getset(get = "pub(crate)")]
+    smtp: Option<Smtp>,
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Where an alert is delivered
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(crate) enum AlertTarget {
+    /// POST the alert as JSON to a URL
+    Webhook {
+        /// The URL to POST to
+        url: String,
+        /// Extra headers sent with the request, e.g. `Authorization`
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// POST a Slack-compatible `{"text": ...}` message to an incoming webhook URL
+    Slack {
+        /// The incoming webhook URL
+        url: String,
+    },
+    /// Send an email through `[alerts.smtp]`
+    Email {
+        /// The recipients
+        to: Vec<String>,
+    },
+}
+
+/// Which runs alert which targets
+#[derive(Builder, Clone, CopyGetters, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+pub(crate) struct AlertRule {
+    /// The rule's name, included in its alerts
+    #[getset(get = "pub(crate)")]
+    #[builder(into)]
+    name: String,
+    /// The clients the rule applies to, every client when empty
+    #[getset(get = "pub(crate)")]
+    #[builder(default)]
+    #[serde(default)]
+    clients: Vec<String>,
+    /// The schedules the rule applies to, every schedule when empty
+    #[getset(get = "pub(crate)")]
+    #[builder(default)]
+    #[serde(default)]
+    schedules: Vec<String>,
+    /// Alert when a run fails
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default = true)]
+    #[serde(default = "on_failure_default")]
+    on_failure: bool,
+    /// Alert once when this many runs of a schedule in a row have failed, and not again
+    /// until it recovers and fails this many times again
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default = 1)]
+    #[serde(default = "consecutive_failures_default")]
+    consecutive_failures: u32,
+    /// Alert when a schedule succeeds after this rule alerted on its failures, or when a
+    /// client reconnects after this rule alerted that it was offline
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default)]
+    #[serde(default)]
+    on_recovery: bool,
+    /// Alert when one of the rule's clients has been offline for
+    /// `offline_clients.threshold_secs`
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default)]
+    #[serde(default)]
+    on_offline: bool,
+    /// The targets to alert, by name
+    #[getset(get = "pub(crate)")]
+    targets: Vec<String>,
+}
+
+fn on_failure_default() -> bool {
+    true
+}
+
+fn consecutive_failures_default() -> u32 {
+    1
+}
+
+impl AlertRule {
+    /// Whether the rule applies to runs of this client schedule
+    pub(crate) fn matches(&self, bartoc_name: &str, schedule_name: &str) -> bool {
+        self.matches_client(bartoc_name)
+            && (self.schedules.is_empty() || self.schedules.iter().any(|s| s == schedule_name))
+    }
+
+    /// Whether the rule applies to this client, whatever the schedule
+    pub(crate) fn matches_client(&self, bartoc_name: &str) -> bool {
+        self.clients.is_empty() || self.clients.iter().any(|c| c == bartoc_name)
+    }
+}
+
+/// The SMTP server email alerts are sent through
+#[derive(Builder, Clone, CopyGetters, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+pub(crate) struct Smtp {
+    /// The server's host name
+    #[getset(get = "pub(crate)")]
+    #[builder(into)]
+    host: String,
+    /// The server's port, the default for `tls` when unset
+    #[getset(get_copy = "pub(crate)")]
+    port: Option<u16>,
+    /// How the connection is secured, `starttls` by default
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default)]
+    #[serde(default)]
+    tls: SmtpTls,
+    /// The user to authenticate as, no authentication when unset
+    #[getset(get = "pub(crate)")]
+    username: Option<String>,
+    /// The password for `username`
+    #[getset(get = "pub(crate)")]
+    password: Option<String>,
+    /// The sender address
+    #[getset(get = "pub(crate)")]
+    #[builder(into)]
+    from: String,
+}
+
+/// How the connection to the SMTP server is secured
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SmtpTls {
+    /// Plain text, only for a relay on the same host
+    None,
+    /// Upgrade a plain connection with `STARTTLS`, port 587 by default
+    #[default]
+    Starttls,
+    /// TLS from the start, port 465 by default
+    Tls,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{AlertRule, AlertTarget, Alerts, SmtpTls};
+
+    #[test]
+    fn defaults() {
+        let alerts = Alerts::default();
+        assert_eq!(alerts.output_lines(), 20);
+        assert_eq!(alerts.timeout_secs(), 10);
+        assert!(alerts.rules().is_empty());
+        assert!(alerts.targets().is_empty());
+        assert!(alerts.smtp().is_none());
+    }
+
+    #[test]
+    fn deserializes_section() {
+        let alerts: Alerts = serde_json::from_str(
+            r#"{
+                "output_lines": 5,
+                "targets": {
+                    "ops": {
+                        "kind": "webhook",
+                        "url": "https://example.com/hook",
+                        "headers": {"Authorization": "Bearer secret"}
+                    },
+                    "chat": {"kind": "slack", "url": "https://hooks.slack.com/services/x"},
+                    "mail": {"kind": "email", "to": ["ops@example.com"]}
+                },
+                "smtp": {"host": "smtp.example.com", "from": "bartos@example.com"},
+                "rules": [{
+                    "name": "backups",
+                    "schedules": ["backup"],
+                    "consecutive_failures": 3,
+                    "on_recovery": true,
+                    "targets": ["ops", "chat", "mail"]
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(alerts.output_lines(), 5);
+        assert_eq!(
+            alerts.targets()["ops"],
+            AlertTarget::Webhook {
+                url: "https://example.com/hook".to_string(),
+                headers: BTreeMap::from([(
+                    "Authorization".to_string(),
+                    "Bearer secret".to_string()
+                )]),
+            }
+        );
+        assert_eq!(
+            alerts.targets()["mail"],
+            AlertTarget::Email {
+                to: vec!["ops@example.com".to_string()]
+            }
+        );
+        let smtp = alerts.smtp().as_ref().unwrap();
+        assert_eq!(smtp.tls(), SmtpTls::Starttls);
+        assert!(smtp.port().is_none());
+        let rule = &alerts.rules()[0];
+        assert!(rule.on_failure());
+        assert!(rule.on_recovery());
+        assert!(!rule.on_offline());
+        assert_eq!(rule.consecutive_failures(), 3);
+        assert!(rule.clients().is_empty());
+    }
+
+    #[test]
+    fn empty_filters_match_everything() {
+        let all = AlertRule::builder()
+            .name("all")
+            .targets(vec!["ops".to_string()])
+            .build();
+        assert!(all.matches("host", "backup"));
+
+        let some = AlertRule::builder()
+            .name("some")
+            .clients(vec!["host".to_string()])
+            .schedules(vec!["backup".to_string()])
+            .targets(vec!["ops".to_string()])
+            .build();
+        assert!(some.matches("host", "backup"));
+        assert!(!some.matches("other", "backup"));
+        assert!(!some.matches("host", "update"));
+        assert!(some.matches_client("host"));
+        assert!(!some.matches_client("other"));
+    }
+}
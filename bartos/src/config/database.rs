@@ -0,0 +1,150 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{fmt::Display, path::PathBuf};
+
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+/// The database backend `bartos` stores output and runs in
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Backend {
+    /// `MariaDB` or `MySQL`, configured by the `[mariadb]` section
+    #[default]
+    Mariadb,
+    /// A local `SQLite` database file, configured by the `[sqlite]` section
+    Sqlite,
+    /// `PostgreSQL`, configured by the `[postgres]` section
+    Postgres,
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let backend = match self {
+            Backend::Mariadb => "mariadb",
+            Backend::Sqlite => "sqlite",
+            Backend::Postgres => "postgres",
+        };
+        write!(f, "{backend}")
+    }
+}
+
+/// The `SQLite` configuration
+#[derive(Clone, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
+pub(crate) struct Sqlite {
+    /// The path to the database file, created if it does not exist
+    #[getset(get = "pub(crate)")]
+    path: PathBuf,
+}
+
+impl Sqlite {
+    /// Generate the `SQLite` connection string
+    pub(crate) fn connection_string(&self) -> String {
+        format!("sqlite://{}?mode=rwc", self.path.display())
+    }
+}
+
+/// The `PostgreSQL` configuration
+#[derive(Clone, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
+pub(crate) struct Postgres {
+    /// The host or IP for the database
+    host: String,
+    /// The port for the database
+    port: Option<u16>,
+    /// The username for the database
+    username: String,
+    /// The password for the database
+    password: String,
+    /// The database name
+    database: String,
+    /// The options string
+    options: Option<String>,
+    /// The schema holding the `bartos` tables, created if it does not exist. When unset
+    /// the tables live in the server's default schema, usually `public`.
+    #[getset(get = "pub(crate)")]
+    schema: Option<String>,
+}
+
+impl Postgres {
+    /// Generate the `PostgreSQL` connection string
+    pub(crate) fn connection_string(&self) -> String {
+        self.url(&self.password)
+    }
+
+    /// Generate a displayable `PostgreSQL` connection string
+    pub(crate) fn disp_connection_string(&self) -> String {
+        self.url("****")
+    }
+
+    fn url(&self, password: &str) -> String {
+        let mut url = format!(
+            "postgres://{}:{password}@{}:{}/{}",
+            self.username,
+            self.host,
+            self.port.unwrap_or(5432),
+            self.database
+        );
+        if let Some(options) = self.options.as_ref() {
+            url.push('?');
+            url.push_str(options);
+        }
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Backend, Postgres, Sqlite};
+
+    #[test]
+    fn backend_deserializes_lowercase() {
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            backend: Backend,
+        }
+        let wrapper: Wrapper = serde_json::from_str(r#"{"backend":"postgres"}"#).unwrap();
+        assert_eq!(wrapper.backend, Backend::Postgres);
+        assert_eq!(Backend::default(), Backend::Mariadb);
+        assert_eq!(Backend::Sqlite.to_string(), "sqlite");
+    }
+
+    #[test]
+    fn sqlite_connection_string() {
+        let sqlite = Sqlite {
+            path: PathBuf::from("/var/lib/bartos/barto.db"),
+        };
+        assert_eq!(
+            sqlite.connection_string(),
+            "sqlite:///var/lib/bartos/barto.db?mode=rwc"
+        );
+    }
+
+    #[test]
+    fn postgres_connection_strings() {
+        let postgres = Postgres {
+            host: "db".to_string(),
+            port: None,
+            username: "barto".to_string(),
+            password: "secret".to_string(),
+            database: "barto".to_string(),
+            options: Some("sslmode=require".to_string()),
+            schema: None,
+        };
+        assert_eq!(
+            postgres.connection_string(),
+            "postgres://barto:secret@db:5432/barto?sslmode=require"
+        );
+        assert_eq!(
+            postgres.disp_connection_string(),
+            "postgres://barto:****@db:5432/barto?sslmode=require"
+        );
+    }
+}
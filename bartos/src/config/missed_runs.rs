@@ -0,0 +1,57 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use bon::Builder;
+use getset::CopyGetters;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_GRACE_SECS: u64 = 300;
+const DEFAULT_CHECK_SECS: u64 = 10;
+
+/// How `bartos` watches for runs that never happen, configured by the `[missed_runs]` section
+#[derive(Builder, Clone, Copy, CopyGetters, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[getset(get_copy = "pub(crate)")]
+#[serde(default)]
+pub(crate) struct MissedRuns {
+    /// Whether expected runs are watched for at all
+    #[builder(default = true)]
+    enabled: bool,
+    /// Seconds either side of an expected run a run may start and still count. This must
+    /// cover how long a bartoc client holds records before sending them.
+    #[builder(default = DEFAULT_GRACE_SECS)]
+    grace_secs: u64,
+    /// Seconds between checks for runs that are overdue
+    #[builder(default = DEFAULT_CHECK_SECS)]
+    check_secs: u64,
+}
+
+impl Default for MissedRuns {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MissedRuns;
+
+    #[test]
+    fn defaults() {
+        let missed_runs = MissedRuns::default();
+        assert!(missed_runs.enabled());
+        assert_eq!(missed_runs.grace_secs(), 300);
+        assert_eq!(missed_runs.check_secs(), 10);
+    }
+
+    #[test]
+    fn deserializes_partial_section() {
+        let missed_runs: MissedRuns = serde_json::from_str(r#"{"grace_secs":120}"#).unwrap();
+        assert!(missed_runs.enabled());
+        assert_eq!(missed_runs.grace_secs(), 120);
+    }
+}
@@ -6,6 +6,12 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+mod alerts;
+mod database;
+mod missed_runs;
+mod offline_clients;
+mod retention;
+
 use std::collections::BTreeMap;
 
 use getset::{CopyGetters, Getters, Setters};
@@ -14,6 +20,16 @@ use serde::{Deserialize, Serialize};
 use tracing::Level;
 use tracing_subscriber_init::{TracingConfig, get_effective_level};
 
+#[cfg(test)]
+pub(crate) use self::retention::RetentionRule;
+pub(crate) use self::{
+    alerts::{AlertRule, AlertTarget, Alerts, Smtp, SmtpTls},
+    database::{Backend, Postgres, Sqlite},
+    missed_runs::MissedRuns,
+    offline_clients::OfflineClients,
+    retention::Retention,
+};
+
 #[derive(
     Clone, CopyGetters, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize, Setters,
 )]
@@ -30,26 +46,100 @@ pub(crate) struct Config {
     actix: Actix,
     #[getset(get = "pub(crate)")]
     schedules: BTreeMap<String, Schedules>,
+    /// The database backend to store output and runs in, `mariadb` by default
+    #[getset(get_copy = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    backend: Backend,
     #[getset(get = "pub(crate)")]
+    #[serde(default)]
     mariadb: Mariadb,
+    /// The `SQLite` database, required when `backend = "sqlite"`
+    #[getset(get = "pub(crate)")]
+    #[serde(default)]
+    sqlite: Option<Sqlite>,
+    /// The `PostgreSQL` database, required when `backend = "postgres"`
+    #[getset(get = "pub(crate)")]
+    #[serde(default)]
+    postgres: Option<Postgres>,
+    /// How long runs and their output are kept, and when that is enforced
+    #[getset(get = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    retention: Retention,
+    /// Who is alerted about failing runs, and how
+    #[getset(get = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    alerts: Alerts,
+    /// How runs that were expected but never reported are found
+    #[getset(get_copy = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    missed_runs: MissedRuns,
+    /// How known clients that stay disconnected are found
+    #[getset(get_copy = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    offline_clients: OfflineClients,
     /// Optional base64-encoded Ed25519 private key for signing outgoing messages to bartoc.
     /// When set, all `BartosToBartoc` messages are prefixed with a 64-byte Ed25519 signature.
     #[getset(get = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
     #[serde(default)]
     signing_key: Option<String>,
+    /// Optional key ID for `signing_key`. When set, the signature prefix carries this ID so
+    /// bartoc can pick the matching key from its `server_public_keys` list during a rotation.
+    #[getset(get_copy = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    signing_key_id: Option<u32>,
     /// Optional shared secret for HMAC-SHA256 authentication of outgoing messages to bartoc.
     /// When set, all `BartosToBartoc` messages are wrapped in an authenticated envelope
     /// containing a timestamp, random nonce, and HMAC-SHA256 MAC.
     /// Must match `hmac_key` in bartoc.toml.
     #[getset(get = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
     #[serde(default)]
     hmac_key: Option<String>,
-    /// Optional pre-shared token for Bearer authentication on the WebSocket upgrade.
-    /// When set, incoming upgrade requests from bartoc and barto-cli must carry
+    /// Optional key ID for `hmac_key`. When set, the HMAC envelope carries this ID so bartoc
+    /// can pick the matching key from its `hmac_keys` list during a rotation.
+    #[getset(get_copy = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    hmac_key_id: Option<u32>,
+    /// Optional pre-shared token for Bearer authentication on the WebSocket upgrade and the
+    /// REST API. When set, incoming requests from bartoc, barto-cli and API callers must carry
     /// `Authorization: Bearer <api_key>`. Connections with wrong or missing tokens are rejected.
     #[getset(get = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
     #[serde(default)]
     api_key: Option<String>,
+    /// When `true`, clients missing from the client registry are rejected even when they
+    /// present a valid `api_key`. Enroll clients with `barto-cli clients enroll`.
+    #[getset(get_copy = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    require_enrollment: bool,
+    /// Names of enrolled barto-cli clients allowed to enroll and revoke other clients.
+    /// Connections authenticated with the shared `api_key` are always allowed.
+    #[getset(get = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    admin_clients: Vec<String>,
+    /// Names of enrolled barto-cli clients allowed to run raw SQL with `barto-cli query --raw`.
+    /// No other connection may, not even one authenticated with the shared `api_key`.
+    #[getset(get = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    raw_query_clients: Vec<String>,
+    /// When `true`, a client's name is taken from its verified mutual TLS certificate (CN or
+    /// DNS SAN) and a mismatching `?name=` is refused. Requires `actix.tls.client_ca_cert`;
+    /// connections without a client certificate are rejected.
+    #[getset(get_copy = "pub(crate)")]
+    #[cfg_attr(test, getset(set = "pub(crate)"))]
+    #[serde(default)]
+    bind_name_to_cert: bool,
 }
 
 impl TracingConfig for Config {
@@ -101,7 +191,7 @@ mod tests {
     use libbarto::TracingConfigExt;
     use tracing_subscriber_init::{TracingConfig, get_effective_level};
 
-    use super::Config;
+    use super::{Backend, Config};
 
     #[test]
     fn defaults() {
@@ -112,7 +202,20 @@ mod tests {
         assert!(config.schedules().is_empty());
         assert!(config.signing_key().is_none());
         assert!(config.hmac_key().is_none());
+        assert!(config.signing_key_id().is_none());
+        assert!(config.hmac_key_id().is_none());
         assert!(config.api_key().is_none());
+        assert!(!config.require_enrollment());
+        assert!(config.admin_clients().is_empty());
+        assert!(config.raw_query_clients().is_empty());
+        assert!(!config.bind_name_to_cert());
+        assert_eq!(config.backend(), Backend::Mariadb);
+        assert!(config.sqlite().is_none());
+        assert!(config.postgres().is_none());
+        assert_eq!(config.retention().days(), 30);
+        assert!(config.alerts().rules().is_empty());
+        assert!(config.missed_runs().enabled());
+        assert_eq!(config.offline_clients().threshold_secs(), 600);
     }
 
     #[test]
@@ -0,0 +1,58 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use bon::Builder;
+use getset::CopyGetters;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_THRESHOLD_SECS: u64 = 600;
+const DEFAULT_CHECK_SECS: u64 = 30;
+
+/// How `bartos` watches for known clients that stay offline, configured by the
+/// `[offline_clients]` section
+#[derive(Builder, Clone, Copy, CopyGetters, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[getset(get_copy = "pub(crate)")]
+#[serde(default)]
+pub(crate) struct OfflineClients {
+    /// Whether known clients are watched at all
+    #[builder(default = true)]
+    enabled: bool,
+    /// Seconds a known client may be disconnected before it counts as offline
+    #[builder(default = DEFAULT_THRESHOLD_SECS)]
+    threshold_secs: u64,
+    /// Seconds between checks for clients that are offline
+    #[builder(default = DEFAULT_CHECK_SECS)]
+    check_secs: u64,
+}
+
+impl Default for OfflineClients {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OfflineClients;
+
+    #[test]
+    fn defaults() {
+        let offline_clients = OfflineClients::default();
+        assert!(offline_clients.enabled());
+        assert_eq!(offline_clients.threshold_secs(), 600);
+        assert_eq!(offline_clients.check_secs(), 30);
+    }
+
+    #[test]
+    fn deserializes_partial_section() {
+        let offline_clients: OfflineClients =
+            serde_json::from_str(r#"{"threshold_secs":120}"#).unwrap();
+        assert!(offline_clients.enabled());
+        assert_eq!(offline_clients.threshold_secs(), 120);
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_DAYS: u32 = 30;
+const DEFAULT_BATCH_SIZE: u32 = 1_000;
+
+/// How long `bartos` keeps runs and their output, configured by the `[retention]` section
+#[derive(Builder, Clone, CopyGetters, Debug, Deserialize, Eq, Getters, PartialEq, Serialize)]
+#[serde(default)]
+pub(crate) struct Retention {
+    /// Days to keep a run after it last reported
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default = DEFAULT_DAYS)]
+    days: u32,
+    /// Days to keep a failed run, `days` when unset
+    #[getset(get_copy = "pub(crate)")]
+    failed_days: Option<u32>,
+    /// The newest runs of each client schedule that are kept regardless of age
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default)]
+    keep_last: u32,
+    /// When the retention rules are enforced, in `on_calendar` format. When unset they are
+    /// only enforced by `barto-cli cleanup`.
+    #[getset(get = "pub(crate)")]
+    on_calendar: Option<String>,
+    /// Runs deleted per statement, so a large cleanup does not lock the tables for long
+    #[getset(get_copy = "pub(crate)")]
+    #[builder(default = DEFAULT_BATCH_SIZE)]
+    batch_size: u32,
+    /// Rules for a bartoc client, by client name
+    #[getset(get = "pub(crate)")]
+    #[builder(default)]
+    clients: BTreeMap<String, RetentionRule>,
+    /// Rules for a schedule, by schedule name, taking precedence over the client rules
+    #[getset(get = "pub(crate)")]
+    #[builder(default)]
+    schedules: BTreeMap<String, RetentionRule>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Overrides for some of the `[retention]` settings
+#[derive(
+    Builder, Clone, Copy, CopyGetters, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+#[getset(get_copy = "pub(crate)")]
+pub(crate) struct RetentionRule {
+    /// Days to keep a run after it last reported
+    days: Option<u32>,
+    /// Days to keep a failed run
+    failed_days: Option<u32>,
+    /// The newest runs that are kept regardless of age
+    keep_last: Option<u32>,
+}
+
+/// The settings in effect for one client schedule
+#[derive(Clone, Copy, CopyGetters, Debug, Eq, PartialEq)]
+#[getset(get_copy = "pub(crate)")]
+pub(crate) struct Policy {
+    /// Days to keep a run after it last reported
+    days: u32,
+    /// Days to keep a failed run
+    failed_days: u32,
+    /// The newest runs that are kept regardless of age
+    keep_last: u32,
+}
+
+impl Retention {
+    /// Resolve the settings for a client schedule. A schedule rule wins over a client
+    /// rule, which wins over the global settings, one setting at a time.
+    pub(crate) fn policy(&self, bartoc_name: &str, schedule_name: &str) -> Policy {
+        let rules = [
+            self.schedules.get(schedule_name),
+            self.clients.get(bartoc_name),
+        ];
+        let pick = |get: fn(&RetentionRule) -> Option<u32>| {
+            rules.iter().flatten().find_map(|rule| get(rule))
+        };
+        let days = pick(RetentionRule::days).unwrap_or(self.days);
+        let failed_days = pick(RetentionRule::failed_days)
+            .or(self.failed_days)
+            .unwrap_or(days);
+        Policy {
+            days,
+            failed_days,
+            keep_last: pick(RetentionRule::keep_last).unwrap_or(self.keep_last),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{Policy, Retention, RetentionRule};
+
+    #[test]
+    fn defaults() {
+        let retention = Retention::default();
+        assert_eq!(retention.days(), 30);
+        assert_eq!(retention.batch_size(), 1_000);
+        assert!(retention.on_calendar().is_none());
+        assert_eq!(
+            retention.policy("host", "backup"),
+            Policy {
+                days: 30,
+                failed_days: 30,
+                keep_last: 0
+            }
+        );
+    }
+
+    #[test]
+    fn deserializes_partial_section() {
+        let retention: Retention =
+            serde_json::from_str(r#"{"days":7,"clients":{"host":{"keep_last":3}}}"#).unwrap();
+        assert_eq!(retention.days(), 7);
+        assert_eq!(retention.batch_size(), 1_000);
+        assert_eq!(retention.policy("host", "backup").keep_last(), 3);
+    }
+
+    #[test]
+    fn schedule_wins_over_client_wins_over_global() {
+        let retention = Retention::builder()
+            .days(14)
+            .failed_days(60)
+            .keep_last(1)
+            .clients(BTreeMap::from([(
+                "host".to_string(),
+                RetentionRule::builder().days(7).keep_last(5).build(),
+            )]))
+            .schedules(BTreeMap::from([(
+                "backup".to_string(),
+                RetentionRule::builder().days(90).build(),
+            )]))
+            .build();
+        assert_eq!(
+            retention.policy("host", "backup"),
+            Policy {
+                days: 90,
+                failed_days: 60,
+                keep_last: 5
+            }
+        );
+        assert_eq!(
+            retention.policy("host", "update"),
+            Policy {
+                days: 7,
+                failed_days: 60,
+                keep_last: 5
+            }
+        );
+        assert_eq!(
+            retention.policy("other", "update"),
+            Policy {
+                days: 14,
+                failed_days: 60,
+                keep_last: 1
+            }
+        );
+    }
+
+    #[test]
+    fn failed_days_follow_days_when_unset() {
+        let retention = Retention::builder()
+            .days(10)
+            .schedules(BTreeMap::from([(
+                "backup".to_string(),
+                RetentionRule::builder().days(3).build(),
+            )]))
+            .build();
+        assert_eq!(retention.policy("host", "backup").failed_days(), 3);
+        assert_eq!(retention.policy("host", "update").failed_days(), 10);
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Output is stored as chunks of lines rather than as a row per line. New lines of a run are
+//! inserted as open chunks, which hold the lines uncompressed, so storing them never reads or
+//! rewrites the output already stored. Once the open chunks of a run hold
+//! [`MAX_CHUNK_LINES`] lines or [`MAX_CHUNK_BYTES`] bytes, or the run ends, they are sealed:
+//! merged into zstd-compressed chunks of at most that size. Each chunk records the offset of
+//! its first line in the run.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bincode_next::{config::standard, decode_from_slice, encode_to_vec};
+use libbarto::{OffsetDataTimeWrapper, Output, OutputKind, compress, decompress, search_words};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// The most lines one chunk holds
+pub(crate) const MAX_CHUNK_LINES: usize = 1_000;
+/// The most output bytes one chunk holds, well inside what `decompress` accepts
+pub(crate) const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// One output line as stored in a chunk
+pub(crate) type ChunkLine = (OffsetDataTimeWrapper, OutputKind, String);
+
+/// The decoded lines of one chunk
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Chunk {
+    lines: Vec<ChunkLine>,
+    bytes: usize,
+}
+
+impl Chunk {
+    /// Decode a stored chunk, decompressing it first if it is sealed
+    pub(crate) fn decode(data: &[u8], sealed: bool) -> Result<Self> {
+        let (lines, _): (Vec<ChunkLine>, usize) = if sealed {
+            decode_from_slice(&decompress(data)?, standard())?
+        } else {
+            decode_from_slice(data, standard())?
+        };
+        let bytes = lines.iter().map(|(_, _, data)| data.len()).sum();
+        Ok(Self { lines, bytes })
+    }
+
+    /// Encode the chunk for storage, compressing it if it is sealed
+    pub(crate) fn encode(&self, sealed: bool) -> Result<Vec<u8>> {
+        let encoded = encode_to_vec(&self.lines, standard())?;
+        if sealed {
+            compress(&encoded)
+        } else {
+            Ok(encoded)
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.lines.len() >= MAX_CHUNK_LINES || self.bytes >= MAX_CHUNK_BYTES
+    }
+
+    fn push(&mut self, line: ChunkLine) {
+        self.bytes += line.2.len();
+        self.lines.push(line);
+    }
+
+    pub(crate) fn into_lines(self) -> Vec<ChunkLine> {
+        self.lines
+    }
+
+    pub(crate) fn line_count(&self) -> i64 {
+        i64::try_from(self.lines.len()).unwrap_or(i64::MAX)
+    }
+
+    /// The earliest line, which bounds the chunk for time filters
+    pub(crate) fn first_at(&self) -> OffsetDateTime {
+        self.lines
+            .iter()
+            .map(|(at, _, _)| at.0)
+            .min()
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+    }
+
+    /// The latest line, which bounds the chunk for time filters
+    pub(crate) fn last_at(&self) -> OffsetDateTime {
+        self.lines
+            .iter()
+            .map(|(at, _, _)| at.0)
+            .max()
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+    }
+
+    /// The distinct words of the chunk, which the full-text indexes are built on since the
+    /// compressed lines cannot be indexed
+    pub(crate) fn words(&self) -> String {
+        let mut seen = HashSet::new();
+        let mut words = vec![];
+        for (_, _, data) in &self.lines {
+            for word in search_words(data) {
+                if seen.insert(word.clone()) {
+                    words.push(word);
+                }
+            }
+        }
+        words.join(" ")
+    }
+}
+
+/// The client and schedule the chunks of a run are stored under
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ChunkOwner {
+    pub(crate) bartoc_uuid: Uuid,
+    pub(crate) bartoc_name: String,
+    pub(crate) cmd_name: String,
+}
+
+impl From<&Output> for ChunkOwner {
+    fn from(output: &Output) -> Self {
+        Self {
+            bartoc_uuid: output.bartoc_uuid().0,
+            bartoc_name: output.bartoc_name().clone(),
+            cmd_name: output.cmd_name().clone(),
+        }
+    }
+}
+
+/// The line of an output as a chunk stores it
+pub(crate) fn chunk_line(output: &Output) -> ChunkLine {
+    (output.timestamp(), output.kind(), output.data().clone())
+}
+
+/// Split lines into as many chunks as they need, the first starting at line `first_line` of
+/// the run. Returns each chunk with the offset of its first line.
+pub(crate) fn pack(
+    first_line: i64,
+    lines: impl IntoIterator<Item = ChunkLine>,
+) -> Vec<(i64, Chunk)> {
+    let mut chunks: Vec<(i64, Chunk)> = vec![];
+    for (line_no, line) in (first_line..).zip(lines) {
+        if chunks.last().is_none_or(|(_, chunk)| chunk.is_full()) {
+            chunks.push((line_no, Chunk::default()));
+        }
+        if let Some((_, chunk)) = chunks.last_mut() {
+            chunk.push(line);
+        }
+    }
+    chunks
+}
+
+/// Merge the open chunks of a run, in line order, into sealed chunks starting where the
+/// first of them starts
+pub(crate) fn seal(open: Vec<(i64, Chunk)>) -> Vec<(i64, Chunk)> {
+    let Some(first_line) = open.first().map(|(first_line, _)| *first_line) else {
+        return vec![];
+    };
+    pack(
+        first_line,
+        open.into_iter().flat_map(|(_, chunk)| chunk.into_lines()),
+    )
+}
+
+/// Whether the open chunks of a run, holding `lines` lines in `bytes` stored bytes, are big
+/// enough to seal
+pub(crate) fn should_seal(lines: i64, bytes: i64) -> bool {
+    usize::try_from(lines).unwrap_or(usize::MAX) >= MAX_CHUNK_LINES
+        || usize::try_from(bytes).unwrap_or(usize::MAX) >= MAX_CHUNK_BYTES
+}
+
+/// The kind of a row of the output table written before output was chunked, which only ever
+/// holds `stdout` or `stderr`
+pub(crate) fn legacy_kind(kind: &str) -> OutputKind {
+    if kind == "stderr" {
+        OutputKind::Stderr
+    } else {
+        OutputKind::Stdout
+    }
+}
+
+/// The outputs of each run, in the order they arrived
+pub(crate) fn by_run(outputs: &[Output]) -> Vec<(Uuid, Vec<&Output>)> {
+    let mut runs: Vec<(Uuid, Vec<&Output>)> = vec![];
+    for output in outputs {
+        let cmd_uuid = output.cmd_uuid().0;
+        match runs.iter_mut().find(|(run, _)| *run == cmd_uuid) {
+            Some((_, lines)) => lines.push(output),
+            None => runs.push((cmd_uuid, vec![output])),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use libbarto::{OffsetDataTimeWrapper, Output, OutputKind, UuidWrapper};
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use super::{Chunk, MAX_CHUNK_LINES, by_run, chunk_line, pack, seal, should_seal};
+
+    fn output(cmd_uuid: Uuid, secs: i64, data: &str) -> Output {
+        Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::nil()))
+            .bartoc_name("host".to_string())
+            .timestamp(OffsetDataTimeWrapper(
+                OffsetDateTime::UNIX_EPOCH + Duration::seconds(secs),
+            ))
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .cmd_name("update".to_string())
+            .kind(OutputKind::Stdout)
+            .data(data.to_string())
+            .build()
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let run = Uuid::new_v4();
+        let outputs = [
+            output(run, 2, "Error: disk full"),
+            output(run, 1, "disk ok"),
+        ];
+        let chunks = pack(0, outputs.iter().map(chunk_line));
+        assert_eq!(chunks.len(), 1);
+        let (first_line, chunk) = &chunks[0];
+        assert_eq!(*first_line, 0);
+        assert_eq!(
+            chunk.first_at(),
+            OffsetDateTime::UNIX_EPOCH + Duration::seconds(1)
+        );
+        assert_eq!(
+            chunk.last_at(),
+            OffsetDateTime::UNIX_EPOCH + Duration::seconds(2)
+        );
+        assert_eq!(chunk.words(), "error disk full ok");
+        for sealed in [true, false] {
+            let decoded = Chunk::decode(&chunk.encode(sealed).unwrap(), sealed).unwrap();
+            assert_eq!(&decoded, chunk);
+        }
+        // Only a sealed chunk is compressed.
+        assert!(Chunk::decode(&chunk.encode(false).unwrap(), true).is_err());
+        let decoded = Chunk::decode(&chunk.encode(true).unwrap(), true).unwrap();
+        assert_eq!(decoded.into_lines()[1].2, "disk ok");
+        assert!(Chunk::decode(b"not zstd", true).is_err());
+    }
+
+    #[test]
+    fn packs_lines_into_full_chunks() {
+        let run = Uuid::new_v4();
+        let outputs: Vec<_> = (0..=MAX_CHUNK_LINES)
+            .map(|line| output(run, 0, &format!("line {line}")))
+            .collect();
+        let chunks = pack(10, outputs.iter().map(chunk_line));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0, 10);
+        assert_eq!(chunks[0].1.line_count(), MAX_CHUNK_LINES as i64);
+        assert_eq!(chunks[1].0, 10 + MAX_CHUNK_LINES as i64);
+        assert_eq!(chunks[1].1.line_count(), 1);
+        assert!(pack(0, vec![]).is_empty());
+    }
+
+    #[test]
+    fn seals_open_chunks_in_line_order() {
+        let run = Uuid::new_v4();
+        let open: Vec<_> = (0..3)
+            .map(|batch| {
+                let lines = [output(run, batch, "a"), output(run, batch, "b")];
+                pack(5 + batch * 2, lines.iter().map(chunk_line)).remove(0)
+            })
+            .collect();
+        let sealed = seal(open);
+        assert_eq!(sealed.len(), 1);
+        assert_eq!(sealed[0].0, 5);
+        assert_eq!(sealed[0].1.line_count(), 6);
+        assert!(seal(vec![]).is_empty());
+
+        assert!(!should_seal(MAX_CHUNK_LINES as i64 - 1, 10));
+        assert!(should_seal(MAX_CHUNK_LINES as i64, 10));
+        assert!(should_seal(1, 1024 * 1024));
+    }
+
+    #[test]
+    fn outputs_are_grouped_by_run_in_order() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let outputs = [output(a, 0, "a1"), output(b, 0, "b1"), output(a, 1, "a2")];
+        let runs = by_run(&outputs);
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].0, a);
+        let data: Vec<_> = runs[0].1.iter().map(|o| o.data().as_str()).collect();
+        assert_eq!(data, vec!["a1", "a2"]);
+    }
+}
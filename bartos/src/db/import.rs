@@ -0,0 +1,145 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Loading a `barto-cli export` into a store. An export holds the rows of each run together,
+//! so the rows are read a run at a time and each run is inserted in one go, or skipped when
+//! the store already has a run with its UUID.
+
+use anyhow::Result;
+use libbarto::{ExportRow, OffsetDataTimeWrapper, Output, UuidWrapper};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::db::Queryable;
+
+/// What an import did
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Imported {
+    /// Runs inserted
+    pub(crate) runs: u64,
+    /// Output lines inserted with them
+    pub(crate) lines: u64,
+    /// Runs skipped because the store already had them
+    pub(crate) skipped: u64,
+}
+
+/// Import the rows of an export in the order they were written
+pub(crate) async fn import<Q: Queryable>(
+    queryable: &Q,
+    rows: impl IntoIterator<Item = Result<ExportRow>>,
+) -> Result<Imported> {
+    let mut imported = Imported::default();
+    let mut run: Option<(ExportRow, Vec<Output>)> = None;
+    for row in rows {
+        let row = row?;
+        if let Some((first, outputs)) = run.take_if(|(first, _)| first.cmd_uuid() != row.cmd_uuid())
+        {
+            import_run(queryable, &first, &outputs, &mut imported).await?;
+        }
+        let output = output(&row);
+        let (_, outputs) = run.get_or_insert_with(|| (row, vec![]));
+        outputs.extend(output);
+    }
+    if let Some((first, outputs)) = run {
+        import_run(queryable, &first, &outputs, &mut imported).await?;
+    }
+    Ok(imported)
+}
+
+async fn import_run<Q: Queryable>(
+    queryable: &Q,
+    run: &ExportRow,
+    outputs: &[Output],
+    imported: &mut Imported,
+) -> Result<()> {
+    if queryable.import_run(run, outputs).await? {
+        imported.runs += 1;
+        imported.lines += u64::try_from(outputs.len())?;
+    } else {
+        imported.skipped += 1;
+    }
+    Ok(())
+}
+
+/// The output line of a row, unless the row is a run without output
+fn output(row: &ExportRow) -> Option<Output> {
+    let kind = row.kind()?;
+    let timestamp = row
+        .timestamp()
+        .or(row.started_at())
+        .unwrap_or(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH));
+    Some(
+        Output::builder()
+            .bartoc_uuid(row.bartoc_uuid().unwrap_or(UuidWrapper(Uuid::nil())))
+            .bartoc_name(row.bartoc_name().clone().unwrap_or_default())
+            .timestamp(timestamp)
+            .cmd_uuid(row.cmd_uuid())
+            .cmd_name(row.schedule_name().clone().unwrap_or_default())
+            .kind(kind)
+            .data(row.data().clone().unwrap_or_default())
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use libbarto::{ExportFilter, ExportRow, OffsetDataTimeWrapper, OutputKind, UuidWrapper};
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use super::{Imported, import};
+    use crate::db::{Queryable, memory::MemoryHandler};
+
+    fn row(cmd_uuid: Uuid, line: Option<&str>) -> ExportRow {
+        let at = OffsetDateTime::UNIX_EPOCH + Duration::days(1);
+        ExportRow::builder()
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .bartoc_uuid(UuidWrapper(Uuid::from_u128(7)))
+            .bartoc_name("host".to_string())
+            .schedule_name("update".to_string())
+            .started_at(OffsetDataTimeWrapper(at))
+            .ended_at(OffsetDataTimeWrapper(at + Duration::seconds(3)))
+            .exit_code(0)
+            .success(true)
+            .maybe_timestamp(line.map(|_| OffsetDataTimeWrapper(at)))
+            .maybe_kind(line.map(|_| OutputKind::Stdout))
+            .maybe_data(line.map(str::to_string))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn runs_are_imported_once() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let rows = vec![row(a, Some("one")), row(a, Some("two")), row(b, None)];
+        let store = MemoryHandler::default();
+        let imported = import(&store, rows.clone().into_iter().map(Ok))
+            .await
+            .unwrap();
+        assert_eq!(
+            imported,
+            Imported {
+                runs: 2,
+                lines: 2,
+                skipped: 0
+            }
+        );
+        let mut exported = store.export(&ExportFilter::default()).await.unwrap();
+        exported.sort_by_key(|row| row.cmd_uuid() != UuidWrapper(a));
+        assert_eq!(exported, rows);
+
+        let again = import(&store, rows.into_iter().map(Ok)).await.unwrap();
+        assert_eq!(
+            again,
+            Imported {
+                runs: 0,
+                lines: 0,
+                skipped: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,724 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! An in-memory [`Queryable`] that follows the SQL backends closely enough for the
+//! handler tests, without a database.
+
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use anyhow::{Result, anyhow};
+use libbarto::{
+    CliUpdateKind, ClientEvent, ClientEventFilter, ExportFilter, ExportRow, FailedOutput,
+    ListOutput, OffsetDataTimeWrapper, Output, QueryFilter, QueryRow, RunStart, RunState,
+    SearchFilter, SearchHit, Status, UpdateKind, UuidWrapper, search_words,
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    common::ClientCredential,
+    db::{
+        Cutoffs, DEFAULT_EXPORT_LIMIT, DEFAULT_HISTORY_LIMIT, DEFAULT_QUERY_LIMIT,
+        DEFAULT_SEARCH_LIMIT, MAX_EXPORT_LIMIT, MAX_HISTORY_LIMIT, MAX_QUERY_LIMIT,
+        MAX_SEARCH_LIMIT, Queryable, RunGroup, SearchLine, search_hits, search_runs,
+        utils::{apt_filter, cachyos_filter, garuda_filter, pacman_filter, wire_success},
+    },
+};
+
+/// One row of the `runs` table
+#[derive(Clone, Debug, Default)]
+struct Run {
+    bartoc_uuid: Option<Uuid>,
+    bartoc_name: Option<String>,
+    schedule_name: Option<String>,
+    cmd: Option<String>,
+    started_at: Option<OffsetDateTime>,
+    ended_at: Option<OffsetDateTime>,
+    exit_code: Option<i32>,
+    exit_signal: Option<i32>,
+    core_dumped: Option<bool>,
+    wait_status: Option<i32>,
+    success: Option<bool>,
+}
+
+impl Run {
+    /// When the run last reported: its end, or its start while it is still going
+    fn at(&self) -> Option<OffsetDateTime> {
+        self.ended_at.or(self.started_at)
+    }
+}
+
+/// A run joined with one of its output lines, or with none when it has no output
+struct Joined<'a> {
+    cmd_uuid: Uuid,
+    run: &'a Run,
+    output: Option<&'a Output>,
+}
+
+impl Joined<'_> {
+    fn bartoc_name(&self) -> Option<&str> {
+        self.run
+            .bartoc_name
+            .as_deref()
+            .or(self.output.map(|o| o.bartoc_name().as_str()))
+    }
+
+    fn schedule_name(&self) -> Option<&str> {
+        self.run
+            .schedule_name
+            .as_deref()
+            .or(self.output.map(|o| o.cmd_name().as_str()))
+    }
+
+    fn at(&self) -> Option<OffsetDateTime> {
+        self.output.map(|o| o.timestamp().0).or(self.run.started_at)
+    }
+
+    fn list_output(&self) -> ListOutput {
+        ListOutput::builder()
+            .maybe_timestamp(self.output.map(|o| o.timestamp()))
+            .maybe_data(self.output.map(|o| o.data().clone()))
+            .maybe_exit_code(self.run.exit_code)
+            .maybe_exit_signal(self.run.exit_signal)
+            .core_dumped(self.run.core_dumped.unwrap_or_default())
+            .success(wire_success(self.run.success))
+            .build()
+    }
+
+    /// Whether this row passes `filter`, with `text` already lowercased
+    fn matches(&self, filter: &QueryFilter, text: Option<&str>) -> bool {
+        let at = self.at();
+        filter
+            .client()
+            .as_deref()
+            .is_none_or(|client| self.bartoc_name() == Some(client))
+            && filter
+                .schedule()
+                .as_deref()
+                .is_none_or(|schedule| self.schedule_name() == Some(schedule))
+            && filter
+                .since()
+                .is_none_or(|since| at.is_some_and(|at| at >= since.0))
+            && filter
+                .until()
+                .is_none_or(|until| at.is_some_and(|at| at < until.0))
+            && match filter.state() {
+                Some(RunState::Succeeded) => self.run.success == Some(true),
+                Some(RunState::Failed) => self.run.success == Some(false),
+                Some(RunState::Running) => self.run.ended_at.is_none(),
+                None => true,
+            }
+            && text.is_none_or(|text| {
+                self.output
+                    .is_some_and(|o| o.data().to_lowercase().contains(text))
+            })
+    }
+
+    fn export_row(&self) -> ExportRow {
+        ExportRow::builder()
+            .cmd_uuid(UuidWrapper(self.cmd_uuid))
+            .maybe_bartoc_uuid(
+                self.run
+                    .bartoc_uuid
+                    .or(self.output.map(|o| o.bartoc_uuid().0))
+                    .map(UuidWrapper),
+            )
+            .maybe_bartoc_name(self.bartoc_name().map(str::to_string))
+            .maybe_schedule_name(self.schedule_name().map(str::to_string))
+            .maybe_cmd(self.run.cmd.clone())
+            .maybe_started_at(self.run.started_at.map(OffsetDataTimeWrapper))
+            .maybe_ended_at(self.run.ended_at.map(OffsetDataTimeWrapper))
+            .maybe_exit_code(self.run.exit_code)
+            .maybe_exit_signal(self.run.exit_signal)
+            .maybe_core_dumped(self.run.core_dumped)
+            .maybe_wait_status(self.run.wait_status)
+            .maybe_success(self.run.success)
+            .maybe_timestamp(self.output.map(|o| o.timestamp()))
+            .maybe_kind(self.output.map(|o| o.kind()))
+            .maybe_data(self.output.map(|o| o.data().clone()))
+            .build()
+    }
+
+    fn query_row(&self) -> QueryRow {
+        QueryRow::builder()
+            .cmd_uuid(UuidWrapper(self.cmd_uuid))
+            .maybe_timestamp(self.at().map(OffsetDataTimeWrapper))
+            .maybe_bartoc_name(self.bartoc_name().map(str::to_string))
+            .maybe_schedule_name(self.schedule_name().map(str::to_string))
+            .maybe_data(self.output.map(|o| o.data().clone()))
+            .maybe_exit_code(self.run.exit_code)
+            .maybe_exit_signal(self.run.exit_signal)
+            .core_dumped(self.run.core_dumped.unwrap_or_default())
+            .success(wire_success(self.run.success))
+            .build()
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    outputs: Vec<Output>,
+    runs: BTreeMap<Uuid, Run>,
+    clients: BTreeMap<String, ClientCredential>,
+    client_events: Vec<ClientEvent>,
+}
+
+impl State {
+    /// `runs r LEFT JOIN output o ON o.cmd_uuid = r.cmd_uuid`, ordered like the SQL backends
+    fn joined(&self) -> Vec<Joined<'_>> {
+        let mut joined = vec![];
+        for (cmd_uuid, run) in &self.runs {
+            let len = joined.len();
+            joined.extend(
+                self.outputs
+                    .iter()
+                    .filter(|o| o.cmd_uuid().0 == *cmd_uuid)
+                    .map(|output| Joined {
+                        cmd_uuid: *cmd_uuid,
+                        run,
+                        output: Some(output),
+                    }),
+            );
+            if joined.len() == len {
+                joined.push(Joined {
+                    cmd_uuid: *cmd_uuid,
+                    run,
+                    output: None,
+                });
+            }
+        }
+        joined.sort_by_key(Joined::at);
+        joined
+    }
+
+    /// Output lines containing `words` in order, like the full-text indexes, newest first. An
+    /// output line's id is its position.
+    fn search_lines(&self, filter: &SearchFilter, words: &[String]) -> Vec<SearchLine> {
+        let mut lines: Vec<SearchLine> = self
+            .outputs
+            .iter()
+            .zip(0..)
+            .filter(|(output, _)| {
+                let at = output.timestamp().0;
+                let schedule_name = self.schedule_name(output);
+                search_words(output.data())
+                    .windows(words.len())
+                    .any(|window| window == words)
+                    && filter
+                        .client()
+                        .as_ref()
+                        .is_none_or(|client| output.bartoc_name() == client)
+                    && filter
+                        .schedule()
+                        .as_deref()
+                        .is_none_or(|schedule| schedule_name == Some(schedule))
+                    && filter.since().is_none_or(|since| at >= since.0)
+                    && filter.until().is_none_or(|until| at < until.0)
+            })
+            .map(|(output, id)| {
+                SearchLine::builder()
+                    .id(id)
+                    .cmd_uuid(output.cmd_uuid().0)
+                    .timestamp(output.timestamp().0)
+                    .bartoc_name(output.bartoc_name().clone())
+                    .maybe_schedule_name(self.schedule_name(output).map(str::to_string))
+                    .data(output.data().clone())
+                    .build()
+            })
+            .collect();
+        lines.sort_by_key(|line| Reverse((line.timestamp, line.id)));
+        let limit = filter
+            .limit()
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .min(MAX_SEARCH_LIMIT);
+        lines.truncate(limit as usize);
+        lines
+    }
+
+    /// Every output line of the given runs as `(id, cmd_uuid, data)`, in the order written
+    fn run_lines(&self, cmd_uuids: &[Uuid]) -> Vec<(i64, Uuid, String)> {
+        let mut lines: Vec<(OffsetDateTime, i64, Uuid, String)> = self
+            .outputs
+            .iter()
+            .zip(0..)
+            .filter(|(output, _)| cmd_uuids.contains(&output.cmd_uuid().0))
+            .map(|(output, id)| {
+                (
+                    output.timestamp().0,
+                    id,
+                    output.cmd_uuid().0,
+                    output.data().clone(),
+                )
+            })
+            .collect();
+        lines.sort_by_key(|(at, id, _, _)| (*at, *id));
+        lines
+            .into_iter()
+            .map(|(_, id, cmd_uuid, data)| (id, cmd_uuid, data))
+            .collect()
+    }
+
+    /// `COALESCE(r.schedule_name, o.cmd_name)` for an output line
+    fn schedule_name<'a>(&'a self, output: &'a Output) -> Option<&'a str> {
+        self.runs
+            .get(&output.cmd_uuid().0)
+            .and_then(|run| run.schedule_name.as_deref())
+            .or(Some(output.cmd_name().as_str()))
+    }
+
+    fn group_runs<'a>(&'a self, group: &'a RunGroup) -> impl Iterator<Item = (&'a Uuid, &'a Run)> {
+        self.runs.iter().filter(|(_, run)| {
+            run.bartoc_name.as_deref().unwrap_or_default() == group.bartoc_name()
+                && run.schedule_name.as_deref().unwrap_or_default() == group.schedule_name()
+        })
+    }
+}
+
+/// A [`Queryable`] backed by maps behind a mutex. Clones share the same data.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MemoryHandler {
+    state: Arc<Mutex<State>>,
+}
+
+impl MemoryHandler {
+    fn state(&self) -> Result<MutexGuard<'_, State>> {
+        self.state
+            .lock()
+            .map_err(|_| anyhow!("memory store poisoned"))
+    }
+}
+
+impl Queryable for MemoryHandler {
+    async fn run_groups(&self) -> Result<Vec<RunGroup>> {
+        let state = self.state()?;
+        let mut groups: Vec<RunGroup> = state
+            .runs
+            .values()
+            .map(|run| {
+                RunGroup::builder()
+                    .bartoc_name(run.bartoc_name.clone().unwrap_or_default())
+                    .schedule_name(run.schedule_name.clone().unwrap_or_default())
+                    .build()
+            })
+            .collect();
+        groups.sort();
+        groups.dedup();
+        Ok(groups)
+    }
+
+    async fn nth_newest_run(&self, group: &RunGroup, n: u32) -> Result<Option<OffsetDateTime>> {
+        let state = self.state()?;
+        let mut ats: Vec<_> = state.group_runs(group).map(|(_, run)| run.at()).collect();
+        ats.sort_by(|a, b| b.cmp(a));
+        Ok(ats.get(n.saturating_sub(1) as usize).copied().flatten())
+    }
+
+    async fn expired_runs(
+        &self,
+        group: &RunGroup,
+        cutoffs: &Cutoffs,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        let state = self.state()?;
+        Ok(state
+            .group_runs(group)
+            .filter(|(_, run)| {
+                let Some(at) = run.at() else {
+                    return false;
+                };
+                let before = if run.success == Some(false) {
+                    cutoffs.failed_before()
+                } else {
+                    cutoffs.before()
+                };
+                at < before && cutoffs.keep_from().is_none_or(|keep_from| at < keep_from)
+            })
+            .map(|(cmd_uuid, _)| *cmd_uuid)
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn delete_runs(&self, cmd_uuids: &[Uuid]) -> Result<(u64, u64)> {
+        let mut state = self.state()?;
+        let before = state.outputs.len();
+        state
+            .outputs
+            .retain(|o| !cmd_uuids.contains(&o.cmd_uuid().0));
+        let output = before - state.outputs.len();
+        let before = state.runs.len();
+        state
+            .runs
+            .retain(|cmd_uuid, _| !cmd_uuids.contains(cmd_uuid));
+        let runs = before - state.runs.len();
+        Ok((output as u64, runs as u64))
+    }
+
+    async fn expired_orphans(&self, cutoff: OffsetDateTime, limit: u32) -> Result<Vec<Uuid>> {
+        let state = self.state()?;
+        let mut orphans: Vec<Uuid> = state
+            .outputs
+            .iter()
+            .filter(|o| o.timestamp().0 < cutoff && !state.runs.contains_key(&o.cmd_uuid().0))
+            .map(|o| o.cmd_uuid().0)
+            .collect();
+        orphans.sort();
+        orphans.dedup();
+        orphans.truncate(limit as usize);
+        Ok(orphans)
+    }
+
+    async fn delete_orphan_output(
+        &self,
+        cmd_uuids: &[Uuid],
+        cutoff: OffsetDateTime,
+    ) -> Result<u64> {
+        let mut state = self.state()?;
+        let before = state.outputs.len();
+        state
+            .outputs
+            .retain(|o| !(o.timestamp().0 < cutoff && cmd_uuids.contains(&o.cmd_uuid().0)));
+        Ok((before - state.outputs.len()) as u64)
+    }
+
+    async fn update_data(&self, kind: CliUpdateKind, name: &str) -> Result<UpdateKind> {
+        let data: Vec<String> = {
+            let state = self.state()?;
+            state
+                .joined()
+                .iter()
+                .filter(|j| j.bartoc_name() == Some(name) && j.run.exit_code == Some(0))
+                .filter_map(|j| j.output.map(|o| o.data().clone()))
+                .collect()
+        };
+        Ok(match kind {
+            CliUpdateKind::Garuda => UpdateKind::Garuda(garuda_filter(data)),
+            CliUpdateKind::Pacman => UpdateKind::Pacman(pacman_filter(&data)),
+            CliUpdateKind::Cachyos => UpdateKind::Cachyos(cachyos_filter(&data)),
+            CliUpdateKind::Apt => UpdateKind::Apt(apt_filter(&data)),
+        })
+    }
+
+    async fn cmd_data(&self, name: &str) -> Result<Vec<String>> {
+        let state = self.state()?;
+        let mut names: Vec<String> = state
+            .joined()
+            .iter()
+            .filter(|j| j.bartoc_name() == Some(name))
+            .filter_map(|j| j.schedule_name().map(str::to_string))
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    async fn cmd_data_by_name(&self, cmd_name: &str) -> Result<BTreeMap<String, Vec<ListOutput>>> {
+        let state = self.state()?;
+        let mut all_output: BTreeMap<String, Vec<ListOutput>> = BTreeMap::new();
+        for joined in state.joined() {
+            if joined.schedule_name() == Some(cmd_name)
+                && joined.run.ended_at.is_some()
+                && let Some(bartoc_name) = joined.bartoc_name()
+            {
+                all_output
+                    .entry(bartoc_name.to_string())
+                    .or_default()
+                    .push(joined.list_output());
+            }
+        }
+        Ok(all_output)
+    }
+
+    async fn cmd_name_data(&self, name: &str, cmd_name: &str) -> Result<Vec<ListOutput>> {
+        let state = self.state()?;
+        Ok(state
+            .joined()
+            .iter()
+            .filter(|j| {
+                j.bartoc_name() == Some(name)
+                    && j.schedule_name() == Some(cmd_name)
+                    && j.run.ended_at.is_some()
+            })
+            .map(Joined::list_output)
+            .collect())
+    }
+
+    async fn failed_cmd_data(&self) -> Result<Vec<FailedOutput>> {
+        let state = self.state()?;
+        Ok(state
+            .joined()
+            .iter()
+            .filter(|j| j.run.success == Some(false))
+            .map(|j| {
+                FailedOutput::builder()
+                    .maybe_timestamp(j.output.map(|o| o.timestamp()))
+                    .maybe_bartoc_name(j.bartoc_name().map(str::to_string))
+                    .maybe_cmd_name(j.schedule_name().map(str::to_string))
+                    .maybe_data(j.output.map(|o| o.data().clone()))
+                    .maybe_exit_code(j.run.exit_code)
+                    .maybe_exit_signal(j.run.exit_signal)
+                    .core_dumped(j.run.core_dumped.unwrap_or_default())
+                    .success(wire_success(j.run.success))
+                    .build()
+            })
+            .collect())
+    }
+
+    async fn query(&self, filter: &QueryFilter) -> Result<Vec<QueryRow>> {
+        let state = self.state()?;
+        let text = filter.text().as_deref().map(str::to_lowercase);
+        let limit = filter
+            .limit()
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        Ok(state
+            .joined()
+            .iter()
+            .filter(|j| j.matches(filter, text.as_deref()))
+            .skip(filter.offset() as usize)
+            .take(limit as usize)
+            .map(Joined::query_row)
+            .collect())
+    }
+
+    async fn raw_query(&self, _query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
+        Err(anyhow!("the memory store cannot run SQL queries"))
+    }
+
+    async fn search(&self, filter: &SearchFilter) -> Result<Vec<SearchHit>> {
+        let state = self.state()?;
+        let words = search_words(filter.text());
+        if words.is_empty() {
+            return Ok(vec![]);
+        }
+        let lines = state.search_lines(filter, &words);
+        let run_lines = state.run_lines(&search_runs(&lines));
+        Ok(search_hits(lines, run_lines, filter.context()))
+    }
+
+    async fn export(&self, filter: &ExportFilter) -> Result<Vec<ExportRow>> {
+        let state = self.state()?;
+        let mut runs: Vec<(&Uuid, &Run)> = state
+            .runs
+            .iter()
+            .filter(|(_, run)| {
+                filter
+                    .client()
+                    .as_ref()
+                    .is_none_or(|client| run.bartoc_name.as_ref() == Some(client))
+                    && filter
+                        .schedule()
+                        .as_ref()
+                        .is_none_or(|schedule| run.schedule_name.as_ref() == Some(schedule))
+                    && filter
+                        .since()
+                        .is_none_or(|since| run.started_at.is_some_and(|at| at >= since.0))
+                    && filter
+                        .until()
+                        .is_none_or(|until| run.started_at.is_some_and(|at| at < until.0))
+            })
+            .collect();
+        runs.sort_by_key(|(cmd_uuid, run)| (run.started_at.is_some(), run.started_at, **cmd_uuid));
+        let limit = filter
+            .limit()
+            .unwrap_or(DEFAULT_EXPORT_LIMIT)
+            .min(MAX_EXPORT_LIMIT);
+        let mut rows = vec![];
+        for (cmd_uuid, run) in runs
+            .into_iter()
+            .skip(filter.offset() as usize)
+            .take(limit as usize)
+        {
+            let joined = |output| Joined {
+                cmd_uuid: *cmd_uuid,
+                run,
+                output,
+            };
+            let len = rows.len();
+            rows.extend(
+                state
+                    .outputs
+                    .iter()
+                    .filter(|o| o.cmd_uuid().0 == *cmd_uuid)
+                    .map(|output| joined(Some(output)).export_row()),
+            );
+            if rows.len() == len {
+                rows.push(joined(None).export_row());
+            }
+        }
+        Ok(rows)
+    }
+
+    async fn run_output(&self, cmd_uuid: Uuid) -> Result<Vec<ExportRow>> {
+        let state = self.state()?;
+        let Some(run) = state.runs.get(&cmd_uuid) else {
+            return Ok(vec![]);
+        };
+        let joined = |output| Joined {
+            cmd_uuid,
+            run,
+            output,
+        };
+        let mut rows: Vec<ExportRow> = state
+            .outputs
+            .iter()
+            .filter(|o| o.cmd_uuid().0 == cmd_uuid)
+            .map(|output| joined(Some(output)).export_row())
+            .collect();
+        if rows.is_empty() {
+            rows.push(joined(None).export_row());
+        }
+        Ok(rows)
+    }
+
+    async fn run_tail(&self, cmd_uuid: Uuid, lines: usize) -> Result<Vec<ExportRow>> {
+        if lines == 0 {
+            let state = self.state()?;
+            return Ok(state
+                .runs
+                .get(&cmd_uuid)
+                .map(|run| {
+                    Joined {
+                        cmd_uuid,
+                        run,
+                        output: None,
+                    }
+                    .export_row()
+                })
+                .into_iter()
+                .collect());
+        }
+        let mut rows = self.run_output(cmd_uuid).await?;
+        let skip = rows.len().saturating_sub(lines);
+        Ok(rows.split_off(skip))
+    }
+
+    async fn insert_outputs(&self, outputs: &[Output]) -> Result<u64> {
+        let mut state = self.state()?;
+        state.outputs.extend_from_slice(outputs);
+        Ok(outputs.len() as u64)
+    }
+
+    async fn insert_status(&self, status: &Status) -> Result<u64> {
+        let mut state = self.state()?;
+        let run = state.runs.entry(status.cmd_uuid().0).or_default();
+        run.ended_at = Some(status.timestamp().0);
+        run.exit_code = status.exit_code();
+        run.exit_signal = status.exit_signal();
+        run.core_dumped = Some(status.core_dumped());
+        run.wait_status = status.wait_status();
+        run.success = Some(status.success());
+        Ok(1)
+    }
+
+    async fn import_run(&self, run: &ExportRow, outputs: &[Output]) -> Result<bool> {
+        let mut state = self.state()?;
+        if state.runs.contains_key(&run.cmd_uuid().0) {
+            return Ok(false);
+        }
+        let _old = state.runs.insert(
+            run.cmd_uuid().0,
+            Run {
+                bartoc_uuid: run.bartoc_uuid().map(|uuid| uuid.0),
+                bartoc_name: run.bartoc_name().clone(),
+                schedule_name: run.schedule_name().clone(),
+                cmd: run.cmd().clone(),
+                started_at: run.started_at().map(|at| at.0),
+                ended_at: run.ended_at().map(|at| at.0),
+                exit_code: run.exit_code(),
+                exit_signal: run.exit_signal(),
+                core_dumped: run.core_dumped(),
+                wait_status: run.wait_status(),
+                success: run.success(),
+            },
+        );
+        state.outputs.extend_from_slice(outputs);
+        Ok(true)
+    }
+
+    async fn insert_run_start(&self, run_start: &RunStart) -> Result<u64> {
+        let mut state = self.state()?;
+        let run = state.runs.entry(run_start.cmd_uuid().0).or_default();
+        run.bartoc_uuid = Some(run_start.bartoc_uuid().0);
+        run.bartoc_name = Some(run_start.bartoc_name().clone());
+        run.schedule_name = Some(run_start.schedule_name().clone());
+        run.cmd = Some(run_start.cmd().clone());
+        run.started_at = Some(run_start.timestamp().0);
+        Ok(1)
+    }
+
+    async fn client_credential(&self, name: &str) -> Result<Option<ClientCredential>> {
+        Ok(self.state()?.clients.get(name).cloned())
+    }
+
+    async fn enroll_client(&self, name: &str, credential: &ClientCredential) -> Result<bool> {
+        let mut state = self.state()?;
+        if matches!(
+            state.clients.get(name),
+            Some(existing) if *existing != ClientCredential::Revoked
+        ) {
+            return Ok(false);
+        }
+        let _old = state.clients.insert(name.to_string(), credential.clone());
+        Ok(true)
+    }
+
+    async fn revoke_client(&self, name: &str) -> Result<bool> {
+        let mut state = self.state()?;
+        Ok(match state.clients.get_mut(name) {
+            Some(credential) if *credential != ClientCredential::Revoked => {
+                *credential = ClientCredential::Revoked;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn insert_client_event(&self, event: &ClientEvent) -> Result<()> {
+        self.state()?.client_events.push(event.clone());
+        Ok(())
+    }
+
+    async fn client_events(&self, filter: &ClientEventFilter) -> Result<Vec<ClientEvent>> {
+        let limit = filter
+            .limit()
+            .unwrap_or(DEFAULT_HISTORY_LIMIT)
+            .min(MAX_HISTORY_LIMIT);
+        Ok(self
+            .state()?
+            .client_events
+            .iter()
+            .rev()
+            .filter(|event| {
+                filter
+                    .client()
+                    .as_ref()
+                    .is_none_or(|client| event.bartoc_name() == client)
+                    && filter
+                        .since()
+                        .is_none_or(|since| event.timestamp().0 >= since.0)
+            })
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn last_client_events(&self) -> Result<Vec<ClientEvent>> {
+        let mut last = BTreeMap::new();
+        for event in &self.state()?.client_events {
+            let _old = last.insert(event.bartoc_name().clone(), event.clone());
+        }
+        Ok(last.into_values().collect())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.state().map(|_| ())
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use anyhow::Result;
+use getset::{CopyGetters, Getters};
+use sqlx::migrate::{AppliedMigration, Migrate, Migration, Migrator};
+use tracing::info;
+
+use crate::error::Error;
+
+use super::Store;
+
+// The migrations are compiled into the binary, so bartos can bring any database it
+// connects to up to the schema it expects without sqlx-cli or the migration files.
+static MARIADB_MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("../migrations/sqlite");
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("../migrations/postgres");
+
+/// The state of a database schema compared to the migrations embedded in this binary
+#[derive(Clone, CopyGetters, Debug, Default, Eq, Getters, PartialEq)]
+pub(crate) struct MigrationStatus {
+    /// Embedded migrations already applied to the database
+    #[get = "pub(crate)"]
+    applied: Vec<MigrationInfo>,
+    /// Embedded migrations not yet applied to the database
+    #[get = "pub(crate)"]
+    pending: Vec<MigrationInfo>,
+    /// Applied migrations whose contents differ from the embedded copy
+    #[get = "pub(crate)"]
+    modified: Vec<i64>,
+    /// Applied migrations this binary knows nothing about, i.e. the schema is newer
+    #[get = "pub(crate)"]
+    unknown: Vec<i64>,
+    /// The version of a migration that failed part way through, if any
+    #[get_copy = "pub(crate)"]
+    dirty: Option<i64>,
+}
+
+/// The version and description of one migration
+#[derive(Clone, CopyGetters, Debug, Eq, Getters, PartialEq)]
+pub(crate) struct MigrationInfo {
+    #[get_copy = "pub(crate)"]
+    version: i64,
+    #[get = "pub(crate)"]
+    description: String,
+}
+
+impl From<&Migration> for MigrationInfo {
+    fn from(migration: &Migration) -> Self {
+        Self {
+            version: migration.version,
+            description: migration.description.to_string(),
+        }
+    }
+}
+
+impl Display for MigrationInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.version, self.description)
+    }
+}
+
+impl MigrationStatus {
+    fn new(migrator: &Migrator, applied: &[AppliedMigration], dirty: Option<i64>) -> Self {
+        let applied: BTreeMap<i64, &AppliedMigration> =
+            applied.iter().map(|m| (m.version, m)).collect();
+        let mut status = Self {
+            dirty,
+            ..Self::default()
+        };
+        for migration in migrator
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+        {
+            match applied.get(&migration.version) {
+                Some(done) => {
+                    if done.checksum != migration.checksum {
+                        status.modified.push(migration.version);
+                    }
+                    status.applied.push(migration.into());
+                }
+                None => status.pending.push(migration.into()),
+            }
+        }
+        status.unknown = applied
+            .keys()
+            .copied()
+            .filter(|version| !migrator.version_exists(*version))
+            .collect();
+        status
+    }
+
+    /// The newest applied migration, if any
+    pub(crate) fn current(&self) -> Option<i64> {
+        self.applied.last().map(MigrationInfo::version)
+    }
+
+    /// Check the migrations can be applied, failing when the database schema is newer
+    /// than this binary, a migration failed part way through or an applied migration
+    /// was changed.
+    pub(crate) fn check(&self) -> Result<()> {
+        if let Some(version) = self.unknown.last() {
+            Err(Error::SchemaNewer(*version).into())
+        } else if let Some(version) = self.dirty {
+            Err(Error::MigrationDirty(version).into())
+        } else if let Some(version) = self.modified.first() {
+            Err(Error::MigrationModified(*version).into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+async fn read_status<C: Migrate>(conn: &mut C, migrator: &Migrator) -> Result<MigrationStatus> {
+    conn.ensure_migrations_table(&migrator.table_name).await?;
+    let dirty = conn.dirty_version(&migrator.table_name).await?;
+    let applied = conn.list_applied_migrations(&migrator.table_name).await?;
+    Ok(MigrationStatus::new(migrator, &applied, dirty))
+}
+
+impl Store {
+    /// Compare the database schema with the embedded migrations.
+    pub(crate) async fn migration_status(&self) -> Result<MigrationStatus> {
+        match self {
+            Store::Mariadb(h) => {
+                read_status(&mut *h.pool().acquire().await?, &MARIADB_MIGRATOR).await
+            }
+            Store::Sqlite(h) => {
+                read_status(&mut *h.pool().acquire().await?, &SQLITE_MIGRATOR).await
+            }
+            Store::Postgres(h) => {
+                read_status(&mut *h.pool().acquire().await?, &POSTGRES_MIGRATOR).await
+            }
+        }
+    }
+
+    /// Apply any pending embedded migrations, refusing to touch a database whose schema
+    /// is newer than this binary, then move any output written before output was chunked
+    /// into chunks and seal any chunks left open.  Returns the migrations that were applied.
+    pub(crate) async fn migrate(&self) -> Result<Vec<MigrationInfo>> {
+        let status = self.migration_status().await?;
+        status.check()?;
+        if status.pending.is_empty() {
+            info!(
+                "database schema is up to date at version {}",
+                status.current().unwrap_or_default()
+            );
+        } else {
+            match self {
+                Store::Mariadb(h) => MARIADB_MIGRATOR.run(&***h.pool()).await?,
+                Store::Sqlite(h) => SQLITE_MIGRATOR.run(h.pool()).await?,
+                Store::Postgres(h) => POSTGRES_MIGRATOR.run(h.pool()).await?,
+            }
+            for migration in &status.pending {
+                info!("applied migration {migration}");
+            }
+        }
+        let moved = match self {
+            Store::Mariadb(h) => h.compact_legacy_output().await?,
+            Store::Sqlite(h) => h.compact_legacy_output().await?,
+            Store::Postgres(h) => h.compact_legacy_output().await?,
+        };
+        if moved > 0 {
+            info!("moved {moved} output lines into compressed chunks");
+        }
+        let sealed = match self {
+            Store::Mariadb(h) => h.seal_open_output().await?,
+            Store::Sqlite(h) => h.seal_open_output().await?,
+            Store::Postgres(h) => h.seal_open_output().await?,
+        };
+        if sealed > 0 {
+            info!("sealed the open output chunks of {sealed} runs");
+        }
+        Ok(status.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libbarto::{OffsetDataTimeWrapper, Output, OutputKind, SearchFilter, Status, UuidWrapper};
+    use sqlx::{Row, SqlitePool, sqlite::SqlitePoolOptions};
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use crate::db::{Queryable, Store, sqlite::SqliteHandler};
+
+    use super::SQLITE_MIGRATOR;
+
+    async fn memory_store() -> (SqlitePool, Store) {
+        // Every connection to `sqlite::memory:` is its own database, so keep just one.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let store = Store::Sqlite(SqliteHandler::builder().pool(pool.clone()).build());
+        (pool, store)
+    }
+
+    #[tokio::test]
+    async fn fresh_database_has_everything_pending() {
+        let (_pool, store) = memory_store().await;
+        let status = store.migration_status().await.unwrap();
+        assert!(status.applied().is_empty());
+        let up = SQLITE_MIGRATOR
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .count();
+        assert_eq!(status.pending().len(), up);
+        assert!(status.current().is_none());
+        assert!(status.check().is_ok());
+    }
+
+    #[tokio::test]
+    async fn migrate_applies_pending_once() {
+        let (_pool, store) = memory_store().await;
+        let applied = store.migrate().await.unwrap();
+        assert!(!applied.is_empty());
+
+        let status = store.migration_status().await.unwrap();
+        assert!(status.pending().is_empty());
+        assert_eq!(status.applied(), &applied);
+        assert_eq!(
+            status.current(),
+            applied.last().map(super::MigrationInfo::version)
+        );
+        assert!(store.migrate().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn newer_schema_refuses_to_migrate() {
+        let (pool, store) = memory_store().await;
+        let _applied = store.migrate().await.unwrap();
+        let _res = sqlx::query(
+            "INSERT INTO _sqlx_migrations \
+             (version, description, success, checksum, execution_time) \
+             VALUES (99990101000000, 'from the future', TRUE, X'00', 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let status = store.migration_status().await.unwrap();
+        assert_eq!(status.unknown(), &vec![99_990_101_000_000]);
+        let err = store.migrate().await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "database schema version 99990101000000 is newer than this bartos supports, upgrade bartos"
+        );
+    }
+
+    #[tokio::test]
+    async fn modified_migration_is_reported() {
+        let (pool, store) = memory_store().await;
+        let applied = store.migrate().await.unwrap();
+        let version = applied[0].version();
+        let _res = sqlx::query("UPDATE _sqlx_migrations SET checksum = X'00' WHERE version = ?")
+            .bind(version)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let status = store.migration_status().await.unwrap();
+        assert_eq!(status.modified(), &vec![version]);
+        assert!(store.migrate().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn legacy_output_moves_into_chunks() {
+        let (pool, store) = memory_store().await;
+        let _applied = store.migrate().await.unwrap();
+        let cmd_uuid = Uuid::new_v4();
+        for (secs, data) in [(2, "error: second"), (1, "first")] {
+            let _res = sqlx::query(
+                "INSERT INTO output (timestamp, bartoc_uuid, bartoc_name, cmd_uuid, cmd_name, kind, data) \
+                 VALUES (?, ?, 'host', ?, 'legacy', 'stderr', ?)",
+            )
+            .bind(OffsetDateTime::UNIX_EPOCH + Duration::seconds(secs))
+            .bind(Uuid::nil())
+            .bind(cmd_uuid)
+            .bind(data)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        assert!(store.migrate().await.unwrap().is_empty());
+        let left: i64 = sqlx::query("SELECT COUNT(*) AS n FROM output")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .get("n");
+        assert_eq!(left, 0);
+        let filter = SearchFilter::builder().text("error").context(1).build();
+        let hits = store.search(&filter).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].cmd_uuid().0, cmd_uuid);
+        assert_eq!(hits[0].before(), &vec!["first".to_string()]);
+    }
+
+    async fn chunks(pool: &SqlitePool) -> Vec<(i64, i64, bool)> {
+        sqlx::query("SELECT first_line, line_count, sealed FROM output_chunks ORDER BY first_line")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| {
+                (
+                    row.get("first_line"),
+                    row.get("line_count"),
+                    row.get("sealed"),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn output_is_appended_open_and_sealed_when_the_run_ends() {
+        let (pool, store) = memory_store().await;
+        let _applied = store.migrate().await.unwrap();
+        let cmd_uuid = Uuid::new_v4();
+        let output = |secs: i64, data: &str| {
+            Output::builder()
+                .bartoc_uuid(UuidWrapper(Uuid::nil()))
+                .bartoc_name("host".to_string())
+                .timestamp(OffsetDataTimeWrapper(
+                    OffsetDateTime::UNIX_EPOCH + Duration::seconds(secs),
+                ))
+                .cmd_uuid(UuidWrapper(cmd_uuid))
+                .cmd_name("backup".to_string())
+                .kind(OutputKind::Stdout)
+                .data(data.to_string())
+                .build()
+        };
+        let _rows = store
+            .insert_outputs(&[output(1, "first"), output(2, "error: second")])
+            .await
+            .unwrap();
+        let _rows = store.insert_outputs(&[output(3, "third")]).await.unwrap();
+        // Each append is its own open chunk, and the lines are readable straight away.
+        assert_eq!(chunks(&pool).await, vec![(0, 2, false), (2, 1, false)]);
+        let filter = SearchFilter::builder().text("error").context(1).build();
+        let hits = store.search(&filter).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].after(), &vec!["third".to_string()]);
+
+        let status = Status::builder()
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .timestamp(OffsetDataTimeWrapper(
+                OffsetDateTime::UNIX_EPOCH + Duration::seconds(4),
+            ))
+            .exit_code(Some(0))
+            .success(true)
+            .build();
+        let _rows = store.insert_status(&status).await.unwrap();
+        assert_eq!(chunks(&pool).await, vec![(0, 3, true)]);
+        let lines: Vec<_> = store
+            .run_output(cmd_uuid)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|row| row.data().clone())
+            .collect();
+        assert_eq!(lines, vec!["first", "error: second", "third"]);
+
+        // Output after the status, or left open by a run that never ended, is sealed at
+        // startup.
+        let _rows = store.insert_outputs(&[output(5, "late")]).await.unwrap();
+        assert_eq!(chunks(&pool).await, vec![(0, 3, true), (3, 1, false)]);
+        assert!(store.migrate().await.unwrap().is_empty());
+        assert_eq!(chunks(&pool).await, vec![(0, 3, true), (3, 1, true)]);
+    }
+}
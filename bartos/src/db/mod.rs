@@ -6,36 +6,1065 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+mod chunk;
+pub(crate) mod import;
+#[cfg(test)]
+pub(crate) mod memory;
+mod migrate;
 pub(crate) mod mysql;
+pub(crate) mod postgres;
+pub(crate) mod retention;
+mod scan;
+pub(crate) mod sqlite;
 mod utils;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
+use actix_web::web::Data;
 use anyhow::Result;
-use libbarto::{CliUpdateKind, FailedOutput, ListOutput, UpdateKind};
+use bon::Builder;
+use libbarto::{
+    CliUpdateKind, ClientEvent, ClientEventFilter, ClientEventKind, ExportFilter, ExportRow,
+    FailedOutput, ListOutput, OffsetDataTimeWrapper, Output, QueryFilter, QueryRow, RunStart,
+    SearchFilter, SearchHit, Status, UpdateKind, UuidWrapper,
+};
+use sqlx::{Database, Encode, MySqlPool, QueryBuilder, SqlitePool, Type};
+use time::OffsetDateTime;
+use tracing::info;
+use uuid::Uuid;
 
-use crate::config::Config;
+use crate::{
+    common::ClientCredential,
+    config::{Backend, Config},
+    error::Error,
+};
+
+use self::{
+    mysql::MySqlHandler,
+    postgres::PostgresHandler,
+    retention::{Cutoffs, RunGroup},
+    sqlite::SqliteHandler,
+};
 
 pub(crate) trait Queryable {
-    async fn delete_data(&self, config: &Config) -> Result<(u64, u64)>;
-    async fn update_data(
+    async fn run_groups(&self) -> Result<Vec<RunGroup>>;
+    async fn nth_newest_run(&self, group: &RunGroup, n: u32) -> Result<Option<OffsetDateTime>>;
+    async fn expired_runs(
         &self,
-        config: &Config,
-        kind: CliUpdateKind,
-        name: &str,
-    ) -> Result<UpdateKind>;
-    async fn cmd_data(&self, config: &Config, name: &str) -> Result<Vec<String>>;
-    async fn cmd_data_by_name(
+        group: &RunGroup,
+        cutoffs: &Cutoffs,
+        limit: u32,
+    ) -> Result<Vec<Uuid>>;
+    async fn delete_runs(&self, cmd_uuids: &[Uuid]) -> Result<(u64, u64)>;
+    async fn expired_orphans(&self, cutoff: OffsetDateTime, limit: u32) -> Result<Vec<Uuid>>;
+    async fn delete_orphan_output(&self, cmd_uuids: &[Uuid], cutoff: OffsetDateTime)
+    -> Result<u64>;
+    async fn update_data(&self, kind: CliUpdateKind, name: &str) -> Result<UpdateKind>;
+    async fn cmd_data(&self, name: &str) -> Result<Vec<String>>;
+    async fn cmd_data_by_name(&self, cmd_name: &str) -> Result<BTreeMap<String, Vec<ListOutput>>>;
+    async fn cmd_name_data(&self, name: &str, cmd_name: &str) -> Result<Vec<ListOutput>>;
+    async fn failed_cmd_data(&self) -> Result<Vec<FailedOutput>>;
+    async fn query(&self, filter: &QueryFilter) -> Result<Vec<QueryRow>>;
+    async fn raw_query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>>;
+    async fn search(&self, filter: &SearchFilter) -> Result<Vec<SearchHit>>;
+    async fn export(&self, filter: &ExportFilter) -> Result<Vec<ExportRow>>;
+    async fn run_output(&self, cmd_uuid: Uuid) -> Result<Vec<ExportRow>>;
+    async fn run_tail(&self, cmd_uuid: Uuid, lines: usize) -> Result<Vec<ExportRow>>;
+    async fn insert_outputs(&self, outputs: &[Output]) -> Result<u64>;
+    async fn insert_status(&self, status: &Status) -> Result<u64>;
+    async fn import_run(&self, run: &ExportRow, outputs: &[Output]) -> Result<bool>;
+    async fn insert_run_start(&self, run_start: &RunStart) -> Result<u64>;
+    async fn client_credential(&self, name: &str) -> Result<Option<ClientCredential>>;
+    /// Enrolls `name` unless it is already enrolled and not revoked, returning whether it was
+    async fn enroll_client(&self, name: &str, credential: &ClientCredential) -> Result<bool>;
+    async fn revoke_client(&self, name: &str) -> Result<bool>;
+    async fn insert_client_event(&self, event: &ClientEvent) -> Result<()>;
+    async fn client_events(&self, filter: &ClientEventFilter) -> Result<Vec<ClientEvent>>;
+    async fn last_client_events(&self) -> Result<Vec<ClientEvent>>;
+    async fn ping(&self) -> Result<()>;
+}
+
+/// When a run last reported: its end, or its start while it is still going
+const RUN_AT: &str = "COALESCE(ended_at, started_at)";
+
+// Runs recorded before bartoc sent start records only carry their names on their output.
+const RUN_BARTOC_NAME: &str = "COALESCE(r.bartoc_name, o.bartoc_name)";
+const RUN_SCHEDULE_NAME: &str = "COALESCE(r.schedule_name, o.cmd_name)";
+
+/// Rows a query filter returns when it sets no limit
+const DEFAULT_QUERY_LIMIT: u32 = 100;
+/// The most rows a query filter can return
+const MAX_QUERY_LIMIT: u32 = 10_000;
+
+/// Matches a full-text search returns when it sets no limit
+const DEFAULT_SEARCH_LIMIT: u32 = 50;
+/// The most matches a full-text search can return
+const MAX_SEARCH_LIMIT: u32 = 1_000;
+
+/// Runs an export page holds when it sets no limit
+const DEFAULT_EXPORT_LIMIT: u32 = 100;
+/// The most runs an export page can hold
+const MAX_EXPORT_LIMIT: u32 = 1_000;
+
+/// Client events a history request returns when it sets no limit
+const DEFAULT_HISTORY_LIMIT: u32 = 100;
+/// The most client events a history request can return
+const MAX_HISTORY_LIMIT: u32 = 10_000;
+
+/// Statements a raw query may start with
+const RAW_QUERY_KEYWORDS: [&str; 7] = [
+    "SELECT", "WITH", "EXPLAIN", "SHOW", "DESCRIBE", "DESC", "VALUES",
+];
+
+/// An output line matching a full-text search, before its context is attached
+#[derive(Builder, Clone, Debug)]
+pub(crate) struct SearchLine {
+    id: i64,
+    cmd_uuid: Uuid,
+    timestamp: OffsetDateTime,
+    bartoc_name: String,
+    schedule_name: Option<String>,
+    data: String,
+}
+
+/// The distinct runs of the search matches
+fn search_runs(lines: &[SearchLine]) -> Vec<Uuid> {
+    let mut cmd_uuids: Vec<Uuid> = lines.iter().map(|line| line.cmd_uuid).collect();
+    cmd_uuids.sort_unstable();
+    cmd_uuids.dedup();
+    cmd_uuids
+}
+
+/// Attach up to `context` lines of the same run either side of each match. `run_lines` holds
+/// `(line, cmd_uuid, data)` for the runs of the matches, in the order they were written, where
+/// `line` is the offset of the line in its run as in the matches' `id`.
+fn search_hits(
+    lines: Vec<SearchLine>,
+    run_lines: Vec<(i64, Uuid, String)>,
+    context: u8,
+) -> Vec<SearchHit> {
+    let mut runs: HashMap<Uuid, Vec<(i64, String)>> = HashMap::new();
+    for (id, cmd_uuid, data) in run_lines {
+        runs.entry(cmd_uuid).or_default().push((id, data));
+    }
+    let context = usize::from(context);
+    lines
+        .into_iter()
+        .map(|line| {
+            let (before, after) = runs
+                .get(&line.cmd_uuid)
+                .and_then(|run| {
+                    let at = run.iter().position(|(id, _)| *id == line.id)?;
+                    let text = |lines: &[(i64, String)]| {
+                        lines.iter().map(|(_, data)| data.clone()).collect()
+                    };
+                    let end = (at + 1 + context).min(run.len());
+                    Some((
+                        text(&run[at.saturating_sub(context)..at]),
+                        text(&run[at + 1..end]),
+                    ))
+                })
+                .unwrap_or_default();
+            SearchHit::builder()
+                .cmd_uuid(UuidWrapper(line.cmd_uuid))
+                .timestamp(OffsetDataTimeWrapper(line.timestamp))
+                .bartoc_name(line.bartoc_name)
+                .maybe_schedule_name(line.schedule_name)
+                .line(line.data)
+                .before(before)
+                .after(after)
+                .build()
+        })
+        .collect()
+}
+
+/// Check that a raw query is a single statement that only reads. The backends also run it
+/// read-only, but a MariaDB DDL statement commits the read-only transaction before it runs
+/// and SQLite runs every statement in the string, so those never reach the database.
+fn read_only_statement(query: &str) -> Result<&str> {
+    let statement = query.trim().trim_end_matches(';').trim_end();
+    let keyword = statement
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    if statement.contains(';') || !RAW_QUERY_KEYWORDS.contains(&keyword.as_str()) {
+        return Err(Error::RawQueryNotReadOnly.into());
+    }
+    Ok(statement)
+}
+
+/// The columns of `client_events` every backend reads a [`ClientEvent`] from
+const CLIENT_EVENT_COLUMNS: &str =
+    "bartoc_uuid, bartoc_name, ip, kind, reason, close_code, timestamp";
+
+/// Build the statement for the client events matching a history filter, newest first. `time`
+/// wraps a timestamp so it compares as a time, as for [`scan::scan_query`].
+fn client_events_query<DB>(filter: &ClientEventFilter, time: (&str, &str)) -> QueryBuilder<DB>
+where
+    DB: Database,
+    <DB as Database>::Arguments: Default,
+    for<'t> i64: Encode<'t, DB> + Type<DB>,
+    for<'t> String: Encode<'t, DB> + Type<DB>,
+    for<'t> OffsetDateTime: Encode<'t, DB> + Type<DB>,
+{
+    let (open, close) = time;
+    let mut query = QueryBuilder::new(format!(
+        "SELECT {CLIENT_EVENT_COLUMNS} FROM client_events WHERE 1 = 1"
+    ));
+    if let Some(client) = filter.client() {
+        let _ = query.push(" AND bartoc_name = ").push_bind(client.clone());
+    }
+    if let Some(since) = filter.since() {
+        let _ = query
+            .push(format!(" AND {open}timestamp{close} >= {open}"))
+            .push_bind(since.0)
+            .push(close);
+    }
+    let limit = filter
+        .limit()
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT);
+    let _ = query
+        .push(" ORDER BY id DESC LIMIT ")
+        .push_bind(i64::from(limit));
+    query
+}
+
+/// The statement for the newest client event of every client
+fn last_client_events_query() -> String {
+    format!(
+        "SELECT {CLIENT_EVENT_COLUMNS} FROM client_events
+WHERE id IN (SELECT MAX(id) FROM client_events GROUP BY bartoc_name)
+ORDER BY bartoc_name"
+    )
+}
+
+/// Assemble a [`ClientEvent`] from the columns of a `client_events` row
+fn build_client_event(
+    bartoc_uuid: Uuid,
+    bartoc_name: String,
+    ip: String,
+    kind: &str,
+    reason: Option<String>,
+    close_code: Option<i32>,
+    timestamp: OffsetDateTime,
+) -> Result<ClientEvent> {
+    Ok(ClientEvent::builder()
+        .bartoc_uuid(UuidWrapper(bartoc_uuid))
+        .bartoc_name(bartoc_name)
+        .ip(ip)
+        .kind(ClientEventKind::try_from(kind)?)
+        .maybe_reason(reason)
+        .maybe_close_code(close_code.and_then(|code| u16::try_from(code).ok()))
+        .timestamp(OffsetDataTimeWrapper(timestamp))
+        .build())
+}
+
+/// The storage backend selected by `backend` in `bartos.toml`
+#[derive(Clone, Debug)]
+pub(crate) enum Store {
+    Mariadb(MySqlHandler),
+    Sqlite(SqliteHandler),
+    Postgres(PostgresHandler),
+}
+
+impl Store {
+    /// Connect to the configured backend.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub(crate) async fn connect(config: &Config) -> Result<Self> {
+        match config.backend() {
+            Backend::Mariadb => {
+                let mariadb = config.mariadb();
+                info!(
+                    "connecting to database at: {}",
+                    mariadb.disp_connection_string()
+                );
+                let pool = MySqlPool::connect(&mariadb.connection_string()).await?;
+                Ok(Store::Mariadb(
+                    MySqlHandler::builder().pool(Data::new(pool)).build(),
+                ))
+            }
+            Backend::Sqlite => {
+                let sqlite = config
+                    .sqlite()
+                    .as_ref()
+                    .ok_or(Error::MissingBackendConfig(Backend::Sqlite))?;
+                info!("opening database at: {}", sqlite.path().display());
+                let pool = SqlitePool::connect(&sqlite.connection_string()).await?;
+                Ok(Store::Sqlite(SqliteHandler::builder().pool(pool).build()))
+            }
+            Backend::Postgres => {
+                let postgres = config
+                    .postgres()
+                    .as_ref()
+                    .ok_or(Error::MissingBackendConfig(Backend::Postgres))?;
+                info!(
+                    "connecting to database at: {}",
+                    postgres.disp_connection_string()
+                );
+                Ok(Store::Postgres(
+                    PostgresHandler::connect(
+                        &postgres.connection_string(),
+                        postgres.schema().as_deref(),
+                    )
+                    .await?,
+                ))
+            }
+        }
+    }
+}
+
+macro_rules! dispatch {
+    ($store:expr, $handler:ident => $call:expr) => {
+        match $store {
+            Store::Mariadb($handler) => $call,
+            Store::Sqlite($handler) => $call,
+            Store::Postgres($handler) => $call,
+        }
+    };
+}
+
+impl Queryable for Store {
+    async fn run_groups(&self) -> Result<Vec<RunGroup>> {
+        dispatch!(self, h => h.run_groups().await)
+    }
+
+    async fn nth_newest_run(&self, group: &RunGroup, n: u32) -> Result<Option<OffsetDateTime>> {
+        dispatch!(self, h => h.nth_newest_run(group, n).await)
+    }
+
+    async fn expired_runs(
         &self,
-        config: &Config,
-        cmd_name: &str,
-    ) -> Result<BTreeMap<String, Vec<ListOutput>>>;
-    async fn cmd_name_data(
+        group: &RunGroup,
+        cutoffs: &Cutoffs,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        dispatch!(self, h => h.expired_runs(group, cutoffs, limit).await)
+    }
+
+    async fn delete_runs(&self, cmd_uuids: &[Uuid]) -> Result<(u64, u64)> {
+        dispatch!(self, h => h.delete_runs(cmd_uuids).await)
+    }
+
+    async fn expired_orphans(&self, cutoff: OffsetDateTime, limit: u32) -> Result<Vec<Uuid>> {
+        dispatch!(self, h => h.expired_orphans(cutoff, limit).await)
+    }
+
+    async fn delete_orphan_output(
         &self,
-        config: &Config,
-        name: &str,
-        cmd_name: &str,
-    ) -> Result<Vec<ListOutput>>;
-    async fn failed_cmd_data(&self, config: &Config) -> Result<Vec<FailedOutput>>;
-    async fn query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>>;
+        cmd_uuids: &[Uuid],
+        cutoff: OffsetDateTime,
+    ) -> Result<u64> {
+        dispatch!(self, h => h.delete_orphan_output(cmd_uuids, cutoff).await)
+    }
+
+    async fn update_data(&self, kind: CliUpdateKind, name: &str) -> Result<UpdateKind> {
+        dispatch!(self, h => h.update_data(kind, name).await)
+    }
+
+    async fn cmd_data(&self, name: &str) -> Result<Vec<String>> {
+        dispatch!(self, h => h.cmd_data(name).await)
+    }
+
+    async fn cmd_data_by_name(&self, cmd_name: &str) -> Result<BTreeMap<String, Vec<ListOutput>>> {
+        dispatch!(self, h => h.cmd_data_by_name(cmd_name).await)
+    }
+
+    async fn cmd_name_data(&self, name: &str, cmd_name: &str) -> Result<Vec<ListOutput>> {
+        dispatch!(self, h => h.cmd_name_data(name, cmd_name).await)
+    }
+
+    async fn failed_cmd_data(&self) -> Result<Vec<FailedOutput>> {
+        dispatch!(self, h => h.failed_cmd_data().await)
+    }
+
+    async fn query(&self, filter: &QueryFilter) -> Result<Vec<QueryRow>> {
+        dispatch!(self, h => Queryable::query(h, filter).await)
+    }
+
+    async fn raw_query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
+        dispatch!(self, h => Queryable::raw_query(h, query).await)
+    }
+
+    async fn search(&self, filter: &SearchFilter) -> Result<Vec<SearchHit>> {
+        dispatch!(self, h => Queryable::search(h, filter).await)
+    }
+
+    async fn export(&self, filter: &ExportFilter) -> Result<Vec<ExportRow>> {
+        dispatch!(self, h => Queryable::export(h, filter).await)
+    }
+
+    async fn run_output(&self, cmd_uuid: Uuid) -> Result<Vec<ExportRow>> {
+        dispatch!(self, h => h.run_output(cmd_uuid).await)
+    }
+
+    async fn run_tail(&self, cmd_uuid: Uuid, lines: usize) -> Result<Vec<ExportRow>> {
+        dispatch!(self, h => h.run_tail(cmd_uuid, lines).await)
+    }
+
+    async fn insert_outputs(&self, outputs: &[Output]) -> Result<u64> {
+        dispatch!(self, h => h.insert_outputs(outputs).await)
+    }
+
+    async fn insert_status(&self, status: &Status) -> Result<u64> {
+        dispatch!(self, h => h.insert_status(status).await)
+    }
+
+    async fn import_run(&self, run: &ExportRow, outputs: &[Output]) -> Result<bool> {
+        dispatch!(self, h => h.import_run(run, outputs).await)
+    }
+
+    async fn insert_run_start(&self, run_start: &RunStart) -> Result<u64> {
+        dispatch!(self, h => h.insert_run_start(run_start).await)
+    }
+
+    async fn client_credential(&self, name: &str) -> Result<Option<ClientCredential>> {
+        dispatch!(self, h => Queryable::client_credential(h, name).await)
+    }
+
+    async fn enroll_client(&self, name: &str, credential: &ClientCredential) -> Result<bool> {
+        dispatch!(self, h => Queryable::enroll_client(h, name, credential).await)
+    }
+
+    async fn revoke_client(&self, name: &str) -> Result<bool> {
+        dispatch!(self, h => Queryable::revoke_client(h, name).await)
+    }
+
+    async fn insert_client_event(&self, event: &ClientEvent) -> Result<()> {
+        dispatch!(self, h => h.insert_client_event(event).await)
+    }
+
+    async fn client_events(&self, filter: &ClientEventFilter) -> Result<Vec<ClientEvent>> {
+        dispatch!(self, h => h.client_events(filter).await)
+    }
+
+    async fn last_client_events(&self) -> Result<Vec<ClientEvent>> {
+        dispatch!(self, h => h.last_client_events().await)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        dispatch!(self, h => h.ping().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use actix_web::web::Data;
+    use libbarto::{
+        CliUpdateKind, ClientEvent, ClientEventFilter, ClientEventKind, ExportFilter,
+        OffsetDataTimeWrapper, Output, OutputKind, QueryFilter, RunStart, RunState, SearchFilter,
+        Status,
+    };
+    use sqlx::{AssertSqlSafe, MySqlPool, sqlite::SqlitePoolOptions};
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use crate::{common::ClientCredential, config::Config};
+
+    use super::{
+        Queryable, SearchLine, Store, import, mysql::MySqlHandler, postgres::PostgresHandler,
+        read_only_statement, retention, search_hits, sqlite::SqliteHandler,
+    };
+
+    fn run_start(
+        bartoc_name: &str,
+        schedule: &str,
+        cmd_uuid: Uuid,
+        at: OffsetDateTime,
+    ) -> RunStart {
+        RunStart::builder()
+            .cmd_uuid(libbarto::UuidWrapper(cmd_uuid))
+            .bartoc_uuid(libbarto::UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(bartoc_name.to_string())
+            .schedule_name(schedule.to_string())
+            .cmd(format!("run {schedule}"))
+            .timestamp(OffsetDataTimeWrapper(at))
+            .build()
+    }
+
+    fn output(
+        bartoc_name: &str,
+        schedule: &str,
+        cmd_uuid: Uuid,
+        at: OffsetDateTime,
+        data: &str,
+    ) -> Output {
+        Output::builder()
+            .bartoc_uuid(libbarto::UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(bartoc_name.to_string())
+            .timestamp(OffsetDataTimeWrapper(at))
+            .cmd_uuid(libbarto::UuidWrapper(cmd_uuid))
+            .cmd_name(schedule.to_string())
+            .kind(OutputKind::Stdout)
+            .data(data.to_string())
+            .build()
+    }
+
+    fn status(cmd_uuid: Uuid, at: OffsetDateTime, exit_code: i32) -> Status {
+        Status::builder()
+            .cmd_uuid(libbarto::UuidWrapper(cmd_uuid))
+            .timestamp(OffsetDataTimeWrapper(at))
+            .exit_code(Some(exit_code))
+            .success(exit_code == 0)
+            .build()
+    }
+
+    /// The behaviour every backend must share, run against a freshly migrated database.
+    async fn exercise(store: Store) {
+        let _applied = store.migrate().await.unwrap();
+        let config = Config::default();
+        let host = format!("host-{}", Uuid::new_v4());
+        let start = OffsetDateTime::now_utc() - Duration::days(2);
+
+        // A successful run, recorded start → output → status.
+        let ok = Uuid::new_v4();
+        let _ = store
+            .insert_run_start(&run_start(&host, "update", ok, start))
+            .await
+            .unwrap();
+        let outputs = vec![
+            output(&host, "update", ok, start + Duration::seconds(1), "line 1"),
+            output(&host, "update", ok, start + Duration::seconds(2), "line 2"),
+        ];
+        assert_eq!(store.insert_outputs(&outputs).await.unwrap(), 2);
+        let _ = store
+            .insert_status(&status(ok, start + Duration::seconds(3), 0))
+            .await
+            .unwrap();
+
+        // A failed run whose status arrives before its start.
+        let failed = Uuid::new_v4();
+        let _ = store
+            .insert_status(&status(failed, start + Duration::seconds(5), 3))
+            .await
+            .unwrap();
+        let _ = store
+            .insert_run_start(&run_start(&host, "backup", failed, start))
+            .await
+            .unwrap();
+
+        // A run that is still going.
+        let running = Uuid::new_v4();
+        let _ = store
+            .insert_run_start(&run_start(&host, "backup", running, start))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.cmd_data(&host).await.unwrap(),
+            vec!["backup".to_string(), "update".to_string()]
+        );
+
+        let list = store.cmd_name_data(&host, "update").await.unwrap();
+        let data: Vec<_> = list.iter().filter_map(|l| l.data().clone()).collect();
+        assert_eq!(data, vec!["line 1".to_string(), "line 2".to_string()]);
+        assert!(
+            list.iter()
+                .all(|l| l.exit_code() == Some(0) && l.success() == 1)
+        );
+
+        let backup = store.cmd_name_data(&host, "backup").await.unwrap();
+        assert_eq!(backup.len(), 1);
+        assert_eq!(backup[0].exit_code(), Some(3));
+        assert_eq!(backup[0].success(), 0);
+
+        let by_name = store.cmd_data_by_name("update").await.unwrap();
+        assert_eq!(by_name.get(&host).map(Vec::len), Some(2));
+
+        let failed_runs = store.failed_cmd_data().await.unwrap();
+        assert!(
+            failed_runs
+                .iter()
+                .any(|f| f.bartoc_name().as_deref() == Some(host.as_str()))
+        );
+
+        let _update = store.update_data(CliUpdateKind::Apt, &host).await.unwrap();
+
+        let filter = || QueryFilter::builder().client(host.clone());
+        let rows = store.query(&filter().build()).await.unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[2].data().as_deref(), Some("line 1"));
+        let failed_rows = store
+            .query(&filter().state(RunState::Failed).build())
+            .await
+            .unwrap();
+        assert_eq!(failed_rows.len(), 1);
+        assert_eq!(failed_rows[0].cmd_uuid().0, failed);
+        assert_eq!(failed_rows[0].exit_code(), Some(3));
+        let running_rows = store
+            .query(&filter().state(RunState::Running).build())
+            .await
+            .unwrap();
+        assert_eq!(running_rows.len(), 1);
+        assert_eq!(running_rows[0].cmd_uuid().0, running);
+        let text_rows = store
+            .query(
+                &filter()
+                    .text("LINE 2".to_string())
+                    .since(OffsetDataTimeWrapper(start + Duration::seconds(2)))
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(text_rows.len(), 1);
+        assert_eq!(text_rows[0].success(), 1);
+        // LIKE wildcards in the text are matched literally.
+        let wildcard = filter().text("line_%".to_string()).build();
+        assert!(store.query(&wildcard).await.unwrap().is_empty());
+        let page = store
+            .query(
+                &filter()
+                    .until(OffsetDataTimeWrapper(start + Duration::seconds(2)))
+                    .limit(1)
+                    .offset(2)
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].data().as_deref(), Some("line 1"));
+
+        let raw = store
+            .raw_query(&format!(
+                "SELECT cmd_uuid, started_at, ended_at, exit_code FROM runs WHERE bartoc_name = '{host}' ORDER BY ended_at"
+            ))
+            .await
+            .unwrap();
+        assert_eq!(raw.len(), 3);
+        let ended: Vec<_> = raw.values().map(|row| row["ended_at"].as_str()).collect();
+        assert!(ended.contains(&"NULL"));
+        let row = raw
+            .values()
+            .find(|row| row["cmd_uuid"] == failed.to_string())
+            .unwrap();
+        assert_eq!(row["exit_code"], "3");
+        assert!(row["started_at"].ends_with('Z'));
+        for write in [
+            "DELETE FROM runs",
+            "SELECT 1; DELETE FROM runs",
+            "WITH gone AS (DELETE FROM runs RETURNING cmd_uuid) SELECT * FROM gone",
+        ] {
+            assert!(store.raw_query(write).await.is_err(), "{write}");
+        }
+        assert_eq!(store.cmd_name_data(&host, "update").await.unwrap().len(), 2);
+
+        let deploy = Uuid::new_v4();
+        let _ = store
+            .insert_run_start(&run_start(&host, "deploy", deploy, start))
+            .await
+            .unwrap();
+        let lines = [
+            "starting commit",
+            "error: failed to commit transaction",
+            "rolling back",
+            "done",
+        ];
+        let outputs: Vec<_> = lines
+            .iter()
+            .zip(1..)
+            .map(|(line, secs)| {
+                output(
+                    &host,
+                    "deploy",
+                    deploy,
+                    start + Duration::seconds(secs),
+                    line,
+                )
+            })
+            .collect();
+        let _ = store.insert_outputs(&outputs).await.unwrap();
+        let _ = store
+            .insert_status(&status(deploy, start + Duration::seconds(5), 1))
+            .await
+            .unwrap();
+        let search = |text: &str| SearchFilter::builder().text(text).client(host.clone());
+        let hits = store
+            .search(&search("Failed to COMMIT").context(1).build())
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].cmd_uuid().0, deploy);
+        assert_eq!(hits[0].line(), lines[1]);
+        assert_eq!(hits[0].schedule_name().as_deref(), Some("deploy"));
+        assert_eq!(hits[0].before(), &vec![lines[0].to_string()]);
+        assert_eq!(hits[0].after(), &vec![lines[2].to_string()]);
+        let hits = store.search(&search("commit").build()).await.unwrap();
+        let matched: Vec<_> = hits.iter().map(|hit| hit.line().as_str()).collect();
+        assert_eq!(matched, vec![lines[1], lines[0]]);
+        assert!(
+            hits.iter()
+                .all(|hit| hit.before().is_empty() && hit.after().is_empty())
+        );
+        let hits = store
+            .search(
+                &search("commit")
+                    .schedule("deploy".to_string())
+                    .since(OffsetDataTimeWrapper(start + Duration::seconds(2)))
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        let elsewhere = SearchFilter::builder()
+            .text("commit")
+            .client(format!("host-{}", Uuid::new_v4()))
+            .build();
+        assert!(store.search(&elsewhere).await.unwrap().is_empty());
+        assert!(
+            store
+                .search(&search("-- ").build())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // A long run spills over into more chunks, across batches.
+        let long = Uuid::new_v4();
+        let _ = store
+            .insert_run_start(&run_start(&host, "long", long, start))
+            .await
+            .unwrap();
+        let outputs: Vec<_> = (0..1_500)
+            .map(|n| {
+                output(
+                    &host,
+                    "long",
+                    long,
+                    start + Duration::milliseconds(n),
+                    &format!("long line {n}"),
+                )
+            })
+            .collect();
+        for batch in outputs.chunks(700) {
+            assert_eq!(
+                store.insert_outputs(batch).await.unwrap(),
+                batch.len() as u64
+            );
+        }
+        let _ = store
+            .insert_status(&status(long, start + Duration::seconds(2), 0))
+            .await
+            .unwrap();
+        let list = store.cmd_name_data(&host, "long").await.unwrap();
+        assert_eq!(list.len(), 1_500);
+        assert_eq!(list[1_499].data().as_deref(), Some("long line 1499"));
+        let page = store
+            .query(
+                &filter()
+                    .schedule("long".to_string())
+                    .limit(2)
+                    .offset(1_200)
+                    .build(),
+            )
+            .await
+            .unwrap();
+        let data: Vec<_> = page
+            .iter()
+            .filter_map(|row| row.data().as_deref())
+            .collect();
+        assert_eq!(data, vec!["long line 1200", "long line 1201"]);
+        let hits = store
+            .search(&search("line 1000").context(1).build())
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].before(), &vec!["long line 999".to_string()]);
+        assert_eq!(hits[0].after(), &vec!["long line 1001".to_string()]);
+
+        // Export pages by run, and importing an export into the same store skips every run.
+        let page = |schedule: &str, offset| {
+            ExportFilter::builder()
+                .client(host.clone())
+                .schedule(schedule.to_string())
+                .limit(1)
+                .offset(offset)
+                .build()
+        };
+        let first = store.export(&page("update", 0)).await.unwrap();
+        assert_eq!(first.len(), 2);
+        assert!(first.iter().all(|row| row.cmd_uuid().0 == ok));
+        assert_eq!(first[1].data().as_deref(), Some("line 2"));
+        assert_eq!(first[1].success(), Some(true));
+        let backups = [
+            store.export(&page("backup", 0)).await.unwrap(),
+            store.export(&page("backup", 1)).await.unwrap(),
+        ];
+        assert!(backups.iter().all(|rows| rows.len() == 1));
+        assert_ne!(backups[0][0].cmd_uuid(), backups[1][0].cmd_uuid());
+        assert!(store.export(&page("backup", 2)).await.unwrap().is_empty());
+        let long_run = store.export(&page("long", 0)).await.unwrap();
+        assert_eq!(long_run.len(), 1_500);
+        assert_eq!(long_run[1_499].data().as_deref(), Some("long line 1499"));
+        let long = long_run[0].cmd_uuid().0;
+        let tail: Vec<_> = store
+            .run_tail(long, 3)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|row| row.data().clone())
+            .collect();
+        assert_eq!(tail, ["long line 1497", "long line 1498", "long line 1499"]);
+        let run = store.run_tail(long, 0).await.unwrap();
+        assert_eq!(run.len(), 1);
+        assert_eq!(run[0].schedule_name().as_deref(), Some("long"));
+        assert!(run[0].data().is_none());
+        assert!(store.run_tail(Uuid::new_v4(), 3).await.unwrap().is_empty());
+        let imported = import::import(&store, first.into_iter().map(Ok))
+            .await
+            .unwrap();
+        assert_eq!((imported.runs, imported.skipped), (0, 1));
+
+        // A run killed by a signal and a Windows exit code outside the range of a byte.
+        let crashed_host = format!("crashed-{}", Uuid::new_v4());
+        let (segv, access_violation) = (Uuid::new_v4(), Uuid::new_v4());
+        for (cmd_uuid, schedule) in [(segv, "segv"), (access_violation, "win")] {
+            let _ = store
+                .insert_run_start(&run_start(&crashed_host, schedule, cmd_uuid, start))
+                .await
+                .unwrap();
+        }
+        let killed = Status::builder()
+            .cmd_uuid(libbarto::UuidWrapper(segv))
+            .timestamp(OffsetDataTimeWrapper(start + Duration::seconds(1)))
+            .exit_code(None)
+            .exit_signal(11)
+            .core_dumped(true)
+            .wait_status(139)
+            .success(false)
+            .build();
+        let _ = store.insert_status(&killed).await.unwrap();
+        let _ = store
+            .insert_status(&status(
+                access_violation,
+                start + Duration::seconds(1),
+                -1_073_741_819,
+            ))
+            .await
+            .unwrap();
+        let segv_runs = store.cmd_name_data(&crashed_host, "segv").await.unwrap();
+        assert_eq!(
+            (
+                segv_runs[0].exit_code(),
+                segv_runs[0].exit_signal(),
+                segv_runs[0].core_dumped()
+            ),
+            (None, Some(11), true)
+        );
+        let failed_runs = store.failed_cmd_data().await.unwrap();
+        let win = failed_runs
+            .iter()
+            .find(|f| {
+                f.bartoc_name().as_deref() == Some(crashed_host.as_str())
+                    && f.cmd_name().as_deref() == Some("win")
+            })
+            .unwrap();
+        assert_eq!(win.exit_code(), Some(-1_073_741_819));
+        let exported = store
+            .export(
+                &ExportFilter::builder()
+                    .client(crashed_host.clone())
+                    .schedule("segv".to_string())
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(exported[0].wait_status(), Some(139));
+        assert_eq!(exported[0].core_dumped(), Some(true));
+
+        let events_host = format!("events-{}", Uuid::new_v4());
+        let client_event = |secs, reason: Option<&str>| {
+            ClientEvent::builder()
+                .bartoc_uuid(libbarto::UuidWrapper(Uuid::new_v4()))
+                .bartoc_name(events_host.clone())
+                .ip("10.0.0.1".to_string())
+                .kind(if reason.is_some() {
+                    ClientEventKind::Disconnect
+                } else {
+                    ClientEventKind::Connect
+                })
+                .maybe_reason(reason.map(str::to_string))
+                .maybe_close_code(reason.map(|_| 1000))
+                .timestamp(OffsetDataTimeWrapper(start + Duration::seconds(secs)))
+                .build()
+        };
+        let connected = client_event(1, None);
+        let closed = client_event(2, Some("closed"));
+        store.insert_client_event(&connected).await.unwrap();
+        store.insert_client_event(&closed).await.unwrap();
+        let history = store
+            .client_events(
+                &ClientEventFilter::builder()
+                    .client(events_host.clone())
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(history, vec![closed.clone(), connected]);
+        let newest = store
+            .client_events(
+                &ClientEventFilter::builder()
+                    .client(events_host.clone())
+                    .since(OffsetDataTimeWrapper(start + Duration::seconds(2)))
+                    .limit(5)
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(newest, vec![closed.clone()]);
+        let last = store.last_client_events().await.unwrap();
+        assert!(last.contains(&closed));
+        assert_eq!(
+            last.iter()
+                .filter(|event| event.bartoc_name() == &events_host)
+                .count(),
+            1
+        );
+
+        let client = format!("client-{}", Uuid::new_v4());
+        assert!(store.client_credential(&client).await.unwrap().is_none());
+        assert!(
+            store
+                .enroll_client(&client, &ClientCredential::Token("hash".to_string()))
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            store.client_credential(&client).await.unwrap(),
+            Some(ClientCredential::Token("hash".to_string()))
+        );
+        // An enrolled name cannot be taken over by enrolling it again
+        assert!(
+            !store
+                .enroll_client(&client, &ClientCredential::Token("other".to_string()))
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            store.client_credential(&client).await.unwrap(),
+            Some(ClientCredential::Token("hash".to_string()))
+        );
+        assert!(store.revoke_client(&client).await.unwrap());
+        assert_eq!(
+            store.client_credential(&client).await.unwrap(),
+            Some(ClientCredential::Revoked)
+        );
+        assert!(
+            store
+                .enroll_client(&client, &ClientCredential::Token("other".to_string()))
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            store.client_credential(&client).await.unwrap(),
+            Some(ClientCredential::Token("other".to_string()))
+        );
+
+        let (output_rows, run_rows) = retention::enforce(
+            &store,
+            &config,
+            OffsetDateTime::now_utc() + Duration::days(29),
+        )
+        .await
+        .unwrap();
+        assert!(output_rows >= 2);
+        assert!(run_rows >= 3);
+        assert!(store.cmd_data(&host).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_context_stays_inside_the_run() {
+        let run = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let line = |id: i64, data: &str| {
+            SearchLine::builder()
+                .id(id)
+                .cmd_uuid(run)
+                .timestamp(OffsetDateTime::UNIX_EPOCH)
+                .bartoc_name("host".to_string())
+                .data(data.to_string())
+                .build()
+        };
+        let run_lines = vec![
+            (1, run, "a".to_string()),
+            (2, other, "x".to_string()),
+            (3, run, "b".to_string()),
+            (4, run, "c".to_string()),
+        ];
+        let hits = search_hits(vec![line(1, "a"), line(3, "b")], run_lines, 2);
+        assert!(hits[0].before().is_empty());
+        assert_eq!(hits[0].after(), &vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(hits[1].before(), &vec!["a".to_string()]);
+        assert_eq!(hits[1].after(), &vec!["c".to_string()]);
+        let hits = search_hits(vec![line(3, "b")], vec![], 2);
+        assert!(hits[0].before().is_empty() && hits[0].after().is_empty());
+    }
+
+    #[test]
+    fn raw_queries_must_only_read() {
+        assert_eq!(
+            read_only_statement("  select * from runs; ").unwrap(),
+            "select * from runs"
+        );
+        for query in [
+            "WITH r AS (SELECT 1) SELECT * FROM r",
+            "EXPLAIN SELECT 1",
+            "SHOW TABLES",
+            "describe runs",
+        ] {
+            assert!(read_only_statement(query).is_ok(), "{query}");
+        }
+        for query in [
+            "",
+            "DROP TABLE runs",
+            "delete from output",
+            "PRAGMA query_only = OFF",
+            "SELECT 1; DROP TABLE runs",
+            "SET TRANSACTION READ WRITE",
+            "SELECTED",
+        ] {
+            assert!(read_only_statement(query).is_err(), "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn sqlite_backend() {
+        // Every connection to `sqlite::memory:` is its own database, so keep just one.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        exercise(Store::Sqlite(SqliteHandler::builder().pool(pool).build())).await;
+    }
+
+    #[tokio::test]
+    async fn postgres_backend() {
+        let Ok(url) = env::var("BARTOS_TEST_POSTGRES_URL") else {
+            return;
+        };
+        // A schema of its own keeps the run away from anything else in the database.
+        let schema = format!("barto_test_{}", Uuid::new_v4().simple());
+        let handler = PostgresHandler::connect(&url, Some(&schema)).await.unwrap();
+        let pool = handler.pool().clone();
+        exercise(Store::Postgres(handler)).await;
+        let _res = sqlx::query(AssertSqlSafe(format!("DROP SCHEMA \"{schema}\" CASCADE")))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn mariadb_backend() {
+        let Ok(url) = env::var("BARTOS_TEST_MARIADB_URL") else {
+            return;
+        };
+        let pool = MySqlPool::connect(&url).await.unwrap();
+        exercise(Store::Mariadb(
+            MySqlHandler::builder().pool(Data::new(pool)).build(),
+        ))
+        .await;
+    }
 }
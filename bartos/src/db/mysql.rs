@@ -11,532 +11,871 @@ use std::collections::BTreeMap;
 use actix_web::web::Data;
 use anyhow::Result;
 use bon::Builder;
+use futures_util::TryStreamExt;
+use getset::Getters;
 use libbarto::{
-    CliUpdateKind, FailedOutput, ListOutput, OffsetDataTimeWrapper, OutputTableName, UpdateKind,
-    midnight,
+    CliUpdateKind, ClientEvent, ClientEventFilter, ExportFilter, ExportRow, FailedOutput,
+    ListOutput, OffsetDataTimeWrapper, Output, QueryFilter, QueryRow, RunStart, SearchFilter,
+    SearchHit, Status, TriggerKind, UpdateKind, UuidWrapper,
 };
-use sqlx::{AssertSqlSafe, Column, MySqlPool, Row};
-use time::{OffsetDateTime, macros::offset};
-use tracing::info;
+use sqlx::{
+    AssertSqlSafe, Column, MySql, MySqlConnection, MySqlPool, QueryBuilder, Row, TypeInfo,
+    ValueRef, mysql::MySqlRow,
+};
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
 use uuid::Uuid;
 
 use crate::{
-    config::Config,
+    common::ClientCredential,
     db::{
-        Queryable,
-        utils::{apt_filter, cachyos_filter, garuda_filter, pacman_filter},
+        Cutoffs, Queryable, RUN_AT, RUN_BARTOC_NAME, RUN_SCHEDULE_NAME, RunGroup,
+        build_client_event,
+        chunk::{Chunk, ChunkOwner, by_run, chunk_line, legacy_kind, pack, seal, should_seal},
+        client_events_query, last_client_events_query, read_only_statement,
+        scan::{self, RawChunk, Scan, Scanner, run_page_query, scan_query},
+        utils::{
+            apt_filter, cachyos_filter, duration_ms, garuda_filter, hex, pacman_filter, raw_rows,
+            rfc3339,
+        },
     },
 };
 
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Getters)]
 pub(crate) struct MySqlHandler {
+    #[getset(get = "pub(crate)")]
     pool: Data<MySqlPool>,
 }
 
 #[cfg_attr(coverage_nightly, coverage(off))]
 impl MySqlHandler {
-    async fn delete_output_data(&self) -> Result<(u64, u64)> {
-        let midnight = midnight()?;
-        info!("deleting records older than: {midnight}");
-        let output_count = sqlx::query!("DELETE FROM output WHERE timestamp < ?", midnight)
-            .execute(self.pool.as_ref())
+    async fn select_run_groups(&self) -> Result<Vec<RunGroup>> {
+        Ok(sqlx::query!(
+            r#"SELECT DISTINCT
+  COALESCE(bartoc_name, '') AS "bartoc_name!",
+  COALESCE(schedule_name, '') AS "schedule_name!"
+FROM
+  runs"#
+        )
+        .fetch_all(self.pool.as_ref())
+        .await?
+        .into_iter()
+        .map(|r| {
+            RunGroup::builder()
+                .bartoc_name(r.bartoc_name)
+                .schedule_name(r.schedule_name)
+                .build()
+        })
+        .collect())
+    }
+
+    /// When the `n`th newest run of the group last reported, if it has that many runs
+    async fn select_nth_newest_run(
+        &self,
+        group: &RunGroup,
+        n: u32,
+    ) -> Result<Option<OffsetDateTime>> {
+        let mut query = QueryBuilder::<MySql>::new(format!(
+            "SELECT {RUN_AT} AS at FROM runs WHERE COALESCE(bartoc_name, '') = "
+        ));
+        let _ = query
+            .push_bind(group.bartoc_name())
+            .push(" AND COALESCE(schedule_name, '') = ")
+            .push_bind(group.schedule_name())
+            .push(format!(" ORDER BY {RUN_AT} DESC LIMIT 1 OFFSET "))
+            .push_bind(i64::from(n.saturating_sub(1)));
+        let row = query.build().fetch_optional(self.pool.as_ref()).await?;
+        Ok(row
+            .map(|row| row.try_get::<Option<OffsetDateTime>, _>("at"))
+            .transpose()?
+            .flatten())
+    }
+
+    async fn select_expired_runs(
+        &self,
+        group: &RunGroup,
+        cutoffs: &Cutoffs,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        let mut query = QueryBuilder::<MySql>::new(
+            "SELECT cmd_uuid FROM runs WHERE COALESCE(bartoc_name, '') = ",
+        );
+        let _ = query
+            .push_bind(group.bartoc_name())
+            .push(" AND COALESCE(schedule_name, '') = ")
+            .push_bind(group.schedule_name())
+            .push(format!(" AND ((success = FALSE AND {RUN_AT} < "))
+            .push_bind(cutoffs.failed_before())
+            .push(format!(") OR (COALESCE(success, TRUE) AND {RUN_AT} < "))
+            .push_bind(cutoffs.before())
+            .push("))");
+        if let Some(keep_from) = cutoffs.keep_from() {
+            let _ = query
+                .push(format!(" AND {RUN_AT} < "))
+                .push_bind(keep_from)
+                .push("");
+        }
+        let _ = query.push(" LIMIT ").push_bind(i64::from(limit));
+        let rows = query.build().fetch_all(self.pool.as_ref()).await?;
+        let mut cmd_uuids = Vec::with_capacity(rows.len());
+        for row in rows {
+            cmd_uuids.push(row.try_get("cmd_uuid")?);
+        }
+        Ok(cmd_uuids)
+    }
+
+    /// Delete the chunks of the given runs, only those whose last line is before `before` if
+    /// set, returning the number of lines they held
+    async fn delete_chunks(
+        &self,
+        cmd_uuids: &[Uuid],
+        before: Option<OffsetDateTime>,
+    ) -> Result<u64> {
+        let filter = |query: &mut QueryBuilder<MySql>| {
+            let _ = query.push(" WHERE cmd_uuid IN (");
+            let mut uuids = query.separated(", ");
+            for cmd_uuid in cmd_uuids {
+                let _ = uuids.push_bind(*cmd_uuid);
+            }
+            let _ = query.push(")");
+            if let Some(before) = before {
+                let _ = query.push(" AND last_at < ").push_bind(before);
+            }
+        };
+        let mut tx = self.pool.begin().await?;
+        let mut count = QueryBuilder::<MySql>::new(
+            "SELECT CAST(COALESCE(SUM(line_count), 0) AS SIGNED) AS line_total FROM output_chunks",
+        );
+        filter(&mut count);
+        let lines: i64 = count
+            .build()
+            .fetch_one(&mut *tx)
             .await?
-            .rows_affected();
-        let exit_status_count =
-            sqlx::query!("DELETE FROM exit_status WHERE timestamp < ?", midnight)
-                .execute(self.pool.as_ref())
-                .await?
-                .rows_affected();
-        Ok((output_count, exit_status_count))
+            .try_get("line_total")?;
+        let mut delete = QueryBuilder::<MySql>::new("DELETE FROM output_chunks");
+        filter(&mut delete);
+        let _ = delete.build().execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(u64::try_from(lines)?)
     }
 
-    async fn delete_output_test_data(&self) -> Result<(u64, u64)> {
-        let midnight = midnight()?;
-        info!("deleting records older than: {midnight}");
-        let output_count = sqlx::query!("DELETE FROM output_test WHERE timestamp < ?", midnight)
+    async fn delete_run_rows(&self, cmd_uuids: &[Uuid]) -> Result<(u64, u64)> {
+        // output first, so an interrupted delete leaves the run to be found next time
+        let lines = self.delete_chunks(cmd_uuids, None).await?;
+        let mut query = QueryBuilder::<MySql>::new("DELETE FROM runs WHERE cmd_uuid IN (");
+        let mut uuids = query.separated(", ");
+        for cmd_uuid in cmd_uuids {
+            let _ = uuids.push_bind(*cmd_uuid);
+        }
+        let _ = query.push(")");
+        let runs = query
+            .build()
             .execute(self.pool.as_ref())
             .await?
             .rows_affected();
-        let exit_status_count =
-            sqlx::query!("DELETE FROM exit_status_test WHERE timestamp < ?", midnight)
-                .execute(self.pool.as_ref())
-                .await?
-                .rows_affected();
-        Ok((output_count, exit_status_count))
+        Ok((lines, runs))
     }
 
-    async fn update_output_data_garuda(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Garuda(garuda_filter(
-            self.output_data(name).await?,
-        )))
+    /// Commands with output before `cutoff` but no run row, from before runs were recorded
+    async fn select_expired_orphans(
+        &self,
+        cutoff: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        let mut query = QueryBuilder::<MySql>::new(
+            "SELECT DISTINCT o.cmd_uuid FROM output_chunks o WHERE o.last_at < ",
+        );
+        let _ = query
+            .push_bind(cutoff)
+            .push(" AND NOT EXISTS (SELECT 1 FROM runs r WHERE r.cmd_uuid = o.cmd_uuid) LIMIT ")
+            .push_bind(i64::from(limit));
+        let rows = query.build().fetch_all(self.pool.as_ref()).await?;
+        let mut cmd_uuids = Vec::with_capacity(rows.len());
+        for row in rows {
+            cmd_uuids.push(row.try_get("cmd_uuid")?);
+        }
+        Ok(cmd_uuids)
     }
 
-    async fn update_output_test_data_garuda(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Garuda(garuda_filter(
-            self.output_test_data(name).await?,
-        )))
+    async fn delete_orphan_rows(&self, cmd_uuids: &[Uuid], cutoff: OffsetDateTime) -> Result<u64> {
+        self.delete_chunks(cmd_uuids, Some(cutoff)).await
     }
 
-    async fn update_output_data_pacman(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Pacman(pacman_filter(
-            &self.output_data(name).await?,
+    async fn schedule_names(&self, name: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query(AssertSqlSafe(format!(
+            "SELECT DISTINCT
+  {RUN_SCHEDULE_NAME} AS schedule_name
+FROM
+  runs r
+LEFT JOIN
+  output_chunks o ON o.cmd_uuid = r.cmd_uuid
+WHERE
+  {RUN_BARTOC_NAME} = ?
+AND
+  {RUN_SCHEDULE_NAME} IS NOT NULL
+ORDER BY
+  schedule_name"
         )))
+        .bind(name)
+        .fetch_all(self.pool.as_ref())
+        .await?;
+        let mut names = Vec::with_capacity(rows.len());
+        for row in rows {
+            names.push(row.try_get("schedule_name")?);
+        }
+        Ok(names)
     }
 
-    async fn update_output_test_data_pacman(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Pacman(pacman_filter(
-            &self.output_test_data(name).await?,
-        )))
+    async fn insert_output_rows(&self, outputs: &[Output]) -> Result<u64> {
+        for (cmd_uuid, lines) in by_run(outputs) {
+            let mut tx = self.pool.begin().await?;
+            append_lines(&mut tx, cmd_uuid, &lines).await?;
+            tx.commit().await?;
+        }
+        Ok(u64::try_from(outputs.len())?)
     }
 
-    async fn update_output_data_cachyos(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Cachyos(cachyos_filter(
-            &self.output_data(name).await?,
-        )))
+    /// Seal the open chunks of a run
+    async fn seal_output(&self, cmd_uuid: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        seal_run(&mut tx, cmd_uuid).await?;
+        tx.commit().await?;
+        Ok(())
     }
 
-    async fn update_output_test_data_cachyos(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Cachyos(cachyos_filter(
-            &self.output_test_data(name).await?,
-        )))
+    /// Seal the open chunks of every run, including those of runs whose status never
+    /// arrived. Returns the number of runs sealed.
+    pub(crate) async fn seal_open_output(&self) -> Result<u64> {
+        let cmd_uuids = sqlx::query_scalar!(
+            r#"SELECT DISTINCT cmd_uuid AS "cmd_uuid: Uuid" FROM output_chunks WHERE sealed = FALSE"#
+        )
+        .fetch_all(self.pool.as_ref())
+        .await?;
+        for cmd_uuid in &cmd_uuids {
+            self.seal_output(*cmd_uuid).await?;
+        }
+        Ok(u64::try_from(cmd_uuids.len())?)
     }
 
-    async fn update_output_data_apt(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Apt(apt_filter(&self.output_data(name).await?)))
+    /// Insert an exported run and its output, unless a run with its UUID is already stored.
+    /// Returns whether the run was inserted.
+    async fn insert_imported_run(&self, run: &ExportRow, outputs: &[Output]) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let inserted = sqlx::query!(
+            "INSERT IGNORE INTO runs
+  (cmd_uuid, bartoc_uuid, bartoc_name, schedule_name, cmd, started_at, ended_at, duration_ms, exit_code,
+   exit_signal, core_dumped, wait_status, success)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            run.cmd_uuid().0,
+            run.bartoc_uuid().map(|uuid| uuid.0),
+            run.bartoc_name().as_deref(),
+            run.schedule_name().as_deref(),
+            run.cmd().as_deref(),
+            run.started_at().map(|at| at.0),
+            run.ended_at().map(|at| at.0),
+            duration_ms(run),
+            run.exit_code(),
+            run.exit_signal(),
+            run.core_dumped(),
+            run.wait_status(),
+            run.success(),
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if inserted {
+            insert_sealed_lines(
+                &mut tx,
+                run.cmd_uuid().0,
+                &outputs.iter().collect::<Vec<_>>(),
+            )
+            .await?;
+            tx.commit().await?;
+        } else {
+            tx.rollback().await?;
+        }
+        Ok(inserted)
     }
 
-    async fn update_output_test_data_apt(&self, name: &str) -> Result<UpdateKind> {
-        Ok(UpdateKind::Apt(apt_filter(
-            &self.output_test_data(name).await?,
-        )))
+    /// Move the rows of the output table, written before output was chunked, into chunks a
+    /// run at a time. Returns the number of lines moved.
+    pub(crate) async fn compact_legacy_output(&self) -> Result<u64> {
+        let mut moved = 0;
+        while let Some(cmd_uuid) =
+            sqlx::query_scalar!(r#"SELECT cmd_uuid AS "cmd_uuid: Uuid" FROM output LIMIT 1"#)
+                .fetch_optional(self.pool.as_ref())
+                .await?
+        {
+            let mut tx = self.pool.begin().await?;
+            let outputs = sqlx::query!(
+                r#"SELECT
+  bartoc_uuid AS "bartoc_uuid: Uuid",
+  bartoc_name,
+  cmd_uuid AS "cmd_uuid: Uuid",
+  cmd_name,
+  timestamp,
+  kind,
+  data
+FROM output
+WHERE cmd_uuid = ?
+ORDER BY timestamp, id
+FOR UPDATE"#,
+                cmd_uuid
+            )
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|r| {
+                Output::builder()
+                    .bartoc_uuid(UuidWrapper(r.bartoc_uuid))
+                    .bartoc_name(r.bartoc_name)
+                    .timestamp(OffsetDataTimeWrapper(r.timestamp))
+                    .cmd_uuid(UuidWrapper(r.cmd_uuid))
+                    .cmd_name(r.cmd_name)
+                    .kind(legacy_kind(&r.kind))
+                    .data(r.data)
+                    .build()
+            })
+            .collect::<Vec<Output>>();
+            insert_sealed_lines(&mut tx, cmd_uuid, &outputs.iter().collect::<Vec<_>>()).await?;
+            let _ = sqlx::query!("DELETE FROM output WHERE cmd_uuid = ?", cmd_uuid)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            moved += u64::try_from(outputs.len())?;
+        }
+        Ok(moved)
     }
 
-    async fn output_data(&self, name: &str) -> Result<Vec<String>> {
-        Ok(sqlx::query!(
-            r#"SELECT 
-  output.data 
-FROM 
-  output
-right join
-  exit_status on exit_status.cmd_uuid = output.cmd_uuid
-WHERE 
-  output.bartoc_name = ?
-and
-  exit_status.exit_code = 0
-order by 
-  output.timestamp"#,
-            name,
+    /// Record the end of a run. The row is normally created by the run's start record, but
+    /// a status that arrives first creates it and the start fills in the rest.
+    async fn upsert_status(&self, status: &Status) -> Result<u64> {
+        let rows = sqlx::query!(
+            "INSERT INTO runs
+  (cmd_uuid, ended_at, exit_code, exit_signal, core_dumped, wait_status, success)
+VALUES (?, ?, ?, ?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE
+  ended_at = VALUES(ended_at),
+  exit_code = VALUES(exit_code),
+  exit_signal = VALUES(exit_signal),
+  core_dumped = VALUES(core_dumped),
+  wait_status = VALUES(wait_status),
+  success = VALUES(success),
+  duration_ms = TIMESTAMPDIFF(MICROSECOND, started_at, VALUES(ended_at)) DIV 1000",
+            status.cmd_uuid().0,
+            status.timestamp().0,
+            status.exit_code(),
+            status.exit_signal(),
+            status.core_dumped(),
+            status.wait_status(),
+            status.success(),
         )
-        .fetch_all(self.pool.as_ref())
+        .execute(self.pool.as_ref())
         .await?
-        .into_iter()
-        .filter_map(|r| r.data)
-        .collect::<Vec<String>>())
+        .rows_affected();
+        Ok(rows)
     }
 
-    async fn output_test_data(&self, name: &str) -> Result<Vec<String>> {
-        Ok(sqlx::query!(
-            r#"SELECT 
-  output_test.data 
-FROM 
-  output_test
-right join
-  exit_status_test on exit_status_test.cmd_uuid = output_test.cmd_uuid
-WHERE 
-  output_test.bartoc_name = ?
-and
-  exit_status_test.exit_code = 0
-order by 
-  output_test.timestamp"#,
-            name,
+    /// Record the start of a run, filling in the duration if its status has already arrived.
+    async fn upsert_run_start(&self, run_start: &RunStart) -> Result<u64> {
+        let rows = sqlx::query!(
+            "INSERT INTO runs
+  (cmd_uuid, bartoc_uuid, bartoc_name, schedule_name, cmd, trigger_type, attempt, started_at)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE
+  bartoc_uuid = VALUES(bartoc_uuid),
+  bartoc_name = VALUES(bartoc_name),
+  schedule_name = VALUES(schedule_name),
+  cmd = VALUES(cmd),
+  trigger_type = VALUES(trigger_type),
+  attempt = VALUES(attempt),
+  started_at = VALUES(started_at),
+  duration_ms = TIMESTAMPDIFF(MICROSECOND, VALUES(started_at), ended_at) DIV 1000",
+            run_start.cmd_uuid().0,
+            run_start.bartoc_uuid().0,
+            run_start.bartoc_name(),
+            run_start.schedule_name(),
+            run_start.cmd(),
+            <TriggerKind as Into<&'static str>>::into(run_start.trigger()),
+            run_start.attempt(),
+            run_start.timestamp().0,
         )
-        .fetch_all(self.pool.as_ref())
+        .execute(self.pool.as_ref())
         .await?
-        .into_iter()
-        .filter_map(|r| r.data)
-        .collect::<Vec<String>>())
+        .rows_affected();
+        Ok(rows)
     }
 
-    async fn cmd_name_output(&self, name: &str) -> Result<Vec<String>> {
-        Ok(sqlx::query!(
-            "SELECT DISTINCT cmd_name FROM output WHERE bartoc_name = ? ORDER BY cmd_name",
+    /// Run a raw query in a read-only transaction, which is always rolled back
+    async fn raw_query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
+        let query = read_only_statement(query)?;
+        let mut tx = self.pool.begin_with("START TRANSACTION READ ONLY").await?;
+        let rows = sqlx::query(AssertSqlSafe(query))
+            .fetch_all(&mut *tx)
+            .await?;
+        tx.rollback().await?;
+        Ok(raw_rows(&rows, column_text))
+    }
+
+    async fn client_credential(&self, name: &str) -> Result<Option<ClientCredential>> {
+        let row_opt = sqlx::query!(
+            r#"SELECT token_hash, public_key, revoked_at IS NOT NULL AS "revoked!: bool"
+FROM client_registry
+WHERE name = ?"#,
             name
         )
-        .fetch_all(self.pool.as_ref())
-        .await?
-        .into_iter()
-        .map(|r| r.cmd_name)
-        .collect::<Vec<String>>())
+        .fetch_optional(self.pool.as_ref())
+        .await?;
+        let Some(row) = row_opt else {
+            return Ok(None);
+        };
+        let credential = match (row.revoked, row.token_hash, row.public_key) {
+            (false, Some(hash), _) => ClientCredential::Token(hash),
+            (false, None, Some(key)) => ClientCredential::PublicKey(key),
+            _ => ClientCredential::Revoked,
+        };
+        Ok(Some(credential))
     }
 
-    async fn cmd_name_output_test(&self, name: &str) -> Result<Vec<String>> {
-        Ok(sqlx::query!(
-            "SELECT DISTINCT cmd_name FROM output_test WHERE bartoc_name = ? ORDER BY cmd_name",
+    async fn enroll_client(&self, name: &str, credential: &ClientCredential) -> Result<bool> {
+        let (token_hash, public_key) = match credential {
+            ClientCredential::Token(hash) => (Some(hash.as_str()), None),
+            ClientCredential::PublicKey(key) => (None, Some(key.as_str())),
+            ClientCredential::Revoked => (None, None),
+        };
+        // MariaDB has no conditional upsert, so re-enroll a revoked name first and otherwise
+        // insert, leaving a name that is still enrolled untouched
+        let reenrolled = sqlx::query!(
+            "UPDATE client_registry
+SET token_hash = ?, public_key = ?, enrolled_at = NOW(), revoked_at = NULL
+WHERE name = ? AND revoked_at IS NOT NULL",
+            token_hash,
+            public_key,
             name
         )
-        .fetch_all(self.pool.as_ref())
+        .execute(self.pool.as_ref())
         .await?
-        .into_iter()
-        .map(|r| r.cmd_name)
-        .collect::<Vec<String>>())
-    }
-
-    async fn cmd_name_data_output(&self, name: &str, cmd_name: &str) -> Result<Vec<ListOutput>> {
-        let all_output = sqlx::query!(
-            "SELECT
-  output.timestamp,
-  output.cmd_name,
-  output.data,
-  exit_status.exit_code,
-  exit_status.success
-FROM
-  output
-RIGHT JOIN
-  exit_status ON exit_status.cmd_uuid = output.cmd_uuid
-WHERE
-  output.bartoc_name = ?
-AND
-  output.cmd_name = ?
-ORDER BY
-  output.timestamp",
+        .rows_affected();
+        if reenrolled > 0 {
+            return Ok(true);
+        }
+        let enrolled = sqlx::query!(
+            "INSERT IGNORE INTO client_registry (name, token_hash, public_key)
+VALUES (?, ?, ?)",
             name,
-            cmd_name
+            token_hash,
+            public_key
         )
-        .fetch_all(self.pool.as_ref())
+        .execute(self.pool.as_ref())
         .await?
-        .into_iter()
-        .map(|r| {
-            ListOutput::builder()
-                .maybe_timestamp(r.timestamp.map(OffsetDataTimeWrapper))
-                .maybe_data(r.data)
-                .exit_code(r.exit_code)
-                .success(r.success)
-                .build()
-        })
-        .collect::<Vec<ListOutput>>();
-
-        Ok(all_output)
+        .rows_affected();
+        Ok(enrolled > 0)
     }
 
-    async fn cmd_name_data_output_test(
-        &self,
-        name: &str,
-        cmd_name: &str,
-    ) -> Result<Vec<ListOutput>> {
-        let all_output = sqlx::query!(
-            "SELECT
-  output_test.timestamp,
-  output_test.cmd_name,
-  output_test.data,
-  exit_status_test.exit_code,
-  exit_status_test.success
-FROM
-  output_test
-RIGHT JOIN
-  exit_status_test ON exit_status_test.cmd_uuid = output_test.cmd_uuid
-WHERE
-  output_test.bartoc_name = ?
-AND
-  output_test.cmd_name = ?
-ORDER BY
-  output_test.timestamp",
-            name,
-            cmd_name
-        )
-        .fetch_all(self.pool.as_ref())
-        .await?
-        .into_iter()
-        .map(|r| {
-            ListOutput::builder()
-                .maybe_timestamp(r.timestamp.map(OffsetDataTimeWrapper))
-                .maybe_data(r.data)
-                .exit_code(r.exit_code)
-                .success(r.success)
-                .build()
-        })
-        .collect::<Vec<ListOutput>>();
-
-        Ok(all_output)
-    }
-
-    async fn failed_cmd_data_output(&self) -> Result<Vec<FailedOutput>> {
-        let all_output = sqlx::query!(
-            "
-select
-  output.timestamp,
-  output.bartoc_name,
-  output.cmd_name,
-  output.data,
-  exit_status.exit_code,
-  exit_status.success
-from
-  output
-right join
-  exit_status on output.cmd_uuid = exit_status.cmd_uuid
-where
-  exit_code != 0"
+    async fn revoke_client(&self, name: &str) -> Result<bool> {
+        let revoked = sqlx::query!(
+            "UPDATE client_registry
+SET token_hash = NULL, public_key = NULL, revoked_at = NOW()
+WHERE name = ? AND revoked_at IS NULL",
+            name
         )
-        .fetch_all(self.pool.as_ref())
+        .execute(self.pool.as_ref())
         .await?
-        .into_iter()
-        .map(|r| {
-            FailedOutput::builder()
-                .maybe_timestamp(r.timestamp.map(OffsetDataTimeWrapper))
-                .maybe_bartoc_name(r.bartoc_name)
-                .maybe_cmd_name(r.cmd_name)
-                .maybe_data(r.data)
-                .exit_code(r.exit_code)
-                .success(r.success)
-                .build()
-        })
-        .collect::<Vec<FailedOutput>>();
-
-        Ok(all_output)
-    }
-
-    async fn failed_cmd_data_output_test(&self) -> Result<Vec<FailedOutput>> {
-        let all_output = sqlx::query!(
-            "
-select
-  output_test.timestamp,
-  output_test.bartoc_name,
-  output_test.cmd_name,
-  output_test.data,
-  exit_status_test.exit_code,
-  exit_status_test.success
-from
-  output_test
-right join
-  exit_status_test on output_test.cmd_uuid = exit_status_test.cmd_uuid
-where
-  exit_code != 0"
+        .rows_affected();
+        Ok(revoked > 0)
+    }
+
+    async fn insert_client_event(&self, event: &ClientEvent) -> Result<()> {
+        let _res = sqlx::query!(
+            "INSERT INTO client_events
+  (bartoc_uuid, bartoc_name, ip, kind, reason, close_code, timestamp)
+VALUES (?, ?, ?, ?, ?, ?, ?)",
+            event.bartoc_uuid().0,
+            event.bartoc_name(),
+            event.ip(),
+            <&'static str>::from(event.kind()),
+            event.reason(),
+            event.close_code().map(i32::from),
+            event.timestamp().0,
         )
-        .fetch_all(self.pool.as_ref())
-        .await?
-        .into_iter()
-        .map(|r| {
-            FailedOutput::builder()
-                .maybe_timestamp(r.timestamp.map(OffsetDataTimeWrapper))
-                .maybe_bartoc_name(r.bartoc_name)
-                .maybe_cmd_name(r.cmd_name)
-                .maybe_data(r.data)
-                .exit_code(r.exit_code)
-                .success(r.success)
-                .build()
-        })
-        .collect::<Vec<FailedOutput>>();
+        .execute(self.pool.as_ref())
+        .await?;
+        Ok(())
+    }
 
-        Ok(all_output)
+    async fn select_client_events(&self, filter: &ClientEventFilter) -> Result<Vec<ClientEvent>> {
+        let mut query = client_events_query::<MySql>(filter, ("", ""));
+        let rows = query.build().fetch_all(self.pool.as_ref()).await?;
+        rows.iter().map(client_event).collect()
     }
 
-    async fn query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
-        let results = sqlx::query(AssertSqlSafe(query))
+    async fn select_last_client_events(&self) -> Result<Vec<ClientEvent>> {
+        let rows = sqlx::query(AssertSqlSafe(last_client_events_query()))
             .fetch_all(self.pool.as_ref())
             .await?;
-        let mut map = BTreeMap::new();
-        for (i, row) in results.iter().enumerate() {
-            let mut row_map = BTreeMap::new();
-            for (j, column) in row.columns().iter().enumerate() {
-                if let Ok(value) = row.try_get::<u64, usize>(j) {
-                    let _old = row_map.insert(column.name().to_string(), value.to_string());
-                } else if let Ok(value) = row.try_get::<OffsetDateTime, usize>(j) {
-                    let value = value.to_offset(offset!(-4));
-                    let _old = row_map.insert(column.name().to_string(), value.to_string());
-                } else if let Ok(value) = row.try_get::<String, usize>(j) {
-                    let _old = row_map.insert(column.name().to_string(), value);
-                } else if let Ok(value) = row.try_get::<Uuid, usize>(j) {
-                    let _old = row_map.insert(column.name().to_string(), value.to_string());
-                }
-            }
-            let _old = map.insert(i, row_map);
-        }
-        Ok(map)
+        rows.iter().map(client_event).collect()
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+impl Queryable for MySqlHandler {
+    async fn run_groups(&self) -> Result<Vec<RunGroup>> {
+        self.select_run_groups().await
     }
 
-    async fn cmd_output_by_name(
+    async fn nth_newest_run(&self, group: &RunGroup, n: u32) -> Result<Option<OffsetDateTime>> {
+        self.select_nth_newest_run(group, n).await
+    }
+
+    async fn expired_runs(
         &self,
-        cmd_name: &str,
-    ) -> Result<BTreeMap<String, Vec<ListOutput>>> {
-        let all_output = sqlx::query!(
-            "SELECT
-  output.timestamp,
-  output.bartoc_name,
-  output.cmd_name,
-  output.data,
-  exit_status.exit_code,
-  exit_status.success
-FROM
-  output
-RIGHT JOIN
-  exit_status ON exit_status.cmd_uuid = output.cmd_uuid
-WHERE
-  output.cmd_name = ?
-ORDER BY
-  output.timestamp",
-            cmd_name
-        )
-        .fetch_all(self.pool.as_ref())
-        .await?
-        .into_iter()
-        .map(|r| {
-            (
-                r.bartoc_name.clone(),
-                ListOutput::builder()
-                    .maybe_timestamp(r.timestamp.map(OffsetDataTimeWrapper))
-                    .maybe_data(r.data)
-                    .exit_code(r.exit_code)
-                    .success(r.success)
-                    .build(),
-            )
-        })
-        .fold(
-            BTreeMap::new(),
-            |mut acc, (bartoc_name_opt, list_output)| {
-                if let Some(bartoc_name) = bartoc_name_opt {
-                    acc.entry(bartoc_name)
-                        .or_insert_with(Vec::new)
-                        .push(list_output);
-                }
-                acc
-            },
-        );
+        group: &RunGroup,
+        cutoffs: &Cutoffs,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        self.select_expired_runs(group, cutoffs, limit).await
+    }
 
-        Ok(all_output)
+    async fn delete_runs(&self, cmd_uuids: &[Uuid]) -> Result<(u64, u64)> {
+        self.delete_run_rows(cmd_uuids).await
     }
 
-    async fn cmd_output_test_by_name(
+    async fn expired_orphans(&self, cutoff: OffsetDateTime, limit: u32) -> Result<Vec<Uuid>> {
+        self.select_expired_orphans(cutoff, limit).await
+    }
+
+    async fn delete_orphan_output(
         &self,
-        cmd_name: &str,
-    ) -> Result<BTreeMap<String, Vec<ListOutput>>> {
-        let all_output = sqlx::query!(
-            "SELECT
-  output.timestamp,
-  output.bartoc_name,
-  output.cmd_name,
-  output.data,
-  exit_status.exit_code,
-  exit_status.success
-FROM
-  output
-RIGHT JOIN
-  exit_status ON exit_status.cmd_uuid = output.cmd_uuid
-WHERE
-  output.cmd_name = ?
-ORDER BY
-  output.timestamp",
-            cmd_name
-        )
-        .fetch_all(self.pool.as_ref())
-        .await?
-        .into_iter()
-        .map(|r| {
-            (
-                r.bartoc_name.clone(),
-                ListOutput::builder()
-                    .maybe_timestamp(r.timestamp.map(OffsetDataTimeWrapper))
-                    .maybe_data(r.data)
-                    .exit_code(r.exit_code)
-                    .success(r.success)
-                    .build(),
-            )
+        cmd_uuids: &[Uuid],
+        cutoff: OffsetDateTime,
+    ) -> Result<u64> {
+        self.delete_orphan_rows(cmd_uuids, cutoff).await
+    }
+
+    async fn update_data(&self, kind: CliUpdateKind, name: &str) -> Result<UpdateKind> {
+        let data = scan::successful_output(self, name).await?;
+        Ok(match kind {
+            CliUpdateKind::Garuda => UpdateKind::Garuda(garuda_filter(data)),
+            CliUpdateKind::Pacman => UpdateKind::Pacman(pacman_filter(&data)),
+            CliUpdateKind::Cachyos => UpdateKind::Cachyos(cachyos_filter(&data)),
+            CliUpdateKind::Apt => UpdateKind::Apt(apt_filter(&data)),
         })
-        .fold(
-            BTreeMap::new(),
-            |mut acc, (bartoc_name_opt, list_output)| {
-                if let Some(bartoc_name) = bartoc_name_opt {
-                    acc.entry(bartoc_name)
-                        .or_insert_with(Vec::new)
-                        .push(list_output);
-                }
-                acc
-            },
-        );
+    }
+
+    async fn cmd_name_data(&self, name: &str, cmd_name: &str) -> Result<Vec<ListOutput>> {
+        scan::schedule_output(self, name, cmd_name).await
+    }
+
+    async fn failed_cmd_data(&self) -> Result<Vec<FailedOutput>> {
+        scan::failed_runs(self).await
+    }
+
+    async fn query(&self, filter: &QueryFilter) -> Result<Vec<QueryRow>> {
+        scan::query(self, filter).await
+    }
+
+    async fn raw_query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
+        self.raw_query(query).await
+    }
+
+    async fn search(&self, filter: &SearchFilter) -> Result<Vec<SearchHit>> {
+        scan::search(self, filter).await
+    }
+
+    async fn export(&self, filter: &ExportFilter) -> Result<Vec<ExportRow>> {
+        scan::export(self, filter).await
+    }
+
+    async fn run_output(&self, cmd_uuid: Uuid) -> Result<Vec<ExportRow>> {
+        scan::run_output(self, cmd_uuid).await
+    }
+
+    async fn run_tail(&self, cmd_uuid: Uuid, lines: usize) -> Result<Vec<ExportRow>> {
+        scan::run_tail(self, cmd_uuid, lines).await
+    }
 
-        Ok(all_output)
+    async fn cmd_data(&self, name: &str) -> Result<Vec<String>> {
+        self.schedule_names(name).await
+    }
+
+    async fn cmd_data_by_name(&self, cmd_name: &str) -> Result<BTreeMap<String, Vec<ListOutput>>> {
+        scan::schedule_output_by_name(self, cmd_name).await
+    }
+
+    async fn insert_outputs(&self, outputs: &[Output]) -> Result<u64> {
+        self.insert_output_rows(outputs).await
+    }
+
+    async fn insert_status(&self, status: &Status) -> Result<u64> {
+        let rows = self.upsert_status(status).await?;
+        self.seal_output(status.cmd_uuid().0).await?;
+        Ok(rows)
+    }
+
+    async fn import_run(&self, run: &ExportRow, outputs: &[Output]) -> Result<bool> {
+        self.insert_imported_run(run, outputs).await
+    }
+
+    async fn insert_run_start(&self, run_start: &RunStart) -> Result<u64> {
+        self.upsert_run_start(run_start).await
+    }
+
+    async fn client_credential(&self, name: &str) -> Result<Option<ClientCredential>> {
+        self.client_credential(name).await
+    }
+
+    async fn enroll_client(&self, name: &str, credential: &ClientCredential) -> Result<bool> {
+        self.enroll_client(name, credential).await
+    }
+
+    async fn revoke_client(&self, name: &str) -> Result<bool> {
+        self.revoke_client(name).await
+    }
+
+    async fn insert_client_event(&self, event: &ClientEvent) -> Result<()> {
+        self.insert_client_event(event).await
+    }
+
+    async fn client_events(&self, filter: &ClientEventFilter) -> Result<Vec<ClientEvent>> {
+        self.select_client_events(filter).await
+    }
+
+    async fn last_client_events(&self) -> Result<Vec<ClientEvent>> {
+        self.select_last_client_events().await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let _res = sqlx::query!("SELECT 1 AS ping")
+            .fetch_one(self.pool.as_ref())
+            .await?;
+        Ok(())
     }
 }
 
 #[cfg_attr(coverage_nightly, coverage(off))]
-impl Queryable for MySqlHandler {
-    async fn delete_data(&self, config: &Config) -> Result<(u64, u64)> {
-        match config.mariadb().output_table() {
-            OutputTableName::Output => self.delete_output_data().await,
-            OutputTableName::OutputTest => self.delete_output_test_data().await,
-        }
+impl Scanner for MySqlHandler {
+    fn fulltext_terms(words: &[String]) -> String {
+        words
+            .iter()
+            .map(|word| format!("+{word}"))
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 
-    async fn update_data(
+    async fn scan(
         &self,
-        config: &Config,
-        kind: CliUpdateKind,
-        name: &str,
-    ) -> Result<UpdateKind> {
-        match (config.mariadb().output_table(), kind) {
-            (OutputTableName::Output, CliUpdateKind::Garuda) => {
-                self.update_output_data_garuda(name).await
-            }
-            (OutputTableName::Output, CliUpdateKind::Pacman) => {
-                self.update_output_data_pacman(name).await
-            }
-            (OutputTableName::Output, CliUpdateKind::Cachyos) => {
-                self.update_output_data_cachyos(name).await
-            }
-            (OutputTableName::Output, CliUpdateKind::Apt) => {
-                self.update_output_data_apt(name).await
-            }
-            (OutputTableName::OutputTest, CliUpdateKind::Garuda) => {
-                self.update_output_test_data_garuda(name).await
-            }
-            (OutputTableName::OutputTest, CliUpdateKind::Pacman) => {
-                self.update_output_test_data_pacman(name).await
-            }
-            (OutputTableName::OutputTest, CliUpdateKind::Cachyos) => {
-                self.update_output_test_data_cachyos(name).await
-            }
-            (OutputTableName::OutputTest, CliUpdateKind::Apt) => {
-                self.update_output_test_data_apt(name).await
+        scan: &Scan,
+        mut visit: impl FnMut(RawChunk) -> Result<bool>,
+    ) -> Result<()> {
+        let mut query = scan_query::<MySql>(
+            scan,
+            ("MATCH (o.words) AGAINST (", " IN BOOLEAN MODE)"),
+            ("", ""),
+        );
+        let mut rows = query.build().fetch(self.pool.as_ref());
+        while let Some(row) = rows.try_next().await? {
+            if visit(raw_chunk(&row)?)? {
+                break;
             }
         }
+        Ok(())
     }
 
-    async fn cmd_name_data(
-        &self,
-        config: &Config,
-        name: &str,
-        cmd_name: &str,
-    ) -> Result<Vec<ListOutput>> {
-        match config.mariadb().output_table() {
-            OutputTableName::Output => self.cmd_name_data_output(name, cmd_name).await,
-            OutputTableName::OutputTest => self.cmd_name_data_output_test(name, cmd_name).await,
-        }
+    async fn run_page(&self, filter: &ExportFilter, limit: u32) -> Result<Vec<Uuid>> {
+        let mut query = run_page_query::<MySql>(filter, limit, ("", ""));
+        let rows = query.build().fetch_all(self.pool.as_ref()).await?;
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get("cmd_uuid"))
+            .collect::<Result<_, _>>()?)
     }
+}
 
-    async fn failed_cmd_data(&self, config: &Config) -> Result<Vec<FailedOutput>> {
-        match config.mariadb().output_table() {
-            OutputTableName::Output => self.failed_cmd_data_output().await,
-            OutputTableName::OutputTest => self.failed_cmd_data_output_test().await,
-        }
-    }
+/// The offset of the line after the newest stored line of a run, locking that chunk until
+/// the transaction ends
+async fn next_line(conn: &mut MySqlConnection, cmd_uuid: Uuid) -> Result<i64> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT first_line + line_count AS "next_line!"
+FROM output_chunks
+WHERE cmd_uuid = ?
+ORDER BY first_line DESC
+LIMIT 1
+FOR UPDATE"#,
+        cmd_uuid
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or_default())
+}
 
-    async fn query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
-        self.query(query).await
+/// Store chunks of one run, compressed when `sealed`
+async fn insert_chunks(
+    conn: &mut MySqlConnection,
+    owner: &ChunkOwner,
+    cmd_uuid: Uuid,
+    chunks: Vec<(i64, Chunk)>,
+    sealed: bool,
+) -> Result<()> {
+    for (first_line, chunk) in chunks {
+        let _ = sqlx::query!(
+            "INSERT INTO output_chunks
+  (bartoc_uuid, bartoc_name, cmd_uuid, cmd_name, first_line, line_count, first_at, last_at, words, sealed, data)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            owner.bartoc_uuid,
+            owner.bartoc_name,
+            cmd_uuid,
+            owner.cmd_name,
+            first_line,
+            chunk.line_count(),
+            chunk.first_at(),
+            chunk.last_at(),
+            chunk.words(),
+            sealed,
+            chunk.encode(sealed)?,
+        )
+        .execute(&mut *conn)
+        .await?;
     }
+    Ok(())
+}
 
-    async fn cmd_data(&self, config: &Config, name: &str) -> Result<Vec<String>> {
-        match config.mariadb().output_table() {
-            OutputTableName::Output => self.cmd_name_output(name).await,
-            OutputTableName::OutputTest => self.cmd_name_output_test(name).await,
-        }
+/// Append the lines of one run as open chunks after its newest chunk, without reading the
+/// stored output, and seal the run's open chunks once they are big enough
+async fn append_lines(conn: &mut MySqlConnection, cmd_uuid: Uuid, lines: &[&Output]) -> Result<()> {
+    let Some(first) = lines.first() else {
+        return Ok(());
+    };
+    let chunks = pack(
+        next_line(conn, cmd_uuid).await?,
+        lines.iter().map(|output| chunk_line(output)),
+    );
+    insert_chunks(conn, &ChunkOwner::from(*first), cmd_uuid, chunks, false).await?;
+    let open = sqlx::query!(
+        r#"SELECT
+  CAST(COALESCE(SUM(line_count), 0) AS SIGNED) AS "lines!",
+  CAST(COALESCE(SUM(LENGTH(data)), 0) AS SIGNED) AS "bytes!"
+FROM output_chunks
+WHERE cmd_uuid = ? AND sealed = FALSE"#,
+        cmd_uuid
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if should_seal(open.lines, open.bytes) {
+        seal_run(conn, cmd_uuid).await?;
     }
+    Ok(())
+}
 
-    async fn cmd_data_by_name(
-        &self,
-        config: &Config,
-        cmd_name: &str,
-    ) -> Result<BTreeMap<String, Vec<ListOutput>>> {
-        match config.mariadb().output_table() {
-            OutputTableName::Output => self.cmd_output_by_name(cmd_name).await,
-            OutputTableName::OutputTest => self.cmd_output_test_by_name(cmd_name).await,
-        }
+/// Store the lines of a run known to be complete, such as an imported one, as sealed chunks
+async fn insert_sealed_lines(
+    conn: &mut MySqlConnection,
+    cmd_uuid: Uuid,
+    lines: &[&Output],
+) -> Result<()> {
+    let Some(first) = lines.first() else {
+        return Ok(());
+    };
+    let chunks = pack(
+        next_line(conn, cmd_uuid).await?,
+        lines.iter().map(|output| chunk_line(output)),
+    );
+    insert_chunks(conn, &ChunkOwner::from(*first), cmd_uuid, chunks, true).await
+}
+
+/// Merge the open chunks of one run into sealed, compressed chunks
+async fn seal_run(conn: &mut MySqlConnection, cmd_uuid: Uuid) -> Result<()> {
+    let rows = sqlx::query!(
+        r#"SELECT bartoc_uuid AS "bartoc_uuid: Uuid", bartoc_name, cmd_name, first_line, data
+FROM output_chunks
+WHERE cmd_uuid = ? AND sealed = FALSE
+ORDER BY first_line
+FOR UPDATE"#,
+        cmd_uuid
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let Some(row) = rows.first() else {
+        return Ok(());
+    };
+    let owner = ChunkOwner {
+        bartoc_uuid: row.bartoc_uuid,
+        bartoc_name: row.bartoc_name.clone(),
+        cmd_name: row.cmd_name.clone(),
+    };
+    let open = rows
+        .iter()
+        .map(|row| Ok((row.first_line, Chunk::decode(&row.data, false)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let _ = sqlx::query!(
+        "DELETE FROM output_chunks WHERE cmd_uuid = ? AND sealed = FALSE",
+        cmd_uuid
+    )
+    .execute(&mut *conn)
+    .await?;
+    insert_chunks(conn, &owner, cmd_uuid, seal(open), true).await
+}
+
+fn raw_chunk(row: &MySqlRow) -> Result<RawChunk> {
+    Ok(RawChunk::builder()
+        .cmd_uuid(row.try_get("cmd_uuid")?)
+        .maybe_bartoc_uuid(row.try_get("bartoc_uuid")?)
+        .maybe_bartoc_name(row.try_get("bartoc_name")?)
+        .maybe_schedule_name(row.try_get("schedule_name")?)
+        .maybe_cmd(row.try_get("cmd")?)
+        .maybe_started_at(row.try_get("started_at")?)
+        .maybe_ended_at(row.try_get("ended_at")?)
+        .maybe_exit_code(row.try_get("exit_code")?)
+        .maybe_exit_signal(row.try_get("exit_signal")?)
+        .maybe_core_dumped(row.try_get("core_dumped")?)
+        .maybe_wait_status(row.try_get("wait_status")?)
+        .maybe_success(row.try_get("success")?)
+        .maybe_first_line(row.try_get("first_line")?)
+        .maybe_first_at(row.try_get("first_at")?)
+        .maybe_last_at(row.try_get("last_at")?)
+        .maybe_sealed(row.try_get("sealed")?)
+        .maybe_data(row.try_get("data")?)
+        .build())
+}
+
+fn client_event(row: &MySqlRow) -> Result<ClientEvent> {
+    build_client_event(
+        row.try_get("bartoc_uuid")?,
+        row.try_get("bartoc_name")?,
+        row.try_get("ip")?,
+        row.try_get("kind")?,
+        row.try_get("reason")?,
+        row.try_get("close_code")?,
+        row.try_get("timestamp")?,
+    )
+}
+
+/// Render a raw query value by the column's type
+fn column_text(row: &MySqlRow, index: usize) -> String {
+    if row.try_get_raw(index).is_ok_and(|value| value.is_null()) {
+        "NULL".to_string()
+    } else if let Ok(value) = row.try_get::<i64, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<u64, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<f64, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<f32, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<OffsetDateTime, _>(index) {
+        rfc3339(value)
+    } else if let Ok(value) = row.try_get::<PrimitiveDateTime, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<Date, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<Time, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<Uuid, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<String, _>(index) {
+        value
+    } else if let Ok(value) = row.try_get::<Vec<u8>, _>(index) {
+        hex(&value)
+    } else {
+        format!("<{}>", row.column(index).type_info().name())
     }
 }
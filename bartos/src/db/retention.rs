@@ -0,0 +1,286 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use anyhow::Result;
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+use time::{Duration, OffsetDateTime};
+use tracing::info;
+
+use crate::config::Config;
+
+use super::Queryable;
+
+/// The runs of one schedule on one bartoc client. Runs that never reported a name are
+/// grouped under an empty name.
+#[derive(Builder, Clone, Debug, Eq, Getters, Ord, PartialEq, PartialOrd)]
+#[getset(get = "pub(crate)")]
+pub(crate) struct RunGroup {
+    bartoc_name: String,
+    schedule_name: String,
+}
+
+/// Which runs of a [`RunGroup`] have expired
+#[derive(Builder, Clone, Copy, CopyGetters, Debug, Eq, PartialEq)]
+#[getset(get_copy = "pub(crate)")]
+pub(crate) struct Cutoffs {
+    /// Successful and unfinished runs that last reported before this have expired
+    before: OffsetDateTime,
+    /// Failed runs that last reported before this have expired
+    failed_before: OffsetDateTime,
+    /// Runs that last reported at or after this are among the newest kept regardless of age
+    keep_from: Option<OffsetDateTime>,
+}
+
+/// Delete the runs and output the `[retention]` rules no longer keep, a batch at a time.
+/// Returns the number of output lines and run rows deleted.
+pub(crate) async fn enforce<Q: Queryable>(
+    queryable: &Q,
+    config: &Config,
+    now: OffsetDateTime,
+) -> Result<(u64, u64)> {
+    let retention = config.retention();
+    let limit = retention.batch_size().max(1);
+    let (mut output_count, mut runs_count) = (0, 0);
+
+    for group in queryable.run_groups().await? {
+        let policy = retention.policy(group.bartoc_name(), group.schedule_name());
+        let keep_from = if policy.keep_last() == 0 {
+            None
+        } else {
+            match queryable.nth_newest_run(&group, policy.keep_last()).await? {
+                Some(at) => Some(at),
+                // fewer runs than keep_last, so all of them are kept
+                None => continue,
+            }
+        };
+        let cutoffs = Cutoffs::builder()
+            .before(now - days(policy.days()))
+            .failed_before(now - days(policy.failed_days()))
+            .maybe_keep_from(keep_from)
+            .build();
+        loop {
+            let expired = queryable.expired_runs(&group, &cutoffs, limit).await?;
+            if expired.is_empty() {
+                break;
+            }
+            let (output, runs) = queryable.delete_runs(&expired).await?;
+            output_count += output;
+            runs_count += runs;
+            if expired.len() < limit as usize {
+                break;
+            }
+        }
+    }
+
+    // Output whose run was never recorded can only follow the global setting.
+    let orphan_before = now - days(retention.days());
+    loop {
+        let orphans = queryable.expired_orphans(orphan_before, limit).await?;
+        if orphans.is_empty() {
+            break;
+        }
+        output_count += queryable
+            .delete_orphan_output(&orphans, orphan_before)
+            .await?;
+        if orphans.len() < limit as usize {
+            break;
+        }
+    }
+
+    info!("retention deleted {output_count} output lines and {runs_count} runs");
+    Ok((output_count, runs_count))
+}
+
+fn days(days: u32) -> Duration {
+    Duration::days(i64::from(days))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use libbarto::{OffsetDataTimeWrapper, Output, OutputKind, RunStart, Status, UuidWrapper};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use crate::{
+        config::{Config, Retention, RetentionRule},
+        db::{Queryable, Store, sqlite::SqliteHandler},
+    };
+
+    use super::enforce;
+
+    async fn store() -> Store {
+        // Every connection to `sqlite::memory:` is its own database, so keep just one.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let store = Store::Sqlite(SqliteHandler::builder().pool(pool).build());
+        let _applied = store.migrate().await.unwrap();
+        store
+    }
+
+    /// Record a finished run of `schedule` on `host` that ended `age` ago, with one output line
+    async fn record(
+        store: &Store,
+        host: &str,
+        schedule: &str,
+        age: Duration,
+        success: bool,
+    ) -> Uuid {
+        let cmd_uuid = Uuid::new_v4();
+        let at = OffsetDateTime::now_utc() - age;
+        let run_start = RunStart::builder()
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(host.to_string())
+            .schedule_name(schedule.to_string())
+            .cmd(format!("run {schedule}"))
+            .timestamp(OffsetDataTimeWrapper(at))
+            .build();
+        let _ = store.insert_run_start(&run_start).await.unwrap();
+        let output = Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(host.to_string())
+            .timestamp(OffsetDataTimeWrapper(at))
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .cmd_name(schedule.to_string())
+            .kind(OutputKind::Stdout)
+            .data(format!("{schedule} output"))
+            .build();
+        let _ = store.insert_outputs(&[output]).await.unwrap();
+        let status = Status::builder()
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .timestamp(OffsetDataTimeWrapper(at))
+            .exit_code(Some(i32::from(!success)))
+            .success(success)
+            .build();
+        let _ = store.insert_status(&status).await.unwrap();
+        cmd_uuid
+    }
+
+    async fn run_count(store: &Store, host: &str, schedule: &str) -> usize {
+        store.cmd_name_data(host, schedule).await.unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn deletes_runs_older_than_days() {
+        let store = store().await;
+        let mut config = Config::default();
+        let _ = config.set_retention(Retention::builder().days(7).batch_size(1).build());
+        for age in [1, 2, 10, 11, 12] {
+            let _ = record(&store, "host", "backup", Duration::days(age), true).await;
+        }
+
+        let (output, runs) = enforce(&store, &config, OffsetDateTime::now_utc())
+            .await
+            .unwrap();
+        assert_eq!((output, runs), (3, 3));
+        assert_eq!(run_count(&store, "host", "backup").await, 2);
+    }
+
+    #[tokio::test]
+    async fn keeps_failures_longer() {
+        let store = store().await;
+        let mut config = Config::default();
+        let _ = config.set_retention(Retention::builder().days(7).failed_days(30).build());
+        let _ok = record(&store, "host", "backup", Duration::days(10), true).await;
+        let _failed = record(&store, "host", "backup", Duration::days(10), false).await;
+        let _old = record(&store, "host", "backup", Duration::days(40), false).await;
+
+        let (_, runs) = enforce(&store, &config, OffsetDateTime::now_utc())
+            .await
+            .unwrap();
+        assert_eq!(runs, 2);
+        let failed = store.failed_cmd_data().await.unwrap();
+        assert_eq!(failed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_last_runs() {
+        let store = store().await;
+        let mut config = Config::default();
+        let _ = config.set_retention(
+            Retention::builder()
+                .days(1)
+                .keep_last(2)
+                .batch_size(2)
+                .build(),
+        );
+        for age in 10..15 {
+            let _ = record(&store, "host", "backup", Duration::days(age), true).await;
+        }
+        let _ = record(&store, "host", "update", Duration::days(20), true).await;
+
+        let _ = enforce(&store, &config, OffsetDateTime::now_utc())
+            .await
+            .unwrap();
+        assert_eq!(run_count(&store, "host", "backup").await, 2);
+        assert_eq!(run_count(&store, "host", "update").await, 1);
+    }
+
+    #[tokio::test]
+    async fn client_and_schedule_rules_apply() {
+        let store = store().await;
+        let mut config = Config::default();
+        let _ = config.set_retention(
+            Retention::builder()
+                .days(5)
+                .clients(BTreeMap::from([(
+                    "keeper".to_string(),
+                    RetentionRule::builder().days(100).build(),
+                )]))
+                .schedules(BTreeMap::from([(
+                    "audit".to_string(),
+                    RetentionRule::builder().days(365).build(),
+                )]))
+                .build(),
+        );
+        for (host, schedule) in [("host", "backup"), ("keeper", "backup"), ("host", "audit")] {
+            let _ = record(&store, host, schedule, Duration::days(50), true).await;
+        }
+
+        let (_, runs) = enforce(&store, &config, OffsetDateTime::now_utc())
+            .await
+            .unwrap();
+        assert_eq!(runs, 1);
+        assert_eq!(run_count(&store, "host", "backup").await, 0);
+        assert_eq!(run_count(&store, "keeper", "backup").await, 1);
+        assert_eq!(run_count(&store, "host", "audit").await, 1);
+    }
+
+    #[tokio::test]
+    async fn deletes_orphaned_output() {
+        let store = store().await;
+        let config = Config::default();
+        let old = OffsetDateTime::now_utc() - Duration::days(60);
+        let outputs: Vec<Output> = (0..3)
+            .map(|i| {
+                Output::builder()
+                    .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+                    .bartoc_name("host".to_string())
+                    .timestamp(OffsetDataTimeWrapper(old + Duration::seconds(i)))
+                    .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+                    .cmd_name("legacy".to_string())
+                    .kind(OutputKind::Stdout)
+                    .data("legacy output".to_string())
+                    .build()
+            })
+            .collect();
+        let _ = store.insert_outputs(&outputs).await.unwrap();
+
+        let (output, runs) = enforce(&store, &config, OffsetDateTime::now_utc())
+            .await
+            .unwrap();
+        assert_eq!((output, runs), (3, 0));
+    }
+}
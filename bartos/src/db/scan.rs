@@ -0,0 +1,700 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Reading output back out of its chunks. The backends select runs and chunks with SQL and
+//! the lines are decoded and filtered here, so every backend reads them the same way.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+use anyhow::Result;
+use bon::Builder;
+use libbarto::{
+    ExportFilter, ExportRow, FailedOutput, ListOutput, OffsetDataTimeWrapper, OutputKind,
+    QueryFilter, QueryRow, RunState, SearchFilter, SearchHit, UuidWrapper, search_words,
+};
+use sqlx::{Database, Encode, QueryBuilder, Type};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::db::{
+    DEFAULT_EXPORT_LIMIT, DEFAULT_QUERY_LIMIT, DEFAULT_SEARCH_LIMIT, MAX_EXPORT_LIMIT,
+    MAX_QUERY_LIMIT, MAX_SEARCH_LIMIT, RUN_BARTOC_NAME, RUN_SCHEDULE_NAME, SearchLine,
+    chunk::Chunk, search_hits, search_runs, utils::wire_success,
+};
+
+/// When a chunk starts: its first line, or the start of a run without output
+const CHUNK_AT: &str = "COALESCE(o.first_at, r.started_at)";
+/// When a chunk ends: its last line, or the start of a run without output
+const CHUNK_END: &str = "COALESCE(o.last_at, r.started_at)";
+
+/// The runs and chunks to read
+#[derive(Builder, Clone, Debug, Default)]
+pub(crate) struct Scan {
+    /// Only runs of the bartoc client with this name
+    client: Option<String>,
+    /// Only runs of the schedule with this name
+    schedule: Option<String>,
+    /// Only runs in this state
+    state: Option<RunState>,
+    /// Only runs that have ended
+    #[builder(default)]
+    finished: bool,
+    /// Only runs that exited with status 0
+    #[builder(default)]
+    exit_zero: bool,
+    /// Only chunks with lines at or after this time
+    since: Option<OffsetDateTime>,
+    /// Only chunks with lines before this time
+    until: Option<OffsetDateTime>,
+    /// Only chunks whose words match these full-text terms
+    terms: Option<String>,
+    /// Only chunks of these runs
+    cmd_uuids: Option<Vec<Uuid>>,
+    /// Only these runs, with or without output
+    runs: Option<Vec<Uuid>>,
+    /// Only chunks, leaving out runs without output
+    #[builder(default)]
+    output_only: bool,
+    /// The chunk with the newest last line first, rather than the oldest first line
+    #[builder(default)]
+    newest_first: bool,
+}
+
+/// Build the statement for a scan. Every value is bound, never spliced into the SQL.
+/// `matcher` wraps the bound full-text terms in the backend's predicate over `o.words` and
+/// `time` wraps a timestamp expression so it compares as a point in time on the backend,
+/// which SQLite needs `julianday` for.
+pub(crate) fn scan_query<DB>(
+    scan: &Scan,
+    matcher: (&str, &str),
+    time: (&str, &str),
+) -> QueryBuilder<DB>
+where
+    DB: Database,
+    <DB as Database>::Arguments: Default,
+    for<'t> String: Encode<'t, DB> + Type<DB>,
+    for<'t> OffsetDateTime: Encode<'t, DB> + Type<DB>,
+    for<'t> Uuid: Encode<'t, DB> + Type<DB>,
+{
+    let (open, close) = time;
+    let from = if scan.output_only {
+        "output_chunks o
+LEFT JOIN
+  runs r ON r.cmd_uuid = o.cmd_uuid"
+    } else {
+        "runs r
+LEFT JOIN
+  output_chunks o ON o.cmd_uuid = r.cmd_uuid"
+    };
+    let mut query = QueryBuilder::new(format!(
+        "SELECT
+  COALESCE(r.cmd_uuid, o.cmd_uuid) AS cmd_uuid,
+  COALESCE(r.bartoc_uuid, o.bartoc_uuid) AS bartoc_uuid,
+  {RUN_BARTOC_NAME} AS bartoc_name,
+  {RUN_SCHEDULE_NAME} AS schedule_name,
+  r.cmd,
+  r.started_at,
+  r.ended_at,
+  r.exit_code,
+  r.exit_signal,
+  r.core_dumped,
+  r.wait_status,
+  r.success,
+  o.first_line,
+  o.first_at,
+  o.last_at,
+  o.sealed,
+  o.data
+FROM
+  {from}
+WHERE
+  1 = 1"
+    ));
+    if let Some(client) = &scan.client {
+        let _ = query
+            .push(format!(" AND {RUN_BARTOC_NAME} = "))
+            .push_bind(client.clone());
+    }
+    if let Some(schedule) = &scan.schedule {
+        let _ = query
+            .push(format!(" AND {RUN_SCHEDULE_NAME} = "))
+            .push_bind(schedule.clone());
+    }
+    let _ = query.push(match scan.state {
+        Some(RunState::Succeeded) => " AND r.success = TRUE",
+        Some(RunState::Failed) => " AND r.success = FALSE",
+        Some(RunState::Running) => " AND r.ended_at IS NULL",
+        None => "",
+    });
+    if scan.finished {
+        let _ = query.push(" AND r.ended_at IS NOT NULL");
+    }
+    if scan.exit_zero {
+        let _ = query.push(" AND r.exit_code = 0");
+    }
+    if let Some(since) = scan.since {
+        let _ = query
+            .push(format!(" AND {open}{CHUNK_END}{close} >= {open}"))
+            .push_bind(since)
+            .push(close);
+    }
+    if let Some(until) = scan.until {
+        let _ = query
+            .push(format!(" AND {open}{CHUNK_AT}{close} < {open}"))
+            .push_bind(until)
+            .push(close);
+    }
+    if let Some(terms) = &scan.terms {
+        let _ = query
+            .push(format!(" AND {}", matcher.0))
+            .push_bind(terms.clone())
+            .push(matcher.1);
+    }
+    if let Some(cmd_uuids) = &scan.cmd_uuids {
+        let _ = query.push(" AND o.cmd_uuid IN (");
+        let mut uuids = query.separated(", ");
+        for cmd_uuid in cmd_uuids {
+            let _ = uuids.push_bind(*cmd_uuid);
+        }
+        let _ = query.push(")");
+    }
+    if let Some(runs) = &scan.runs {
+        let _ = query.push(" AND r.cmd_uuid IN (");
+        let mut uuids = query.separated(", ");
+        for cmd_uuid in runs {
+            let _ = uuids.push_bind(*cmd_uuid);
+        }
+        let _ = query.push(")");
+    }
+    // `IS NOT NULL` sorts a run without a start or output first on every backend.
+    let _ = query.push(if scan.newest_first {
+        format!(" ORDER BY {open}o.last_at{close} DESC, o.id DESC")
+    } else {
+        format!(" ORDER BY {CHUNK_AT} IS NOT NULL, {open}{CHUNK_AT}{close}, cmd_uuid, o.first_line")
+    });
+    query
+}
+
+/// Build the statement for a page of runs to export, oldest start first. `time` is as for
+/// [`scan_query`].
+pub(crate) fn run_page_query<DB>(
+    filter: &ExportFilter,
+    limit: u32,
+    time: (&str, &str),
+) -> QueryBuilder<DB>
+where
+    DB: Database,
+    <DB as Database>::Arguments: Default,
+    for<'t> i64: Encode<'t, DB> + Type<DB>,
+    for<'t> String: Encode<'t, DB> + Type<DB>,
+    for<'t> OffsetDateTime: Encode<'t, DB> + Type<DB>,
+{
+    let (open, close) = time;
+    let mut query = QueryBuilder::new("SELECT r.cmd_uuid FROM runs r WHERE 1 = 1");
+    if let Some(client) = filter.client() {
+        let _ = query
+            .push(" AND r.bartoc_name = ")
+            .push_bind(client.clone());
+    }
+    if let Some(schedule) = filter.schedule() {
+        let _ = query
+            .push(" AND r.schedule_name = ")
+            .push_bind(schedule.clone());
+    }
+    if let Some(since) = filter.since() {
+        let _ = query
+            .push(format!(" AND {open}r.started_at{close} >= {open}"))
+            .push_bind(since.0)
+            .push(close);
+    }
+    if let Some(until) = filter.until() {
+        let _ = query
+            .push(format!(" AND {open}r.started_at{close} < {open}"))
+            .push_bind(until.0)
+            .push(close);
+    }
+    let _ = query
+        .push(format!(
+            " ORDER BY r.started_at IS NOT NULL, {open}r.started_at{close}, r.cmd_uuid LIMIT "
+        ))
+        .push_bind(i64::from(limit))
+        .push(" OFFSET ")
+        .push_bind(i64::from(filter.offset()));
+    query
+}
+
+/// A chunk with the run it belongs to, or a run without output, as a scan returns it
+#[derive(Builder, Clone, Debug)]
+pub(crate) struct RawChunk {
+    cmd_uuid: Uuid,
+    bartoc_uuid: Option<Uuid>,
+    bartoc_name: Option<String>,
+    schedule_name: Option<String>,
+    cmd: Option<String>,
+    started_at: Option<OffsetDateTime>,
+    ended_at: Option<OffsetDateTime>,
+    exit_code: Option<i32>,
+    exit_signal: Option<i32>,
+    core_dumped: Option<bool>,
+    wait_status: Option<i32>,
+    success: Option<bool>,
+    first_line: Option<i64>,
+    first_at: Option<OffsetDateTime>,
+    last_at: Option<OffsetDateTime>,
+    sealed: Option<bool>,
+    data: Option<Vec<u8>>,
+}
+
+impl RawChunk {
+    /// Decode the chunk into its lines, or the run on its own when it has no output
+    fn rows(self) -> Result<Vec<ScanRow>> {
+        let Self {
+            cmd_uuid,
+            bartoc_uuid,
+            bartoc_name,
+            schedule_name,
+            cmd,
+            started_at,
+            ended_at,
+            exit_code,
+            exit_signal,
+            core_dumped,
+            wait_status,
+            success,
+            first_line,
+            sealed,
+            data,
+            ..
+        } = self;
+        let row = |line, timestamp, kind, data| ScanRow {
+            cmd_uuid,
+            bartoc_uuid,
+            bartoc_name: bartoc_name.clone(),
+            schedule_name: schedule_name.clone(),
+            cmd: cmd.clone(),
+            started_at,
+            ended_at,
+            exit_code,
+            exit_signal,
+            core_dumped,
+            wait_status,
+            success,
+            line,
+            timestamp,
+            kind,
+            data,
+        };
+        let Some(data) = data else {
+            return Ok(vec![row(None, None, None, None)]);
+        };
+        Ok(Chunk::decode(&data, sealed.unwrap_or(true))?
+            .into_lines()
+            .into_iter()
+            .zip(first_line.unwrap_or_default()..)
+            .map(|((at, kind, data), line)| row(Some(line), Some(at.0), Some(kind), Some(data)))
+            .collect())
+    }
+}
+
+/// One output line of a run, or a run without output
+#[derive(Clone, Debug)]
+struct ScanRow {
+    cmd_uuid: Uuid,
+    bartoc_uuid: Option<Uuid>,
+    bartoc_name: Option<String>,
+    schedule_name: Option<String>,
+    cmd: Option<String>,
+    started_at: Option<OffsetDateTime>,
+    ended_at: Option<OffsetDateTime>,
+    exit_code: Option<i32>,
+    exit_signal: Option<i32>,
+    core_dumped: Option<bool>,
+    wait_status: Option<i32>,
+    success: Option<bool>,
+    /// The offset of the line in its run
+    line: Option<i64>,
+    timestamp: Option<OffsetDateTime>,
+    kind: Option<OutputKind>,
+    data: Option<String>,
+}
+
+impl ScanRow {
+    /// When the row happened: its output line, or the start of a run without output
+    fn at(&self) -> Option<OffsetDateTime> {
+        self.timestamp.or(self.started_at)
+    }
+
+    fn list_output(self) -> ListOutput {
+        ListOutput::builder()
+            .maybe_timestamp(self.timestamp.map(OffsetDataTimeWrapper))
+            .maybe_data(self.data)
+            .maybe_exit_code(self.exit_code)
+            .maybe_exit_signal(self.exit_signal)
+            .core_dumped(self.core_dumped.unwrap_or_default())
+            .success(wire_success(self.success))
+            .build()
+    }
+
+    fn query_row(self) -> QueryRow {
+        QueryRow::builder()
+            .cmd_uuid(UuidWrapper(self.cmd_uuid))
+            .maybe_timestamp(self.at().map(OffsetDataTimeWrapper))
+            .maybe_bartoc_name(self.bartoc_name)
+            .maybe_schedule_name(self.schedule_name)
+            .maybe_data(self.data)
+            .maybe_exit_code(self.exit_code)
+            .maybe_exit_signal(self.exit_signal)
+            .core_dumped(self.core_dumped.unwrap_or_default())
+            .success(wire_success(self.success))
+            .build()
+    }
+
+    fn failed_output(self) -> FailedOutput {
+        FailedOutput::builder()
+            .maybe_timestamp(self.timestamp.map(OffsetDataTimeWrapper))
+            .maybe_bartoc_name(self.bartoc_name)
+            .maybe_cmd_name(self.schedule_name)
+            .maybe_data(self.data)
+            .maybe_exit_code(self.exit_code)
+            .maybe_exit_signal(self.exit_signal)
+            .core_dumped(self.core_dumped.unwrap_or_default())
+            .success(wire_success(self.success))
+            .build()
+    }
+
+    fn export_row(self) -> ExportRow {
+        ExportRow::builder()
+            .cmd_uuid(UuidWrapper(self.cmd_uuid))
+            .maybe_bartoc_uuid(self.bartoc_uuid.map(UuidWrapper))
+            .maybe_bartoc_name(self.bartoc_name)
+            .maybe_schedule_name(self.schedule_name)
+            .maybe_cmd(self.cmd)
+            .maybe_started_at(self.started_at.map(OffsetDataTimeWrapper))
+            .maybe_ended_at(self.ended_at.map(OffsetDataTimeWrapper))
+            .maybe_exit_code(self.exit_code)
+            .maybe_exit_signal(self.exit_signal)
+            .maybe_core_dumped(self.core_dumped)
+            .maybe_wait_status(self.wait_status)
+            .maybe_success(self.success)
+            .maybe_timestamp(self.timestamp.map(OffsetDataTimeWrapper))
+            .maybe_kind(self.kind)
+            .maybe_data(self.data)
+            .build()
+    }
+}
+
+/// A backend that can run a scan
+pub(crate) trait Scanner {
+    /// Render search words as full-text terms requiring every word for the backend's matcher
+    fn fulltext_terms(words: &[String]) -> String;
+
+    /// Run the scan, handing each chunk to `visit` in order until it returns `true`
+    async fn scan(&self, scan: &Scan, visit: impl FnMut(RawChunk) -> Result<bool>) -> Result<()>;
+
+    /// The UUIDs of a page of at most `limit` runs to export, in the order to export them
+    async fn run_page(&self, filter: &ExportFilter, limit: u32) -> Result<Vec<Uuid>>;
+}
+
+/// Every line of the scan, ordered by when it happened
+async fn scan_rows<S: Scanner>(scanner: &S, scan: &Scan) -> Result<Vec<ScanRow>> {
+    let mut rows = vec![];
+    scanner
+        .scan(scan, |chunk| {
+            rows.extend(chunk.rows()?);
+            Ok(false)
+        })
+        .await?;
+    rows.sort_by_key(ScanRow::at);
+    Ok(rows)
+}
+
+/// The output of the successful runs of a client
+pub(crate) async fn successful_output<S: Scanner>(scanner: &S, name: &str) -> Result<Vec<String>> {
+    let scan = Scan::builder()
+        .client(name.to_string())
+        .exit_zero(true)
+        .output_only(true)
+        .build();
+    Ok(scan_rows(scanner, &scan)
+        .await?
+        .into_iter()
+        .filter_map(|row| row.data)
+        .collect())
+}
+
+/// The output of the finished runs of a schedule on a client
+pub(crate) async fn schedule_output<S: Scanner>(
+    scanner: &S,
+    name: &str,
+    cmd_name: &str,
+) -> Result<Vec<ListOutput>> {
+    let scan = Scan::builder()
+        .client(name.to_string())
+        .schedule(cmd_name.to_string())
+        .finished(true)
+        .build();
+    Ok(scan_rows(scanner, &scan)
+        .await?
+        .into_iter()
+        .map(ScanRow::list_output)
+        .collect())
+}
+
+/// The output of every failed run
+pub(crate) async fn failed_runs<S: Scanner>(scanner: &S) -> Result<Vec<FailedOutput>> {
+    let scan = Scan::builder().state(RunState::Failed).build();
+    Ok(scan_rows(scanner, &scan)
+        .await?
+        .into_iter()
+        .map(ScanRow::failed_output)
+        .collect())
+}
+
+/// The output of the finished runs of a schedule, by client
+pub(crate) async fn schedule_output_by_name<S: Scanner>(
+    scanner: &S,
+    cmd_name: &str,
+) -> Result<BTreeMap<String, Vec<ListOutput>>> {
+    let scan = Scan::builder()
+        .schedule(cmd_name.to_string())
+        .finished(true)
+        .build();
+    let mut all_output: BTreeMap<String, Vec<ListOutput>> = BTreeMap::new();
+    for mut row in scan_rows(scanner, &scan).await? {
+        if let Some(bartoc_name) = row.bartoc_name.take() {
+            all_output
+                .entry(bartoc_name)
+                .or_default()
+                .push(row.list_output());
+        }
+    }
+    Ok(all_output)
+}
+
+/// The rows of a query filter. The chunks come oldest first, so the scan stops once the
+/// rows wanted all happened before the next chunk starts.
+pub(crate) async fn query<S: Scanner>(scanner: &S, filter: &QueryFilter) -> Result<Vec<QueryRow>> {
+    let text = filter.text().as_ref().map(|text| text.to_lowercase());
+    let since = filter.since().map(|since| since.0);
+    let until = filter.until().map(|until| until.0);
+    let scan = Scan::builder()
+        .maybe_client(filter.client().clone())
+        .maybe_schedule(filter.schedule().clone())
+        .maybe_state(filter.state())
+        .maybe_since(since)
+        .maybe_until(until)
+        .output_only(text.is_some())
+        .build();
+    let keep = |row: &ScanRow| {
+        let at = row.at();
+        since.is_none_or(|since| at.is_some_and(|at| at >= since))
+            && until.is_none_or(|until| at.is_some_and(|at| at < until))
+            && text.as_ref().is_none_or(|text| {
+                row.data
+                    .as_ref()
+                    .is_some_and(|data| data.to_lowercase().contains(text))
+            })
+    };
+    let limit = filter
+        .limit()
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .min(MAX_QUERY_LIMIT);
+    let offset = usize::try_from(filter.offset()).unwrap_or(usize::MAX);
+    let wanted = offset.saturating_add(usize::try_from(limit).unwrap_or(usize::MAX));
+    let mut rows: Vec<ScanRow> = vec![];
+    scanner
+        .scan(&scan, |chunk| {
+            if wanted == 0 {
+                return Ok(true);
+            }
+            if rows.len() >= wanted {
+                rows.sort_by_key(|row| (row.at(), row.cmd_uuid));
+                rows.truncate(wanted);
+                let next = chunk.first_at.or(chunk.started_at);
+                if next.is_some_and(|next| rows[wanted - 1].at().is_none_or(|at| at < next)) {
+                    return Ok(true);
+                }
+            }
+            rows.extend(chunk.rows()?.into_iter().filter(&keep));
+            Ok(false)
+        })
+        .await?;
+    rows.sort_by_key(|row| (row.at(), row.cmd_uuid));
+    Ok(rows
+        .into_iter()
+        .skip(offset)
+        .take(wanted.saturating_sub(offset))
+        .map(ScanRow::query_row)
+        .collect())
+}
+
+/// Full-text search the output, newest match first, with the lines around each match. The
+/// full-text index finds the chunks holding every word and the lines are matched here, so
+/// the words must appear in order on one line. The chunks come newest first, so the scan
+/// stops once the matches wanted are all newer than the next chunk.
+pub(crate) async fn search<S: Scanner>(
+    scanner: &S,
+    filter: &SearchFilter,
+) -> Result<Vec<SearchHit>> {
+    let words = search_words(filter.text());
+    if words.is_empty() {
+        return Ok(vec![]);
+    }
+    let since = filter.since().map(|since| since.0);
+    let until = filter.until().map(|until| until.0);
+    let scan = Scan::builder()
+        .maybe_client(filter.client().clone())
+        .maybe_schedule(filter.schedule().clone())
+        .maybe_since(since)
+        .maybe_until(until)
+        .terms(S::fulltext_terms(&words))
+        .output_only(true)
+        .newest_first(true)
+        .build();
+    let limit = filter
+        .limit()
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .min(MAX_SEARCH_LIMIT);
+    let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+    let newest_first = |line: &SearchLine| Reverse((line.timestamp, line.cmd_uuid, line.id));
+    let mut lines: Vec<SearchLine> = vec![];
+    scanner
+        .scan(&scan, |chunk| {
+            if limit == 0 {
+                return Ok(true);
+            }
+            if lines.len() >= limit {
+                lines.sort_by_key(newest_first);
+                lines.truncate(limit);
+                if chunk
+                    .last_at
+                    .is_some_and(|last| lines[limit - 1].timestamp > last)
+                {
+                    return Ok(true);
+                }
+            }
+            for row in chunk.rows()? {
+                let (Some(line), Some(timestamp), Some(data)) = (row.line, row.timestamp, row.data)
+                else {
+                    continue;
+                };
+                let in_time = since.is_none_or(|since| timestamp >= since)
+                    && until.is_none_or(|until| timestamp < until);
+                if in_time
+                    && search_words(&data)
+                        .windows(words.len())
+                        .any(|window| window == words.as_slice())
+                {
+                    lines.push(
+                        SearchLine::builder()
+                            .id(line)
+                            .cmd_uuid(row.cmd_uuid)
+                            .timestamp(timestamp)
+                            .bartoc_name(row.bartoc_name.unwrap_or_default())
+                            .maybe_schedule_name(row.schedule_name)
+                            .data(data)
+                            .build(),
+                    );
+                }
+            }
+            Ok(false)
+        })
+        .await?;
+    lines.sort_by_key(newest_first);
+    lines.truncate(limit);
+    let mut run_lines = vec![];
+    if filter.context() > 0 && !lines.is_empty() {
+        let scan = Scan::builder()
+            .cmd_uuids(search_runs(&lines))
+            .output_only(true)
+            .build();
+        scanner
+            .scan(&scan, |chunk| {
+                for row in chunk.rows()? {
+                    if let (Some(line), Some(data)) = (row.line, row.data) {
+                        run_lines.push((line, row.cmd_uuid, data));
+                    }
+                }
+                Ok(false)
+            })
+            .await?;
+        run_lines.sort_by_key(|(line, cmd_uuid, _)| (*cmd_uuid, *line));
+    }
+    Ok(search_hits(lines, run_lines, filter.context()))
+}
+
+/// A page of runs with their output, a row per line in the order written. The runs are
+/// paged first so a run is never split across pages.
+pub(crate) async fn export<S: Scanner>(
+    scanner: &S,
+    filter: &ExportFilter,
+) -> Result<Vec<ExportRow>> {
+    let limit = filter
+        .limit()
+        .unwrap_or(DEFAULT_EXPORT_LIMIT)
+        .min(MAX_EXPORT_LIMIT);
+    let page = scanner.run_page(filter, limit).await?;
+    if page.is_empty() {
+        return Ok(vec![]);
+    }
+    let order: HashMap<Uuid, usize> = page.iter().copied().zip(0..).collect();
+    let scan = Scan::builder().runs(page).build();
+    let mut rows = scan_rows(scanner, &scan).await?;
+    rows.sort_by_key(|row| (order.get(&row.cmd_uuid).copied(), row.line));
+    Ok(rows.into_iter().map(ScanRow::export_row).collect())
+}
+
+/// One run with its output, a row per line in the order written, or nothing when the run
+/// is unknown
+pub(crate) async fn run_output<S: Scanner>(scanner: &S, cmd_uuid: Uuid) -> Result<Vec<ExportRow>> {
+    let scan = Scan::builder().runs(vec![cmd_uuid]).build();
+    let mut rows = scan_rows(scanner, &scan).await?;
+    rows.sort_by_key(|row| row.line);
+    Ok(rows.into_iter().map(ScanRow::export_row).collect())
+}
+
+/// One run with at most its last `lines` lines of output, in the order written, or nothing
+/// when the run is unknown. The chunks are read newest first and only until there are
+/// enough lines. With no `lines` the run is returned on its own and no chunk is decoded.
+pub(crate) async fn run_tail<S: Scanner>(
+    scanner: &S,
+    cmd_uuid: Uuid,
+    lines: usize,
+) -> Result<Vec<ExportRow>> {
+    let scan = Scan::builder()
+        .runs(vec![cmd_uuid])
+        .newest_first(true)
+        .build();
+    let mut rows = vec![];
+    scanner
+        .scan(&scan, |chunk| {
+            if lines == 0 {
+                rows.extend(
+                    RawChunk {
+                        data: None,
+                        ..chunk
+                    }
+                    .rows()?,
+                );
+                return Ok(true);
+            }
+            rows.extend(chunk.rows()?);
+            Ok(rows.len() >= lines)
+        })
+        .await?;
+    rows.sort_by_key(|row| row.line);
+    let skip = rows.len().saturating_sub(lines.max(1));
+    Ok(rows
+        .into_iter()
+        .skip(skip)
+        .map(ScanRow::export_row)
+        .collect())
+}
@@ -0,0 +1,866 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use bon::Builder;
+use futures_util::TryStreamExt;
+use getset::Getters;
+use libbarto::{
+    CliUpdateKind, ClientEvent, ClientEventFilter, ExportFilter, ExportRow, FailedOutput,
+    ListOutput, OffsetDataTimeWrapper, Output, QueryFilter, QueryRow, RunStart, SearchFilter,
+    SearchHit, Status, TriggerKind, UpdateKind, UuidWrapper,
+};
+use sqlx::{
+    AssertSqlSafe, Column, QueryBuilder, Row, Sqlite, SqliteConnection, SqlitePool, TypeInfo,
+    ValueRef, sqlite::SqliteRow,
+};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use uuid::Uuid;
+
+use crate::{
+    common::ClientCredential,
+    db::{
+        Cutoffs, Queryable, RUN_AT, RUN_BARTOC_NAME, RUN_SCHEDULE_NAME, RunGroup,
+        build_client_event,
+        chunk::{Chunk, ChunkOwner, by_run, chunk_line, legacy_kind, pack, seal, should_seal},
+        client_events_query, last_client_events_query, read_only_statement,
+        scan::{self, RawChunk, Scan, Scanner, run_page_query, scan_query},
+        utils::{
+            apt_filter, cachyos_filter, duration_ms, garuda_filter, hex, pacman_filter, raw_rows,
+            rfc3339,
+        },
+    },
+};
+
+#[derive(Builder, Clone, Debug, Getters)]
+pub(crate) struct SqliteHandler {
+    #[getset(get = "pub(crate)")]
+    pool: SqlitePool,
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+impl SqliteHandler {
+    async fn select_run_groups(&self) -> Result<Vec<RunGroup>> {
+        let rows = sqlx::query(
+            "SELECT DISTINCT
+  COALESCE(bartoc_name, '') AS bartoc_name,
+  COALESCE(schedule_name, '') AS schedule_name
+FROM
+  runs",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut groups = Vec::with_capacity(rows.len());
+        for row in rows {
+            groups.push(
+                RunGroup::builder()
+                    .bartoc_name(row.try_get("bartoc_name")?)
+                    .schedule_name(row.try_get("schedule_name")?)
+                    .build(),
+            );
+        }
+        Ok(groups)
+    }
+
+    /// When the `n`th newest run of the group last reported, if it has that many runs
+    async fn select_nth_newest_run(
+        &self,
+        group: &RunGroup,
+        n: u32,
+    ) -> Result<Option<OffsetDateTime>> {
+        let mut query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {RUN_AT} AS at FROM runs WHERE COALESCE(bartoc_name, '') = "
+        ));
+        let _ = query
+            .push_bind(group.bartoc_name())
+            .push(" AND COALESCE(schedule_name, '') = ")
+            .push_bind(group.schedule_name())
+            .push(format!(
+                " ORDER BY julianday({RUN_AT}) DESC LIMIT 1 OFFSET "
+            ))
+            .push_bind(i64::from(n.saturating_sub(1)));
+        let row = query.build().fetch_optional(&self.pool).await?;
+        Ok(row
+            .map(|row| row.try_get::<Option<OffsetDateTime>, _>("at"))
+            .transpose()?
+            .flatten())
+    }
+
+    async fn select_expired_runs(
+        &self,
+        group: &RunGroup,
+        cutoffs: &Cutoffs,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT cmd_uuid FROM runs WHERE COALESCE(bartoc_name, '') = ",
+        );
+        let _ = query
+            .push_bind(group.bartoc_name())
+            .push(" AND COALESCE(schedule_name, '') = ")
+            .push_bind(group.schedule_name())
+            .push(format!(
+                " AND ((success = FALSE AND julianday({RUN_AT}) < julianday("
+            ))
+            .push_bind(cutoffs.failed_before())
+            .push(format!(
+                ")) OR (COALESCE(success, TRUE) AND julianday({RUN_AT}) < julianday("
+            ))
+            .push_bind(cutoffs.before())
+            .push(")))");
+        if let Some(keep_from) = cutoffs.keep_from() {
+            let _ = query
+                .push(format!(" AND julianday({RUN_AT}) < julianday("))
+                .push_bind(keep_from)
+                .push(")");
+        }
+        let _ = query.push(" LIMIT ").push_bind(i64::from(limit));
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let mut cmd_uuids = Vec::with_capacity(rows.len());
+        for row in rows {
+            cmd_uuids.push(row.try_get("cmd_uuid")?);
+        }
+        Ok(cmd_uuids)
+    }
+
+    /// Delete the chunks of the given runs, only those whose last line is before `before` if
+    /// set, returning the number of lines they held
+    async fn delete_chunks(
+        &self,
+        cmd_uuids: &[Uuid],
+        before: Option<OffsetDateTime>,
+    ) -> Result<u64> {
+        let filter = |query: &mut QueryBuilder<Sqlite>| {
+            let _ = query.push(" WHERE cmd_uuid IN (");
+            let mut uuids = query.separated(", ");
+            for cmd_uuid in cmd_uuids {
+                let _ = uuids.push_bind(*cmd_uuid);
+            }
+            let _ = query.push(")");
+            if let Some(before) = before {
+                let _ = query
+                    .push(" AND julianday(last_at) < julianday(")
+                    .push_bind(before)
+                    .push(")");
+            }
+        };
+        let mut tx = self.pool.begin().await?;
+        let mut count = QueryBuilder::<Sqlite>::new(
+            "SELECT CAST(COALESCE(SUM(line_count), 0) AS BIGINT) AS lines FROM output_chunks",
+        );
+        filter(&mut count);
+        let lines: i64 = count.build().fetch_one(&mut *tx).await?.try_get("lines")?;
+        let mut delete = QueryBuilder::<Sqlite>::new("DELETE FROM output_chunks");
+        filter(&mut delete);
+        let _ = delete.build().execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(u64::try_from(lines)?)
+    }
+
+    async fn delete_run_rows(&self, cmd_uuids: &[Uuid]) -> Result<(u64, u64)> {
+        // output first, so an interrupted delete leaves the run to be found next time
+        let lines = self.delete_chunks(cmd_uuids, None).await?;
+        let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM runs WHERE cmd_uuid IN (");
+        let mut uuids = query.separated(", ");
+        for cmd_uuid in cmd_uuids {
+            let _ = uuids.push_bind(*cmd_uuid);
+        }
+        let _ = query.push(")");
+        let runs = query.build().execute(&self.pool).await?.rows_affected();
+        Ok((lines, runs))
+    }
+
+    /// Commands with output before `cutoff` but no run row, from before runs were recorded
+    async fn select_expired_orphans(
+        &self,
+        cutoff: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT DISTINCT o.cmd_uuid FROM output_chunks o WHERE julianday(o.last_at) < julianday(",
+        );
+        let _ = query
+            .push_bind(cutoff)
+            .push(") AND NOT EXISTS (SELECT 1 FROM runs r WHERE r.cmd_uuid = o.cmd_uuid) LIMIT ")
+            .push_bind(i64::from(limit));
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let mut cmd_uuids = Vec::with_capacity(rows.len());
+        for row in rows {
+            cmd_uuids.push(row.try_get("cmd_uuid")?);
+        }
+        Ok(cmd_uuids)
+    }
+
+    async fn delete_orphan_rows(&self, cmd_uuids: &[Uuid], cutoff: OffsetDateTime) -> Result<u64> {
+        self.delete_chunks(cmd_uuids, Some(cutoff)).await
+    }
+
+    async fn schedule_names(&self, name: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query(AssertSqlSafe(format!(
+            "SELECT DISTINCT
+  {RUN_SCHEDULE_NAME} AS schedule_name
+FROM
+  runs r
+LEFT JOIN
+  output_chunks o ON o.cmd_uuid = r.cmd_uuid
+WHERE
+  {RUN_BARTOC_NAME} = ?
+AND
+  {RUN_SCHEDULE_NAME} IS NOT NULL
+ORDER BY
+  schedule_name"
+        )))
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut names = Vec::with_capacity(rows.len());
+        for row in rows {
+            names.push(row.try_get("schedule_name")?);
+        }
+        Ok(names)
+    }
+
+    async fn insert_output_rows(&self, outputs: &[Output]) -> Result<u64> {
+        for (cmd_uuid, lines) in by_run(outputs) {
+            // Take the write lock up front, so no other append reads the same newest chunk.
+            let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+            append_lines(&mut tx, cmd_uuid, &lines).await?;
+            tx.commit().await?;
+        }
+        Ok(u64::try_from(outputs.len())?)
+    }
+
+    /// Seal the open chunks of a run
+    async fn seal_output(&self, cmd_uuid: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        seal_run(&mut tx, cmd_uuid).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Seal the open chunks of every run, including those of runs whose status never
+    /// arrived. Returns the number of runs sealed.
+    pub(crate) async fn seal_open_output(&self) -> Result<u64> {
+        let rows = sqlx::query("SELECT DISTINCT cmd_uuid FROM output_chunks WHERE sealed = FALSE")
+            .fetch_all(&self.pool)
+            .await?;
+        for row in &rows {
+            self.seal_output(row.try_get("cmd_uuid")?).await?;
+        }
+        Ok(u64::try_from(rows.len())?)
+    }
+
+    /// Insert an exported run and its output, unless a run with its UUID is already stored.
+    /// Returns whether the run was inserted.
+    async fn insert_imported_run(&self, run: &ExportRow, outputs: &[Output]) -> Result<bool> {
+        // Take the write lock up front, so no other import of the run reads the same chunks.
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let inserted = sqlx::query(
+            "INSERT INTO runs
+  (cmd_uuid, bartoc_uuid, bartoc_name, schedule_name, cmd, started_at, ended_at, duration_ms, exit_code,
+   exit_signal, core_dumped, wait_status, success)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (cmd_uuid) DO NOTHING",
+        )
+        .bind(run.cmd_uuid().0)
+        .bind(run.bartoc_uuid().map(|uuid| uuid.0))
+        .bind(run.bartoc_name().as_deref())
+        .bind(run.schedule_name().as_deref())
+        .bind(run.cmd().as_deref())
+        .bind(run.started_at().map(|at| at.0))
+        .bind(run.ended_at().map(|at| at.0))
+        .bind(duration_ms(run))
+        .bind(run.exit_code())
+        .bind(run.exit_signal())
+        .bind(run.core_dumped())
+        .bind(run.wait_status())
+        .bind(run.success())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if inserted {
+            insert_sealed_lines(
+                &mut tx,
+                run.cmd_uuid().0,
+                &outputs.iter().collect::<Vec<_>>(),
+            )
+            .await?;
+            tx.commit().await?;
+        } else {
+            tx.rollback().await?;
+        }
+        Ok(inserted)
+    }
+
+    /// Move the rows of the output table, written before output was chunked, into chunks a
+    /// run at a time. Returns the number of lines moved.
+    pub(crate) async fn compact_legacy_output(&self) -> Result<u64> {
+        let mut moved = 0;
+        while let Some(row) = sqlx::query("SELECT cmd_uuid FROM output LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?
+        {
+            let cmd_uuid: Uuid = row.try_get("cmd_uuid")?;
+            let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+            let rows = sqlx::query(
+                "SELECT bartoc_uuid, bartoc_name, cmd_uuid, cmd_name, timestamp, kind, data
+FROM output
+WHERE cmd_uuid = ?
+ORDER BY julianday(timestamp), id",
+            )
+            .bind(cmd_uuid)
+            .fetch_all(&mut *tx)
+            .await?;
+            let outputs = rows.iter().map(legacy_output).collect::<Result<Vec<_>>>()?;
+            insert_sealed_lines(&mut tx, cmd_uuid, &outputs.iter().collect::<Vec<_>>()).await?;
+            let _ = sqlx::query("DELETE FROM output WHERE cmd_uuid = ?")
+                .bind(cmd_uuid)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            moved += u64::try_from(outputs.len())?;
+        }
+        Ok(moved)
+    }
+
+    /// Record the end of a run. The row is normally created by the run's start record, but
+    /// a status that arrives first creates it and the start fills in the rest.
+    async fn upsert_status(&self, status: &Status) -> Result<u64> {
+        let rows = sqlx::query("INSERT INTO runs
+  (cmd_uuid, ended_at, exit_code, exit_signal, core_dumped, wait_status, success)
+VALUES (?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (cmd_uuid) DO UPDATE SET
+  ended_at = excluded.ended_at,
+  exit_code = excluded.exit_code,
+  exit_signal = excluded.exit_signal,
+  core_dumped = excluded.core_dumped,
+  wait_status = excluded.wait_status,
+  success = excluded.success,
+  duration_ms = CAST(ROUND((julianday(excluded.ended_at) - julianday(runs.started_at)) * 86400000) AS INTEGER)")
+        .bind(status.cmd_uuid().0)
+        .bind(status.timestamp().0)
+        .bind(status.exit_code())
+        .bind(status.exit_signal())
+        .bind(status.core_dumped())
+        .bind(status.wait_status())
+        .bind(status.success())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(rows)
+    }
+
+    /// Record the start of a run, filling in the duration if its status has already arrived.
+    async fn upsert_run_start(&self, run_start: &RunStart) -> Result<u64> {
+        let rows = sqlx::query("INSERT INTO runs
+  (cmd_uuid, bartoc_uuid, bartoc_name, schedule_name, cmd, trigger_type, attempt, started_at)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (cmd_uuid) DO UPDATE SET
+  bartoc_uuid = excluded.bartoc_uuid,
+  bartoc_name = excluded.bartoc_name,
+  schedule_name = excluded.schedule_name,
+  cmd = excluded.cmd,
+  trigger_type = excluded.trigger_type,
+  attempt = excluded.attempt,
+  started_at = excluded.started_at,
+  duration_ms = CAST(ROUND((julianday(runs.ended_at) - julianday(excluded.started_at)) * 86400000) AS INTEGER)")
+        .bind(run_start.cmd_uuid().0)
+        .bind(run_start.bartoc_uuid().0)
+        .bind(run_start.bartoc_name())
+        .bind(run_start.schedule_name())
+        .bind(run_start.cmd())
+        .bind(<TriggerKind as Into<&'static str>>::into(run_start.trigger()))
+        .bind(run_start.attempt())
+        .bind(run_start.timestamp().0)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(rows)
+    }
+
+    /// Run a raw query with `query_only` set, so the connection refuses any write
+    async fn raw_query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
+        let query = read_only_statement(query)?;
+        let mut tx = self.pool.begin().await?;
+        let _ = sqlx::query("PRAGMA query_only = ON")
+            .execute(&mut *tx)
+            .await?;
+        let rows = sqlx::query(AssertSqlSafe(query)).fetch_all(&mut *tx).await;
+        let _ = sqlx::query("PRAGMA query_only = OFF")
+            .execute(&mut *tx)
+            .await?;
+        tx.rollback().await?;
+        Ok(raw_rows(&rows?, column_text))
+    }
+
+    async fn client_credential(&self, name: &str) -> Result<Option<ClientCredential>> {
+        let row_opt = sqlx::query(
+            "SELECT token_hash, public_key, revoked_at IS NOT NULL AS revoked
+FROM client_registry
+WHERE name = ?",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(row) = row_opt else {
+            return Ok(None);
+        };
+        let token_hash: Option<String> = row.try_get("token_hash")?;
+        let public_key: Option<String> = row.try_get("public_key")?;
+        let revoked: bool = row.try_get("revoked")?;
+        let credential = match (revoked, token_hash, public_key) {
+            (false, Some(hash), _) => ClientCredential::Token(hash),
+            (false, None, Some(key)) => ClientCredential::PublicKey(key),
+            _ => ClientCredential::Revoked,
+        };
+        Ok(Some(credential))
+    }
+
+    async fn enroll_client(&self, name: &str, credential: &ClientCredential) -> Result<bool> {
+        let (token_hash, public_key) = match credential {
+            ClientCredential::Token(hash) => (Some(hash.as_str()), None),
+            ClientCredential::PublicKey(key) => (None, Some(key.as_str())),
+            ClientCredential::Revoked => (None, None),
+        };
+        let enrolled = sqlx::query(
+            "INSERT INTO client_registry (name, token_hash, public_key)
+VALUES (?, ?, ?)
+ON CONFLICT (name) DO UPDATE SET
+  token_hash = excluded.token_hash,
+  public_key = excluded.public_key,
+  enrolled_at = CURRENT_TIMESTAMP,
+  revoked_at = NULL
+WHERE client_registry.revoked_at IS NOT NULL",
+        )
+        .bind(name)
+        .bind(token_hash)
+        .bind(public_key)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(enrolled > 0)
+    }
+
+    async fn revoke_client(&self, name: &str) -> Result<bool> {
+        let revoked = sqlx::query(
+            "UPDATE client_registry
+SET token_hash = NULL, public_key = NULL, revoked_at = CURRENT_TIMESTAMP
+WHERE name = ? AND revoked_at IS NULL",
+        )
+        .bind(name)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(revoked > 0)
+    }
+
+    async fn insert_client_event(&self, event: &ClientEvent) -> Result<()> {
+        let _res = sqlx::query(
+            "INSERT INTO client_events
+  (bartoc_uuid, bartoc_name, ip, kind, reason, close_code, timestamp)
+VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(event.bartoc_uuid().0)
+        .bind(event.bartoc_name())
+        .bind(event.ip())
+        .bind(<&'static str>::from(event.kind()))
+        .bind(event.reason())
+        .bind(event.close_code().map(i32::from))
+        .bind(event.timestamp().0)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn select_client_events(&self, filter: &ClientEventFilter) -> Result<Vec<ClientEvent>> {
+        let mut query = client_events_query::<Sqlite>(filter, ("julianday(", ")"));
+        let rows = query.build().fetch_all(&self.pool).await?;
+        rows.iter().map(client_event).collect()
+    }
+
+    async fn select_last_client_events(&self) -> Result<Vec<ClientEvent>> {
+        let rows = sqlx::query(AssertSqlSafe(last_client_events_query()))
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(client_event).collect()
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+impl Queryable for SqliteHandler {
+    async fn run_groups(&self) -> Result<Vec<RunGroup>> {
+        self.select_run_groups().await
+    }
+
+    async fn nth_newest_run(&self, group: &RunGroup, n: u32) -> Result<Option<OffsetDateTime>> {
+        self.select_nth_newest_run(group, n).await
+    }
+
+    async fn expired_runs(
+        &self,
+        group: &RunGroup,
+        cutoffs: &Cutoffs,
+        limit: u32,
+    ) -> Result<Vec<Uuid>> {
+        self.select_expired_runs(group, cutoffs, limit).await
+    }
+
+    async fn delete_runs(&self, cmd_uuids: &[Uuid]) -> Result<(u64, u64)> {
+        self.delete_run_rows(cmd_uuids).await
+    }
+
+    async fn expired_orphans(&self, cutoff: OffsetDateTime, limit: u32) -> Result<Vec<Uuid>> {
+        self.select_expired_orphans(cutoff, limit).await
+    }
+
+    async fn delete_orphan_output(
+        &self,
+        cmd_uuids: &[Uuid],
+        cutoff: OffsetDateTime,
+    ) -> Result<u64> {
+        self.delete_orphan_rows(cmd_uuids, cutoff).await
+    }
+
+    async fn update_data(&self, kind: CliUpdateKind, name: &str) -> Result<UpdateKind> {
+        let data = scan::successful_output(self, name).await?;
+        Ok(match kind {
+            CliUpdateKind::Garuda => UpdateKind::Garuda(garuda_filter(data)),
+            CliUpdateKind::Pacman => UpdateKind::Pacman(pacman_filter(&data)),
+            CliUpdateKind::Cachyos => UpdateKind::Cachyos(cachyos_filter(&data)),
+            CliUpdateKind::Apt => UpdateKind::Apt(apt_filter(&data)),
+        })
+    }
+
+    async fn cmd_name_data(&self, name: &str, cmd_name: &str) -> Result<Vec<ListOutput>> {
+        scan::schedule_output(self, name, cmd_name).await
+    }
+
+    async fn failed_cmd_data(&self) -> Result<Vec<FailedOutput>> {
+        scan::failed_runs(self).await
+    }
+
+    async fn query(&self, filter: &QueryFilter) -> Result<Vec<QueryRow>> {
+        scan::query(self, filter).await
+    }
+
+    async fn raw_query(&self, query: &str) -> Result<BTreeMap<usize, BTreeMap<String, String>>> {
+        self.raw_query(query).await
+    }
+
+    async fn search(&self, filter: &SearchFilter) -> Result<Vec<SearchHit>> {
+        scan::search(self, filter).await
+    }
+
+    async fn export(&self, filter: &ExportFilter) -> Result<Vec<ExportRow>> {
+        scan::export(self, filter).await
+    }
+
+    async fn run_output(&self, cmd_uuid: Uuid) -> Result<Vec<ExportRow>> {
+        scan::run_output(self, cmd_uuid).await
+    }
+
+    async fn run_tail(&self, cmd_uuid: Uuid, lines: usize) -> Result<Vec<ExportRow>> {
+        scan::run_tail(self, cmd_uuid, lines).await
+    }
+
+    async fn cmd_data(&self, name: &str) -> Result<Vec<String>> {
+        self.schedule_names(name).await
+    }
+
+    async fn cmd_data_by_name(&self, cmd_name: &str) -> Result<BTreeMap<String, Vec<ListOutput>>> {
+        scan::schedule_output_by_name(self, cmd_name).await
+    }
+
+    async fn insert_outputs(&self, outputs: &[Output]) -> Result<u64> {
+        self.insert_output_rows(outputs).await
+    }
+
+    async fn insert_status(&self, status: &Status) -> Result<u64> {
+        let rows = self.upsert_status(status).await?;
+        self.seal_output(status.cmd_uuid().0).await?;
+        Ok(rows)
+    }
+
+    async fn import_run(&self, run: &ExportRow, outputs: &[Output]) -> Result<bool> {
+        self.insert_imported_run(run, outputs).await
+    }
+
+    async fn insert_run_start(&self, run_start: &RunStart) -> Result<u64> {
+        self.upsert_run_start(run_start).await
+    }
+
+    async fn client_credential(&self, name: &str) -> Result<Option<ClientCredential>> {
+        self.client_credential(name).await
+    }
+
+    async fn enroll_client(&self, name: &str, credential: &ClientCredential) -> Result<bool> {
+        self.enroll_client(name, credential).await
+    }
+
+    async fn revoke_client(&self, name: &str) -> Result<bool> {
+        self.revoke_client(name).await
+    }
+
+    async fn insert_client_event(&self, event: &ClientEvent) -> Result<()> {
+        self.insert_client_event(event).await
+    }
+
+    async fn client_events(&self, filter: &ClientEventFilter) -> Result<Vec<ClientEvent>> {
+        self.select_client_events(filter).await
+    }
+
+    async fn last_client_events(&self) -> Result<Vec<ClientEvent>> {
+        self.select_last_client_events().await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let _res = sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(coverage_nightly, coverage(off))]
+impl Scanner for SqliteHandler {
+    fn fulltext_terms(words: &[String]) -> String {
+        words
+            .iter()
+            .map(|word| format!("\"{word}\""))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    async fn scan(
+        &self,
+        scan: &Scan,
+        mut visit: impl FnMut(RawChunk) -> Result<bool>,
+    ) -> Result<()> {
+        let mut query = scan_query::<Sqlite>(
+            scan,
+            (
+                "o.id IN (SELECT rowid FROM output_chunks_fts WHERE output_chunks_fts MATCH ",
+                ")",
+            ),
+            ("julianday(", ")"),
+        );
+        let mut rows = query.build().fetch(&self.pool);
+        while let Some(row) = rows.try_next().await? {
+            if visit(raw_chunk(&row)?)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_page(&self, filter: &ExportFilter, limit: u32) -> Result<Vec<Uuid>> {
+        let mut query = run_page_query::<Sqlite>(filter, limit, ("julianday(", ")"));
+        let rows = query.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get("cmd_uuid"))
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+/// The offset of the line after the newest stored line of a run
+async fn next_line(conn: &mut SqliteConnection, cmd_uuid: Uuid) -> Result<i64> {
+    Ok(sqlx::query_scalar(
+        "SELECT first_line + line_count FROM output_chunks WHERE cmd_uuid = ? ORDER BY first_line DESC LIMIT 1",
+    )
+    .bind(cmd_uuid)
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or_default())
+}
+
+/// Store chunks of one run, compressed when `sealed`
+async fn insert_chunks(
+    conn: &mut SqliteConnection,
+    owner: &ChunkOwner,
+    cmd_uuid: Uuid,
+    chunks: Vec<(i64, Chunk)>,
+    sealed: bool,
+) -> Result<()> {
+    for (first_line, chunk) in chunks {
+        let _ = sqlx::query(
+            "INSERT INTO output_chunks
+  (bartoc_uuid, bartoc_name, cmd_uuid, cmd_name, first_line, line_count, first_at, last_at, words, sealed, data)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(owner.bartoc_uuid)
+        .bind(&owner.bartoc_name)
+        .bind(cmd_uuid)
+        .bind(&owner.cmd_name)
+        .bind(first_line)
+        .bind(chunk.line_count())
+        .bind(chunk.first_at())
+        .bind(chunk.last_at())
+        .bind(chunk.words())
+        .bind(sealed)
+        .bind(chunk.encode(sealed)?)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Append the lines of one run as open chunks after its newest chunk, without reading the
+/// stored output, and seal the run's open chunks once they are big enough
+async fn append_lines(
+    conn: &mut SqliteConnection,
+    cmd_uuid: Uuid,
+    lines: &[&Output],
+) -> Result<()> {
+    let Some(first) = lines.first() else {
+        return Ok(());
+    };
+    let chunks = pack(
+        next_line(conn, cmd_uuid).await?,
+        lines.iter().map(|output| chunk_line(output)),
+    );
+    insert_chunks(conn, &ChunkOwner::from(*first), cmd_uuid, chunks, false).await?;
+    let open = sqlx::query(
+        "SELECT
+  CAST(COALESCE(SUM(line_count), 0) AS BIGINT) AS lines,
+  CAST(COALESCE(SUM(LENGTH(data)), 0) AS BIGINT) AS bytes
+FROM output_chunks
+WHERE cmd_uuid = ? AND sealed = FALSE",
+    )
+    .bind(cmd_uuid)
+    .fetch_one(&mut *conn)
+    .await?;
+    if should_seal(open.try_get("lines")?, open.try_get("bytes")?) {
+        seal_run(conn, cmd_uuid).await?;
+    }
+    Ok(())
+}
+
+/// Store the lines of a run known to be complete, such as an imported one, as sealed chunks
+async fn insert_sealed_lines(
+    conn: &mut SqliteConnection,
+    cmd_uuid: Uuid,
+    lines: &[&Output],
+) -> Result<()> {
+    let Some(first) = lines.first() else {
+        return Ok(());
+    };
+    let chunks = pack(
+        next_line(conn, cmd_uuid).await?,
+        lines.iter().map(|output| chunk_line(output)),
+    );
+    insert_chunks(conn, &ChunkOwner::from(*first), cmd_uuid, chunks, true).await
+}
+
+/// Merge the open chunks of one run into sealed, compressed chunks
+async fn seal_run(conn: &mut SqliteConnection, cmd_uuid: Uuid) -> Result<()> {
+    let rows = sqlx::query(
+        "SELECT bartoc_uuid, bartoc_name, cmd_name, first_line, data
+FROM output_chunks
+WHERE cmd_uuid = ? AND sealed = FALSE
+ORDER BY first_line",
+    )
+    .bind(cmd_uuid)
+    .fetch_all(&mut *conn)
+    .await?;
+    let Some(row) = rows.first() else {
+        return Ok(());
+    };
+    let owner = ChunkOwner {
+        bartoc_uuid: row.try_get("bartoc_uuid")?,
+        bartoc_name: row.try_get("bartoc_name")?,
+        cmd_name: row.try_get("cmd_name")?,
+    };
+    let open = rows
+        .iter()
+        .map(|row| -> Result<(i64, Chunk)> {
+            Ok((
+                row.try_get("first_line")?,
+                Chunk::decode(&row.try_get::<Vec<u8>, _>("data")?, false)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let _ = sqlx::query("DELETE FROM output_chunks WHERE cmd_uuid = ? AND sealed = FALSE")
+        .bind(cmd_uuid)
+        .execute(&mut *conn)
+        .await?;
+    insert_chunks(conn, &owner, cmd_uuid, seal(open), true).await
+}
+
+fn raw_chunk(row: &SqliteRow) -> Result<RawChunk> {
+    Ok(RawChunk::builder()
+        .cmd_uuid(row.try_get("cmd_uuid")?)
+        .maybe_bartoc_uuid(row.try_get("bartoc_uuid")?)
+        .maybe_bartoc_name(row.try_get("bartoc_name")?)
+        .maybe_schedule_name(row.try_get("schedule_name")?)
+        .maybe_cmd(row.try_get("cmd")?)
+        .maybe_started_at(row.try_get("started_at")?)
+        .maybe_ended_at(row.try_get("ended_at")?)
+        .maybe_exit_code(row.try_get("exit_code")?)
+        .maybe_exit_signal(row.try_get("exit_signal")?)
+        .maybe_core_dumped(row.try_get("core_dumped")?)
+        .maybe_wait_status(row.try_get("wait_status")?)
+        .maybe_success(row.try_get("success")?)
+        .maybe_first_line(row.try_get("first_line")?)
+        .maybe_first_at(row.try_get("first_at")?)
+        .maybe_last_at(row.try_get("last_at")?)
+        .maybe_sealed(row.try_get("sealed")?)
+        .maybe_data(row.try_get("data")?)
+        .build())
+}
+
+fn client_event(row: &SqliteRow) -> Result<ClientEvent> {
+    build_client_event(
+        row.try_get("bartoc_uuid")?,
+        row.try_get("bartoc_name")?,
+        row.try_get("ip")?,
+        row.try_get("kind")?,
+        row.try_get("reason")?,
+        row.try_get("close_code")?,
+        row.try_get("timestamp")?,
+    )
+}
+
+fn legacy_output(row: &SqliteRow) -> Result<Output> {
+    Ok(Output::builder()
+        .bartoc_uuid(UuidWrapper(row.try_get("bartoc_uuid")?))
+        .bartoc_name(row.try_get("bartoc_name")?)
+        .timestamp(OffsetDataTimeWrapper(row.try_get("timestamp")?))
+        .cmd_uuid(UuidWrapper(row.try_get("cmd_uuid")?))
+        .cmd_name(row.try_get("cmd_name")?)
+        .kind(legacy_kind(row.try_get("kind")?))
+        .data(row.try_get("data")?)
+        .build())
+}
+
+/// Render a raw query value. SQLite types values rather than columns, so this tries each
+/// storage class in turn.
+fn column_text(row: &SqliteRow, index: usize) -> String {
+    if row.try_get_raw(index).is_ok_and(|value| value.is_null()) {
+        "NULL".to_string()
+    } else if let Ok(value) = row.try_get::<i64, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<f64, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<bool, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<OffsetDateTime, _>(index) {
+        rfc3339(value)
+    } else if let Ok(value) = row.try_get::<PrimitiveDateTime, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<String, _>(index) {
+        value
+    } else if let Ok(value) = row.try_get::<Uuid, _>(index) {
+        value.to_string()
+    } else if let Ok(value) = row.try_get::<Vec<u8>, _>(index) {
+        hex(&value)
+    } else {
+        format!("<{}>", row.column(index).type_info().name())
+    }
+}
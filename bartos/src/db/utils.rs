@@ -6,10 +6,12 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::sync::LazyLock;
+use std::{collections::BTreeMap, fmt::Write as _, sync::LazyLock};
 
-use libbarto::{Garuda, Pacman};
+use libbarto::{ExportRow, Garuda, Pacman};
 use regex::Regex;
+use sqlx::{Column as _, Row};
+use time::{OffsetDateTime, UtcOffset, format_description::well_known::Rfc3339};
 
 // homebrew
 // ==> Upgrading 2 outdated packages:
@@ -228,9 +230,60 @@ pub(crate) fn apt_filter(data: &[String]) -> Vec<String> {
         .collect()
 }
 
+pub(crate) fn wire_success(success: Option<bool>) -> i8 {
+    i8::from(success.unwrap_or_default())
+}
+
+/// The rows of a raw query by index, each as column name → the value rendered by `text`
+pub(crate) fn raw_rows<R: Row>(
+    rows: &[R],
+    text: fn(&R, usize) -> String,
+) -> BTreeMap<usize, BTreeMap<String, String>> {
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let columns = row
+                .columns()
+                .iter()
+                .enumerate()
+                .map(|(j, column)| (column.name().to_string(), text(row, j)))
+                .collect();
+            (i, columns)
+        })
+        .collect()
+}
+
+/// Raw query timestamps are rendered as RFC 3339 in UTC, whatever zone the database uses
+pub(crate) fn rfc3339(at: OffsetDateTime) -> String {
+    let at = at.to_offset(UtcOffset::UTC);
+    at.format(&Rfc3339).unwrap_or_else(|_| at.to_string())
+}
+
+/// The duration of an imported run, which the backends otherwise work out as its start and
+/// status arrive
+pub(crate) fn duration_ms(run: &ExportRow) -> Option<i64> {
+    let duration = run.ended_at()?.0 - run.started_at()?.0;
+    i64::try_from(duration.whole_milliseconds()).ok()
+}
+
+/// Raw query binary values are rendered as `0x`-prefixed hex
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
 #[cfg(test)]
 mod test {
-    use super::{GARUDA_UPDATE_RE, apt_filter, cachyos_filter, garuda_filter, pacman_filter};
+    use time::macros::datetime;
+
+    use super::{
+        GARUDA_UPDATE_RE, apt_filter, cachyos_filter, garuda_filter, hex, pacman_filter, rfc3339,
+        wire_success,
+    };
 
     use anyhow::Result;
 
@@ -352,4 +405,25 @@ mod test {
         assert!((pacman.download_size() - 4.00).abs() < f64::EPSILON);
         assert!((pacman.install_size() - 0.00).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_wire_success() {
+        assert_eq!(wire_success(Some(true)), 1);
+        assert_eq!(wire_success(Some(false)), 0);
+        assert_eq!(wire_success(None), 0);
+    }
+
+    #[test]
+    fn test_rfc3339_is_utc() {
+        assert_eq!(
+            rfc3339(datetime!(2025-06-15 08:30:05 -4)),
+            "2025-06-15T12:30:05Z"
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(&[]), "0x");
+        assert_eq!(hex(&[0x00, 0xab, 0x0f]), "0x00ab0f");
+    }
 }
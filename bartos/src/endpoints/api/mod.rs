@@ -0,0 +1,1025 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The HTTP/JSON REST API
+//!
+//! Every endpoint authenticates the caller the way the `barto-cli` WebSocket does: the
+//! caller names itself with `?name=` (or its client certificate) and presents its enrolled
+//! credential or the shared `api_key`.
+
+mod model;
+
+use std::{collections::BTreeMap, time::Duration};
+
+use actix_web::{
+    HttpRequest, HttpResponse, Result,
+    error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    web::{Data, Path, Query, ServiceConfig, get, post, scope},
+};
+use libbarto::{
+    ClientEventFilter, OffsetDataTimeWrapper, QueryFilter, RunState, Schedules, SearchFilter,
+    parse_time_bound,
+};
+use time::OffsetDateTime;
+use tokio::sync::{Mutex, RwLock, broadcast};
+use tracing::{error, info};
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+use uuid::Uuid;
+use vergen_pretty::{Pretty, vergen_pretty_env};
+
+use crate::{
+    common::{Clients, DashboardTickets, WorkerSignal},
+    config::Config,
+    db::Queryable,
+    endpoints::insecure::{AuthLevel, Name, authenticate},
+    handler::cli,
+};
+
+use self::model::{
+    CleanupReport, Client, ClientEvent, ClientHistoryParams, CommandRun, DashboardTicket,
+    FailedRun, Run, RunOutput, RunsParams, Schedule, SearchMatch, SearchParams, Updates,
+    UpdatesParams,
+};
+
+/// How long a dashboard WebSocket ticket stays valid
+const DASHBOARD_TICKET_TTL: Duration = Duration::from_secs(30);
+
+/// The `OpenAPI` document of the REST API
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "bartos",
+        description = "The bartos REST API, mirroring the barto-cli operations as JSON"
+    ),
+    paths(
+        info,
+        clients,
+        client_versions,
+        client_history,
+        client_schedules,
+        client_commands,
+        client_command_runs,
+        command_runs,
+        runs,
+        failed_runs,
+        search,
+        run_output,
+        updates,
+        cleanup,
+        dashboard_ticket
+    ),
+    modifiers(&BearerAuth),
+    security(("bearer" = [])),
+    tags(
+        (name = "server", description = "bartos itself"),
+        (name = "clients", description = "Connected bartoc clients"),
+        (name = "runs", description = "Recorded command runs and their output"),
+    )
+)]
+pub(crate) struct ApiDoc;
+
+/// Adds the bearer credential to the `OpenAPI` document
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+/// Binds and authenticates the caller of a REST request
+async fn caller<T: Queryable>(
+    request: &HttpRequest,
+    name: &Name,
+    config: &Config,
+    queryable: &T,
+) -> Result<(Name, AuthLevel)> {
+    let name = name.bind(request, config)?;
+    let auth_level = authenticate(request, &name, config, queryable).await?;
+    Ok((name, auth_level))
+}
+
+fn internal(e: &anyhow::Error) -> actix_web::Error {
+    error!("api request failed: {e}");
+    ErrorInternalServerError("internal server error")
+}
+
+fn time_bound(value: Option<&str>, now: OffsetDateTime) -> Result<Option<OffsetDataTimeWrapper>> {
+    value
+        .map(|bound| {
+            parse_time_bound(bound, now)
+                .map_err(|_| ErrorBadRequest(format!("invalid time bound '{bound}'")))
+        })
+        .transpose()
+}
+
+/// The build information of bartos
+#[utoipa::path(
+    get,
+    path = "/v1/api/info",
+    tag = "server",
+    params(Name),
+    responses(
+        (status = 200, description = "The flattened build information", body = Object),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+async fn info<T: Queryable>(
+    request: HttpRequest,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let _ = caller(&request, &name, &config, queryable.get_ref()).await?;
+    let pretty = Pretty::builder()
+        .env(vergen_pretty_env!())
+        .flatten(true)
+        .build();
+    Ok(HttpResponse::Ok().json(pretty))
+}
+
+/// The connected bartoc clients, sorted by name
+#[utoipa::path(
+    get,
+    path = "/v1/api/clients",
+    tag = "clients",
+    params(Name),
+    responses(
+        (status = 200, description = "The connected clients", body = [Client]),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn clients<T: Queryable>(
+    request: HttpRequest,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+    clients_mutex: Data<Mutex<Clients>>,
+) -> Result<HttpResponse> {
+    let _ = caller(&request, &name, &config, queryable.get_ref()).await?;
+    let mut clients = clients_mutex
+        .lock()
+        .await
+        .clients()
+        .iter()
+        .map(|(id, data)| Client::new(*id, data))
+        .collect::<Vec<Client>>();
+    clients.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+    Ok(HttpResponse::Ok().json(clients))
+}
+
+/// The bartoc version of each connected client, by client name
+#[utoipa::path(
+    get,
+    path = "/v1/api/clients/versions",
+    tag = "clients",
+    params(Name),
+    responses(
+        (status = 200, description = "The version of each client", body = BTreeMap<String, String>),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn client_versions<T: Queryable>(
+    request: HttpRequest,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+    clients_mutex: Data<Mutex<Clients>>,
+) -> Result<HttpResponse> {
+    let _ = caller(&request, &name, &config, queryable.get_ref()).await?;
+    let versions = clients_mutex
+        .lock()
+        .await
+        .clients()
+        .values()
+        .map(|data| {
+            let version = data
+                .bartoc_info()
+                .as_ref()
+                .map_or_else(|| "unknown".to_string(), |info| info.version().clone());
+            (data.name().clone(), version)
+        })
+        .collect::<BTreeMap<String, String>>();
+    Ok(HttpResponse::Ok().json(versions))
+}
+
+/// The recorded connects and disconnects of the bartoc clients, newest first
+#[utoipa::path(
+    get,
+    path = "/v1/api/clients/history",
+    tag = "clients",
+    params(ClientHistoryParams, Name),
+    responses(
+        (status = 200, description = "The client events", body = [ClientEvent]),
+        (status = 400, description = "An invalid time bound"),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn client_history<T: Queryable>(
+    request: HttpRequest,
+    params: Query<ClientHistoryParams>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let filter = ClientEventFilter::builder()
+        .maybe_client(params.client.clone())
+        .maybe_since(time_bound(
+            params.since.as_deref(),
+            OffsetDateTime::now_utc(),
+        )?)
+        .maybe_limit(params.limit)
+        .build();
+    let events = queryable
+        .client_events(&filter)
+        .await
+        .map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(events.iter().map(ClientEvent::from).collect::<Vec<_>>()))
+}
+
+/// The schedules configured for a client, with when each runs next
+#[utoipa::path(
+    get,
+    path = "/v1/api/clients/{client}/schedules",
+    tag = "clients",
+    params(("client" = String, Path, description = "The name of the bartoc client"), Name),
+    responses(
+        (status = 200, description = "The schedules of the client", body = [Schedule]),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn client_schedules<T: Queryable>(
+    request: HttpRequest,
+    client: Path<String>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+    live_schedules: Data<RwLock<BTreeMap<String, Schedules>>>,
+) -> Result<HttpResponse> {
+    let _ = caller(&request, &name, &config, queryable.get_ref()).await?;
+    let now = OffsetDateTime::now_utc();
+    let schedules = live_schedules
+        .read()
+        .await
+        .get(client.as_str())
+        .map(|schedules| {
+            schedules
+                .schedules()
+                .iter()
+                .map(|schedule| Schedule::new(schedule, now))
+                .collect::<Vec<Schedule>>()
+        })
+        .unwrap_or_default();
+    Ok(HttpResponse::Ok().json(schedules))
+}
+
+/// The commands recorded for a client
+#[utoipa::path(
+    get,
+    path = "/v1/api/clients/{client}/commands",
+    tag = "clients",
+    params(("client" = String, Path, description = "The name of the bartoc client"), Name),
+    responses(
+        (status = 200, description = "The command names", body = [String]),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn client_commands<T: Queryable>(
+    request: HttpRequest,
+    client: Path<String>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let cmds = queryable
+        .cmd_data(&client)
+        .await
+        .map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(cmds))
+}
+
+/// The runs of one command on a client
+#[utoipa::path(
+    get,
+    path = "/v1/api/clients/{client}/commands/{command}",
+    tag = "clients",
+    params(
+        ("client" = String, Path, description = "The name of the bartoc client"),
+        ("command" = String, Path, description = "The name of the command"),
+        Name
+    ),
+    responses(
+        (status = 200, description = "The runs of the command", body = [CommandRun]),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn client_command_runs<T: Queryable>(
+    request: HttpRequest,
+    path: Path<(String, String)>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let (client, command) = path.into_inner();
+    let runs = queryable
+        .cmd_name_data(&client, &command)
+        .await
+        .map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(runs.iter().map(CommandRun::from).collect::<Vec<_>>()))
+}
+
+/// The runs of one command on every client, by client name
+#[utoipa::path(
+    get,
+    path = "/v1/api/commands/{command}",
+    tag = "runs",
+    params(("command" = String, Path, description = "The name of the command"), Name),
+    responses(
+        (status = 200, description = "The runs of the command on each client", body = BTreeMap<String, Vec<CommandRun>>),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn command_runs<T: Queryable>(
+    request: HttpRequest,
+    command: Path<String>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let by_client = queryable
+        .cmd_data_by_name(&command)
+        .await
+        .map_err(|e| internal(&e))?
+        .iter()
+        .map(|(client, runs)| (client.clone(), runs.iter().map(CommandRun::from).collect()))
+        .collect::<BTreeMap<String, Vec<CommandRun>>>();
+    Ok(HttpResponse::Ok().json(by_client))
+}
+
+/// The recorded runs matching a filter, oldest first
+#[utoipa::path(
+    get,
+    path = "/v1/api/runs",
+    tag = "runs",
+    params(RunsParams, Name),
+    responses(
+        (status = 200, description = "The matching runs", body = [Run]),
+        (status = 400, description = "An invalid time bound or state"),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn runs<T: Queryable>(
+    request: HttpRequest,
+    params: Query<RunsParams>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let now = OffsetDateTime::now_utc();
+    let state = params
+        .state
+        .as_deref()
+        .map(|state| {
+            RunState::try_from(state)
+                .map_err(|_| ErrorBadRequest(format!("invalid run state '{state}'")))
+        })
+        .transpose()?;
+    let filter = QueryFilter::builder()
+        .maybe_client(params.client.clone())
+        .maybe_schedule(params.schedule.clone())
+        .maybe_since(time_bound(params.since.as_deref(), now)?)
+        .maybe_until(time_bound(params.until.as_deref(), now)?)
+        .maybe_state(state)
+        .maybe_text(params.text.clone())
+        .maybe_limit(params.limit)
+        .offset(params.offset.unwrap_or_default())
+        .build();
+    let rows = queryable.query(&filter).await.map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(rows.iter().map(Run::from).collect::<Vec<_>>()))
+}
+
+/// The last run of every command that failed
+#[utoipa::path(
+    get,
+    path = "/v1/api/runs/failed",
+    tag = "runs",
+    params(Name),
+    responses(
+        (status = 200, description = "The failed runs", body = [FailedRun]),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn failed_runs<T: Queryable>(
+    request: HttpRequest,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let failed = queryable
+        .failed_cmd_data()
+        .await
+        .map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(failed.iter().map(FailedRun::from).collect::<Vec<_>>()))
+}
+
+/// The stored output lines matching a full-text search, newest first
+#[utoipa::path(
+    get,
+    path = "/v1/api/runs/search",
+    tag = "runs",
+    params(SearchParams, Name),
+    responses(
+        (status = 200, description = "The matching lines", body = [SearchMatch]),
+        (status = 400, description = "An invalid time bound"),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn search<T: Queryable>(
+    request: HttpRequest,
+    params: Query<SearchParams>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let now = OffsetDateTime::now_utc();
+    let filter = SearchFilter::builder()
+        .text(params.text.clone())
+        .maybe_client(params.client.clone())
+        .maybe_schedule(params.schedule.clone())
+        .maybe_since(time_bound(params.since.as_deref(), now)?)
+        .maybe_until(time_bound(params.until.as_deref(), now)?)
+        .maybe_limit(params.limit)
+        .context(params.context.unwrap_or_default())
+        .build();
+    let hits = queryable.search(&filter).await.map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(hits.iter().map(SearchMatch::from).collect::<Vec<_>>()))
+}
+
+/// One run with all of its output
+#[utoipa::path(
+    get,
+    path = "/v1/api/runs/{cmd_uuid}/output",
+    tag = "runs",
+    params(("cmd_uuid" = Uuid, Path, description = "The UUID of the run"), Name),
+    responses(
+        (status = 200, description = "The run and its output", body = RunOutput),
+        (status = 401, description = "Missing or invalid credential"),
+        (status = 404, description = "No run with this UUID was recorded"),
+    )
+)]
+async fn run_output<T: Queryable>(
+    request: HttpRequest,
+    cmd_uuid: Path<Uuid>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let rows = queryable
+        .run_output(*cmd_uuid)
+        .await
+        .map_err(|e| internal(&e))?;
+    let run = RunOutput::from_rows(&rows).ok_or_else(|| ErrorNotFound("no such run"))?;
+    Ok(HttpResponse::Ok().json(run))
+}
+
+/// The pending updates a client last reported
+#[utoipa::path(
+    get,
+    path = "/v1/api/updates/{client}",
+    tag = "clients",
+    params(
+        ("client" = String, Path, description = "The name of the bartoc client"),
+        UpdatesParams,
+        Name
+    ),
+    responses(
+        (status = 200, description = "The pending updates", body = Updates),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn updates<T: Queryable>(
+    request: HttpRequest,
+    client: Path<String>,
+    params: Query<UpdatesParams>,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let _ = caller(&request, &name, &config, queryable).await?;
+    let update_kind = queryable
+        .update_data(params.kind.into(), &client)
+        .await
+        .map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(Updates::from(&update_kind)))
+}
+
+/// Enforces the retention policy and signals every connected client to clean up
+#[utoipa::path(
+    post,
+    path = "/v1/api/cleanup",
+    tag = "server",
+    params(Name),
+    responses(
+        (status = 200, description = "What was cleaned up", body = CleanupReport),
+        (status = 401, description = "Missing or invalid credential"),
+        (status = 403, description = "The caller is not an admin client"),
+    )
+)]
+async fn cleanup<T: Queryable>(
+    request: HttpRequest,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+    worker_bcast: Data<broadcast::Sender<WorkerSignal>>,
+) -> Result<HttpResponse> {
+    let queryable = queryable.get_ref();
+    let (name, auth_level) = caller(&request, &name, &config, queryable).await?;
+    if !auth_level.is_admin(&name.name(), &config) {
+        info!("cleanup by '{}' denied", name.describe(&request));
+        return Err(ErrorForbidden("cleanup requires admin permission"));
+    }
+    info!("received cleanup request");
+    let (output_lines, runs, clients_signaled) = cli::cleanup(queryable, &config, &worker_bcast)
+        .await
+        .map_err(|e| internal(&e))?;
+    Ok(HttpResponse::Ok().json(CleanupReport {
+        output_lines,
+        runs,
+        clients_signaled,
+    }))
+}
+
+/// Issues a short-lived, single-use ticket to open the dashboard WebSocket with, since a
+/// browser cannot send a credential on a WebSocket upgrade
+#[utoipa::path(
+    post,
+    path = "/v1/api/dashboard/ticket",
+    tag = "server",
+    params(Name),
+    responses(
+        (status = 200, description = "The ticket", body = DashboardTicket),
+        (status = 401, description = "Missing or invalid credential"),
+    )
+)]
+async fn dashboard_ticket<T: Queryable>(
+    request: HttpRequest,
+    name: Query<Name>,
+    config: Data<Config>,
+    queryable: Data<T>,
+    tickets: Data<Mutex<DashboardTickets>>,
+) -> Result<HttpResponse> {
+    let _ = caller(&request, &name, &config, queryable.get_ref()).await?;
+    let ticket = tickets.lock().await.issue(DASHBOARD_TICKET_TTL);
+    Ok(HttpResponse::Ok().json(DashboardTicket {
+        ticket,
+        expires_in: DASHBOARD_TICKET_TTL.as_secs(),
+    }))
+}
+
+async fn openapi() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+/// Registers the REST API under `/api`, backed by the `T` in the app data
+pub(crate) fn api_config<T: Queryable + 'static>(cfg: &mut ServiceConfig) {
+    _ = cfg.service(
+        scope("/api")
+            .route("/openapi.json", get().to(openapi))
+            .route("/info", get().to(info::<T>))
+            .route("/clients", get().to(clients::<T>))
+            .route("/clients/versions", get().to(client_versions::<T>))
+            .route("/clients/history", get().to(client_history::<T>))
+            .route(
+                "/clients/{client}/schedules",
+                get().to(client_schedules::<T>),
+            )
+            .route("/clients/{client}/commands", get().to(client_commands::<T>))
+            .route(
+                "/clients/{client}/commands/{command}",
+                get().to(client_command_runs::<T>),
+            )
+            .route("/commands/{command}", get().to(command_runs::<T>))
+            .route("/runs", get().to(runs::<T>))
+            .route("/runs/failed", get().to(failed_runs::<T>))
+            .route("/runs/search", get().to(search::<T>))
+            .route("/runs/{cmd_uuid}/output", get().to(run_output::<T>))
+            .route("/updates/{client}", get().to(updates::<T>))
+            .route("/cleanup", post().to(cleanup::<T>))
+            .route("/dashboard/ticket", post().to(dashboard_ticket::<T>)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use actix_web::{
+        App,
+        http::StatusCode,
+        test::{TestRequest, call_and_read_body_json, call_service, init_service},
+        web::{Data, scope},
+    };
+    use libbarto::{
+        ClientEvent, ClientEventKind, OffsetDataTimeWrapper, Output, OutputKind, RunStart,
+        Schedules, Status, UuidWrapper, hash_client_token,
+    };
+    use serde_json::Value;
+    use time::{Duration, OffsetDateTime};
+    use tokio::sync::{Mutex, RwLock, broadcast};
+    use uuid::Uuid;
+
+    use crate::{
+        common::{ClientCredential, Clients, DashboardTickets, WorkerSignal},
+        config::Config,
+        db::{Queryable, memory::MemoryHandler},
+    };
+
+    use super::api_config;
+
+    /// Record a finished run of `schedule` on `host` that ended `age` ago
+    async fn record(
+        store: &MemoryHandler,
+        host: &str,
+        schedule: &str,
+        age: Duration,
+        code: i32,
+    ) -> Uuid {
+        let cmd_uuid = UuidWrapper(Uuid::new_v4());
+        let at = OffsetDataTimeWrapper(OffsetDateTime::now_utc() - age);
+        let run_start = RunStart::builder()
+            .cmd_uuid(cmd_uuid)
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(host.to_string())
+            .schedule_name(schedule.to_string())
+            .cmd(format!("run {schedule}"))
+            .timestamp(at)
+            .build();
+        let _ = store.insert_run_start(&run_start).await.unwrap();
+        let output = Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(host.to_string())
+            .timestamp(at)
+            .cmd_uuid(cmd_uuid)
+            .cmd_name(schedule.to_string())
+            .kind(OutputKind::Stdout)
+            .data(format!("{schedule} output"))
+            .build();
+        let _ = store.insert_outputs(&[output]).await.unwrap();
+        let status = Status::builder()
+            .cmd_uuid(cmd_uuid)
+            .timestamp(at)
+            .exit_code(Some(code))
+            .success(code == 0)
+            .build();
+        let _ = store.insert_status(&status).await.unwrap();
+        cmd_uuid.0
+    }
+
+    async fn seeded() -> MemoryHandler {
+        let store = MemoryHandler::default();
+        record(&store, "host1", "backup", Duration::hours(1), 0).await;
+        record(&store, "host1", "update", Duration::hours(2), 2).await;
+        record(&store, "host2", "update", Duration::days(3), 0).await;
+        store
+    }
+
+    fn live_schedules() -> RwLock<BTreeMap<String, Schedules>> {
+        let schedules = serde_json::from_value(serde_json::json!({
+            "schedules": [
+                { "name": "backup", "on_calendar": "daily", "cmds": ["backup"] },
+                { "name": "jitter", "on_calendar": "* R:R:00", "cmds": ["jitter"] },
+            ]
+        }))
+        .unwrap();
+        RwLock::new(BTreeMap::from([("host1".to_string(), schedules)]))
+    }
+
+    macro_rules! app {
+        ($config:expr, $store:expr) => {{
+            let (tx, _rx) = broadcast::channel::<WorkerSignal>(8);
+            let mut clients = Clients::builder().build();
+            let _old = clients.add_client(Uuid::nil(), "host1", "127.0.0.1");
+            init_service(
+                App::new()
+                    .app_data(Data::new($config))
+                    .app_data(Data::new($store))
+                    .app_data(Data::new(Mutex::new(clients)))
+                    .app_data(Data::new(tx))
+                    .app_data(Data::new(live_schedules()))
+                    .app_data(Data::new(Mutex::new(DashboardTickets::default())))
+                    .service(scope("/v1").configure(api_config::<MemoryHandler>)),
+            )
+            .await
+        }};
+    }
+
+    fn get(uri: &str) -> TestRequest {
+        TestRequest::get().uri(uri)
+    }
+
+    #[actix_web::test]
+    async fn lists_clients_and_commands() {
+        let app = app!(Config::default(), seeded().await);
+
+        let clients: Value =
+            call_and_read_body_json(&app, get("/v1/api/clients").to_request()).await;
+        assert_eq!(clients[0]["name"], "host1");
+        assert_eq!(clients[0]["ip"], "127.0.0.1");
+        assert!(clients[0]["info"].is_null());
+
+        let versions: Value =
+            call_and_read_body_json(&app, get("/v1/api/clients/versions").to_request()).await;
+        assert_eq!(versions["host1"], "unknown");
+
+        let cmds: Value =
+            call_and_read_body_json(&app, get("/v1/api/clients/host1/commands").to_request()).await;
+        assert_eq!(cmds, serde_json::json!(["backup", "update"]));
+
+        let runs: Value = call_and_read_body_json(
+            &app,
+            get("/v1/api/clients/host1/commands/backup").to_request(),
+        )
+        .await;
+        assert_eq!(runs[0]["data"], "backup output");
+        assert_eq!(runs[0]["exit_code"], 0);
+        assert_eq!(runs[0]["success"], 1);
+
+        let by_client: Value =
+            call_and_read_body_json(&app, get("/v1/api/commands/update").to_request()).await;
+        assert_eq!(by_client["host1"].as_array().map(Vec::len), Some(1));
+        assert_eq!(by_client["host2"].as_array().map(Vec::len), Some(1));
+    }
+
+    #[actix_web::test]
+    async fn lists_client_history() {
+        let store = MemoryHandler::default();
+        for (name, kind) in [
+            ("host1", ClientEventKind::Connect),
+            ("host2", ClientEventKind::Connect),
+            ("host1", ClientEventKind::Disconnect),
+        ] {
+            let event = ClientEvent::builder()
+                .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+                .bartoc_name(name.to_string())
+                .ip("10.0.0.1".to_string())
+                .kind(kind)
+                .maybe_reason((kind == ClientEventKind::Disconnect).then(|| "closed".to_string()))
+                .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+                .build();
+            store.insert_client_event(&event).await.unwrap();
+        }
+        let app = app!(Config::default(), store);
+
+        let events: Value =
+            call_and_read_body_json(&app, get("/v1/api/clients/history").to_request()).await;
+        assert_eq!(events.as_array().map(Vec::len), Some(3));
+        assert_eq!(events[0]["name"], "host1");
+        assert_eq!(events[0]["kind"], "disconnect");
+        assert_eq!(events[0]["reason"], "closed");
+
+        let host2: Value = call_and_read_body_json(
+            &app,
+            get("/v1/api/clients/history?client=host2&since=1h").to_request(),
+        )
+        .await;
+        assert_eq!(host2.as_array().map(Vec::len), Some(1));
+        assert_eq!(host2[0]["kind"], "connect");
+
+        let res = call_service(&app, get("/v1/api/clients/history?since=soon").to_request()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn queries_runs() {
+        let app = app!(Config::default(), seeded().await);
+
+        let runs: Value = call_and_read_body_json(&app, get("/v1/api/runs").to_request()).await;
+        assert_eq!(runs.as_array().map(Vec::len), Some(3));
+        assert_eq!(runs[0]["bartoc_name"], "host2");
+
+        let failed: Value = call_and_read_body_json(
+            &app,
+            get("/v1/api/runs?client=host1&state=failed&since=1d").to_request(),
+        )
+        .await;
+        assert_eq!(failed.as_array().map(Vec::len), Some(1));
+        assert_eq!(failed[0]["schedule_name"], "update");
+        assert_eq!(failed[0]["exit_code"], 2);
+
+        let last_failed: Value =
+            call_and_read_body_json(&app, get("/v1/api/runs/failed").to_request()).await;
+        assert_eq!(last_failed[0]["cmd_name"], "update");
+
+        let hits: Value =
+            call_and_read_body_json(&app, get("/v1/api/runs/search?text=backup").to_request())
+                .await;
+        assert_eq!(hits[0]["line"], "backup output");
+
+        let updates: Value =
+            call_and_read_body_json(&app, get("/v1/api/updates/host1?kind=apt").to_request()).await;
+        assert_eq!(updates["kind"], "apt");
+
+        for uri in [
+            "/v1/api/runs?since=yesterday",
+            "/v1/api/runs?state=lost",
+            "/v1/api/updates/host1?kind=yum",
+        ] {
+            let res = call_service(&app, get(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn requires_a_credential() {
+        let mut config = Config::default();
+        let _ = config.set_api_key(Some("shared".to_string()));
+        let app = app!(config, seeded().await);
+
+        let res = call_service(&app, get("/v1/api/runs").to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = call_service(
+            &app,
+            get("/v1/api/runs")
+                .insert_header(("Authorization", "Bearer shared"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // The document describing the API is public.
+        let res = call_service(&app, get("/v1/api/openapi.json").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn cleanup_requires_admin() {
+        let store = seeded().await;
+        store
+            .enroll_client(
+                "viewer",
+                &ClientCredential::Token(hash_client_token("viewer-token")),
+            )
+            .await
+            .unwrap();
+        store
+            .enroll_client(
+                "admin",
+                &ClientCredential::Token(hash_client_token("admin-token")),
+            )
+            .await
+            .unwrap();
+        let mut config = Config::default();
+        let _ = config.set_admin_clients(vec!["admin".to_string()]);
+        let app = app!(config, store);
+
+        let cleanup = |name: &str, token: &str| {
+            TestRequest::post()
+                .uri(&format!("/v1/api/cleanup?name={name}"))
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .to_request()
+        };
+        let res = call_service(&app, cleanup("viewer", "viewer-token")).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let report: Value = call_and_read_body_json(&app, cleanup("admin", "admin-token")).await;
+        assert_eq!(report["output_lines"], 0);
+        assert_eq!(report["clients_signaled"], 0);
+    }
+
+    #[actix_web::test]
+    async fn cleanup_denies_the_shared_api_key() {
+        let mut config = Config::default();
+        let _ = config.set_api_key(Some("shared".to_string()));
+        let _ = config.set_admin_clients(vec!["ops".to_string()]);
+        let app = app!(config, seeded().await);
+
+        // Listed in admin_clients, but not enrolled, so only the shared api_key vouches for it.
+        let req = TestRequest::post()
+            .uri("/v1/api/cleanup?name=ops")
+            .insert_header(("Authorization", "Bearer shared"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn lists_schedules_with_next_run() {
+        let app = app!(Config::default(), MemoryHandler::default());
+
+        let schedules: Value =
+            call_and_read_body_json(&app, get("/v1/api/clients/host1/schedules").to_request())
+                .await;
+        assert_eq!(schedules[0]["name"], "backup");
+        assert_eq!(schedules[0]["cmds"], serde_json::json!(["backup"]));
+        let next_run = schedules[0]["next_run"].as_str().unwrap();
+        assert!(next_run.ends_with("T00:00:00Z"), "{next_run}");
+        // Only the client knows the random time it picked.
+        assert_eq!(schedules[1]["on_calendar"], "* R:R:00");
+        assert!(schedules[1]["next_run"].is_null());
+
+        let none: Value =
+            call_and_read_body_json(&app, get("/v1/api/clients/host2/schedules").to_request())
+                .await;
+        assert_eq!(none, serde_json::json!([]));
+    }
+
+    #[actix_web::test]
+    async fn shows_the_output_of_a_run() {
+        let store = MemoryHandler::default();
+        let cmd_uuid = record(&store, "host1", "backup", Duration::minutes(5), 3).await;
+        let app = app!(Config::default(), store);
+
+        let run: Value = call_and_read_body_json(
+            &app,
+            get(&format!("/v1/api/runs/{cmd_uuid}/output")).to_request(),
+        )
+        .await;
+        assert_eq!(run["cmd_uuid"], cmd_uuid.to_string());
+        assert_eq!(run["bartoc_name"], "host1");
+        assert_eq!(run["cmd"], "run backup");
+        assert_eq!(run["exit_code"], 3);
+        assert_eq!(run["success"], false);
+        assert_eq!(run["lines"][0]["stream"], "stdout");
+        assert_eq!(run["lines"][0]["data"], "backup output");
+
+        let uri = format!("/v1/api/runs/{}/output", Uuid::new_v4());
+        let res = call_service(&app, get(&uri).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn issues_dashboard_tickets_to_authenticated_callers() {
+        let mut config = Config::default();
+        let _ = config.set_api_key(Some("shared".to_string()));
+        let app = app!(config, MemoryHandler::default());
+
+        let ticket = || TestRequest::post().uri("/v1/api/dashboard/ticket");
+        let res = call_service(&app, ticket().to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let issued: Value = call_and_read_body_json(
+            &app,
+            ticket()
+                .insert_header(("Authorization", "Bearer shared"))
+                .to_request(),
+        )
+        .await;
+        assert!(issued["ticket"].as_str().unwrap().parse::<Uuid>().is_ok());
+        assert_eq!(issued["expires_in"], 30);
+    }
+
+    #[actix_web::test]
+    async fn serves_the_openapi_document() {
+        let app = app!(Config::default(), MemoryHandler::default());
+        let doc: Value =
+            call_and_read_body_json(&app, get("/v1/api/openapi.json").to_request()).await;
+        assert_eq!(doc["info"]["title"], "bartos");
+        for path in [
+            "/v1/api/clients",
+            "/v1/api/clients/history",
+            "/v1/api/clients/{client}/commands/{command}",
+            "/v1/api/runs",
+            "/v1/api/runs/failed",
+            "/v1/api/runs/{cmd_uuid}/output",
+            "/v1/api/clients/{client}/schedules",
+            "/v1/api/updates/{client}",
+        ] {
+            assert!(doc["paths"][path]["get"].is_object(), "{path}");
+        }
+        assert!(doc["paths"]["/v1/api/cleanup"]["post"].is_object());
+        assert!(doc["components"]["schemas"]["Run"].is_object());
+        assert_eq!(
+            doc["components"]["securitySchemes"]["bearer"]["scheme"],
+            "bearer"
+        );
+    }
+}
@@ -0,0 +1,541 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The JSON bodies of the REST API
+//!
+//! The wire types in `libbarto` are bincode only, so each one gets a JSON twin here that
+//! the `OpenAPI` document is generated from. Timestamps are RFC 3339 strings.
+
+use libbarto::{
+    CliUpdateKind, ClientData, ClientEvent as StoredClientEvent, ExportRow, FailedOutput, Garuda,
+    ListOutput, OffsetDataTimeWrapper, OutputKind, Pacman, QueryRow, Realtime,
+    Schedule as ConfiguredSchedule, SearchHit, UpdateKind,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+fn rfc3339(timestamp: Option<&OffsetDataTimeWrapper>) -> Option<String> {
+    timestamp.map(ToString::to_string)
+}
+
+/// A connected bartoc client
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct Client {
+    /// The id of the connection
+    pub(crate) id: Uuid,
+    /// The name the client connected with
+    pub(crate) name: String,
+    /// The address the client connected from
+    pub(crate) ip: String,
+    /// The system information the client reported, once it has
+    pub(crate) info: Option<BartocInfo>,
+}
+
+impl Client {
+    pub(crate) fn new(id: Uuid, data: &ClientData) -> Self {
+        Self {
+            id,
+            name: data.name().clone(),
+            ip: data.ip().clone(),
+            info: data.bartoc_info().as_ref().map(|info| BartocInfo {
+                hostname: info.hostname().clone(),
+                os_version: info.os_version().clone(),
+                kernel_version: info.kernel_version().clone(),
+                version: info.version().clone(),
+            }),
+        }
+    }
+}
+
+/// A bartoc client connecting to or disconnecting from bartos
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct ClientEvent {
+    /// The id of the connection
+    pub(crate) id: Uuid,
+    /// The name of the client
+    pub(crate) name: String,
+    /// The address the client connected from
+    pub(crate) ip: String,
+    /// `connect` or `disconnect`
+    pub(crate) kind: String,
+    /// Why the client disconnected, e.g. `closed`, `heartbeat_timeout` or `replaced`
+    pub(crate) reason: Option<String>,
+    /// The WebSocket close code the client sent, if it closed the connection
+    pub(crate) close_code: Option<u16>,
+    /// When the event happened
+    pub(crate) timestamp: String,
+}
+
+impl From<&StoredClientEvent> for ClientEvent {
+    fn from(event: &StoredClientEvent) -> Self {
+        Self {
+            id: event.bartoc_uuid().0,
+            name: event.bartoc_name().clone(),
+            ip: event.ip().clone(),
+            kind: event.kind().to_string(),
+            reason: event.reason().clone(),
+            close_code: event.close_code(),
+            timestamp: event.timestamp().to_string(),
+        }
+    }
+}
+
+/// The query string of the client history endpoint
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct ClientHistoryParams {
+    /// Only events of the bartoc client with this name
+    pub(crate) client: Option<String>,
+    /// Only events at or after this RFC 3339 time or age, e.g. `24h` or `7d`
+    pub(crate) since: Option<String>,
+    /// The most events to return, capped by bartos
+    pub(crate) limit: Option<u32>,
+}
+
+/// The system information of a bartoc client
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct BartocInfo {
+    /// The hostname of the client
+    pub(crate) hostname: String,
+    /// The operating system version of the client
+    pub(crate) os_version: String,
+    /// The kernel version of the client
+    pub(crate) kernel_version: String,
+    /// The bartoc binary version
+    pub(crate) version: String,
+}
+
+/// One run of a command on one client
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct CommandRun {
+    /// When the run last reported
+    pub(crate) timestamp: Option<String>,
+    /// The output of the run
+    pub(crate) data: Option<String>,
+    /// The exit code, `null` when the command was killed by a signal or is still going
+    pub(crate) exit_code: Option<i32>,
+    /// The signal that killed the command
+    pub(crate) exit_signal: Option<i32>,
+    /// Whether the command dumped core when it was killed
+    pub(crate) core_dumped: bool,
+    /// 1 when the run succeeded, 0 when it failed
+    pub(crate) success: i8,
+}
+
+impl From<&ListOutput> for CommandRun {
+    fn from(output: &ListOutput) -> Self {
+        Self {
+            timestamp: rfc3339(output.timestamp().as_ref()),
+            data: output.data().clone(),
+            exit_code: output.exit_code(),
+            exit_signal: output.exit_signal(),
+            core_dumped: output.core_dumped(),
+            success: output.success(),
+        }
+    }
+}
+
+/// A recorded run matching a runs query
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct Run {
+    /// The UUID of the run
+    pub(crate) cmd_uuid: Uuid,
+    /// When the run last reported
+    pub(crate) timestamp: Option<String>,
+    /// The name of the bartoc client
+    pub(crate) bartoc_name: Option<String>,
+    /// The name of the schedule
+    pub(crate) schedule_name: Option<String>,
+    /// The output of the run
+    pub(crate) data: Option<String>,
+    /// The exit code, `null` when the command was killed by a signal or is still going
+    pub(crate) exit_code: Option<i32>,
+    /// The signal that killed the command
+    pub(crate) exit_signal: Option<i32>,
+    /// Whether the command dumped core when it was killed
+    pub(crate) core_dumped: bool,
+    /// 1 when the run succeeded, 0 when it failed
+    pub(crate) success: i8,
+}
+
+impl From<&QueryRow> for Run {
+    fn from(row: &QueryRow) -> Self {
+        Self {
+            cmd_uuid: row.cmd_uuid().0,
+            timestamp: rfc3339(row.timestamp().as_ref()),
+            bartoc_name: row.bartoc_name().clone(),
+            schedule_name: row.schedule_name().clone(),
+            data: row.data().clone(),
+            exit_code: row.exit_code(),
+            exit_signal: row.exit_signal(),
+            core_dumped: row.core_dumped(),
+            success: row.success(),
+        }
+    }
+}
+
+/// The last run of a command that failed
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct FailedRun {
+    /// When the run last reported
+    pub(crate) timestamp: Option<String>,
+    /// The name of the bartoc client
+    pub(crate) bartoc_name: Option<String>,
+    /// The name of the command
+    pub(crate) cmd_name: Option<String>,
+    /// The output of the run
+    pub(crate) data: Option<String>,
+    /// The exit code, `null` when the command was killed by a signal
+    pub(crate) exit_code: Option<i32>,
+    /// The signal that killed the command
+    pub(crate) exit_signal: Option<i32>,
+    /// Whether the command dumped core when it was killed
+    pub(crate) core_dumped: bool,
+}
+
+impl From<&FailedOutput> for FailedRun {
+    fn from(output: &FailedOutput) -> Self {
+        Self {
+            timestamp: rfc3339(output.timestamp().as_ref()),
+            bartoc_name: output.bartoc_name().clone(),
+            cmd_name: output.cmd_name().clone(),
+            data: output.data().clone(),
+            exit_code: output.exit_code(),
+            exit_signal: output.exit_signal(),
+            core_dumped: output.core_dumped(),
+        }
+    }
+}
+
+/// An output line matching a search, with the lines of the same run around it
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct SearchMatch {
+    /// The UUID of the run
+    pub(crate) cmd_uuid: Uuid,
+    /// When the matching line was generated
+    pub(crate) timestamp: String,
+    /// The name of the bartoc client
+    pub(crate) bartoc_name: String,
+    /// The name of the schedule, if the run was recorded
+    pub(crate) schedule_name: Option<String>,
+    /// The matching line
+    pub(crate) line: String,
+    /// The lines of the run before the matching line, oldest first
+    pub(crate) before: Vec<String>,
+    /// The lines of the run after the matching line, oldest first
+    pub(crate) after: Vec<String>,
+}
+
+impl From<&SearchHit> for SearchMatch {
+    fn from(hit: &SearchHit) -> Self {
+        Self {
+            cmd_uuid: hit.cmd_uuid().0,
+            timestamp: hit.timestamp().to_string(),
+            bartoc_name: hit.bartoc_name().clone(),
+            schedule_name: hit.schedule_name().clone(),
+            line: hit.line().clone(),
+            before: hit.before().clone(),
+            after: hit.after().clone(),
+        }
+    }
+}
+
+/// The package manager whose pending updates are requested
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum UpdateSource {
+    /// garuda-update
+    Garuda,
+    /// Archlinux pacman
+    Pacman,
+    /// `CachyOS` pacman
+    Cachyos,
+    /// apt
+    Apt,
+}
+
+impl From<UpdateSource> for CliUpdateKind {
+    fn from(source: UpdateSource) -> Self {
+        match source {
+            UpdateSource::Garuda => CliUpdateKind::Garuda,
+            UpdateSource::Pacman => CliUpdateKind::Pacman,
+            UpdateSource::Cachyos => CliUpdateKind::Cachyos,
+            UpdateSource::Apt => CliUpdateKind::Apt,
+        }
+    }
+}
+
+/// The query string of the updates endpoint
+#[derive(Clone, Copy, Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct UpdatesParams {
+    /// The package manager to report on
+    pub(crate) kind: UpdateSource,
+}
+
+/// The pending updates a client last reported
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "kind", content = "updates", rename_all = "lowercase")]
+pub(crate) enum Updates {
+    /// garuda-update packages
+    Garuda(Vec<GarudaUpdate>),
+    /// An Archlinux pacman update
+    Pacman(PacmanUpdate),
+    /// A `CachyOS` pacman update
+    Cachyos(PacmanUpdate),
+    /// apt packages
+    Apt(Vec<String>),
+}
+
+impl From<&UpdateKind> for Updates {
+    fn from(kind: &UpdateKind) -> Self {
+        match kind {
+            UpdateKind::Garuda(updates) => {
+                Updates::Garuda(updates.iter().map(GarudaUpdate::from).collect())
+            }
+            UpdateKind::Pacman(pacman) => Updates::Pacman(pacman.into()),
+            UpdateKind::Cachyos(pacman) => Updates::Cachyos(pacman.into()),
+            UpdateKind::Apt(packages) => Updates::Apt(packages.clone()),
+        }
+    }
+}
+
+/// One package garuda-update will upgrade
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct GarudaUpdate {
+    /// The repository channel
+    pub(crate) channel: String,
+    /// The package name
+    pub(crate) package: String,
+    /// The installed version
+    pub(crate) old_version: String,
+    /// The version that will be installed
+    pub(crate) new_version: String,
+    /// The change in installed size
+    pub(crate) size_change: String,
+    /// The download size
+    pub(crate) download_size: String,
+}
+
+impl From<&Garuda> for GarudaUpdate {
+    fn from(garuda: &Garuda) -> Self {
+        Self {
+            channel: garuda.channel().clone(),
+            package: garuda.package().clone(),
+            old_version: garuda.old_version().clone(),
+            new_version: garuda.new_version().clone(),
+            size_change: garuda.size_change().clone(),
+            download_size: garuda.download_size().clone(),
+        }
+    }
+}
+
+/// A pending pacman upgrade
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub(crate) struct PacmanUpdate {
+    /// The number of packages to upgrade
+    pub(crate) update_count: usize,
+    /// The packages to upgrade
+    pub(crate) packages: Vec<String>,
+    /// The total installed size, in MiB
+    pub(crate) install_size: f64,
+    /// The net change in installed size, in MiB
+    pub(crate) net_size: f64,
+    /// The download size, in MiB
+    pub(crate) download_size: f64,
+}
+
+impl From<&Pacman> for PacmanUpdate {
+    fn from(pacman: &Pacman) -> Self {
+        Self {
+            update_count: pacman.update_count(),
+            packages: pacman.packages().clone(),
+            install_size: pacman.install_size(),
+            net_size: pacman.net_size(),
+            download_size: pacman.download_size(),
+        }
+    }
+}
+
+/// The query string of the runs endpoint
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct RunsParams {
+    /// Only runs of the bartoc client with this name
+    pub(crate) client: Option<String>,
+    /// Only runs of the schedule with this name
+    pub(crate) schedule: Option<String>,
+    /// Only runs at or after this RFC 3339 time or age, e.g. `24h` or `7d`
+    pub(crate) since: Option<String>,
+    /// Only runs before this RFC 3339 time or age
+    pub(crate) until: Option<String>,
+    /// Only runs in this state: `succeeded`, `failed` or `running`
+    pub(crate) state: Option<String>,
+    /// Only runs whose output contains this text, ignoring case
+    pub(crate) text: Option<String>,
+    /// The most runs to return, capped by bartos
+    pub(crate) limit: Option<u32>,
+    /// The number of matching runs to skip
+    pub(crate) offset: Option<u32>,
+}
+
+/// The query string of the search endpoint
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SearchParams {
+    /// The words to search for
+    pub(crate) text: String,
+    /// Only output of the bartoc client with this name
+    pub(crate) client: Option<String>,
+    /// Only output of the schedule with this name
+    pub(crate) schedule: Option<String>,
+    /// Only output at or after this RFC 3339 time or age, e.g. `24h` or `7d`
+    pub(crate) since: Option<String>,
+    /// Only output before this RFC 3339 time or age
+    pub(crate) until: Option<String>,
+    /// The most matches to return, capped by bartos
+    pub(crate) limit: Option<u32>,
+    /// The number of lines of the same run to return around each match
+    pub(crate) context: Option<u8>,
+}
+
+/// The outcome of a cleanup
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct CleanupReport {
+    /// The number of output lines deleted
+    pub(crate) output_lines: u64,
+    /// The number of run rows deleted
+    pub(crate) runs: u64,
+    /// The number of connected clients signaled to clean up
+    pub(crate) clients_signaled: usize,
+}
+
+/// A schedule configured for a client
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct Schedule {
+    /// The name of the schedule
+    pub(crate) name: String,
+    /// When the schedule runs, in the `on_calendar` format
+    pub(crate) on_calendar: String,
+    /// The commands the schedule runs
+    pub(crate) cmds: Vec<String>,
+    /// The next time the schedule runs, in UTC. `null` when the calendar picks a random
+    /// (`R`) value, which only the client knows, or does not run in the next four years.
+    pub(crate) next_run: Option<String>,
+}
+
+impl Schedule {
+    pub(crate) fn new(schedule: &ConfiguredSchedule, now: OffsetDateTime) -> Self {
+        let on_calendar = schedule.on_calendar();
+        let next_run = if on_calendar.contains('R') {
+            None
+        } else {
+            Realtime::try_from(on_calendar.as_str())
+                .ok()
+                .and_then(|realtime| realtime.next_after(now))
+                .map(|next| OffsetDataTimeWrapper(next).to_string())
+        };
+        Self {
+            name: schedule.name().clone(),
+            on_calendar: on_calendar.clone(),
+            cmds: schedule.cmds().clone(),
+            next_run,
+        }
+    }
+}
+
+/// One run with all of its output
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct RunOutput {
+    /// The UUID of the run
+    pub(crate) cmd_uuid: Uuid,
+    /// The name of the bartoc client
+    pub(crate) bartoc_name: Option<String>,
+    /// The name of the schedule
+    pub(crate) schedule_name: Option<String>,
+    /// The command string that was run
+    pub(crate) cmd: Option<String>,
+    /// When the run started
+    pub(crate) started_at: Option<String>,
+    /// When the run ended, `null` while it is still going
+    pub(crate) ended_at: Option<String>,
+    /// The exit code, `null` when the command was killed by a signal or is still going
+    pub(crate) exit_code: Option<i32>,
+    /// The signal that killed the command
+    pub(crate) exit_signal: Option<i32>,
+    /// Whether the command dumped core when it was killed
+    pub(crate) core_dumped: Option<bool>,
+    /// Whether the run succeeded, `null` while it is still going
+    pub(crate) success: Option<bool>,
+    /// The output of the run, oldest line first
+    pub(crate) lines: Vec<OutputLine>,
+}
+
+impl RunOutput {
+    /// The run the rows of one run describe, or `None` when there are none
+    pub(crate) fn from_rows(rows: &[ExportRow]) -> Option<Self> {
+        let run = rows.first()?;
+        Some(Self {
+            cmd_uuid: run.cmd_uuid().0,
+            bartoc_name: run.bartoc_name().clone(),
+            schedule_name: run.schedule_name().clone(),
+            cmd: run.cmd().clone(),
+            started_at: rfc3339(run.started_at().as_ref()),
+            ended_at: rfc3339(run.ended_at().as_ref()),
+            exit_code: run.exit_code(),
+            exit_signal: run.exit_signal(),
+            core_dumped: run.core_dumped(),
+            success: run.success(),
+            lines: rows
+                .iter()
+                .filter_map(|row| {
+                    Some(OutputLine {
+                        timestamp: row.timestamp()?.to_string(),
+                        stream: match row.kind()? {
+                            OutputKind::Stdout => Stream::Stdout,
+                            OutputKind::Stderr => Stream::Stderr,
+                        },
+                        data: row.data().clone()?,
+                    })
+                })
+                .collect(),
+        })
+    }
+}
+
+/// One line of output of a run
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct OutputLine {
+    /// When the line was generated
+    pub(crate) timestamp: String,
+    /// The stream the line was written to
+    pub(crate) stream: Stream,
+    /// The line
+    pub(crate) data: String,
+}
+
+/// The stream an output line was written to
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Stream {
+    /// Standard output
+    Stdout,
+    /// Standard error
+    Stderr,
+}
+
+/// A ticket to open the dashboard WebSocket with
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, ToSchema)]
+pub(crate) struct DashboardTicket {
+    /// The single-use ticket, passed as `?ticket=` to `/v1/ws/dashboard`
+    pub(crate) ticket: Uuid,
+    /// The seconds the ticket is valid for
+    pub(crate) expires_in: u64,
+}
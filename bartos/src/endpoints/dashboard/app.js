@@ -0,0 +1,331 @@
+// The bartos dashboard. Everything is read from the REST API under /v1/api with the same
+// credential barto-cli uses; the dashboard WebSocket only says what changed.
+"use strict";
+
+const RECENT_RUNS = 50;
+const RECONNECT_MS = 5000;
+
+const state = {
+  name: sessionStorage.getItem("bartos.name") || "",
+  token: sessionStorage.getItem("bartos.token") || "",
+  clients: new Map(),
+  client: null,
+  run: null,
+  socket: null,
+  pending: new Map(),
+  // Why the watchdog cannot watch a schedule, by client and schedule name
+  unwatchable: new Map(),
+};
+
+const $ = (id) => document.getElementById(id);
+
+function el(tag, attrs, ...children) {
+  const node = document.createElement(tag);
+  for (const [key, value] of Object.entries(attrs || {})) {
+    if (key.startsWith("on")) {
+      node.addEventListener(key.slice(2), value);
+    } else if (value !== undefined && value !== null) {
+      node.setAttribute(key, value);
+    }
+  }
+  for (const child of children) {
+    if (child !== undefined && child !== null) {
+      node.append(child instanceof Node ? child : String(child));
+    }
+  }
+  return node;
+}
+
+class Unauthorized extends Error {}
+
+async function api(path, method = "GET") {
+  const url = new URL(`/v1/api${path}`, location.origin);
+  if (state.name) {
+    url.searchParams.set("name", state.name);
+  }
+  const headers = state.token ? { Authorization: `Bearer ${state.token}` } : {};
+  const res = await fetch(url, { method, headers });
+  if (res.status === 401) {
+    throw new Unauthorized();
+  }
+  if (!res.ok) {
+    throw new Error(`${method} ${path}: ${res.status}`);
+  }
+  return res.json();
+}
+
+// Coalesce the refreshes a burst of events asks for into one call each.
+function soon(key, fn, delay = 250) {
+  if (!state.pending.has(key)) {
+    state.pending.set(key, setTimeout(() => {
+      state.pending.delete(key);
+      fn().catch(failed);
+    }, delay));
+  }
+}
+
+function failed(e) {
+  if (e instanceof Unauthorized) {
+    showLogin("The credential was rejected.");
+  } else {
+    console.error(e);
+  }
+}
+
+function when(timestamp) {
+  return timestamp ? new Date(timestamp).toLocaleString() : "—";
+}
+
+// bartos records a run that never happened with a single "missed: ..." line of output.
+function missed(run) {
+  const last = run.lines ? run.lines[run.lines.length - 1] : run;
+  return Boolean(last && last.data && last.data.startsWith("missed: "));
+}
+
+function outcome(run) {
+  if (missed(run)) {
+    return el("span", { class: "failed" }, "missed");
+  }
+  if (run.exit_code === null && run.exit_signal === null && !run.ended_at) {
+    return el("span", { class: "running" }, "running");
+  }
+  if (run.success === 1 || run.success === true) {
+    return el("span", { class: "ok" }, "ok");
+  }
+  const why = run.exit_signal !== null
+    ? `signal ${run.exit_signal}${run.core_dumped ? " (core dumped)" : ""}`
+    : run.exit_code !== null ? `exit ${run.exit_code}` : "no exit status";
+  return el("span", { class: "failed" }, why);
+}
+
+async function loadClients() {
+  const clients = await api("/clients");
+  const list = $("clients");
+  list.replaceChildren(...clients.map((client) => el("li", {
+    class: client.name === state.client ? "selected" : null,
+    onclick: () => selectClient(client.name),
+  }, client.name, el("small", {}, client.info ? client.info.version : client.ip))));
+  if (clients.length === 0) {
+    list.append(el("li", { class: "muted" }, "No clients connected."));
+  }
+  state.clients = new Map(clients.map((client) => [client.name, client]));
+  if (state.client) {
+    renderInfo();
+  }
+}
+
+function renderInfo() {
+  const client = state.clients.get(state.client);
+  const info = client && client.info;
+  const rows = client
+    ? [["Address", client.ip], ["Hostname", info && info.hostname], ["OS", info && info.os_version],
+      ["Kernel", info && info.kernel_version], ["bartoc", info && info.version]]
+    : [["Status", "disconnected"]];
+  $("client-info").replaceChildren(...rows.flatMap(([key, value]) =>
+    [el("dt", {}, key), el("dd", {}, value || "—")]));
+}
+
+function nextRun(schedule) {
+  const error = state.unwatchable.get(`${state.client}/${schedule.name}`);
+  if (error) {
+    return el("span", { class: "error", title: error }, "invalid calendar");
+  }
+  return schedule.next_run ? when(schedule.next_run) : el("span", { class: "muted" }, "on client");
+}
+
+async function loadSchedules() {
+  const schedules = await api(`/clients/${encodeURIComponent(state.client)}/schedules`);
+  $("schedules").replaceChildren(...schedules.map((schedule) => el("tr", {},
+    el("td", {}, schedule.name),
+    el("td", {}, el("code", {}, schedule.on_calendar)),
+    el("td", {}, nextRun(schedule)))));
+  if (schedules.length === 0) {
+    $("schedules").append(el("tr", {}, el("td", { colspan: 3, class: "muted" }, "No schedules.")));
+  }
+}
+
+async function loadRuns() {
+  const query = new URLSearchParams({ client: state.client, since: "24h", limit: "10000" });
+  const rows = await api(`/runs?${query}`);
+  // The query returns a row per output line, so keep the last row of each run.
+  const runs = new Map();
+  for (const row of rows) {
+    runs.delete(row.cmd_uuid);
+    runs.set(row.cmd_uuid, row);
+  }
+  const recent = [...runs.values()].reverse().slice(0, RECENT_RUNS);
+  $("runs").replaceChildren(...recent.map((run) => el("tr", {
+    class: run.cmd_uuid === state.run ? "run selected" : "run",
+    onclick: () => openRun(run.cmd_uuid),
+  }, el("td", {}, when(run.timestamp)), el("td", {}, run.schedule_name || "—"), el("td", {}, outcome(run)))));
+  if (recent.length === 0) {
+    $("runs").append(el("tr", {}, el("td", { colspan: 3, class: "muted" }, "No runs in the last 24 hours.")));
+  }
+}
+
+async function selectClient(name) {
+  state.client = name;
+  for (const li of $("clients").children) {
+    li.classList.toggle("selected", li.firstChild.textContent === name);
+  }
+  $("client").replaceChildren(
+    el("h2", {}, name),
+    el("dl", { id: "client-info" }),
+    el("h3", {}, "Schedules"),
+    el("table", {}, el("thead", {}, el("tr", {}, el("th", {}, "Name"), el("th", {}, "Calendar"),
+      el("th", {}, "Next run (local time)"))), el("tbody", { id: "schedules" })),
+    el("h3", {}, "Recent runs"),
+    el("table", {}, el("thead", {}, el("tr", {}, el("th", {}, "Last reported"), el("th", {}, "Schedule"),
+      el("th", {}, "Status"))), el("tbody", { id: "runs" })),
+  );
+  renderInfo();
+  await Promise.all([loadSchedules(), loadRuns()]);
+}
+
+async function openRun(cmdUuid) {
+  state.run = cmdUuid;
+  for (const tr of document.querySelectorAll("tr.run")) {
+    tr.classList.remove("selected");
+  }
+  await loadLog();
+  soon("runs", loadRuns, 0);
+}
+
+async function loadLog() {
+  const run = await api(`/runs/${state.run}/output`);
+  const log = $("log-lines");
+  const follow = log.scrollTop + log.clientHeight >= log.scrollHeight - 4;
+  $("log").hidden = false;
+  $("log-title").textContent = `${run.schedule_name || "run"} on ${run.bartoc_name || "?"}`;
+  $("log-status").replaceChildren(
+    run.cmd ? el("code", {}, run.cmd) : "", " · started ", when(run.started_at), " · ", outcome(run));
+  log.replaceChildren(...run.lines.map((line) => el("div", { class: line.stream },
+    el("time", {}, new Date(line.timestamp).toLocaleTimeString()), line.data)));
+  if (follow) {
+    log.scrollTop = log.scrollHeight;
+  }
+}
+
+function onEvent(event) {
+  switch (event.event) {
+    case "connected":
+    case "disconnected":
+    case "client_info":
+      soon("clients", loadClients);
+      break;
+    case "run_started":
+    case "run_finished":
+      if (event.client === state.client) {
+        soon("runs", loadRuns);
+        soon("schedules", loadSchedules);
+      }
+      if (event.cmd_uuid === state.run) {
+        soon("log", loadLog);
+      }
+      break;
+    case "run_output":
+      if (event.cmd_uuid === state.run) {
+        soon("log", loadLog, 500);
+      }
+      break;
+    case "schedules_reloaded":
+      if (state.client) {
+        soon("schedules", loadSchedules);
+      }
+      break;
+    case "schedule_unwatchable":
+      state.unwatchable.set(`${event.client}/${event.schedule}`, event.error);
+      if (event.client === state.client) {
+        soon("schedules", loadSchedules);
+      }
+      break;
+    default:
+      refreshAll();
+  }
+}
+
+function setLive(on) {
+  $("live").textContent = on ? "live" : "offline";
+  $("live").className = on ? "live on" : "live off";
+}
+
+async function connect() {
+  if (state.socket) {
+    return;
+  }
+  try {
+    const { ticket } = await api("/dashboard/ticket", "POST");
+    const scheme = location.protocol === "https:" ? "wss:" : "ws:";
+    const socket = new WebSocket(`${scheme}//${location.host}/v1/ws/dashboard?ticket=${ticket}`);
+    state.socket = socket;
+    socket.onopen = () => {
+      setLive(true);
+      refreshAll();
+    };
+    socket.onmessage = (message) => onEvent(JSON.parse(message.data));
+    socket.onclose = () => {
+      state.socket = null;
+      setLive(false);
+      if (!$("fleet").hidden) {
+        setTimeout(connect, RECONNECT_MS);
+      }
+    };
+  } catch (e) {
+    failed(e);
+    if (!(e instanceof Unauthorized)) {
+      setTimeout(connect, RECONNECT_MS);
+    }
+  }
+}
+
+function refreshAll() {
+  soon("clients", loadClients, 0);
+  if (state.client) {
+    soon("schedules", loadSchedules, 0);
+    soon("runs", loadRuns, 0);
+  }
+  if (state.run) {
+    soon("log", loadLog, 0);
+  }
+}
+
+function showLogin(message) {
+  $("fleet").hidden = true;
+  $("logout").hidden = true;
+  $("login").hidden = false;
+  $("login-name").value = state.name;
+  $("login-error").textContent = message || "";
+  if (state.socket) {
+    state.socket.close();
+  }
+}
+
+async function start() {
+  try {
+    await loadClients();
+  } catch (e) {
+    failed(e);
+    return;
+  }
+  $("login").hidden = true;
+  $("fleet").hidden = false;
+  $("logout").hidden = false;
+  connect();
+}
+
+$("login").addEventListener("submit", (e) => {
+  e.preventDefault();
+  state.name = $("login-name").value.trim();
+  state.token = $("login-token").value;
+  sessionStorage.setItem("bartos.name", state.name);
+  sessionStorage.setItem("bartos.token", state.token);
+  start();
+});
+
+$("logout").addEventListener("click", () => {
+  sessionStorage.removeItem("bartos.token");
+  state.token = "";
+  showLogin();
+});
+
+start();
@@ -0,0 +1,107 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The embedded web dashboard
+//!
+//! The page itself is public and holds no data. It signs in with the same name and
+//! credential as the REST API, reads everything from `/v1/api` and refreshes when the
+//! `/v1/ws/dashboard` WebSocket says something changed.
+
+use actix_web::{
+    HttpResponse,
+    http::header::{CACHE_CONTROL, CONTENT_SECURITY_POLICY},
+    web::{ServiceConfig, get},
+};
+
+const INDEX_HTML: &str = include_str!("index.html");
+const APP_JS: &str = include_str!("app.js");
+const STYLE_CSS: &str = include_str!("style.css");
+
+/// Only the embedded assets and this origin's API and WebSocket are allowed
+const POLICY: &str = "default-src 'none'; script-src 'self'; style-src 'self'; \
+                      connect-src 'self' ws: wss:; form-action 'none'; frame-ancestors 'none'";
+
+fn asset(content_type: &'static str, body: &'static str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .insert_header((CONTENT_SECURITY_POLICY, POLICY))
+        .body(body)
+}
+
+async fn index() -> HttpResponse {
+    asset("text/html; charset=utf-8", INDEX_HTML)
+}
+
+async fn app_js() -> HttpResponse {
+    asset("text/javascript; charset=utf-8", APP_JS)
+}
+
+async fn style_css() -> HttpResponse {
+    asset("text/css; charset=utf-8", STYLE_CSS)
+}
+
+/// Serves the dashboard at `/dashboard`
+pub(crate) fn dashboard_config(cfg: &mut ServiceConfig) {
+    _ = cfg
+        .route("/dashboard", get().to(index))
+        .route("/dashboard/", get().to(index))
+        .route("/dashboard/app.js", get().to(app_js))
+        .route("/dashboard/style.css", get().to(style_css));
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        http::{
+            StatusCode,
+            header::{CONTENT_SECURITY_POLICY, CONTENT_TYPE},
+        },
+        test::{TestRequest, call_service, init_service, read_body},
+    };
+
+    use super::dashboard_config;
+
+    #[actix_web::test]
+    async fn serves_the_embedded_assets() {
+        let app = init_service(App::new().configure(dashboard_config)).await;
+
+        for (uri, content_type, marker) in [
+            (
+                "/dashboard",
+                "text/html; charset=utf-8",
+                "<title>bartos</title>",
+            ),
+            (
+                "/dashboard/app.js",
+                "text/javascript; charset=utf-8",
+                "/v1/ws/dashboard",
+            ),
+            (
+                "/dashboard/style.css",
+                "text/css; charset=utf-8",
+                "#log-lines",
+            ),
+        ] {
+            let res = call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+            assert_eq!(
+                res.headers().get(CONTENT_TYPE).unwrap(),
+                content_type,
+                "{uri}"
+            );
+            assert!(res.headers().contains_key(CONTENT_SECURITY_POLICY), "{uri}");
+            let body = read_body(res).await;
+            assert!(
+                String::from_utf8_lossy(&body).contains(marker),
+                "{uri} is missing {marker}"
+            );
+        }
+    }
+}
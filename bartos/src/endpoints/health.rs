@@ -0,0 +1,133 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The health endpoints for load balancers and service managers
+
+use actix_web::{
+    HttpRequest, HttpResponse, Result,
+    error::ErrorUnauthorized,
+    web::{Data, ServiceConfig, get},
+};
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    common::Clients,
+    config::Config,
+    db::Queryable,
+    endpoints::insecure::bearer_auth_ok,
+    health::{Health, Readiness},
+};
+
+/// The process is up and serving requests
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+/// `200` while the database is reachable and `bartos` is not shutting down, otherwise `503`
+async fn readyz<T: Queryable>(token: Data<CancellationToken>, store: Data<T>) -> HttpResponse {
+    let readiness = Readiness::check(store.get_ref(), &token).await;
+    if readiness.ready() {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+/// The version, uptime, connected client count and last config reload of `bartos`. When an
+/// `api_key` is configured the caller must present it as a bearer token.
+async fn status(
+    request: HttpRequest,
+    config: Data<Config>,
+    health: Data<Health>,
+    clients: Data<Mutex<Clients>>,
+) -> Result<HttpResponse> {
+    if !bearer_auth_ok(&request, config.api_key().as_deref()) {
+        return Err(ErrorUnauthorized("unauthorized"));
+    }
+    let clients_connected = clients.lock().await.clients().len();
+    Ok(HttpResponse::Ok().json(health.status(clients_connected, OffsetDateTime::now_utc())))
+}
+
+/// Registers the health endpoints, checking the database of the `T` in the app data
+pub(crate) fn health_config<T: Queryable + 'static>(cfg: &mut ServiceConfig) {
+    _ = cfg
+        .route("/healthz", get().to(healthz))
+        .route("/readyz", get().to(readyz::<T>))
+        .route("/status", get().to(status));
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        http::StatusCode,
+        test::{TestRequest, call_and_read_body_json, call_service, init_service, read_body},
+        web::Data,
+    };
+    use serde_json::{Value, json};
+    use time::OffsetDateTime;
+    use tokio::sync::Mutex;
+    use tokio_util::sync::CancellationToken;
+    use uuid::Uuid;
+
+    use super::health_config;
+    use crate::{
+        common::Clients, config::Config, db::memory::MemoryHandler, health::Health, metrics::Reload,
+    };
+
+    #[actix_web::test]
+    async fn serves_health_readiness_and_status() {
+        let mut config = Config::default();
+        let _ = config.set_api_key(Some("shared".to_string()));
+        let token = CancellationToken::new();
+        let health = Health::new(OffsetDateTime::now_utc());
+        health.reloaded(Reload::Invalid, OffsetDateTime::now_utc());
+        let mut clients = Clients::builder().build();
+        let _old = clients.add_client(Uuid::nil(), "host1", "127.0.0.1");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(config))
+                .app_data(Data::new(token.clone()))
+                .app_data(Data::new(MemoryHandler::default()))
+                .app_data(Data::new(health))
+                .app_data(Data::new(Mutex::new(clients)))
+                .configure(health_config::<MemoryHandler>),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "ok");
+
+        let readiness: Value =
+            call_and_read_body_json(&app, TestRequest::get().uri("/readyz").to_request()).await;
+        assert_eq!(
+            readiness,
+            json!({ "ready": true, "database_reachable": true, "shutting_down": false })
+        );
+
+        let res = call_service(&app, TestRequest::get().uri("/status").to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let req = TestRequest::get()
+            .uri("/status")
+            .insert_header(("Authorization", "Bearer shared"))
+            .to_request();
+        let status: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(status["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(status["clients_connected"], 1);
+        assert_eq!(status["last_reload"]["outcome"], "invalid");
+
+        token.cancel();
+        let res = call_service(&app, TestRequest::get().uri("/readyz").to_request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let healthz = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(healthz.status(), StatusCode::OK);
+    }
+}
@@ -13,7 +13,6 @@ use actix_web::{
 };
 use actix_ws::{AggregatedMessage, handle};
 use futures_util::StreamExt as _;
-use sqlx::MySqlPool;
 use tokio::{
     select,
     sync::{Mutex, broadcast},
@@ -24,9 +23,11 @@ use tracing::{error, info, trace};
 use crate::{
     common::{Clients, WorkerSignal},
     config::Config,
-    db::mysql::MySqlHandler,
-    endpoints::insecure::{Name, bearer_auth_ok},
+    db::Store,
+    endpoints::insecure::{Name, authenticate},
     handler::cli::BinaryMessageHandler,
+    metrics::Metrics,
+    tail::TailRecord,
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -37,17 +38,20 @@ pub(crate) async fn cli(
     name: Query<Name>,
     token: Data<CancellationToken>,
     config: Data<Config>,
-    pool: Data<MySqlPool>,
+    store: Data<Store>,
     clients_mutex: Data<Mutex<Clients>>,
     worker_bcast: Data<broadcast::Sender<WorkerSignal>>,
+    tail_bcast: Data<broadcast::Sender<TailRecord>>,
+    metrics: Data<Metrics>,
 ) -> Result<impl Responder> {
+    let name = name.bind(&request, &config)?;
     let describe = name.describe(&request);
     info!("cli connection from '{describe}'");
-    if !bearer_auth_ok(&request, config.api_key().as_deref()) {
-        info!("cli connection from '{describe}' rejected: missing or invalid Bearer token");
-        return Err(actix_web::error::ErrorUnauthorized("unauthorized"));
-    }
+    let queryable = store.get_ref().clone();
+    let auth_level = authenticate(&request, &name, &config, &queryable).await?;
+    let client_name = name.name();
     let ws_token = token.get_ref().clone();
+    let mut worker_rx = worker_bcast.subscribe();
     let (response, session, msg_stream) = handle(&request, body)?;
     let mut ws_session = session.clone();
     let mut agms = msg_stream.aggregate_continuations();
@@ -55,8 +59,11 @@ pub(crate) async fn cli(
         .config(config.clone())
         .clients_mutex(clients_mutex.clone())
         .worker_bcast(worker_bcast.clone())
+        .tail_bcast(tail_bcast.clone())
+        .admin(auth_level.is_admin(&name.name(), &config))
+        .raw_query(auth_level.may_raw_query(&name.name(), &config))
+        .metrics(metrics.clone())
         .build();
-    let queryable = MySqlHandler::builder().pool(pool.clone()).build();
 
     let _handle = spawn(async move {
         loop {
@@ -66,6 +73,20 @@ pub(crate) async fn cli(
                     let _ = ws_session.close(None).await;
                     break;
                 }
+                signal = worker_rx.recv() => {
+                    if let Ok(WorkerSignal::Disconnect(target)) = signal
+                        && target == client_name
+                    {
+                        info!("disconnecting '{describe}': credential revoked");
+                        break;
+                    }
+                }
+                res = handler.tail(&mut ws_session) => {
+                    if let Err(e) = res {
+                        error!("unable to send tail record: {e}");
+                        break;
+                    }
+                }
                 res_opt = agms.next() => {
                     match res_opt {
                         Some(Ok(msg)) => {
@@ -0,0 +1,149 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use actix_web::{
+    HttpRequest, Responder, Result,
+    error::ErrorUnauthorized,
+    rt::spawn,
+    web::{Data, Payload, Query},
+};
+use actix_ws::{AggregatedMessage, Session, handle};
+use futures_util::StreamExt as _;
+use serde::Deserialize;
+use tokio::{
+    select,
+    sync::{Mutex, broadcast, broadcast::error::RecvError},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, trace};
+use uuid::Uuid;
+
+use crate::{
+    common::{DashboardEvent, DashboardTickets},
+    endpoints::insecure::Name,
+};
+
+/// The query string of the dashboard WebSocket
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub(crate) struct Ticket {
+    /// A ticket from `POST /v1/api/dashboard/ticket`
+    ticket: Uuid,
+}
+
+/// Pushes every [`DashboardEvent`] to a browser as a JSON text message. The browser
+/// authenticates with a ticket it got from the REST API, which checked its credential.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub(crate) async fn dashboard(
+    request: HttpRequest,
+    body: Payload,
+    ticket: Query<Ticket>,
+    token: Data<CancellationToken>,
+    tickets: Data<Mutex<DashboardTickets>>,
+    events: Data<broadcast::Sender<DashboardEvent>>,
+) -> Result<impl Responder> {
+    if !tickets.lock().await.redeem(&ticket.ticket) {
+        return Err(ErrorUnauthorized("invalid or expired dashboard ticket"));
+    }
+    let describe = Name::ip(&request);
+    info!("dashboard connection from '{describe}'");
+    let ws_token = token.get_ref().clone();
+    let mut events_rx = events.subscribe();
+    let (response, mut session, msg_stream) = handle(&request, body)?;
+    let mut agms = msg_stream.aggregate_continuations();
+
+    let _handle = spawn(async move {
+        loop {
+            select! {
+                () = ws_token.cancelled() => {
+                    trace!("cancellation token triggered, closing websocket");
+                    break;
+                }
+                res = agms.next() => {
+                    match res {
+                        Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                            if session.pong(&bytes).await.is_err() {
+                                break;
+                            }
+                        }
+                        Some(Ok(AggregatedMessage::Close(_)) | Err(_)) | None => break,
+                        Some(Ok(_)) => {}
+                    }
+                }
+                event = events_rx.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(_)) => DashboardEvent::Lagged,
+                        Err(RecvError::Closed) => break,
+                    };
+                    if !send_event(&mut session, &event).await {
+                        break;
+                    }
+                }
+            }
+        }
+        info!("dashboard disconnected '{describe}'");
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+/// Returns `false` once the session is closed.
+#[cfg_attr(coverage_nightly, coverage(off))]
+async fn send_event(session: &mut Session, event: &DashboardEvent) -> bool {
+    match serde_json::to_string(event) {
+        Ok(json) => session.text(json).await.is_ok(),
+        Err(e) => {
+            error!("unable to encode dashboard event: {e}");
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{
+        App,
+        http::StatusCode,
+        test::{TestRequest, call_service, init_service},
+        web::{Data, get},
+    };
+    use tokio::sync::{Mutex, broadcast};
+    use tokio_util::sync::CancellationToken;
+    use uuid::Uuid;
+
+    use super::dashboard;
+    use crate::common::{DashboardEvent, DashboardTickets};
+
+    #[actix_web::test]
+    async fn requires_a_valid_ticket() {
+        let mut tickets = DashboardTickets::default();
+        let expired = tickets.issue(Duration::ZERO);
+        let (tx, _rx) = broadcast::channel::<DashboardEvent>(8);
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(CancellationToken::new()))
+                .app_data(Data::new(Mutex::new(tickets)))
+                .app_data(Data::new(tx))
+                .route("/ws/dashboard", get().to(dashboard)),
+        )
+        .await;
+
+        for ticket in [expired, Uuid::new_v4()] {
+            let req = TestRequest::get()
+                .uri(&format!("/ws/dashboard?ticket={ticket}"))
+                .to_request();
+            let res = call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        }
+        let res = call_service(&app, TestRequest::get().uri("/ws/dashboard").to_request()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
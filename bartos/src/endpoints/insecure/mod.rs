@@ -7,21 +7,51 @@
 // modified, or distributed except according to those terms.
 
 mod cli;
+mod dashboard;
 mod worker;
 
 use actix_web::{
     HttpRequest,
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
     web::{ServiceConfig, get},
 };
+use libbarto::{CLIENT_AUTH_HEADER, hash_client_token, parse_verifying_key, verify_client_auth};
 use serde::Deserialize;
 use subtle::ConstantTimeEq as _;
+use tracing::{error, info};
+use utoipa::IntoParams;
 
-#[derive(Deserialize)]
+use crate::{
+    common::{CertIdentity, ClientCredential},
+    config::Config,
+    db::Queryable,
+};
+
+/// The accepted clock skew, in seconds, for a signed client credential header.
+const CLIENT_AUTH_WINDOW_SECS: u64 = 60;
+
+#[derive(Clone, Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub(crate) struct Name {
+    /// The name of the calling client
     name: Option<String>,
 }
 
 impl Name {
+    /// Binds the claimed name to the verified client certificate when `bind_name_to_cert`
+    /// is set, returning the name the connection is known by from here on.
+    pub(crate) fn bind(&self, request: &HttpRequest, config: &Config) -> actix_web::Result<Name> {
+        if !config.bind_name_to_cert() {
+            return Ok(self.clone());
+        }
+        let identity = request.conn_data::<CertIdentity>();
+        bind_cert_identity(self.name.as_deref(), identity)
+            .map(|name| Name { name: Some(name) })
+            .inspect_err(|e| {
+                info!("connection from '{}' rejected: {e}", self.describe(request));
+            })
+    }
+
     pub(crate) fn describe(&self, request: &HttpRequest) -> String {
         let conn_info = request.connection_info();
         let ip = conn_info
@@ -50,6 +80,53 @@ impl Name {
     }
 }
 
+/// Resolves the name of a connection from its client certificate identity.
+///
+/// A connection without a verified certificate is refused, as is a claimed name that is not
+/// one of the certificate's identities. Without a claimed name the certificate's primary
+/// identity is used.
+fn bind_cert_identity(
+    claimed: Option<&str>,
+    identity: Option<&CertIdentity>,
+) -> actix_web::Result<String> {
+    let identity =
+        identity.ok_or_else(|| ErrorUnauthorized("a verified client certificate is required"))?;
+    match claimed {
+        Some(name) if identity.contains(name) => Ok(name.to_string()),
+        Some(_) => Err(ErrorForbidden("name does not match the client certificate")),
+        None => identity
+            .primary()
+            .map(ToString::to_string)
+            .ok_or_else(|| ErrorForbidden("the client certificate has no usable identity")),
+    }
+}
+
+/// How a WebSocket connection proved the identity it claims with `?name=`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum AuthLevel {
+    /// No authentication is configured and the client is not enrolled.
+    Open,
+    /// The client presented the shared `api_key`.
+    Shared,
+    /// The client presented the credential enrolled for its name in the client registry.
+    Enrolled,
+}
+
+impl AuthLevel {
+    /// Returns `true` if the connection may run admin requests (enroll, revoke, cleanup).
+    /// Only enrolled clients can be granted this, since every bartoc holds the shared
+    /// `api_key` and the name of any other connection is unproven.
+    pub(crate) fn is_admin(self, name: &str, config: &Config) -> bool {
+        self == AuthLevel::Enrolled && config.admin_clients().iter().any(|admin| admin == name)
+    }
+
+    /// Returns `true` if the connection may run raw SQL. Only enrolled clients can be
+    /// granted this, since the name of any other connection is unproven.
+    pub(crate) fn may_raw_query(self, name: &str, config: &Config) -> bool {
+        self == AuthLevel::Enrolled && config.raw_query_clients().iter().any(|raw| raw == name)
+    }
+}
+
 /// Returns `true` if the request carries a valid `Authorization: Bearer <token>` header
 /// matching `expected`, using a constant-time comparison to prevent timing attacks.
 /// Returns `true` unconditionally when `expected` is `None` (no auth configured).
@@ -57,30 +134,100 @@ pub(crate) fn bearer_auth_ok(request: &HttpRequest, expected: Option<&str>) -> b
     let Some(expected) = expected else {
         return true;
     };
-    let Some(header_val) = request.headers().get("Authorization") else {
-        return false;
-    };
-    let Ok(header_str) = header_val.to_str() else {
-        return false;
-    };
-    let Some(token) = header_str.strip_prefix("Bearer ") else {
+    let Some(token) = bearer_token(request) else {
         return false;
     };
     token.as_bytes().ct_eq(expected.as_bytes()).into()
 }
 
+fn bearer_token(request: &HttpRequest) -> Option<&str> {
+    request
+        .headers()
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Checks the request against the registry entry for the claimed `name`.
+///
+/// An enrolled client must present its own credential; the shared `api_key` is not enough,
+/// so nobody can connect under (and kick off) an enrolled client's name. Clients without a
+/// registry entry fall back to the shared `api_key`, unless `require_enrollment` is set.
+pub(crate) fn check_credential(
+    request: &HttpRequest,
+    name: &str,
+    config: &Config,
+    credential: Option<&ClientCredential>,
+) -> Option<AuthLevel> {
+    match credential {
+        Some(ClientCredential::Token(hash)) => {
+            let token = bearer_token(request)?;
+            let valid: bool = hash_client_token(token)
+                .as_bytes()
+                .ct_eq(hash.as_bytes())
+                .into();
+            valid.then_some(AuthLevel::Enrolled)
+        }
+        Some(ClientCredential::PublicKey(pk_b64)) => {
+            let header = request.headers().get(CLIENT_AUTH_HEADER)?.to_str().ok()?;
+            let key = parse_verifying_key(pk_b64).ok()?;
+            verify_client_auth(&key, name, header, CLIENT_AUTH_WINDOW_SECS)
+                .ok()
+                .map(|()| AuthLevel::Enrolled)
+        }
+        Some(ClientCredential::Revoked) => None,
+        None if config.require_enrollment() => None,
+        None => match config.api_key() {
+            Some(api_key) => bearer_auth_ok(request, Some(api_key)).then_some(AuthLevel::Shared),
+            None => Some(AuthLevel::Open),
+        },
+    }
+}
+
+/// Authenticates a WebSocket upgrade request for the claimed client `name`, looking up its
+/// credential in the client registry.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub(crate) async fn authenticate<T: Queryable>(
+    request: &HttpRequest,
+    name: &Name,
+    config: &Config,
+    queryable: &T,
+) -> actix_web::Result<AuthLevel> {
+    let describe = name.describe(request);
+    let credential = queryable
+        .client_credential(&name.name())
+        .await
+        .map_err(|e| {
+            error!("unable to look up client credential for '{describe}': {e}");
+            ErrorInternalServerError("internal server error")
+        })?;
+    check_credential(request, &name.name(), config, credential.as_ref()).ok_or_else(|| {
+        info!("connection from '{describe}' rejected: missing or invalid credential");
+        ErrorUnauthorized("unauthorized")
+    })
+}
+
 #[cfg_attr(coverage_nightly, coverage(off))]
 pub(crate) fn insecure_config(cfg: &mut ServiceConfig) {
     _ = cfg
         .route("/ws/cli", get().to(cli::cli))
+        .route("/ws/dashboard", get().to(dashboard::dashboard))
         .route("/ws/worker", get().to(worker::worker));
 }
 
 #[cfg(test)]
 mod tests {
-    use actix_web::test::TestRequest;
+    use actix_web::{http::StatusCode, test::TestRequest};
+    use libbarto::{
+        CLIENT_AUTH_HEADER, SigningKey, hash_client_token, public_key_b64, sign_client_auth,
+    };
 
-    use super::{Name, bearer_auth_ok};
+    use super::{AuthLevel, Name, bearer_auth_ok, bind_cert_identity, check_credential};
+    use crate::{
+        common::{CertIdentity, ClientCredential},
+        config::Config,
+    };
 
     fn name_from(json: &str) -> Name {
         serde_json::from_str(json).expect("deserialize Name")
@@ -151,4 +298,202 @@ mod tests {
         );
         assert_eq!(name_from("{}").describe(&req), "Unknown (Unknown)");
     }
+
+    fn config(api_key: Option<&str>) -> Config {
+        let mut config = Config::default();
+        let _ = config.set_api_key(api_key.map(ToString::to_string));
+        config
+    }
+
+    #[test]
+    fn unenrolled_client_open_without_api_key() {
+        let req = TestRequest::get().to_http_request();
+        assert_eq!(
+            check_credential(&req, "host1", &config(None), None),
+            Some(AuthLevel::Open)
+        );
+    }
+
+    #[test]
+    fn unenrolled_client_uses_shared_api_key() {
+        let req = TestRequest::get()
+            .insert_header(("Authorization", "Bearer shared"))
+            .to_http_request();
+        let config = config(Some("shared"));
+        assert_eq!(
+            check_credential(&req, "host1", &config, None),
+            Some(AuthLevel::Shared)
+        );
+        let bad = TestRequest::get()
+            .insert_header(("Authorization", "Bearer wrong"))
+            .to_http_request();
+        assert_eq!(check_credential(&bad, "host1", &config, None), None);
+    }
+
+    #[test]
+    fn unenrolled_client_rejected_when_enrollment_required() {
+        let req = TestRequest::get()
+            .insert_header(("Authorization", "Bearer shared"))
+            .to_http_request();
+        let mut config = config(Some("shared"));
+        let _ = config.set_require_enrollment(true);
+        assert_eq!(check_credential(&req, "host1", &config, None), None);
+    }
+
+    #[test]
+    fn enrolled_token_must_match() {
+        let credential = ClientCredential::Token(hash_client_token("per-client"));
+        let config = config(Some("shared"));
+        let good = TestRequest::get()
+            .insert_header(("Authorization", "Bearer per-client"))
+            .to_http_request();
+        assert_eq!(
+            check_credential(&good, "host1", &config, Some(&credential)),
+            Some(AuthLevel::Enrolled)
+        );
+        // The shared api_key is not enough to claim an enrolled name.
+        let shared = TestRequest::get()
+            .insert_header(("Authorization", "Bearer shared"))
+            .to_http_request();
+        assert_eq!(
+            check_credential(&shared, "host1", &config, Some(&credential)),
+            None
+        );
+        let missing = TestRequest::get().to_http_request();
+        assert_eq!(
+            check_credential(&missing, "host1", &config, Some(&credential)),
+            None
+        );
+    }
+
+    #[test]
+    fn enrolled_public_key_must_sign_claimed_name() {
+        let sk = SigningKey::from_bytes(&[3; 32]);
+        let credential = ClientCredential::PublicKey(public_key_b64(&sk));
+        let config = config(None);
+        let good = TestRequest::get()
+            .insert_header((CLIENT_AUTH_HEADER, sign_client_auth(&sk, "host1")))
+            .to_http_request();
+        assert_eq!(
+            check_credential(&good, "host1", &config, Some(&credential)),
+            Some(AuthLevel::Enrolled)
+        );
+        let impostor = TestRequest::get()
+            .insert_header((CLIENT_AUTH_HEADER, sign_client_auth(&sk, "host2")))
+            .to_http_request();
+        assert_eq!(
+            check_credential(&impostor, "host1", &config, Some(&credential)),
+            None
+        );
+        let missing = TestRequest::get().to_http_request();
+        assert_eq!(
+            check_credential(&missing, "host1", &config, Some(&credential)),
+            None
+        );
+    }
+
+    #[test]
+    fn revoked_client_always_rejected() {
+        let req = TestRequest::get()
+            .insert_header(("Authorization", "Bearer shared"))
+            .to_http_request();
+        assert_eq!(
+            check_credential(
+                &req,
+                "host1",
+                &config(Some("shared")),
+                Some(&ClientCredential::Revoked)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn admin_permission() {
+        let mut config = config(Some("shared"));
+        let _ = config.set_admin_clients(vec!["ops".to_string()]);
+        // Every bartoc holds the shared api_key, so neither it nor no auth grants admin.
+        assert!(!AuthLevel::Open.is_admin("ops", &config));
+        assert!(!AuthLevel::Shared.is_admin("ops", &config));
+        assert!(AuthLevel::Enrolled.is_admin("ops", &config));
+        assert!(!AuthLevel::Enrolled.is_admin("host1", &config));
+    }
+
+    #[test]
+    fn raw_query_permission() {
+        let mut config = config(Some("shared"));
+        let _ = config.set_raw_query_clients(vec!["ops".to_string()]);
+        assert!(!AuthLevel::Open.may_raw_query("ops", &config));
+        assert!(!AuthLevel::Shared.may_raw_query("ops", &config));
+        assert!(AuthLevel::Enrolled.may_raw_query("ops", &config));
+        assert!(!AuthLevel::Enrolled.may_raw_query("host1", &config));
+    }
+
+    fn identity() -> CertIdentity {
+        CertIdentity::new(vec!["host1".to_string(), "host1.lan".to_string()])
+    }
+
+    fn status(result: actix_web::Result<String>) -> StatusCode {
+        result.unwrap_err().as_response_error().status_code()
+    }
+
+    #[test]
+    fn bind_without_name_uses_primary_identity() {
+        assert_eq!(
+            bind_cert_identity(None, Some(&identity())).unwrap(),
+            "host1"
+        );
+    }
+
+    #[test]
+    fn bind_matching_name_accepted() {
+        assert_eq!(
+            bind_cert_identity(Some("host1.lan"), Some(&identity())).unwrap(),
+            "host1.lan"
+        );
+    }
+
+    #[test]
+    fn bind_mismatching_name_forbidden() {
+        assert_eq!(
+            status(bind_cert_identity(Some("host2"), Some(&identity()))),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[test]
+    fn bind_without_certificate_unauthorized() {
+        assert_eq!(
+            status(bind_cert_identity(Some("host1"), None)),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn bind_certificate_without_identity_forbidden() {
+        assert_eq!(
+            status(bind_cert_identity(None, Some(&CertIdentity::new(vec![])))),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[test]
+    fn bind_disabled_keeps_claimed_name() {
+        let req = TestRequest::get().to_http_request();
+        let name = name_from(r#"{"name":"host1"}"#);
+        let bound = name.bind(&req, &Config::default()).unwrap();
+        assert_eq!(bound.name(), "host1");
+    }
+
+    #[test]
+    fn bind_enabled_plain_connection_rejected() {
+        let req = TestRequest::get().to_http_request();
+        let mut config = Config::default();
+        let _ = config.set_bind_name_to_cert(true);
+        assert!(
+            name_from(r#"{"name":"host1"}"#)
+                .bind(&req, &config)
+                .is_err()
+        );
+    }
 }
@@ -6,11 +6,15 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    slice,
+};
 
 use actix_web::{
     HttpRequest, Responder, Result,
     error::ErrorInternalServerError,
+    http::header::{HeaderName, HeaderValue},
     rt::spawn,
     web::{Bytes, Data, Payload, Query},
 };
@@ -18,24 +22,30 @@ use actix_ws::{AggregatedMessage, Session, handle};
 use bincode_next::{config::standard, decode_from_slice, encode_to_vec};
 use futures_util::StreamExt as _;
 use libbarto::{
-    Bartoc, BartosToBartoc, Initialize, Output, OutputKind, OutputTableName, Schedules, Status,
-    StatusTableName, UuidWrapper, hmac_sign, parse_hmac_key, parse_signing_key, parse_ts_ping,
-    sign_payload,
+    Bartoc, BartosToBartoc, ClientEvent, ClientEventKind, ENCODING_HEADER, Initialize,
+    LinkEncoding, OffsetDataTimeWrapper, Output, RunStart, SESSION_HEADER, Schedules, TraceParent,
+    UuidWrapper, decompress, hmac_sign, hmac_sign_with_id, parse_hmac_key, parse_signing_key,
+    parse_ts_ping, sequence_wrap, sign_payload, sign_payload_with_id,
 };
-use sqlx::MySqlPool;
+use time::OffsetDateTime;
 use tokio::{
     select,
     sync::{Mutex, RwLock, broadcast},
     time::{Duration, Instant, interval},
 };
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info, trace};
+use tracing::{Instrument as _, error, info, info_span, trace, warn};
 use uuid::Uuid;
 
 use crate::{
-    common::{Clients, WorkerSignal},
+    alert::Alerter,
+    common::{Clients, DashboardEvent, DisconnectReason, WorkerSignal},
     config::Config,
-    endpoints::insecure::{Name, bearer_auth_ok},
+    db::{Queryable, Store},
+    endpoints::insecure::{Name, authenticate},
+    metrics::{Metrics, Record, Socket},
+    tail::{TailRecord, publish},
+    watchdog::Watchdog,
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -46,67 +56,104 @@ pub(crate) async fn worker(
     name: Query<Name>,
     token: Data<CancellationToken>,
     config: Data<Config>,
-    pool: Data<MySqlPool>,
+    store: Data<Store>,
     clients: Data<Mutex<Clients>>,
     live_schedules: Data<RwLock<BTreeMap<String, Schedules>>>,
     worker_bcast: Data<broadcast::Sender<WorkerSignal>>,
+    metrics: Data<Metrics>,
+    dashboard: Data<broadcast::Sender<DashboardEvent>>,
+    tail: Data<broadcast::Sender<TailRecord>>,
+    alerter: Data<Alerter>,
+    watchdog: Data<Watchdog>,
 ) -> Result<impl Responder> {
+    let name = name.bind(&request, &config)?;
     let describe = name.describe(&request);
     info!("worker connection from '{describe}'");
-    if !bearer_auth_ok(&request, config.api_key().as_deref()) {
-        info!("worker connection from '{describe}' rejected: missing or invalid Bearer token");
-        return Err(actix_web::error::ErrorUnauthorized("unauthorized"));
-    }
+    let _auth_level = authenticate(&request, &name, &config, store.get_ref()).await?;
     let id = Uuid::new_v4();
-    let (response, session, msg_stream) = handle(&request, body)?;
+    let mut sequencer = requested_session(&request).map(Sequencer::new);
+    let (mut response, session, msg_stream) = handle(&request, body)?;
+    if let Some(sequencer) = &sequencer
+        && let Ok(name) = HeaderName::from_bytes(SESSION_HEADER.as_bytes())
+        && let Ok(value) = HeaderValue::from_str(&sequencer.session.to_string())
+    {
+        response.headers_mut().insert(name, value);
+    }
+    if let Some(encoding) = accepted_encoding(&request)
+        && let Ok(name) = HeaderName::from_bytes(ENCODING_HEADER.as_bytes())
+        && let Ok(value) = HeaderValue::from_str(&encoding)
+    {
+        response.headers_mut().insert(name, value);
+    }
     let mut agms = msg_stream.aggregate_continuations();
     let ws_token = token.get_ref().clone();
     let mut ws_session = session.clone();
     let mut init_session = session.clone();
     let config_c = config.clone();
     let clients_c = clients.clone();
-    // Capture client name before it is moved into initialize()
+    // Capture client name and address before they are moved into initialize()
     let client_name = name.name();
+    let client_ip = Name::ip(&request);
     let mut worker_rx = worker_bcast.subscribe();
-    if let Err(e) = initialize(id, &mut init_session, request, name, config, clients).await {
+    if let Err(e) = initialize(
+        id,
+        &mut init_session,
+        request,
+        name,
+        config,
+        store.get_ref(),
+        clients,
+        sequencer.as_mut(),
+    )
+    .await
+    {
         error!("unable to initialize worker session: {e}");
         let _ = init_session.close(None).await;
         return Err(e);
     }
+    record_client_event(
+        store.get_ref(),
+        &client_event(id, &client_name, &client_ip, None),
+    )
+    .await;
+    let _ = dashboard.send(DashboardEvent::Connected {
+        client: client_name.clone(),
+    });
 
     let _handle = spawn(async move {
         const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
         const CLIENT_TIMEOUT: Duration = Duration::from_secs(15);
         let mut last_heartbeat = Instant::now();
         let mut hb_interval = interval(HEARTBEAT_INTERVAL);
-        loop {
+        let reason = loop {
             select! {
                 () = ws_token.cancelled() => {
                     trace!("cancellation token triggered, closing websocket");
                     let _ = ws_session.close(None).await;
-                    break;
+                    break DisconnectReason::Shutdown;
                 }
                 _ = hb_interval.tick() => {
                     if last_heartbeat.elapsed() > CLIENT_TIMEOUT {
                         error!("client '{describe}' heartbeat timed out, disconnecting");
-                        break;
+                        metrics.heartbeat_timed_out(&client_name);
+                        break DisconnectReason::HeartbeatTimeout;
                     }
                 }
                 res = agms.next() => {
                     match res {
                         Some(Ok(msg)) => {
                             last_heartbeat = Instant::now();
-                            if handle_ws_msg(id, msg, &config_c, pool.as_ref(), clients_c.clone(), &mut ws_session).await {
-                                break;
+                            if let Some(reason) = handle_ws_msg(id, &client_name, msg, &config_c, store.get_ref(), clients_c.clone(), &metrics, &dashboard, &tail, &alerter, &watchdog, &mut ws_session).await {
+                                break reason;
                             }
                         }
                         Some(Err(e)) => {
                             error!("websocket error: {e}");
-                            break;
+                            break DisconnectReason::Error;
                         }
                         None => {
                             trace!("websocket stream closed");
-                            break;
+                            break DisconnectReason::Dropped;
                         }
                     }
                 }
@@ -116,7 +163,7 @@ pub(crate) async fn worker(
                             let schedules_guard = live_schedules.read().await;
                             let schedules = schedules_guard.get(&client_name).cloned();
                             drop(schedules_guard);
-                            let init_bytes = build_init_bytes(id, schedules, &config_c);
+                            let init_bytes = build_init_bytes(id, schedules, &config_c, sequencer.as_mut());
                             if let Err(e) = ws_session.binary(init_bytes).await {
                                 error!("unable to send updated schedules to '{describe}': {e}");
                             } else {
@@ -124,47 +171,89 @@ pub(crate) async fn worker(
                             }
                         }
                         Ok(WorkerSignal::Cleanup) => {
-                            let cleanup_bytes = build_cleanup_bytes(&config_c);
+                            let cleanup_bytes = build_cleanup_bytes(&config_c, sequencer.as_mut());
                             if let Err(e) = ws_session.binary(cleanup_bytes).await {
                                 error!("unable to send cleanup signal to '{describe}': {e}");
                             } else {
                                 info!("sent cleanup signal to '{describe}'");
                             }
                         }
+                        Ok(WorkerSignal::Disconnect(target)) => {
+                            if target == client_name {
+                                info!("disconnecting '{describe}': credential revoked");
+                                break DisconnectReason::Revoked;
+                            }
+                        }
                         Err(_) => {}
                     }
                 }
             }
-        }
+        };
 
-        info!("websocket disconnected '{describe}'");
+        info!(
+            "websocket disconnected '{describe}': {}",
+            <&'static str>::from(reason)
+        );
         let _ = session.close(None).await;
         let mut clients = clients_c.lock().await;
-        let _old = clients.remove_client(&id);
-        trace!("removed client '{describe}' from active clients");
+        let removed = clients.remove_client(&id);
+        drop(clients);
+        if removed.is_some() {
+            trace!("removed client '{describe}' from active clients");
+            record_client_event(
+                store.get_ref(),
+                &client_event(id, &client_name, &client_ip, Some(reason)),
+            )
+            .await;
+        } else {
+            // A client with the same name took over, and its disconnect was recorded then.
+            trace!("client '{describe}' was already replaced");
+        }
+        let _ = dashboard.send(DashboardEvent::Disconnected {
+            client: client_name,
+        });
     });
 
     Ok(response)
 }
 
-/// Returns `true` if the loop should break (connection close or unrecoverable error).
+/// Returns why the connection ended if the loop should break.
+#[allow(clippy::too_many_arguments)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 async fn handle_ws_msg(
     id: Uuid,
+    client_name: &str,
     msg: AggregatedMessage,
     config: &Config,
-    pool: &MySqlPool,
+    store: &Store,
     clients: Data<Mutex<Clients>>,
+    metrics: &Metrics,
+    dashboard: &broadcast::Sender<DashboardEvent>,
+    tail: &broadcast::Sender<TailRecord>,
+    alerter: &Alerter,
+    watchdog: &Watchdog,
     ws_session: &mut Session,
-) -> bool {
+) -> Option<DisconnectReason> {
     match msg {
         AggregatedMessage::Text(_) => error!("unexpected text message"),
         AggregatedMessage::Binary(bytes) => {
-            handle_binary(id, bytes, config, pool, clients)
-                .await
-                .unwrap_or_else(|e| {
-                    error!("unable to handle binary message: {e}");
-                });
+            handle_binary(
+                id,
+                client_name,
+                bytes,
+                config,
+                store,
+                clients,
+                metrics,
+                dashboard,
+                tail,
+                alerter,
+                watchdog,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                error!("unable to handle binary message: {e}");
+            });
         }
         AggregatedMessage::Ping(bytes) => {
             trace!("handling ping message");
@@ -193,15 +282,57 @@ async fn handle_ws_msg(
             } else {
                 trace!("close reason: none");
             }
-            return true;
+            return Some(DisconnectReason::Closed(
+                close_reason.map(|cr| u16::from(cr.code)),
+            ));
         }
     }
-    false
+    None
+}
+
+/// The connect event of a client, or its disconnect event when the connection ended for
+/// `reason`.
+fn client_event(
+    id: Uuid,
+    client_name: &str,
+    ip: &str,
+    reason: Option<DisconnectReason>,
+) -> ClientEvent {
+    let kind = if reason.is_some() {
+        ClientEventKind::Disconnect
+    } else {
+        ClientEventKind::Connect
+    };
+    ClientEvent::builder()
+        .bartoc_uuid(UuidWrapper(id))
+        .bartoc_name(client_name.to_string())
+        .ip(ip.to_string())
+        .kind(kind)
+        .maybe_reason(reason.map(|reason| <&'static str>::from(reason).to_string()))
+        .maybe_close_code(reason.and_then(DisconnectReason::close_code))
+        .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+        .build()
+}
+
+/// Stores a client event, logging rather than failing when the database is unavailable.
+async fn record_client_event(store: &Store, event: &ClientEvent) {
+    if let Err(e) = store.insert_client_event(event).await {
+        error!(
+            "unable to record {} of '{}': {e}",
+            event.kind(),
+            event.bartoc_name()
+        );
+    }
 }
 
 /// Builds and returns the encoded (and optionally signed) Initialize payload.
 /// Returns an empty vec when there are no schedules for this client.
-fn build_init_bytes(id: Uuid, schedules: Option<Schedules>, config: &Config) -> Vec<u8> {
+fn build_init_bytes(
+    id: Uuid,
+    schedules: Option<Schedules>,
+    config: &Config,
+    sequencer: Option<&mut Sequencer>,
+) -> Vec<u8> {
     let Some(schedules) = schedules else {
         return vec![];
     };
@@ -216,12 +347,12 @@ fn build_init_bytes(id: Uuid, schedules: Option<Schedules>, config: &Config) ->
             return vec![];
         }
     };
-    sign_worker_payload(payload, config)
+    sign_worker_payload(payload, config, sequencer)
 }
 
 /// Builds and returns the encoded (and optionally signed) Cleanup payload, asking the
 /// worker to clean up old entries from its local redb database.
-fn build_cleanup_bytes(config: &Config) -> Vec<u8> {
+fn build_cleanup_bytes(config: &Config, sequencer: Option<&mut Sequencer>) -> Vec<u8> {
     trace!("building cleanup payload");
     let payload = match encode_to_vec(BartosToBartoc::Cleanup, standard()) {
         Ok(p) => p,
@@ -230,15 +361,60 @@ fn build_cleanup_bytes(config: &Config) -> Vec<u8> {
             return vec![];
         }
     };
-    sign_worker_payload(payload, config)
+    sign_worker_payload(payload, config, sequencer)
+}
+
+/// Numbers the messages sent on a connection whose bartoc asked for sequencing by sending a
+/// session id on the WebSocket upgrade.
+#[derive(Clone, Copy, Debug)]
+struct Sequencer {
+    session: Uuid,
+    counter: u64,
+}
+
+impl Sequencer {
+    fn new(session: Uuid) -> Self {
+        Self {
+            session,
+            counter: 0,
+        }
+    }
+
+    fn wrap(&mut self, payload: &[u8]) -> Vec<u8> {
+        self.counter += 1;
+        sequence_wrap(&self.session, self.counter, payload)
+    }
 }
 
-/// Applies the optional HMAC-SHA256 envelope and Ed25519 signature to a worker-bound
-/// payload, matching the auth configuration. Shared by all bartos → bartoc messages.
-fn sign_worker_payload(payload: Vec<u8>, config: &Config) -> Vec<u8> {
+/// Returns the session id from the upgrade request, if the bartoc asked for sequencing.
+fn requested_session(request: &HttpRequest) -> Option<Uuid> {
+    request
+        .headers()
+        .get(SESSION_HEADER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Applies the optional sequence header, HMAC-SHA256 envelope and Ed25519 signature to a
+/// worker-bound payload, matching the auth configuration. Shared by all bartos → bartoc messages.
+fn sign_worker_payload(
+    payload: Vec<u8>,
+    config: &Config,
+    sequencer: Option<&mut Sequencer>,
+) -> Vec<u8> {
+    let payload = match sequencer {
+        Some(sequencer) => sequencer.wrap(&payload),
+        None => payload,
+    };
     let payload = if let Some(hmac_key_str) = config.hmac_key() {
         trace!("wrapping worker message with HMAC-SHA256 envelope");
-        hmac_sign(&parse_hmac_key(hmac_key_str), &payload)
+        let hmac_key = parse_hmac_key(hmac_key_str);
+        match config.hmac_key_id() {
+            Some(key_id) => hmac_sign_with_id(&hmac_key, key_id, &payload),
+            None => hmac_sign(&hmac_key, &payload),
+        }
     } else {
         payload
     };
@@ -246,7 +422,10 @@ fn sign_worker_payload(payload: Vec<u8>, config: &Config) -> Vec<u8> {
         match parse_signing_key(sk_b64) {
             Ok(sk) => {
                 trace!("signing worker message with Ed25519 key");
-                sign_payload(&sk, &payload)
+                match config.signing_key_id() {
+                    Some(key_id) => sign_payload_with_id(&sk, key_id, &payload),
+                    None => sign_payload(&sk, &payload),
+                }
             }
             Err(e) => {
                 error!("invalid signing key, sending unsigned: {e}");
@@ -258,25 +437,35 @@ fn sign_worker_payload(payload: Vec<u8>, config: &Config) -> Vec<u8> {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 async fn initialize(
     id: Uuid,
     session: &mut Session,
     request: HttpRequest,
-    name: Query<Name>,
+    name: Name,
     config: Data<Config>,
+    store: &Store,
     clients: Data<Mutex<Clients>>,
+    sequencer: Option<&mut Sequencer>,
 ) -> Result<()> {
     let describe = name.describe(&request);
     let mut clients = clients.lock().await;
     let old_opt = clients.remove_client_by_name(&name.name());
-    if let Some(_old) = old_opt {
+    if let Some((old_id, old)) = old_opt {
         info!("removed old client with same name '{}'", name.name());
+        let replaced = client_event(
+            old_id,
+            old.name(),
+            old.ip(),
+            Some(DisconnectReason::Replaced),
+        );
+        record_client_event(store, &replaced).await;
     }
     let _old = clients.add_client(id, &name.name(), &Name::ip(&request));
     let name = name.name();
     let schedules_opt = config.schedules().get(&name).cloned();
-    let init_bytes = build_init_bytes(id, schedules_opt, &config);
+    let init_bytes = build_init_bytes(id, schedules_opt, &config, sequencer);
     if !init_bytes.is_empty() {
         let count = config
             .schedules()
@@ -291,140 +480,196 @@ async fn initialize(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 async fn handle_binary(
     id: Uuid,
+    client_name: &str,
     bytes: Bytes,
     config: &Config,
-    pool: &MySqlPool,
+    store: &Store,
     clients_mutex: Data<Mutex<Clients>>,
+    metrics: &Metrics,
+    dashboard: &broadcast::Sender<DashboardEvent>,
+    tail: &broadcast::Sender<TailRecord>,
+    alerter: &Alerter,
+    watchdog: &Watchdog,
 ) -> Result<()> {
     trace!("handling binary message");
-    match decode_from_slice(&bytes, standard()) {
-        Err(e) => error!("unable to decode binary message: {e}"),
-        Ok((bartoc_msg, _)) => match bartoc_msg {
+    match decode_worker_message(&bytes) {
+        Err(e) => {
+            error!("unable to decode binary message: {e}");
+            metrics.decode_failed(Socket::Worker);
+        }
+        Ok(bartoc_msg) => match bartoc_msg {
             Bartoc::Record(data) => match data {
-                libbarto::Data::Output(output) => match config.mariadb().output_table() {
-                    OutputTableName::Output => {
-                        trace!("handling output data: {}", output);
-                        let _id = insert_output(pool, &output).await.unwrap_or_else(|e| {
-                            error!("unable to insert output into database: {e}");
-                            0
-                        });
-                    }
-                    OutputTableName::OutputTest => {
-                        trace!("handling output data: {}", output);
-                        let _id = insert_output_test(pool, &output).await.unwrap_or_else(|e| {
-                            error!("unable to insert output into database: {e}");
-                            0
-                        });
-                    }
-                },
-                libbarto::Data::Status(status) => match config.mariadb().status_table() {
-                    StatusTableName::Status => {
-                        trace!("handling status data: {}", status);
-                        let _id = insert_status(pool, &status).await.unwrap_or_else(|e| {
-                            error!("unable to insert status into database: {e}");
-                            0
-                        });
-                    }
-                    StatusTableName::StatusTest => {
-                        trace!("handling status data: {}", status);
-                        let _id = insert_status_test(pool, &status).await.unwrap_or_else(|e| {
-                            error!("unable to insert status into database: {e}");
-                            0
-                        });
-                    }
-                },
+                libbarto::Data::Output(mut output) => {
+                    bind_output_name(&mut output, client_name, config);
+                    trace!("handling output data: {}", output);
+                    let cmd_uuid = output.cmd_uuid().0;
+                    let rows = store.insert_outputs(slice::from_ref(&output)).await;
+                    record_insert(metrics, Record::Output, "output", rows);
+                    publish(tail, client_name, || libbarto::Data::Output(output));
+                    let _ = dashboard.send(DashboardEvent::RunOutput {
+                        client: client_name.to_string(),
+                        cmd_uuid,
+                    });
+                }
+                libbarto::Data::Status(status) => {
+                    trace!("handling status data: {}", status);
+                    let rows = store
+                        .insert_status(&status)
+                        .instrument(TraceParent::span_in(status.trace_parent(), || {
+                            info_span!("insert", record = "status")
+                        }))
+                        .await;
+                    record_insert(metrics, Record::Status, "status", rows);
+                    metrics.run_finished(client_name, &status);
+                    alerter.status(store, client_name, &status).await;
+                    publish(tail, client_name, || libbarto::Data::Status(status));
+                    let _ = dashboard.send(DashboardEvent::RunFinished {
+                        client: client_name.to_string(),
+                        cmd_uuid: status.cmd_uuid().0,
+                        success: status.success(),
+                    });
+                }
+                libbarto::Data::Started(mut run_start) => {
+                    bind_run_name(&mut run_start, client_name, config);
+                    trace!("handling run start: {}", run_start);
+                    let rows = store
+                        .insert_run_start(&run_start)
+                        .instrument(TraceParent::span_in(run_start.trace_parent(), || {
+                            info_span!("insert", record = "run start")
+                        }))
+                        .await;
+                    record_insert(metrics, Record::RunStart, "run start", rows);
+                    metrics.run_started(&run_start);
+                    watchdog.run_started(&run_start);
+                    publish(tail, client_name, || {
+                        libbarto::Data::Started(run_start.clone())
+                    });
+                    let _ = dashboard.send(DashboardEvent::RunStarted {
+                        client: client_name.to_string(),
+                        schedule: run_start.schedule_name().clone(),
+                        cmd_uuid: run_start.cmd_uuid().0,
+                    });
+                }
             },
             Bartoc::ClientInfo(bi) => {
                 info!("received client info: {bi}");
                 let mut clients = clients_mutex.lock().await;
                 clients.add_client_data(&id, bi);
+                drop(clients);
+                let _ = dashboard.send(DashboardEvent::ClientInfo {
+                    client: client_name.to_string(),
+                });
             }
+            Bartoc::RecordBatch(batch) => {
+                let trace_parent = batch.trace_parent();
+                let mut outputs = batch.into_outputs();
+                for output in &mut outputs {
+                    bind_output_name(output, client_name, config);
+                }
+                trace!("handling batch of {} output records", outputs.len());
+                let cmd_uuids: BTreeSet<Uuid> =
+                    outputs.iter().map(|output| output.cmd_uuid().0).collect();
+                let rows = store
+                    .insert_outputs(&outputs)
+                    .instrument(TraceParent::span_in(trace_parent, || {
+                        info_span!("insert", record = "output", lines = outputs.len())
+                    }))
+                    .await;
+                record_insert(metrics, Record::Output, "output batch", rows);
+                for output in &outputs {
+                    publish(tail, client_name, || libbarto::Data::Output(output.clone()));
+                }
+                for cmd_uuid in cmd_uuids {
+                    let _ = dashboard.send(DashboardEvent::RunOutput {
+                        client: client_name.to_string(),
+                        cmd_uuid,
+                    });
+                }
+            }
+            Bartoc::Compressed(_) => error!("nested compressed message, ignoring"),
         },
     }
     Ok(())
 }
 
-#[cfg_attr(coverage_nightly, coverage(off))]
-async fn insert_output(pool: &MySqlPool, output: &Output) -> anyhow::Result<u64> {
-    let id = sqlx::query!(
-        r#"INSERT INTO output (bartoc_uuid, bartoc_name, cmd_uuid, cmd_name, timestamp, kind, data)
-VALUES (?, ?, ?, ?, ?, ?, ?)"#,
-        output.bartoc_uuid().0,
-        output.bartoc_name(),
-        output.cmd_uuid().0,
-        output.cmd_name(),
-        output.timestamp().0,
-        <OutputKind as Into<&'static str>>::into(output.kind()),
-        output.data()
-    )
-    .execute(pool)
-    .await?
-    .last_insert_id();
-    Ok(id)
+/// Logs a failed insert of a bartoc record and counts the outcome either way.
+fn record_insert(metrics: &Metrics, record: Record, what: &str, rows: anyhow::Result<u64>) {
+    match rows {
+        Ok(rows) => metrics.inserted(record, rows),
+        Err(e) => {
+            error!("unable to insert {what} into database: {e}");
+            metrics.insert_failed(record);
+        }
+    }
 }
 
-#[cfg_attr(coverage_nightly, coverage(off))]
-async fn insert_output_test(pool: &MySqlPool, output: &Output) -> anyhow::Result<u64> {
-    let id = sqlx::query!(
-        r#"INSERT INTO output_test (bartoc_uuid, bartoc_name, cmd_uuid, cmd_name, timestamp, kind, data)
-VALUES (?, ?, ?, ?, ?, ?, ?)"#,
-        output.bartoc_uuid().0,
-        output.bartoc_name(),
-        output.cmd_uuid().0,
-        output.cmd_name(),
-        output.timestamp().0,
-        <OutputKind as Into<&'static str>>::into(output.kind()),
-        output.data()
-    )
-    .execute(pool)
-    .await?
-    .last_insert_id();
-    Ok(id)
+/// When names are bound to client certificates, records output under the connection's
+/// verified identity rather than whatever name the worker reported.
+fn bind_output_name(output: &mut Output, client_name: &str, config: &Config) {
+    if config.bind_name_to_cert() && output.bartoc_name() != client_name {
+        warn!(
+            "output reported for '{}' on connection bound to '{client_name}', recording as '{client_name}'",
+            output.bartoc_name()
+        );
+        let _ = output.set_bartoc_name(client_name.to_string());
+    }
 }
 
-#[cfg_attr(coverage_nightly, coverage(off))]
-async fn insert_status(pool: &MySqlPool, status: &Status) -> anyhow::Result<u64> {
-    let id = sqlx::query!(
-        r#"INSERT INTO exit_status (cmd_uuid, timestamp, exit_code, success)
-VALUES (?, ?, ?, ?)"#,
-        status.cmd_uuid().0,
-        status.timestamp().0,
-        status.exit_code(),
-        status.success()
-    )
-    .execute(pool)
-    .await?
-    .last_insert_id();
-    Ok(id)
+/// As [`bind_output_name`], for the start record of a run.
+fn bind_run_name(run_start: &mut RunStart, client_name: &str, config: &Config) {
+    if config.bind_name_to_cert() && run_start.bartoc_name() != client_name {
+        warn!(
+            "run reported for '{}' on connection bound to '{client_name}', recording as '{client_name}'",
+            run_start.bartoc_name()
+        );
+        let _ = run_start.set_bartoc_name(client_name.to_string());
+    }
 }
 
-#[cfg_attr(coverage_nightly, coverage(off))]
-async fn insert_status_test(pool: &MySqlPool, status: &Status) -> anyhow::Result<u64> {
-    let id = sqlx::query!(
-        r#"INSERT INTO exit_status_test (cmd_uuid, timestamp, exit_code, success)
-VALUES (?, ?, ?, ?)"#,
-        status.cmd_uuid().0,
-        status.timestamp().0,
-        status.exit_code(),
-        status.success()
-    )
-    .execute(pool)
-    .await?
-    .last_insert_id();
-    Ok(id)
+/// Decode a worker frame, unwrapping one level of zstd compression.
+fn decode_worker_message(bytes: &[u8]) -> anyhow::Result<Bartoc> {
+    let (bartoc_msg, _) = decode_from_slice(bytes, standard())?;
+    match bartoc_msg {
+        Bartoc::Compressed(compressed) => {
+            let (inner, _) = decode_from_slice(&decompress(&compressed)?, standard())?;
+            Ok(inner)
+        }
+        bartoc_msg => Ok(bartoc_msg),
+    }
+}
+
+/// Returns the `X-Barto-Encoding` value to echo: every encoding the bartoc offered that
+/// bartos understands, or `None` if it offered none.
+fn accepted_encoding(request: &HttpRequest) -> Option<String> {
+    let offered = request.headers().get(ENCODING_HEADER)?.to_str().ok()?;
+    LinkEncoding::parse(offered).header_value()
 }
 
 #[cfg(test)]
 mod tests {
-    use bincode_next::{config::standard, decode_from_slice};
-    use libbarto::{BartosToBartoc, Schedules};
+    use std::collections::BTreeMap;
+
+    use actix_web::test::TestRequest;
+    use bincode_next::{config::standard, decode_from_slice, encode_to_vec};
+    use libbarto::{
+        Bartoc, BartosToBartoc, ClientEventKind, ENCODING_HEADER, OffsetDataTimeWrapper, Output,
+        OutputBatch, OutputKind, RunStart, SESSION_HEADER, Schedules, SigningKey, UuidWrapper,
+        compress, hmac_verify_and_extract_with_id, parse_hmac_key, sequence_unwrap,
+        signing_key_b64, verify_and_extract_with_id,
+    };
+    use time::OffsetDateTime;
     use uuid::Uuid;
 
-    use super::{build_cleanup_bytes, build_init_bytes, sign_worker_payload};
-    use crate::config::Config;
+    use super::{
+        Sequencer, accepted_encoding, bind_output_name, bind_run_name, build_cleanup_bytes,
+        build_init_bytes, client_event, decode_worker_message, requested_session,
+        sign_worker_payload,
+    };
+    use crate::{common::DisconnectReason, config::Config};
 
     fn empty_schedules() -> Schedules {
         // `Schedules` only derives `Builder` under libbarto's own test cfg, so build
@@ -434,13 +679,18 @@ mod tests {
 
     #[test]
     fn build_init_bytes_none_is_empty() {
-        let bytes = build_init_bytes(Uuid::new_v4(), None, &Config::default());
+        let bytes = build_init_bytes(Uuid::new_v4(), None, &Config::default(), None);
         assert!(bytes.is_empty());
     }
 
     #[test]
     fn build_init_bytes_some_is_non_empty() {
-        let bytes = build_init_bytes(Uuid::new_v4(), Some(empty_schedules()), &Config::default());
+        let bytes = build_init_bytes(
+            Uuid::new_v4(),
+            Some(empty_schedules()),
+            &Config::default(),
+            None,
+        );
         assert!(!bytes.is_empty());
     }
 
@@ -448,7 +698,7 @@ mod tests {
     fn build_cleanup_bytes_round_trips() {
         // Default config has no HMAC/signing configured, so the payload is the raw
         // encoded message and decodes straight back.
-        let bytes = build_cleanup_bytes(&Config::default());
+        let bytes = build_cleanup_bytes(&Config::default(), None);
         let (decoded, _): (BartosToBartoc, _) =
             decode_from_slice(&bytes, standard()).expect("decode");
         assert!(matches!(decoded, BartosToBartoc::Cleanup));
@@ -458,8 +708,158 @@ mod tests {
     fn sign_worker_payload_passthrough_without_auth() {
         let payload = vec![1_u8, 2, 3, 4];
         assert_eq!(
-            sign_worker_payload(payload.clone(), &Config::default()),
+            sign_worker_payload(payload.clone(), &Config::default(), None),
             payload
         );
     }
+
+    #[test]
+    fn sign_worker_payload_with_key_ids() {
+        let sk = SigningKey::from_bytes(&[3; 32]);
+        let mut config = Config::default();
+        let _ = config.set_signing_key(Some(signing_key_b64(&sk)));
+        let _ = config.set_signing_key_id(Some(7));
+        let _ = config.set_hmac_key(Some("secret".to_string()));
+        let _ = config.set_hmac_key_id(Some(9));
+        let signed = sign_worker_payload(vec![1_u8, 2, 3], &config, None);
+        let enveloped =
+            verify_and_extract_with_id(&BTreeMap::from([(7, sk.verifying_key())]), &signed)
+                .unwrap();
+        let hmac_keys = BTreeMap::from([(9, parse_hmac_key("secret"))]);
+        let (payload, _ts, _nonce) =
+            hmac_verify_and_extract_with_id(&hmac_keys, &enveloped, 60).unwrap();
+        assert_eq!(payload, vec![1_u8, 2, 3]);
+    }
+
+    #[test]
+    fn sign_worker_payload_sequences_each_message() {
+        let session = Uuid::new_v4();
+        let mut sequencer = Sequencer::new(session);
+        let config = Config::default();
+        for expected in 1..=3 {
+            let bytes = sign_worker_payload(vec![1_u8, 2, 3], &config, Some(&mut sequencer));
+            let (got_session, counter, payload) = sequence_unwrap(&bytes).unwrap();
+            assert_eq!(got_session, session);
+            assert_eq!(counter, expected);
+            assert_eq!(payload, vec![1_u8, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn client_event_records_kind_and_reason() {
+        let id = Uuid::new_v4();
+        let connect = client_event(id, "host1", "10.0.0.1", None);
+        assert_eq!(connect.bartoc_uuid(), UuidWrapper(id));
+        assert_eq!(connect.kind(), ClientEventKind::Connect);
+        assert!(connect.reason().is_none());
+
+        let closed = client_event(
+            id,
+            "host1",
+            "10.0.0.1",
+            Some(DisconnectReason::Closed(Some(1001))),
+        );
+        assert_eq!(closed.kind(), ClientEventKind::Disconnect);
+        assert_eq!(closed.reason().as_deref(), Some("closed"));
+        assert_eq!(closed.close_code(), Some(1001));
+
+        let timed_out = client_event(
+            id,
+            "host1",
+            "10.0.0.1",
+            Some(DisconnectReason::HeartbeatTimeout),
+        );
+        assert_eq!(timed_out.reason().as_deref(), Some("heartbeat_timeout"));
+        assert!(timed_out.close_code().is_none());
+    }
+
+    #[test]
+    fn decode_worker_message_unwraps_compression() {
+        let batch = OutputBatch::from_outputs(vec![output("bartoc"), output("bartoc")], 1024)
+            .pop()
+            .unwrap();
+        let plain = encode_to_vec(Bartoc::RecordBatch(batch.clone()), standard()).unwrap();
+        let compressed =
+            encode_to_vec(Bartoc::Compressed(compress(&plain).unwrap()), standard()).unwrap();
+        for bytes in [plain, compressed] {
+            assert_eq!(
+                decode_worker_message(&bytes).unwrap(),
+                Bartoc::RecordBatch(batch.clone())
+            );
+        }
+        assert!(decode_worker_message(&[0xff; 4]).is_err());
+    }
+
+    #[test]
+    fn accepted_encoding_echoes_known_encodings() {
+        let request = TestRequest::default()
+            .insert_header((ENCODING_HEADER, "batch, zstd, brotli"))
+            .to_http_request();
+        assert_eq!(accepted_encoding(&request).as_deref(), Some("batch, zstd"));
+        let request = TestRequest::default()
+            .insert_header((ENCODING_HEADER, "brotli"))
+            .to_http_request();
+        assert!(accepted_encoding(&request).is_none());
+        assert!(accepted_encoding(&TestRequest::default().to_http_request()).is_none());
+    }
+
+    #[test]
+    fn requested_session_parses_header() {
+        let session = Uuid::new_v4();
+        let request = TestRequest::default()
+            .insert_header((SESSION_HEADER, session.to_string()))
+            .to_http_request();
+        assert_eq!(requested_session(&request), Some(session));
+        let request = TestRequest::default()
+            .insert_header((SESSION_HEADER, "not-a-uuid"))
+            .to_http_request();
+        assert!(requested_session(&request).is_none());
+        assert!(requested_session(&TestRequest::default().to_http_request()).is_none());
+    }
+
+    fn output(bartoc_name: &str) -> Output {
+        Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(bartoc_name.to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .cmd_name("cmd".to_string())
+            .kind(OutputKind::Stdout)
+            .data("data".to_string())
+            .build()
+    }
+
+    #[test]
+    fn bind_output_name_disabled_keeps_reported_name() {
+        let mut output = output("reported");
+        bind_output_name(&mut output, "host1", &Config::default());
+        assert_eq!(output.bartoc_name(), "reported");
+    }
+
+    #[test]
+    fn bind_output_name_enabled_uses_connection_identity() {
+        let mut config = Config::default();
+        let _ = config.set_bind_name_to_cert(true);
+        let mut output = output("reported");
+        bind_output_name(&mut output, "host1", &config);
+        assert_eq!(output.bartoc_name(), "host1");
+    }
+
+    #[test]
+    fn bind_run_name_enabled_uses_connection_identity() {
+        let mut run_start = RunStart::builder()
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name("reported".to_string())
+            .schedule_name("cmd".to_string())
+            .cmd("echo".to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+            .build();
+        bind_run_name(&mut run_start, "host1", &Config::default());
+        assert_eq!(run_start.bartoc_name(), "reported");
+        let mut config = Config::default();
+        let _ = config.set_bind_name_to_cert(true);
+        bind_run_name(&mut run_start, "host1", &config);
+        assert_eq!(run_start.bartoc_name(), "host1");
+    }
 }
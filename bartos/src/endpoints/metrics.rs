@@ -0,0 +1,102 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The Prometheus exposition endpoint
+
+use actix_web::{
+    HttpRequest, HttpResponse, Result,
+    error::{ErrorInternalServerError, ErrorUnauthorized},
+    web::{Data, ServiceConfig, get},
+};
+use tokio::sync::Mutex;
+use tracing::error;
+
+use crate::{
+    common::Clients, config::Config, endpoints::insecure::bearer_auth_ok, metrics::Metrics,
+};
+
+/// The content type of the `OpenMetrics` text format
+const OPENMETRICS: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Serves the metrics. When an `api_key` is configured the scraper must present it as a
+/// bearer token, since the labels name every client and schedule.
+async fn metrics(
+    request: HttpRequest,
+    config: Data<Config>,
+    metrics: Data<Metrics>,
+    clients: Data<Mutex<Clients>>,
+) -> Result<HttpResponse> {
+    if !bearer_auth_ok(&request, config.api_key().as_deref()) {
+        return Err(ErrorUnauthorized("unauthorized"));
+    }
+    let text = metrics.encode(&*clients.lock().await).map_err(|e| {
+        error!("unable to encode metrics: {e}");
+        ErrorInternalServerError("internal server error")
+    })?;
+    Ok(HttpResponse::Ok().content_type(OPENMETRICS).body(text))
+}
+
+pub(crate) fn metrics_config(cfg: &mut ServiceConfig) {
+    _ = cfg.route("/metrics", get().to(metrics));
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        http::{StatusCode, header::CONTENT_TYPE},
+        test::{TestRequest, call_service, init_service, read_body},
+        web::Data,
+    };
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+
+    use super::{OPENMETRICS, metrics_config};
+    use crate::{
+        common::Clients,
+        config::Config,
+        metrics::{Metrics, Record},
+    };
+
+    #[actix_web::test]
+    async fn serves_the_metrics() {
+        let mut config = Config::default();
+        let _ = config.set_api_key(Some("shared".to_string()));
+        let metrics = Metrics::default();
+        metrics.inserted(Record::Output, 4);
+        let mut clients = Clients::builder().build();
+        let _old = clients.add_client(Uuid::nil(), "host1", "127.0.0.1");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(config))
+                .app_data(Data::new(metrics))
+                .app_data(Data::new(Mutex::new(clients)))
+                .configure(metrics_config),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::get()
+            .uri("/metrics")
+            .insert_header(("Authorization", "Bearer shared"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok()),
+            Some(OPENMETRICS)
+        );
+        let body = String::from_utf8(read_body(res).await.to_vec()).unwrap();
+        assert!(body.contains(r#"bartos_records_inserted_total{record="output"} 4"#));
+        assert!(body.contains(r#"bartos_clients_connected{client="host1",version="unknown"} 1"#));
+    }
+}
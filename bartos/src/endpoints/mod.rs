@@ -8,4 +8,8 @@
 
 //! Endpoints
 
+pub(crate) mod api;
+pub(crate) mod dashboard;
+pub(crate) mod health;
 pub(crate) mod insecure;
+pub(crate) mod metrics;
@@ -6,6 +6,8 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+use crate::config::Backend;
+
 /// Error types for bartos
 #[derive(Clone, Copy, Debug, thiserror::Error)]
 pub(crate) enum Error {
@@ -15,10 +17,26 @@ pub(crate) enum Error {
     TracingInit,
     #[error("Invalid IP address")]
     InvalidIp,
+    #[error("bind_name_to_cert requires mutual TLS (actix.tls.client_ca_cert)")]
+    CertBindingWithoutMtls,
+    #[error("backend = \"{0}\" requires a [{0}] configuration section")]
+    MissingBackendConfig(Backend),
+    #[error("database schema version {0} is newer than this bartos supports, upgrade bartos")]
+    SchemaNewer(i64),
+    #[error("migration {0} failed part way through, repair the database before starting bartos")]
+    MigrationDirty(i64),
+    #[error("applied migration {0} differs from the one embedded in this bartos")]
+    MigrationModified(i64),
+    #[error(
+        "a raw query must be a single SELECT, WITH, EXPLAIN, SHOW, DESCRIBE or VALUES statement"
+    )]
+    RawQueryNotReadOnly,
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::config::Backend;
+
     use super::Error;
 
     #[test]
@@ -41,4 +59,28 @@ mod tests {
     fn invalid_ip_display() {
         assert_eq!(Error::InvalidIp.to_string(), "Invalid IP address");
     }
+
+    #[test]
+    fn cert_binding_without_mtls_display() {
+        assert_eq!(
+            Error::CertBindingWithoutMtls.to_string(),
+            "bind_name_to_cert requires mutual TLS (actix.tls.client_ca_cert)"
+        );
+    }
+
+    #[test]
+    fn missing_backend_config_display() {
+        assert_eq!(
+            Error::MissingBackendConfig(Backend::Sqlite).to_string(),
+            "backend = \"sqlite\" requires a [sqlite] configuration section"
+        );
+    }
+
+    #[test]
+    fn schema_newer_display() {
+        assert_eq!(
+            Error::SchemaNewer(20_300_101_000_000).to_string(),
+            "database schema version 20300101000000 is newer than this bartos supports, upgrade bartos"
+        );
+    }
 }
@@ -6,29 +6,49 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::pending,
+};
 
 use actix_web::web::{Bytes, Data};
 use actix_ws::Session;
 use anyhow::Result;
 use bincode_next::{config::standard, decode_from_slice, encode_to_vec};
 use bon::Builder;
-use libbarto::{BartoCli, BartosToBartoCli, CliUpdateKind, ClientData, ListOutput, UuidWrapper};
+use libbarto::{
+    BartoCli, BartosToBartoCli, CliUpdateKind, ClientData, ClientEventFilter, ExportFilter,
+    ListOutput, QueryFilter, SearchFilter, TailFilter, UuidWrapper, generate_client_token,
+    hash_client_token, parse_verifying_key,
+};
+use time::OffsetDateTime;
 use tokio::sync::{Mutex, broadcast};
 use tracing::{info, trace};
 use vergen_pretty::{Pretty, PrettyExt, vergen_pretty_env};
 
 use crate::{
-    common::{Clients, WorkerSignal},
+    common::{ClientCredential, Clients, WorkerSignal},
     config::Config,
-    db::Queryable,
+    db::{Queryable, retention},
+    metrics::{Metrics, Socket},
+    tail::{Subscription, TailRecord},
 };
 
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Debug)]
 pub(crate) struct BinaryMessageHandler {
     config: Data<Config>,
     clients_mutex: Data<Mutex<Clients>>,
     worker_bcast: Data<broadcast::Sender<WorkerSignal>>,
+    tail_bcast: Data<broadcast::Sender<TailRecord>>,
+    /// The records this connection subscribed to, once it has
+    #[builder(skip)]
+    subscription: Option<Subscription>,
+    /// Whether this connection may run admin requests (enroll, revoke, cleanup).
+    admin: bool,
+    /// Whether this connection may run raw SQL.
+    raw_query: bool,
+    #[builder(default)]
+    metrics: Data<Metrics>,
 }
 
 #[cfg_attr(coverage_nightly, coverage(off))]
@@ -43,32 +63,120 @@ impl BinaryMessageHandler {
         session: &mut Session,
         queryable: T,
     ) -> Result<()> {
-        let (message, size) = decode_from_slice::<BartoCli, _>(&bytes, standard())?;
+        let (message, size) = decode_from_slice::<BartoCli, _>(&bytes, standard())
+            .inspect_err(|_| self.metrics.decode_failed(Socket::Cli))?;
         trace!("decoded binary message of size {size} bytes");
+        let reply = self.reply(message, queryable).await?;
+        let encoded = encode_to_vec(&reply, standard())?;
+        session.binary(encoded).await?;
+        Ok(())
+    }
+
+    /// Wait for the next record this connection subscribed to and send it. Never finishes
+    /// without a subscription.
+    pub(crate) async fn tail(&mut self, session: &mut Session) -> Result<()> {
+        let Some(subscription) = &mut self.subscription else {
+            return pending().await;
+        };
+        let Some(data) = subscription.next().await else {
+            self.subscription = None;
+            return Ok(());
+        };
+        let encoded = encode_to_vec(BartosToBartoCli::Tail(data), standard())?;
+        session.binary(encoded).await?;
+        Ok(())
+    }
 
+    /// Build the reply to one `barto-cli` request
+    pub(crate) async fn reply<T: Queryable>(
+        &mut self,
+        message: BartoCli,
+        queryable: T,
+    ) -> Result<BartosToBartoCli> {
         match message {
-            BartoCli::Info { json } => self.handle_info(json, session).await,
-            BartoCli::Updates { name, kind } => {
-                self.handle_updates(name, kind, session, queryable).await
-            }
-            BartoCli::Cleanup => self.handle_cleanup(session, queryable).await,
-            BartoCli::Clients => self.handle_clients(session).await,
-            BartoCli::Query { query } => self.handle_query(query, session, queryable).await,
+            BartoCli::Info { json } => Self::handle_info(json),
+            BartoCli::Updates { name, kind } => self.handle_updates(name, kind, queryable).await,
+            BartoCli::Cleanup => self.handle_cleanup(queryable).await,
+            BartoCli::Clients => Ok(self.handle_clients().await),
+            BartoCli::Query { filter } => self.handle_query(&filter, queryable).await,
             BartoCli::List { name, cmd_name } => {
-                self.handle_list(&name, &cmd_name, session, queryable).await
+                self.handle_list(&name, &cmd_name, queryable).await
+            }
+            BartoCli::ListCommands { name } => self.handle_list_command(&name, queryable).await,
+            BartoCli::Failed => self.handle_failed(queryable).await,
+            BartoCli::Cmd { cmd_name } => self.handle_command_all(&cmd_name, queryable).await,
+            BartoCli::ClientVersions => Ok(self.handle_client_versions().await),
+            BartoCli::Enroll { name, public_key } => {
+                self.handle_enroll(&name, public_key, queryable).await
             }
-            BartoCli::ListCommands { name } => {
-                self.handle_list_command(&name, session, queryable).await
+            BartoCli::Revoke { name } => self.handle_revoke(&name, queryable).await,
+            BartoCli::RawQuery { query } => self.handle_raw_query(&query, queryable).await,
+            BartoCli::Search { filter } => self.handle_search(&filter, queryable).await,
+            BartoCli::Export { filter } => self.handle_export(&filter, queryable).await,
+            BartoCli::ClientHistory { filter } => {
+                self.handle_client_history(&filter, queryable).await
             }
-            BartoCli::Failed => self.handle_failed(session, queryable).await,
-            BartoCli::Cmd { cmd_name } => {
-                self.handle_command_all(&cmd_name, session, queryable).await
+            BartoCli::Subscribe { filter } => Ok(self.handle_subscribe(filter)),
+        }
+    }
+
+    fn denied(reason: &str) -> BartosToBartoCli {
+        info!("request denied: {reason}");
+        BartosToBartoCli::Denied(reason.to_string())
+    }
+
+    async fn handle_enroll<T: Queryable>(
+        &mut self,
+        name: &str,
+        public_key: Option<String>,
+        queryable: T,
+    ) -> Result<BartosToBartoCli> {
+        info!("received enroll message for '{name}'");
+        if !self.admin {
+            return Ok(Self::denied("enroll requires admin permission"));
+        }
+        let (credential, token) = if let Some(public_key) = public_key {
+            if parse_verifying_key(&public_key).is_err() {
+                return Ok(Self::denied("invalid Ed25519 public key"));
             }
-            BartoCli::ClientVersions => self.handle_client_versions(session).await,
+            (ClientCredential::PublicKey(public_key), None)
+        } else {
+            let token = generate_client_token();
+            (
+                ClientCredential::Token(hash_client_token(&token)),
+                Some(token),
+            )
+        };
+        if !queryable.enroll_client(name, &credential).await? {
+            return Ok(Self::denied(&format!(
+                "'{name}' is already enrolled, revoke it first"
+            )));
+        }
+        info!("enrolled client '{name}'");
+        Ok(BartosToBartoCli::Enroll((name.to_string(), token)))
+    }
+
+    async fn handle_revoke<T: Queryable>(
+        &mut self,
+        name: &str,
+        queryable: T,
+    ) -> Result<BartosToBartoCli> {
+        info!("received revoke message for '{name}'");
+        if !self.admin {
+            return Ok(Self::denied("revoke requires admin permission"));
+        }
+        let revoked = queryable.revoke_client(name).await?;
+        if revoked {
+            info!("revoked client '{name}'");
+            // Drop any live connection still using the revoked credential.
+            let _ = self
+                .worker_bcast
+                .send(WorkerSignal::Disconnect(name.to_string()));
         }
+        Ok(BartosToBartoCli::Revoke((name.to_string(), revoked)))
     }
 
-    async fn handle_info(&mut self, json: bool, session: &mut Session) -> Result<()> {
+    fn handle_info(json: bool) -> Result<BartosToBartoCli> {
         info!("received info message");
         let pretty = Pretty::builder().env(vergen_pretty_env!());
 
@@ -79,48 +187,29 @@ impl BinaryMessageHandler {
             let pretty_ext = PrettyExt::from(pretty.build());
             BartosToBartoCli::Info(pretty_ext)
         };
-        let encoded = encode_to_vec(&btbc, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        Ok(btbc)
     }
 
     async fn handle_updates<T: Queryable>(
         &mut self,
         name: String,
         kind: CliUpdateKind,
-        session: &mut Session,
         queryable: T,
-    ) -> Result<()> {
-        let update_kind = queryable.update_data(self.config(), kind, &name).await?;
-        let msg = BartosToBartoCli::Updates(update_kind);
-        let encoded = encode_to_vec(&msg, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+    ) -> Result<BartosToBartoCli> {
+        let update_kind = queryable.update_data(kind, &name).await?;
+        Ok(BartosToBartoCli::Updates(update_kind))
     }
 
-    async fn handle_cleanup<T: Queryable>(
-        &mut self,
-        session: &mut Session,
-        queryable: T,
-    ) -> Result<()> {
+    async fn handle_cleanup<T: Queryable>(&mut self, queryable: T) -> Result<BartosToBartoCli> {
         info!("received cleanup message");
-        let counts = queryable.delete_data(self.config()).await?;
-        info!("deleted {} output rows", counts.0);
-        info!("deleted {} exit status rows", counts.1);
-        // Broadcast a cleanup signal to every connected bartoc worker. `send` returns the
-        // number of subscribed worker tasks (i.e. connected clients); treat no receivers as 0.
-        let clients_signaled = self
-            .worker_bcast
-            .send(WorkerSignal::Cleanup)
-            .unwrap_or_default();
-        info!("signaled {clients_signaled} connected clients to clean up");
-        let cleanup = BartosToBartoCli::Cleanup((counts.0, counts.1, clients_signaled));
-        let encoded = encode_to_vec(&cleanup, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        if !self.admin {
+            return Ok(Self::denied("cleanup requires admin permission"));
+        }
+        let counts = cleanup(&queryable, self.config(), &self.worker_bcast).await?;
+        Ok(BartosToBartoCli::Cleanup(counts))
     }
 
-    async fn handle_clients(&mut self, session: &mut Session) -> Result<()> {
+    async fn handle_clients(&mut self) -> BartosToBartoCli {
         info!("received clients message");
         let clients = self.clients_mutex.lock().await;
         let mapped_clients = clients
@@ -128,13 +217,27 @@ impl BinaryMessageHandler {
             .iter()
             .map(|c| (UuidWrapper(*c.0), c.1.clone()))
             .collect::<HashMap<UuidWrapper, ClientData>>();
-        let clients = BartosToBartoCli::Clients(mapped_clients);
-        let encoded = encode_to_vec(&clients, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        BartosToBartoCli::Clients(mapped_clients)
+    }
+
+    async fn handle_client_history<T: Queryable>(
+        &mut self,
+        filter: &ClientEventFilter,
+        queryable: T,
+    ) -> Result<BartosToBartoCli> {
+        info!("received client history message");
+        let events = queryable.client_events(filter).await?;
+        info!("client history returned {} events", events.len());
+        Ok(BartosToBartoCli::ClientHistory(events))
     }
 
-    async fn handle_client_versions(&mut self, session: &mut Session) -> Result<()> {
+    fn handle_subscribe(&mut self, filter: TailFilter) -> BartosToBartoCli {
+        info!("received subscribe message");
+        self.subscription = Some(Subscription::new(filter, self.tail_bcast.subscribe()));
+        BartosToBartoCli::Subscribed
+    }
+
+    async fn handle_client_versions(&mut self) -> BartosToBartoCli {
         info!("received client versions message");
         let clients = self.clients_mutex.lock().await;
         let versions = clients
@@ -148,83 +251,494 @@ impl BinaryMessageHandler {
                 (cd.name().clone(), version)
             })
             .collect::<BTreeMap<String, String>>();
-        let msg = BartosToBartoCli::ClientVersions(versions);
-        let encoded = encode_to_vec(&msg, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        BartosToBartoCli::ClientVersions(versions)
     }
 
     async fn handle_list_command<T: Queryable>(
         &mut self,
         name: &str,
-        session: &mut Session,
         queryable: T,
-    ) -> Result<()> {
+    ) -> Result<BartosToBartoCli> {
         info!("received list commands for '{name}'");
-        let cmds: Vec<String> = queryable.cmd_data(self.config(), name).await?;
-        let msg = BartosToBartoCli::ListCommands(cmds);
-        let encoded = encode_to_vec(&msg, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        let cmds: Vec<String> = queryable.cmd_data(name).await?;
+        Ok(BartosToBartoCli::ListCommands(cmds))
     }
 
     async fn handle_list<T: Queryable>(
         &mut self,
         name: &str,
         cmd_name: &str,
-        session: &mut Session,
         queryable: T,
-    ) -> Result<()> {
+    ) -> Result<BartosToBartoCli> {
         info!("received list message for '{name}' (cmd: {cmd_name})");
-        let list_output = queryable
-            .cmd_name_data(self.config(), name, cmd_name)
-            .await?;
-        let msg = BartosToBartoCli::List(list_output);
-        let encoded = encode_to_vec(&msg, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        let list_output = queryable.cmd_name_data(name, cmd_name).await?;
+        Ok(BartosToBartoCli::List(list_output))
     }
 
-    async fn handle_failed<T: Queryable>(
-        &mut self,
-        session: &mut Session,
-        queryable: T,
-    ) -> Result<()> {
+    async fn handle_failed<T: Queryable>(&mut self, queryable: T) -> Result<BartosToBartoCli> {
         info!("received failed message");
-        let failed_output = queryable.failed_cmd_data(self.config()).await?;
-        let msg = BartosToBartoCli::Failed(failed_output);
-        let encoded = encode_to_vec(&msg, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        let failed_output = queryable.failed_cmd_data().await?;
+        Ok(BartosToBartoCli::Failed(failed_output))
     }
 
     async fn handle_query<T: Queryable>(
         &mut self,
-        query: String,
-        session: &mut Session,
+        filter: &QueryFilter,
         queryable: T,
-    ) -> Result<()> {
+    ) -> Result<BartosToBartoCli> {
         info!("received query message");
-        let map = queryable.query(&query).await?;
-        info!("query returned {} rows", map.len());
-        let query_result = BartosToBartoCli::Query(map);
-        let encoded = encode_to_vec(&query_result, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        let rows = queryable.query(filter).await?;
+        info!("query returned {} rows", rows.len());
+        Ok(BartosToBartoCli::Query(rows))
+    }
+
+    async fn handle_raw_query<T: Queryable>(
+        &mut self,
+        query: &str,
+        queryable: T,
+    ) -> Result<BartosToBartoCli> {
+        info!("received raw query message");
+        if !self.raw_query {
+            return Ok(Self::denied("raw queries require raw query permission"));
+        }
+        let map = queryable.raw_query(query).await?;
+        info!("raw query returned {} rows", map.len());
+        Ok(BartosToBartoCli::RawQuery(map))
+    }
+
+    async fn handle_search<T: Queryable>(
+        &mut self,
+        filter: &SearchFilter,
+        queryable: T,
+    ) -> Result<BartosToBartoCli> {
+        info!("received search message");
+        let hits = queryable.search(filter).await?;
+        info!("search returned {} matches", hits.len());
+        Ok(BartosToBartoCli::Search(hits))
+    }
+
+    async fn handle_export<T: Queryable>(
+        &mut self,
+        filter: &ExportFilter,
+        queryable: T,
+    ) -> Result<BartosToBartoCli> {
+        info!("received export message at offset {}", filter.offset());
+        let rows = queryable.export(filter).await?;
+        info!("export returned {} output lines", rows.len());
+        Ok(BartosToBartoCli::Export(rows))
     }
 
     async fn handle_command_all<T: Queryable>(
         &mut self,
         cmd_name: &str,
-        session: &mut Session,
         queryable: T,
-    ) -> Result<()> {
+    ) -> Result<BartosToBartoCli> {
         info!("received list commands for '{cmd_name}'");
-        let cmds: BTreeMap<String, Vec<ListOutput>> =
-            queryable.cmd_data_by_name(self.config(), cmd_name).await?;
-        let msg = BartosToBartoCli::Cmd(cmds);
-        let encoded = encode_to_vec(&msg, standard())?;
-        session.binary(encoded).await?;
-        Ok(())
+        let cmds: BTreeMap<String, Vec<ListOutput>> = queryable.cmd_data_by_name(cmd_name).await?;
+        Ok(BartosToBartoCli::Cmd(cmds))
+    }
+}
+
+/// Enforces the retention policy and signals every connected bartoc worker to clean up.
+///
+/// Returns the number of output lines deleted, run rows deleted and clients signaled.
+pub(crate) async fn cleanup<T: Queryable>(
+    queryable: &T,
+    config: &Config,
+    worker_bcast: &broadcast::Sender<WorkerSignal>,
+) -> Result<(u64, u64, usize)> {
+    let counts = retention::enforce(queryable, config, OffsetDateTime::now_utc()).await?;
+    info!("deleted {} output lines", counts.0);
+    info!("deleted {} run rows", counts.1);
+    // Broadcast a cleanup signal to every connected bartoc worker. `send` returns the
+    // number of subscribed worker tasks (i.e. connected clients); treat no receivers as 0.
+    let clients_signaled = worker_bcast.send(WorkerSignal::Cleanup).unwrap_or_default();
+    info!("signaled {clients_signaled} connected clients to clean up");
+    Ok((counts.0, counts.1, clients_signaled))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::web::Data;
+    use libbarto::{
+        BartoCli, BartosToBartoCli, OffsetDataTimeWrapper, Output, OutputKind, QueryFilter,
+        RunStart, RunState, SearchFilter, Status, TailFilter, UuidWrapper, hash_client_token,
+    };
+    use time::{Duration, OffsetDateTime};
+    use tokio::sync::{Mutex, broadcast};
+    use uuid::Uuid;
+
+    use crate::{
+        common::{ClientCredential, Clients, WorkerSignal},
+        config::Config,
+        db::{Queryable, memory::MemoryHandler},
+        tail::publish,
+    };
+
+    use super::BinaryMessageHandler;
+
+    fn cli_handler(admin: bool) -> (BinaryMessageHandler, broadcast::Receiver<WorkerSignal>) {
+        let (tx, rx) = broadcast::channel(8);
+        let mut clients = Clients::builder().build();
+        let _old = clients.add_client(Uuid::nil(), "host1", "127.0.0.1");
+        let handler = BinaryMessageHandler::builder()
+            .config(Data::new(Config::default()))
+            .clients_mutex(Data::new(Mutex::new(clients)))
+            .worker_bcast(Data::new(tx))
+            .tail_bcast(Data::new(broadcast::channel(8).0))
+            .admin(admin)
+            .raw_query(false)
+            .build();
+        (handler, rx)
+    }
+
+    /// Record a finished run of `schedule` on `host` that ended `age` ago
+    async fn record(store: &MemoryHandler, host: &str, schedule: &str, age: Duration, code: i32) {
+        let cmd_uuid = UuidWrapper(Uuid::new_v4());
+        let at = OffsetDataTimeWrapper(OffsetDateTime::now_utc() - age);
+        let run_start = RunStart::builder()
+            .cmd_uuid(cmd_uuid)
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(host.to_string())
+            .schedule_name(schedule.to_string())
+            .cmd(format!("run {schedule}"))
+            .timestamp(at)
+            .build();
+        let _ = store.insert_run_start(&run_start).await.unwrap();
+        let output = Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(host.to_string())
+            .timestamp(at)
+            .cmd_uuid(cmd_uuid)
+            .cmd_name(schedule.to_string())
+            .kind(OutputKind::Stdout)
+            .data(format!("{schedule} output"))
+            .build();
+        let _ = store.insert_outputs(&[output]).await.unwrap();
+        let status = Status::builder()
+            .cmd_uuid(cmd_uuid)
+            .timestamp(at)
+            .exit_code(Some(code))
+            .success(code == 0)
+            .build();
+        let _ = store.insert_status(&status).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn lists_runs() {
+        let store = MemoryHandler::default();
+        record(&store, "host1", "backup", Duration::hours(1), 0).await;
+        record(&store, "host1", "update", Duration::hours(2), 2).await;
+        let (mut handler, _rx) = cli_handler(false);
+
+        let list_commands = BartoCli::ListCommands {
+            name: "host1".to_string(),
+        };
+        assert_eq!(
+            handler.reply(list_commands, store.clone()).await.unwrap(),
+            BartosToBartoCli::ListCommands(vec!["backup".to_string(), "update".to_string()])
+        );
+
+        let list = BartoCli::List {
+            name: "host1".to_string(),
+            cmd_name: "backup".to_string(),
+        };
+        let BartosToBartoCli::List(list) = handler.reply(list, store.clone()).await.unwrap() else {
+            panic!("expected a list reply");
+        };
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].data().as_deref(), Some("backup output"));
+        assert_eq!((list[0].exit_code(), list[0].success()), (Some(0), 1));
+
+        let BartosToBartoCli::Failed(failed) = handler
+            .reply(BartoCli::Failed, store.clone())
+            .await
+            .unwrap()
+        else {
+            panic!("expected a failed reply");
+        };
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].cmd_name().as_deref(), Some("update"));
+        assert_eq!(failed[0].exit_code(), Some(2));
+
+        let cmd = BartoCli::Cmd {
+            cmd_name: "update".to_string(),
+        };
+        let BartosToBartoCli::Cmd(by_host) = handler.reply(cmd, store).await.unwrap() else {
+            panic!("expected a cmd reply");
+        };
+        assert_eq!(by_host.get("host1").map(Vec::len), Some(1));
+    }
+
+    #[tokio::test]
+    async fn queries_runs_by_filter() {
+        let store = MemoryHandler::default();
+        record(&store, "host1", "backup", Duration::hours(1), 0).await;
+        record(&store, "host1", "update", Duration::hours(2), 2).await;
+        record(&store, "host2", "update", Duration::days(3), 0).await;
+        let (mut handler, _rx) = cli_handler(false);
+
+        let query = |filter: QueryFilter| BartoCli::Query { filter };
+        let BartosToBartoCli::Query(rows) = handler
+            .reply(query(QueryFilter::default()), store.clone())
+            .await
+            .unwrap()
+        else {
+            panic!("expected a query reply");
+        };
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].bartoc_name().as_deref(), Some("host2"));
+
+        let failed = QueryFilter::builder()
+            .client("host1".to_string())
+            .state(RunState::Failed)
+            .build();
+        let BartosToBartoCli::Query(rows) =
+            handler.reply(query(failed), store.clone()).await.unwrap()
+        else {
+            panic!("expected a query reply");
+        };
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].schedule_name().as_deref(), Some("update"));
+        assert_eq!(rows[0].exit_code(), Some(2));
+
+        let recent = QueryFilter::builder()
+            .since(OffsetDataTimeWrapper(
+                OffsetDateTime::now_utc() - Duration::days(1),
+            ))
+            .text("BACKUP".to_string())
+            .build();
+        let BartosToBartoCli::Query(rows) =
+            handler.reply(query(recent), store.clone()).await.unwrap()
+        else {
+            panic!("expected a query reply");
+        };
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].data().as_deref(), Some("backup output"));
+
+        let page = QueryFilter::builder().limit(1).offset(1).build();
+        let BartosToBartoCli::Query(rows) = handler.reply(query(page), store).await.unwrap() else {
+            panic!("expected a query reply");
+        };
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].schedule_name().as_deref(), Some("update"));
+    }
+
+    #[tokio::test]
+    async fn searches_output_by_words() {
+        let store = MemoryHandler::default();
+        record(&store, "host1", "backup", Duration::hours(1), 0).await;
+        record(&store, "host1", "update", Duration::hours(2), 2).await;
+        record(&store, "host2", "update", Duration::days(3), 0).await;
+        let (mut handler, _rx) = cli_handler(false);
+
+        let search = |filter: SearchFilter| BartoCli::Search { filter };
+        let BartosToBartoCli::Search(hits) = handler
+            .reply(
+                search(SearchFilter::builder().text("OUTPUT").build()),
+                store.clone(),
+            )
+            .await
+            .unwrap()
+        else {
+            panic!("expected a search reply");
+        };
+        let hosts: Vec<_> = hits.iter().map(|hit| hit.bartoc_name().as_str()).collect();
+        assert_eq!(hosts, vec!["host1", "host1", "host2"]);
+
+        let updates = SearchFilter::builder()
+            .text("update output")
+            .client("host2".to_string())
+            .build();
+        let BartosToBartoCli::Search(hits) =
+            handler.reply(search(updates), store.clone()).await.unwrap()
+        else {
+            panic!("expected a search reply");
+        };
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].schedule_name().as_deref(), Some("update"));
+
+        let reversed = SearchFilter::builder().text("output update").build();
+        let BartosToBartoCli::Search(hits) = handler.reply(search(reversed), store).await.unwrap()
+        else {
+            panic!("expected a search reply");
+        };
+        assert!(hits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn raw_query_needs_permission() {
+        let raw = BartoCli::RawQuery {
+            query: "SELECT 1".to_string(),
+        };
+        let (mut handler, _rx) = cli_handler(true);
+        assert_eq!(
+            handler
+                .reply(raw.clone(), MemoryHandler::default())
+                .await
+                .unwrap(),
+            BartosToBartoCli::Denied("raw queries require raw query permission".to_string())
+        );
+
+        // With permission the request reaches the store, which cannot run SQL.
+        handler.raw_query = true;
+        assert!(handler.reply(raw, MemoryHandler::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cleanup_applies_retention_and_signals_workers() {
+        let store = MemoryHandler::default();
+        record(&store, "host1", "backup", Duration::days(40), 0).await;
+        record(&store, "host1", "backup", Duration::days(1), 0).await;
+        let (mut handler, _rx) = cli_handler(false);
+        assert_eq!(
+            handler
+                .reply(BartoCli::Cleanup, store.clone())
+                .await
+                .unwrap(),
+            BartosToBartoCli::Denied("cleanup requires admin permission".to_string())
+        );
+
+        let (mut handler, mut rx) = cli_handler(true);
+
+        assert_eq!(
+            handler
+                .reply(BartoCli::Cleanup, store.clone())
+                .await
+                .unwrap(),
+            BartosToBartoCli::Cleanup((1, 1, 1))
+        );
+        assert_eq!(rx.recv().await.unwrap(), WorkerSignal::Cleanup);
+        assert_eq!(store.run_groups().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn enroll_and_revoke_need_admin() {
+        let store = MemoryHandler::default();
+        let enroll = BartoCli::Enroll {
+            name: "host1".to_string(),
+            public_key: None,
+        };
+        let (mut handler, _rx) = cli_handler(false);
+        assert_eq!(
+            handler.reply(enroll.clone(), store.clone()).await.unwrap(),
+            BartosToBartoCli::Denied("enroll requires admin permission".to_string())
+        );
+        assert!(store.client_credential("host1").await.unwrap().is_none());
+
+        let (mut handler, mut rx) = cli_handler(true);
+        let BartosToBartoCli::Enroll((name, Some(token))) =
+            handler.reply(enroll.clone(), store.clone()).await.unwrap()
+        else {
+            panic!("expected an enroll reply with a token");
+        };
+        assert_eq!(name, "host1");
+        assert_eq!(
+            store.client_credential("host1").await.unwrap(),
+            Some(ClientCredential::Token(hash_client_token(&token)))
+        );
+
+        // An enrolled name keeps its credential until it is revoked.
+        assert_eq!(
+            handler.reply(enroll.clone(), store.clone()).await.unwrap(),
+            BartosToBartoCli::Denied("'host1' is already enrolled, revoke it first".to_string())
+        );
+        assert_eq!(
+            store.client_credential("host1").await.unwrap(),
+            Some(ClientCredential::Token(hash_client_token(&token)))
+        );
+
+        let revoke = BartoCli::Revoke {
+            name: "host1".to_string(),
+        };
+        assert_eq!(
+            handler.reply(revoke, store.clone()).await.unwrap(),
+            BartosToBartoCli::Revoke(("host1".to_string(), true))
+        );
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            WorkerSignal::Disconnect("host1".to_string())
+        );
+        assert_eq!(
+            store.client_credential("host1").await.unwrap(),
+            Some(ClientCredential::Revoked)
+        );
+        assert!(matches!(
+            handler.reply(enroll, store.clone()).await.unwrap(),
+            BartosToBartoCli::Enroll((_, Some(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn enroll_rejects_a_bad_public_key() {
+        let (mut handler, _rx) = cli_handler(true);
+        let enroll = BartoCli::Enroll {
+            name: "host1".to_string(),
+            public_key: Some("not a key".to_string()),
+        };
+        assert_eq!(
+            handler
+                .reply(enroll, MemoryHandler::default())
+                .await
+                .unwrap(),
+            BartosToBartoCli::Denied("invalid Ed25519 public key".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_connected_clients() {
+        let (mut handler, _rx) = cli_handler(false);
+        let BartosToBartoCli::Clients(clients) = handler
+            .reply(BartoCli::Clients, MemoryHandler::default())
+            .await
+            .unwrap()
+        else {
+            panic!("expected a clients reply");
+        };
+        assert_eq!(clients.len(), 1);
+
+        let BartosToBartoCli::ClientVersions(versions) = handler
+            .reply(BartoCli::ClientVersions, MemoryHandler::default())
+            .await
+            .unwrap()
+        else {
+            panic!("expected a client versions reply");
+        };
+        assert_eq!(versions.get("host1").map(String::as_str), Some("unknown"));
+    }
+
+    #[tokio::test]
+    async fn subscribe_streams_matching_records() {
+        let (mut handler, _rx) = cli_handler(false);
+        let subscribe = BartoCli::Subscribe {
+            filter: TailFilter::builder().client("host1".to_string()).build(),
+        };
+        assert_eq!(
+            handler
+                .reply(subscribe, MemoryHandler::default())
+                .await
+                .unwrap(),
+            BartosToBartoCli::Subscribed
+        );
+        let output = Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name("host1".to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
+            .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+            .cmd_name("backup".to_string())
+            .kind(OutputKind::Stdout)
+            .data("copied".to_string())
+            .build();
+        publish(&handler.tail_bcast, "host2", || {
+            libbarto::Data::Output(output.clone())
+        });
+        publish(&handler.tail_bcast, "host1", || {
+            libbarto::Data::Output(output.clone())
+        });
+        let subscription = handler.subscription.as_mut().unwrap();
+        assert_eq!(
+            subscription.next().await,
+            Some(libbarto::Data::Output(output))
+        );
     }
 }
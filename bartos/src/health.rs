@@ -0,0 +1,194 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The health of `bartos`, served on `/healthz`, `/readyz` and `/status` and reported to
+//! systemd
+//!
+//! `bartos` is ready while its database answers a query and it is not shutting down. The
+//! config is loaded before the listeners start, and a reload that fails keeps the config
+//! in use, so readiness does not depend on it. The outcome of the last reload is reported
+//! on `/status` instead.
+
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use libbarto::OffsetDataTimeWrapper;
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::{db::Queryable, metrics::Reload};
+
+/// How long the database has to answer a readiness check
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A config reload and when it ended
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct LastReload {
+    outcome: Reload,
+    at: OffsetDateTime,
+}
+
+/// What `bartos` remembers for `/status`
+#[derive(Debug)]
+pub(crate) struct Health {
+    started: OffsetDateTime,
+    last_reload: Mutex<Option<LastReload>>,
+}
+
+impl Health {
+    pub(crate) fn new(started: OffsetDateTime) -> Self {
+        Self {
+            started,
+            last_reload: Mutex::new(None),
+        }
+    }
+
+    fn last_reload(&self) -> MutexGuard<'_, Option<LastReload>> {
+        self.last_reload
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Remembers the outcome of a config reload
+    pub(crate) fn reloaded(&self, outcome: Reload, at: OffsetDateTime) {
+        *self.last_reload() = Some(LastReload { outcome, at });
+    }
+
+    /// The status of `bartos` at `now`, with `clients_connected` bartoc clients
+    pub(crate) fn status(&self, clients_connected: usize, now: OffsetDateTime) -> StatusReport {
+        StatusReport {
+            version: env!("CARGO_PKG_VERSION"),
+            started_at: OffsetDataTimeWrapper(self.started).to_string(),
+            uptime_secs: (now - self.started).whole_seconds().max(0),
+            clients_connected,
+            last_reload: self.last_reload().map(|last| ReloadReport {
+                outcome: last.outcome.into(),
+                at: OffsetDataTimeWrapper(last.at).to_string(),
+            }),
+        }
+    }
+}
+
+/// Whether `bartos` can take traffic, the body of `/readyz`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub(crate) struct Readiness {
+    /// Whether every check below passed
+    ready: bool,
+    /// Whether the database answered a query
+    database_reachable: bool,
+    /// Whether `bartos` is shutting down
+    shutting_down: bool,
+}
+
+impl Readiness {
+    /// Checks the database behind `store` and the shutdown `token`
+    pub(crate) async fn check<T: Queryable>(store: &T, token: &CancellationToken) -> Self {
+        let database_reachable = match timeout(PING_TIMEOUT, store.ping()).await {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                warn!("readiness check: the database is unreachable: {e}");
+                false
+            }
+            Err(_) => {
+                warn!("readiness check: the database did not answer in time");
+                false
+            }
+        };
+        let shutting_down = token.is_cancelled();
+        Self {
+            ready: database_reachable && !shutting_down,
+            database_reachable,
+            shutting_down,
+        }
+    }
+
+    pub(crate) fn ready(self) -> bool {
+        self.ready
+    }
+
+    /// Why `bartos` is not ready, if it is not
+    pub(crate) fn reason(self) -> Option<&'static str> {
+        if self.shutting_down {
+            Some("shutting down")
+        } else if !self.database_reachable {
+            Some("database unreachable")
+        } else {
+            None
+        }
+    }
+}
+
+/// The body of `/status`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub(crate) struct StatusReport {
+    /// The version of `bartos`
+    version: &'static str,
+    /// When `bartos` started
+    started_at: String,
+    /// How long `bartos` has been running
+    uptime_secs: i64,
+    /// The number of connected bartoc clients
+    clients_connected: usize,
+    /// The last config reload, `None` until the config is reloaded
+    last_reload: Option<ReloadReport>,
+}
+
+/// A config reload on `/status`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+struct ReloadReport {
+    /// `applied`, `unchanged`, `invalid` or `failed`
+    outcome: &'static str,
+    /// When the reload ended
+    at: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Duration, OffsetDateTime};
+    use tokio_util::sync::CancellationToken;
+
+    use super::{Health, Readiness};
+    use crate::{db::memory::MemoryHandler, metrics::Reload};
+
+    #[test]
+    fn status_reports_uptime_and_the_last_reload() {
+        let started = OffsetDateTime::UNIX_EPOCH;
+        let health = Health::new(started);
+        let status = health.status(2, started + Duration::seconds(90));
+        assert_eq!(status.uptime_secs, 90);
+        assert_eq!(status.clients_connected, 2);
+        assert_eq!(status.version, env!("CARGO_PKG_VERSION"));
+        assert!(status.last_reload.is_none());
+
+        health.reloaded(Reload::Failed, started + Duration::seconds(60));
+        health.reloaded(Reload::Applied, started + Duration::seconds(80));
+        let status = health.status(0, started + Duration::seconds(90));
+        let last_reload = status.last_reload.unwrap();
+        assert_eq!(last_reload.outcome, "applied");
+        assert_eq!(last_reload.at, "1970-01-01T00:01:20Z");
+    }
+
+    #[tokio::test]
+    async fn ready_until_shutting_down() {
+        let store = MemoryHandler::default();
+        let token = CancellationToken::new();
+        let readiness = Readiness::check(&store, &token).await;
+        assert!(readiness.ready());
+        assert!(readiness.reason().is_none());
+
+        token.cancel();
+        let readiness = Readiness::check(&store, &token).await;
+        assert!(!readiness.ready());
+        assert_eq!(readiness.reason(), Some("shutting down"));
+    }
+}
@@ -231,13 +231,19 @@
 #![cfg_attr(all(docsrs), feature(doc_cfg))]
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
 
+mod alert;
 mod common;
 mod config;
 mod db;
 mod endpoints;
 mod error;
 mod handler;
+mod health;
+mod metrics;
+mod presence;
 mod runtime;
+mod tail;
+mod watchdog;
 
 use std::process::exit;
 
@@ -0,0 +1,539 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Prometheus metrics
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Write as _},
+    sync::{Mutex, MutexGuard},
+};
+
+use libbarto::{RunStart, Status};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, EncodeLabelValue, LabelValueEncoder, text::encode},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        gauge::Gauge,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::Registry,
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::common::Clients;
+
+/// The most runs tracked between their start and status records. Past this, the oldest
+/// start is dropped and that run's duration goes unrecorded.
+const MAX_IN_FLIGHT: usize = 10_000;
+
+/// The label for a schedule or version that is not known
+const UNKNOWN: &str = "unknown";
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct ClientLabels {
+    client: String,
+    version: String,
+}
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct RunLabels {
+    client: String,
+    schedule: String,
+}
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct OutcomeLabels {
+    client: String,
+    schedule: String,
+    outcome: Outcome,
+}
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct HeartbeatLabels {
+    client: String,
+}
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct RecordLabels {
+    record: Record,
+}
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct SocketLabels {
+    socket: Socket,
+}
+
+#[derive(Clone, Debug, EncodeLabelSet, Eq, Hash, PartialEq)]
+struct ReloadLabels {
+    outcome: Reload,
+}
+
+/// Implements [`EncodeLabelValue`] for an enum, writing each variant as its label value
+macro_rules! label_values {
+    ($name:ident { $($variant:ident => $value:literal),+ $(,)? }) => {
+        impl From<$name> for &'static str {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value),+
+                }
+            }
+        }
+
+        impl EncodeLabelValue for $name {
+            fn encode(&self, encoder: &mut LabelValueEncoder<'_>) -> Result<(), fmt::Error> {
+                encoder.write_str((*self).into())
+            }
+        }
+    };
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Outcome {
+    Succeeded,
+    Failed,
+    Missed,
+}
+
+label_values!(Outcome {
+    Succeeded => "succeeded",
+    Failed => "failed",
+    Missed => "missed",
+});
+
+/// The kind of record a bartoc sent to be stored
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Record {
+    /// Output lines
+    Output,
+    /// The exit status of a run
+    Status,
+    /// The start of a run
+    RunStart,
+}
+
+label_values!(Record {
+    Output => "output",
+    Status => "status",
+    RunStart => "run_start",
+});
+
+/// The WebSocket a message arrived on
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Socket {
+    /// `/v1/ws/worker`, from bartoc
+    Worker,
+    /// `/v1/ws/cli`, from barto-cli
+    Cli,
+}
+
+label_values!(Socket {
+    Worker => "worker",
+    Cli => "cli",
+});
+
+/// How a config reload ended
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Reload {
+    /// The schedules changed and were pushed to the connected clients
+    Applied,
+    /// The schedules did not change
+    Unchanged,
+    /// A schedule had an invalid `on_calendar`, so the old schedules were kept
+    Invalid,
+    /// The config could not be loaded, so the old schedules were kept
+    Failed,
+}
+
+label_values!(Reload {
+    Applied => "applied",
+    Unchanged => "unchanged",
+    Invalid => "invalid",
+    Failed => "failed",
+});
+
+/// A run whose start was seen but whose status has not arrived yet
+#[derive(Clone, Debug)]
+struct InFlight {
+    schedule: String,
+    started: OffsetDateTime,
+}
+
+fn duration_histogram() -> Histogram {
+    // 1s up to ~18h, by powers of 4
+    Histogram::new(exponential_buckets(1.0, 4.0, 9))
+}
+
+/// The metrics `bartos` exposes on `/metrics`
+#[derive(Debug)]
+pub(crate) struct Metrics {
+    registry: Registry,
+    clients_connected: Family<ClientLabels, Gauge>,
+    runs_started: Family<RunLabels, Counter>,
+    runs_finished: Family<OutcomeLabels, Counter>,
+    run_duration: Family<RunLabels, Histogram, fn() -> Histogram>,
+    records_inserted: Family<RecordLabels, Counter>,
+    insert_errors: Family<RecordLabels, Counter>,
+    decode_errors: Family<SocketLabels, Counter>,
+    heartbeat_timeouts: Family<HeartbeatLabels, Counter>,
+    config_reloads: Family<ReloadLabels, Counter>,
+    in_flight: Mutex<HashMap<Uuid, InFlight>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let mut registry = Registry::with_prefix("bartos");
+        let clients_connected = Family::default();
+        registry.register(
+            "clients_connected",
+            "Connected bartoc clients by name and version",
+            clients_connected.clone(),
+        );
+        let runs_started = Family::default();
+        registry.register(
+            "runs_started",
+            "Runs started by client and schedule",
+            runs_started.clone(),
+        );
+        let runs_finished = Family::default();
+        registry.register(
+            "runs_finished",
+            "Runs finished by client, schedule and outcome",
+            runs_finished.clone(),
+        );
+        let run_duration: Family<RunLabels, Histogram, fn() -> Histogram> =
+            Family::new_with_constructor(duration_histogram);
+        registry.register(
+            "run_duration_seconds",
+            "Run durations by client and schedule",
+            run_duration.clone(),
+        );
+        let records_inserted = Family::default();
+        registry.register(
+            "records_inserted",
+            "Rows stored from bartoc records by record kind",
+            records_inserted.clone(),
+        );
+        let insert_errors = Family::default();
+        registry.register(
+            "db_insert_errors",
+            "Bartoc records that could not be stored by record kind",
+            insert_errors.clone(),
+        );
+        let decode_errors = Family::default();
+        registry.register(
+            "websocket_decode_errors",
+            "WebSocket messages that could not be decoded by socket",
+            decode_errors.clone(),
+        );
+        let heartbeat_timeouts = Family::default();
+        registry.register(
+            "heartbeat_timeouts",
+            "Bartoc connections dropped for missing heartbeats by client",
+            heartbeat_timeouts.clone(),
+        );
+        let config_reloads = Family::default();
+        registry.register(
+            "config_reloads",
+            "Config reloads by outcome",
+            config_reloads.clone(),
+        );
+        Self {
+            registry,
+            clients_connected,
+            runs_started,
+            runs_finished,
+            run_duration,
+            records_inserted,
+            insert_errors,
+            decode_errors,
+            heartbeat_timeouts,
+            config_reloads,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Metrics {
+    fn in_flight(&self) -> MutexGuard<'_, HashMap<Uuid, InFlight>> {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Counts the start of a run and remembers it until its status arrives
+    pub(crate) fn run_started(&self, run_start: &RunStart) {
+        let _ = self
+            .runs_started
+            .get_or_create(&RunLabels {
+                client: run_start.bartoc_name().clone(),
+                schedule: run_start.schedule_name().clone(),
+            })
+            .inc();
+        let mut in_flight = self.in_flight();
+        if in_flight.len() >= MAX_IN_FLIGHT
+            && let Some(oldest) = in_flight
+                .iter()
+                .min_by_key(|(_, run)| run.started)
+                .map(|(id, _)| *id)
+        {
+            let _ = in_flight.remove(&oldest);
+        }
+        let _ = in_flight.insert(
+            run_start.cmd_uuid().0,
+            InFlight {
+                schedule: run_start.schedule_name().clone(),
+                started: run_start.timestamp().0,
+            },
+        );
+    }
+
+    /// Counts the end of a run of `client`, and its duration if its start was seen
+    pub(crate) fn run_finished(&self, client: &str, status: &Status) {
+        let started = self.in_flight().remove(&status.cmd_uuid().0);
+        let schedule = started
+            .as_ref()
+            .map_or_else(|| UNKNOWN.to_string(), |run| run.schedule.clone());
+        let outcome = if status.success() {
+            Outcome::Succeeded
+        } else {
+            Outcome::Failed
+        };
+        let _ = self
+            .runs_finished
+            .get_or_create(&OutcomeLabels {
+                client: client.to_string(),
+                schedule: schedule.clone(),
+                outcome,
+            })
+            .inc();
+        if let Some(run) = started {
+            let seconds = (status.timestamp().0 - run.started).as_seconds_f64();
+            self.run_duration
+                .get_or_create(&RunLabels {
+                    client: client.to_string(),
+                    schedule,
+                })
+                .observe(seconds.max(0.0));
+        }
+    }
+
+    /// Counts a run that was expected but never reported
+    pub(crate) fn run_missed(&self, client: &str, schedule: &str) {
+        let _ = self
+            .runs_finished
+            .get_or_create(&OutcomeLabels {
+                client: client.to_string(),
+                schedule: schedule.to_string(),
+                outcome: Outcome::Missed,
+            })
+            .inc();
+    }
+
+    /// Counts the rows stored from a bartoc record
+    pub(crate) fn inserted(&self, record: Record, rows: u64) {
+        let _ = self
+            .records_inserted
+            .get_or_create(&RecordLabels { record })
+            .inc_by(rows);
+    }
+
+    /// Counts a bartoc record that could not be stored
+    pub(crate) fn insert_failed(&self, record: Record) {
+        let _ = self
+            .insert_errors
+            .get_or_create(&RecordLabels { record })
+            .inc();
+    }
+
+    /// Counts a WebSocket message that could not be decoded
+    pub(crate) fn decode_failed(&self, socket: Socket) {
+        let _ = self
+            .decode_errors
+            .get_or_create(&SocketLabels { socket })
+            .inc();
+    }
+
+    /// Counts a bartoc connection dropped for missing heartbeats
+    pub(crate) fn heartbeat_timed_out(&self, client: &str) {
+        let _ = self
+            .heartbeat_timeouts
+            .get_or_create(&HeartbeatLabels {
+                client: client.to_string(),
+            })
+            .inc();
+    }
+
+    /// Counts the outcome of a config reload
+    pub(crate) fn config_reloaded(&self, outcome: Reload) {
+        let _ = self
+            .config_reloads
+            .get_or_create(&ReloadLabels { outcome })
+            .inc();
+    }
+
+    /// Renders the metrics in the `OpenMetrics` text format, with the connected clients
+    /// taken from `clients`
+    pub(crate) fn encode(&self, clients: &Clients) -> Result<String, fmt::Error> {
+        self.clients_connected.clear();
+        for data in clients.clients().values() {
+            let version = data
+                .bartoc_info()
+                .as_ref()
+                .map_or_else(|| UNKNOWN.to_string(), |info| info.version().clone());
+            let _ = self
+                .clients_connected
+                .get_or_create(&ClientLabels {
+                    client: data.name().clone(),
+                    version,
+                })
+                .inc();
+        }
+        let mut text = String::new();
+        encode(&mut text, &self.registry)?;
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libbarto::{BartocInfo, OffsetDataTimeWrapper, RunStart, Status, UuidWrapper};
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use super::{Metrics, Record, Reload, Socket};
+    use crate::common::Clients;
+
+    fn run_start(cmd_uuid: Uuid, at: OffsetDateTime) -> RunStart {
+        RunStart::builder()
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name("host1".to_string())
+            .schedule_name("backup".to_string())
+            .cmd("backup.sh".to_string())
+            .timestamp(OffsetDataTimeWrapper(at))
+            .build()
+    }
+
+    fn status(cmd_uuid: Uuid, at: OffsetDateTime, success: bool) -> Status {
+        Status::builder()
+            .cmd_uuid(UuidWrapper(cmd_uuid))
+            .timestamp(OffsetDataTimeWrapper(at))
+            .exit_code(Some(i32::from(!success)))
+            .success(success)
+            .build()
+    }
+
+    fn encode(metrics: &Metrics) -> String {
+        metrics.encode(&Clients::builder().build()).unwrap()
+    }
+
+    #[test]
+    fn counts_runs_and_their_durations() {
+        let metrics = Metrics::default();
+        let now = OffsetDateTime::now_utc();
+        let ok = Uuid::new_v4();
+        let bad = Uuid::new_v4();
+        metrics.run_started(&run_start(ok, now - Duration::seconds(30)));
+        metrics.run_started(&run_start(bad, now - Duration::seconds(2)));
+        metrics.run_finished("host1", &status(ok, now, true));
+        metrics.run_finished("host1", &status(bad, now, false));
+        // A status whose start was never seen is counted without a schedule or duration.
+        metrics.run_finished("host1", &status(Uuid::new_v4(), now, true));
+        metrics.run_missed("host1", "backup");
+
+        let text = encode(&metrics);
+        assert!(text.contains(r#"bartos_runs_started_total{client="host1",schedule="backup"} 2"#));
+        assert!(text.contains(
+            r#"bartos_runs_finished_total{client="host1",schedule="backup",outcome="succeeded"} 1"#
+        ));
+        assert!(text.contains(
+            r#"bartos_runs_finished_total{client="host1",schedule="backup",outcome="failed"} 1"#
+        ));
+        assert!(text.contains(
+            r#"bartos_runs_finished_total{client="host1",schedule="backup",outcome="missed"} 1"#
+        ));
+        assert!(text.contains(
+            r#"bartos_runs_finished_total{client="host1",schedule="unknown",outcome="succeeded"} 1"#
+        ));
+        assert!(
+            text.contains(
+                r#"bartos_run_duration_seconds_sum{client="host1",schedule="backup"} 32.0"#
+            )
+        );
+        assert!(
+            text.contains(
+                r#"bartos_run_duration_seconds_count{client="host1",schedule="backup"} 2"#
+            )
+        );
+        assert!(metrics.in_flight().is_empty());
+    }
+
+    #[test]
+    fn counts_records_errors_and_reloads() {
+        let metrics = Metrics::default();
+        metrics.inserted(Record::Output, 12);
+        metrics.inserted(Record::Output, 3);
+        metrics.insert_failed(Record::Status);
+        metrics.decode_failed(Socket::Worker);
+        metrics.heartbeat_timed_out("host1");
+        metrics.config_reloaded(Reload::Applied);
+        metrics.config_reloaded(Reload::Invalid);
+
+        let text = encode(&metrics);
+        assert!(text.contains(r#"bartos_records_inserted_total{record="output"} 15"#));
+        assert!(text.contains(r#"bartos_db_insert_errors_total{record="status"} 1"#));
+        assert!(text.contains(r#"bartos_websocket_decode_errors_total{socket="worker"} 1"#));
+        assert!(text.contains(r#"bartos_heartbeat_timeouts_total{client="host1"} 1"#));
+        assert!(text.contains(r#"bartos_config_reloads_total{outcome="applied"} 1"#));
+        assert!(text.contains(r#"bartos_config_reloads_total{outcome="invalid"} 1"#));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn reports_the_connected_clients() {
+        let metrics = Metrics::default();
+        let mut clients = Clients::builder().build();
+        let id = Uuid::new_v4();
+        let _old = clients.add_client(id, "host1", "10.0.0.1");
+        let _old = clients.add_client(Uuid::new_v4(), "host2", "10.0.0.2");
+        clients.add_client_data(
+            &id,
+            BartocInfo::builder()
+                .name("host1".to_string())
+                .version("1.5.12".to_string())
+                .build(),
+        );
+        let text = metrics.encode(&clients).unwrap();
+        assert!(text.contains(r#"bartos_clients_connected{client="host1",version="1.5.12"} 1"#));
+        assert!(text.contains(r#"bartos_clients_connected{client="host2",version="unknown"} 1"#));
+
+        // A client that disconnected is gone from the next scrape.
+        let _old = clients.remove_client(&id);
+        let text = metrics.encode(&clients).unwrap();
+        assert!(!text.contains(r#"client="host1""#));
+    }
+
+    #[test]
+    fn bounds_the_runs_in_flight() {
+        let metrics = Metrics::default();
+        let now = OffsetDateTime::now_utc();
+        let oldest = Uuid::new_v4();
+        metrics.run_started(&run_start(oldest, now - Duration::days(1)));
+        for _ in 1..super::MAX_IN_FLIGHT {
+            metrics.run_started(&run_start(Uuid::new_v4(), now));
+        }
+        assert_eq!(metrics.in_flight().len(), super::MAX_IN_FLIGHT);
+        metrics.run_started(&run_start(Uuid::new_v4(), now));
+        let in_flight = metrics.in_flight();
+        assert_eq!(in_flight.len(), super::MAX_IN_FLIGHT);
+        assert!(!in_flight.contains_key(&oldest));
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Known clients that stay offline
+//!
+//! A client is known once a connect or disconnect of it has been recorded. A known client
+//! that is not connected counts as offline from its last recorded event, or from when `bartos`
+//! started if that is later, since `bartos` cannot tell what happened while it was down. Once
+//! it has been offline for `offline_clients.threshold_secs` it is reported, once, and reported
+//! again when it reconnects. A client whose credential was revoked is never reported. Which
+//! clients were reported is kept in memory, so it starts over when `bartos` restarts.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use libbarto::{ClientEvent, ClientEventKind};
+use time::{Duration, OffsetDateTime};
+
+use crate::config::OfflineClients;
+
+/// The disconnect reason of a client whose credential was revoked
+const REVOKED: &str = "revoked";
+
+/// A known client that has been offline longer than the threshold
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Offline {
+    bartoc_name: String,
+    /// The address the client last connected from
+    ip: String,
+    /// Why the client last disconnected, if that was recorded
+    reason: Option<String>,
+    /// When the client counts as offline from
+    since: OffsetDateTime,
+}
+
+impl Offline {
+    pub(crate) fn bartoc_name(&self) -> &str {
+        &self.bartoc_name
+    }
+
+    pub(crate) fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    pub(crate) fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    pub(crate) fn since(&self) -> OffsetDateTime {
+        self.since
+    }
+}
+
+/// A change in whether a known client is offline
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Change {
+    /// The client has been offline longer than the threshold
+    Offline(Offline),
+    /// A client reported offline has reconnected
+    Back(Offline),
+}
+
+/// Tracks which known clients are offline
+#[derive(Debug)]
+pub(crate) struct Presence {
+    threshold: Duration,
+    started: OffsetDateTime,
+    reported: Mutex<HashMap<String, Offline>>,
+}
+
+impl Presence {
+    pub(crate) fn new(offline_clients: OfflineClients, started: OffsetDateTime) -> Self {
+        Self {
+            threshold: Duration::seconds(
+                i64::try_from(offline_clients.threshold_secs()).unwrap_or(i64::MAX),
+            ),
+            started,
+            reported: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn reported(&self) -> MutexGuard<'_, HashMap<String, Offline>> {
+        self.reported
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Compare the last recorded event of every known client with the clients that are
+    /// connected and return what changed since the last check
+    pub(crate) fn check(
+        &self,
+        last_events: &[ClientEvent],
+        connected: &HashSet<String>,
+        now: OffsetDateTime,
+    ) -> Vec<Change> {
+        let mut reported = self.reported();
+        let mut changes = vec![];
+        let back: Vec<String> = reported
+            .keys()
+            .filter(|name| connected.contains(*name))
+            .cloned()
+            .collect();
+        for name in back {
+            if let Some(offline) = reported.remove(&name) {
+                changes.push(Change::Back(offline));
+            }
+        }
+        for event in last_events {
+            let name = event.bartoc_name();
+            if connected.contains(name)
+                || reported.contains_key(name)
+                || event.reason().as_deref() == Some(REVOKED)
+            {
+                continue;
+            }
+            let since = event.timestamp().0.max(self.started);
+            if since + self.threshold > now {
+                continue;
+            }
+            let offline = Offline {
+                bartoc_name: name.clone(),
+                ip: event.ip().clone(),
+                reason: match event.kind() {
+                    ClientEventKind::Connect => None,
+                    ClientEventKind::Disconnect => event.reason().clone(),
+                },
+                since,
+            };
+            let _old = reported.insert(name.clone(), offline.clone());
+            changes.push(Change::Offline(offline));
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use libbarto::{ClientEvent, ClientEventKind, OffsetDataTimeWrapper, UuidWrapper};
+    use time::{OffsetDateTime, macros::datetime};
+    use uuid::Uuid;
+
+    use super::{Change, Presence};
+    use crate::config::OfflineClients;
+
+    const STARTED: OffsetDateTime = datetime!(2025-06-01 09:00:00 UTC);
+
+    fn presence() -> Presence {
+        Presence::new(
+            OfflineClients::builder().threshold_secs(600).build(),
+            STARTED,
+        )
+    }
+
+    fn event(name: &str, reason: Option<&str>, at: OffsetDateTime) -> ClientEvent {
+        let kind = if reason.is_some() {
+            ClientEventKind::Disconnect
+        } else {
+            ClientEventKind::Connect
+        };
+        ClientEvent::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+            .bartoc_name(name.to_string())
+            .ip("10.0.0.1".to_string())
+            .kind(kind)
+            .maybe_reason(reason.map(str::to_string))
+            .timestamp(OffsetDataTimeWrapper(at))
+            .build()
+    }
+
+    fn connected(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|name| (*name).to_string()).collect()
+    }
+
+    #[test]
+    fn reports_a_client_offline_past_the_threshold_once() {
+        let presence = presence();
+        let events = [event(
+            "host1",
+            Some("heartbeat_timeout"),
+            datetime!(2025-06-01 10:00:00 UTC),
+        )];
+        assert!(
+            presence
+                .check(&events, &connected(&[]), datetime!(2025-06-01 10:09:59 UTC))
+                .is_empty()
+        );
+
+        let changes = presence.check(&events, &connected(&[]), datetime!(2025-06-01 10:10:00 UTC));
+        let [Change::Offline(offline)] = changes.as_slice() else {
+            panic!("expected one offline client, got {changes:?}");
+        };
+        assert_eq!(offline.bartoc_name(), "host1");
+        assert_eq!(offline.ip(), "10.0.0.1");
+        assert_eq!(offline.reason(), Some("heartbeat_timeout"));
+        assert_eq!(offline.since(), datetime!(2025-06-01 10:00:00 UTC));
+        assert!(
+            presence
+                .check(&events, &connected(&[]), datetime!(2025-06-01 11:00:00 UTC))
+                .is_empty()
+        );
+
+        // Reconnecting is reported once too
+        let changes = presence.check(
+            &events,
+            &connected(&["host1"]),
+            datetime!(2025-06-01 11:00:00 UTC),
+        );
+        assert!(
+            matches!(changes.as_slice(), [Change::Back(offline)] if offline.bartoc_name() == "host1")
+        );
+        assert!(
+            presence
+                .check(
+                    &events,
+                    &connected(&["host1"]),
+                    datetime!(2025-06-01 11:01:00 UTC)
+                )
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn counts_from_startup_and_skips_connected_and_revoked_clients() {
+        let presence = presence();
+        let long_ago = datetime!(2025-05-01 00:00:00 UTC);
+        let events = [
+            // Still marked connected from before bartos restarted
+            event("stale", None, long_ago),
+            event("revoked", Some("revoked"), long_ago),
+            event("online", Some("closed"), long_ago),
+        ];
+        assert!(
+            presence
+                .check(&events, &connected(&["online"]), STARTED)
+                .is_empty()
+        );
+        let changes = presence.check(
+            &events,
+            &connected(&["online"]),
+            datetime!(2025-06-01 09:10:00 UTC),
+        );
+        let [Change::Offline(offline)] = changes.as_slice() else {
+            panic!("expected one offline client, got {changes:?}");
+        };
+        assert_eq!(offline.bartoc_name(), "stale");
+        assert_eq!(offline.since(), STARTED);
+        assert!(offline.reason().is_none());
+    }
+}
@@ -6,7 +6,9 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use clap::{ArgAction, Parser};
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser, Subcommand};
 use config::{ConfigError, Map, Source, Value, ValueKind};
 use getset::Getters;
 use libbarto::PathDefaults;
@@ -47,6 +49,46 @@ pub(crate) struct Cli {
         help = "Specify the absolute path to the tracing output file"
     )]
     tracing_absolute_path: Option<String>,
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Subcommand)]
+pub(crate) enum Commands {
+    /// Apply the embedded database migrations and exit without starting the server.
+    /// bartos also applies them at startup.
+    Migrate {
+        /// List the applied and pending migrations without applying anything
+        #[clap(long, conflicts_with = "dry_run")]
+        status: bool,
+        /// Show the migrations that would be applied without applying them
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Load a `barto-cli export` file into the database and exit without starting the server.
+    /// Runs already in the database are skipped.
+    Import {
+        /// The export file to load
+        file: PathBuf,
+        /// The format of the file (jsonl, csv or arrow), by default from its extension
+        #[clap(short, long)]
+        format: Option<String>,
+    },
+    /// Enroll a bartoc client and exit without starting the server, printing its token.
+    /// Use this to enroll the first of the `admin_clients`.
+    Enroll {
+        /// The name the client connects with
+        name: String,
+        /// Enroll an Ed25519 public key (base64) instead of issuing a token
+        #[clap(short, long)]
+        public_key: Option<String>,
+    },
+    /// Revoke the credential of a bartoc client and exit without starting the server.
+    /// Sessions already connected to a running bartos stay open until they reconnect.
+    Revoke {
+        /// The name of the client to revoke
+        name: String,
+    },
 }
 
 impl Source for Cli {
@@ -117,11 +159,13 @@ impl PathDefaults for Cli {
 
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+
     use clap::Parser;
     use config::Source;
     use libbarto::PathDefaults;
 
-    use super::Cli;
+    use super::{Cli, Commands};
 
     fn parse(args: &[&str]) -> Cli {
         Cli::parse_from(std::iter::once("bartos").chain(args.iter().copied()))
@@ -135,6 +179,77 @@ mod tests {
         assert!(!*cli.enable_std_output());
         assert!(cli.config_absolute_path().is_none());
         assert!(cli.tracing_absolute_path().is_none());
+        assert!(cli.command().is_none());
+    }
+
+    #[test]
+    fn migrate_subcommand() {
+        assert_eq!(
+            *parse(&["migrate"]).command(),
+            Some(Commands::Migrate {
+                status: false,
+                dry_run: false
+            })
+        );
+        assert_eq!(
+            *parse(&["-c", "/etc/bartos.toml", "migrate", "--status"]).command(),
+            Some(Commands::Migrate {
+                status: true,
+                dry_run: false
+            })
+        );
+        assert_eq!(
+            *parse(&["migrate", "--dry-run"]).command(),
+            Some(Commands::Migrate {
+                status: false,
+                dry_run: true
+            })
+        );
+        assert!(Cli::try_parse_from(["bartos", "migrate", "--status", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn import_subcommand() {
+        assert_eq!(
+            *parse(&["import", "/tmp/history.jsonl"]).command(),
+            Some(Commands::Import {
+                file: PathBuf::from("/tmp/history.jsonl"),
+                format: None
+            })
+        );
+        assert_eq!(
+            *parse(&["import", "-f", "csv", "history.txt"]).command(),
+            Some(Commands::Import {
+                file: PathBuf::from("history.txt"),
+                format: Some("csv".to_string())
+            })
+        );
+        assert!(Cli::try_parse_from(["bartos", "import"]).is_err());
+    }
+
+    #[test]
+    fn enroll_and_revoke_subcommands() {
+        assert_eq!(
+            *parse(&["enroll", "ops"]).command(),
+            Some(Commands::Enroll {
+                name: "ops".to_string(),
+                public_key: None
+            })
+        );
+        assert_eq!(
+            *parse(&["enroll", "-p", "a2V5", "ops"]).command(),
+            Some(Commands::Enroll {
+                name: "ops".to_string(),
+                public_key: Some("a2V5".to_string())
+            })
+        );
+        assert_eq!(
+            *parse(&["revoke", "ops"]).command(),
+            Some(Commands::Revoke {
+                name: "ops".to_string()
+            })
+        );
+        assert!(Cli::try_parse_from(["bartos", "revoke"]).is_err());
     }
 
     #[test]
@@ -9,32 +9,38 @@
 mod cli;
 
 use std::{
-    collections::BTreeMap,
+    any::Any,
+    collections::{BTreeMap, HashSet},
     env,
     ffi::OsString,
-    fs,
+    fs::{self, File},
     io::{Write, stdout},
     net::{IpAddr, SocketAddr},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::mpsc as std_mpsc,
     time::Duration,
 };
 
+use actix_tls::accept::rustls_0_23::TlsStream;
 use actix_web::{
     App, HttpServer,
-    dev::Server,
+    dev::{Extensions, Server},
     middleware::Compress,
+    rt::net::TcpStream,
     web::{Data, scope},
 };
 use anyhow::{Context, Result};
 use clap::Parser;
 use libbarto::{
-    Realtime, Schedules, header, init_tracing, key_fingerprint, load, load_tls_config,
-    parse_signing_key, resolve_config_path,
+    ExportFormat, ExportReader, Realtime, Schedules, cert_identities, generate_client_token,
+    hash_client_token, header, init_tracing, key_fingerprint, load, load_tls_config, otlp_layer,
+    parse_signing_key, parse_verifying_key, resolve_config_path,
 };
 use notify_debouncer_mini::{DebounceEventResult, new_debouncer, notify::RecursiveMode};
 use rustls::{ServerConfig, crypto::ring::default_provider};
-use sqlx::MySqlPool;
+#[cfg(unix)]
+use sd_notify::{NotifyState, notify, watchdog_enabled};
+use time::OffsetDateTime;
 #[cfg(not(unix))]
 use tokio::signal::ctrl_c;
 #[cfg(unix)]
@@ -43,27 +49,46 @@ use tokio::{
     select, spawn,
     sync::{Mutex, RwLock, broadcast, mpsc, oneshot},
     task::JoinHandle,
-    time::sleep,
+    time::{MissedTickBehavior, interval, sleep},
 };
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, trace, warn};
 
 use crate::{
-    common::{Clients, WorkerSignal},
+    alert::Alerter,
+    common::{
+        CertIdentity, ClientCredential, Clients, DashboardEvent, DashboardTickets, WorkerSignal,
+    },
     config::Config,
-    endpoints::insecure::insecure_config,
+    db::{Queryable, Store, import::import, retention},
+    endpoints::{
+        api::api_config, dashboard::dashboard_config, health::health_config,
+        insecure::insecure_config, metrics::metrics_config,
+    },
     error::Error,
+    health::{Health, Readiness},
+    metrics::{Metrics, Reload},
+    presence::{Change, Presence},
+    tail::TailRecord,
+    watchdog::Watchdog,
 };
 
-use self::cli::Cli;
+use self::cli::{Cli, Commands};
 
 struct WebAppData {
     token: Data<CancellationToken>,
     config: Data<Config>,
-    pool: Data<MySqlPool>,
+    store: Data<Store>,
     clients: Data<Mutex<Clients>>,
     live_schedules: Data<RwLock<BTreeMap<String, Schedules>>>,
     worker_bcast: Data<broadcast::Sender<WorkerSignal>>,
+    metrics: Data<Metrics>,
+    dashboard_bcast: Data<broadcast::Sender<DashboardEvent>>,
+    tail_bcast: Data<broadcast::Sender<TailRecord>>,
+    dashboard_tickets: Data<Mutex<DashboardTickets>>,
+    alerter: Data<Alerter>,
+    watchdog: Data<Watchdog>,
+    health: Data<Health>,
 }
 
 const HEADER_PREFIX: &str = r"██████╗  █████╗ ██████╗ ████████╗ ██████╗ ███████╗
@@ -85,12 +110,45 @@ where
         Cli::try_parse()?
     };
     let config = load::<Cli, Config, Cli>(&cli, &cli).with_context(|| Error::ConfigLoad)?;
-    init_tracing(&config, config.tracing().file(), &cli, None)
-        .with_context(|| Error::TracingInit)?;
+    let (otlp_layer, _otlp_guard) = config
+        .tracing()
+        .otlp()
+        .as_ref()
+        .map(|otlp| otlp_layer(otlp, env!("CARGO_PKG_NAME")))
+        .transpose()
+        .with_context(|| Error::TracingInit)?
+        .unzip();
+    init_tracing(
+        &config,
+        config.tracing().file(),
+        &cli,
+        otlp_layer.map(|layer| vec![layer]),
+    )
+    .with_context(|| Error::TracingInit)?;
     trace!("configuration loaded");
     trace!("tracing initialized");
     display_startup_info(&config)?;
 
+    match cli.command() {
+        Some(Commands::Migrate { status, dry_run }) => {
+            let store = Store::connect(&config).await?;
+            return run_migrate(&store, *status, *dry_run).await;
+        }
+        Some(Commands::Import { file, format }) => {
+            let store = Store::connect(&config).await?;
+            return run_import(&store, file, format.as_deref()).await;
+        }
+        Some(Commands::Enroll { name, public_key }) => {
+            let store = Store::connect(&config).await?;
+            return run_enroll(&store, name, public_key.clone()).await;
+        }
+        Some(Commands::Revoke { name }) => {
+            let store = Store::connect(&config).await?;
+            return run_revoke(&store, name).await;
+        }
+        None => {}
+    }
+
     let workers = usize::from(*config.actix().workers());
     let tls_opt = resolve_tls_config(&config)?;
     let bartos_port = *config.actix().port();
@@ -99,21 +157,48 @@ where
     let token = CancellationToken::new();
     let server_token = token.clone();
 
-    let url = config.mariadb().connection_string();
-    info!(
-        "connecting to database at: {}",
-        config.mariadb().disp_connection_string()
-    );
+    let store = Data::new(Store::connect(&config).await?);
+    let _applied = store.migrate().await?;
+    let _retention_handle = spawn_retention_task(&config, store.clone(), server_token.clone())?;
     let (worker_bcast_tx, _) = broadcast::channel::<WorkerSignal>(16);
+    let (dashboard_bcast_tx, _) = broadcast::channel::<DashboardEvent>(256);
+    let (tail_bcast_tx, _) = broadcast::channel::<TailRecord>(1024);
     let (reload_trigger_tx, reload_trigger_rx) = mpsc::channel::<()>(4);
     let live_schedules_data: Data<RwLock<BTreeMap<String, Schedules>>> =
         Data::new(RwLock::new(config.schedules().clone()));
+    let metrics = Data::new(Metrics::default());
+    let health = Data::new(Health::new(OffsetDateTime::now_utc()));
+    let alerter = Data::new(Alerter::new(config.alerts())?);
+    let watchdog = Data::new(Watchdog::new(config.missed_runs()));
+    let clients = Data::new(Mutex::new(Clients::builder().build()));
 
     let _reload_handle = spawn_reload_task(
         cli.clone(),
         live_schedules_data.clone(),
         reload_trigger_rx,
         worker_bcast_tx.clone(),
+        dashboard_bcast_tx.clone(),
+        metrics.clone(),
+        health.clone(),
+    );
+
+    let _watchdog_handle = spawn_watchdog_task(
+        &config,
+        watchdog.clone(),
+        live_schedules_data.clone(),
+        store.clone(),
+        alerter.clone(),
+        metrics.clone(),
+        dashboard_bcast_tx.clone(),
+        server_token.clone(),
+    );
+
+    let _presence_handle = spawn_presence_task(
+        &config,
+        store.clone(),
+        clients.clone(),
+        alerter.clone(),
+        server_token.clone(),
     );
 
     let config_path = resolve_config_path(&cli).with_context(|| Error::ConfigLoad)?;
@@ -126,16 +211,25 @@ where
     let web_app_data = WebAppData {
         token: Data::new(server_token.clone()),
         config: Data::new(config),
-        pool: Data::new(MySqlPool::connect(&url).await?),
-        clients: Data::new(Mutex::new(Clients::builder().build())),
+        store: store.clone(),
+        clients,
         live_schedules: live_schedules_data,
         worker_bcast: Data::new(worker_bcast_tx),
+        metrics,
+        dashboard_bcast: Data::new(dashboard_bcast_tx),
+        tail_bcast: Data::new(tail_bcast_tx),
+        dashboard_tickets: Data::new(Mutex::new(DashboardTickets::default())),
+        alerter,
+        watchdog,
+        health,
     };
     let server = build_http_server(web_app_data, workers, &bartos_host, bartos_port, tls_opt)?;
+    let _systemd_handle = spawn_systemd_notify(store.clone(), server_token.clone());
 
     select! {
         () = server_token.cancelled() => {
             trace!("cancellation token triggered, shutting down bartos");
+            notify_stopping();
             // sleep to allow existing connections to send close messages
             sleep(Duration::from_secs(1)).await;
         }
@@ -157,10 +251,16 @@ fn display_startup_info(config: &Config) -> Result<()> {
     info!("{} configured!", env!("CARGO_PKG_NAME"));
     if let Some(sk_b64) = config.signing_key() {
         match parse_signing_key(sk_b64) {
-            Ok(sk) => info!(
-                "Ed25519 signing key loaded (public fingerprint: {})",
-                key_fingerprint(&sk.verifying_key())
-            ),
+            Ok(sk) => match config.signing_key_id() {
+                Some(key_id) => info!(
+                    "Ed25519 signing key {key_id} loaded (public fingerprint: {})",
+                    key_fingerprint(&sk.verifying_key())
+                ),
+                None => info!(
+                    "Ed25519 signing key loaded (public fingerprint: {})",
+                    key_fingerprint(&sk.verifying_key())
+                ),
+            },
             Err(e) => warn!("Ed25519 signing key is set but invalid: {e}"),
         }
     } else {
@@ -173,7 +273,119 @@ fn display_startup_info(config: &Config) -> Result<()> {
     Ok(())
 }
 
+/// Handle `bartos migrate`, printing to stdout rather than the tracing output
+#[cfg_attr(coverage_nightly, coverage(off))]
+async fn run_migrate(store: &Store, status: bool, dry_run: bool) -> Result<()> {
+    let mut out = stdout();
+    let schema = store.migration_status().await?;
+    if status {
+        for migration in schema.applied() {
+            let note = if schema.modified().contains(&migration.version()) {
+                " (modified)"
+            } else {
+                ""
+            };
+            writeln!(out, "applied  {migration}{note}")?;
+        }
+        for migration in schema.pending() {
+            writeln!(out, "pending  {migration}")?;
+        }
+        for version in schema.unknown() {
+            writeln!(out, "unknown  {version} (newer than this bartos)")?;
+        }
+        if let Some(version) = schema.dirty() {
+            writeln!(out, "dirty    {version}")?;
+        }
+    } else if dry_run {
+        schema.check()?;
+        if schema.pending().is_empty() {
+            writeln!(out, "nothing to apply, the schema is up to date")?;
+        }
+        for migration in schema.pending() {
+            writeln!(out, "would apply  {migration}")?;
+        }
+    } else {
+        let applied = store.migrate().await?;
+        if applied.is_empty() {
+            writeln!(out, "nothing to apply, the schema is up to date")?;
+        }
+        for migration in applied {
+            writeln!(out, "applied  {migration}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Handle `bartos import`, migrating the database first as startup would and printing to
+/// stdout rather than the tracing output
+#[cfg_attr(coverage_nightly, coverage(off))]
+async fn run_import(store: &Store, file: &Path, format: Option<&str>) -> Result<()> {
+    let format = match format {
+        Some(format) => ExportFormat::try_from(format)?,
+        None => ExportFormat::from_path(file).unwrap_or(ExportFormat::Jsonl),
+    };
+    let reader = File::open(file).with_context(|| format!("unable to open {}", file.display()))?;
+    let _applied = store.migrate().await?;
+    let imported = import(store, ExportReader::new(format, reader)?).await?;
+    writeln!(
+        stdout(),
+        "imported {} runs with {} output lines, skipped {} runs already stored",
+        imported.runs,
+        imported.lines,
+        imported.skipped
+    )?;
+    Ok(())
+}
+
+/// Enroll `name`, printing the token it must present when no `public_key` is given
+#[cfg_attr(coverage_nightly, coverage(off))]
+async fn run_enroll(store: &Store, name: &str, public_key: Option<String>) -> Result<()> {
+    let (credential, token) = if let Some(public_key) = public_key {
+        let _key = parse_verifying_key(&public_key)?;
+        (ClientCredential::PublicKey(public_key), None)
+    } else {
+        let token = generate_client_token();
+        (
+            ClientCredential::Token(hash_client_token(&token)),
+            Some(token),
+        )
+    };
+    let _applied = store.migrate().await?;
+    if !store.enroll_client(name, &credential).await? {
+        return Err(anyhow::anyhow!(
+            "'{name}' is already enrolled, revoke it first"
+        ));
+    }
+    let mut out = stdout();
+    if let Some(token) = token {
+        writeln!(out, "enrolled '{name}', its token is {token}")?;
+    } else {
+        writeln!(out, "enrolled '{name}' with its public key")?;
+    }
+    Ok(())
+}
+
+/// Revoke the credential of `name`
+#[cfg_attr(coverage_nightly, coverage(off))]
+async fn run_revoke(store: &Store, name: &str) -> Result<()> {
+    let _applied = store.migrate().await?;
+    if store.revoke_client(name).await? {
+        writeln!(stdout(), "revoked '{name}'")?;
+    } else {
+        writeln!(stdout(), "'{name}' is not enrolled")?;
+    }
+    Ok(())
+}
+
 fn resolve_tls_config(config: &Config) -> Result<Option<(SocketAddr, ServerConfig)>> {
+    let mtls_enabled = config
+        .actix()
+        .tls()
+        .as_ref()
+        .is_some_and(|tls| tls.client_ca_cert().is_some());
+    if config.bind_name_to_cert() && !mtls_enabled {
+        return Err(Error::CertBindingWithoutMtls.into());
+    }
     if let Some(actix_tls) = config.actix().tls() {
         let server_config = load_tls_config(actix_tls)?;
         let ip_addr: IpAddr = actix_tls.ip().parse().with_context(|| Error::InvalidIp)?;
@@ -186,18 +398,194 @@ fn resolve_tls_config(config: &Config) -> Result<Option<(SocketAddr, ServerConfi
     }
 }
 
+/// Enforce the retention rules whenever `retention.on_calendar` fires
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn spawn_retention_task(
+    config: &Config,
+    store: Data<Store>,
+    token: CancellationToken,
+) -> Result<Option<JoinHandle<()>>> {
+    let Some(on_calendar) = config.retention().on_calendar() else {
+        info!("retention.on_calendar not set, run barto-cli cleanup to apply retention");
+        return Ok(None);
+    };
+    let realtime = Realtime::try_from(on_calendar.as_str())?;
+    info!("enforcing retention on calendar '{on_calendar}'");
+    let config = config.clone();
+    Ok(Some(spawn(async move {
+        let mut ticker = interval(Duration::from_secs(1));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut last_run = 0;
+        loop {
+            select! {
+                () = token.cancelled() => break,
+                _ = ticker.tick() => {
+                    let now = OffsetDateTime::now_utc();
+                    if realtime.is_now(now) && now.unix_timestamp() != last_run {
+                        last_run = now.unix_timestamp();
+                        if let Err(e) = retention::enforce(&**store, &config, now).await {
+                            error!("retention cleanup failed: {e}");
+                        }
+                    }
+                }
+            }
+        }
+    })))
+}
+
+/// Check for runs that were expected but never reported every `missed_runs.check_secs`,
+/// recording and alerting on each one
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn spawn_watchdog_task(
+    config: &Config,
+    watchdog: Data<Watchdog>,
+    live_schedules: Data<RwLock<BTreeMap<String, Schedules>>>,
+    store: Data<Store>,
+    alerter: Data<Alerter>,
+    metrics: Data<Metrics>,
+    dashboard_bcast_tx: broadcast::Sender<DashboardEvent>,
+    token: CancellationToken,
+) -> Option<JoinHandle<()>> {
+    let missed_runs = config.missed_runs();
+    if !missed_runs.enabled() {
+        info!("missed_runs.enabled is false, not watching for missed runs");
+        return None;
+    }
+    info!(
+        "watching for missed runs with a grace of {}s",
+        missed_runs.grace_secs()
+    );
+    Some(spawn(async move {
+        let mut ticker = interval(Duration::from_secs(missed_runs.check_secs().max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            select! {
+                () = token.cancelled() => break,
+                _ = ticker.tick() => {
+                    let missed = {
+                        let schedules = live_schedules.read().await;
+                        watchdog.check(&schedules, OffsetDateTime::now_utc())
+                    };
+                    for schedule in watchdog.take_unwatchable() {
+                        warn!(
+                            "schedule '{}' on '{}' is not watched for missed runs, its on_calendar '{}' is invalid: {}",
+                            schedule.schedule_name(),
+                            schedule.bartoc_name(),
+                            schedule.on_calendar(),
+                            schedule.error()
+                        );
+                        alerter.unwatchable(&schedule, OffsetDateTime::now_utc());
+                        let _ = dashboard_bcast_tx.send(DashboardEvent::ScheduleUnwatchable {
+                            client: schedule.bartoc_name().to_string(),
+                            schedule: schedule.schedule_name().to_string(),
+                            error: schedule.error().to_string(),
+                        });
+                    }
+                    for run in missed {
+                        warn!(
+                            "schedule '{}' on '{}' missed a run",
+                            run.schedule_name(),
+                            run.bartoc_name()
+                        );
+                        metrics.run_missed(run.bartoc_name(), run.schedule_name());
+                        if let Err(e) = run.record(&**store).await {
+                            error!("unable to record missed run: {e}");
+                            continue;
+                        }
+                        let status = run.status();
+                        alerter.missed(&store, run.bartoc_name(), &status).await;
+                        let _ = dashboard_bcast_tx.send(DashboardEvent::RunFinished {
+                            client: run.bartoc_name().to_string(),
+                            cmd_uuid: status.cmd_uuid().0,
+                            success: false,
+                        });
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Check for known clients that have stayed offline every `offline_clients.check_secs`,
+/// alerting on each one and again when it reconnects
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn spawn_presence_task(
+    config: &Config,
+    store: Data<Store>,
+    clients: Data<Mutex<Clients>>,
+    alerter: Data<Alerter>,
+    token: CancellationToken,
+) -> Option<JoinHandle<()>> {
+    let offline_clients = config.offline_clients();
+    if !offline_clients.enabled() {
+        info!("offline_clients.enabled is false, not watching for offline clients");
+        return None;
+    }
+    info!(
+        "watching for clients offline longer than {}s",
+        offline_clients.threshold_secs()
+    );
+    let presence = Presence::new(offline_clients, OffsetDateTime::now_utc());
+    Some(spawn(async move {
+        let mut ticker = interval(Duration::from_secs(offline_clients.check_secs().max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            select! {
+                () = token.cancelled() => break,
+                _ = ticker.tick() => {
+                    let last_events = match store.last_client_events().await {
+                        Ok(last_events) => last_events,
+                        Err(e) => {
+                            error!("unable to read the client history: {e}");
+                            continue;
+                        }
+                    };
+                    let connected = clients
+                        .lock()
+                        .await
+                        .clients()
+                        .values()
+                        .map(|data| data.name().clone())
+                        .collect::<HashSet<String>>();
+                    let now = OffsetDateTime::now_utc();
+                    for change in presence.check(&last_events, &connected, now) {
+                        match &change {
+                            Change::Offline(offline) => warn!(
+                                "client '{}' has been offline since {}",
+                                offline.bartoc_name(),
+                                offline.since()
+                            ),
+                            Change::Back(offline) => {
+                                info!("client '{}' is back online", offline.bartoc_name());
+                            }
+                        }
+                        alerter.client(&change, now);
+                    }
+                }
+            }
+        }
+    }))
+}
+
 #[cfg_attr(coverage_nightly, coverage(off))]
 fn spawn_reload_task(
     cli: Cli,
     live_schedules: Data<RwLock<BTreeMap<String, Schedules>>>,
     mut reload_trigger_rx: mpsc::Receiver<()>,
     worker_bcast_tx: broadcast::Sender<WorkerSignal>,
+    dashboard_bcast_tx: broadcast::Sender<DashboardEvent>,
+    metrics: Data<Metrics>,
+    health: Data<Health>,
 ) -> JoinHandle<()> {
     spawn(async move {
         while reload_trigger_rx.recv().await.is_some() {
             while reload_trigger_rx.try_recv().is_ok() {}
-            match load::<Cli, Config, Cli>(&cli, &cli) {
-                Err(e) => error!("config reload failed, keeping existing schedules: {e}"),
+            let outcome = match load::<Cli, Config, Cli>(&cli, &cli) {
+                Err(e) => {
+                    error!("config reload failed, keeping existing schedules: {e}");
+                    Reload::Failed
+                }
                 Ok(new_config) => {
                     let mut valid = true;
                     for (client, sched_group) in new_config.schedules() {
@@ -217,19 +605,84 @@ fn spawn_reload_task(
                         if *schedules_guard == new_schedules {
                             drop(schedules_guard);
                             info!("config reloaded, schedules unchanged, skipping broadcast");
+                            Reload::Unchanged
                         } else {
                             *schedules_guard = new_schedules;
                             drop(schedules_guard);
                             let _ = worker_bcast_tx.send(WorkerSignal::Reload);
+                            let _ = dashboard_bcast_tx.send(DashboardEvent::SchedulesReloaded);
                             info!("config reloaded, schedules pushed to all connected clients");
+                            Reload::Applied
                         }
+                    } else {
+                        Reload::Invalid
                     }
                 }
-            }
+            };
+            metrics.config_reloaded(outcome);
+            health.reloaded(outcome, OffsetDateTime::now_utc());
         }
     })
 }
 
+/// Tell systemd `bartos` is ready and, when the unit sets `WatchdogSec=`, feed its watchdog
+/// for as long as the process is alive, so systemd only restarts a hung `bartos`. The
+/// readiness checks are reported in `STATUS=` alongside, and a database outage never stops
+/// the feed. Outside systemd this does nothing.
+#[cfg(unix)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn spawn_systemd_notify(store: Data<Store>, token: CancellationToken) -> Option<JoinHandle<()>> {
+    if let Err(e) = notify(false, &[NotifyState::Ready]) {
+        warn!("unable to notify systemd: {e}");
+    }
+    let mut usec = 0;
+    if !watchdog_enabled(false, &mut usec) {
+        return None;
+    }
+    let period = Duration::from_micros(usec / 2);
+    info!(
+        "feeding the systemd watchdog every {}ms",
+        period.as_millis()
+    );
+    Some(spawn(async move {
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            select! {
+                () = token.cancelled() => break,
+                _ = ticker.tick() => {
+                    if let Err(e) = notify(false, &[NotifyState::Watchdog]) {
+                        warn!("unable to notify systemd: {e}");
+                    }
+                    let readiness = Readiness::check(&**store, &token).await;
+                    let status = readiness.reason().unwrap_or("ready");
+                    if let Err(e) = notify(false, &[NotifyState::Status(status)]) {
+                        warn!("unable to notify systemd: {e}");
+                    }
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(not(unix))]
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn spawn_systemd_notify(_store: Data<Store>, _token: CancellationToken) -> Option<JoinHandle<()>> {
+    None
+}
+
+#[cfg(unix)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn notify_stopping() {
+    if let Err(e) = notify(false, &[NotifyState::Stopping]) {
+        warn!("unable to notify systemd: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn notify_stopping() {}
+
 // File watcher: a std bridge thread feeds a tokio channel so we can select! on cancellation.
 // We use a std thread because notify's callback is sync; a tokio mpsc bridges to async code.
 #[cfg_attr(coverage_nightly, coverage(off))]
@@ -334,22 +787,44 @@ fn build_http_server(
     let WebAppData {
         token,
         config,
-        pool,
+        store,
         clients,
         live_schedules,
         worker_bcast,
+        metrics,
+        dashboard_bcast,
+        tail_bcast,
+        dashboard_tickets,
+        alerter,
+        watchdog,
+        health,
     } = app_data;
     let server = HttpServer::new(move || {
         App::new()
             .app_data(token.clone())
             .app_data(config.clone())
-            .app_data(pool.clone())
+            .app_data(store.clone())
             .app_data(clients.clone())
             .app_data(live_schedules.clone())
             .app_data(worker_bcast.clone())
+            .app_data(metrics.clone())
+            .app_data(dashboard_bcast.clone())
+            .app_data(tail_bcast.clone())
+            .app_data(dashboard_tickets.clone())
+            .app_data(alerter.clone())
+            .app_data(watchdog.clone())
+            .app_data(health.clone())
             .wrap(Compress::default())
-            .service(scope("/v1").configure(insecure_config))
+            .configure(health_config::<Store>)
+            .configure(metrics_config)
+            .configure(dashboard_config)
+            .service(
+                scope("/v1")
+                    .configure(insecure_config)
+                    .configure(api_config::<Store>),
+            )
     })
+    .on_connect(attach_cert_identity)
     .workers(workers)
     .bind((host, port))?;
     let server = if let Some((addr, server_config)) = tls_opt {
@@ -360,6 +835,24 @@ fn build_http_server(
     Ok(server.run())
 }
 
+/// Attaches the identity from a verified client certificate to each mutual TLS connection,
+/// so the endpoints can bind the client name to it.
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn attach_cert_identity(conn: &dyn Any, data: &mut Extensions) {
+    let Some(tls_stream) = conn.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    let (_, session) = tls_stream.get_ref();
+    if let Some(cert) = session.peer_certificates().and_then(<[_]>::first) {
+        match cert_identities(cert) {
+            Ok(names) => {
+                let _old = data.insert(CertIdentity::new(names));
+            }
+            Err(e) => warn!("unable to read identity from client certificate: {e}"),
+        }
+    }
+}
+
 #[cfg(unix)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 async fn handle_signals(token: CancellationToken, reload_tx: mpsc::Sender<()>) -> Result<()> {
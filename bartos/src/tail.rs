@@ -0,0 +1,156 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Live tails of job output
+//!
+//! Every record a `bartoc` sends is published as it is stored, and each `barto-cli` that
+//! subscribed receives the ones of the runs its filter matches. Run starts and output carry
+//! the schedule name, so a run matches from its first record; a status carries neither, so
+//! it is only passed on for runs that already matched. A subscriber that falls too far behind
+//! skips what it missed rather than holding up the workers.
+
+use std::collections::HashSet;
+
+use libbarto::{Data, TailFilter};
+use tokio::sync::broadcast::{Receiver, Sender, error::RecvError};
+use tracing::warn;
+use uuid::Uuid;
+
+/// A record from a bartoc client, as published to the subscribers
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct TailRecord {
+    /// The name of the client that sent the record
+    client: String,
+    data: Data,
+}
+
+/// Publish a record to the subscribers, building it only when there are any
+pub(crate) fn publish(tail: &Sender<TailRecord>, client: &str, data: impl FnOnce() -> Data) {
+    if tail.receiver_count() > 0 {
+        let _ = tail.send(TailRecord {
+            client: client.to_string(),
+            data: data(),
+        });
+    }
+}
+
+/// The records one `barto-cli` subscribed to
+#[derive(Debug)]
+pub(crate) struct Subscription {
+    filter: TailFilter,
+    /// The runs that matched the filter and have not finished yet
+    runs: HashSet<Uuid>,
+    rx: Receiver<TailRecord>,
+}
+
+impl Subscription {
+    pub(crate) fn new(filter: TailFilter, rx: Receiver<TailRecord>) -> Self {
+        Self {
+            filter,
+            runs: HashSet::new(),
+            rx,
+        }
+    }
+
+    /// Wait for the next record that matches, or `None` once bartos stops publishing
+    pub(crate) async fn next(&mut self) -> Option<Data> {
+        loop {
+            match self.rx.recv().await {
+                Ok(record) => {
+                    if self.accepts(&record) {
+                        return Some(record.data);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("tail subscriber fell behind, skipped {skipped} records");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    fn accepts(&mut self, record: &TailRecord) -> bool {
+        let (cmd_uuid, schedule) = match &record.data {
+            Data::Started(run_start) => (run_start.cmd_uuid().0, run_start.schedule_name()),
+            Data::Output(output) => (output.cmd_uuid().0, output.cmd_name()),
+            Data::Status(status) => return self.runs.remove(&status.cmd_uuid().0),
+        };
+        if self.filter.matches(&record.client, schedule) {
+            let _ = self.runs.insert(cmd_uuid);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libbarto::{
+        Data, OffsetDataTimeWrapper, Output, OutputKind, Status, TailFilter, UuidWrapper,
+    };
+    use time::OffsetDateTime;
+    use tokio::sync::broadcast;
+    use uuid::Uuid;
+
+    use super::{Subscription, TailRecord, publish};
+
+    fn output(cmd_uuid: Uuid, schedule: &str, line: &str) -> Data {
+        Data::Output(
+            Output::builder()
+                .bartoc_uuid(UuidWrapper(Uuid::nil()))
+                .bartoc_name("host1".to_string())
+                .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+                .cmd_uuid(UuidWrapper(cmd_uuid))
+                .cmd_name(schedule.to_string())
+                .kind(OutputKind::Stdout)
+                .data(line.to_string())
+                .build(),
+        )
+    }
+
+    fn status(cmd_uuid: Uuid) -> Data {
+        Data::Status(
+            Status::builder()
+                .cmd_uuid(UuidWrapper(cmd_uuid))
+                .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+                .exit_code(Some(0))
+                .success(true)
+                .build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn streams_only_matching_runs() {
+        let (tx, _rx) = broadcast::channel::<TailRecord>(16);
+        let filter = TailFilter::builder().schedule("backup".to_string()).build();
+        let mut subscription = Subscription::new(filter, tx.subscribe());
+        let (backup, update) = (Uuid::new_v4(), Uuid::new_v4());
+        publish(&tx, "host1", || output(update, "update", "skipped"));
+        publish(&tx, "host1", || output(backup, "backup", "copied"));
+        publish(&tx, "host1", || status(update));
+        publish(&tx, "host1", || status(backup));
+        // A run that already finished is not followed any more
+        publish(&tx, "host1", || status(backup));
+        drop(tx);
+
+        assert_eq!(
+            subscription.next().await,
+            Some(output(backup, "backup", "copied"))
+        );
+        assert_eq!(subscription.next().await, Some(status(backup)));
+        assert_eq!(subscription.next().await, None);
+    }
+
+    #[test]
+    fn publishes_nothing_without_subscribers() {
+        let (tx, rx) = broadcast::channel::<TailRecord>(16);
+        drop(rx);
+        publish(&tx, "host1", || panic!("built a record nobody receives"));
+    }
+}
@@ -0,0 +1,480 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The dead man's switch: runs that were expected but never reported
+//!
+//! The fire times of each configured schedule are computed from its `on_calendar` on the
+//! `bartos` clock. Once a fire time is `grace_secs` in the past and no run of the schedule
+//! started within `grace_secs` of it, on the client's clock, the run is recorded as missed.
+//! A schedule is watched from when `bartos` starts or the schedule changes, and calendars
+//! with random (`R`) values are not watched, since only the client knows when they fire.
+//! A calendar that cannot be parsed is not watched either, and is reported once until it
+//! changes.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use anyhow::Result;
+use libbarto::{
+    OffsetDataTimeWrapper, Output, OutputKind, Realtime, RunStart, Schedules, Status, TriggerKind,
+    UuidWrapper,
+};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::{config::MissedRuns, db::Queryable};
+
+/// The most run starts remembered per schedule between checks
+const MAX_STARTS: usize = 10_000;
+
+/// A schedule being watched
+#[derive(Debug)]
+struct Watch {
+    /// The calendar the fire times come from, to notice when it changes
+    on_calendar: String,
+    /// `None` when the calendar cannot be parsed
+    realtime: Option<Realtime>,
+    /// The schedule's commands, as recorded for a missed run
+    cmd: String,
+    /// The next fire time not yet checked
+    next: Option<OffsetDateTime>,
+    /// When recent runs of the schedule started
+    starts: Vec<OffsetDateTime>,
+}
+
+/// A run that was expected but never reported
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Missed {
+    /// The UUID the missed run is recorded under
+    cmd_uuid: Uuid,
+    bartoc_name: String,
+    schedule_name: String,
+    cmd: String,
+    /// When the schedule should have fired
+    expected: OffsetDateTime,
+    grace: Duration,
+}
+
+impl Missed {
+    pub(crate) fn bartoc_name(&self) -> &str {
+        &self.bartoc_name
+    }
+
+    pub(crate) fn schedule_name(&self) -> &str {
+        &self.schedule_name
+    }
+
+    /// The start of the missed run, at the time it was expected
+    pub(crate) fn run_start(&self) -> RunStart {
+        RunStart::builder()
+            .cmd_uuid(UuidWrapper(self.cmd_uuid))
+            .bartoc_uuid(UuidWrapper(Uuid::nil()))
+            .bartoc_name(self.bartoc_name.clone())
+            .schedule_name(self.schedule_name.clone())
+            .cmd(self.cmd.clone())
+            .trigger(TriggerKind::Missed)
+            .timestamp(OffsetDataTimeWrapper(self.expected))
+            .build()
+    }
+
+    /// A line of output saying why the run was recorded
+    pub(crate) fn output(&self) -> Output {
+        Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::nil()))
+            .bartoc_name(self.bartoc_name.clone())
+            .timestamp(OffsetDataTimeWrapper(self.expected))
+            .cmd_uuid(UuidWrapper(self.cmd_uuid))
+            .cmd_name(self.schedule_name.clone())
+            .kind(OutputKind::Stderr)
+            .data(format!(
+                "missed: no run was reported within {}s of {}",
+                self.grace.whole_seconds(),
+                OffsetDataTimeWrapper(self.expected)
+            ))
+            .build()
+    }
+
+    /// The failed status of the missed run, without an exit code
+    pub(crate) fn status(&self) -> Status {
+        Status::builder()
+            .cmd_uuid(UuidWrapper(self.cmd_uuid))
+            .timestamp(OffsetDataTimeWrapper(self.expected))
+            .exit_code(None)
+            .success(false)
+            .build()
+    }
+
+    /// Store the missed run as a failed run with the `missed` trigger
+    pub(crate) async fn record<Q: Queryable>(&self, store: &Q) -> Result<()> {
+        let _rows = store.insert_run_start(&self.run_start()).await?;
+        let _rows = store.insert_outputs(&[self.output()]).await?;
+        let _rows = store.insert_status(&self.status()).await?;
+        Ok(())
+    }
+}
+
+/// A schedule whose `on_calendar` cannot be parsed, so its missed runs cannot be found
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Unwatchable {
+    bartoc_name: String,
+    schedule_name: String,
+    on_calendar: String,
+    /// Why the calendar cannot be parsed
+    error: String,
+}
+
+impl Unwatchable {
+    pub(crate) fn bartoc_name(&self) -> &str {
+        &self.bartoc_name
+    }
+
+    pub(crate) fn schedule_name(&self) -> &str {
+        &self.schedule_name
+    }
+
+    pub(crate) fn on_calendar(&self) -> &str {
+        &self.on_calendar
+    }
+
+    pub(crate) fn error(&self) -> &str {
+        &self.error
+    }
+}
+
+/// Tracks the expected runs of every configured schedule
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    grace: Duration,
+    watches: Mutex<HashMap<(String, String), Watch>>,
+    /// Schedules found unwatchable by `check` and not yet taken
+    unwatchable: Mutex<Vec<Unwatchable>>,
+}
+
+impl Watchdog {
+    pub(crate) fn new(missed_runs: MissedRuns) -> Self {
+        Self {
+            grace: Duration::seconds(i64::try_from(missed_runs.grace_secs()).unwrap_or(i64::MAX)),
+            watches: Mutex::new(HashMap::new()),
+            unwatchable: Mutex::new(vec![]),
+        }
+    }
+
+    fn watches(&self) -> MutexGuard<'_, HashMap<(String, String), Watch>> {
+        self.watches
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Take the schedules `check` found with a calendar that cannot be parsed. Each one is
+    /// returned once, until its calendar changes.
+    pub(crate) fn take_unwatchable(&self) -> Vec<Unwatchable> {
+        std::mem::take(
+            &mut *self
+                .unwatchable
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        )
+    }
+
+    /// Note that a run of a watched schedule started
+    pub(crate) fn run_started(&self, run_start: &RunStart) {
+        let key = (
+            run_start.bartoc_name().clone(),
+            run_start.schedule_name().clone(),
+        );
+        if let Some(watch) = self.watches().get_mut(&key)
+            && watch.starts.len() < MAX_STARTS
+        {
+            watch.starts.push(run_start.timestamp().0);
+        }
+    }
+
+    /// Bring the watches in line with the configured schedules and return the runs that
+    /// are now overdue
+    pub(crate) fn check(
+        &self,
+        schedules: &BTreeMap<String, Schedules>,
+        now: OffsetDateTime,
+    ) -> Vec<Missed> {
+        let mut watches = self.watches();
+        let mut configured = HashSet::new();
+        let mut missed = vec![];
+        let mut unwatchable = vec![];
+        for (bartoc_name, group) in schedules {
+            for schedule in group.schedules() {
+                let on_calendar = schedule.on_calendar();
+                if on_calendar.contains('R') {
+                    continue;
+                }
+                let key = (bartoc_name.clone(), schedule.name().clone());
+                if watches
+                    .get(&key)
+                    .is_none_or(|watch| watch.on_calendar != *on_calendar)
+                {
+                    let realtime = match Realtime::try_from(on_calendar.as_str()) {
+                        Ok(realtime) => Some(realtime),
+                        Err(e) => {
+                            unwatchable.push(Unwatchable {
+                                bartoc_name: key.0.clone(),
+                                schedule_name: key.1.clone(),
+                                on_calendar: on_calendar.clone(),
+                                error: e.to_string(),
+                            });
+                            None
+                        }
+                    };
+                    let next = realtime
+                        .as_ref()
+                        .and_then(|realtime| realtime.next_after(now));
+                    let _old = watches.insert(
+                        key.clone(),
+                        Watch {
+                            on_calendar: on_calendar.clone(),
+                            realtime,
+                            cmd: String::new(),
+                            next,
+                            starts: vec![],
+                        },
+                    );
+                }
+                let Some(watch) = watches.get_mut(&key) else {
+                    continue;
+                };
+                watch.cmd = schedule.cmds().join("; ");
+                while let Some(expected) = watch.next
+                    && expected + self.grace <= now
+                {
+                    let window = (expected - self.grace)..=(expected + self.grace);
+                    if !watch.starts.iter().any(|start| window.contains(start)) {
+                        missed.push(Missed {
+                            cmd_uuid: Uuid::new_v4(),
+                            bartoc_name: key.0.clone(),
+                            schedule_name: key.1.clone(),
+                            cmd: watch.cmd.clone(),
+                            expected,
+                            grace: self.grace,
+                        });
+                    }
+                    watch.next = watch
+                        .realtime
+                        .as_ref()
+                        .and_then(|realtime| realtime.next_after(expected));
+                }
+                if let Some(next) = watch.next {
+                    watch.starts.retain(|start| *start >= next - self.grace);
+                }
+                let _new = configured.insert(key);
+            }
+        }
+        watches.retain(|key, _| configured.contains(key));
+        drop(watches);
+        self.unwatchable
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .extend(unwatchable);
+        missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use libbarto::{
+        OffsetDataTimeWrapper, OutputKind, RunStart, Schedules, TriggerKind, UuidWrapper,
+    };
+    use time::{Duration, OffsetDateTime, macros::datetime};
+    use uuid::Uuid;
+
+    use super::Watchdog;
+    use crate::{
+        config::MissedRuns,
+        db::{Queryable, memory::MemoryHandler},
+    };
+
+    fn schedules(on_calendar: &str) -> BTreeMap<String, Schedules> {
+        // `Schedules` only derives `Builder` under libbarto's own test cfg
+        let json = format!(
+            r#"{{"schedules":[
+                {{"name":"backup","on_calendar":"{on_calendar}","cmds":["backup.sh","prune.sh"]}},
+                {{"name":"jitter","on_calendar":"* R:R:00","cmds":["jitter"]}}
+            ]}}"#
+        );
+        BTreeMap::from([("host".to_string(), serde_json::from_str(&json).unwrap())])
+    }
+
+    fn watchdog() -> Watchdog {
+        Watchdog::new(MissedRuns::builder().grace_secs(60).build())
+    }
+
+    fn started(watchdog: &Watchdog, schedule: &str, at: OffsetDateTime) {
+        watchdog.run_started(
+            &RunStart::builder()
+                .cmd_uuid(UuidWrapper(Uuid::new_v4()))
+                .bartoc_uuid(UuidWrapper(Uuid::new_v4()))
+                .bartoc_name("host".to_string())
+                .schedule_name(schedule.to_string())
+                .cmd("backup.sh".to_string())
+                .timestamp(OffsetDataTimeWrapper(at))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn reports_a_run_that_never_started() {
+        let watchdog = watchdog();
+        let hourly = schedules("* *:00:00");
+        let start = datetime!(2025-06-01 09:30:00 UTC);
+        assert!(watchdog.check(&hourly, start).is_empty());
+        // Not overdue until the grace has passed
+        assert!(
+            watchdog
+                .check(&hourly, datetime!(2025-06-01 10:00:59 UTC))
+                .is_empty()
+        );
+
+        let missed = watchdog.check(&hourly, datetime!(2025-06-01 10:01:00 UTC));
+        assert_eq!(missed.len(), 1);
+        let missed = &missed[0];
+        assert_eq!(missed.bartoc_name(), "host");
+        assert_eq!(missed.schedule_name(), "backup");
+        assert_eq!(missed.run_start().trigger(), TriggerKind::Missed);
+        assert_eq!(missed.run_start().cmd(), "backup.sh; prune.sh");
+        assert_eq!(
+            missed.run_start().timestamp().0,
+            datetime!(2025-06-01 10:00:00 UTC)
+        );
+        assert_eq!(missed.output().kind(), OutputKind::Stderr);
+        assert!(
+            missed
+                .output()
+                .data()
+                .starts_with("missed: no run was reported within 60s of")
+        );
+        assert!(!missed.status().success());
+        assert!(missed.status().exit_code().is_none());
+        // Each expected run is only reported once
+        assert!(
+            watchdog
+                .check(&hourly, datetime!(2025-06-01 10:02:00 UTC))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn a_run_within_the_grace_counts() {
+        let watchdog = watchdog();
+        let hourly = schedules("* *:00:00");
+        assert!(
+            watchdog
+                .check(&hourly, datetime!(2025-06-01 09:30:00 UTC))
+                .is_empty()
+        );
+        // A client clock a little behind still counts
+        started(&watchdog, "backup", datetime!(2025-06-01 09:59:30 UTC));
+        assert!(
+            watchdog
+                .check(&hourly, datetime!(2025-06-01 10:05:00 UTC))
+                .is_empty()
+        );
+        // A run far from the expected time does not
+        started(&watchdog, "backup", datetime!(2025-06-01 10:30:00 UTC));
+        assert_eq!(
+            watchdog
+                .check(&hourly, datetime!(2025-06-01 11:05:00 UTC))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn catches_up_and_follows_schedule_changes() {
+        let watchdog = watchdog();
+        let start = datetime!(2025-06-01 09:30:00 UTC);
+        assert!(watchdog.check(&schedules("* *:00:00"), start).is_empty());
+        let missed = watchdog.check(&schedules("* *:00:00"), start + Duration::hours(3));
+        assert_eq!(missed.len(), 3);
+
+        // A changed calendar is watched afresh from now
+        let later = start + Duration::hours(4);
+        assert!(watchdog.check(&schedules("* *:30:00"), later).is_empty());
+        assert!(
+            watchdog
+                .check(&schedules("* *:30:00"), later + Duration::minutes(30))
+                .is_empty()
+        );
+        assert_eq!(
+            watchdog
+                .check(&schedules("* *:30:00"), later + Duration::minutes(61))
+                .len(),
+            1
+        );
+
+        // A removed schedule is forgotten
+        assert!(watchdog.check(&BTreeMap::new(), later).is_empty());
+        assert!(watchdog.watches().is_empty());
+    }
+
+    #[test]
+    fn reports_an_invalid_calendar_once() {
+        let watchdog = watchdog();
+        let invalid = schedules("not a calendar");
+        let start = datetime!(2025-06-01 09:30:00 UTC);
+        assert!(watchdog.check(&invalid, start).is_empty());
+        let unwatchable = watchdog.take_unwatchable();
+        assert_eq!(unwatchable.len(), 1);
+        assert_eq!(unwatchable[0].bartoc_name(), "host");
+        assert_eq!(unwatchable[0].schedule_name(), "backup");
+        assert_eq!(unwatchable[0].on_calendar(), "not a calendar");
+        assert!(!unwatchable[0].error().is_empty());
+        assert!(watchdog.take_unwatchable().is_empty());
+
+        // Not reported again, and never missed, until the calendar changes
+        assert!(
+            watchdog
+                .check(&invalid, start + Duration::hours(3))
+                .is_empty()
+        );
+        assert!(watchdog.take_unwatchable().is_empty());
+        let _none = watchdog.check(&schedules("also not a calendar"), start);
+        assert_eq!(watchdog.take_unwatchable().len(), 1);
+
+        // A fixed calendar is watched again
+        let later = start + Duration::hours(4);
+        assert!(watchdog.check(&schedules("* *:00:00"), later).is_empty());
+        assert!(watchdog.take_unwatchable().is_empty());
+        assert_eq!(
+            watchdog
+                .check(&schedules("* *:00:00"), later + Duration::minutes(31))
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn records_a_failed_run() {
+        let watchdog = watchdog();
+        let hourly = schedules("* *:00:00");
+        let _none = watchdog.check(&hourly, datetime!(2025-06-01 09:30:00 UTC));
+        let missed = watchdog.check(&hourly, datetime!(2025-06-01 10:01:00 UTC));
+        let store = MemoryHandler::default();
+        missed[0].record(&store).await.unwrap();
+
+        let failed = store.failed_cmd_data().await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].cmd_name().as_deref(), Some("backup"));
+        assert!(
+            failed[0]
+                .data()
+                .as_deref()
+                .is_some_and(|data| data.starts_with("missed:"))
+        );
+    }
+}
@@ -1,2 +0,0 @@
--- Add down migration script here
-DROP TABLE IF EXISTS exit_status;
\ No newline at end of file
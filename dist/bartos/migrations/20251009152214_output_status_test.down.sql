@@ -1,2 +0,0 @@
-DROP TABLE IF EXISTS output_test;
-DROP TABLE IF EXISTS exit_status_test;
\ No newline at end of file
@@ -1,5 +0,0 @@
-ALTER TABLE exit_status
-    DROP COLUMN timestamp;
-
-ALTER TABLE exit_status_test
-    DROP COLUMN timestamp;
\ No newline at end of file
@@ -1,4 +0,0 @@
-ALTER TABLE output MODIFY bartoc_uuid BINARY(16) NOT NULL DEFAULT 0;
-ALTER TABLE output MODIFY cmd_uuid BINARY(16) NOT NULL DEFAULT 0;
-ALTER TABLE output_test MODIFY bartoc_uuid BINARY(16) NOT NULL DEFAULT 0;
-ALTER TABLE output_test MODIFY cmd_uuid BINARY(16) NOT NULL DEFAULT 0;
\ No newline at end of file
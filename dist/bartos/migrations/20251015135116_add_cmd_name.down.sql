@@ -1 +0,0 @@
--- Add down migration script here
@@ -1,2 +0,0 @@
-ALTER TABLE output_test ADD cmd_name VARCHAR(256) NOT NULL DEFAULT "unset" AFTER cmd_uuid;
-ALTER TABLE output ADD cmd_name VARCHAR(256) NOT NULL DEFAULT "unset" AFTER cmd_uuid;
\ No newline at end of file
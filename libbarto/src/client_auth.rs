@@ -0,0 +1,227 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ed25519_dalek::{Signature, Signer as _, Verifier as _};
+use rand::RngExt as _;
+use sha2::{Digest as _, Sha256};
+
+use crate::{Bartos, Error, SigningKey, VerifyingKey, hmac_auth::current_secs, parse_signing_key};
+
+/// The WebSocket upgrade header carrying a per-client Ed25519 credential.
+///
+/// The value has the form `<timestamp_secs>.<base64 signature>`, see [`sign_client_auth`].
+pub const CLIENT_AUTH_HEADER: &str = "X-Barto-Client-Auth";
+
+/// Domain separation prefix for client credential signatures, so a client auth signature can
+/// never be confused with a signed protocol message.
+const CLIENT_AUTH_CONTEXT: &[u8] = b"barto-client-auth";
+
+/// Generate a new random per-client token (32 random bytes, base64-encoded).
+///
+/// The token is handed to the client once at enrollment; `bartos` only stores
+/// [`hash_client_token`] of it.
+#[must_use]
+pub fn generate_client_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    STANDARD.encode(bytes)
+}
+
+/// Hash a per-client token for storage in the client registry (lowercase hex SHA-256).
+#[must_use]
+pub fn hash_client_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        })
+}
+
+/// Produce a [`CLIENT_AUTH_HEADER`] value proving possession of `key` for the client `name`.
+///
+/// The signature covers a fixed context string, the client name and the current timestamp.
+#[must_use]
+pub fn sign_client_auth(key: &SigningKey, name: &str) -> String {
+    let timestamp = current_secs();
+    let signature = key.sign(&client_auth_message(name, timestamp));
+    format!("{timestamp}.{}", STANDARD.encode(signature.to_bytes()))
+}
+
+/// Verify a [`CLIENT_AUTH_HEADER`] value for the client `name` against its enrolled public key.
+///
+/// # Errors
+/// Returns [`Error::SignatureInvalid`] if the header is malformed or the signature does not verify.
+/// Returns [`Error::MessageExpired`] if the timestamp is outside `window_secs` of now.
+pub fn verify_client_auth(
+    key: &VerifyingKey,
+    name: &str,
+    header: &str,
+    window_secs: u64,
+) -> Result<()> {
+    let (ts_str, sig_b64) = header.split_once('.').ok_or(Error::SignatureInvalid)?;
+    let timestamp: u64 = ts_str.parse().map_err(|_| Error::SignatureInvalid)?;
+    let sig_bytes = STANDARD
+        .decode(sig_b64)
+        .map_err(|_| Error::SignatureInvalid)?;
+    let sig_arr: [u8; 64] = sig_bytes.try_into().map_err(|_| Error::SignatureInvalid)?;
+    key.verify(
+        &client_auth_message(name, timestamp),
+        &Signature::from_bytes(&sig_arr),
+    )
+    .map_err(|_| Error::SignatureInvalid)?;
+    if current_secs().abs_diff(timestamp) > window_secs {
+        return Err(Error::MessageExpired.into());
+    }
+    Ok(())
+}
+
+/// Build the authentication headers a client sends on the WebSocket upgrade request.
+///
+/// The Bearer token is the per-client `client_token` when enrolled with one, falling back to
+/// the shared `api_key`. When `client_signing_key` is set, a [`CLIENT_AUTH_HEADER`] is added.
+///
+/// # Errors
+/// Returns [`Error::InvalidKey`] if `client_signing_key` is not a valid base64 Ed25519 seed.
+pub fn client_auth_headers(bartos: &Bartos, name: &str) -> Result<Vec<(&'static str, String)>> {
+    let mut headers = vec![];
+    if let Some(token) = bartos.client_token().as_ref().or(bartos.api_key().as_ref()) {
+        headers.push(("Authorization", format!("Bearer {token}")));
+    }
+    if let Some(sk_b64) = bartos.client_signing_key() {
+        let key = parse_signing_key(sk_b64)?;
+        headers.push((CLIENT_AUTH_HEADER, sign_client_auth(&key, name)));
+    }
+    Ok(headers)
+}
+
+fn client_auth_message(name: &str, timestamp: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(CLIENT_AUTH_CONTEXT.len() + name.len() + 8);
+    message.extend_from_slice(CLIENT_AUTH_CONTEXT);
+    message.extend_from_slice(name.as_bytes());
+    message.extend_from_slice(&timestamp.to_be_bytes());
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer as _, SigningKey};
+
+    use super::{
+        CLIENT_AUTH_HEADER, STANDARD, client_auth_headers, client_auth_message,
+        generate_client_token, hash_client_token, sign_client_auth, verify_client_auth,
+    };
+    use crate::{Bartos, Error};
+    use base64::Engine as _;
+
+    fn bartos(client_token: Option<&str>, client_signing_key: Option<String>) -> Bartos {
+        Bartos::builder()
+            .prefix("wss".to_string())
+            .host("localhost".to_string())
+            .port(8443)
+            .api_key("shared".to_string())
+            .maybe_client_token(client_token.map(ToString::to_string))
+            .maybe_client_signing_key(client_signing_key)
+            .build()
+    }
+
+    #[test]
+    fn generated_tokens_are_unique() {
+        assert_ne!(generate_client_token(), generate_client_token());
+    }
+
+    #[test]
+    fn hash_is_stable_hex() {
+        let hash = hash_client_token("token");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_client_token("token"));
+        assert_ne!(hash, hash_client_token("other"));
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let sk = SigningKey::from_bytes(&[7; 32]);
+        let header = sign_client_auth(&sk, "host1");
+        assert!(verify_client_auth(&sk.verifying_key(), "host1", &header, 60).is_ok());
+    }
+
+    #[test]
+    fn wrong_name_rejected() {
+        let sk = SigningKey::from_bytes(&[7; 32]);
+        let header = sign_client_auth(&sk, "host1");
+        assert!(verify_client_auth(&sk.verifying_key(), "host2", &header, 60).is_err());
+    }
+
+    #[test]
+    fn wrong_key_rejected() {
+        let sk = SigningKey::from_bytes(&[7; 32]);
+        let other = SigningKey::from_bytes(&[8; 32]);
+        let header = sign_client_auth(&sk, "host1");
+        assert!(verify_client_auth(&other.verifying_key(), "host1", &header, 60).is_err());
+    }
+
+    #[test]
+    fn malformed_header_rejected() {
+        let vk = SigningKey::from_bytes(&[7; 32]).verifying_key();
+        assert!(verify_client_auth(&vk, "host1", "garbage", 60).is_err());
+        assert!(verify_client_auth(&vk, "host1", "12.not-base64!!", 60).is_err());
+        assert!(verify_client_auth(&vk, "host1", "x.AAAA", 60).is_err());
+    }
+
+    #[test]
+    fn expired_header_rejected() {
+        let sk = SigningKey::from_bytes(&[7; 32]);
+        let timestamp = 1_000_000_u64;
+        let signature = sk.sign(&client_auth_message("host1", timestamp));
+        let header = format!("{timestamp}.{}", STANDARD.encode(signature.to_bytes()));
+        let err = verify_client_auth(&sk.verifying_key(), "host1", &header, 60).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::MessageExpired)
+        ));
+    }
+
+    #[test]
+    fn headers_use_shared_api_key_by_default() {
+        let headers = client_auth_headers(&bartos(None, None), "host1").unwrap();
+        assert_eq!(
+            headers,
+            vec![("Authorization", "Bearer shared".to_string())]
+        );
+    }
+
+    #[test]
+    fn headers_prefer_client_token() {
+        let bartos = bartos(Some("per-client"), None);
+        let headers = client_auth_headers(&bartos, "host1").unwrap();
+        assert_eq!(
+            headers,
+            vec![("Authorization", "Bearer per-client".to_string())]
+        );
+    }
+
+    #[test]
+    fn headers_include_signature() {
+        let sk = SigningKey::from_bytes(&[7; 32]);
+        let bartos = bartos(None, Some(STANDARD.encode(sk.as_bytes())));
+        let headers = client_auth_headers(&bartos, "host1").unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[1].0, CLIENT_AUTH_HEADER);
+        assert!(verify_client_auth(&sk.verifying_key(), "host1", &headers[1].1, 60).is_ok());
+    }
+
+    #[test]
+    fn headers_invalid_signing_key_is_err() {
+        let bartos = bartos(None, Some("not-a-key".to_string()));
+        assert!(client_auth_headers(&bartos, "host1").is_err());
+    }
+}
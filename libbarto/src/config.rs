@@ -6,7 +6,10 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::path::PathBuf;
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+};
 
 use anyhow::{Context, Result};
 use bincode_next::{Decode, Encode};
@@ -21,7 +24,7 @@ use tracing_subscriber_init::{TracingConfig, get_effective_level};
 
 #[cfg(test)]
 use crate::utils::Mock;
-use crate::{TlsConfig, TracingConfigExt, error::Error, utils::to_path_buf};
+use crate::{TlsConfig, TracingConfigExt, TracingFileConfigExt, error::Error, utils::to_path_buf};
 
 /// Trait to allow default paths to be supplied to [`load`]
 pub trait PathDefaults {
@@ -50,6 +53,10 @@ pub struct Tracing {
     /// file layer configuration
     #[getset(get = "pub")]
     file: FileLayer,
+    /// OpenTelemetry trace export configuration, spans are only exported when set
+    #[getset(get = "pub")]
+    #[serde(default)]
+    otlp: Option<Otlp>,
 }
 
 /// Tracing configuration
@@ -61,6 +68,48 @@ pub struct FileLayer {
     verbose: u8,
     /// layer configuration
     layer: Layer,
+    /// The format of the lines written to the log file
+    #[serde(default)]
+    format: LogFormat,
+    /// How often the log file is rotated
+    #[serde(default)]
+    rotation: LogRotation,
+    /// Rotate the log file once it grows past this many bytes, instead of on `rotation`
+    #[serde(default)]
+    max_size: Option<NonZeroU64>,
+    /// The number of log files to keep, the oldest are removed first, default all
+    #[serde(default)]
+    max_files: Option<NonZeroUsize>,
+}
+
+/// The format of the lines written to the log file
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `tracing_subscriber::fmt::format::Compact`
+    #[default]
+    Compact,
+    /// `tracing_subscriber::fmt::format::Json`, one JSON object per line
+    Json,
+    /// `tracing_subscriber::fmt::format::Pretty`
+    Pretty,
+}
+
+/// How often the log file is rotated
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// `Rotation::MINUTELY`
+    Minutely,
+    /// `Rotation::HOURLY`
+    Hourly,
+    /// `Rotation::DAILY`
+    #[default]
+    Daily,
+    /// `Rotation::WEEKLY`
+    Weekly,
+    /// `Rotation::NEVER`
+    Never,
 }
 
 impl TracingConfig for FileLayer {
@@ -111,6 +160,24 @@ impl TracingConfigExt for FileLayer {
     }
 }
 
+impl TracingFileConfigExt for FileLayer {
+    fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    fn rotation(&self) -> LogRotation {
+        self.rotation
+    }
+
+    fn max_size(&self) -> Option<NonZeroU64> {
+        self.max_size
+    }
+
+    fn max_files(&self) -> Option<NonZeroUsize> {
+        self.max_files
+    }
+}
+
 /// Tracing configuration
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, CopyGetters, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
@@ -135,6 +202,21 @@ pub struct Layer {
     directives: Option<String>,
 }
 
+/// OpenTelemetry trace export configuration
+#[derive(Clone, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
+#[cfg_attr(test, derive(Builder))]
+#[getset(get = "pub")]
+pub struct Otlp {
+    /// The OTLP/HTTP traces endpoint of the collector, i.e. `http://localhost:4318/v1/traces`
+    endpoint: String,
+    /// The service name the spans are reported under, defaults to the binary name
+    #[serde(default)]
+    service_name: Option<String>,
+    /// Tracing directives selecting the spans to export, defaults to `info`
+    #[serde(default)]
+    directives: Option<String>,
+}
+
 /// A command to run on a worker
 #[derive(Clone, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
 #[getset(get = "pub")]
@@ -195,6 +277,7 @@ impl TlsConfig for Tls {
 
 /// Used in bartoc configuration to define the bartos instance to connect to
 #[derive(Clone, CopyGetters, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
+#[cfg_attr(test, derive(Builder))]
 pub struct Bartos {
     /// The websocket prefix (ws or wss)
     #[getset(get = "pub")]
@@ -226,6 +309,29 @@ pub struct Bartos {
     #[getset(get = "pub")]
     #[serde(default)]
     api_key: Option<String>,
+    /// Optional per-client token issued by `barto-cli clients enroll`.
+    /// When set, it is sent as the Bearer token instead of `api_key`.
+    #[getset(get = "pub")]
+    #[serde(default)]
+    client_token: Option<String>,
+    /// Optional base64 Ed25519 signing key for a client enrolled with a public key.
+    /// When set, the client signs its name in the `X-Barto-Client-Auth` header.
+    #[getset(get = "pub")]
+    #[serde(default)]
+    client_signing_key: Option<String>,
+}
+
+/// A key identified by the key ID embedded in keyed signatures and HMAC envelopes.
+///
+/// Used for lists of accepted verification keys, so keys can be rotated without a flag day.
+#[derive(Clone, CopyGetters, Debug, Default, Deserialize, Eq, Getters, PartialEq, Serialize)]
+pub struct KeyWithId {
+    /// The key ID
+    #[getset(get_copy = "pub")]
+    id: u32,
+    /// The key material (base64-encoded Ed25519 public key, or HMAC secret)
+    #[getset(get = "pub")]
+    key: String,
 }
 
 /// The `MariaDB` configuration
@@ -243,16 +349,6 @@ pub struct Mariadb {
     database: String,
     /// The options string
     options: Option<String>,
-    /// The output table name, used for testing
-    #[doc(hidden)]
-    #[getset(get_copy = "pub")]
-    #[serde(default = "OutputTableName::default")]
-    output_table: OutputTableName,
-    /// The status table name, used for testing
-    #[doc(hidden)]
-    #[getset(get_copy = "pub")]
-    #[serde(default = "StatusTableName::default")]
-    status_table: StatusTableName,
 }
 
 impl Mariadb {
@@ -291,46 +387,6 @@ impl Mariadb {
         url
     }
 }
-/// The output table name
-#[doc(hidden)]
-#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
-pub enum OutputTableName {
-    /// Use the default table name `output`
-    #[default]
-    Output,
-    /// Use the test table name `output_test`
-    OutputTest,
-}
-
-impl From<OutputTableName> for &'static str {
-    fn from(value: OutputTableName) -> Self {
-        match value {
-            OutputTableName::Output => "output",
-            OutputTableName::OutputTest => "output_test",
-        }
-    }
-}
-
-/// The status table name
-#[doc(hidden)]
-#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
-pub enum StatusTableName {
-    /// Use the default table name `status`
-    #[default]
-    Status,
-    /// Use the test table name `status_test`
-    StatusTest,
-}
-
-impl From<StatusTableName> for &'static str {
-    fn from(value: StatusTableName) -> Self {
-        match value {
-            StatusTableName::Status => "status",
-            StatusTableName::StatusTest => "status_test",
-        }
-    }
-}
-
 /// The output table name
 #[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub enum MissedTick {
@@ -468,6 +524,8 @@ mod tests {
         assert!(bartos.client_key().is_none());
         assert!(bartos.ca_cert().is_none());
         assert!(bartos.api_key().is_none());
+        assert!(bartos.client_token().is_none());
+        assert!(bartos.client_signing_key().is_none());
     }
 
     #[test]
@@ -502,6 +560,8 @@ mod tests {
             client_cert: Some(PathBuf::from("/etc/bartoc/client.pem")),
             client_key: Some(PathBuf::from("/etc/bartoc/client.key")),
             api_key: None,
+            client_token: None,
+            client_signing_key: None,
         };
         assert_eq!(
             bartos.client_cert().as_deref(),
@@ -582,4 +642,56 @@ mod tests {
 
         assert_eq!(cfg.my_field.as_deref(), Some("flatval"));
     }
+
+    #[test]
+    fn test_file_layer_log_file_options() {
+        use std::num::{NonZeroU64, NonZeroUsize};
+
+        use config::{Config, File, FileFormat};
+
+        use crate::{LogFormat, LogRotation, TracingFileConfigExt as _};
+
+        use super::FileLayer;
+
+        let layer = r"
+quiet = 0
+verbose = 3
+
+[layer]
+with_target = true
+with_thread_ids = false
+with_thread_names = false
+with_line_number = false
+with_level = true
+";
+        let defaults: FileLayer = Config::builder()
+            .add_source(File::from_str(layer, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(defaults.format(), LogFormat::Compact);
+        assert_eq!(defaults.rotation(), LogRotation::Daily);
+        assert!(defaults.max_size().is_none());
+        assert!(defaults.max_files().is_none());
+
+        let options = format!(
+            r#"
+format = "json"
+rotation = "hourly"
+max_size = 1048576
+max_files = 5
+{layer}"#
+        );
+        let file: FileLayer = Config::builder()
+            .add_source(File::from_str(&options, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(file.format(), LogFormat::Json);
+        assert_eq!(file.rotation(), LogRotation::Hourly);
+        assert_eq!(file.max_size(), NonZeroU64::new(1_048_576));
+        assert_eq!(file.max_files(), NonZeroUsize::new(5));
+    }
 }
@@ -0,0 +1,131 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use anyhow::Result;
+
+/// The WebSocket upgrade header a bartoc uses to offer batched and compressed output records.
+///
+/// The value is a comma separated list of `batch` and `zstd`. `bartos` echoes the subset it
+/// accepts on the upgrade response; anything not echoed is not used on that connection.
+pub const ENCODING_HEADER: &str = "X-Barto-Encoding";
+
+/// Upper bound on a decompressed worker frame, guarding `bartos` against compression bombs.
+pub const MAX_DECOMPRESSED_LEN: usize = 16 * 1024 * 1024;
+
+const BATCH: &str = "batch";
+const ZSTD: &str = "zstd";
+const ZSTD_LEVEL: i32 = 3;
+
+/// The encodings in use on a bartoc → bartos connection
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LinkEncoding {
+    /// Output records are sent as per-command batches
+    pub batch: bool,
+    /// Batches are compressed with zstd
+    pub zstd: bool,
+}
+
+impl LinkEncoding {
+    /// Parse an [`ENCODING_HEADER`] value, ignoring unknown encodings. zstd is only used
+    /// together with batching.
+    #[must_use]
+    pub fn parse(value: &str) -> Self {
+        let mut encoding = Self::default();
+        for token in value.split(',').map(str::trim) {
+            if token.eq_ignore_ascii_case(BATCH) {
+                encoding.batch = true;
+            } else if token.eq_ignore_ascii_case(ZSTD) {
+                encoding.zstd = true;
+            }
+        }
+        encoding.zstd &= encoding.batch;
+        encoding
+    }
+
+    /// The [`ENCODING_HEADER`] value for this encoding, or `None` if nothing is enabled.
+    #[must_use]
+    pub fn header_value(self) -> Option<String> {
+        match (self.batch, self.zstd) {
+            (true, true) => Some(format!("{BATCH}, {ZSTD}")),
+            (true, false) => Some(BATCH.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Compress a worker frame with zstd.
+///
+/// # Errors
+/// Returns an error if zstd fails to compress the data.
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    Ok(zstd::bulk::compress(data, ZSTD_LEVEL)?)
+}
+
+/// Decompress a zstd worker frame, refusing frames larger than [`MAX_DECOMPRESSED_LEN`].
+///
+/// # Errors
+/// Returns an error if the data is not valid zstd or decompresses past the limit.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    Ok(zstd::bulk::decompress(data, MAX_DECOMPRESSED_LEN)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LinkEncoding, compress, decompress};
+
+    #[test]
+    fn parse_header_values() {
+        assert_eq!(
+            LinkEncoding::parse("batch, zstd"),
+            LinkEncoding {
+                batch: true,
+                zstd: true
+            }
+        );
+        assert_eq!(
+            LinkEncoding::parse("BATCH,gzip"),
+            LinkEncoding {
+                batch: true,
+                zstd: false
+            }
+        );
+        assert_eq!(LinkEncoding::parse("zstd"), LinkEncoding::default());
+        assert_eq!(LinkEncoding::parse(""), LinkEncoding::default());
+    }
+
+    #[test]
+    fn header_value_round_trips() {
+        for encoding in [
+            LinkEncoding {
+                batch: true,
+                zstd: true,
+            },
+            LinkEncoding {
+                batch: true,
+                zstd: false,
+            },
+        ] {
+            let value = encoding.header_value().unwrap();
+            assert_eq!(LinkEncoding::parse(&value), encoding);
+        }
+        assert!(LinkEncoding::default().header_value().is_none());
+    }
+
+    #[test]
+    fn compress_round_trips() {
+        let data = b"the same line over and over\n".repeat(100);
+        let compressed = compress(&data).unwrap();
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn decompress_rejects_garbage() {
+        assert!(decompress(b"not zstd").is_err());
+    }
+}
@@ -32,6 +32,9 @@ pub enum Error {
     /// No valid private keys found in the key file
     #[error("No valid private keys found in the key file")]
     NoPrivateKeys,
+    /// Unable to parse a certificate presented by a peer
+    #[error("Unable to parse the peer certificate")]
+    CertParse,
     /// No valid captures when parsing a realtime schedule
     #[error("no valid captures")]
     NoValidCaptures,
@@ -62,6 +65,27 @@ pub enum Error {
         /// The invalid update kind
         kind: String,
     },
+    /// An invalid run state was specified
+    #[error("invalid run state: '{}'", state)]
+    InvalidRunState {
+        /// The invalid run state
+        state: String,
+    },
+    /// A query time bound was neither a timestamp nor a relative age
+    #[error("invalid time bound: '{}'", .0)]
+    InvalidTimeBound(String),
+    /// An export file format was neither `jsonl`, `csv` nor `arrow`
+    #[error("invalid export format: '{}'", .0)]
+    InvalidExportFormat(String),
+    /// A record of an export file could not be read back into a row
+    #[error("invalid export record: '{}'", .0)]
+    InvalidExportRecord(String),
+    /// A trace context was not a W3C `traceparent` of a valid span
+    #[error("invalid trace parent: '{}'", .0)]
+    InvalidTraceParent(String),
+    /// A client event was neither `connect` nor `disconnect`
+    #[error("invalid client event kind: '{}'", .0)]
+    InvalidClientEventKind(String),
     /// An invalid date string was specified when parsing a realtime schedule
     #[error("invalid day of week: '{}'", .0)]
     InvalidDayOfWeek(String),
@@ -104,6 +128,12 @@ pub enum Error {
     /// Message nonce has already been seen — possible replay attack
     #[error("message nonce has already been seen")]
     MessageReplayed,
+    /// A sequenced message is too short to carry a session id and counter
+    #[error("message sequence header is invalid")]
+    SequenceInvalid,
+    /// A keyed message names a key ID that is not in the accepted key list
+    #[error("no accepted key with id {}", .0)]
+    UnknownKeyId(u32),
 }
 
 /// Converts an `anyhow::Error` into a suitable exit code or clap message for a CLI application.
@@ -0,0 +1,554 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The file formats `barto-cli export` writes and `bartos import` reads. Every format holds one
+//! [`ExportRow`] per record with the same columns, so an export can be loaded back whatever
+//! format it was written in.
+
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Formatter},
+    io::{BufRead, BufReader, Lines, Read, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Int32Array, RecordBatch, StringArray, TimestampNanosecondArray,
+};
+use arrow_ipc::{reader::StreamReader, writer::StreamWriter};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+use crate::{Error, ExportRow, OffsetDataTimeWrapper, OutputKind, UuidWrapper};
+
+/// The format of an export file
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// Comma separated values with a header row
+    Csv,
+    /// An Arrow IPC stream
+    Arrow,
+}
+
+impl ExportFormat {
+    /// The format named by the extension of `path`, if it has a known one
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::try_from(extension).ok()
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::Jsonl => write!(f, "jsonl"),
+            ExportFormat::Csv => write!(f, "csv"),
+            ExportFormat::Arrow => write!(f, "arrow"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ExportFormat {
+    type Error = Error;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            "arrow" | "arrows" | "ipc" => Ok(ExportFormat::Arrow),
+            _ => Err(Error::InvalidExportFormat(value.to_string())),
+        }
+    }
+}
+
+/// An [`ExportRow`] as written to a JSON Lines or CSV file: UUIDs as strings, times as
+/// RFC 3339 and the output kind by name. Fields added since the first export format default
+/// to empty so older exports still load.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct ExportRecord {
+    cmd_uuid: String,
+    bartoc_uuid: Option<String>,
+    bartoc_name: Option<String>,
+    schedule_name: Option<String>,
+    cmd: Option<String>,
+    started_at: Option<String>,
+    ended_at: Option<String>,
+    exit_code: Option<i32>,
+    exit_signal: Option<i32>,
+    core_dumped: Option<bool>,
+    wait_status: Option<i32>,
+    success: Option<bool>,
+    timestamp: Option<String>,
+    kind: Option<String>,
+    data: Option<String>,
+}
+
+impl ExportRecord {
+    fn from_row(row: &ExportRow) -> Result<Self> {
+        let time =
+            |at: Option<OffsetDataTimeWrapper>| at.map(|at| at.0.format(&Rfc3339)).transpose();
+        Ok(Self {
+            cmd_uuid: row.cmd_uuid().to_string(),
+            bartoc_uuid: row.bartoc_uuid().map(|uuid| uuid.to_string()),
+            bartoc_name: row.bartoc_name().clone(),
+            schedule_name: row.schedule_name().clone(),
+            cmd: row.cmd().clone(),
+            started_at: time(row.started_at())?,
+            ended_at: time(row.ended_at())?,
+            exit_code: row.exit_code(),
+            exit_signal: row.exit_signal(),
+            core_dumped: row.core_dumped(),
+            wait_status: row.wait_status(),
+            success: row.success(),
+            timestamp: time(row.timestamp())?,
+            kind: row.kind().map(|kind| kind.to_string()),
+            data: row.data().clone(),
+        })
+    }
+
+    fn into_row(self) -> Result<ExportRow> {
+        let time = |at: Option<String>| at.as_deref().map(parse_time).transpose();
+        let kind = self.kind.as_deref().map(parse_kind).transpose()?;
+        // CSV cannot tell an empty field from a missing one, so an output line always has data.
+        let data = match (kind, self.data) {
+            (Some(_), data) => Some(data.unwrap_or_default()),
+            (None, data) => data,
+        };
+        Ok(ExportRow::builder()
+            .cmd_uuid(parse_uuid(&self.cmd_uuid)?)
+            .maybe_bartoc_uuid(self.bartoc_uuid.as_deref().map(parse_uuid).transpose()?)
+            .maybe_bartoc_name(self.bartoc_name)
+            .maybe_schedule_name(self.schedule_name)
+            .maybe_cmd(self.cmd)
+            .maybe_started_at(time(self.started_at)?)
+            .maybe_ended_at(time(self.ended_at)?)
+            .maybe_exit_code(self.exit_code)
+            .maybe_exit_signal(self.exit_signal)
+            .maybe_core_dumped(self.core_dumped)
+            .maybe_wait_status(self.wait_status)
+            .maybe_success(self.success)
+            .maybe_timestamp(time(self.timestamp)?)
+            .maybe_kind(kind)
+            .maybe_data(data)
+            .build())
+    }
+}
+
+fn invalid(value: &str) -> Error {
+    Error::InvalidExportRecord(value.to_string())
+}
+
+fn parse_uuid(value: &str) -> Result<UuidWrapper> {
+    Ok(UuidWrapper(
+        Uuid::parse_str(value).map_err(|_| invalid(value))?,
+    ))
+}
+
+fn parse_time(value: &str) -> Result<OffsetDataTimeWrapper> {
+    Ok(OffsetDataTimeWrapper(
+        OffsetDateTime::parse(value, &Rfc3339).map_err(|_| invalid(value))?,
+    ))
+}
+
+fn parse_kind(value: &str) -> Result<OutputKind> {
+    match value {
+        "stdout" => Ok(OutputKind::Stdout),
+        "stderr" => Ok(OutputKind::Stderr),
+        _ => Err(invalid(value).into()),
+    }
+}
+
+fn arrow_schema() -> SchemaRef {
+    let timestamp = DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()));
+    Arc::new(Schema::new(vec![
+        Field::new("cmd_uuid", DataType::Utf8, false),
+        Field::new("bartoc_uuid", DataType::Utf8, true),
+        Field::new("bartoc_name", DataType::Utf8, true),
+        Field::new("schedule_name", DataType::Utf8, true),
+        Field::new("cmd", DataType::Utf8, true),
+        Field::new("started_at", timestamp.clone(), true),
+        Field::new("ended_at", timestamp.clone(), true),
+        Field::new("exit_code", DataType::Int32, true),
+        Field::new("exit_signal", DataType::Int32, true),
+        Field::new("core_dumped", DataType::Boolean, true),
+        Field::new("wait_status", DataType::Int32, true),
+        Field::new("success", DataType::Boolean, true),
+        Field::new("timestamp", timestamp, true),
+        Field::new("kind", DataType::Utf8, true),
+        Field::new("data", DataType::Utf8, true),
+    ]))
+}
+
+fn nanos(at: Option<OffsetDataTimeWrapper>) -> Option<i64> {
+    at.and_then(|at| i64::try_from(at.0.unix_timestamp_nanos()).ok())
+}
+
+fn to_batch(schema: &SchemaRef, rows: &[ExportRow]) -> Result<RecordBatch> {
+    let strings = |f: &dyn Fn(&ExportRow) -> Option<String>| -> ArrayRef {
+        Arc::new(rows.iter().map(f).collect::<StringArray>())
+    };
+    let times = |f: &dyn Fn(&ExportRow) -> Option<OffsetDataTimeWrapper>| -> ArrayRef {
+        Arc::new(
+            rows.iter()
+                .map(|row| nanos(f(row)))
+                .collect::<TimestampNanosecondArray>()
+                .with_timezone("UTC"),
+        )
+    };
+    let ints = |f: &dyn Fn(&ExportRow) -> Option<i32>| -> ArrayRef {
+        Arc::new(rows.iter().map(f).collect::<Int32Array>())
+    };
+    let bools = |f: &dyn Fn(&ExportRow) -> Option<bool>| -> ArrayRef {
+        Arc::new(rows.iter().map(f).collect::<BooleanArray>())
+    };
+    let columns = vec![
+        strings(&|row| Some(row.cmd_uuid().to_string())),
+        strings(&|row| row.bartoc_uuid().map(|uuid| uuid.to_string())),
+        strings(&|row| row.bartoc_name().clone()),
+        strings(&|row| row.schedule_name().clone()),
+        strings(&|row| row.cmd().clone()),
+        times(&ExportRow::started_at),
+        times(&ExportRow::ended_at),
+        ints(&ExportRow::exit_code),
+        ints(&ExportRow::exit_signal),
+        bools(&ExportRow::core_dumped),
+        ints(&ExportRow::wait_status),
+        bools(&ExportRow::success),
+        times(&ExportRow::timestamp),
+        strings(&|row| row.kind().map(|kind| kind.to_string())),
+        strings(&|row| row.data().clone()),
+    ];
+    Ok(RecordBatch::try_new(Arc::clone(schema), columns)?)
+}
+
+fn column<'a, T: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a T> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<T>())
+        .ok_or_else(|| invalid(name).into())
+}
+
+/// A column added since the first export format, which older exports do not have
+fn added_column<'a, T: Array + 'static>(
+    batch: &'a RecordBatch,
+    name: &str,
+) -> Result<Option<&'a T>> {
+    batch
+        .column_by_name(name)
+        .map(|_| column(batch, name))
+        .transpose()
+}
+
+fn from_batch(batch: &RecordBatch) -> Result<Vec<ExportRow>> {
+    let cmd_uuid = column::<StringArray>(batch, "cmd_uuid")?;
+    let bartoc_uuid = column::<StringArray>(batch, "bartoc_uuid")?;
+    let bartoc_name = column::<StringArray>(batch, "bartoc_name")?;
+    let schedule_name = column::<StringArray>(batch, "schedule_name")?;
+    let cmd = column::<StringArray>(batch, "cmd")?;
+    let started_at = column::<TimestampNanosecondArray>(batch, "started_at")?;
+    let ended_at = column::<TimestampNanosecondArray>(batch, "ended_at")?;
+    let exit_code = column::<Int32Array>(batch, "exit_code")?;
+    let exit_signal = added_column::<Int32Array>(batch, "exit_signal")?;
+    let core_dumped = added_column::<BooleanArray>(batch, "core_dumped")?;
+    let wait_status = added_column::<Int32Array>(batch, "wait_status")?;
+    let success = column::<BooleanArray>(batch, "success")?;
+    let timestamp = column::<TimestampNanosecondArray>(batch, "timestamp")?;
+    let kind = column::<StringArray>(batch, "kind")?;
+    let data = column::<StringArray>(batch, "data")?;
+
+    fn string(array: &StringArray, i: usize) -> Option<&str> {
+        array.is_valid(i).then(|| array.value(i))
+    }
+    fn int(array: Option<&Int32Array>, i: usize) -> Option<i32> {
+        array
+            .filter(|array| array.is_valid(i))
+            .map(|array| array.value(i))
+    }
+    fn boolean(array: Option<&BooleanArray>, i: usize) -> Option<bool> {
+        array
+            .filter(|array| array.is_valid(i))
+            .map(|array| array.value(i))
+    }
+    let time = |array: &TimestampNanosecondArray, i: usize| -> Result<_> {
+        array
+            .is_valid(i)
+            .then(|| {
+                OffsetDateTime::from_unix_timestamp_nanos(i128::from(array.value(i)))
+                    .map(OffsetDataTimeWrapper)
+                    .map_err(|_| invalid("timestamp").into())
+            })
+            .transpose()
+    };
+    (0..batch.num_rows())
+        .map(|i| {
+            let uuid = string(cmd_uuid, i).ok_or_else(|| invalid("cmd_uuid"))?;
+            Ok(ExportRow::builder()
+                .cmd_uuid(parse_uuid(uuid)?)
+                .maybe_bartoc_uuid(string(bartoc_uuid, i).map(parse_uuid).transpose()?)
+                .maybe_bartoc_name(string(bartoc_name, i).map(str::to_string))
+                .maybe_schedule_name(string(schedule_name, i).map(str::to_string))
+                .maybe_cmd(string(cmd, i).map(str::to_string))
+                .maybe_started_at(time(started_at, i)?)
+                .maybe_ended_at(time(ended_at, i)?)
+                .maybe_exit_code(int(Some(exit_code), i))
+                .maybe_exit_signal(int(exit_signal, i))
+                .maybe_core_dumped(boolean(core_dumped, i))
+                .maybe_wait_status(int(wait_status, i))
+                .maybe_success(boolean(Some(success), i))
+                .maybe_timestamp(time(timestamp, i)?)
+                .maybe_kind(string(kind, i).map(parse_kind).transpose()?)
+                .maybe_data(string(data, i).map(str::to_string))
+                .build())
+        })
+        .collect()
+}
+
+enum Sink<W: Write> {
+    Jsonl(W),
+    Csv(Box<csv::Writer<W>>),
+    Arrow(Box<StreamWriter<W>>, SchemaRef),
+}
+
+/// Writes [`ExportRow`]s to an export file a page at a time
+pub struct ExportWriter<W: Write> {
+    sink: Sink<W>,
+}
+
+impl<W: Write> ExportWriter<W> {
+    /// Start an export in `format`
+    ///
+    /// # Errors
+    /// * The Arrow stream header cannot be written.
+    pub fn new(format: ExportFormat, writer: W) -> Result<Self> {
+        let sink = match format {
+            ExportFormat::Jsonl => Sink::Jsonl(writer),
+            ExportFormat::Csv => Sink::Csv(Box::new(csv::Writer::from_writer(writer))),
+            ExportFormat::Arrow => {
+                let schema = arrow_schema();
+                Sink::Arrow(Box::new(StreamWriter::try_new(writer, &schema)?), schema)
+            }
+        };
+        Ok(Self { sink })
+    }
+
+    /// Write a page of rows
+    ///
+    /// # Errors
+    /// * A row cannot be encoded or the underlying writer fails.
+    pub fn write(&mut self, rows: &[ExportRow]) -> Result<()> {
+        match &mut self.sink {
+            Sink::Jsonl(writer) => {
+                for row in rows {
+                    serde_json::to_writer(&mut *writer, &ExportRecord::from_row(row)?)?;
+                    writer.write_all(b"\n")?;
+                }
+            }
+            Sink::Csv(writer) => {
+                for row in rows {
+                    writer.serialize(ExportRecord::from_row(row)?)?;
+                }
+            }
+            Sink::Arrow(writer, schema) => {
+                if !rows.is_empty() {
+                    writer.write(&to_batch(schema, rows)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish the export, writing any trailer and flushing the underlying writer
+    ///
+    /// # Errors
+    /// * The underlying writer fails.
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            Sink::Jsonl(mut writer) => writer.flush()?,
+            Sink::Csv(mut writer) => writer.flush()?,
+            Sink::Arrow(mut writer, _) => {
+                writer.finish()?;
+                writer.get_mut().flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Source<R: Read> {
+    Jsonl(Lines<BufReader<R>>),
+    Csv(csv::DeserializeRecordsIntoIter<R, ExportRecord>),
+    Arrow(Box<StreamReader<BufReader<R>>>, VecDeque<ExportRow>),
+}
+
+/// Reads the [`ExportRow`]s of an export file in the order they were written
+pub struct ExportReader<R: Read> {
+    source: Source<R>,
+}
+
+impl<R: Read> ExportReader<R> {
+    /// Open an export in `format`
+    ///
+    /// # Errors
+    /// * The Arrow stream header cannot be read.
+    pub fn new(format: ExportFormat, reader: R) -> Result<Self> {
+        let source = match format {
+            ExportFormat::Jsonl => Source::Jsonl(BufReader::new(reader).lines()),
+            ExportFormat::Csv => Source::Csv(csv::Reader::from_reader(reader).into_deserialize()),
+            ExportFormat::Arrow => Source::Arrow(
+                Box::new(StreamReader::try_new_buffered(reader, None)?),
+                VecDeque::new(),
+            ),
+        };
+        Ok(Self { source })
+    }
+}
+
+impl<R: Read> Iterator for ExportReader<R> {
+    type Item = Result<ExportRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.source {
+            Source::Jsonl(lines) => loop {
+                let line = match lines.next()? {
+                    Ok(line) => line,
+                    Err(e) => return Some(Err(e.into())),
+                };
+                if !line.trim().is_empty() {
+                    return Some(
+                        serde_json::from_str::<ExportRecord>(&line)
+                            .map_err(Into::into)
+                            .and_then(ExportRecord::into_row),
+                    );
+                }
+            },
+            Source::Csv(records) => Some(
+                records
+                    .next()?
+                    .map_err(Into::into)
+                    .and_then(ExportRecord::into_row),
+            ),
+            Source::Arrow(reader, pending) => loop {
+                if let Some(row) = pending.pop_front() {
+                    return Some(Ok(row));
+                }
+                match reader.next()? {
+                    Ok(batch) => match from_batch(&batch) {
+                        Ok(rows) => pending.extend(rows),
+                        Err(e) => return Some(Err(e)),
+                    },
+                    Err(e) => return Some(Err(e.into())),
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{ExportFormat, ExportReader, ExportWriter};
+    use crate::{ExportRow, OutputKind, UuidWrapper, utils::Mock};
+
+    fn rows() -> Vec<ExportRow> {
+        let mock = ExportRow::mock();
+        let line = |kind: OutputKind, data: &str| {
+            ExportRow::builder()
+                .cmd_uuid(mock.cmd_uuid())
+                .maybe_bartoc_uuid(mock.bartoc_uuid())
+                .maybe_bartoc_name(mock.bartoc_name().clone())
+                .maybe_schedule_name(mock.schedule_name().clone())
+                .maybe_cmd(mock.cmd().clone())
+                .maybe_started_at(mock.started_at())
+                .maybe_ended_at(mock.ended_at())
+                .maybe_exit_code(mock.exit_code())
+                .maybe_exit_signal(mock.exit_signal())
+                .maybe_core_dumped(mock.core_dumped())
+                .maybe_wait_status(mock.wait_status())
+                .maybe_success(mock.success())
+                .maybe_timestamp(mock.timestamp())
+                .kind(kind)
+                .data(data.to_string())
+                .build()
+        };
+        vec![
+            line(OutputKind::Stderr, "error: \"disk\", full"),
+            line(OutputKind::Stdout, ""),
+            ExportRow::builder().cmd_uuid(UuidWrapper::mock()).build(),
+        ]
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let rows = rows();
+        for format in [ExportFormat::Jsonl, ExportFormat::Csv, ExportFormat::Arrow] {
+            let mut buf = vec![];
+            let mut writer = ExportWriter::new(format, &mut buf).unwrap();
+            writer.write(&rows[..1]).unwrap();
+            writer.write(&[]).unwrap();
+            writer.write(&rows[1..]).unwrap();
+            writer.finish().unwrap();
+
+            let read = ExportReader::new(format, buf.as_slice())
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(read, rows, "{format}");
+        }
+    }
+
+    #[test]
+    fn exports_without_exit_signals_load() {
+        let csv = "cmd_uuid,exit_code,success,kind,data\n\
+                   67e55044-10b1-426f-9247-bb680e5fe0c8,3,false,stdout,done\n";
+        let jsonl = "{\"cmd_uuid\":\"67e55044-10b1-426f-9247-bb680e5fe0c8\",\"exit_code\":3}\n";
+        for (format, export) in [(ExportFormat::Csv, csv), (ExportFormat::Jsonl, jsonl)] {
+            let row = ExportReader::new(format, export.as_bytes())
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap();
+            assert_eq!(row.exit_code(), Some(3), "{format}");
+            assert_eq!((row.exit_signal(), row.core_dumped()), (None, None));
+        }
+    }
+
+    #[test]
+    fn invalid_records_are_errors() {
+        let bad = b"{\"cmd_uuid\":\"not a uuid\"}\n";
+        let mut reader = ExportReader::new(ExportFormat::Jsonl, &bad[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+        assert!(ExportReader::new(ExportFormat::Arrow, &bad[..]).is_err());
+    }
+
+    #[test]
+    fn formats_parse() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("/tmp/history.CSV")),
+            Some(ExportFormat::Csv)
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("history.arrow")),
+            Some(ExportFormat::Arrow)
+        );
+        assert_eq!(
+            ExportFormat::try_from("ndjson").unwrap(),
+            ExportFormat::Jsonl
+        );
+        assert!(ExportFormat::from_path(Path::new("history.parquet")).is_none());
+        assert!(ExportFormat::from_path(Path::new("history")).is_none());
+    }
+}
@@ -6,20 +6,33 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
 use hmac::{Hmac, KeyInit, Mac};
 use rand::RngExt as _;
 use sha2::Sha256;
 
-use crate::Error;
+use crate::{Error, signing::KEY_ID_LEN};
 
 type HmacSha256 = Hmac<Sha256>;
 
 /// Number of bytes in the HMAC envelope header: 8 (timestamp) + 8 (nonce) + 32 (MAC).
 pub const HMAC_HEADER_LEN: usize = 48;
 
+/// Generate a new random HMAC key (32 random bytes, base64-encoded).
+///
+/// The base64 string itself is the key, see [`parse_hmac_key`].
+#[must_use]
+pub fn generate_hmac_key() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    STANDARD.encode(bytes)
+}
+
 /// Parse an HMAC key from a plain string — the raw UTF-8 bytes become the key.
 ///
 /// HMAC-SHA256 accepts any key length; a sufficiently random string (e.g., from
@@ -35,11 +48,28 @@ pub fn parse_hmac_key(s: &str) -> Vec<u8> {
 /// The MAC covers `timestamp || nonce || payload`.
 #[must_use]
 pub fn hmac_sign(key: &[u8], payload: &[u8]) -> Vec<u8> {
+    sign_envelope(key, None, payload)
+}
+
+/// Wrap `payload` in an HMAC-SHA256 authenticated envelope that names the key it was made with.
+///
+/// Wire format: `[4-byte key id BE][8-byte timestamp_secs BE][8-byte nonce BE][32-byte MAC][payload]`.
+/// The MAC covers `key_id || timestamp || nonce || payload`, so the key ID cannot be swapped.
+#[must_use]
+pub fn hmac_sign_with_id(key: &[u8], key_id: u32, payload: &[u8]) -> Vec<u8> {
+    sign_envelope(key, Some(key_id), payload)
+}
+
+fn sign_envelope(key: &[u8], key_id: Option<u32>, payload: &[u8]) -> Vec<u8> {
     let timestamp = current_secs();
     let nonce: u64 = rand::rng().random();
-    let mac = compute_mac(key, timestamp, nonce, payload);
+    let mac = compute_mac_with_id(key, key_id, timestamp, nonce, payload);
 
-    let mut out = Vec::with_capacity(HMAC_HEADER_LEN + payload.len());
+    let id_len = key_id.map_or(0, |_| KEY_ID_LEN);
+    let mut out = Vec::with_capacity(id_len + HMAC_HEADER_LEN + payload.len());
+    if let Some(key_id) = key_id {
+        out.extend_from_slice(&key_id.to_be_bytes());
+    }
     out.extend_from_slice(&timestamp.to_be_bytes());
     out.extend_from_slice(&nonce.to_be_bytes());
     out.extend_from_slice(&mac);
@@ -59,6 +89,36 @@ pub fn hmac_verify_and_extract(
     key: &[u8],
     data: &[u8],
     window_secs: u64,
+) -> Result<(Vec<u8>, u64, u64)> {
+    verify_envelope(key, None, data, window_secs)
+}
+
+/// Verify a keyed HMAC-SHA256 envelope (see [`hmac_sign_with_id`]) against the accepted key
+/// with the embedded key ID and return `(payload, timestamp, nonce)`.
+///
+/// # Errors
+/// Returns [`Error::HmacInvalid`] if the data is too short or the MAC does not verify.
+/// Returns [`Error::UnknownKeyId`] if no accepted key has the embedded key ID.
+/// Returns [`Error::MessageExpired`] if the timestamp is outside the replay window.
+pub fn hmac_verify_and_extract_with_id(
+    keys: &BTreeMap<u32, Vec<u8>>,
+    data: &[u8],
+    window_secs: u64,
+) -> Result<(Vec<u8>, u64, u64)> {
+    if data.len() < KEY_ID_LEN + HMAC_HEADER_LEN {
+        return Err(Error::HmacInvalid.into());
+    }
+    let (id_bytes, envelope) = data.split_at(KEY_ID_LEN);
+    let key_id = u32::from_be_bytes(id_bytes.try_into().map_err(|_| Error::HmacInvalid)?);
+    let key = keys.get(&key_id).ok_or(Error::UnknownKeyId(key_id))?;
+    verify_envelope(key, Some(key_id), envelope, window_secs)
+}
+
+fn verify_envelope(
+    key: &[u8],
+    key_id: Option<u32>,
+    data: &[u8],
+    window_secs: u64,
 ) -> Result<(Vec<u8>, u64, u64)> {
     if data.len() < HMAC_HEADER_LEN {
         return Err(Error::HmacInvalid.into());
@@ -72,6 +132,9 @@ pub fn hmac_verify_and_extract(
 
     // Verify MAC first (constant-time) before revealing timestamp information.
     let mut mac = HmacSha256::new_from_slice(key).map_err(|_| Error::HmacInvalid)?;
+    if let Some(key_id) = key_id {
+        mac.update(&key_id.to_be_bytes());
+    }
     mac.update(&timestamp.to_be_bytes());
     mac.update(&nonce.to_be_bytes());
     mac.update(payload);
@@ -85,15 +148,29 @@ pub fn hmac_verify_and_extract(
     Ok((payload.to_vec(), timestamp, nonce))
 }
 
-fn current_secs() -> u64 {
+pub(crate) fn current_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs()
 }
 
+#[cfg(test)]
 fn compute_mac(key: &[u8], timestamp: u64, nonce: u64, payload: &[u8]) -> [u8; 32] {
+    compute_mac_with_id(key, None, timestamp, nonce, payload)
+}
+
+fn compute_mac_with_id(
+    key: &[u8],
+    key_id: Option<u32>,
+    timestamp: u64,
+    nonce: u64,
+    payload: &[u8],
+) -> [u8; 32] {
     let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    if let Some(key_id) = key_id {
+        mac.update(&key_id.to_be_bytes());
+    }
     mac.update(&timestamp.to_be_bytes());
     mac.update(&nonce.to_be_bytes());
     mac.update(payload);
@@ -102,7 +179,12 @@ fn compute_mac(key: &[u8], timestamp: u64, nonce: u64, payload: &[u8]) -> [u8; 3
 
 #[cfg(test)]
 mod tests {
-    use super::{HMAC_HEADER_LEN, compute_mac, hmac_sign, hmac_verify_and_extract, parse_hmac_key};
+    use std::collections::BTreeMap;
+
+    use super::{
+        HMAC_HEADER_LEN, compute_mac, generate_hmac_key, hmac_sign, hmac_sign_with_id,
+        hmac_verify_and_extract, hmac_verify_and_extract_with_id, parse_hmac_key,
+    };
     use crate::Error;
 
     fn key() -> Vec<u8> {
@@ -220,4 +302,57 @@ mod tests {
         // Collision probability ≈ 2⁻⁶⁴
         assert_ne!(n1, n2);
     }
+
+    fn keyring() -> BTreeMap<u32, Vec<u8>> {
+        BTreeMap::from([
+            (1, parse_hmac_key("old-secret")),
+            (2, parse_hmac_key("new-secret")),
+        ])
+    }
+
+    #[test]
+    fn test_sign_and_verify_with_id() {
+        let envelope = hmac_sign_with_id(&parse_hmac_key("new-secret"), 2, b"payload");
+        assert_eq!(envelope.len(), 4 + HMAC_HEADER_LEN + 7);
+        assert_eq!(&envelope[..4], &2_u32.to_be_bytes());
+        let (extracted, _ts, _nonce) =
+            hmac_verify_and_extract_with_id(&keyring(), &envelope, 60).unwrap();
+        assert_eq!(extracted, b"payload");
+    }
+
+    #[test]
+    fn test_with_id_unknown_key_id() {
+        let envelope = hmac_sign_with_id(&parse_hmac_key("new-secret"), 3, b"payload");
+        let err = hmac_verify_and_extract_with_id(&keyring(), &envelope, 60).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::UnknownKeyId(3))
+        ));
+    }
+
+    #[test]
+    fn test_with_id_wrong_key_for_id() {
+        let envelope = hmac_sign_with_id(&parse_hmac_key("new-secret"), 1, b"payload");
+        assert!(hmac_verify_and_extract_with_id(&keyring(), &envelope, 60).is_err());
+    }
+
+    #[test]
+    fn test_with_id_swapped_key_id() {
+        // Re-labelling a valid envelope with another accepted key ID breaks the MAC.
+        let mut envelope = hmac_sign_with_id(&parse_hmac_key("old-secret"), 1, b"payload");
+        envelope[..4].copy_from_slice(&2_u32.to_be_bytes());
+        assert!(hmac_verify_and_extract_with_id(&keyring(), &envelope, 60).is_err());
+    }
+
+    #[test]
+    fn test_with_id_too_short() {
+        assert!(hmac_verify_and_extract_with_id(&keyring(), &[0u8; 50], 60).is_err());
+    }
+
+    #[test]
+    fn test_generate_hmac_key() {
+        let key = generate_hmac_key();
+        assert_eq!(key.len(), 44);
+        assert_ne!(key, generate_hmac_key());
+    }
 }
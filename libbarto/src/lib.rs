@@ -23,6 +23,7 @@
 //! | [`BartoCli`] | Command messages sent from `barto-cli` to `bartos` |
 //! | [`load`] | Load a component's TOML configuration with env-var overrides |
 //! | [`init_tracing`] | Initialize file and stdout tracing layers |
+//! | [`otlp_layer`] | Export spans to an OpenTelemetry collector |
 //!
 //! # Realtime Scheduling
 //!
@@ -275,43 +276,66 @@
 #![cfg_attr(all(docsrs), feature(doc_cfg))]
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
 
+mod client_auth;
 mod config;
 mod db;
+mod encoding;
 mod error;
+mod export;
 mod header;
 mod hmac_auth;
 mod message;
 mod realtime;
 // mod schedule;
+mod sequence;
 mod signing;
 mod tls;
 mod tracing;
 mod utils;
 
+pub use self::client_auth::CLIENT_AUTH_HEADER;
+pub use self::client_auth::client_auth_headers;
+pub use self::client_auth::generate_client_token;
+pub use self::client_auth::hash_client_token;
+pub use self::client_auth::sign_client_auth;
+pub use self::client_auth::verify_client_auth;
 pub use self::config::Actix;
 pub use self::config::Bartos;
 pub use self::config::Command;
 pub use self::config::FileLayer;
+pub use self::config::KeyWithId;
 pub use self::config::Layer;
+pub use self::config::LogFormat;
+pub use self::config::LogRotation;
 pub use self::config::Mariadb;
 pub use self::config::MissedTick;
-pub use self::config::OutputTableName;
+pub use self::config::Otlp;
 pub use self::config::PathDefaults;
 pub use self::config::Schedule;
 pub use self::config::Schedules;
-pub use self::config::StatusTableName;
 pub use self::config::Tls;
 pub use self::config::Tracing;
 pub use self::config::load;
 pub use self::config::resolve_config_path;
 pub use self::db::bincode::Bincode;
+pub use self::encoding::ENCODING_HEADER;
+pub use self::encoding::LinkEncoding;
+pub use self::encoding::MAX_DECOMPRESSED_LEN;
+pub use self::encoding::compress;
+pub use self::encoding::decompress;
 pub use self::error::Error;
 pub use self::error::clap_or_error;
 pub use self::error::success;
+pub use self::export::ExportFormat;
+pub use self::export::ExportReader;
+pub use self::export::ExportWriter;
 pub use self::header::header;
 pub use self::hmac_auth::HMAC_HEADER_LEN;
+pub use self::hmac_auth::generate_hmac_key;
 pub use self::hmac_auth::hmac_sign;
+pub use self::hmac_auth::hmac_sign_with_id;
 pub use self::hmac_auth::hmac_verify_and_extract;
+pub use self::hmac_auth::hmac_verify_and_extract_with_id;
 pub use self::hmac_auth::parse_hmac_key;
 pub use self::message::cli::BartoCli;
 pub use self::message::cli::UpdateKind as CliUpdateKind;
@@ -319,6 +343,10 @@ pub use self::message::client::Bartoc;
 pub use self::message::client::BartocWs;
 pub use self::message::server::BartosToBartoCli;
 pub use self::message::server::BartosToBartoc;
+pub use self::message::shared::batch::OutputBatch;
+pub use self::message::shared::batch::OutputLine;
+pub use self::message::shared::export::ExportFilter;
+pub use self::message::shared::export::ExportRow;
 pub use self::message::shared::failed::FailedOutput;
 pub use self::message::shared::init::Initialize;
 pub use self::message::shared::list::ListOutput;
@@ -327,8 +355,23 @@ pub use self::message::shared::output::Data;
 pub use self::message::shared::output::Output;
 pub use self::message::shared::output::OutputKind;
 pub use self::message::shared::output::Status;
+pub use self::message::shared::output::describe_exit;
+pub use self::message::shared::presence::ClientEvent;
+pub use self::message::shared::presence::ClientEventFilter;
+pub use self::message::shared::presence::ClientEventKind;
+pub use self::message::shared::query::QueryFilter;
+pub use self::message::shared::query::QueryRow;
+pub use self::message::shared::query::RunState;
+pub use self::message::shared::query::parse_time_bound;
+pub use self::message::shared::run::RunStart;
+pub use self::message::shared::run::TriggerKind;
+pub use self::message::shared::search::SearchFilter;
+pub use self::message::shared::search::SearchHit;
+pub use self::message::shared::search::search_words;
 pub use self::message::shared::sys::BartocInfo;
 pub use self::message::shared::sys::ClientData;
+pub use self::message::shared::tail::TailFilter;
+pub use self::message::shared::trace::TraceParent;
 pub use self::message::shared::update::Garuda;
 pub use self::message::shared::update::Pacman;
 pub use self::message::shared::update::UpdateKind;
@@ -350,20 +393,33 @@ pub use self::realtime::ymd::day::DayOfMonth;
 pub use self::realtime::ymd::month::Month;
 pub use self::realtime::ymd::month::MonthOfYear;
 pub use self::realtime::ymd::year::Year;
+pub use self::sequence::SEQUENCE_HEADER_LEN;
+pub use self::sequence::SESSION_HEADER;
+pub use self::sequence::sequence_unwrap;
+pub use self::sequence::sequence_wrap;
+pub use self::signing::KEY_ID_LEN;
 pub use self::signing::SigningKey;
 pub use self::signing::VerifyingKey;
+pub use self::signing::generate_signing_key;
 pub use self::signing::key_fingerprint;
 pub use self::signing::parse_signing_key;
 pub use self::signing::parse_verifying_key;
 pub use self::signing::public_key_b64;
 pub use self::signing::sign_payload;
+pub use self::signing::sign_payload_with_id;
+pub use self::signing::signing_key_b64;
 pub use self::signing::verify_and_extract;
+pub use self::signing::verify_and_extract_with_id;
 pub use self::tls::TlsConfig;
+pub use self::tls::cert_identities;
 pub use self::tls::load_client_cert_and_key;
 pub use self::tls::load_pinned_root_store;
 pub use self::tls::load_tls_config;
+pub use self::tracing::OtlpGuard;
 pub use self::tracing::TracingConfigExt;
+pub use self::tracing::TracingFileConfigExt;
 pub use self::tracing::init_tracing;
+pub use self::tracing::otlp_layer;
 pub use self::utils::clean_output_string;
 pub use self::utils::midnight;
 pub use self::utils::parse_ts_ping;
@@ -14,6 +14,8 @@ use bincode_next::{
     error::{AllowedEnumVariants, DecodeError, EncodeError},
 };
 
+use crate::{ClientEventFilter, ExportFilter, QueryFilter, SearchFilter, TailFilter};
+
 /// Messages from barto-cli to bartos
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum BartoCli {
@@ -33,10 +35,10 @@ pub enum BartoCli {
     Cleanup,
     /// The currently connected clients
     Clients,
-    /// A query to run on bartos
+    /// A request for the runs matching a filter
     Query {
-        /// The query to run on bartos
-        query: String,
+        /// The filter the runs must match
+        filter: QueryFilter,
     },
     /// A request to list the output for a given command
     List {
@@ -59,6 +61,43 @@ pub enum BartoCli {
     },
     /// A request to list the running versions of all connected clients
     ClientVersions,
+    /// Enroll a client in the bartos client registry (admin only)
+    Enroll {
+        /// The name of the client to enroll
+        name: String,
+        /// The base64 Ed25519 public key to enroll, or `None` to have bartos issue a token
+        public_key: Option<String>,
+    },
+    /// Revoke the credential of a client in the bartos client registry (admin only)
+    Revoke {
+        /// The name of the client to revoke
+        name: String,
+    },
+    /// A raw SQL query to run on bartos in a read-only transaction (raw query clients only)
+    RawQuery {
+        /// The query to run on bartos
+        query: String,
+    },
+    /// A full-text search of the stored output
+    Search {
+        /// The words to search for and the filters to apply
+        filter: SearchFilter,
+    },
+    /// A page of the stored job history to export
+    Export {
+        /// The runs to export
+        filter: ExportFilter,
+    },
+    /// The recorded connects and disconnects of the bartoc clients
+    ClientHistory {
+        /// The events to return
+        filter: ClientEventFilter,
+    },
+    /// Stream the records of matching runs as they reach bartos, until the connection closes
+    Subscribe {
+        /// The runs to stream
+        filter: TailFilter,
+    },
 }
 
 impl<Context> Decode<Context> for BartoCli {
@@ -77,8 +116,8 @@ impl<Context> Decode<Context> for BartoCli {
             2 => Ok(BartoCli::Cleanup),
             3 => Ok(BartoCli::Clients),
             4 => {
-                let query: String = Decode::decode(decoder)?;
-                Ok(BartoCli::Query { query })
+                let filter: QueryFilter = Decode::decode(decoder)?;
+                Ok(BartoCli::Query { filter })
             }
             5 => {
                 let name: String = Decode::decode(decoder)?;
@@ -95,9 +134,38 @@ impl<Context> Decode<Context> for BartoCli {
                 Ok(BartoCli::Cmd { cmd_name })
             }
             9 => Ok(BartoCli::ClientVersions),
+            10 => {
+                let name: String = Decode::decode(decoder)?;
+                let public_key: Option<String> = Decode::decode(decoder)?;
+                Ok(BartoCli::Enroll { name, public_key })
+            }
+            11 => {
+                let name: String = Decode::decode(decoder)?;
+                Ok(BartoCli::Revoke { name })
+            }
+            12 => {
+                let query: String = Decode::decode(decoder)?;
+                Ok(BartoCli::RawQuery { query })
+            }
+            13 => {
+                let filter: SearchFilter = Decode::decode(decoder)?;
+                Ok(BartoCli::Search { filter })
+            }
+            14 => {
+                let filter: ExportFilter = Decode::decode(decoder)?;
+                Ok(BartoCli::Export { filter })
+            }
+            15 => {
+                let filter: ClientEventFilter = Decode::decode(decoder)?;
+                Ok(BartoCli::ClientHistory { filter })
+            }
+            16 => {
+                let filter: TailFilter = Decode::decode(decoder)?;
+                Ok(BartoCli::Subscribe { filter })
+            }
             _ => Err(DecodeError::UnexpectedVariant {
                 type_name: "BartoCli",
-                allowed: &AllowedEnumVariants::Range { min: 0, max: 9 },
+                allowed: &AllowedEnumVariants::Range { min: 0, max: 16 },
                 found: variant,
             }),
         }
@@ -122,8 +190,8 @@ impl<'de, Context> BorrowDecode<'de, Context> for BartoCli {
             2 => Ok(BartoCli::Cleanup),
             3 => Ok(BartoCli::Clients),
             4 => {
-                let query: String = BorrowDecode::borrow_decode(decoder)?;
-                Ok(BartoCli::Query { query })
+                let filter: QueryFilter = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::Query { filter })
             }
             5 => {
                 let name: String = BorrowDecode::borrow_decode(decoder)?;
@@ -140,9 +208,38 @@ impl<'de, Context> BorrowDecode<'de, Context> for BartoCli {
                 Ok(BartoCli::Cmd { cmd_name })
             }
             9 => Ok(BartoCli::ClientVersions),
+            10 => {
+                let name: String = BorrowDecode::borrow_decode(decoder)?;
+                let public_key: Option<String> = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::Enroll { name, public_key })
+            }
+            11 => {
+                let name: String = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::Revoke { name })
+            }
+            12 => {
+                let query: String = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::RawQuery { query })
+            }
+            13 => {
+                let filter: SearchFilter = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::Search { filter })
+            }
+            14 => {
+                let filter: ExportFilter = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::Export { filter })
+            }
+            15 => {
+                let filter: ClientEventFilter = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::ClientHistory { filter })
+            }
+            16 => {
+                let filter: TailFilter = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartoCli::Subscribe { filter })
+            }
             _ => Err(DecodeError::UnexpectedVariant {
                 type_name: "BartoCli",
-                allowed: &AllowedEnumVariants::Range { min: 0, max: 9 },
+                allowed: &AllowedEnumVariants::Range { min: 0, max: 16 },
                 found: variant,
             }),
         }
@@ -163,9 +260,9 @@ impl Encode for BartoCli {
             }
             BartoCli::Cleanup => 2u32.encode(encoder),
             BartoCli::Clients => 3u32.encode(encoder),
-            BartoCli::Query { query } => {
+            BartoCli::Query { filter } => {
                 4u32.encode(encoder)?;
-                query.encode(encoder)
+                filter.encode(encoder)
             }
             BartoCli::List { name, cmd_name } => {
                 5u32.encode(encoder)?;
@@ -182,6 +279,35 @@ impl Encode for BartoCli {
                 cmd_name.encode(encoder)
             }
             BartoCli::ClientVersions => 9u32.encode(encoder),
+            BartoCli::Enroll { name, public_key } => {
+                10u32.encode(encoder)?;
+                name.encode(encoder)?;
+                public_key.encode(encoder)
+            }
+            BartoCli::Revoke { name } => {
+                11u32.encode(encoder)?;
+                name.encode(encoder)
+            }
+            BartoCli::RawQuery { query } => {
+                12u32.encode(encoder)?;
+                query.encode(encoder)
+            }
+            BartoCli::Search { filter } => {
+                13u32.encode(encoder)?;
+                filter.encode(encoder)
+            }
+            BartoCli::Export { filter } => {
+                14u32.encode(encoder)?;
+                filter.encode(encoder)
+            }
+            BartoCli::ClientHistory { filter } => {
+                15u32.encode(encoder)?;
+                filter.encode(encoder)
+            }
+            BartoCli::Subscribe { filter } => {
+                16u32.encode(encoder)?;
+                filter.encode(encoder)
+            }
         }
     }
 }
@@ -272,6 +398,7 @@ mod test {
     };
 
     use super::{BartoCli, UpdateKind};
+    use crate::{ClientEventFilter, ExportFilter, QueryFilter, RunState, SearchFilter, TailFilter};
 
     #[test]
     fn test_update_kind_try_from() {
@@ -315,7 +442,40 @@ mod test {
             BartoCli::Cleanup,
             BartoCli::Clients,
             BartoCli::Query {
-                query: "SELECT * FROM test".to_string(),
+                filter: QueryFilter::default(),
+            },
+            BartoCli::Query {
+                filter: QueryFilter::builder()
+                    .client("test_client".to_string())
+                    .state(RunState::Failed)
+                    .limit(10)
+                    .build(),
+            },
+            BartoCli::RawQuery {
+                query: "SELECT * FROM runs".to_string(),
+            },
+            BartoCli::Search {
+                filter: SearchFilter::builder()
+                    .text("failed to commit")
+                    .client("test_client".to_string())
+                    .context(3)
+                    .build(),
+            },
+            BartoCli::Export {
+                filter: ExportFilter::builder()
+                    .schedule("update".to_string())
+                    .limit(100)
+                    .offset(200)
+                    .build(),
+            },
+            BartoCli::ClientHistory {
+                filter: ClientEventFilter::builder()
+                    .client("test_client".to_string())
+                    .limit(20)
+                    .build(),
+            },
+            BartoCli::Subscribe {
+                filter: TailFilter::builder().schedule("backup".to_string()).build(),
             },
             BartoCli::List {
                 name: "test".to_string(),
@@ -329,6 +489,17 @@ mod test {
                 cmd_name: "status".to_string(),
             },
             BartoCli::ClientVersions,
+            BartoCli::Enroll {
+                name: "test_client".to_string(),
+                public_key: None,
+            },
+            BartoCli::Enroll {
+                name: "test_client".to_string(),
+                public_key: Some("cHVibGljLWtleQ==".to_string()),
+            },
+            BartoCli::Revoke {
+                name: "test_client".to_string(),
+            },
         ];
 
         for command in &commands {
@@ -13,7 +13,7 @@ use bincode_next::{
     error::{AllowedEnumVariants, DecodeError, EncodeError},
 };
 
-use crate::{BartocInfo, Data};
+use crate::{BartocInfo, Data, OutputBatch};
 
 /// A supported websocket message from bartoc to bartos
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -104,6 +104,10 @@ pub enum Bartoc {
     Record(Data),
     /// barto client info
     ClientInfo(BartocInfo),
+    /// A batch of output records from one command
+    RecordBatch(OutputBatch),
+    /// A zstd-compressed, encoded `Bartoc` message
+    Compressed(Vec<u8>),
 }
 
 impl<Context> Decode<Context> for Bartoc {
@@ -118,9 +122,17 @@ impl<Context> Decode<Context> for Bartoc {
                 let client_info: BartocInfo = Decode::decode(decoder)?;
                 Ok(Bartoc::ClientInfo(client_info))
             }
+            2 => {
+                let batch: OutputBatch = Decode::decode(decoder)?;
+                Ok(Bartoc::RecordBatch(batch))
+            }
+            3 => {
+                let compressed: Vec<u8> = Decode::decode(decoder)?;
+                Ok(Bartoc::Compressed(compressed))
+            }
             _ => Err(DecodeError::UnexpectedVariant {
                 type_name: "Bartoc",
-                allowed: &AllowedEnumVariants::Range { min: 0, max: 1 },
+                allowed: &AllowedEnumVariants::Range { min: 0, max: 3 },
                 found: variant,
             }),
         }
@@ -141,9 +153,17 @@ impl<'de, Context> BorrowDecode<'de, Context> for Bartoc {
                 let client_info: BartocInfo = BorrowDecode::borrow_decode(decoder)?;
                 Ok(Bartoc::ClientInfo(client_info))
             }
+            2 => {
+                let batch: OutputBatch = BorrowDecode::borrow_decode(decoder)?;
+                Ok(Bartoc::RecordBatch(batch))
+            }
+            3 => {
+                let compressed: Vec<u8> = BorrowDecode::borrow_decode(decoder)?;
+                Ok(Bartoc::Compressed(compressed))
+            }
             _ => Err(DecodeError::UnexpectedVariant {
                 type_name: "Bartoc",
-                allowed: &AllowedEnumVariants::Range { min: 0, max: 1 },
+                allowed: &AllowedEnumVariants::Range { min: 0, max: 3 },
                 found: variant,
             }),
         }
@@ -161,6 +181,14 @@ impl Encode for Bartoc {
                 1u32.encode(encoder)?;
                 client_info.encode(encoder)
             }
+            Bartoc::RecordBatch(batch) => {
+                2u32.encode(encoder)?;
+                batch.encode(encoder)
+            }
+            Bartoc::Compressed(compressed) => {
+                3u32.encode(encoder)?;
+                compressed.encode(encoder)
+            }
         }
     }
 }
@@ -169,7 +197,7 @@ impl Encode for Bartoc {
 mod tests {
     use super::{Bartoc, BartocWs};
 
-    use crate::{BartocInfo, Data, Output, utils::Mock as _};
+    use crate::{BartocInfo, Data, Output, OutputBatch, utils::Mock as _};
     use bincode_next::{
         borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
     };
@@ -252,11 +280,38 @@ mod tests {
         assert_eq!(original, borrow_decoded);
     }
 
+    #[test]
+    fn test_bartoc_record_batch_encode_decode() {
+        let batch = OutputBatch::from_outputs(vec![Output::mock(), Output::mock()], 1024);
+        for batch in batch {
+            let original = Bartoc::RecordBatch(batch);
+            let encoded = encode_to_vec(&original, standard()).unwrap();
+            let (decoded, _): (Bartoc, usize) = decode_from_slice(&encoded, standard()).unwrap();
+            let (borrow_decoded, _): (Bartoc, usize) =
+                borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrow_decoded);
+        }
+    }
+
+    #[test]
+    fn test_bartoc_compressed_encode_decode() {
+        let original = Bartoc::Compressed(vec![1, 2, 3]);
+        let encoded = encode_to_vec(&original, standard()).unwrap();
+        let (decoded, _): (Bartoc, usize) = decode_from_slice(&encoded, standard()).unwrap();
+        let (borrow_decoded, _): (Bartoc, usize) =
+            borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrow_decoded);
+    }
+
     #[test]
     fn test_bartoc_bad_variant_decode() {
-        // Encode a bad variant (2) manually
+        // Encode a bad variant (4) manually
         let mut encoded = Vec::new();
-        encoded.extend_from_slice(&2u32.to_le_bytes()); // Invalid variant
+        encoded.extend_from_slice(&4u32.to_le_bytes()); // Invalid variant
 
         let result: Result<(Bartoc, usize), _> = decode_from_slice(&encoded, standard());
         assert!(result.is_err());
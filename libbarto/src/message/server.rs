@@ -18,7 +18,8 @@ use bincode_next::{
 use vergen_pretty::PrettyExt;
 
 use crate::{
-    FailedOutput, Initialize, UpdateKind, UuidWrapper,
+    ClientEvent, Data, ExportRow, FailedOutput, Initialize, QueryRow, SearchHit, UpdateKind,
+    UuidWrapper,
     message::shared::{list::ListOutput, sys::ClientData},
 };
 
@@ -90,12 +91,12 @@ pub enum BartosToBartoCli {
     InfoJson(String),
     /// Updates about a named bartoc client
     Updates(UpdateKind),
-    /// Result of a cleanup operation: (output rows, exit-status rows, clients signaled)
+    /// Result of a cleanup operation: (output lines, exit-status rows, clients signaled)
     Cleanup((u64, u64, usize)),
     /// Current connected clients
     Clients(HashMap<UuidWrapper, ClientData>),
-    /// Result of a query operation
-    Query(BTreeMap<usize, BTreeMap<String, String>>),
+    /// The runs matching a query filter
+    Query(Vec<QueryRow>),
     /// Result of a list operation
     List(Vec<ListOutput>),
     /// Result of a failed command operation request
@@ -106,6 +107,24 @@ pub enum BartosToBartoCli {
     Cmd(BTreeMap<String, Vec<ListOutput>>),
     /// Running versions of all connected clients (name → version)
     ClientVersions(BTreeMap<String, String>),
+    /// Result of an enroll operation: (client name, issued token when enrolled without a key)
+    Enroll((String, Option<String>)),
+    /// Result of a revoke operation: (client name, whether an active credential was revoked)
+    Revoke((String, bool)),
+    /// The request was refused, with the reason
+    Denied(String),
+    /// Result of a raw query: each row by index, as column name → rendered value
+    RawQuery(BTreeMap<usize, BTreeMap<String, String>>),
+    /// Result of a full-text search: the matching lines, newest first
+    Search(Vec<SearchHit>),
+    /// A page of exported job history: each output line with its run, oldest run first
+    Export(Vec<ExportRow>),
+    /// The recorded connects and disconnects of the bartoc clients, newest first
+    ClientHistory(Vec<ClientEvent>),
+    /// A `Subscribe` request was accepted, and matching records follow as they arrive
+    Subscribed,
+    /// A record of a subscribed run, as it reached bartos
+    Tail(Data),
 }
 
 impl<Context> Decode<Context> for BartosToBartoCli {
@@ -133,8 +152,7 @@ impl<Context> Decode<Context> for BartosToBartoCli {
                 Ok(BartosToBartoCli::Clients(clients_data))
             }
             5 => {
-                let query_data: BTreeMap<usize, BTreeMap<String, String>> =
-                    Decode::decode(decoder)?;
+                let query_data: Vec<QueryRow> = Decode::decode(decoder)?;
                 Ok(BartosToBartoCli::Query(query_data))
             }
             6 => {
@@ -157,9 +175,43 @@ impl<Context> Decode<Context> for BartosToBartoCli {
                 let versions_data: BTreeMap<String, String> = Decode::decode(decoder)?;
                 Ok(BartosToBartoCli::ClientVersions(versions_data))
             }
+            11 => {
+                let enroll_data: (String, Option<String>) = Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::Enroll(enroll_data))
+            }
+            12 => {
+                let revoke_data: (String, bool) = Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::Revoke(revoke_data))
+            }
+            13 => {
+                let reason: String = Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::Denied(reason))
+            }
+            14 => {
+                let raw_query_data: BTreeMap<usize, BTreeMap<String, String>> =
+                    Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::RawQuery(raw_query_data))
+            }
+            15 => {
+                let search_data: Vec<SearchHit> = Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::Search(search_data))
+            }
+            16 => {
+                let export_data: Vec<ExportRow> = Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::Export(export_data))
+            }
+            17 => {
+                let history_data: Vec<ClientEvent> = Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::ClientHistory(history_data))
+            }
+            18 => Ok(BartosToBartoCli::Subscribed),
+            19 => {
+                let tail_data: Data = Decode::decode(decoder)?;
+                Ok(BartosToBartoCli::Tail(tail_data))
+            }
             _ => Err(DecodeError::UnexpectedVariant {
                 type_name: "BartosToBartoCli",
-                allowed: &bincode_next::error::AllowedEnumVariants::Range { min: 0, max: 10 },
+                allowed: &bincode_next::error::AllowedEnumVariants::Range { min: 0, max: 19 },
                 found: variant,
             }),
         }
@@ -194,8 +246,7 @@ impl<'de, Context> BorrowDecode<'de, Context> for BartosToBartoCli {
                 Ok(BartosToBartoCli::Clients(clients_data))
             }
             5 => {
-                let query_data: BTreeMap<usize, BTreeMap<String, String>> =
-                    BorrowDecode::borrow_decode(decoder)?;
+                let query_data: Vec<QueryRow> = BorrowDecode::borrow_decode(decoder)?;
                 Ok(BartosToBartoCli::Query(query_data))
             }
             6 => {
@@ -219,9 +270,43 @@ impl<'de, Context> BorrowDecode<'de, Context> for BartosToBartoCli {
                 let versions_data: BTreeMap<String, String> = BorrowDecode::borrow_decode(decoder)?;
                 Ok(BartosToBartoCli::ClientVersions(versions_data))
             }
+            11 => {
+                let enroll_data: (String, Option<String>) = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::Enroll(enroll_data))
+            }
+            12 => {
+                let revoke_data: (String, bool) = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::Revoke(revoke_data))
+            }
+            13 => {
+                let reason: String = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::Denied(reason))
+            }
+            14 => {
+                let raw_query_data: BTreeMap<usize, BTreeMap<String, String>> =
+                    BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::RawQuery(raw_query_data))
+            }
+            15 => {
+                let search_data: Vec<SearchHit> = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::Search(search_data))
+            }
+            16 => {
+                let export_data: Vec<ExportRow> = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::Export(export_data))
+            }
+            17 => {
+                let history_data: Vec<ClientEvent> = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::ClientHistory(history_data))
+            }
+            18 => Ok(BartosToBartoCli::Subscribed),
+            19 => {
+                let tail_data: Data = BorrowDecode::borrow_decode(decoder)?;
+                Ok(BartosToBartoCli::Tail(tail_data))
+            }
             _ => Err(DecodeError::UnexpectedVariant {
                 type_name: "BartosToBartoCli",
-                allowed: &bincode_next::error::AllowedEnumVariants::Range { min: 0, max: 10 },
+                allowed: &bincode_next::error::AllowedEnumVariants::Range { min: 0, max: 19 },
                 found: variant,
             }),
         }
@@ -275,6 +360,39 @@ impl Encode for BartosToBartoCli {
                 10u32.encode(encoder)?;
                 versions_data.encode(encoder)
             }
+            BartosToBartoCli::Enroll(enroll_data) => {
+                11u32.encode(encoder)?;
+                enroll_data.encode(encoder)
+            }
+            BartosToBartoCli::Revoke(revoke_data) => {
+                12u32.encode(encoder)?;
+                revoke_data.encode(encoder)
+            }
+            BartosToBartoCli::Denied(reason) => {
+                13u32.encode(encoder)?;
+                reason.encode(encoder)
+            }
+            BartosToBartoCli::RawQuery(raw_query_data) => {
+                14u32.encode(encoder)?;
+                raw_query_data.encode(encoder)
+            }
+            BartosToBartoCli::Search(search_data) => {
+                15u32.encode(encoder)?;
+                search_data.encode(encoder)
+            }
+            BartosToBartoCli::Export(export_data) => {
+                16u32.encode(encoder)?;
+                export_data.encode(encoder)
+            }
+            BartosToBartoCli::ClientHistory(history_data) => {
+                17u32.encode(encoder)?;
+                history_data.encode(encoder)
+            }
+            BartosToBartoCli::Subscribed => 18u32.encode(encoder),
+            BartosToBartoCli::Tail(tail_data) => {
+                19u32.encode(encoder)?;
+                tail_data.encode(encoder)
+            }
         }
     }
 }
@@ -285,8 +403,13 @@ mod tests {
 
     use super::{BartosToBartoCli, BartosToBartoc};
 
+    use crate::ClientEvent;
+    use crate::Data;
+    use crate::ExportRow;
     use crate::FailedOutput;
     use crate::Initialize;
+    use crate::QueryRow;
+    use crate::SearchHit;
     use crate::UpdateKind;
     use crate::utils::Mock as _;
     use bincode_next::{borrow_decode_from_slice, decode_from_slice};
@@ -368,7 +491,50 @@ mod tests {
 
     #[test]
     fn test_bartos_to_bartocli_query_roundtrip() {
-        let original = BartosToBartoCli::Query(BTreeMap::new());
+        let original = BartosToBartoCli::Query(vec![QueryRow::mock()]);
+
+        let encoded = encode_to_vec(&original, standard()).unwrap();
+        let (decoded, _): (BartosToBartoCli, usize) =
+            decode_from_slice(&encoded, standard()).unwrap();
+        let (borrowed_decoded, _): (BartosToBartoCli, usize) =
+            borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrowed_decoded);
+    }
+
+    #[test]
+    fn test_bartos_to_bartocli_raw_query_roundtrip() {
+        let row = BTreeMap::from([("count".to_string(), "3".to_string())]);
+        let original = BartosToBartoCli::RawQuery(BTreeMap::from([(0, row)]));
+
+        let encoded = encode_to_vec(&original, standard()).unwrap();
+        let (decoded, _): (BartosToBartoCli, usize) =
+            decode_from_slice(&encoded, standard()).unwrap();
+        let (borrowed_decoded, _): (BartosToBartoCli, usize) =
+            borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrowed_decoded);
+    }
+
+    #[test]
+    fn test_bartos_to_bartocli_search_roundtrip() {
+        let original = BartosToBartoCli::Search(vec![SearchHit::mock()]);
+
+        let encoded = encode_to_vec(&original, standard()).unwrap();
+        let (decoded, _): (BartosToBartoCli, usize) =
+            decode_from_slice(&encoded, standard()).unwrap();
+        let (borrowed_decoded, _): (BartosToBartoCli, usize) =
+            borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrowed_decoded);
+    }
+
+    #[test]
+    fn test_bartos_to_bartocli_export_roundtrip() {
+        let original = BartosToBartoCli::Export(vec![ExportRow::mock()]);
 
         let encoded = encode_to_vec(&original, standard()).unwrap();
         let (decoded, _): (BartosToBartoCli, usize) =
@@ -380,6 +546,38 @@ mod tests {
         assert_eq!(original, borrowed_decoded);
     }
 
+    #[test]
+    fn test_bartos_to_bartocli_client_history_roundtrip() {
+        let original = BartosToBartoCli::ClientHistory(vec![ClientEvent::mock()]);
+
+        let encoded = encode_to_vec(&original, standard()).unwrap();
+        let (decoded, _): (BartosToBartoCli, usize) =
+            decode_from_slice(&encoded, standard()).unwrap();
+        let (borrowed_decoded, _): (BartosToBartoCli, usize) =
+            borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrowed_decoded);
+    }
+
+    #[test]
+    fn test_bartos_to_bartocli_tail_roundtrip() {
+        for original in [
+            BartosToBartoCli::Subscribed,
+            BartosToBartoCli::Tail(Data::Output(crate::Output::mock())),
+            BartosToBartoCli::Tail(Data::Started(crate::RunStart::mock())),
+        ] {
+            let encoded = encode_to_vec(&original, standard()).unwrap();
+            let (decoded, _): (BartosToBartoCli, usize) =
+                decode_from_slice(&encoded, standard()).unwrap();
+            let (borrowed_decoded, _): (BartosToBartoCli, usize) =
+                borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed_decoded);
+        }
+    }
+
     #[test]
     fn test_bartos_to_bartocli_list_roundtrip() {
         let original = BartosToBartoCli::List(Vec::new());
@@ -511,4 +709,25 @@ mod tests {
         assert_eq!(original, decoded);
         assert_eq!(original, borrowed_decoded);
     }
+
+    #[test]
+    fn test_bartos_to_bartocli_enroll_revoke_denied_roundtrip() {
+        let messages = [
+            BartosToBartoCli::Enroll(("client-a".to_string(), Some("token".to_string()))),
+            BartosToBartoCli::Enroll(("client-b".to_string(), None)),
+            BartosToBartoCli::Revoke(("client-a".to_string(), true)),
+            BartosToBartoCli::Denied("admin permission required".to_string()),
+        ];
+
+        for original in messages {
+            let encoded = encode_to_vec(&original, standard()).unwrap();
+            let (decoded, _): (BartosToBartoCli, usize) =
+                decode_from_slice(&encoded, standard()).unwrap();
+            let (borrowed_decoded, _): (BartosToBartoCli, usize) =
+                borrow_decode_from_slice(&encoded, standard()).unwrap();
+
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed_decoded);
+        }
+    }
 }
@@ -0,0 +1,294 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use bon::Builder;
+use getset::{CopyGetters, Getters, Setters};
+
+use crate::{
+    Output, OutputKind,
+    message::shared::{odt::OffsetDataTimeWrapper, trace::TraceParent, uuid::UuidWrapper},
+};
+
+/// A single line of output inside an [`OutputBatch`]
+#[derive(Builder, Clone, CopyGetters, Debug, Eq, Getters, Hash, PartialEq)]
+pub struct OutputLine {
+    /// The timestamp of the output
+    #[get_copy = "pub"]
+    timestamp: OffsetDataTimeWrapper,
+    /// The kind of output (stdout or stderr)
+    #[get_copy = "pub"]
+    kind: OutputKind,
+    /// The output data
+    #[get = "pub"]
+    data: String,
+}
+
+impl<Context> Decode<Context> for OutputLine {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let timestamp = OffsetDataTimeWrapper::decode(decoder)?;
+        let kind = OutputKind::decode(decoder)?;
+        let data = String::decode(decoder)?;
+        Ok(OutputLine {
+            timestamp,
+            kind,
+            data,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for OutputLine {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        let timestamp = OffsetDataTimeWrapper::borrow_decode(decoder)?;
+        let kind = OutputKind::borrow_decode(decoder)?;
+        let data = String::borrow_decode(decoder)?;
+        Ok(OutputLine {
+            timestamp,
+            kind,
+            data,
+        })
+    }
+}
+
+impl Encode for OutputLine {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.timestamp, encoder)?;
+        Encode::encode(&self.kind, encoder)?;
+        Encode::encode(&self.data, encoder)?;
+        Ok(())
+    }
+}
+
+/// A batch of output lines from a single command, carrying the bartoc and command
+/// fields once rather than on every line
+#[derive(Builder, Clone, CopyGetters, Debug, Eq, Getters, Hash, PartialEq, Setters)]
+pub struct OutputBatch {
+    /// The id of the bartoc that produced the output
+    #[get_copy = "pub"]
+    bartoc_uuid: UuidWrapper,
+    /// The name of the bartoc that produced the output
+    #[get = "pub"]
+    bartoc_name: String,
+    /// The UUID of the bartoc command that produced the output
+    #[get_copy = "pub"]
+    cmd_uuid: UuidWrapper,
+    /// The name of the command that produced the output
+    #[get = "pub"]
+    cmd_name: String,
+    /// The output lines, in the order they were produced
+    #[get = "pub"]
+    lines: Vec<OutputLine>,
+    /// The trace context of the run, when its spans are exported
+    #[get_copy = "pub"]
+    #[set = "pub"]
+    trace_parent: Option<TraceParent>,
+}
+
+impl OutputBatch {
+    /// Group output records into batches per command, preserving the order of the lines
+    /// within each command. A batch is closed once its line data reaches `max_data_len`
+    /// bytes, so a chatty command is split over several batches.
+    #[must_use]
+    pub fn from_outputs(
+        outputs: impl IntoIterator<Item = Output>,
+        max_data_len: usize,
+    ) -> Vec<OutputBatch> {
+        let mut open: BTreeMap<(UuidWrapper, String), (OutputBatch, usize)> = BTreeMap::new();
+        let mut batches = vec![];
+        for output in outputs {
+            let key = (output.cmd_uuid(), output.bartoc_name().clone());
+            let (batch, data_len) = open.entry(key).or_insert_with(|| {
+                (
+                    OutputBatch {
+                        bartoc_uuid: output.bartoc_uuid(),
+                        bartoc_name: output.bartoc_name().clone(),
+                        cmd_uuid: output.cmd_uuid(),
+                        cmd_name: output.cmd_name().clone(),
+                        lines: vec![],
+                        trace_parent: None,
+                    },
+                    0,
+                )
+            });
+            *data_len += output.data().len();
+            batch.lines.push(OutputLine {
+                timestamp: output.timestamp(),
+                kind: output.kind(),
+                data: output.data().clone(),
+            });
+            if *data_len >= max_data_len {
+                let key = (output.cmd_uuid(), output.bartoc_name().clone());
+                if let Some((batch, _)) = open.remove(&key) {
+                    batches.push(batch);
+                }
+            }
+        }
+        batches.extend(open.into_values().map(|(batch, _)| batch));
+        batches
+    }
+
+    /// Expand the batch back into one output record per line
+    #[must_use]
+    pub fn into_outputs(self) -> Vec<Output> {
+        self.lines
+            .into_iter()
+            .map(|line| {
+                Output::builder()
+                    .bartoc_uuid(self.bartoc_uuid)
+                    .bartoc_name(self.bartoc_name.clone())
+                    .timestamp(line.timestamp)
+                    .cmd_uuid(self.cmd_uuid)
+                    .cmd_name(self.cmd_name.clone())
+                    .kind(line.kind)
+                    .data(line.data)
+                    .build()
+            })
+            .collect()
+    }
+}
+
+impl<Context> Decode<Context> for OutputBatch {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let bartoc_uuid = UuidWrapper::decode(decoder)?;
+        let bartoc_name = String::decode(decoder)?;
+        let cmd_uuid = UuidWrapper::decode(decoder)?;
+        let cmd_name = String::decode(decoder)?;
+        let lines = Vec::<OutputLine>::decode(decoder)?;
+        let trace_parent = Option::<TraceParent>::decode(decoder)?;
+        Ok(OutputBatch {
+            bartoc_uuid,
+            bartoc_name,
+            cmd_uuid,
+            cmd_name,
+            lines,
+            trace_parent,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for OutputBatch {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        let bartoc_uuid = UuidWrapper::borrow_decode(decoder)?;
+        let bartoc_name = String::borrow_decode(decoder)?;
+        let cmd_uuid = UuidWrapper::borrow_decode(decoder)?;
+        let cmd_name = String::borrow_decode(decoder)?;
+        let lines = Vec::<OutputLine>::borrow_decode(decoder)?;
+        let trace_parent = Option::<TraceParent>::borrow_decode(decoder)?;
+        Ok(OutputBatch {
+            bartoc_uuid,
+            bartoc_name,
+            cmd_uuid,
+            cmd_name,
+            lines,
+            trace_parent,
+        })
+    }
+}
+
+impl Encode for OutputBatch {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.bartoc_uuid, encoder)?;
+        Encode::encode(&self.bartoc_name, encoder)?;
+        Encode::encode(&self.cmd_uuid, encoder)?;
+        Encode::encode(&self.cmd_name, encoder)?;
+        Encode::encode(&self.lines, encoder)?;
+        Encode::encode(&self.trace_parent, encoder)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    use crate::{
+        OffsetDataTimeWrapper, Output, OutputKind, TraceParent, UuidWrapper, utils::Mock as _,
+    };
+
+    use super::OutputBatch;
+
+    fn output(cmd_uuid: UuidWrapper, data: &str) -> Output {
+        Output::builder()
+            .bartoc_uuid(UuidWrapper(Uuid::nil()))
+            .bartoc_name("bartoc".to_string())
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+            .cmd_uuid(cmd_uuid)
+            .cmd_name("cmd".to_string())
+            .kind(OutputKind::Stdout)
+            .data(data.to_string())
+            .build()
+    }
+
+    #[test]
+    fn batches_group_by_command_and_round_trip() {
+        let a = UuidWrapper(Uuid::new_v4());
+        let b = UuidWrapper(Uuid::new_v4());
+        let outputs = vec![output(a, "a1"), output(b, "b1"), output(a, "a2")];
+        let batches = OutputBatch::from_outputs(outputs.clone(), 1024);
+        assert_eq!(batches.len(), 2);
+        let mut round_tripped: Vec<Output> = batches
+            .into_iter()
+            .flat_map(OutputBatch::into_outputs)
+            .collect();
+        let mut expected = outputs;
+        round_tripped.sort();
+        expected.sort();
+        assert_eq!(round_tripped, expected);
+    }
+
+    #[test]
+    fn batches_preserve_line_order() {
+        let a = UuidWrapper(Uuid::new_v4());
+        let batches = OutputBatch::from_outputs(vec![output(a, "1"), output(a, "2")], 1024);
+        let data: Vec<&str> = batches[0]
+            .lines()
+            .iter()
+            .map(|l| l.data().as_str())
+            .collect();
+        assert_eq!(data, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn batches_split_at_max_data_len() {
+        let a = UuidWrapper(Uuid::new_v4());
+        let outputs = (0..5).map(|_| output(a, "0123456789"));
+        let batches = OutputBatch::from_outputs(outputs, 20);
+        let sizes: Vec<usize> = batches.iter().map(|b| b.lines().len()).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn batch_encode_decode() {
+        let a = UuidWrapper(Uuid::new_v4());
+        let mut batch = OutputBatch::from_outputs(vec![output(a, "x"), output(a, "y")], 1024)
+            .pop()
+            .unwrap();
+        let _ = batch.set_trace_parent(Some(TraceParent::mock()));
+        let encoded = encode_to_vec(&batch, standard()).unwrap();
+        let (decoded, _): (OutputBatch, _) = decode_from_slice(&encoded, standard()).unwrap();
+        let (borrow_decoded, _): (OutputBatch, _) =
+            borrow_decode_from_slice(&encoded, standard()).unwrap();
+        assert_eq!(batch, decoded);
+        assert_eq!(batch, borrow_decoded);
+    }
+}
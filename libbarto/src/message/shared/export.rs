@@ -0,0 +1,278 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+
+#[cfg(test)]
+use crate::utils::Mock;
+use crate::{OffsetDataTimeWrapper, OutputKind, UuidWrapper};
+
+/// The runs an `Export` request asks for, a page at a time. Every filter that is set must
+/// match, and the page is counted in runs so a run is never split across pages.
+#[derive(Builder, Clone, CopyGetters, Debug, Default, Eq, Getters, PartialEq)]
+pub struct ExportFilter {
+    /// Only runs on the bartoc client with this name
+    #[getset(get = "pub")]
+    client: Option<String>,
+    /// Only runs of the schedule with this name
+    #[getset(get = "pub")]
+    schedule: Option<String>,
+    /// Only runs that started at or after this time
+    #[getset(get_copy = "pub")]
+    since: Option<OffsetDataTimeWrapper>,
+    /// Only runs that started before this time
+    #[getset(get_copy = "pub")]
+    until: Option<OffsetDataTimeWrapper>,
+    /// The most runs to return, capped by `bartos`
+    #[getset(get_copy = "pub")]
+    limit: Option<u32>,
+    /// The number of matching runs to skip
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    offset: u32,
+}
+
+#[cfg(test)]
+impl Mock for ExportFilter {
+    fn mock() -> Self {
+        Self {
+            client: Some("mock_bartoc".to_string()),
+            schedule: Some("mock_cmd".to_string()),
+            since: Some(OffsetDataTimeWrapper::mock()),
+            until: None,
+            limit: Some(100),
+            offset: 200,
+        }
+    }
+}
+
+impl<Context> Decode<Context> for ExportFilter {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: Decode::decode(decoder)?,
+            schedule: Decode::decode(decoder)?,
+            since: Decode::decode(decoder)?,
+            until: Decode::decode(decoder)?,
+            limit: Decode::decode(decoder)?,
+            offset: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for ExportFilter {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: BorrowDecode::borrow_decode(decoder)?,
+            schedule: BorrowDecode::borrow_decode(decoder)?,
+            since: BorrowDecode::borrow_decode(decoder)?,
+            until: BorrowDecode::borrow_decode(decoder)?,
+            limit: BorrowDecode::borrow_decode(decoder)?,
+            offset: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for ExportFilter {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.client, encoder)?;
+        Encode::encode(&self.schedule, encoder)?;
+        Encode::encode(&self.since, encoder)?;
+        Encode::encode(&self.until, encoder)?;
+        Encode::encode(&self.limit, encoder)?;
+        Encode::encode(&self.offset, encoder)?;
+        Ok(())
+    }
+}
+
+/// One output line of an exported run with the run it belongs to, or the run itself when it
+/// has no output. This is the `ListOutput`/`FailedOutput` data with what another `bartos`
+/// needs to import the run: its UUID, its start and end and the kind of each line.
+#[derive(Builder, Clone, CopyGetters, Debug, Eq, Getters, PartialEq)]
+pub struct ExportRow {
+    /// The UUID of the run
+    #[getset(get_copy = "pub")]
+    cmd_uuid: UuidWrapper,
+    /// The id of the bartoc connection that ran the command
+    #[getset(get_copy = "pub")]
+    bartoc_uuid: Option<UuidWrapper>,
+    /// The name of the bartoc client
+    #[getset(get = "pub")]
+    bartoc_name: Option<String>,
+    /// The name of the schedule
+    #[getset(get = "pub")]
+    schedule_name: Option<String>,
+    /// The command string that was run
+    #[getset(get = "pub")]
+    cmd: Option<String>,
+    /// When the run started
+    #[getset(get_copy = "pub")]
+    started_at: Option<OffsetDataTimeWrapper>,
+    /// When the run ended
+    #[getset(get_copy = "pub")]
+    ended_at: Option<OffsetDataTimeWrapper>,
+    /// The exit code of the run
+    #[getset(get_copy = "pub")]
+    exit_code: Option<i32>,
+    /// The signal that killed the run
+    #[getset(get_copy = "pub")]
+    exit_signal: Option<i32>,
+    /// Whether the run dumped core when it was killed
+    #[getset(get_copy = "pub")]
+    core_dumped: Option<bool>,
+    /// The raw wait status of the run
+    #[getset(get_copy = "pub")]
+    wait_status: Option<i32>,
+    /// Whether the run was successful
+    #[getset(get_copy = "pub")]
+    success: Option<bool>,
+    /// When the output line was generated
+    #[getset(get_copy = "pub")]
+    timestamp: Option<OffsetDataTimeWrapper>,
+    /// The kind of the output line
+    #[getset(get_copy = "pub")]
+    kind: Option<OutputKind>,
+    /// The output line
+    #[getset(get = "pub")]
+    data: Option<String>,
+}
+
+#[cfg(test)]
+impl Mock for ExportRow {
+    fn mock() -> Self {
+        Self {
+            cmd_uuid: UuidWrapper::mock(),
+            bartoc_uuid: Some(UuidWrapper::mock()),
+            bartoc_name: Some("mock_bartoc".to_string()),
+            schedule_name: Some("mock_cmd".to_string()),
+            cmd: Some("echo mock".to_string()),
+            started_at: Some(OffsetDataTimeWrapper::mock()),
+            ended_at: Some(OffsetDataTimeWrapper::mock()),
+            exit_code: None,
+            exit_signal: Some(15),
+            core_dumped: Some(false),
+            wait_status: Some(15),
+            success: Some(false),
+            timestamp: Some(OffsetDataTimeWrapper::mock()),
+            kind: Some(OutputKind::Stderr),
+            data: Some("mock".to_string()),
+        }
+    }
+}
+
+impl<Context> Decode<Context> for ExportRow {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd_uuid: Decode::decode(decoder)?,
+            bartoc_uuid: Decode::decode(decoder)?,
+            bartoc_name: Decode::decode(decoder)?,
+            schedule_name: Decode::decode(decoder)?,
+            cmd: Decode::decode(decoder)?,
+            started_at: Decode::decode(decoder)?,
+            ended_at: Decode::decode(decoder)?,
+            exit_code: Decode::decode(decoder)?,
+            exit_signal: Decode::decode(decoder)?,
+            core_dumped: Decode::decode(decoder)?,
+            wait_status: Decode::decode(decoder)?,
+            success: Decode::decode(decoder)?,
+            timestamp: Decode::decode(decoder)?,
+            kind: Decode::decode(decoder)?,
+            data: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for ExportRow {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd_uuid: BorrowDecode::borrow_decode(decoder)?,
+            bartoc_uuid: BorrowDecode::borrow_decode(decoder)?,
+            bartoc_name: BorrowDecode::borrow_decode(decoder)?,
+            schedule_name: BorrowDecode::borrow_decode(decoder)?,
+            cmd: BorrowDecode::borrow_decode(decoder)?,
+            started_at: BorrowDecode::borrow_decode(decoder)?,
+            ended_at: BorrowDecode::borrow_decode(decoder)?,
+            exit_code: BorrowDecode::borrow_decode(decoder)?,
+            exit_signal: BorrowDecode::borrow_decode(decoder)?,
+            core_dumped: BorrowDecode::borrow_decode(decoder)?,
+            wait_status: BorrowDecode::borrow_decode(decoder)?,
+            success: BorrowDecode::borrow_decode(decoder)?,
+            timestamp: BorrowDecode::borrow_decode(decoder)?,
+            kind: BorrowDecode::borrow_decode(decoder)?,
+            data: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for ExportRow {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.cmd_uuid, encoder)?;
+        Encode::encode(&self.bartoc_uuid, encoder)?;
+        Encode::encode(&self.bartoc_name, encoder)?;
+        Encode::encode(&self.schedule_name, encoder)?;
+        Encode::encode(&self.cmd, encoder)?;
+        Encode::encode(&self.started_at, encoder)?;
+        Encode::encode(&self.ended_at, encoder)?;
+        Encode::encode(&self.exit_code, encoder)?;
+        Encode::encode(&self.exit_signal, encoder)?;
+        Encode::encode(&self.core_dumped, encoder)?;
+        Encode::encode(&self.wait_status, encoder)?;
+        Encode::encode(&self.success, encoder)?;
+        Encode::encode(&self.timestamp, encoder)?;
+        Encode::encode(&self.kind, encoder)?;
+        Encode::encode(&self.data, encoder)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+
+    use super::{ExportFilter, ExportRow};
+    use crate::{UuidWrapper, utils::Mock};
+
+    #[test]
+    fn test_export_filter_encode_decode() -> Result<()> {
+        for original in [ExportFilter::mock(), ExportFilter::default()] {
+            let encoded = encode_to_vec(&original, standard())?;
+            let (decoded, _): (ExportFilter, usize) = decode_from_slice(&encoded, standard())?;
+            let (borrowed, _): (ExportFilter, usize) =
+                borrow_decode_from_slice(&encoded, standard())?;
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_row_encode_decode() -> Result<()> {
+        let run = ExportRow::builder().cmd_uuid(UuidWrapper::mock()).build();
+        for original in [ExportRow::mock(), run] {
+            let encoded = encode_to_vec(&original, standard())?;
+            let (decoded, _): (ExportRow, usize) = decode_from_slice(&encoded, standard())?;
+            let (borrowed, _): (ExportRow, usize) = borrow_decode_from_slice(&encoded, standard())?;
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed);
+        }
+        Ok(())
+    }
+}
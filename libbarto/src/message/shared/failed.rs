@@ -34,9 +34,16 @@ pub struct FailedOutput {
     /// The data returned from the command
     #[getset(get = "pub")]
     data: Option<String>,
-    /// The exit code of the command
+    /// The exit code of the command, `None` when it was killed by a signal or is still going
     #[getset(get_copy = "pub")]
-    exit_code: u8,
+    exit_code: Option<i32>,
+    /// The signal that killed the command
+    #[getset(get_copy = "pub")]
+    exit_signal: Option<i32>,
+    /// Whether the command dumped core when it was killed
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    core_dumped: bool,
     /// Whether the command was successful
     #[getset(get_copy = "pub")]
     success: i8,
@@ -50,7 +57,9 @@ impl Mock for FailedOutput {
             bartoc_name: Some("mock_bartoc".to_string()),
             cmd_name: Some("mock_cmd".to_string()),
             data: Some("mock_data".to_string()),
-            exit_code: 1,
+            exit_code: None,
+            exit_signal: Some(9),
+            core_dumped: false,
             success: 0,
         }
     }
@@ -64,6 +73,8 @@ impl<Context> Decode<Context> for FailedOutput {
             cmd_name: Decode::decode(decoder)?,
             data: Decode::decode(decoder)?,
             exit_code: Decode::decode(decoder)?,
+            exit_signal: Decode::decode(decoder)?,
+            core_dumped: Decode::decode(decoder)?,
             success: Decode::decode(decoder)?,
         })
     }
@@ -79,6 +90,8 @@ impl<'de, Context> BorrowDecode<'de, Context> for FailedOutput {
             cmd_name: BorrowDecode::borrow_decode(decoder)?,
             data: BorrowDecode::borrow_decode(decoder)?,
             exit_code: BorrowDecode::borrow_decode(decoder)?,
+            exit_signal: BorrowDecode::borrow_decode(decoder)?,
+            core_dumped: BorrowDecode::borrow_decode(decoder)?,
             success: BorrowDecode::borrow_decode(decoder)?,
         })
     }
@@ -91,6 +104,8 @@ impl Encode for FailedOutput {
         Encode::encode(&self.cmd_name, encoder)?;
         Encode::encode(&self.data, encoder)?;
         Encode::encode(&self.exit_code, encoder)?;
+        Encode::encode(&self.exit_signal, encoder)?;
+        Encode::encode(&self.core_dumped, encoder)?;
         Encode::encode(&self.success, encoder)?;
         Ok(())
     }
@@ -26,9 +26,16 @@ pub struct ListOutput {
     /// The data returned from the command
     #[getset(get = "pub")]
     data: Option<String>,
-    /// The exit code of the command
+    /// The exit code of the command, `None` when it was killed by a signal or is still going
     #[getset(get_copy = "pub")]
-    exit_code: u8,
+    exit_code: Option<i32>,
+    /// The signal that killed the command
+    #[getset(get_copy = "pub")]
+    exit_signal: Option<i32>,
+    /// Whether the command dumped core when it was killed
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    core_dumped: bool,
     /// Whether the command was successful
     #[getset(get_copy = "pub")]
     success: i8,
@@ -40,6 +47,8 @@ impl<Context> Decode<Context> for ListOutput {
             timestamp: Decode::decode(decoder)?,
             data: Decode::decode(decoder)?,
             exit_code: Decode::decode(decoder)?,
+            exit_signal: Decode::decode(decoder)?,
+            core_dumped: Decode::decode(decoder)?,
             success: Decode::decode(decoder)?,
         })
     }
@@ -53,6 +62,8 @@ impl<'de, Context> BorrowDecode<'de, Context> for ListOutput {
             timestamp: BorrowDecode::borrow_decode(decoder)?,
             data: BorrowDecode::borrow_decode(decoder)?,
             exit_code: BorrowDecode::borrow_decode(decoder)?,
+            exit_signal: BorrowDecode::borrow_decode(decoder)?,
+            core_dumped: BorrowDecode::borrow_decode(decoder)?,
             success: BorrowDecode::borrow_decode(decoder)?,
         })
     }
@@ -63,6 +74,8 @@ impl Encode for ListOutput {
         Encode::encode(&self.timestamp, encoder)?;
         Encode::encode(&self.data, encoder)?;
         Encode::encode(&self.exit_code, encoder)?;
+        Encode::encode(&self.exit_signal, encoder)?;
+        Encode::encode(&self.core_dumped, encoder)?;
         Encode::encode(&self.success, encoder)?;
         Ok(())
     }
@@ -86,7 +99,8 @@ mod test {
         let list_output = ListOutput::builder()
             .timestamp(odtw)
             .data("client1\nclient2\n".to_string())
-            .exit_code(0)
+            .exit_signal(6)
+            .core_dumped(true)
             .success(1)
             .build();
 
@@ -99,6 +113,8 @@ mod test {
         assert_eq!(list_output.timestamp(), decoded.timestamp());
         assert_eq!(list_output.data(), decoded.data());
         assert_eq!(list_output.exit_code(), decoded.exit_code());
+        assert_eq!(list_output.exit_signal(), decoded.exit_signal());
+        assert_eq!(list_output.core_dumped(), decoded.core_dumped());
         assert_eq!(list_output.success(), decoded.success());
         assert!(!format!("{list_output:?}").is_empty());
         Ok(())
@@ -6,11 +6,19 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+pub(crate) mod batch;
+pub(crate) mod export;
 pub(crate) mod failed;
 pub(crate) mod init;
 pub(crate) mod list;
 pub(crate) mod odt;
 pub(crate) mod output;
+pub(crate) mod presence;
+pub(crate) mod query;
+pub(crate) mod run;
+pub(crate) mod search;
 pub(crate) mod sys;
+pub(crate) mod tail;
+pub(crate) mod trace;
 pub(crate) mod update;
 pub(crate) mod uuid;
@@ -15,9 +15,11 @@ use bincode_next::{
     error::{DecodeError, EncodeError},
 };
 use bon::Builder;
-use getset::{CopyGetters, Getters};
+use getset::{CopyGetters, Getters, Setters};
 
-use crate::message::shared::{odt::OffsetDataTimeWrapper, uuid::UuidWrapper};
+use crate::message::shared::{
+    odt::OffsetDataTimeWrapper, run::RunStart, trace::TraceParent, uuid::UuidWrapper,
+};
 #[cfg(test)]
 use crate::utils::Mock;
 
@@ -28,6 +30,31 @@ pub enum Data {
     Output(Output),
     /// A status record
     Status(Status),
+    /// A record that a command was started
+    Started(RunStart),
+}
+
+impl Data {
+    /// The trace context of the run a run start or status belongs to, output lines carry none
+    #[must_use]
+    pub fn trace_parent(&self) -> Option<TraceParent> {
+        match self {
+            Data::Output(_) => None,
+            Data::Status(status) => status.trace_parent,
+            Data::Started(run_start) => run_start.trace_parent(),
+        }
+    }
+
+    /// Set the trace context of a run start or status, output lines carry none
+    pub fn set_trace_parent(&mut self, trace_parent: Option<TraceParent>) {
+        match self {
+            Data::Output(_) => {}
+            Data::Status(status) => status.trace_parent = trace_parent,
+            Data::Started(run_start) => {
+                let _ = run_start.set_trace_parent(trace_parent);
+            }
+        }
+    }
 }
 
 impl<Context> Decode<Context> for Data {
@@ -42,6 +69,10 @@ impl<Context> Decode<Context> for Data {
                 let status = Status::decode(decoder)?;
                 Ok(Data::Status(status))
             }
+            2 => {
+                let run_start = RunStart::decode(decoder)?;
+                Ok(Data::Started(run_start))
+            }
             _ => Err(DecodeError::Other("Invalid variant for Data enum")),
         }
     }
@@ -61,6 +92,10 @@ impl<'de, Context> BorrowDecode<'de, Context> for Data {
                 let status = Status::borrow_decode(decoder)?;
                 Ok(Data::Status(status))
             }
+            2 => {
+                let run_start = RunStart::borrow_decode(decoder)?;
+                Ok(Data::Started(run_start))
+            }
             _ => Err(DecodeError::Other("Invalid variant for Data enum")),
         }
     }
@@ -77,6 +112,10 @@ impl Encode for Data {
                 Encode::encode(&1u8, encoder)?;
                 Encode::encode(status, encoder)?;
             }
+            Data::Started(run_start) => {
+                Encode::encode(&2u8, encoder)?;
+                Encode::encode(run_start, encoder)?;
+            }
         }
         Ok(())
     }
@@ -148,13 +187,16 @@ impl From<OutputKind> for &'static str {
 }
 
 /// An output record from a bartoc client
-#[derive(Builder, Clone, CopyGetters, Debug, Eq, Getters, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(
+    Builder, Clone, CopyGetters, Debug, Eq, Getters, Hash, Ord, PartialEq, PartialOrd, Setters,
+)]
 pub struct Output {
     /// The id of the bartoc that produced the output
     #[get_copy = "pub"]
     bartoc_uuid: UuidWrapper,
     /// The name of the bartoc that produced the output
     #[get = "pub"]
+    #[set = "pub"]
     bartoc_name: String,
     /// The timestamp of the output
     #[get_copy = "pub"]
@@ -259,7 +301,7 @@ impl Display for Output {
 
 /// An output record from a bartoc client
 #[derive(
-    Builder, Clone, Copy, CopyGetters, Debug, Eq, Getters, Hash, Ord, PartialEq, PartialOrd,
+    Builder, Clone, Copy, CopyGetters, Debug, Eq, Getters, Hash, Ord, PartialEq, PartialOrd, Setters,
 )]
 pub struct Status {
     /// The command `Uuid` of the bartoc command that produced the status
@@ -268,13 +310,27 @@ pub struct Status {
     /// The timestamp of the status
     #[get_copy = "pub"]
     timestamp: OffsetDataTimeWrapper,
-    /// The exit code of the command
+    /// The exit code of the command, `None` when it was killed by a signal
     #[get_copy = "pub"]
     #[builder(required)]
     exit_code: Option<i32>,
+    /// The signal that killed the command, Unix only
+    #[get_copy = "pub"]
+    exit_signal: Option<i32>,
+    /// Whether the command dumped core when it was killed, Unix only
+    #[get_copy = "pub"]
+    #[builder(default)]
+    core_dumped: bool,
+    /// The raw wait status of the command as returned by `waitpid`, Unix only
+    #[get_copy = "pub"]
+    wait_status: Option<i32>,
     /// The success status of the command
     #[get_copy = "pub"]
     success: bool,
+    /// The trace context of the run, when its spans are exported
+    #[get_copy = "pub"]
+    #[set = "pub"]
+    trace_parent: Option<TraceParent>,
 }
 
 impl<Context> Decode<Context> for Status {
@@ -282,13 +338,21 @@ impl<Context> Decode<Context> for Status {
         let cmd_uuid = UuidWrapper::decode(decoder)?;
         let timestamp = OffsetDataTimeWrapper::decode(decoder)?;
         let exit_code = Option::<i32>::decode(decoder)?;
+        let exit_signal = Option::<i32>::decode(decoder)?;
+        let core_dumped = bool::decode(decoder)?;
+        let wait_status = Option::<i32>::decode(decoder)?;
         let success = bool::decode(decoder)?;
+        let trace_parent = Option::<TraceParent>::decode(decoder)?;
 
         Ok(Status {
             cmd_uuid,
             timestamp,
             exit_code,
+            exit_signal,
+            core_dumped,
+            wait_status,
             success,
+            trace_parent,
         })
     }
 }
@@ -300,13 +364,21 @@ impl<'de, Context> BorrowDecode<'de, Context> for Status {
         let cmd_uuid = UuidWrapper::borrow_decode(decoder)?;
         let timestamp = OffsetDataTimeWrapper::borrow_decode(decoder)?;
         let exit_code = Option::<i32>::borrow_decode(decoder)?;
+        let exit_signal = Option::<i32>::borrow_decode(decoder)?;
+        let core_dumped = bool::borrow_decode(decoder)?;
+        let wait_status = Option::<i32>::borrow_decode(decoder)?;
         let success = bool::borrow_decode(decoder)?;
+        let trace_parent = Option::<TraceParent>::borrow_decode(decoder)?;
 
         Ok(Status {
             cmd_uuid,
             timestamp,
             exit_code,
+            exit_signal,
+            core_dumped,
+            wait_status,
             success,
+            trace_parent,
         })
     }
 }
@@ -316,7 +388,11 @@ impl Encode for Status {
         Encode::encode(&self.cmd_uuid, encoder)?;
         Encode::encode(&self.timestamp, encoder)?;
         Encode::encode(&self.exit_code, encoder)?;
+        Encode::encode(&self.exit_signal, encoder)?;
+        Encode::encode(&self.core_dumped, encoder)?;
+        Encode::encode(&self.wait_status, encoder)?;
         Encode::encode(&self.success, encoder)?;
+        Encode::encode(&self.trace_parent, encoder)?;
         Ok(())
     }
 }
@@ -324,21 +400,59 @@ impl Encode for Status {
 impl Display for Status {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if let Some(code) = self.exit_code {
-            write!(
-                f,
-                "({} exit_code={} success={})",
-                self.cmd_uuid, code, self.success,
-            )
+            write!(f, "({} exit_code={code}", self.cmd_uuid)?;
         } else {
-            write!(
-                f,
-                "({} exit_code=None success={})",
-                self.cmd_uuid, self.success,
-            )
+            write!(f, "({} exit_code=None", self.cmd_uuid)?;
+        }
+        if let Some(signal) = self.exit_signal {
+            write!(f, " exit_signal={signal}")?;
         }
+        if self.core_dumped {
+            write!(f, " core_dumped")?;
+        }
+        write!(f, " success={})", self.success)
     }
 }
 
+/// The name of a signal whose number is the same on every Unix
+fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        _ => return None,
+    })
+}
+
+/// Describe how a run ended for display: its exit code, or the signal that killed it and
+/// whether it dumped core, or `-` while neither is known
+#[must_use]
+pub fn describe_exit(
+    exit_code: Option<i32>,
+    exit_signal: Option<i32>,
+    core_dumped: bool,
+) -> String {
+    let mut description = match (exit_signal, exit_code) {
+        (Some(signal), _) => {
+            signal_name(signal).map_or_else(|| format!("signal {signal}"), ToString::to_string)
+        }
+        (None, Some(code)) => code.to_string(),
+        (None, None) => "-".to_string(),
+    };
+    if core_dumped {
+        description.push_str(" (core dumped)");
+    }
+    description
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -349,9 +463,9 @@ mod tests {
     use time::OffsetDateTime;
     use uuid::Uuid;
 
-    use crate::{OffsetDataTimeWrapper, UuidWrapper};
+    use crate::{OffsetDataTimeWrapper, RunStart, TraceParent, UuidWrapper, utils::Mock as _};
 
-    use super::{Data, Output, OutputKind, Status};
+    use super::{Data, Output, OutputKind, Status, describe_exit};
 
     #[test]
     fn output_kind_display() {
@@ -393,6 +507,7 @@ mod tests {
             .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
             .exit_code(Some(0))
             .success(true)
+            .trace_parent(TraceParent::mock())
             .build();
 
         let data = Data::Status(status);
@@ -402,6 +517,17 @@ mod tests {
         assert_eq!(data, decoded);
     }
 
+    #[test]
+    fn data_trace_parent() {
+        let mut data = Data::Started(RunStart::mock());
+        assert_eq!(data.trace_parent(), Some(TraceParent::mock()));
+        data.set_trace_parent(None);
+        assert_eq!(data.trace_parent(), None);
+        let mut data = Data::Output(Output::mock());
+        data.set_trace_parent(Some(TraceParent::mock()));
+        assert_eq!(data.trace_parent(), None);
+    }
+
     #[test]
     fn output_kind_encode_decode() {
         let stdout = OutputKind::Stdout;
@@ -455,6 +581,34 @@ mod tests {
         assert!(display_str.contains("success=false"));
     }
 
+    #[test]
+    fn status_display_with_exit_signal() {
+        let status = Status::builder()
+            .cmd_uuid(UuidWrapper(Uuid::nil()))
+            .timestamp(OffsetDataTimeWrapper(OffsetDateTime::UNIX_EPOCH))
+            .exit_code(None)
+            .exit_signal(11)
+            .core_dumped(true)
+            .wait_status(139)
+            .success(false)
+            .build();
+        let display_str = status.to_string();
+        assert!(display_str.contains("exit_code=None exit_signal=11 core_dumped success=false"));
+    }
+
+    #[test]
+    fn exit_descriptions() {
+        assert_eq!(describe_exit(Some(0), None, false), "0");
+        assert_eq!(
+            describe_exit(Some(-1_073_741_819), None, false),
+            "-1073741819"
+        );
+        assert_eq!(describe_exit(None, Some(9), false), "SIGKILL");
+        assert_eq!(describe_exit(None, Some(11), true), "SIGSEGV (core dumped)");
+        assert_eq!(describe_exit(None, Some(64), false), "signal 64");
+        assert_eq!(describe_exit(None, None, false), "-");
+    }
+
     #[test]
     fn status_display_without_exit_code() {
         let cmd_uuid = UuidWrapper(Uuid::new_v4());
@@ -549,6 +703,8 @@ mod tests {
             .cmd_uuid(UuidWrapper(Uuid::new_v4()))
             .timestamp(OffsetDataTimeWrapper(OffsetDateTime::now_utc()))
             .exit_code(None)
+            .exit_signal(9)
+            .wait_status(9)
             .success(false)
             .build();
 
@@ -588,10 +744,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn data_encode_decode_started() {
+        let data = Data::Started(RunStart::mock());
+        let encoded = encode_to_vec(&data, standard()).unwrap();
+        let decoded: Data = decode_from_slice(&encoded, standard()).unwrap().0;
+        let borrow_decoded: Data = borrow_decode_from_slice(&encoded, standard()).unwrap().0;
+
+        assert_eq!(data, decoded);
+        assert_eq!(data, borrow_decoded);
+    }
+
     #[test]
     fn data_bad_decode_variant() -> Result<()> {
-        // Manually create encoded data with invalid variant (2)
-        let bad_encoded = encode_to_vec(2u8, standard())?;
+        // Manually create encoded data with invalid variant (3)
+        let bad_encoded = encode_to_vec(3u8, standard())?;
 
         let result: Result<(Data, usize), _> = decode_from_slice(&bad_encoded, standard());
         assert!(result.is_err());
@@ -604,8 +771,8 @@ mod tests {
 
     #[test]
     fn data_bad_borrow_decode_variant() -> Result<()> {
-        // Manually create encoded data with invalid variant (2)
-        let bad_encoded = encode_to_vec(2u8, standard())?;
+        // Manually create encoded data with invalid variant (3)
+        let bad_encoded = encode_to_vec(3u8, standard())?;
 
         let result: Result<(Data, usize), _> = borrow_decode_from_slice(&bad_encoded, standard());
         assert!(result.is_err());
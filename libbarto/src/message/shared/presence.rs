@@ -0,0 +1,298 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+
+#[cfg(test)]
+use crate::utils::Mock;
+use crate::{Error, OffsetDataTimeWrapper, UuidWrapper};
+
+/// Whether a bartoc client connected or disconnected
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ClientEventKind {
+    /// The client connected
+    Connect,
+    /// The client disconnected
+    Disconnect,
+}
+
+impl ClientEventKind {
+    fn from_variant(variant: u8) -> Result<Self, DecodeError> {
+        match variant {
+            0 => Ok(ClientEventKind::Connect),
+            1 => Ok(ClientEventKind::Disconnect),
+            _ => Err(DecodeError::Other(
+                "Invalid variant for ClientEventKind enum",
+            )),
+        }
+    }
+}
+
+impl<Context> Decode<Context> for ClientEventKind {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let variant: u8 = Decode::decode(decoder)?;
+        ClientEventKind::from_variant(variant)
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for ClientEventKind {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        let variant: u8 = BorrowDecode::borrow_decode(decoder)?;
+        ClientEventKind::from_variant(variant)
+    }
+}
+
+impl Encode for ClientEventKind {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        let variant: u8 = match self {
+            ClientEventKind::Connect => 0,
+            ClientEventKind::Disconnect => 1,
+        };
+        Encode::encode(&variant, encoder)
+    }
+}
+
+impl Display for ClientEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", <&'static str>::from(*self))
+    }
+}
+
+impl From<ClientEventKind> for &'static str {
+    fn from(kind: ClientEventKind) -> Self {
+        match kind {
+            ClientEventKind::Connect => "connect",
+            ClientEventKind::Disconnect => "disconnect",
+        }
+    }
+}
+
+impl TryFrom<&str> for ClientEventKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "connect" => Ok(ClientEventKind::Connect),
+            "disconnect" => Ok(ClientEventKind::Disconnect),
+            _ => Err(Error::InvalidClientEventKind(value.to_string()).into()),
+        }
+    }
+}
+
+/// A bartoc client connecting to or disconnecting from bartos
+#[derive(Builder, Clone, CopyGetters, Debug, Eq, Getters, PartialEq)]
+pub struct ClientEvent {
+    /// The id of the connection
+    #[getset(get_copy = "pub")]
+    bartoc_uuid: UuidWrapper,
+    /// The name of the bartoc client
+    #[getset(get = "pub")]
+    bartoc_name: String,
+    /// The address the client connected from
+    #[getset(get = "pub")]
+    ip: String,
+    /// Whether the client connected or disconnected
+    #[getset(get_copy = "pub")]
+    kind: ClientEventKind,
+    /// Why the client disconnected, e.g. `heartbeat_timeout` or `replaced`
+    #[getset(get = "pub")]
+    reason: Option<String>,
+    /// The WebSocket close code the client sent, if it closed the connection
+    #[getset(get_copy = "pub")]
+    close_code: Option<u16>,
+    /// When the event happened
+    #[getset(get_copy = "pub")]
+    timestamp: OffsetDataTimeWrapper,
+}
+
+#[cfg(test)]
+impl Mock for ClientEvent {
+    fn mock() -> Self {
+        Self {
+            bartoc_uuid: UuidWrapper::mock(),
+            bartoc_name: "mock_bartoc".to_string(),
+            ip: "127.0.0.1".to_string(),
+            kind: ClientEventKind::Disconnect,
+            reason: Some("closed".to_string()),
+            close_code: Some(1000),
+            timestamp: OffsetDataTimeWrapper::mock(),
+        }
+    }
+}
+
+impl<Context> Decode<Context> for ClientEvent {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            bartoc_uuid: Decode::decode(decoder)?,
+            bartoc_name: Decode::decode(decoder)?,
+            ip: Decode::decode(decoder)?,
+            kind: Decode::decode(decoder)?,
+            reason: Decode::decode(decoder)?,
+            close_code: Decode::decode(decoder)?,
+            timestamp: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for ClientEvent {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            bartoc_uuid: BorrowDecode::borrow_decode(decoder)?,
+            bartoc_name: BorrowDecode::borrow_decode(decoder)?,
+            ip: BorrowDecode::borrow_decode(decoder)?,
+            kind: BorrowDecode::borrow_decode(decoder)?,
+            reason: BorrowDecode::borrow_decode(decoder)?,
+            close_code: BorrowDecode::borrow_decode(decoder)?,
+            timestamp: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for ClientEvent {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.bartoc_uuid, encoder)?;
+        Encode::encode(&self.bartoc_name, encoder)?;
+        Encode::encode(&self.ip, encoder)?;
+        Encode::encode(&self.kind, encoder)?;
+        Encode::encode(&self.reason, encoder)?;
+        Encode::encode(&self.close_code, encoder)?;
+        Encode::encode(&self.timestamp, encoder)?;
+        Ok(())
+    }
+}
+
+/// The client events a `ClientHistory` request asks for, newest first
+#[derive(Builder, Clone, CopyGetters, Debug, Default, Eq, Getters, PartialEq)]
+pub struct ClientEventFilter {
+    /// Only events of the bartoc client with this name
+    #[getset(get = "pub")]
+    client: Option<String>,
+    /// Only events at or after this time
+    #[getset(get_copy = "pub")]
+    since: Option<OffsetDataTimeWrapper>,
+    /// The most events to return, capped by `bartos`
+    #[getset(get_copy = "pub")]
+    limit: Option<u32>,
+}
+
+#[cfg(test)]
+impl Mock for ClientEventFilter {
+    fn mock() -> Self {
+        Self {
+            client: Some("mock_bartoc".to_string()),
+            since: Some(OffsetDataTimeWrapper::mock()),
+            limit: Some(50),
+        }
+    }
+}
+
+impl<Context> Decode<Context> for ClientEventFilter {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: Decode::decode(decoder)?,
+            since: Decode::decode(decoder)?,
+            limit: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for ClientEventFilter {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: BorrowDecode::borrow_decode(decoder)?,
+            since: BorrowDecode::borrow_decode(decoder)?,
+            limit: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for ClientEventFilter {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.client, encoder)?;
+        Encode::encode(&self.since, encoder)?;
+        Encode::encode(&self.limit, encoder)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+
+    use super::{ClientEvent, ClientEventFilter, ClientEventKind};
+    use crate::utils::Mock;
+
+    #[test]
+    fn test_client_event_kind_strings() -> Result<()> {
+        for kind in [ClientEventKind::Connect, ClientEventKind::Disconnect] {
+            let name: &'static str = kind.into();
+            assert_eq!(ClientEventKind::try_from(name)?, kind);
+            assert_eq!(kind.to_string(), name);
+        }
+        assert!(ClientEventKind::try_from("reconnect").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_event_encode_decode() -> Result<()> {
+        let connect = ClientEvent::builder()
+            .bartoc_uuid(crate::UuidWrapper::mock())
+            .bartoc_name("host".to_string())
+            .ip("10.0.0.1".to_string())
+            .kind(ClientEventKind::Connect)
+            .timestamp(crate::OffsetDataTimeWrapper::mock())
+            .build();
+        for original in [ClientEvent::mock(), connect] {
+            let encoded = encode_to_vec(&original, standard())?;
+            let (decoded, _): (ClientEvent, usize) = decode_from_slice(&encoded, standard())?;
+            let (borrowed, _): (ClientEvent, usize) =
+                borrow_decode_from_slice(&encoded, standard())?;
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_event_filter_encode_decode() -> Result<()> {
+        for original in [ClientEventFilter::mock(), ClientEventFilter::default()] {
+            let encoded = encode_to_vec(&original, standard())?;
+            let (decoded, _): (ClientEventFilter, usize) = decode_from_slice(&encoded, standard())?;
+            let (borrowed, _): (ClientEventFilter, usize) =
+                borrow_decode_from_slice(&encoded, standard())?;
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_event_kind_bad_variant() {
+        let encoded = encode_to_vec(2u8, standard()).unwrap();
+        assert!(decode_from_slice::<ClientEventKind, _>(&encoded, standard()).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt::{Display, Formatter};
+
+use anyhow::{Error, Result};
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+use time::{Duration, OffsetDateTime, format_description::well_known::Rfc3339};
+
+#[cfg(test)]
+use crate::utils::Mock;
+use crate::{OffsetDataTimeWrapper, UuidWrapper};
+
+/// How a run ended, or that it has not ended yet
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RunState {
+    /// The run finished successfully
+    Succeeded,
+    /// The run finished unsuccessfully
+    Failed,
+    /// The run has started but not reported a status yet
+    Running,
+}
+
+impl<Context> Decode<Context> for RunState {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let variant: u8 = Decode::decode(decoder)?;
+        RunState::from_variant(variant)
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for RunState {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        let variant: u8 = BorrowDecode::borrow_decode(decoder)?;
+        RunState::from_variant(variant)
+    }
+}
+
+impl Encode for RunState {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        let variant: u8 = match self {
+            RunState::Succeeded => 0,
+            RunState::Failed => 1,
+            RunState::Running => 2,
+        };
+        Encode::encode(&variant, encoder)
+    }
+}
+
+impl RunState {
+    fn from_variant(variant: u8) -> Result<Self, DecodeError> {
+        match variant {
+            0 => Ok(RunState::Succeeded),
+            1 => Ok(RunState::Failed),
+            2 => Ok(RunState::Running),
+            _ => Err(DecodeError::Other("Invalid variant for RunState enum")),
+        }
+    }
+}
+
+impl Display for RunState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            RunState::Succeeded => "succeeded",
+            RunState::Failed => "failed",
+            RunState::Running => "running",
+        };
+        write!(f, "{state}")
+    }
+}
+
+impl TryFrom<&str> for RunState {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "succeeded" | "success" | "ok" => Ok(RunState::Succeeded),
+            "failed" | "failure" => Ok(RunState::Failed),
+            "running" => Ok(RunState::Running),
+            _ => Err(crate::Error::InvalidRunState {
+                state: value.to_string(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// The runs a `Query` request asks for. Every filter that is set must match. `bartos` turns
+/// the filter into parameterized SQL, so none of the values can change the statement.
+#[derive(Builder, Clone, CopyGetters, Debug, Default, Eq, Getters, PartialEq)]
+pub struct QueryFilter {
+    /// Only runs on the bartoc client with this name
+    #[getset(get = "pub")]
+    client: Option<String>,
+    /// Only runs of the schedule with this name
+    #[getset(get = "pub")]
+    schedule: Option<String>,
+    /// Only output (or runs without output) at or after this time
+    #[getset(get_copy = "pub")]
+    since: Option<OffsetDataTimeWrapper>,
+    /// Only output (or runs without output) before this time
+    #[getset(get_copy = "pub")]
+    until: Option<OffsetDataTimeWrapper>,
+    /// Only runs in this state
+    #[getset(get_copy = "pub")]
+    state: Option<RunState>,
+    /// Only output lines containing this text, ignoring case
+    #[getset(get = "pub")]
+    text: Option<String>,
+    /// The most rows to return, capped by `bartos`
+    #[getset(get_copy = "pub")]
+    limit: Option<u32>,
+    /// The number of matching rows to skip
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    offset: u32,
+}
+
+#[cfg(test)]
+impl Mock for QueryFilter {
+    fn mock() -> Self {
+        Self {
+            client: Some("mock_bartoc".to_string()),
+            schedule: Some("mock_cmd".to_string()),
+            since: Some(OffsetDataTimeWrapper::mock()),
+            until: None,
+            state: Some(RunState::Failed),
+            text: Some("error".to_string()),
+            limit: Some(10),
+            offset: 20,
+        }
+    }
+}
+
+impl<Context> Decode<Context> for QueryFilter {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: Decode::decode(decoder)?,
+            schedule: Decode::decode(decoder)?,
+            since: Decode::decode(decoder)?,
+            until: Decode::decode(decoder)?,
+            state: Decode::decode(decoder)?,
+            text: Decode::decode(decoder)?,
+            limit: Decode::decode(decoder)?,
+            offset: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for QueryFilter {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: BorrowDecode::borrow_decode(decoder)?,
+            schedule: BorrowDecode::borrow_decode(decoder)?,
+            since: BorrowDecode::borrow_decode(decoder)?,
+            until: BorrowDecode::borrow_decode(decoder)?,
+            state: BorrowDecode::borrow_decode(decoder)?,
+            text: BorrowDecode::borrow_decode(decoder)?,
+            limit: BorrowDecode::borrow_decode(decoder)?,
+            offset: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for QueryFilter {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.client, encoder)?;
+        Encode::encode(&self.schedule, encoder)?;
+        Encode::encode(&self.since, encoder)?;
+        Encode::encode(&self.until, encoder)?;
+        Encode::encode(&self.state, encoder)?;
+        Encode::encode(&self.text, encoder)?;
+        Encode::encode(&self.limit, encoder)?;
+        Encode::encode(&self.offset, encoder)?;
+        Ok(())
+    }
+}
+
+/// One output line of a run matching a `Query` request, or the run itself when it has no output
+#[derive(Builder, Clone, CopyGetters, Debug, Eq, Getters, PartialEq)]
+pub struct QueryRow {
+    /// The UUID of the run
+    #[getset(get_copy = "pub")]
+    cmd_uuid: UuidWrapper,
+    /// When the output line was generated, or when the run started if it has no output
+    #[getset(get = "pub")]
+    timestamp: Option<OffsetDataTimeWrapper>,
+    /// The name of the bartoc client
+    #[getset(get = "pub")]
+    bartoc_name: Option<String>,
+    /// The name of the schedule
+    #[getset(get = "pub")]
+    schedule_name: Option<String>,
+    /// The output line
+    #[getset(get = "pub")]
+    data: Option<String>,
+    /// The exit code of the run, `None` when it was killed by a signal or is still going
+    #[getset(get_copy = "pub")]
+    exit_code: Option<i32>,
+    /// The signal that killed the run
+    #[getset(get_copy = "pub")]
+    exit_signal: Option<i32>,
+    /// Whether the run dumped core when it was killed
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    core_dumped: bool,
+    /// Whether the run was successful
+    #[getset(get_copy = "pub")]
+    success: i8,
+}
+
+#[cfg(test)]
+impl Mock for QueryRow {
+    fn mock() -> Self {
+        Self {
+            cmd_uuid: UuidWrapper::mock(),
+            timestamp: Some(OffsetDataTimeWrapper::mock()),
+            bartoc_name: Some("mock_bartoc".to_string()),
+            schedule_name: Some("mock_cmd".to_string()),
+            data: Some("mock_data".to_string()),
+            exit_code: Some(0),
+            exit_signal: None,
+            core_dumped: false,
+            success: 1,
+        }
+    }
+}
+
+impl<Context> Decode<Context> for QueryRow {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd_uuid: Decode::decode(decoder)?,
+            timestamp: Decode::decode(decoder)?,
+            bartoc_name: Decode::decode(decoder)?,
+            schedule_name: Decode::decode(decoder)?,
+            data: Decode::decode(decoder)?,
+            exit_code: Decode::decode(decoder)?,
+            exit_signal: Decode::decode(decoder)?,
+            core_dumped: Decode::decode(decoder)?,
+            success: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for QueryRow {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd_uuid: BorrowDecode::borrow_decode(decoder)?,
+            timestamp: BorrowDecode::borrow_decode(decoder)?,
+            bartoc_name: BorrowDecode::borrow_decode(decoder)?,
+            schedule_name: BorrowDecode::borrow_decode(decoder)?,
+            data: BorrowDecode::borrow_decode(decoder)?,
+            exit_code: BorrowDecode::borrow_decode(decoder)?,
+            exit_signal: BorrowDecode::borrow_decode(decoder)?,
+            core_dumped: BorrowDecode::borrow_decode(decoder)?,
+            success: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for QueryRow {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.cmd_uuid, encoder)?;
+        Encode::encode(&self.timestamp, encoder)?;
+        Encode::encode(&self.bartoc_name, encoder)?;
+        Encode::encode(&self.schedule_name, encoder)?;
+        Encode::encode(&self.data, encoder)?;
+        Encode::encode(&self.exit_code, encoder)?;
+        Encode::encode(&self.exit_signal, encoder)?;
+        Encode::encode(&self.core_dumped, encoder)?;
+        Encode::encode(&self.success, encoder)?;
+        Ok(())
+    }
+}
+
+/// Parse a time bound for a [`QueryFilter`], either an RFC 3339 timestamp or an age relative
+/// to `now` such as `30m`, `12h`, `7d` or `2w`.
+///
+/// # Errors
+/// * The value is neither an RFC 3339 timestamp nor a number followed by `s`, `m`, `h`, `d`
+///   or `w`.
+pub fn parse_time_bound(value: &str, now: OffsetDateTime) -> Result<OffsetDataTimeWrapper> {
+    if let Ok(at) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(OffsetDataTimeWrapper(at));
+    }
+    let invalid = || crate::Error::InvalidTimeBound(value.to_string());
+    let unit_at = value.len().checked_sub(1).ok_or_else(invalid)?;
+    let (count, unit) = value.split_at(unit_at);
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let age = match unit {
+        "s" => Duration::seconds(count),
+        "m" => Duration::minutes(count),
+        "h" => Duration::hours(count),
+        "d" => Duration::days(count),
+        "w" => Duration::weeks(count),
+        _ => return Err(invalid().into()),
+    };
+    Ok(OffsetDataTimeWrapper(now - age))
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+    use time::{Duration, OffsetDateTime, macros::datetime};
+
+    use super::{QueryFilter, QueryRow, RunState, parse_time_bound};
+    use crate::utils::Mock;
+
+    #[test]
+    fn test_query_filter_encode_decode() -> Result<()> {
+        for original in [QueryFilter::mock(), QueryFilter::default()] {
+            let encoded = encode_to_vec(&original, standard())?;
+            let (decoded, _): (QueryFilter, usize) = decode_from_slice(&encoded, standard())?;
+            let (borrowed, _): (QueryFilter, usize) =
+                borrow_decode_from_slice(&encoded, standard())?;
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_row_encode_decode() -> Result<()> {
+        let original = QueryRow::mock();
+        let encoded = encode_to_vec(&original, standard())?;
+        let (decoded, _): (QueryRow, usize) = decode_from_slice(&encoded, standard())?;
+        let (borrowed, _): (QueryRow, usize) = borrow_decode_from_slice(&encoded, standard())?;
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrowed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_state() -> Result<()> {
+        for state in [RunState::Succeeded, RunState::Failed, RunState::Running] {
+            assert_eq!(RunState::try_from(state.to_string().as_str())?, state);
+            let encoded = encode_to_vec(state, standard())?;
+            let (decoded, _): (RunState, usize) = decode_from_slice(&encoded, standard())?;
+            assert_eq!(state, decoded);
+        }
+        assert_eq!(RunState::try_from("OK")?, RunState::Succeeded);
+        assert!(RunState::try_from("done").is_err());
+        let encoded = encode_to_vec(9u8, standard())?;
+        assert!(decode_from_slice::<RunState, _>(&encoded, standard()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_time_bound() -> Result<()> {
+        let now = datetime!(2025-06-15 12:00 UTC);
+        assert_eq!(
+            parse_time_bound("2025-06-01T00:00:00Z", now)?.0,
+            datetime!(2025-06-01 00:00 UTC)
+        );
+        assert_eq!(parse_time_bound("90s", now)?.0, now - Duration::seconds(90));
+        assert_eq!(parse_time_bound("30m", now)?.0, now - Duration::minutes(30));
+        assert_eq!(parse_time_bound("12h", now)?.0, now - Duration::hours(12));
+        assert_eq!(parse_time_bound("7d", now)?.0, now - Duration::days(7));
+        assert_eq!(parse_time_bound("2w", now)?.0, now - Duration::weeks(2));
+        for invalid in ["", "d", "7y", "seven days", "2025-06-01"] {
+            assert!(parse_time_bound(invalid, OffsetDateTime::now_utc()).is_err());
+        }
+        Ok(())
+    }
+}
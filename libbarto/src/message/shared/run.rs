@@ -0,0 +1,270 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt::{Display, Formatter};
+
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use bon::Builder;
+use getset::{CopyGetters, Getters, Setters};
+
+use crate::message::shared::{odt::OffsetDataTimeWrapper, trace::TraceParent, uuid::UuidWrapper};
+#[cfg(test)]
+use crate::utils::Mock;
+
+/// What caused a command to run
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TriggerKind {
+    /// The command ran because its schedule fired
+    #[default]
+    Schedule,
+    /// The schedule was expected to fire but no run was reported, recorded by bartos
+    Missed,
+}
+
+impl<Context> Decode<Context> for TriggerKind {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let variant: u8 = Decode::decode(decoder)?;
+        match variant {
+            0 => Ok(TriggerKind::Schedule),
+            1 => Ok(TriggerKind::Missed),
+            _ => Err(DecodeError::Other("Invalid variant for TriggerKind enum")),
+        }
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for TriggerKind {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        let variant: u8 = BorrowDecode::borrow_decode(decoder)?;
+        match variant {
+            0 => Ok(TriggerKind::Schedule),
+            1 => Ok(TriggerKind::Missed),
+            _ => Err(DecodeError::Other("Invalid variant for TriggerKind enum")),
+        }
+    }
+}
+
+impl Encode for TriggerKind {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        match self {
+            TriggerKind::Schedule => Encode::encode(&0u8, encoder),
+            TriggerKind::Missed => Encode::encode(&1u8, encoder),
+        }
+    }
+}
+
+impl Display for TriggerKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", <&'static str>::from(*self))
+    }
+}
+
+impl From<TriggerKind> for &'static str {
+    fn from(kind: TriggerKind) -> Self {
+        match kind {
+            TriggerKind::Schedule => "schedule",
+            TriggerKind::Missed => "missed",
+        }
+    }
+}
+
+/// A record that a bartoc client started running a command
+#[derive(
+    Builder, Clone, CopyGetters, Debug, Eq, Getters, Hash, Ord, PartialEq, PartialOrd, Setters,
+)]
+pub struct RunStart {
+    /// The UUID of this run of the command, shared with its output and status records
+    #[get_copy = "pub"]
+    cmd_uuid: UuidWrapper,
+    /// The id of the bartoc running the command
+    #[get_copy = "pub"]
+    bartoc_uuid: UuidWrapper,
+    /// The name of the bartoc running the command
+    #[get = "pub"]
+    #[set = "pub"]
+    bartoc_name: String,
+    /// The name of the schedule the command belongs to
+    #[get = "pub"]
+    schedule_name: String,
+    /// The command string being run
+    #[get = "pub"]
+    cmd: String,
+    /// What caused the command to run
+    #[get_copy = "pub"]
+    #[builder(default)]
+    trigger: TriggerKind,
+    /// The attempt number of this run, starting at 1
+    #[get_copy = "pub"]
+    #[builder(default = 1)]
+    attempt: u32,
+    /// When the command was started
+    #[get_copy = "pub"]
+    timestamp: OffsetDataTimeWrapper,
+    /// The trace context of the run, when its spans are exported
+    #[get_copy = "pub"]
+    #[set = "pub"]
+    trace_parent: Option<TraceParent>,
+}
+
+#[cfg(test)]
+impl Mock for RunStart {
+    fn mock() -> Self {
+        Self::builder()
+            .cmd_uuid(UuidWrapper::mock())
+            .bartoc_uuid(UuidWrapper::mock())
+            .bartoc_name("mock_bartoc".to_string())
+            .schedule_name("mock_schedule".to_string())
+            .cmd("echo mock".to_string())
+            .timestamp(OffsetDataTimeWrapper::mock())
+            .trace_parent(TraceParent::mock())
+            .build()
+    }
+}
+
+impl<Context> Decode<Context> for RunStart {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let cmd_uuid = UuidWrapper::decode(decoder)?;
+        let bartoc_uuid = UuidWrapper::decode(decoder)?;
+        let bartoc_name = String::decode(decoder)?;
+        let schedule_name = String::decode(decoder)?;
+        let cmd = String::decode(decoder)?;
+        let trigger = TriggerKind::decode(decoder)?;
+        let attempt = u32::decode(decoder)?;
+        let timestamp = OffsetDataTimeWrapper::decode(decoder)?;
+        let trace_parent = Option::<TraceParent>::decode(decoder)?;
+
+        Ok(RunStart {
+            cmd_uuid,
+            bartoc_uuid,
+            bartoc_name,
+            schedule_name,
+            cmd,
+            trigger,
+            attempt,
+            timestamp,
+            trace_parent,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for RunStart {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        let cmd_uuid = UuidWrapper::borrow_decode(decoder)?;
+        let bartoc_uuid = UuidWrapper::borrow_decode(decoder)?;
+        let bartoc_name = String::borrow_decode(decoder)?;
+        let schedule_name = String::borrow_decode(decoder)?;
+        let cmd = String::borrow_decode(decoder)?;
+        let trigger = TriggerKind::borrow_decode(decoder)?;
+        let attempt = u32::borrow_decode(decoder)?;
+        let timestamp = OffsetDataTimeWrapper::borrow_decode(decoder)?;
+        let trace_parent = Option::<TraceParent>::borrow_decode(decoder)?;
+
+        Ok(RunStart {
+            cmd_uuid,
+            bartoc_uuid,
+            bartoc_name,
+            schedule_name,
+            cmd,
+            trigger,
+            attempt,
+            timestamp,
+            trace_parent,
+        })
+    }
+}
+
+impl Encode for RunStart {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.cmd_uuid, encoder)?;
+        Encode::encode(&self.bartoc_uuid, encoder)?;
+        Encode::encode(&self.bartoc_name, encoder)?;
+        Encode::encode(&self.schedule_name, encoder)?;
+        Encode::encode(&self.cmd, encoder)?;
+        Encode::encode(&self.trigger, encoder)?;
+        Encode::encode(&self.attempt, encoder)?;
+        Encode::encode(&self.timestamp, encoder)?;
+        Encode::encode(&self.trace_parent, encoder)?;
+        Ok(())
+    }
+}
+
+impl Display for RunStart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({} {} {} attempt={}) => {}",
+            self.bartoc_uuid, self.cmd_uuid, self.trigger, self.attempt, self.cmd,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+
+    use crate::utils::Mock as _;
+
+    use super::{RunStart, TriggerKind};
+
+    #[test]
+    fn run_start_defaults() {
+        let run_start = RunStart::mock();
+        assert_eq!(run_start.trigger(), TriggerKind::Schedule);
+        assert_eq!(run_start.attempt(), 1);
+    }
+
+    #[test]
+    fn run_start_encode_decode() {
+        let run_start = RunStart::mock();
+        let encoded = encode_to_vec(&run_start, standard()).unwrap();
+        let (decoded, _): (RunStart, _) = decode_from_slice(&encoded, standard()).unwrap();
+        let (borrow_decoded, _): (RunStart, _) =
+            borrow_decode_from_slice(&encoded, standard()).unwrap();
+        assert_eq!(run_start, decoded);
+        assert_eq!(run_start, borrow_decoded);
+    }
+
+    #[test]
+    fn trigger_kind_encode_decode() {
+        for kind in [TriggerKind::Schedule, TriggerKind::Missed] {
+            let encoded = encode_to_vec(kind, standard()).unwrap();
+            let (decoded, _): (TriggerKind, _) = decode_from_slice(&encoded, standard()).unwrap();
+            let (borrow_decoded, _): (TriggerKind, _) =
+                borrow_decode_from_slice(&encoded, standard()).unwrap();
+            assert_eq!(kind, decoded);
+            assert_eq!(kind, borrow_decoded);
+        }
+        assert_eq!(TriggerKind::Missed.to_string(), "missed");
+    }
+
+    #[test]
+    fn trigger_kind_bad_variant() {
+        let encoded = encode_to_vec(2u8, standard()).unwrap();
+        assert!(decode_from_slice::<TriggerKind, _>(&encoded, standard()).is_err());
+        assert!(borrow_decode_from_slice::<TriggerKind, _>(&encoded, standard()).is_err());
+    }
+
+    #[test]
+    fn run_start_display() {
+        let run_start = RunStart::mock();
+        let display = run_start.to_string();
+        assert!(display.contains("schedule"));
+        assert!(display.contains("attempt=1"));
+        assert!(display.contains("echo mock"));
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use bon::Builder;
+use getset::{CopyGetters, Getters};
+
+#[cfg(test)]
+use crate::utils::Mock;
+use crate::{OffsetDataTimeWrapper, UuidWrapper};
+
+/// The output lines a `Search` request looks for. `bartos` matches `text` against the
+/// full-text index of the stored output, so words are matched whole and in order, ignoring case.
+#[derive(Builder, Clone, CopyGetters, Debug, Default, Eq, Getters, PartialEq)]
+pub struct SearchFilter {
+    /// The words to search for
+    #[getset(get = "pub")]
+    #[builder(into)]
+    text: String,
+    /// Only output of the bartoc client with this name
+    #[getset(get = "pub")]
+    client: Option<String>,
+    /// Only output of the schedule with this name
+    #[getset(get = "pub")]
+    schedule: Option<String>,
+    /// Only output at or after this time
+    #[getset(get_copy = "pub")]
+    since: Option<OffsetDataTimeWrapper>,
+    /// Only output before this time
+    #[getset(get_copy = "pub")]
+    until: Option<OffsetDataTimeWrapper>,
+    /// The most matches to return, capped by `bartos`
+    #[getset(get_copy = "pub")]
+    limit: Option<u32>,
+    /// The number of lines of the same run to return before and after each match
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    context: u8,
+}
+
+#[cfg(test)]
+impl Mock for SearchFilter {
+    fn mock() -> Self {
+        Self {
+            text: "failed to commit".to_string(),
+            client: Some("mock_bartoc".to_string()),
+            schedule: Some("mock_cmd".to_string()),
+            since: Some(OffsetDataTimeWrapper::mock()),
+            until: None,
+            limit: Some(10),
+            context: 2,
+        }
+    }
+}
+
+impl<Context> Decode<Context> for SearchFilter {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            text: Decode::decode(decoder)?,
+            client: Decode::decode(decoder)?,
+            schedule: Decode::decode(decoder)?,
+            since: Decode::decode(decoder)?,
+            until: Decode::decode(decoder)?,
+            limit: Decode::decode(decoder)?,
+            context: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for SearchFilter {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            text: BorrowDecode::borrow_decode(decoder)?,
+            client: BorrowDecode::borrow_decode(decoder)?,
+            schedule: BorrowDecode::borrow_decode(decoder)?,
+            since: BorrowDecode::borrow_decode(decoder)?,
+            until: BorrowDecode::borrow_decode(decoder)?,
+            limit: BorrowDecode::borrow_decode(decoder)?,
+            context: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for SearchFilter {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.text, encoder)?;
+        Encode::encode(&self.client, encoder)?;
+        Encode::encode(&self.schedule, encoder)?;
+        Encode::encode(&self.since, encoder)?;
+        Encode::encode(&self.until, encoder)?;
+        Encode::encode(&self.limit, encoder)?;
+        Encode::encode(&self.context, encoder)?;
+        Ok(())
+    }
+}
+
+/// One output line matching a `Search` request, with the lines of the same run around it
+#[derive(Builder, Clone, CopyGetters, Debug, Eq, Getters, PartialEq)]
+pub struct SearchHit {
+    /// The UUID of the run
+    #[getset(get_copy = "pub")]
+    cmd_uuid: UuidWrapper,
+    /// When the matching line was generated
+    #[getset(get_copy = "pub")]
+    timestamp: OffsetDataTimeWrapper,
+    /// The name of the bartoc client
+    #[getset(get = "pub")]
+    bartoc_name: String,
+    /// The name of the schedule, if the run was recorded
+    #[getset(get = "pub")]
+    schedule_name: Option<String>,
+    /// The matching line
+    #[getset(get = "pub")]
+    line: String,
+    /// The lines of the run before the matching line, oldest first
+    #[getset(get = "pub")]
+    #[builder(default)]
+    before: Vec<String>,
+    /// The lines of the run after the matching line, oldest first
+    #[getset(get = "pub")]
+    #[builder(default)]
+    after: Vec<String>,
+}
+
+#[cfg(test)]
+impl Mock for SearchHit {
+    fn mock() -> Self {
+        Self {
+            cmd_uuid: UuidWrapper::mock(),
+            timestamp: OffsetDataTimeWrapper::mock(),
+            bartoc_name: "mock_bartoc".to_string(),
+            schedule_name: Some("mock_cmd".to_string()),
+            line: "error: failed to commit transaction".to_string(),
+            before: vec!["updating packages".to_string()],
+            after: vec!["rolling back".to_string(), "done".to_string()],
+        }
+    }
+}
+
+impl<Context> Decode<Context> for SearchHit {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd_uuid: Decode::decode(decoder)?,
+            timestamp: Decode::decode(decoder)?,
+            bartoc_name: Decode::decode(decoder)?,
+            schedule_name: Decode::decode(decoder)?,
+            line: Decode::decode(decoder)?,
+            before: Decode::decode(decoder)?,
+            after: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for SearchHit {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd_uuid: BorrowDecode::borrow_decode(decoder)?,
+            timestamp: BorrowDecode::borrow_decode(decoder)?,
+            bartoc_name: BorrowDecode::borrow_decode(decoder)?,
+            schedule_name: BorrowDecode::borrow_decode(decoder)?,
+            line: BorrowDecode::borrow_decode(decoder)?,
+            before: BorrowDecode::borrow_decode(decoder)?,
+            after: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for SearchHit {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.cmd_uuid, encoder)?;
+        Encode::encode(&self.timestamp, encoder)?;
+        Encode::encode(&self.bartoc_name, encoder)?;
+        Encode::encode(&self.schedule_name, encoder)?;
+        Encode::encode(&self.line, encoder)?;
+        Encode::encode(&self.before, encoder)?;
+        Encode::encode(&self.after, encoder)?;
+        Ok(())
+    }
+}
+
+/// Split search text into the words a full-text index matches on: runs of letters, digits and
+/// underscores, lowercased.
+#[must_use]
+pub fn search_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+
+    use super::{SearchFilter, SearchHit, search_words};
+    use crate::utils::Mock;
+
+    #[test]
+    fn test_search_filter_encode_decode() -> Result<()> {
+        for original in [SearchFilter::mock(), SearchFilter::default()] {
+            let encoded = encode_to_vec(&original, standard())?;
+            let (decoded, _): (SearchFilter, usize) = decode_from_slice(&encoded, standard())?;
+            let (borrowed, _): (SearchFilter, usize) =
+                borrow_decode_from_slice(&encoded, standard())?;
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_hit_encode_decode() -> Result<()> {
+        let original = SearchHit::mock();
+        let encoded = encode_to_vec(&original, standard())?;
+        let (decoded, _): (SearchHit, usize) = decode_from_slice(&encoded, standard())?;
+        let (borrowed, _): (SearchHit, usize) = borrow_decode_from_slice(&encoded, standard())?;
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrowed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_words() {
+        assert_eq!(
+            search_words("error: failed to COMMIT \"transaction\""),
+            vec!["error", "failed", "to", "commit", "transaction"]
+        );
+        assert_eq!(search_words("apt-get_cache"), vec!["apt", "get_cache"]);
+        assert!(search_words(" -*- ").is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use anyhow::Result;
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use bon::Builder;
+use getset::Getters;
+
+#[cfg(test)]
+use crate::utils::Mock;
+
+/// The runs a `Subscribe` request streams the records of as they reach bartos
+#[derive(Builder, Clone, Debug, Default, Eq, Getters, PartialEq)]
+pub struct TailFilter {
+    /// Only runs on the bartoc client with this name
+    #[getset(get = "pub")]
+    client: Option<String>,
+    /// Only runs of this schedule
+    #[getset(get = "pub")]
+    schedule: Option<String>,
+}
+
+impl TailFilter {
+    /// Does a run of `schedule` on `client` match the filter
+    #[must_use]
+    pub fn matches(&self, client: &str, schedule: &str) -> bool {
+        self.client.as_deref().is_none_or(|name| name == client)
+            && self.schedule.as_deref().is_none_or(|name| name == schedule)
+    }
+}
+
+#[cfg(test)]
+impl Mock for TailFilter {
+    fn mock() -> Self {
+        Self {
+            client: Some("mock_bartoc".to_string()),
+            schedule: Some("mock_schedule".to_string()),
+        }
+    }
+}
+
+impl<Context> Decode<Context> for TailFilter {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: Decode::decode(decoder)?,
+            schedule: Decode::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for TailFilter {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            client: BorrowDecode::borrow_decode(decoder)?,
+            schedule: BorrowDecode::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for TailFilter {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.client, encoder)?;
+        Encode::encode(&self.schedule, encoder)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+
+    use super::TailFilter;
+    use crate::utils::Mock;
+
+    #[test]
+    fn test_tail_filter_matches() {
+        assert!(TailFilter::default().matches("host", "backup"));
+        let filter = TailFilter::builder().client("host".to_string()).build();
+        assert!(filter.matches("host", "backup"));
+        assert!(!filter.matches("other", "backup"));
+        let filter = TailFilter::mock();
+        assert!(filter.matches("mock_bartoc", "mock_schedule"));
+        assert!(!filter.matches("mock_bartoc", "backup"));
+    }
+
+    #[test]
+    fn test_tail_filter_encode_decode() -> Result<()> {
+        for original in [TailFilter::mock(), TailFilter::default()] {
+            let encoded = encode_to_vec(&original, standard())?;
+            let (decoded, _): (TailFilter, usize) = decode_from_slice(&encoded, standard())?;
+            let (borrowed, _): (TailFilter, usize) =
+                borrow_decode_from_slice(&encoded, standard())?;
+            assert_eq!(original, decoded);
+            assert_eq!(original, borrowed);
+        }
+        Ok(())
+    }
+}
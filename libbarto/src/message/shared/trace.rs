@@ -0,0 +1,216 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt::{Display, Formatter};
+
+use bincode_next::{
+    BorrowDecode, Decode, Encode,
+    de::{BorrowDecoder, Decoder},
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+};
+use opentelemetry::{
+    Context,
+    trace::{SpanContext, SpanId, TraceContextExt as _, TraceFlags, TraceId, TraceState},
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+use crate::Error;
+#[cfg(test)]
+use crate::utils::Mock;
+
+/// The W3C trace context of a span, carried in the records of a job run so the spans
+/// recorded for it on bartoc and bartos form one trace
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TraceParent {
+    trace_id: u128,
+    span_id: u64,
+    sampled: bool,
+}
+
+impl TraceParent {
+    /// The trace context of a span, `None` unless the span is being exported
+    #[must_use]
+    pub fn of(span: &Span) -> Option<Self> {
+        let context = span.context();
+        let span_context = context.span().span_context().clone();
+        span_context.is_valid().then(|| Self {
+            trace_id: u128::from_be_bytes(span_context.trace_id().to_bytes()),
+            span_id: u64::from_be_bytes(span_context.span_id().to_bytes()),
+            sampled: span_context.is_sampled(),
+        })
+    }
+
+    /// Make `span` a child of the span this trace context was taken from
+    pub fn adopt(self, span: &Span) {
+        let flags = if self.sampled {
+            TraceFlags::SAMPLED
+        } else {
+            TraceFlags::default()
+        };
+        let span_context = SpanContext::new(
+            TraceId::from(self.trace_id),
+            SpanId::from(self.span_id),
+            flags,
+            true,
+            TraceState::default(),
+        );
+        // A span that is not exported has no trace to join
+        let _ = span.set_parent(Context::new().with_remote_span_context(span_context));
+    }
+
+    /// The span made by `span`, joining the trace of a run whose spans are exported, otherwise
+    /// a disabled span so records without a trace do not start one each
+    pub fn span_in(trace_parent: Option<Self>, span: impl FnOnce() -> Span) -> Span {
+        trace_parent.map_or_else(Span::none, |trace_parent| {
+            let span = span();
+            trace_parent.adopt(&span);
+            span
+        })
+    }
+}
+
+impl Display for TraceParent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+}
+
+impl TryFrom<&str> for TraceParent {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || Error::InvalidTraceParent(value.to_string());
+        let [version, trace_id, span_id, flags] = value
+            .split('-')
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| invalid())?;
+        if version.len() != 2 || trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+            return Err(invalid());
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).map_err(|_| invalid())?;
+        let span_id = u64::from_str_radix(span_id, 16).map_err(|_| invalid())?;
+        let flags = u8::from_str_radix(flags, 16).map_err(|_| invalid())?;
+        if trace_id == 0 || span_id == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            trace_id,
+            span_id,
+            sampled: flags & 1 == 1,
+        })
+    }
+}
+
+#[cfg(test)]
+impl Mock for TraceParent {
+    fn mock() -> Self {
+        Self {
+            trace_id: 0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736,
+            span_id: 0x00f0_67aa_0ba9_02b7,
+            sampled: true,
+        }
+    }
+}
+
+impl<Context> Decode<Context> for TraceParent {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            trace_id: u128::decode(decoder)?,
+            span_id: u64::decode(decoder)?,
+            sampled: bool::decode(decoder)?,
+        })
+    }
+}
+
+impl<'de, Context> BorrowDecode<'de, Context> for TraceParent {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            trace_id: u128::borrow_decode(decoder)?,
+            span_id: u64::borrow_decode(decoder)?,
+            sampled: bool::borrow_decode(decoder)?,
+        })
+    }
+}
+
+impl Encode for TraceParent {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        Encode::encode(&self.trace_id, encoder)?;
+        Encode::encode(&self.span_id, encoder)?;
+        Encode::encode(&self.sampled, encoder)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bincode_next::{
+        borrow_decode_from_slice, config::standard, decode_from_slice, encode_to_vec,
+    };
+    use tracing::info_span;
+
+    use super::TraceParent;
+    use crate::utils::Mock;
+
+    #[test]
+    fn test_trace_parent_display() {
+        assert_eq!(
+            TraceParent::mock().to_string(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+    }
+
+    #[test]
+    fn test_trace_parent_try_from() {
+        let trace_parent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        assert_eq!(
+            TraceParent::try_from(trace_parent).unwrap(),
+            TraceParent::mock()
+        );
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902bx-01",
+        ] {
+            assert!(TraceParent::try_from(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_trace_parent_none_without_export() {
+        assert!(TraceParent::of(&info_span!("job")).is_none());
+        // Adopting a parent is a no-op when nothing is exported
+        TraceParent::mock().adopt(&info_span!("job"));
+        assert!(TraceParent::span_in(None, || info_span!("job")).is_none());
+    }
+
+    #[test]
+    fn test_trace_parent_encode_decode() -> Result<()> {
+        let original = TraceParent::mock();
+        let encoded = encode_to_vec(original, standard())?;
+        let (decoded, _): (TraceParent, usize) = decode_from_slice(&encoded, standard())?;
+        let (borrowed, _): (TraceParent, usize) = borrow_decode_from_slice(&encoded, standard())?;
+        assert_eq!(original, decoded);
+        assert_eq!(original, borrowed);
+        Ok(())
+    }
+}
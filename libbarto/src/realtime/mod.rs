@@ -21,7 +21,7 @@ use bon::Builder;
 #[cfg(test)]
 use getset::{Getters, Setters};
 use num_traits::FromPrimitive as _;
-use time::OffsetDateTime;
+use time::{Date, Duration, OffsetDateTime, Time, UtcOffset};
 
 use crate::{
     error::Error::InvalidCalendar,
@@ -51,6 +51,8 @@ const MONTHLY: &str = "monthly";
 const QUARTERLY: &str = "quarterly";
 const SEMIANNUALLY: &str = "semiannually";
 const YEARLY: &str = "yearly";
+/// How many days ahead to look for the next run, long enough to reach a leap day
+const MAX_LOOKAHEAD_DAYS: i64 = 4 * 366;
 
 /// A realtime schedule definition
 #[derive(Builder, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -99,6 +101,74 @@ impl Realtime {
             && minute_match
             && second_match
     }
+
+    /// The first time after `now`, in UTC and to the second, this schedule runs at, or `None`
+    /// if it does not run in the next four years
+    #[must_use]
+    pub fn next_after(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        let start = now
+            .to_offset(UtcOffset::UTC)
+            .replace_nanosecond(0)
+            .ok()?
+            .checked_add(Duration::SECOND)?;
+        let mut date = start.date();
+        for _ in 0..MAX_LOOKAHEAD_DAYS {
+            if self.date_matches(date) {
+                let earliest = if date == start.date() {
+                    start.time()
+                } else {
+                    Time::MIDNIGHT
+                };
+                if let Some(time) = self.first_time_from(earliest) {
+                    return Some(date.with_time(time).assume_utc());
+                }
+            }
+            date = date.next_day()?;
+        }
+        None
+    }
+
+    fn date_matches(&self, date: Date) -> bool {
+        let dow_match = match &self.day_of_week.0 {
+            Some(dows) => dows.contains(&date.weekday().number_days_from_sunday()),
+            None => true,
+        };
+        dow_match
+            && self.year.matches(date.year())
+            && MonthOfYear::from_u8(date.month().into())
+                .is_some_and(|month| self.month.matches(month))
+            && DayOfMonth::from_u8(date.day()).is_some_and(|day| self.day.matches(day))
+    }
+
+    /// The first time of day at or after `earliest` this schedule runs at
+    fn first_time_from(&self, earliest: Time) -> Option<Time> {
+        for hour in earliest.hour()..24 {
+            if !HourOfDay::from_u8(hour).is_some_and(|h| self.hour.matches(h)) {
+                continue;
+            }
+            let first_minute = if hour == earliest.hour() {
+                earliest.minute()
+            } else {
+                0
+            };
+            for minute in first_minute..60 {
+                if !MinuteOfHour::from_u8(minute).is_some_and(|m| self.minute.matches(m)) {
+                    continue;
+                }
+                let first_second = if (hour, minute) == (earliest.hour(), earliest.minute()) {
+                    earliest.second()
+                } else {
+                    0
+                };
+                if let Some(second) = (first_second..60).find(|second| {
+                    SecondOfMinute::from_u8(*second).is_some_and(|s| self.second.matches(s))
+                }) {
+                    return Time::from_hms(hour, minute, second).ok();
+                }
+            }
+        }
+        None
+    }
 }
 
 impl TryFrom<&str> for Realtime {
@@ -233,7 +303,10 @@ impl Display for Realtime {
 #[cfg(test)]
 mod tests {
     use proptest::{prelude::proptest, prop_compose};
-    use time::{OffsetDateTime, macros::date};
+    use time::{
+        OffsetDateTime,
+        macros::{date, datetime},
+    };
 
     use crate::realtime::{dow::test::arb_dow, hms::test::arb_hms, ymd::test::arb_ymd};
 
@@ -278,6 +351,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn next_after_daily_is_next_midnight() {
+        let rt = Realtime::try_from("daily").unwrap();
+        let now = datetime!(2025-03-04 10:20:30 UTC);
+        assert_eq!(rt.next_after(now), Some(datetime!(2025-03-05 00:00:00 UTC)));
+    }
+
+    #[test]
+    fn next_after_is_strictly_after_now() {
+        let rt = Realtime::try_from("minutely").unwrap();
+        let now = datetime!(2025-03-04 10:20:00.5 UTC);
+        assert_eq!(rt.next_after(now), Some(datetime!(2025-03-04 10:21:00 UTC)));
+    }
+
+    #[test]
+    fn next_after_finds_later_time_today() {
+        let rt = Realtime::try_from("* 10,14:30:00").unwrap();
+        let now = datetime!(2025-03-04 10:30:00 UTC);
+        assert_eq!(rt.next_after(now), Some(datetime!(2025-03-04 14:30:00 UTC)));
+    }
+
+    #[test]
+    fn next_after_honours_day_of_week_and_offset() {
+        // 2025-03-04 is a Tuesday
+        let rt = Realtime::try_from("Mon * 00:00:00").unwrap();
+        let now = datetime!(2025-03-04 01:00:00 +02:00);
+        assert_eq!(rt.next_after(now), Some(datetime!(2025-03-10 00:00:00 UTC)));
+    }
+
+    #[test]
+    fn next_after_reaches_leap_day() {
+        let rt = Realtime::try_from("*,2,29 12:00:00").unwrap();
+        let now = datetime!(2024-03-01 00:00:00 UTC);
+        assert_eq!(rt.next_after(now), Some(datetime!(2028-02-29 12:00:00 UTC)));
+    }
+
+    #[test]
+    fn next_after_past_year_is_none() {
+        let rt = Realtime::try_from("2020,*,* 00:00:00").unwrap();
+        assert_eq!(rt.next_after(datetime!(2025-01-01 00:00:00 UTC)), None);
+    }
+
     #[test]
     fn empty_string_errors() {
         assert!(Realtime::try_from("").is_err());
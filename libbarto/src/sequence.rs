@@ -0,0 +1,84 @@
+// Copyright (c) 2025 barto developers
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::Error;
+
+/// The WebSocket upgrade header carrying the session id a bartoc generated for a connection.
+///
+/// `bartos` echoes the header on the upgrade response when it will sequence the messages it
+/// sends on that connection, see [`sequence_wrap`].
+pub const SESSION_HEADER: &str = "X-Barto-Session";
+
+/// Number of bytes in the sequence header: 16 (session id) + 8 (counter).
+pub const SEQUENCE_HEADER_LEN: usize = 24;
+
+/// Prefix `payload` with the connection's session id and a per-connection message counter.
+///
+/// Wire format: `[16-byte session id][8-byte counter BE][payload]`. The sequence header is
+/// applied inside the HMAC and Ed25519 layers, so it is covered by both.
+#[must_use]
+pub fn sequence_wrap(session: &Uuid, counter: u64, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(SEQUENCE_HEADER_LEN + payload.len());
+    out.extend_from_slice(session.as_bytes());
+    out.extend_from_slice(&counter.to_be_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Split a sequenced message into `(session id, counter, payload)`.
+///
+/// # Errors
+/// Returns [`Error::SequenceInvalid`] if the data is shorter than the sequence header.
+pub fn sequence_unwrap(data: &[u8]) -> Result<(Uuid, u64, Vec<u8>)> {
+    if data.len() < SEQUENCE_HEADER_LEN {
+        return Err(Error::SequenceInvalid.into());
+    }
+    let (session_bytes, rest) = data.split_at(16);
+    let (counter_bytes, payload) = rest.split_at(8);
+    let session = Uuid::from_slice(session_bytes).map_err(|_| Error::SequenceInvalid)?;
+    let counter = u64::from_be_bytes(
+        counter_bytes
+            .try_into()
+            .map_err(|_| Error::SequenceInvalid)?,
+    );
+    Ok((session, counter, payload.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{SEQUENCE_HEADER_LEN, sequence_unwrap, sequence_wrap};
+
+    #[test]
+    fn wrap_and_unwrap() {
+        let session = Uuid::new_v4();
+        let wrapped = sequence_wrap(&session, 42, b"payload");
+        assert_eq!(wrapped.len(), SEQUENCE_HEADER_LEN + 7);
+        let (unwrapped_session, counter, payload) = sequence_unwrap(&wrapped).unwrap();
+        assert_eq!(unwrapped_session, session);
+        assert_eq!(counter, 42);
+        assert_eq!(payload, b"payload");
+    }
+
+    #[test]
+    fn unwrap_empty_payload() {
+        let session = Uuid::new_v4();
+        let (_, counter, payload) = sequence_unwrap(&sequence_wrap(&session, 1, b"")).unwrap();
+        assert_eq!(counter, 1);
+        assert!(payload.is_empty());
+    }
+
+    #[test]
+    fn unwrap_too_short() {
+        assert!(sequence_unwrap(&[0u8; SEQUENCE_HEADER_LEN - 1]).is_err());
+    }
+}
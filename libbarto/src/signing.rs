@@ -6,13 +6,32 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+use std::collections::BTreeMap;
+
 use anyhow::Result;
 use base64::{Engine, engine::general_purpose::STANDARD};
 use ed25519_dalek::{Signature, Signer, Verifier};
 pub use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand::RngExt as _;
 
 use crate::Error;
 
+/// Number of bytes in the key ID that prefixes a keyed signature or HMAC envelope.
+pub const KEY_ID_LEN: usize = 4;
+
+/// Generate a new random Ed25519 signing key.
+#[must_use]
+pub fn generate_signing_key() -> SigningKey {
+    let seed: [u8; 32] = rand::rng().random();
+    SigningKey::from_bytes(&seed)
+}
+
+/// Return the base64-encoded 32-byte seed of a signing key, as accepted by [`parse_signing_key`].
+#[must_use]
+pub fn signing_key_b64(key: &SigningKey) -> String {
+    STANDARD.encode(key.as_bytes())
+}
+
 /// Parse a base64-encoded Ed25519 signing (private) key.
 ///
 /// The key must be a base64-encoded 32-byte seed.
@@ -94,12 +113,57 @@ pub fn verify_and_extract(key: &VerifyingKey, data: &[u8]) -> Result<Vec<u8>> {
     Ok(payload.to_vec())
 }
 
+/// Sign a payload under a key ID, returning `[4-byte key id BE][64-byte signature][payload]`.
+///
+/// The signature covers the key ID followed by the payload, so the ID cannot be swapped.
+#[must_use]
+pub fn sign_payload_with_id(key: &SigningKey, key_id: u32, payload: &[u8]) -> Vec<u8> {
+    let id_bytes = key_id.to_be_bytes();
+    let signature = key.sign(&[id_bytes.as_slice(), payload].concat());
+    let mut result = Vec::with_capacity(KEY_ID_LEN + 64 + payload.len());
+    result.extend_from_slice(&id_bytes);
+    result.extend_from_slice(&signature.to_bytes());
+    result.extend_from_slice(payload);
+    result
+}
+
+/// Verify a keyed signature prefix (see [`sign_payload_with_id`]) against the accepted key with
+/// the embedded key ID and return the payload.
+///
+/// # Errors
+/// Returns [`Error::SignatureInvalid`] if the data is too short or the signature does not verify.
+/// Returns [`Error::UnknownKeyId`] if no accepted key has the embedded key ID.
+///
+pub fn verify_and_extract_with_id(
+    keys: &BTreeMap<u32, VerifyingKey>,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    if data.len() < KEY_ID_LEN + 64 {
+        return Err(Error::SignatureInvalid.into());
+    }
+    let (id_bytes, rest) = data.split_at(KEY_ID_LEN);
+    let (sig_bytes, payload) = rest.split_at(64);
+    let key_id = u32::from_be_bytes(id_bytes.try_into().map_err(|_| Error::SignatureInvalid)?);
+    let key = keys.get(&key_id).ok_or(Error::UnknownKeyId(key_id))?;
+    let sig_arr: [u8; 64] = sig_bytes.try_into().map_err(|_| Error::SignatureInvalid)?;
+    key.verify(
+        &[id_bytes, payload].concat(),
+        &Signature::from_bytes(&sig_arr),
+    )
+    .map_err(|_| Error::SignatureInvalid)?;
+    Ok(payload.to_vec())
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use super::{
-        key_fingerprint, parse_signing_key, parse_verifying_key, public_key_b64, sign_payload,
-        verify_and_extract,
+        generate_signing_key, key_fingerprint, parse_signing_key, parse_verifying_key,
+        public_key_b64, sign_payload, sign_payload_with_id, signing_key_b64, verify_and_extract,
+        verify_and_extract_with_id,
     };
+    use crate::Error;
     use base64::{Engine, engine::general_purpose::STANDARD};
     use ed25519_dalek::SigningKey;
 
@@ -206,4 +270,54 @@ mod tests {
         let vk2 = parse_verifying_key(&pk_b64_2).unwrap();
         assert_ne!(key_fingerprint(&vk1), key_fingerprint(&vk2));
     }
+
+    fn keyring() -> BTreeMap<u32, super::VerifyingKey> {
+        BTreeMap::from([
+            (1, make_keypair(1).0.verifying_key()),
+            (2, make_keypair(2).0.verifying_key()),
+        ])
+    }
+
+    #[test]
+    fn test_sign_and_verify_with_id() {
+        let (sk, _, _) = make_keypair(2);
+        let signed = sign_payload_with_id(&sk, 2, b"payload");
+        assert_eq!(signed.len(), 4 + 64 + 7);
+        assert_eq!(
+            verify_and_extract_with_id(&keyring(), &signed).unwrap(),
+            b"payload"
+        );
+    }
+
+    #[test]
+    fn test_verify_with_id_unknown_key_id() {
+        let (sk, _, _) = make_keypair(3);
+        let signed = sign_payload_with_id(&sk, 3, b"payload");
+        let err = verify_and_extract_with_id(&keyring(), &signed).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::UnknownKeyId(3))
+        ));
+    }
+
+    #[test]
+    fn test_verify_with_id_swapped_key_id() {
+        let (sk, _, _) = make_keypair(1);
+        let mut signed = sign_payload_with_id(&sk, 1, b"payload");
+        signed[..4].copy_from_slice(&2_u32.to_be_bytes());
+        assert!(verify_and_extract_with_id(&keyring(), &signed).is_err());
+    }
+
+    #[test]
+    fn test_verify_with_id_too_short() {
+        assert!(verify_and_extract_with_id(&keyring(), &[0u8; 67]).is_err());
+    }
+
+    #[test]
+    fn test_generate_signing_key_roundtrip() {
+        let sk = generate_signing_key();
+        let parsed = parse_signing_key(&signing_key_b64(&sk)).unwrap();
+        assert_eq!(parsed.as_bytes(), sk.as_bytes());
+        assert_ne!(generate_signing_key().as_bytes(), sk.as_bytes());
+    }
 }
@@ -15,6 +15,7 @@ use rustls::{
     server::WebPkiClientVerifier,
 };
 use tracing::trace;
+use x509_parser::{extensions::GeneralName, parse_x509_certificate};
 
 use crate::Error;
 
@@ -136,11 +137,58 @@ pub fn load_pinned_root_store(path: &Path) -> Result<RootCertStore> {
     Ok(root_store)
 }
 
+/// Returns the identities a client certificate was issued for: the subject common name(s)
+/// first, followed by any DNS subject alternative names.
+///
+/// Used to bind a client name to a verified mutual TLS certificate. Duplicates are removed,
+/// preserving order, so the first entry is the certificate's primary identity.
+///
+/// # Errors
+/// * Returns an error if the certificate cannot be parsed as DER-encoded X.509
+///
+pub fn cert_identities(cert: &CertificateDer<'_>) -> Result<Vec<String>> {
+    let (_, x509) = parse_x509_certificate(cert.as_ref()).map_err(|_| Error::CertParse)?;
+    let common_names = x509
+        .subject()
+        .iter_common_name()
+        .filter_map(|cn| cn.as_str().ok())
+        .map(ToString::to_string);
+    let dns_names = x509
+        .subject_alternative_name()
+        .map_err(|_| Error::CertParse)?
+        .into_iter()
+        .flat_map(|san| san.value.general_names.iter())
+        .filter_map(|name| match name {
+            GeneralName::DNSName(dns) => Some((*dns).to_string()),
+            _ => None,
+        });
+    let mut identities: Vec<String> = vec![];
+    for identity in common_names.chain(dns_names) {
+        if !identities.contains(&identity) {
+            identities.push(identity);
+        }
+    }
+    Ok(identities)
+}
+
 #[cfg(test)]
 mod test {
-    use std::path::Path;
+    use std::{fs::File, io::BufReader, path::Path};
+
+    use rustls::pki_types::{CertificateDer, pem::PemObject as _};
+
+    use super::{
+        TlsConfig, cert_identities, load_client_cert_and_key, load_pinned_root_store,
+        load_tls_config,
+    };
 
-    use super::{TlsConfig, load_client_cert_and_key, load_pinned_root_store, load_tls_config};
+    fn first_cert(path: &str) -> CertificateDer<'static> {
+        let reader = &mut BufReader::new(File::open(path).unwrap());
+        CertificateDer::pem_reader_iter(reader)
+            .next()
+            .unwrap()
+            .unwrap()
+    }
 
     struct MockTlsConfig;
     struct MockEmptyKeysTlsConfig;
@@ -267,4 +315,34 @@ mod test {
             .is_err()
         );
     }
+
+    #[test]
+    fn test_cert_identities_common_name() {
+        let identities = cert_identities(&first_cert("./testtls/test-client.pem")).unwrap();
+        assert_eq!(identities, vec!["barto test client".to_string()]);
+    }
+
+    #[test]
+    fn test_cert_identities_common_name_and_dns_sans() {
+        let identities = cert_identities(&first_cert("./testtls/test-client-san.pem")).unwrap();
+        assert_eq!(
+            identities,
+            vec![
+                "host1".to_string(),
+                "host1.example.com".to_string(),
+                "host1-alt".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cert_identities_no_common_name() {
+        let identities = cert_identities(&first_cert("./testtls/onlytests.pem")).unwrap();
+        assert!(identities.is_empty());
+    }
+
+    #[test]
+    fn test_cert_identities_invalid_der() {
+        assert!(cert_identities(&CertificateDer::from(vec![0_u8; 8])).is_err());
+    }
 }
@@ -6,18 +6,30 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::path::PathBuf;
+use std::{
+    fs::{File, OpenOptions, create_dir_all, remove_file, rename},
+    io::{self, Write},
+    num::{NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 
 use anyhow::Result;
 use dirs2::data_dir;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig as _};
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
 use tracing::{Level, level_filters::LevelFilter, subscriber::DefaultGuard};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::{EnvFilter, Layer, Registry, fmt::time::UtcTime};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry,
+    fmt::{time::UtcTime, writer::BoxMakeWriter},
+};
 #[cfg(not(test))]
 use tracing_subscriber_init::try_init;
-use tracing_subscriber_init::{Iso8601, TracingConfig, compact};
+use tracing_subscriber_init::{Iso8601, TracingConfig, compact, json, pretty};
 
-use crate::{Error, PathDefaults, utils::to_path_buf};
+use crate::{Error, LogFormat, LogRotation, Otlp, PathDefaults, utils::to_path_buf};
 
 /// Extension trait for `TracingConfig` to add additional configuration options
 pub trait TracingConfigExt: TracingConfig {
@@ -29,6 +41,18 @@ pub trait TracingConfigExt: TracingConfig {
     fn level(&self) -> Level;
 }
 
+/// Extension trait for the `TracingConfig` of the file layer to configure the log file
+pub trait TracingFileConfigExt: TracingConfigExt {
+    /// The format of the lines written to the log file
+    fn format(&self) -> LogFormat;
+    /// How often the log file is rotated
+    fn rotation(&self) -> LogRotation;
+    /// Rotate the log file once it grows past this many bytes, instead of on [`rotation`](Self::rotation)
+    fn max_size(&self) -> Option<NonZeroU64>;
+    /// The number of log files to keep, `None` keeps them all
+    fn max_files(&self) -> Option<NonZeroUsize>;
+}
+
 /// Initialize tracing
 ///
 /// # Errors
@@ -43,7 +67,7 @@ pub fn init_tracing<T, U, V>(
 where
     T: TracingConfigExt,
     U: PathDefaults,
-    V: TracingConfigExt,
+    V: TracingFileConfigExt,
 {
     let mut layers = layers_opt.unwrap_or_default();
 
@@ -64,22 +88,213 @@ where
 
     // Setup the tracing file layer
     let (directory, logfile) = tracing_absolute_path(defaults)?;
-    let tracing_file = RollingFileAppender::new(Rotation::DAILY, directory, logfile);
-    let (layer, _level_filter) = compact(file);
+    let tracing_file = file_writer(file, &directory, &logfile)?;
+    layers.push(file_layer(file, tracing_file));
+
+    let _guard_opt = try_initialize(layers)?;
+    Ok(())
+}
+
+fn file_writer<V>(file: &V, directory: &Path, logfile: &Path) -> Result<BoxMakeWriter>
+where
+    V: TracingFileConfigExt,
+{
+    if let Some(max_size) = file.max_size() {
+        let writer = SizeRollingFile::new(directory.join(logfile), max_size, file.max_files())?;
+        return Ok(BoxMakeWriter::new(Mutex::new(writer)));
+    }
+    let rotation = match file.rotation() {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Weekly => Rotation::WEEKLY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    let builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(logfile.to_string_lossy());
+    let builder = if let Some(max_files) = file.max_files() {
+        builder.max_log_files(max_files.get())
+    } else {
+        builder
+    };
+    Ok(BoxMakeWriter::new(builder.build(directory)?))
+}
+
+fn file_layer<V>(file: &V, writer: BoxMakeWriter) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    V: TracingFileConfigExt,
+{
     let level_filter = LevelFilter::from(file.level());
     let directives = directives(file, level_filter);
     let filter = EnvFilter::builder()
         .with_default_directive(level_filter.into())
         .parse_lossy(directives);
-    let file_layer = layer
-        .with_ansi_sanitization(false)
-        .with_timer(UtcTime::new(Iso8601::DEFAULT))
-        .with_writer(tracing_file)
-        .with_filter(filter);
-    layers.push(file_layer.boxed());
+    let timer = UtcTime::new(Iso8601::DEFAULT);
+    match file.format() {
+        LogFormat::Compact => compact(file)
+            .0
+            .with_ansi_sanitization(false)
+            .with_timer(timer)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+        LogFormat::Json => json(file)
+            .0
+            .with_ansi_sanitization(false)
+            .with_timer(timer)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+        LogFormat::Pretty => pretty(file)
+            .0
+            .with_ansi_sanitization(false)
+            .with_timer(timer)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+    }
+}
 
-    let _guard_opt = try_initialize(layers)?;
-    Ok(())
+/// A log file rotated once it grows past a size, the rotated files are numbered from
+/// newest to oldest as `<logfile>.1`, `<logfile>.2`, ...
+#[derive(Debug)]
+struct SizeRollingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: Option<NonZeroUsize>,
+    file: Option<File>,
+    size: u64,
+}
+
+impl SizeRollingFile {
+    fn new(path: PathBuf, max_size: NonZeroU64, max_files: Option<NonZeroUsize>) -> Result<Self> {
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut rolling = Self {
+            path,
+            max_size: max_size.get(),
+            max_files,
+            file: None,
+            size: 0,
+        };
+        let _ = rolling.open()?;
+        Ok(rolling)
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        PathBuf::from(path)
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("log file not open"))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        drop(self.file.take());
+        // The current file counts towards the files kept
+        let keep = self.max_files.map(|max_files| max_files.get() - 1);
+        let mut next = 1;
+        while self.rotated(next).exists() {
+            next += 1;
+        }
+        if let Some(keep) = keep {
+            // Make room for the current file as `.1`
+            for n in keep.max(1)..next {
+                remove_file(self.rotated(n))?;
+            }
+            next = next.min(keep.max(1));
+        }
+        for n in (1..next).rev() {
+            rename(self.rotated(n), self.rotated(n + 1))?;
+        }
+        if keep == Some(0) {
+            remove_file(&self.path)
+        } else {
+            rename(&self.path, self.rotated(1))
+        }
+    }
+}
+
+impl Write for SizeRollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = u64::try_from(buf.len()).unwrap_or(u64::MAX);
+        if self.size > 0
+            && self.size.saturating_add(len) > self.max_size
+            && let Err(e) = self.rotate()
+        {
+            // Keep logging to the current file rather than lose the line
+            eprintln!("unable to rotate {}: {e}", self.path.display());
+        }
+        let written = self.open()?.write(buf)?;
+        self.size += u64::try_from(written).unwrap_or(u64::MAX);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().map_or(Ok(()), File::flush)
+    }
+}
+
+/// Exports the spans batched by an [`otlp_layer`], flushing what is left when dropped
+#[derive(Debug)]
+pub struct OtlpGuard {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("unable to flush the remaining spans: {e}");
+        }
+    }
+}
+
+/// Build a layer exporting spans to an OpenTelemetry collector over OTLP/HTTP, to pass to
+/// [`init_tracing`]. Keep the guard alive for as long as spans should be exported.
+///
+/// # Errors
+/// * If the exporter cannot be built
+///
+pub fn otlp_layer(
+    otlp: &Otlp,
+    service_name: &str,
+) -> Result<(Box<dyn Layer<Registry> + Send + Sync>, OtlpGuard)> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(otlp.endpoint())
+        .build()?;
+    let resource = Resource::builder()
+        .with_service_name(
+            otlp.service_name()
+                .clone()
+                .unwrap_or_else(|| service_name.to_string()),
+        )
+        .build();
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build();
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .parse_lossy(otlp.directives().as_deref().unwrap_or("info"));
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(service_name.to_string()))
+        .with_filter(filter);
+    Ok((layer.boxed(), OtlpGuard { provider }))
 }
 
 #[cfg(not(test))]
@@ -150,12 +365,95 @@ where
 
 #[cfg(test)]
 mod test {
-    use tempfile::NamedTempFile;
-    use tracing::level_filters::LevelFilter;
+    use std::{
+        fs::read_to_string,
+        io::{BufRead as _, BufReader, Read as _, Write as _},
+        net::TcpListener,
+        num::{NonZeroU64, NonZeroUsize},
+        path::Path,
+        sync::mpsc::{Receiver, channel},
+        thread::spawn,
+        time::Duration,
+    };
+
+    use serde_json::Value;
+    use tempfile::{NamedTempFile, tempdir};
+    use tracing::{info, info_span, level_filters::LevelFilter, subscriber::with_default};
+    use tracing_subscriber::{Registry, layer::SubscriberExt as _};
 
-    use crate::{PathDefaults, utils::test::TestConfig};
+    use crate::{LogFormat, Otlp, PathDefaults, TraceParent, utils::test::TestConfig};
+
+    use super::{SizeRollingFile, directives, file_layer, file_writer, init_tracing, otlp_layer};
+
+    /// A stand-in OTLP collector, passing on the path and body of each export request
+    fn collector() -> (String, Receiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        let (tx, rx) = channel();
+        let _handle = spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                let _ = reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    let _ = reader.read_line(&mut header).unwrap();
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                    .unwrap();
+                let path = request_line
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_string();
+                if tx.send((path, body)).is_err() {
+                    break;
+                }
+            }
+        });
+        (endpoint, rx)
+    }
 
-    use super::{directives, init_tracing};
+    fn contains(body: &[u8], needle: &str) -> bool {
+        body.windows(needle.len()).any(|w| w == needle.as_bytes())
+    }
+
+    #[test]
+    fn otlp_layer_exports_to_the_collector() {
+        let (endpoint, rx) = collector();
+        let otlp = Otlp::builder().endpoint(endpoint).build();
+        let (layer, guard) = otlp_layer(&otlp, "barto_test").unwrap();
+        with_default(Registry::default().with(layer), || {
+            let job = info_span!("job_run");
+            let trace_parent = TraceParent::of(&job).unwrap();
+            // A span on the other side of the link joins the trace of the run
+            let store = info_span!("store_status");
+            trace_parent.adopt(&store);
+            let joined = TraceParent::of(&store).unwrap();
+            assert_eq!(joined.to_string()[..35], trace_parent.to_string()[..35]);
+            assert_ne!(joined, trace_parent);
+        });
+        drop(guard);
+
+        let (path, body) = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(path, "/v1/traces");
+        assert!(contains(&body, "barto_test"));
+        assert!(contains(&body, "job_run"));
+        assert!(contains(&body, "store_status"));
+    }
 
     impl PathDefaults for TestConfig {
         fn default_tracing_path(&self) -> String {
@@ -210,6 +508,95 @@ mod test {
         assert!(res.is_ok());
     }
 
+    fn log_lines(config: &TestConfig, directory: &Path, lines: usize) {
+        let writer = file_writer(config, directory, Path::new("barto.log")).unwrap();
+        let layer = file_layer(config, writer);
+        with_default(Registry::default().with(layer), || {
+            for line in 0..lines {
+                info!(line, "logged");
+            }
+        });
+    }
+
+    #[test]
+    fn file_layer_writes_json_lines() {
+        let directory = tempdir().unwrap();
+        let config = TestConfig::with_file(LogFormat::Json, NonZeroU64::new(1 << 20), None);
+        log_lines(&config, directory.path(), 2);
+
+        let logged = read_to_string(directory.path().join("barto.log")).unwrap();
+        let lines = logged
+            .lines()
+            .map(serde_json::from_str::<Value>)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[1]["fields"]["message"], "logged");
+        assert_eq!(lines[1]["fields"]["line"], 1);
+    }
+
+    #[test]
+    fn file_layer_rotates_by_size() {
+        let directory = tempdir().unwrap();
+        let config = TestConfig::with_file(LogFormat::Compact, NonZeroU64::new(1), None);
+        log_lines(&config, directory.path(), 4);
+
+        let logfile = directory.path().join("barto.log");
+        assert!(read_to_string(&logfile).unwrap().contains("line=3"));
+        for (n, line) in [(1, 2), (2, 1), (3, 0)] {
+            let rotated = read_to_string(directory.path().join(format!("barto.log.{n}"))).unwrap();
+            assert!(rotated.contains(&format!("line={line}")), "{rotated}");
+        }
+    }
+
+    #[test]
+    fn file_layer_keeps_max_files() {
+        let directory = tempdir().unwrap();
+        let config =
+            TestConfig::with_file(LogFormat::Pretty, NonZeroU64::new(1), NonZeroUsize::new(2));
+        log_lines(&config, directory.path(), 4);
+
+        let mut files = directory
+            .path()
+            .read_dir()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["barto.log", "barto.log.1"]);
+        let rotated = read_to_string(directory.path().join("barto.log.1")).unwrap();
+        assert!(rotated.contains("line: 2"), "{rotated}");
+    }
+
+    #[test]
+    fn size_rolling_file_keeps_only_the_current_file() {
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("barto.log");
+        let mut rolling = SizeRollingFile::new(
+            path.clone(),
+            NonZeroU64::new(4).unwrap(),
+            NonZeroUsize::new(1),
+        )
+        .unwrap();
+        rolling.write_all(b"first").unwrap();
+        rolling.write_all(b"second").unwrap();
+        rolling.flush().unwrap();
+
+        assert_eq!(read_to_string(&path).unwrap(), "second");
+        assert!(!directory.path().join("barto.log.1").exists());
+    }
+
+    #[test]
+    fn file_writer_keeps_max_files_with_time_rotation() {
+        let directory = tempdir().unwrap();
+        let config = TestConfig::with_file(LogFormat::Compact, None, NonZeroUsize::new(3));
+        log_lines(&config, directory.path(), 1);
+
+        let files = directory.path().read_dir().unwrap().count();
+        assert_eq!(files, 1);
+    }
+
     #[test]
     fn test_directives() {
         let config = TestConfig::default();
@@ -94,14 +94,17 @@ pub(crate) trait Mock {
 
 #[cfg(test)]
 pub(crate) mod test {
-    use std::time::Instant;
+    use std::{
+        num::{NonZeroU64, NonZeroUsize},
+        time::Instant,
+    };
 
     use bytes::Bytes;
     use tracing::Level;
     use tracing_subscriber_init::TracingConfig;
     use unicode_width::UnicodeWidthStr as _;
 
-    use crate::TracingConfigExt;
+    use crate::{LogFormat, LogRotation, TracingConfigExt, TracingFileConfigExt};
 
     use super::{clean_output_string, parse_ts_ping, send_ts_ping, to_path_buf};
 
@@ -110,15 +113,29 @@ pub(crate) mod test {
         quiet: u8,
         level: Level,
         directives: Option<String>,
+        format: LogFormat,
+        max_size: Option<NonZeroU64>,
+        max_files: Option<NonZeroUsize>,
     }
 
     impl TestConfig {
         pub(crate) fn with_directives() -> Self {
             Self {
-                verbose: 3,
-                quiet: 0,
-                level: Level::INFO,
                 directives: Some("actix_web=error".to_string()),
+                ..Self::default()
+            }
+        }
+
+        pub(crate) fn with_file(
+            format: LogFormat,
+            max_size: Option<NonZeroU64>,
+            max_files: Option<NonZeroUsize>,
+        ) -> Self {
+            Self {
+                format,
+                max_size,
+                max_files,
+                ..Self::default()
             }
         }
     }
@@ -130,6 +147,9 @@ pub(crate) mod test {
                 quiet: 0,
                 level: Level::INFO,
                 directives: None,
+                format: LogFormat::Compact,
+                max_size: None,
+                max_files: None,
             }
         }
     }
@@ -142,6 +162,10 @@ pub(crate) mod test {
         fn verbose(&self) -> u8 {
             self.verbose
         }
+
+        fn with_ansi(&self) -> bool {
+            false
+        }
     }
 
     impl TracingConfigExt for TestConfig {
@@ -158,6 +182,24 @@ pub(crate) mod test {
         }
     }
 
+    impl TracingFileConfigExt for TestConfig {
+        fn format(&self) -> LogFormat {
+            self.format
+        }
+
+        fn rotation(&self) -> LogRotation {
+            LogRotation::Daily
+        }
+
+        fn max_size(&self) -> Option<NonZeroU64> {
+            self.max_size
+        }
+
+        fn max_files(&self) -> Option<NonZeroUsize> {
+            self.max_files
+        }
+    }
+
     #[test]
     fn test_to_path_buf() {
         let path_str = String::from("/some/test/path");
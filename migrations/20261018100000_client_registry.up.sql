@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS client_registry
+(
+    id          BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    name        VARCHAR(256)                NOT NULL UNIQUE,
+    token_hash  CHAR(64)                    NULL,
+    public_key  VARCHAR(64)                 NULL,
+    enrolled_at TIMESTAMP                   NOT NULL DEFAULT NOW(),
+    revoked_at  TIMESTAMP                   NULL
+);
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS exit_status
+(
+    id          BIGINT  UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    timestamp   TIMESTAMP                    NOT NULL DEFAULT NOW(),
+    cmd_uuid    UUID                         NOT NULL,
+    exit_code   TINYINT UNSIGNED             NOT NULL,
+    success     BOOLEAN                      NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS exit_status_test
+(
+    id          BIGINT  UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    timestamp   TIMESTAMP                    NOT NULL DEFAULT NOW(),
+    cmd_uuid    UUID                         NOT NULL,
+    exit_code   TINYINT UNSIGNED             NOT NULL,
+    success     BOOLEAN                      NOT NULL
+);
+
+-- Runs that never finished have no exit status to restore.
+INSERT INTO exit_status (timestamp, cmd_uuid, exit_code, success)
+SELECT ended_at, HEX(cmd_uuid), IF(exit_code BETWEEN 0 AND 255, exit_code, 255), success
+FROM runs
+WHERE ended_at IS NOT NULL AND success IS NOT NULL;
+
+INSERT INTO exit_status_test (timestamp, cmd_uuid, exit_code, success)
+SELECT ended_at, HEX(cmd_uuid), IF(exit_code BETWEEN 0 AND 255, exit_code, 255), success
+FROM runs_test
+WHERE ended_at IS NOT NULL AND success IS NOT NULL;
+
+DROP TABLE IF EXISTS runs;
+DROP TABLE IF EXISTS runs_test;
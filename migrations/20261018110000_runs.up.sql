@@ -0,0 +1,73 @@
+CREATE TABLE IF NOT EXISTS runs
+(
+    id            BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    cmd_uuid      BINARY(16)                  NOT NULL UNIQUE,
+    bartoc_uuid   BINARY(16)                  NULL,
+    bartoc_name   VARCHAR(256)                NULL,
+    schedule_name VARCHAR(256)                NULL,
+    cmd           TEXT                        NULL,
+    trigger_type  VARCHAR(16)                 NULL,
+    attempt       INT UNSIGNED                NOT NULL DEFAULT 1,
+    started_at    TIMESTAMP(6)                NULL,
+    ended_at      TIMESTAMP(6)                NULL,
+    duration_ms   BIGINT                      NULL,
+    exit_code     INT                         NULL,
+    exit_signal   INT                         NULL,
+    success       BOOLEAN                     NULL,
+    INDEX runs_schedule (bartoc_name, schedule_name),
+    INDEX runs_started_at (started_at)
+);
+
+CREATE TABLE IF NOT EXISTS runs_test
+(
+    id            BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    cmd_uuid      BINARY(16)                  NOT NULL UNIQUE,
+    bartoc_uuid   BINARY(16)                  NULL,
+    bartoc_name   VARCHAR(256)                NULL,
+    schedule_name VARCHAR(256)                NULL,
+    cmd           TEXT                        NULL,
+    trigger_type  VARCHAR(16)                 NULL,
+    attempt       INT UNSIGNED                NOT NULL DEFAULT 1,
+    started_at    TIMESTAMP(6)                NULL,
+    ended_at      TIMESTAMP(6)                NULL,
+    duration_ms   BIGINT                      NULL,
+    exit_code     INT                         NULL,
+    exit_signal   INT                         NULL,
+    success       BOOLEAN                     NULL,
+    INDEX runs_test_schedule (bartoc_name, schedule_name),
+    INDEX runs_test_started_at (started_at)
+);
+
+-- Existing runs only have an exit status; take the start from their first output line.
+INSERT IGNORE INTO runs
+    (cmd_uuid, bartoc_uuid, bartoc_name, schedule_name, trigger_type, started_at, ended_at,
+     duration_ms, exit_code, success)
+SELECT
+    s.cmd_uuid, o.bartoc_uuid, o.bartoc_name, o.cmd_name, 'schedule', o.started_at, s.timestamp,
+    TIMESTAMPDIFF(MICROSECOND, o.started_at, s.timestamp) DIV 1000, s.exit_code, s.success
+FROM
+    (SELECT UNHEX(REPLACE(CAST(cmd_uuid AS CHAR), '-', '')) AS cmd_uuid, timestamp, exit_code, success
+     FROM exit_status) s
+LEFT JOIN
+    (SELECT cmd_uuid, MIN(bartoc_uuid) AS bartoc_uuid, MIN(bartoc_name) AS bartoc_name,
+            MIN(cmd_name) AS cmd_name, MIN(timestamp) AS started_at
+     FROM output GROUP BY cmd_uuid) o
+ON o.cmd_uuid = s.cmd_uuid;
+
+INSERT IGNORE INTO runs_test
+    (cmd_uuid, bartoc_uuid, bartoc_name, schedule_name, trigger_type, started_at, ended_at,
+     duration_ms, exit_code, success)
+SELECT
+    s.cmd_uuid, o.bartoc_uuid, o.bartoc_name, o.cmd_name, 'schedule', o.started_at, s.timestamp,
+    TIMESTAMPDIFF(MICROSECOND, o.started_at, s.timestamp) DIV 1000, s.exit_code, s.success
+FROM
+    (SELECT UNHEX(REPLACE(CAST(cmd_uuid AS CHAR), '-', '')) AS cmd_uuid, timestamp, exit_code, success
+     FROM exit_status_test) s
+LEFT JOIN
+    (SELECT cmd_uuid, MIN(bartoc_uuid) AS bartoc_uuid, MIN(bartoc_name) AS bartoc_name,
+            MIN(cmd_name) AS cmd_name, MIN(timestamp) AS started_at
+     FROM output_test GROUP BY cmd_uuid) o
+ON o.cmd_uuid = s.cmd_uuid;
+
+DROP TABLE IF EXISTS exit_status;
+DROP TABLE IF EXISTS exit_status_test;
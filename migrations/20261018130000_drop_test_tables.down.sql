@@ -0,0 +1,2 @@
+CREATE TABLE IF NOT EXISTS output_test LIKE output;
+CREATE TABLE IF NOT EXISTS runs_test LIKE runs;
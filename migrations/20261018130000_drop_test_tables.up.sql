@@ -0,0 +1,3 @@
+-- Tests isolate themselves with their own database, so the test tables are no longer used.
+DROP TABLE IF EXISTS runs_test;
+DROP TABLE IF EXISTS output_test;
@@ -0,0 +1 @@
+ALTER TABLE output DROP INDEX output_data_fulltext;
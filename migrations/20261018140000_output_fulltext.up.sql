@@ -0,0 +1,2 @@
+-- Index the output text so `barto-cli search` can use MATCH ... AGAINST instead of LIKE.
+ALTER TABLE output ADD FULLTEXT INDEX output_data_fulltext (data);
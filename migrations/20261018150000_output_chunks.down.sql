@@ -0,0 +1,2 @@
+-- Chunked output is not moved back into the output table.
+DROP TABLE IF EXISTS output_chunks;
@@ -0,0 +1,25 @@
+-- Output is stored as chunks of lines per run rather than a row per line. New lines are
+-- inserted as open, uncompressed chunks, which are sealed into zstd-compressed chunks of up
+-- to 1000 lines once they hold that many or the run ends. bartos moves the rows of the
+-- output table into chunks when it starts; the full-text index over that table stays.
+
+CREATE TABLE IF NOT EXISTS output_chunks
+(
+    id          BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    bartoc_uuid BINARY(16)                  NOT NULL,
+    bartoc_name VARCHAR(256)                NOT NULL,
+    cmd_uuid    BINARY(16)                  NOT NULL,
+    cmd_name    VARCHAR(256)                NOT NULL,
+    first_line  BIGINT                      NOT NULL,
+    line_count  BIGINT                      NOT NULL,
+    first_at    TIMESTAMP(6)                NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    last_at     TIMESTAMP(6)                NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    words       MEDIUMTEXT                  NOT NULL,
+    sealed      BOOLEAN                     NOT NULL,
+    data        MEDIUMBLOB                  NOT NULL,
+    UNIQUE INDEX output_chunks_line (cmd_uuid, first_line),
+    INDEX output_chunks_last_at (last_at),
+    -- The compressed lines cannot be indexed, so `barto-cli search` matches the distinct
+    -- words of each chunk and bartos finds the lines within it.
+    FULLTEXT INDEX output_chunks_fulltext (words)
+);
@@ -0,0 +1,13 @@
+-- Every connect and disconnect of a bartoc client, so its history survives it going away.
+CREATE TABLE IF NOT EXISTS client_events
+(
+    id          BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+    bartoc_uuid BINARY(16)                  NOT NULL,
+    bartoc_name VARCHAR(256)                NOT NULL,
+    ip          VARCHAR(64)                 NOT NULL,
+    kind        VARCHAR(16)                 NOT NULL,
+    reason      VARCHAR(32)                 NULL,
+    close_code  INT                         NULL,
+    timestamp   TIMESTAMP(6)                NOT NULL,
+    INDEX client_events_name (bartoc_name, id)
+);
@@ -0,0 +1,75 @@
+CREATE TABLE IF NOT EXISTS output
+(
+    id          BIGSERIAL    PRIMARY KEY NOT NULL,
+    timestamp   TIMESTAMPTZ  NOT NULL,
+    bartoc_uuid UUID         NOT NULL,
+    bartoc_name TEXT         NOT NULL,
+    cmd_uuid    UUID         NOT NULL,
+    cmd_name    VARCHAR(256) NOT NULL DEFAULT 'unset',
+    kind        VARCHAR(6)   NOT NULL,
+    data        TEXT         NOT NULL
+);
+CREATE INDEX IF NOT EXISTS output_cmd_uuid ON output (cmd_uuid);
+
+CREATE TABLE IF NOT EXISTS output_test
+(
+    id          BIGSERIAL    PRIMARY KEY NOT NULL,
+    timestamp   TIMESTAMPTZ  NOT NULL,
+    bartoc_uuid UUID         NOT NULL,
+    bartoc_name TEXT         NOT NULL,
+    cmd_uuid    UUID         NOT NULL,
+    cmd_name    VARCHAR(256) NOT NULL DEFAULT 'unset',
+    kind        VARCHAR(6)   NOT NULL,
+    data        TEXT         NOT NULL
+);
+CREATE INDEX IF NOT EXISTS output_test_cmd_uuid ON output_test (cmd_uuid);
+
+CREATE TABLE IF NOT EXISTS runs
+(
+    id            BIGSERIAL    PRIMARY KEY NOT NULL,
+    cmd_uuid      UUID         NOT NULL UNIQUE,
+    bartoc_uuid   UUID         NULL,
+    bartoc_name   VARCHAR(256) NULL,
+    schedule_name VARCHAR(256) NULL,
+    cmd           TEXT         NULL,
+    trigger_type  VARCHAR(16)  NULL,
+    attempt       BIGINT       NOT NULL DEFAULT 1,
+    started_at    TIMESTAMPTZ  NULL,
+    ended_at      TIMESTAMPTZ  NULL,
+    duration_ms   BIGINT       NULL,
+    exit_code     INTEGER      NULL,
+    exit_signal   INTEGER      NULL,
+    success       BOOLEAN      NULL
+);
+CREATE INDEX IF NOT EXISTS runs_schedule ON runs (bartoc_name, schedule_name);
+CREATE INDEX IF NOT EXISTS runs_started_at ON runs (started_at);
+
+CREATE TABLE IF NOT EXISTS runs_test
+(
+    id            BIGSERIAL    PRIMARY KEY NOT NULL,
+    cmd_uuid      UUID         NOT NULL UNIQUE,
+    bartoc_uuid   UUID         NULL,
+    bartoc_name   VARCHAR(256) NULL,
+    schedule_name VARCHAR(256) NULL,
+    cmd           TEXT         NULL,
+    trigger_type  VARCHAR(16)  NULL,
+    attempt       BIGINT       NOT NULL DEFAULT 1,
+    started_at    TIMESTAMPTZ  NULL,
+    ended_at      TIMESTAMPTZ  NULL,
+    duration_ms   BIGINT       NULL,
+    exit_code     INTEGER      NULL,
+    exit_signal   INTEGER      NULL,
+    success       BOOLEAN      NULL
+);
+CREATE INDEX IF NOT EXISTS runs_test_schedule ON runs_test (bartoc_name, schedule_name);
+CREATE INDEX IF NOT EXISTS runs_test_started_at ON runs_test (started_at);
+
+CREATE TABLE IF NOT EXISTS client_registry
+(
+    id          BIGSERIAL    PRIMARY KEY NOT NULL,
+    name        VARCHAR(256) NOT NULL UNIQUE,
+    token_hash  VARCHAR(64)  NULL,
+    public_key  VARCHAR(64)  NULL,
+    enrolled_at TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    revoked_at  TIMESTAMPTZ  NULL
+);
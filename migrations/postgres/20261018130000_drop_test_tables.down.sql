@@ -0,0 +1,2 @@
+CREATE TABLE IF NOT EXISTS output_test (LIKE output INCLUDING ALL);
+CREATE TABLE IF NOT EXISTS runs_test (LIKE runs INCLUDING ALL);
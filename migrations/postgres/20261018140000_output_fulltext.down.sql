@@ -0,0 +1 @@
+DROP INDEX IF EXISTS output_data_fulltext;
@@ -0,0 +1,3 @@
+-- Index the output text so `barto-cli search` can match a tsquery instead of scanning with LIKE.
+-- The 'simple' configuration lowercases words without stemming or dropping stop words.
+CREATE INDEX IF NOT EXISTS output_data_fulltext ON output USING GIN (to_tsvector('simple', data));
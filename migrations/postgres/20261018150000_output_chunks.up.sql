@@ -0,0 +1,26 @@
+-- Output is stored as chunks of lines per run rather than a row per line. New lines are
+-- inserted as open, uncompressed chunks, which are sealed into zstd-compressed chunks of up
+-- to 1000 lines once they hold that many or the run ends. bartos moves the rows of the
+-- output table into chunks when it starts; the full-text index over that table stays.
+
+CREATE TABLE IF NOT EXISTS output_chunks
+(
+    id          BIGSERIAL    PRIMARY KEY NOT NULL,
+    bartoc_uuid UUID         NOT NULL,
+    bartoc_name TEXT         NOT NULL,
+    cmd_uuid    UUID         NOT NULL,
+    cmd_name    VARCHAR(256) NOT NULL,
+    first_line  BIGINT       NOT NULL,
+    line_count  BIGINT       NOT NULL,
+    first_at    TIMESTAMPTZ  NOT NULL,
+    last_at     TIMESTAMPTZ  NOT NULL,
+    words       TEXT         NOT NULL,
+    sealed      BOOLEAN      NOT NULL,
+    data        BYTEA        NOT NULL,
+    UNIQUE (cmd_uuid, first_line)
+);
+CREATE INDEX IF NOT EXISTS output_chunks_last_at ON output_chunks (last_at);
+
+-- The compressed lines cannot be indexed, so `barto-cli search` matches the distinct words
+-- of each chunk and bartos finds the lines within it.
+CREATE INDEX IF NOT EXISTS output_chunks_fulltext ON output_chunks USING GIN (to_tsvector('simple', words));
@@ -0,0 +1,3 @@
+ALTER TABLE runs
+    DROP COLUMN wait_status,
+    DROP COLUMN core_dumped;
@@ -0,0 +1,5 @@
+-- exit_signal has been in runs since it was added; a run killed by a signal now also records
+-- whether it dumped core and the raw wait status bartoc saw.
+ALTER TABLE runs
+    ADD COLUMN core_dumped BOOLEAN NULL,
+    ADD COLUMN wait_status INTEGER NULL;
@@ -0,0 +1,14 @@
+-- Every connect and disconnect of a bartoc client, so its history survives it going away.
+CREATE TABLE IF NOT EXISTS client_events
+(
+    id          BIGSERIAL    PRIMARY KEY NOT NULL,
+    bartoc_uuid UUID         NOT NULL,
+    bartoc_name VARCHAR(256) NOT NULL,
+    ip          VARCHAR(64)  NOT NULL,
+    kind        VARCHAR(16)  NOT NULL,
+    reason      VARCHAR(32)  NULL,
+    close_code  INTEGER      NULL,
+    timestamp   TIMESTAMPTZ  NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS client_events_name ON client_events (bartoc_name, id);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS client_registry;
+DROP TABLE IF EXISTS runs_test;
+DROP TABLE IF EXISTS runs;
+DROP TABLE IF EXISTS output_test;
+DROP TABLE IF EXISTS output;
@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS output_test
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    timestamp   DATETIME                          NOT NULL,
+    bartoc_uuid BLOB                              NOT NULL,
+    bartoc_name TEXT                              NOT NULL,
+    cmd_uuid    BLOB                              NOT NULL,
+    cmd_name    TEXT                              NOT NULL DEFAULT 'unset',
+    kind        TEXT                              NOT NULL,
+    data        TEXT                              NOT NULL
+);
+CREATE INDEX IF NOT EXISTS output_test_cmd_uuid ON output_test (cmd_uuid);
+
+CREATE TABLE IF NOT EXISTS runs_test
+(
+    id            INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    cmd_uuid      BLOB                              NOT NULL UNIQUE,
+    bartoc_uuid   BLOB                              NULL,
+    bartoc_name   TEXT                              NULL,
+    schedule_name TEXT                              NULL,
+    cmd           TEXT                              NULL,
+    trigger_type  TEXT                              NULL,
+    attempt       INTEGER                           NOT NULL DEFAULT 1,
+    started_at    DATETIME                          NULL,
+    ended_at      DATETIME                          NULL,
+    duration_ms   INTEGER                           NULL,
+    exit_code     INTEGER                           NULL,
+    exit_signal   INTEGER                           NULL,
+    success       BOOLEAN                           NULL
+);
+CREATE INDEX IF NOT EXISTS runs_test_schedule ON runs_test (bartoc_name, schedule_name);
+CREATE INDEX IF NOT EXISTS runs_test_started_at ON runs_test (started_at);
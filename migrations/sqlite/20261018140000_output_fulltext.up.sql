@@ -0,0 +1,22 @@
+-- An FTS5 index over the output text, kept in step with the output table by triggers, so
+-- `barto-cli search` can MATCH instead of scanning with LIKE.
+CREATE VIRTUAL TABLE IF NOT EXISTS output_fts USING fts5(data, content = 'output', content_rowid = 'id');
+
+CREATE TRIGGER IF NOT EXISTS output_fts_insert AFTER INSERT ON output
+BEGIN
+    INSERT INTO output_fts (rowid, data) VALUES (new.id, new.data);
+END;
+
+CREATE TRIGGER IF NOT EXISTS output_fts_delete AFTER DELETE ON output
+BEGIN
+    INSERT INTO output_fts (output_fts, rowid, data) VALUES ('delete', old.id, old.data);
+END;
+
+CREATE TRIGGER IF NOT EXISTS output_fts_update AFTER UPDATE OF data ON output
+BEGIN
+    INSERT INTO output_fts (output_fts, rowid, data) VALUES ('delete', old.id, old.data);
+    INSERT INTO output_fts (rowid, data) VALUES (new.id, new.data);
+END;
+
+-- Index the output stored before this migration.
+INSERT INTO output_fts (output_fts) VALUES ('rebuild');
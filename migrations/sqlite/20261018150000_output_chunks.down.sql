@@ -0,0 +1,6 @@
+-- Chunked output is not moved back into the output table.
+DROP TRIGGER IF EXISTS output_chunks_fts_update;
+DROP TRIGGER IF EXISTS output_chunks_fts_delete;
+DROP TRIGGER IF EXISTS output_chunks_fts_insert;
+DROP TABLE IF EXISTS output_chunks_fts;
+DROP TABLE IF EXISTS output_chunks;
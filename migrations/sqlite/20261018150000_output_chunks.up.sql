@@ -0,0 +1,42 @@
+-- Output is stored as chunks of lines per run rather than a row per line. New lines are
+-- inserted as open, uncompressed chunks, which are sealed into zstd-compressed chunks of up
+-- to 1000 lines once they hold that many or the run ends. bartos moves the rows of the
+-- output table into chunks when it starts; the full-text index over that table stays.
+
+CREATE TABLE IF NOT EXISTS output_chunks
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    bartoc_uuid BLOB                              NOT NULL,
+    bartoc_name TEXT                              NOT NULL,
+    cmd_uuid    BLOB                              NOT NULL,
+    cmd_name    TEXT                              NOT NULL,
+    first_line  INTEGER                           NOT NULL,
+    line_count  INTEGER                           NOT NULL,
+    first_at    DATETIME                          NOT NULL,
+    last_at     DATETIME                          NOT NULL,
+    words       TEXT                              NOT NULL,
+    sealed      BOOLEAN                           NOT NULL,
+    data        BLOB                              NOT NULL,
+    UNIQUE (cmd_uuid, first_line)
+);
+CREATE INDEX IF NOT EXISTS output_chunks_last_at ON output_chunks (last_at);
+
+-- The compressed lines cannot be indexed, so `barto-cli search` matches the distinct words
+-- of each chunk and bartos finds the lines within it.
+CREATE VIRTUAL TABLE IF NOT EXISTS output_chunks_fts USING fts5(words, content = 'output_chunks', content_rowid = 'id');
+
+CREATE TRIGGER IF NOT EXISTS output_chunks_fts_insert AFTER INSERT ON output_chunks
+BEGIN
+    INSERT INTO output_chunks_fts (rowid, words) VALUES (new.id, new.words);
+END;
+
+CREATE TRIGGER IF NOT EXISTS output_chunks_fts_delete AFTER DELETE ON output_chunks
+BEGIN
+    INSERT INTO output_chunks_fts (output_chunks_fts, rowid, words) VALUES ('delete', old.id, old.words);
+END;
+
+CREATE TRIGGER IF NOT EXISTS output_chunks_fts_update AFTER UPDATE OF words ON output_chunks
+BEGIN
+    INSERT INTO output_chunks_fts (output_chunks_fts, rowid, words) VALUES ('delete', old.id, old.words);
+    INSERT INTO output_chunks_fts (rowid, words) VALUES (new.id, new.words);
+END;
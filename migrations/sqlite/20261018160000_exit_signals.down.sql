@@ -0,0 +1,2 @@
+ALTER TABLE runs DROP COLUMN wait_status;
+ALTER TABLE runs DROP COLUMN core_dumped;
@@ -0,0 +1,14 @@
+-- Every connect and disconnect of a bartoc client, so its history survives it going away.
+CREATE TABLE IF NOT EXISTS client_events
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    bartoc_uuid BLOB                              NOT NULL,
+    bartoc_name TEXT                              NOT NULL,
+    ip          TEXT                              NOT NULL,
+    kind        TEXT                              NOT NULL,
+    reason      TEXT                              NULL,
+    close_code  INTEGER                           NULL,
+    timestamp   DATETIME                          NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS client_events_name ON client_events (bartoc_name, id);
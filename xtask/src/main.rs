@@ -132,22 +132,6 @@ fn copy_extras(binary: &str, out_dir: &Path) -> Result<()> {
             out_dir.join("bartos-secrets-init"),
         )
         .context("failed to copy bartos-secrets-init")?;
-
-        fs::copy(
-            "packaging/nfpm/scripts/barto-migrate",
-            out_dir.join("barto-migrate"),
-        )
-        .context("failed to copy barto-migrate")?;
-
-        let mig_out = out_dir.join("migrations");
-        fs::create_dir_all(&mig_out).context("failed to create migrations dir")?;
-        for entry in fs::read_dir("migrations").context("failed to read migrations/")? {
-            let entry = entry?;
-            if entry.path().extension().and_then(|e| e.to_str()) == Some("sql") {
-                fs::copy(entry.path(), mig_out.join(entry.file_name()))
-                    .with_context(|| format!("failed to copy {}", entry.path().display()))?;
-            }
-        }
     }
     Ok(())
 }
@@ -202,6 +186,77 @@ fn bartos_command() -> Command {
         .arg(enable_std_output_arg())
         .arg(config_absolute_path_arg())
         .arg(tracing_absolute_path_arg())
+        .subcommand(
+            Command::new("migrate")
+                .about(
+                    "Apply the embedded database migrations and exit without starting the server",
+                )
+                .arg(
+                    Arg::new("status")
+                        .long("status")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("dry-run")
+                        .help("List the applied and pending migrations without applying anything"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Show the migrations that would be applied without applying them"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about(
+                    "Load a barto-cli export file into the database and exit without starting the server",
+                )
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("The export file to load"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help(
+                            "The format of the file (jsonl, csv or arrow), by default from its extension",
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("enroll")
+                .about(
+                    "Enroll a bartoc client and exit without starting the server, printing its token",
+                )
+                .arg(
+                    Arg::new("name")
+                        .value_name("NAME")
+                        .required(true)
+                        .help("The name the client connects with"),
+                )
+                .arg(
+                    Arg::new("public-key")
+                        .short('p')
+                        .long("public-key")
+                        .value_name("PUBLIC_KEY")
+                        .help("Enroll an Ed25519 public key (base64) instead of issuing a token"),
+                ),
+        )
+        .subcommand(
+            Command::new("revoke")
+                .about(
+                    "Revoke the credential of a bartoc client and exit without starting the server",
+                )
+                .arg(
+                    Arg::new("name")
+                        .value_name("NAME")
+                        .required(true)
+                        .help("The name of the client to revoke"),
+                ),
+        )
 }
 
 /// `bartoc` — scheduled job executor
@@ -270,6 +325,27 @@ fn barto_cli_command() -> Command {
                                 .required(true)
                                 .help("Name of the secret to delete"),
                         ),
+                )
+                .subcommand(
+                    Command::new("rotate")
+                        .about(
+                            "Generate new Ed25519 signing material for a key rotation and print the config changes",
+                        )
+                        .arg(
+                            Arg::new("key-id")
+                                .long("key-id")
+                                .value_name("KEY_ID")
+                                .required(true)
+                                .help(
+                                    "The key ID for the new key (must differ from the key IDs currently in use)",
+                                ),
+                        )
+                        .arg(
+                            Arg::new("hmac")
+                                .long("hmac")
+                                .action(ArgAction::SetTrue)
+                                .help("Also generate a new HMAC key under the same key ID"),
+                        ),
                 ),
         )
         .subcommand(
@@ -304,16 +380,232 @@ fn barto_cli_command() -> Command {
                 ),
         )
         .subcommand(Command::new("cleanup").about("Perform cleanup of old database entries"))
-        .subcommand(Command::new("clients").about("List the currently connected clients"))
         .subcommand(
-            Command::new("query").about("Run a query on bartos").arg(
-                Arg::new("query")
-                    .short('q')
-                    .long("query")
-                    .value_name("QUERY")
-                    .required(true)
-                    .help("The query to run on bartos"),
-            ),
+            Command::new("clients")
+                .about("List the currently connected clients, or manage client enrollment")
+                .arg(
+                    Arg::new("versions")
+                        .long("versions")
+                        .action(ArgAction::SetTrue)
+                        .help("Show the bartoc version for each client"),
+                )
+                .arg(
+                    Arg::new("history")
+                        .long("history")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("versions")
+                        .help("Show the recorded connects and disconnects, newest first"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .short('n')
+                        .long("name")
+                        .value_name("NAME")
+                        .requires("history")
+                        .help("Only the history of this bartoc client"),
+                )
+                .arg(since_arg("Only events at or after this time").requires("history"))
+                .arg(
+                    Arg::new("limit")
+                        .short('l')
+                        .long("limit")
+                        .value_name("LIMIT")
+                        .requires("history")
+                        .help("The most events to return (bartos defaults to 100)"),
+                )
+                .subcommand(
+                    Command::new("enroll")
+                        .about(
+                            "Enroll a client in the bartos client registry (requires admin permission)",
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .value_name("NAME")
+                                .required(true)
+                                .help("Name of the client to enroll"),
+                        )
+                        .arg(
+                            Arg::new("public-key")
+                                .long("public-key")
+                                .value_name("PUBLIC_KEY")
+                                .help("Base64 Ed25519 public key the client signs its name with"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("revoke")
+                        .about("Revoke a client's credential (requires admin permission)")
+                        .arg(
+                            Arg::new("name")
+                                .value_name("NAME")
+                                .required(true)
+                                .help("Name of the client to revoke"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("query")
+                .about("Query the runs recorded on bartos")
+                .arg(
+                    Arg::new("name")
+                        .short('n')
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Only runs on this bartoc client"),
+                )
+                .arg(
+                    Arg::new("schedule")
+                        .short('s')
+                        .long("schedule")
+                        .value_name("SCHEDULE")
+                        .help("Only runs of this schedule"),
+                )
+                .arg(since_arg("Only output at or after this time"))
+                .arg(until_arg("Only output before this time"))
+                .arg(
+                    Arg::new("state")
+                        .long("state")
+                        .value_name("STATE")
+                        .help("Only runs in this state (succeeded, failed or running)"),
+                )
+                .arg(
+                    Arg::new("text")
+                        .short('t')
+                        .long("text")
+                        .value_name("TEXT")
+                        .help("Only output lines containing this text, ignoring case"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .short('l')
+                        .long("limit")
+                        .value_name("LIMIT")
+                        .help("The most rows to return (bartos defaults to 100)"),
+                )
+                .arg(
+                    Arg::new("offset")
+                        .short('o')
+                        .long("offset")
+                        .value_name("OFFSET")
+                        .default_value("0")
+                        .help("The number of rows to skip"),
+                )
+                .arg(
+                    Arg::new("raw")
+                        .long("raw")
+                        .value_name("RAW")
+                        .conflicts_with_all([
+                            "name", "schedule", "since", "until", "state", "text", "limit",
+                            "offset",
+                        ])
+                        .help(
+                            "Run this read-only SQL instead (requires raw query permission on bartos)",
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Search the stored job output for words, with the lines around each match")
+                .arg(
+                    Arg::new("text")
+                        .value_name("TEXT")
+                        .required(true)
+                        .help("The words to search for, matched whole, in order and ignoring case"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .short('n')
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Only output of this bartoc client"),
+                )
+                .arg(
+                    Arg::new("schedule")
+                        .short('s')
+                        .long("schedule")
+                        .value_name("SCHEDULE")
+                        .help("Only output of this schedule"),
+                )
+                .arg(since_arg("Only output at or after this time"))
+                .arg(until_arg("Only output before this time"))
+                .arg(
+                    Arg::new("limit")
+                        .short('l')
+                        .long("limit")
+                        .value_name("LIMIT")
+                        .help("The most matches to return (bartos defaults to 50)"),
+                )
+                .arg(
+                    Arg::new("context")
+                        .short('C')
+                        .long("context")
+                        .value_name("CONTEXT")
+                        .default_value("2")
+                        .help("The lines of the same run to show before and after each match"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export the job history as JSON Lines, CSV or an Arrow IPC stream")
+                .arg(
+                    Arg::new("name")
+                        .short('n')
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Only runs of this bartoc client"),
+                )
+                .arg(
+                    Arg::new("schedule")
+                        .short('s')
+                        .long("schedule")
+                        .value_name("SCHEDULE")
+                        .help("Only runs of this schedule"),
+                )
+                .arg(since_arg("Only runs that started at or after this time"))
+                .arg(until_arg("Only runs that started before this time"))
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help(
+                            "The format to write: jsonl, csv or arrow (defaults to the output file extension, then jsonl)",
+                        ),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("The file to write, rather than stdout"),
+                ),
+        )
+        .subcommand(
+            Command::new("tail")
+                .about("Stream the output of running jobs as it reaches bartos")
+                .arg(
+                    Arg::new("name")
+                        .short('n')
+                        .long("name")
+                        .visible_alias("client")
+                        .value_name("NAME")
+                        .help("Only runs on this bartoc client"),
+                )
+                .arg(
+                    Arg::new("schedule")
+                        .short('s')
+                        .long("schedule")
+                        .value_name("SCHEDULE")
+                        .help("Only runs of this schedule"),
+                )
+                .arg(
+                    Arg::new("follow")
+                        .short('f')
+                        .long("follow")
+                        .action(ArgAction::SetTrue)
+                        .help(
+                            "Keep streaming new runs until interrupted, rather than stopping once the runs shown have finished",
+                        ),
+                ),
         )
         .subcommand(
             Command::new("list")
@@ -383,6 +675,20 @@ fn config_absolute_path_arg() -> Arg {
         .help("Specify the absolute path to the config file")
 }
 
+fn since_arg(help: &'static str) -> Arg {
+    Arg::new("since")
+        .long("since")
+        .value_name("SINCE")
+        .help(format!("{help} (RFC 3339, or an age such as 12h or 7d)"))
+}
+
+fn until_arg(help: &'static str) -> Arg {
+    Arg::new("until")
+        .long("until")
+        .value_name("UNTIL")
+        .help(format!("{help} (RFC 3339, or an age such as 12h or 7d)"))
+}
+
 fn tracing_absolute_path_arg() -> Arg {
     Arg::new("tracing-absolute-path")
         .short('t')